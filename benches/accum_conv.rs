@@ -70,9 +70,11 @@ impl Circuit<Fr> for MyCircuit {
                         &mut region,
                         &[self.image.clone(), self.kernel.clone(), self.bias.clone()],
                         Box::new(PolyOp::Conv {
-                            padding: vec![(0, 0)],
+                            padding: vec![(0, 0); 2],
                             stride: vec![1; 2],
                             group: 1,
+                            channels_last: false,
+                            strategy: ConvStrategy::Direct,
                         }),
                     )
                     .unwrap();
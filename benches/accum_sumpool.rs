@@ -69,6 +69,8 @@ impl Circuit<Fr> for MyCircuit {
                             stride: vec![1, 1],
                             kernel_shape: vec![2, 2],
                             normalized: false,
+                            channels_last: false,
+                            avgpool_mode: AvgPoolMode::Exact,
                         }),
                     )
                     .unwrap();
@@ -1,6 +1,6 @@
 use ezkl::circuit::region::RegionCtx;
 use ezkl::circuit::{
-    ops::lookup::LookupOp, ops::poly::PolyOp, BaseConfig as PolyConfig, CheckMode,
+    ops::lookup::LookupOp, ops::poly::PolyOp, BaseConfig as PolyConfig, CheckMode, ConvStrategy,
 };
 use ezkl::fieldutils::{self, integer_rep_to_felt, IntegerRep};
 use ezkl::tensor::*;
@@ -205,9 +205,11 @@ where
                     let mut region = RegionCtx::new(region, 0, NUM_INNER_COLS, 1024, 2);
 
                     let op = PolyOp::Conv {
-                        padding: vec![(PADDING, PADDING); 2],
+                        padding: vec![(PADDING as isize, PADDING as isize); 2],
                         stride: vec![STRIDE; 2],
                         group: 1,
+                        channels_last: false,
+                        strategy: ConvStrategy::Direct,
                     };
                     let x = config
                         .layer_config
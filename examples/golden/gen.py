@@ -0,0 +1,323 @@
+"""Generates the golden fixtures under this directory, checked by tests/golden_tests.rs.
+
+Each fixture is a directory holding
+  - network.onnx: the model,
+  - input.json: an input and the model's float output on it,
+  - golden.json: the output ezkl computes with the default run args, as integers at
+    the output scale. These are the public instances of a proof, so the tests check
+    them exactly.
+
+The golden outputs come from `fixed_point`, which follows ezkl's quantization rather
+than calling it: inputs and weights quantized at scale 7, biases at the scale of the
+products they're added to (14), and every linear layer rebased back to 7 by dividing
+with rounding half away from zero. A change that moves these numbers either fixes a
+bug (regenerate, and say why in the PR) or introduces one.
+
+Weights are fixed pseudo-random draws rather than trained ones, and inputs synthetic
+images with the datasets' normalization, so fixtures regenerate byte for byte with
+nothing but the standard library. To add a fixture for a new op, write a function
+returning `(nodes, initializers, input_shape, output_shape, input)` alongside those
+below, extend `fixed_point` and `float_forward` with the op, and register it in
+FIXTURES.
+
+    python gen.py
+"""
+
+import json
+import math
+import os
+import random
+import struct
+
+INPUT_SCALE = 7
+PARAM_SCALE = 7
+
+
+# --- onnx protobuf writing -----------------------------------------------------
+
+def varint(v):
+    out = b''
+    while v >= 0x80:
+        out += bytes([(v & 0x7f) | 0x80])
+        v >>= 7
+    return out + bytes([v])
+
+
+def field_int(number, v):
+    return varint(number << 3) + varint(v)
+
+
+def field_bytes(number, b):
+    if isinstance(b, str):
+        b = b.encode()
+    return varint((number << 3) | 2) + varint(len(b)) + b
+
+
+def value_info(name, dims):
+    shape = b''.join(field_bytes(1, field_int(1, d)) for d in dims)
+    tensor_type = field_int(1, 1) + field_bytes(2, shape)
+    return field_bytes(1, name) + field_bytes(2, field_bytes(1, tensor_type))
+
+
+def node(op, inputs, outputs, name, **attributes):
+    out = b''.join(field_bytes(1, i) for i in inputs)
+    out += b''.join(field_bytes(2, o) for o in outputs)
+    out += field_bytes(3, name) + field_bytes(4, op)
+    for key, value in attributes.items():
+        if isinstance(value, list):
+            attribute = field_bytes(1, key) + b''.join(field_int(8, v) for v in value)
+            attribute += field_int(20, 7)
+        else:
+            attribute = field_bytes(1, key) + field_int(3, value) + field_int(20, 2)
+        out += field_bytes(5, attribute)
+    return out
+
+
+def initializer(name, dims, values):
+    out = b''.join(field_int(1, d) for d in dims) + field_int(2, 1)
+    out += field_bytes(8, name)
+    out += field_bytes(9, b''.join(struct.pack('<f', v) for v in values))
+    return out
+
+
+def model(name, nodes, initializers, input_shape, output_shape):
+    graph = b''.join(field_bytes(1, n) for n in nodes) + field_bytes(2, name)
+    graph += b''.join(field_bytes(5, initializer(*i)) for i in initializers)
+    graph += field_bytes(11, value_info('input', input_shape))
+    graph += field_bytes(12, value_info('output', output_shape))
+    return field_int(1, 7) + field_bytes(7, graph) + field_bytes(8, field_int(2, 13))
+
+
+# --- fixtures --------------------------------------------------------------------
+
+def f32(v):
+    return struct.unpack('<f', struct.pack('<f', v))[0]
+
+
+def weights(rng, count, fan_in):
+    bound = 1 / math.sqrt(fan_in)
+    return [f32(rng.uniform(-bound, bound)) for _ in range(count)]
+
+
+def mnist_mlp():
+    """A 196 -> 8 -> 10 relu MLP over 14x14 (2x downsampled) MNIST digits."""
+    rng = random.Random(718)
+    initializers = [
+        ('w0', [196, 8], weights(rng, 196 * 8, 196)),
+        ('b0', [8], weights(rng, 8, 196)),
+        ('w1', [8, 10], weights(rng, 8 * 10, 8)),
+        ('b1', [10], weights(rng, 10, 8)),
+    ]
+    nodes = [
+        node('MatMul', ['input', 'w0'], ['mm0'], 'matmul0'),
+        node('Add', ['mm0', 'b0'], ['fc0'], 'add0'),
+        node('Relu', ['fc0'], ['relu0'], 'relu0'),
+        node('MatMul', ['relu0', 'w1'], ['mm1'], 'matmul1'),
+        node('Add', ['mm1', 'b1'], ['output'], 'add1'),
+    ]
+    # a 7: a bar along the top and a stroke down to the bottom left
+    pixels = [[0.0] * 14 for _ in range(14)]
+    for x in range(3, 11):
+        pixels[3][x] = 1.0
+        pixels[4][x] = 0.6
+    for y in range(4, 12):
+        x = 10 - (y - 4) * 5 // 8
+        pixels[y][x] = 1.0
+        pixels[y][x - 1] = 0.5
+    image = [(p - 0.1307) / 0.3081 for row in pixels for p in row]
+    return nodes, initializers, [1, 196], [1, 10], image
+
+
+def cifar_cnn():
+    """Two strided 3x3 relu convs and a linear head over 32x32 CIFAR images."""
+    rng = random.Random(719)
+    initializers = [
+        ('k0', [4, 3, 3, 3], weights(rng, 4 * 3 * 9, 27)),
+        ('c0', [4], weights(rng, 4, 27)),
+        ('k1', [8, 4, 3, 3], weights(rng, 8 * 4 * 9, 36)),
+        ('c1', [8], weights(rng, 8, 36)),
+        ('w2', [128, 10], weights(rng, 128 * 10, 128)),
+        ('b2', [10], weights(rng, 10, 128)),
+    ]
+    conv = dict(kernel_shape=[3, 3], pads=[1, 1, 1, 1])
+    nodes = [
+        node('Conv', ['input', 'k0', 'c0'], ['conv0'], 'conv0', strides=[2, 2], **conv),
+        node('Relu', ['conv0'], ['relu0'], 'relu0'),
+        node('Conv', ['relu0', 'k1', 'c1'], ['conv1'], 'conv1', strides=[4, 4], **conv),
+        node('Relu', ['conv1'], ['relu1'], 'relu1'),
+        node('Flatten', ['relu1'], ['flat'], 'flatten', axis=1),
+        node('MatMul', ['flat', 'w2'], ['mm2'], 'matmul2'),
+        node('Add', ['mm2', 'b2'], ['output'], 'add2'),
+    ]
+    # a sky-ish gradient with a reddish disc in the middle
+    mean, std = [0.4914, 0.4822, 0.4465], [0.2470, 0.2435, 0.2616]
+    image = []
+    for c in range(3):
+        for y in range(32):
+            for x in range(32):
+                inside = (x - 16) ** 2 + (y - 18) ** 2 < 64
+                p = [0.8, 0.3, 0.2][c] if inside else [0.3, 0.5, 0.9][c] * (1 - y / 64)
+                image.append((p - mean[c]) / std[c])
+    return nodes, initializers, [1, 3, 32, 32], [1, 10], image
+
+
+FIXTURES = {
+    'mnist_mlp': mnist_mlp,
+    'cifar_cnn': cifar_cnn,
+}
+
+
+# --- reference forward passes ------------------------------------------------------
+
+def round_half_away(v):
+    r = math.floor(abs(v))
+    if abs(v) - r >= 0.5:
+        r += 1
+    return int(math.copysign(r, v))
+
+
+def div_round(n, d):
+    """n / d rounded half away from zero, on integers."""
+    q = (2 * abs(n) + d) // (2 * d)
+    return q if n >= 0 else -q
+
+
+def quantize(values, scale):
+    return [round_half_away(v * 2.0 ** scale) for v in values]
+
+
+def run(nodes_, initializers, input_shape, values, linear, relu):
+    """Runs the model's ops in order, `linear` computing conv layers and the matmul +
+    add pairs linear layers are written as."""
+    params = {name: (dims, vals) for name, dims, vals in initializers}
+    x, shape = values, input_shape
+    ops = [parse_node(n) for n in nodes_]
+    i = 0
+    while i < len(ops):
+        op, inputs, attributes = ops[i]
+        if op == 'MatMul':
+            x, shape = linear(x, shape, params[inputs[1]], params[ops[i + 1][1][1]], op, {})
+            i += 1
+        elif op == 'Conv':
+            x, shape = linear(x, shape, params[inputs[1]], params[inputs[2]], op, attributes)
+        elif op == 'Relu':
+            x = [relu(v) for v in x]
+        elif op == 'Flatten':
+            shape = [shape[0], math.prod(shape[1:])]
+        else:
+            raise ValueError(op)
+        i += 1
+    return x
+
+
+def parse_node(encoded):
+    """Reads back the op, inputs and int list attributes of a node written by `node`."""
+    def read_varint(buf, pos):
+        v, shift = 0, 0
+        while True:
+            b = buf[pos]
+            pos += 1
+            v |= (b & 0x7f) << shift
+            shift += 7
+            if b < 0x80:
+                return v, pos
+
+    def fields(buf):
+        pos, out = 0, []
+        while pos < len(buf):
+            key, pos = read_varint(buf, pos)
+            if key & 7 == 2:
+                n, pos = read_varint(buf, pos)
+                out.append((key >> 3, buf[pos:pos + n]))
+                pos += n
+            else:
+                v, pos = read_varint(buf, pos)
+                out.append((key >> 3, v))
+        return out
+
+    op, inputs, attributes = None, [], {}
+    for number, value in fields(encoded):
+        if number == 1:
+            inputs.append(value.decode())
+        elif number == 4:
+            op = value.decode()
+        elif number == 5:
+            attribute = fields(value)
+            name = next(v for n, v in attribute if n == 1).decode()
+            attributes[name] = [v for n, v in attribute if n in (3, 8)]
+    return op, inputs, attributes
+
+
+def apply_linear(x, shape, weight, bias, op, attributes, mul, finish):
+    (w_dims, w), (_, b) = weight, bias
+    if op == 'MatMul':
+        n, m = w_dims
+        out = [finish(sum(mul(x[k], w[k * m + j]) for k in range(n)), b[j]) for j in range(m)]
+        return out, [1, m]
+    o, c, kh, kw = w_dims
+    _, _, h, wd = shape
+    stride, pad = attributes['strides'][0], attributes['pads'][0]
+    oh, ow = (h + 2 * pad - kh) // stride + 1, (wd + 2 * pad - kw) // stride + 1
+    out = []
+    for oc in range(o):
+        for y in range(oh):
+            for xx in range(ow):
+                acc = 0
+                for ic in range(c):
+                    for dy in range(kh):
+                        for dx in range(kw):
+                            iy, ix = y * stride + dy - pad, xx * stride + dx - pad
+                            if 0 <= iy < h and 0 <= ix < wd:
+                                acc += mul(x[(ic * h + iy) * wd + ix],
+                                           w[((oc * c + ic) * kh + dy) * kw + dx])
+                out.append(finish(acc, b[oc]))
+    return out, [1, o, oh, ow]
+
+
+def float_forward(nodes_, initializers, input_shape, image):
+    def linear(x, shape, weight, bias, op, attributes):
+        return apply_linear(x, shape, weight, bias, op, attributes,
+                            lambda a, b: a * b, lambda acc, b: acc + b)
+    return run(nodes_, initializers, input_shape, image, linear, lambda v: max(v, 0.0))
+
+
+def fixed_point(nodes_, initializers, input_shape, image):
+    # biases are added to the products, so sit at their scale
+    ops = [parse_node(n) for n in nodes_]
+    biases = {inputs[2] for op, inputs, _ in ops if op == 'Conv'}
+    biases |= {inputs[1] for op, inputs, _ in ops if op == 'Add'}
+    quantized = [
+        (name, dims, quantize(values, INPUT_SCALE + PARAM_SCALE if name in biases else PARAM_SCALE))
+        for name, dims, values in initializers
+    ]
+
+    def linear(x, shape, weight, bias, op, attributes):
+        return apply_linear(x, shape, weight, bias, op, attributes, lambda a, b: a * b,
+                            lambda acc, b: div_round(acc + b, 2 ** PARAM_SCALE))
+    x = quantize(image, INPUT_SCALE)
+    return run(nodes_, quantized, input_shape, x, linear, lambda v: max(v, 0))
+
+
+def main():
+    here = os.path.dirname(os.path.abspath(__file__))
+    for name, fixture in FIXTURES.items():
+        nodes_, initializers, input_shape, output_shape, image = fixture()
+        path = os.path.join(here, name)
+        os.makedirs(path, exist_ok=True)
+        with open(os.path.join(path, 'network.onnx'), 'wb') as f:
+            f.write(model(name, nodes_, initializers, input_shape, output_shape))
+
+        output = float_forward(nodes_, initializers, input_shape, image)
+        with open(os.path.join(path, 'input.json'), 'w') as f:
+            json.dump({'input_data': [image], 'output_data': [output]}, f, indent=4)
+
+        golden = fixed_point(nodes_, initializers, input_shape, image)
+        with open(os.path.join(path, 'golden.json'), 'w') as f:
+            json.dump({'output_scale': INPUT_SCALE, 'outputs': [golden]}, f, indent=4)
+
+        error = max(abs(g / 2 ** INPUT_SCALE - o) for g, o in zip(golden, output))
+        print('{}: max quantization error {:.4f}'.format(name, error))
+
+
+if __name__ == '__main__':
+    main()
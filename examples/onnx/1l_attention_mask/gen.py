@@ -0,0 +1,36 @@
+import json
+import numpy as np
+import onnx
+from onnx import TensorProto, helper, numpy_helper
+
+# the masked attention scores of a tiny decoder block: a constant causal mask of -inf above
+# the diagonal is added to the scores ahead of the softmax, so masked positions must come out
+# as exactly 0
+mask = np.triu(np.full((4, 4), -np.inf, dtype=np.float32), k=1)
+
+graph = helper.make_graph(
+    [
+        helper.make_node('Add', ['scores', 'mask'], ['masked'], name='mask'),
+        helper.make_node('Softmax', ['masked'], ['output'], name='softmax', axis=-1),
+    ],
+    'attention_mask',
+    [helper.make_tensor_value_info('scores', TensorProto.FLOAT, [1, 4, 4])],
+    [helper.make_tensor_value_info('output', TensorProto.FLOAT, [1, 4, 4])],
+    [numpy_helper.from_array(mask, 'mask')],
+)
+model = helper.make_model(graph, opset_imports=[helper.make_opsetid('', 13)])
+onnx.checker.check_model(model)
+onnx.save(model, 'network.onnx')
+
+# scores are multiples of 1/128, so they are exact at ezkl's default input scale
+x = np.array([-101, -70, 55, 191, 29, -105, 23, -28, 6, -76, -85, 41, 227, -152, -175, -226],
+             dtype=np.float64).reshape(1, 4, 4) / 128
+masked = x + mask
+e = np.exp(masked - masked.max(axis=-1, keepdims=True))
+output = e / e.sum(axis=-1, keepdims=True)
+
+json.dump({
+    'input_data': [x.flatten().tolist()],
+    'input_shapes': [[1, 4, 4]],
+    'output_data': [output.flatten().tolist()],
+}, open('input.json', 'w'), indent=4)
@@ -0,0 +1,43 @@
+import json
+import numpy as np
+import onnx
+import onnxruntime as ort
+from onnx import helper, TensorProto
+
+# a 3x3 average pool, whose window size (9) is not a power of two, so
+# approximating the division by the window with a shift of the output scale
+# (by log2(8) = 3) is lossy
+shape = [2, 6, 6]
+
+node = helper.make_node(
+    'AveragePool',
+    inputs=['input'],
+    outputs=['output'],
+    kernel_shape=[3, 3],
+    strides=[1, 1],
+)
+
+graph = helper.make_graph(
+    [node],
+    'avg_pool_3x3',
+    [helper.make_tensor_value_info(
+        'input', TensorProto.FLOAT, ['batch_size', *shape])],
+    [helper.make_tensor_value_info(
+        'output', TensorProto.FLOAT, ['batch_size', 2, 4, 4])],
+)
+
+model = helper.make_model(graph, opset_imports=[helper.make_opsetid('', 10)])
+onnx.checker.check_model(model)
+onnx.save(model, 'network.onnx')
+
+x = np.random.rand(1, *shape).astype(np.float32)
+session = ort.InferenceSession('network.onnx')
+y = session.run(None, {'input': x})[0]
+assert list(y.shape) == [1, 2, 4, 4]
+
+data = dict(input_shapes=[shape],
+            input_data=[x.reshape([-1]).tolist()],
+            output_data=[y.reshape([-1]).tolist()])
+
+# Serialize data into file:
+json.dump(data, open("input.json", 'w'))
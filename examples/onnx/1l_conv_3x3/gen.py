@@ -0,0 +1,13 @@
+from torch import nn
+from ezkl import export
+
+class Model(nn.Module):
+    def __init__(self):
+        super(Model, self).__init__()
+        self.layer = nn.Conv2d(2, 3, (3, 3), 1, 1)
+
+    def forward(self, x):
+        return self.layer(x)
+
+circuit = Model()
+export(circuit, input_shape = [2, 6, 6])
@@ -0,0 +1,42 @@
+import json
+import numpy as np
+import onnx
+from onnx import TensorProto, helper, numpy_helper
+
+# a conv + relu over a non-square 3x16x24 image with a non-square 3x5 kernel, strided and
+# padded differently along height and width, so mixing up the two spatial dims changes the output
+C, H, W, O = 3, 16, 24, 4
+weight = np.array([((i * 7) % 11 - 5) / 16 for i in range(O * C * 3 * 5)],
+                  dtype=np.float32).reshape(O, C, 3, 5)
+bias = np.array([(i - 1.5) / 8 for i in range(O)], dtype=np.float32)
+
+graph = helper.make_graph(
+    [
+        helper.make_node('Conv', ['input', 'weight', 'bias'], ['conv'], name='conv',
+                         kernel_shape=[3, 5], strides=[1, 2], pads=[1, 2, 1, 2]),
+        helper.make_node('Relu', ['conv'], ['output'], name='relu'),
+    ],
+    'conv_nonsquare',
+    [helper.make_tensor_value_info('input', TensorProto.FLOAT, [1, C, H, W])],
+    [helper.make_tensor_value_info('output', TensorProto.FLOAT, [1, O, H, W // 2])],
+    [numpy_helper.from_array(weight, 'weight'), numpy_helper.from_array(bias, 'bias')],
+)
+model = helper.make_model(graph, opset_imports=[helper.make_opsetid('', 13)])
+onnx.checker.check_model(model)
+onnx.save(model, 'network.onnx')
+
+# inputs are multiples of 1/32, so they are exact at ezkl's default input scale
+x = np.array([((k * 13) % 29 - 14) / 32 for k in range(C * H * W)]).reshape(1, C, H, W)
+padded = np.pad(x, ((0, 0), (0, 0), (1, 1), (2, 2)))
+output = np.zeros((1, O, H, W // 2))
+for oh in range(H):
+    for ow in range(W // 2):
+        patch = padded[0, :, oh:oh + 3, ow * 2:ow * 2 + 5]
+        output[0, :, oh, ow] = (weight * patch).sum(axis=(1, 2, 3)) + bias
+output = np.maximum(output, 0)
+
+json.dump({
+    'input_data': [x.flatten().tolist()],
+    'input_shapes': [[1, C, H, W]],
+    'output_data': [output.flatten().tolist()],
+}, open('input.json', 'w'))
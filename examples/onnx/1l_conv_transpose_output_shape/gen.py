@@ -0,0 +1,52 @@
+import json
+import numpy as np
+import onnx
+import onnxruntime as ort
+from onnx import helper, numpy_helper, TensorProto
+
+# a stride 2 ConvTranspose whose explicit output_shape (10x10) differs from the
+# shape given by the default formula (13x13 with no pads), so the implicit
+# padding has to be derived from output_shape: total padding 3 -> (2, 1)
+shape = [3, 5, 5]
+weight = (0.1 * np.random.randn(3, 3, 5, 5)).astype(np.float32)
+bias = (0.1 * np.random.randn(3)).astype(np.float32)
+
+node = helper.make_node(
+    'ConvTranspose',
+    inputs=['input', 'convtranspose.weight', 'convtranspose.bias'],
+    outputs=['output'],
+    kernel_shape=[5, 5],
+    strides=[2, 2],
+    dilations=[1, 1],
+    group=1,
+    output_shape=[10, 10],
+)
+
+graph = helper.make_graph(
+    [node],
+    'conv_transpose_output_shape',
+    [helper.make_tensor_value_info(
+        'input', TensorProto.FLOAT, ['batch_size', *shape])],
+    [helper.make_tensor_value_info(
+        'output', TensorProto.FLOAT, ['batch_size', 3, 10, 10])],
+    initializer=[
+        numpy_helper.from_array(weight, 'convtranspose.weight'),
+        numpy_helper.from_array(bias, 'convtranspose.bias'),
+    ],
+)
+
+model = helper.make_model(graph, opset_imports=[helper.make_opsetid('', 10)])
+onnx.checker.check_model(model)
+onnx.save(model, 'network.onnx')
+
+x = (0.1 * np.random.rand(1, *shape)).astype(np.float32)
+session = ort.InferenceSession('network.onnx')
+y = session.run(None, {'input': x})[0]
+assert list(y.shape) == [1, 3, 10, 10]
+
+data = dict(input_shapes=[shape],
+            input_data=[x.reshape([-1]).tolist()],
+            output_data=[y.reshape([-1]).tolist()])
+
+# Serialize data into file:
+json.dump(data, open("input.json", 'w'))
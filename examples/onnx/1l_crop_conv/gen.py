@@ -0,0 +1,49 @@
+import json
+import numpy as np
+import onnx
+from onnx import TensorProto, helper, numpy_helper
+
+# a spatial crop feeding a padded conv, as exported for a center crop ahead of
+# a conv layer: the conv pads the cropped input, so ezkl keeps the crop's slices
+# rather than folding them into the conv's padding
+weight = np.array([-0.45, 0.4, 0.36, 0.02, 0.63, 0.24, 0.34, 0.83, -0.15,
+                   0.83, 0.09, 0.09, 0.92, 0.03, -0.31, -0.19, -0.5, 0.12],
+                  dtype=np.float32).reshape(2, 1, 3, 3)
+bias = np.array([0.25, -0.5], dtype=np.float32)
+
+graph = helper.make_graph(
+    [
+        helper.make_node('Slice', ['input', 'starts', 'ends', 'axes'], ['cropped'],
+                         name='crop'),
+        helper.make_node('Conv', ['cropped', 'weight', 'bias'], ['output'],
+                         name='conv', kernel_shape=[3, 3], pads=[1, 1, 1, 1],
+                         strides=[1, 1]),
+    ],
+    'crop_conv',
+    [helper.make_tensor_value_info('input', TensorProto.FLOAT, [1, 1, 6, 6])],
+    [helper.make_tensor_value_info('output', TensorProto.FLOAT, [1, 2, 5, 3])],
+    [
+        numpy_helper.from_array(np.array([1, 2], dtype=np.int64), 'starts'),
+        numpy_helper.from_array(np.array([6, 5], dtype=np.int64), 'ends'),
+        numpy_helper.from_array(np.array([2, 3], dtype=np.int64), 'axes'),
+        numpy_helper.from_array(weight, 'weight'),
+        numpy_helper.from_array(bias, 'bias'),
+    ],
+)
+model = helper.make_model(graph, opset_imports=[helper.make_opsetid('', 13)])
+onnx.checker.check_model(model)
+onnx.save(model, 'network.onnx')
+
+rng = np.random.default_rng(719)
+x = np.round(rng.uniform(-1, 1, (1, 1, 6, 6)), 4).astype(np.float32)
+padded = np.pad(x[:, :, 1:6, 2:5], ((0, 0), (0, 0), (1, 1), (1, 1)))
+output = np.zeros((1, 2, 5, 3), dtype=np.float32)
+for o in range(2):
+    for r in range(5):
+        for c in range(3):
+            output[0, o, r, c] = (weight[o, 0] * padded[0, 0, r:r + 3, c:c + 3]).sum() + bias[o]
+json.dump({
+    'input_data': [x.flatten().tolist()],
+    'input_shapes': [[1, 1, 6, 6]],
+    'output_data': [output.flatten().tolist()],
+}, open('input.json', 'w'), indent=4)
@@ -0,0 +1,48 @@
+import json
+import numpy as np
+import onnx
+from onnx import TensorProto, helper, numpy_helper
+
+# an If on a constant condition, as exported for a flag fixed at trace time:
+# the then branch multiplies by a weight of its own and applies a relu, the
+# else branch a sigmoid
+weight = np.array([[0.5, -0.25, 1.0], [0.75, 0.5, -0.5], [-1.0, 0.25, 0.5]],
+                  dtype=np.float32)
+
+
+def branch(name, nodes, initializers=[]):
+    return helper.make_graph(
+        nodes, name, [],
+        [helper.make_tensor_value_info(name + '_out', TensorProto.FLOAT, [1, 3])],
+        initializers)
+
+
+then_branch = branch('then', [
+    helper.make_node('MatMul', ['input', 'weight'], ['mm'], name='matmul'),
+    helper.make_node('Relu', ['mm'], ['then_out'], name='relu'),
+], [numpy_helper.from_array(weight, 'weight')])
+else_branch = branch('else', [
+    helper.make_node('Sigmoid', ['input'], ['else_out'], name='sigmoid'),
+])
+
+graph = helper.make_graph(
+    [
+        helper.make_node('Constant', [], ['cond'], name='cond',
+                         value=helper.make_tensor('value', TensorProto.BOOL, [], [True])),
+        helper.make_node('If', ['cond'], ['output'], name='if',
+                         then_branch=then_branch, else_branch=else_branch),
+    ],
+    'if_const',
+    [helper.make_tensor_value_info('input', TensorProto.FLOAT, [1, 3])],
+    [helper.make_tensor_value_info('output', TensorProto.FLOAT, [1, 3])],
+)
+model = helper.make_model(graph, opset_imports=[helper.make_opsetid('', 13)])
+onnx.checker.check_model(model)
+onnx.save(model, 'network.onnx')
+
+x = np.array([[1.5417295, 0.5346153, 1.2172532]], dtype=np.float32)
+json.dump({
+    'input_data': x.tolist(),
+    'input_shapes': [[1, 3]],
+    'output_data': np.maximum(x @ weight, 0).tolist(),
+}, open('input.json', 'w'), indent=4)
@@ -0,0 +1,12 @@
+import onnx
+
+# the 1l_mlp model with its dense weight moved into an external data file,
+# as torch and onnx do for models over 2GB
+model = onnx.load('../1l_mlp/network.onnx')
+weight = next(t for t in model.graph.initializer if t.name == 'dense.weight')
+onnx.external_data_helper.set_external_data(
+    weight, 'network.onnx.data', offset=0, length=len(weight.raw_data))
+with open('network.onnx.data', 'wb') as f:
+    f.write(weight.raw_data)
+weight.ClearField('raw_data')
+onnx.save(model, 'network.onnx')
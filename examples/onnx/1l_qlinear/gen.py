@@ -0,0 +1,95 @@
+import json
+import numpy as np
+import onnx
+from onnx import TensorProto, helper, numpy_helper
+
+# a conv and a matmul as onnxruntime's static quantization emits them: QLinearConv and
+# QLinearMatMul over uint8 activations and int8 weights, between a QuantizeLinear of the float
+# input and a DequantizeLinear of the output. ezkl lowers both onto their integer codes.
+x_scale, x_zero_point = 0.02, 120
+w_scale, y_scale, y_zero_point = 0.05, 0.0713, 128
+m_scale, o_scale, o_zero_point = 0.04, 0.0917, 100
+
+w = np.array([18, -12, -17, -5, -20, -9, -18, -13, -3,
+              13, -18, 2, -1, -18, -10, 18, -15, -4], dtype=np.int8).reshape(2, 1, 3, 3)
+b = np.array([-126, 30], dtype=np.int32)
+m = np.array([15, 19, 11, -25, 18, 7, 2, 9, 18, 10, -8, -14, 26, -16, 2],
+             dtype=np.int8).reshape(5, 3)
+
+
+def scalar(name, value, dtype):
+    return numpy_helper.from_array(np.array(value, dtype=dtype), name)
+
+
+graph = helper.make_graph(
+    [
+        helper.make_node('QuantizeLinear', ['input', 'x_scale', 'x_zero_point'], ['x_q'],
+                         name='quantize'),
+        helper.make_node('QLinearConv', ['x_q', 'x_scale', 'x_zero_point', 'w', 'w_scale',
+                                         'w_zero_point', 'y_scale', 'y_zero_point', 'b'],
+                         ['y_q'], name='conv', kernel_shape=[3, 3], pads=[1, 1, 1, 1]),
+        helper.make_node('QLinearMatMul', ['y_q', 'y_scale', 'y_zero_point', 'm', 'm_scale',
+                                           'm_zero_point', 'o_scale', 'o_zero_point'],
+                         ['o_q'], name='matmul'),
+        helper.make_node('DequantizeLinear', ['o_q', 'o_scale', 'o_zero_point'], ['output'],
+                         name='dequantize'),
+    ],
+    'qlinear',
+    [helper.make_tensor_value_info('input', TensorProto.FLOAT, [1, 1, 5, 5])],
+    [helper.make_tensor_value_info('output', TensorProto.FLOAT, [1, 2, 5, 3])],
+    [
+        scalar('x_scale', x_scale, np.float32),
+        scalar('x_zero_point', x_zero_point, np.uint8),
+        numpy_helper.from_array(w, 'w'),
+        scalar('w_scale', w_scale, np.float32),
+        scalar('w_zero_point', 0, np.int8),
+        scalar('y_scale', y_scale, np.float32),
+        scalar('y_zero_point', y_zero_point, np.uint8),
+        numpy_helper.from_array(b, 'b'),
+        numpy_helper.from_array(m, 'm'),
+        scalar('m_scale', m_scale, np.float32),
+        scalar('m_zero_point', 0, np.int8),
+        scalar('o_scale', o_scale, np.float32),
+        scalar('o_zero_point', o_zero_point, np.uint8),
+    ],
+)
+model = helper.make_model(graph, opset_imports=[helper.make_opsetid('', 13)])
+onnx.checker.check_model(model)
+onnx.save(model, 'network.onnx')
+
+# inputs are multiples of 1/128, so they are exact at ezkl's default input scale, and none of
+# them (nor any accumulator) sits on a rounding tie
+x = np.array([-50, 83, -34, 48, 119, 86, 109, -56, 53, -5, 28, -128, -31, 100, 99,
+              108, -110, 100, 71, 39, -124, -39, -45, 23, -79]).reshape(1, 1, 5, 5) / 128
+
+
+def requantize(acc, multiplier, zero_point):
+    return np.clip(np.rint(acc * np.float32(multiplier)) + zero_point, 0, 255).astype(np.int64)
+
+
+x_q = requantize(x, 1 / np.float32(x_scale), x_zero_point)
+padded = np.pad(x_q - x_zero_point, ((0, 0), (0, 0), (1, 1), (1, 1)))
+acc = np.zeros((1, 2, 5, 5), dtype=np.int64)
+for o in range(2):
+    for r in range(5):
+        for c in range(5):
+            acc[0, o, r, c] = (w[o, 0] * padded[0, 0, r:r + 3, c:c + 3]).sum() + b[o]
+y_q = requantize(acc, np.float32(x_scale) * np.float32(w_scale) / np.float32(y_scale),
+                 y_zero_point)
+o_q = requantize((y_q - y_zero_point) @ m.astype(np.int64),
+                 np.float32(y_scale) * np.float32(m_scale) / np.float32(o_scale), o_zero_point)
+output = ((o_q - o_zero_point) * np.float32(o_scale)).astype(np.float32)
+
+try:
+    import onnxruntime
+    session = onnxruntime.InferenceSession('network.onnx')
+    expected = session.run(None, {'input': x.astype(np.float32)})[0]
+    assert np.array_equal(expected, output), 'reference disagrees with onnxruntime'
+except ImportError:
+    pass
+
+json.dump({
+    'input_data': [x.flatten().tolist()],
+    'input_shapes': [[1, 1, 5, 5]],
+    'output_data': [output.flatten().tolist()],
+}, open('input.json', 'w'), indent=4)
@@ -0,0 +1,61 @@
+import json
+import numpy as np
+import onnx
+from onnx import TensorProto, helper, numpy_helper
+
+# a tiny classifier whose logits are tempered before the softmax, the way exporters emit
+# temperature scaling: divided by a constant temperature of 2.0, multiplied by a constant
+# sharpness of 1.5, and multiplied by a scale fed in as an input (which can't be folded)
+W = np.array([[0.5, -0.25, 0.75, 0.125],
+              [-0.5, 0.625, 0.25, -0.375],
+              [0.25, 0.375, -0.625, 0.5]], dtype=np.float32)
+B = np.array([0.125, -0.25, 0.0625], dtype=np.float32)
+
+graph = helper.make_graph(
+    [
+        helper.make_node('Gemm', ['x', 'W', 'B'], ['logits'], name='classifier', transB=1),
+        helper.make_node('Div', ['logits', 'temperature'], ['tempered'], name='temperature'),
+        helper.make_node('Softmax', ['tempered'], ['probs'], name='softmax', axis=-1),
+        helper.make_node('Mul', ['logits', 'sharpness'], ['sharpened'], name='sharpen'),
+        helper.make_node('Softmax', ['sharpened'], ['sharpened_probs'],
+                         name='sharpened_softmax', axis=-1),
+        helper.make_node('Mul', ['logits', 'scale'], ['scaled'], name='scale'),
+        helper.make_node('Softmax', ['scaled'], ['scaled_probs'], name='scaled_softmax', axis=-1),
+    ],
+    'softmax_temperature',
+    [
+        helper.make_tensor_value_info('x', TensorProto.FLOAT, [1, 4]),
+        helper.make_tensor_value_info('scale', TensorProto.FLOAT, [1]),
+    ],
+    [
+        helper.make_tensor_value_info(name, TensorProto.FLOAT, [1, 3])
+        for name in ['probs', 'sharpened_probs', 'scaled_probs']
+    ],
+    [
+        numpy_helper.from_array(W, 'W'),
+        numpy_helper.from_array(B, 'B'),
+        numpy_helper.from_array(np.array(2.0, dtype=np.float32), 'temperature'),
+        numpy_helper.from_array(np.array(1.5, dtype=np.float32), 'sharpness'),
+    ],
+)
+model = helper.make_model(graph, opset_imports=[helper.make_opsetid('', 13)])
+onnx.checker.check_model(model)
+onnx.save(model, 'network.onnx')
+
+
+def softmax(v):
+    e = np.exp(v - v.max(axis=-1, keepdims=True))
+    return e / e.sum(axis=-1, keepdims=True)
+
+
+# inputs are multiples of 1/128, so they are exact at ezkl's default input scale
+x = np.array([[77, -45, 130, -18]], dtype=np.float64) / 128
+scale = 0.5
+logits = x @ W.T.astype(np.float64) + B
+outputs = [softmax(logits / 2.0), softmax(logits * 1.5), softmax(logits * scale)]
+
+json.dump({
+    'input_data': [x.flatten().tolist(), [scale]],
+    'input_shapes': [[1, 4], [1]],
+    'output_data': [o.flatten().tolist() for o in outputs],
+}, open('input.json', 'w'), indent=4)
@@ -0,0 +1,128 @@
+import json
+import numpy as np
+import onnx
+from onnx import TensorProto, helper, numpy_helper
+
+# a MobileNetV2 inverted residual block as onnxruntime's static quantization emits it: a 1x1
+# expand conv, a strided 3x3 depthwise conv and a 1x1 projection, each a QLinearConv over uint8
+# activations and int8 weights. The expand and depthwise convs quantize onto [0, 6] with a zero
+# point of 0, so the uint8 clip is their ReLU6.
+channels, expanded, hw = 4, 8, 8
+x_scale, x_zero_point = 1 / 64, 128
+w1_scale, y1_scale = 0.02, 6 / 255
+w2_scale, y2_scale = 0.03, 6 / 255
+w3_scale, y3_scale, y3_zero_point = 0.025, 0.05, 128
+
+
+def pattern(n, a, b, m):
+    # a fixed spread of integers in [-m // 2, m // 2], chosen so that no requantization below
+    # lands within 0.004 of a rounding tie
+    return (np.arange(n) * a + b) % m - m // 2
+
+
+w1 = pattern(expanded * channels, 189, 10, 41).astype(np.int8).reshape(expanded, channels, 1, 1)
+b1 = pattern(expanded, 114, 31, 801).astype(np.int32)
+w2 = pattern(expanded * 9, 188, 124, 21).astype(np.int8).reshape(expanded, 1, 3, 3)
+b2 = pattern(expanded, 66, 51, 401).astype(np.int32)
+w3 = pattern(channels * expanded, 97, 42, 17).astype(np.int8).reshape(channels, expanded, 1, 1)
+b3 = pattern(channels, 111, 57, 1601).astype(np.int32)
+
+
+def scalar(name, value, dtype):
+    return numpy_helper.from_array(np.array(value, dtype=dtype), name)
+
+
+def qlinear_conv(i, x, y, name, **attributes):
+    x_params = ['x_scale', 'x_zero_point'] if i == 1 else [f'y{i - 1}_scale', f'y{i - 1}_zero_point']
+    return helper.make_node('QLinearConv', [x] + x_params + [f'w{i}', f'w{i}_scale',
+                                                             f'w{i}_zero_point', f'y{i}_scale',
+                                                             f'y{i}_zero_point', f'b{i}'],
+                            [y], name=name, **attributes)
+
+
+graph = helper.make_graph(
+    [
+        helper.make_node('QuantizeLinear', ['input', 'x_scale', 'x_zero_point'], ['x_q'],
+                         name='quantize'),
+        qlinear_conv(1, 'x_q', 'y1_q', 'expand', kernel_shape=[1, 1]),
+        qlinear_conv(2, 'y1_q', 'y2_q', 'depthwise', group=expanded, kernel_shape=[3, 3],
+                     pads=[1, 1, 1, 1], strides=[2, 2]),
+        qlinear_conv(3, 'y2_q', 'y3_q', 'project', kernel_shape=[1, 1]),
+        helper.make_node('DequantizeLinear', ['y3_q', 'y3_scale', 'y3_zero_point'], ['output'],
+                         name='dequantize'),
+    ],
+    'qlinear_mobilenet_block',
+    [helper.make_tensor_value_info('input', TensorProto.FLOAT, [1, channels, hw, hw])],
+    [helper.make_tensor_value_info('output', TensorProto.FLOAT,
+                                   [1, channels, hw // 2, hw // 2])],
+    [
+        scalar('x_scale', x_scale, np.float32),
+        scalar('x_zero_point', x_zero_point, np.uint8),
+        numpy_helper.from_array(w1, 'w1'),
+        scalar('w1_scale', w1_scale, np.float32),
+        scalar('w1_zero_point', 0, np.int8),
+        numpy_helper.from_array(b1, 'b1'),
+        scalar('y1_scale', y1_scale, np.float32),
+        scalar('y1_zero_point', 0, np.uint8),
+        numpy_helper.from_array(w2, 'w2'),
+        scalar('w2_scale', w2_scale, np.float32),
+        scalar('w2_zero_point', 0, np.int8),
+        numpy_helper.from_array(b2, 'b2'),
+        scalar('y2_scale', y2_scale, np.float32),
+        scalar('y2_zero_point', 0, np.uint8),
+        numpy_helper.from_array(w3, 'w3'),
+        scalar('w3_scale', w3_scale, np.float32),
+        scalar('w3_zero_point', 0, np.int8),
+        numpy_helper.from_array(b3, 'b3'),
+        scalar('y3_scale', y3_scale, np.float32),
+        scalar('y3_zero_point', y3_zero_point, np.uint8),
+    ],
+)
+model = helper.make_model(graph, opset_imports=[helper.make_opsetid('', 13)])
+onnx.checker.check_model(model)
+onnx.save(model, 'network.onnx')
+
+# inputs are multiples of 1/64 in [-1.5, 1.5], so they are exact at ezkl's default input scale
+# and quantize without clipping
+x = pattern(channels * hw * hw, 87, 108, 193).reshape(1, channels, hw, hw) / 64
+
+
+def requantize(acc, multiplier, zero_point):
+    return np.clip(np.rint(acc * np.float32(multiplier)) + zero_point, 0, 255).astype(np.int64)
+
+
+def multiplier(x_scale, w_scale, y_scale):
+    return np.float32(x_scale) * np.float32(w_scale) / np.float32(y_scale)
+
+
+x_q = requantize(x, 1 / np.float32(x_scale), x_zero_point)
+acc = np.einsum('ec,nchw->nehw', w1[:, :, 0, 0].astype(np.int64), x_q - x_zero_point)
+y1_q = requantize(acc + b1.reshape(1, -1, 1, 1), multiplier(x_scale, w1_scale, y1_scale), 0)
+
+padded = np.pad(y1_q, ((0, 0), (0, 0), (1, 1), (1, 1)))
+acc = np.zeros((1, expanded, hw // 2, hw // 2), dtype=np.int64)
+for e in range(expanded):
+    for r in range(hw // 2):
+        for c in range(hw // 2):
+            window = padded[0, e, 2 * r:2 * r + 3, 2 * c:2 * c + 3]
+            acc[0, e, r, c] = (w2[e, 0] * window).sum() + b2[e]
+y2_q = requantize(acc, multiplier(y1_scale, w2_scale, y2_scale), 0)
+
+acc = np.einsum('oe,nehw->nohw', w3[:, :, 0, 0].astype(np.int64), y2_q)
+y3_q = requantize(acc + b3.reshape(1, -1, 1, 1), multiplier(y2_scale, w3_scale, y3_scale),
+                  y3_zero_point)
+output = ((y3_q - y3_zero_point) * np.float32(y3_scale)).astype(np.float32)
+
+try:
+    import onnxruntime
+    session = onnxruntime.InferenceSession('network.onnx')
+    expected = session.run(None, {'input': x.astype(np.float32)})[0]
+    assert np.array_equal(expected, output), 'reference disagrees with onnxruntime'
+except ImportError:
+    pass
+
+json.dump({
+    'input_data': [x.flatten().tolist()],
+    'input_shapes': [[1, channels, hw, hw]],
+    'output_data': [output.flatten().tolist()],
+}, open('input.json', 'w'), indent=4)
@@ -0,0 +1,44 @@
+import torch
+from torch import nn
+import json
+
+
+class MyModel(nn.Module):
+    def __init__(self):
+        super(MyModel, self).__init__()
+        # rank-0 constants
+        self.scale = torch.tensor(0.5)
+        self.bias = torch.tensor(1.25)
+
+    def forward(self, x):
+        return x * self.scale + self.bias
+
+
+circuit = MyModel()
+
+x = 0.1*torch.rand(1, *[4], requires_grad=True)
+
+# Flips the neural net into inference mode
+circuit.eval()
+
+# Export the model
+torch.onnx.export(circuit,               # model being run
+                  # model input (or a tuple for multiple inputs)
+                  x,
+                  # where to save the model (can be a file or file-like object)
+                  "network.onnx",
+                  export_params=True,        # store the trained parameter weights inside the model file
+                  opset_version=13,          # the ONNX version to export the model to
+                  do_constant_folding=True,  # whether to execute constant folding for optimization
+                  input_names=['input'],   # the model's input names
+                  output_names=['output'],  # the model's output names
+                  dynamic_axes={'input': {0: 'batch_size'},    # variable length axes
+                                'output': {0: 'batch_size'}})
+
+data_array = ((x).detach().numpy()).reshape([-1]).tolist()
+
+data = dict(input_data=[data_array],
+            output_data=[((circuit(x)).detach().numpy()).reshape([-1]).tolist()])
+
+# Serialize data into file:
+json.dump(data, open("input.json", 'w'))
@@ -0,0 +1,31 @@
+import json
+import numpy as np
+import onnxruntime as rt
+from skl2onnx import to_onnx
+from skl2onnx.common.data_types import FloatTensorType
+from sklearn.datasets import load_iris
+from sklearn.linear_model import LogisticRegression
+
+# a logistic regression telling virginica from the rest of iris, exported by skl2onnx as a
+# LinearClassifier, a Normalizer and a ZipMap over its probabilities. ezkl lowers these onto a
+# Gemm and a Sigmoid and drops the class label output.
+iris = load_iris()
+X, y = iris.data.astype(np.float32), (iris.target == 2).astype(np.int64)
+clr = LogisticRegression()
+clr.fit(X, y)
+
+onx = to_onnx(clr, initial_types=[('float_input', FloatTensorType([1, 4]))],
+              target_opset={'': 15, 'ai.onnx.ml': 1})
+with open("network.onnx", "wb") as f:
+    f.write(onx.SerializeToString())
+
+x = np.array([[6.3, 2.8, 4.9, 1.6]], dtype=np.float32)
+sess = rt.InferenceSession("network.onnx")
+_, probabilities = sess.run(None, {"float_input": x})
+
+data = dict(input_shapes=[list(x.shape)],
+            input_data=[x.reshape([-1]).tolist()],
+            output_data=[[p[c] for p in probabilities for c in sorted(p)]])
+assert np.allclose(data["output_data"], clr.predict_proba(x), atol=1e-5)
+
+json.dump(data, open("input.json", 'w'), indent=4)
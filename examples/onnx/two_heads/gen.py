@@ -0,0 +1,39 @@
+import json
+import numpy as np
+import onnx
+from onnx import TensorProto, helper, numpy_helper
+
+# a model with two heads decoded differently: a regression head holding the logits, at the
+# default output scale, and a classification head holding their argmax, at scale 0
+W = np.array([[0.5, -0.25, 0.75, 0.125],
+              [-0.5, 0.625, 0.25, -0.375],
+              [0.25, 0.375, -0.625, 0.5]], dtype=np.float32)
+B = np.array([0.125, -0.25, 0.0625], dtype=np.float32)
+
+graph = helper.make_graph(
+    [
+        helper.make_node('Gemm', ['x', 'W', 'B'], ['logits'], name='logits', transB=1),
+        helper.make_node('ArgMax', ['logits'], ['label'], name='label', axis=1, keepdims=1),
+    ],
+    'two_heads',
+    [helper.make_tensor_value_info('x', TensorProto.FLOAT, [1, 4])],
+    [
+        helper.make_tensor_value_info('logits', TensorProto.FLOAT, [1, 3]),
+        helper.make_tensor_value_info('label', TensorProto.INT64, [1, 1]),
+    ],
+    [numpy_helper.from_array(W, 'W'), numpy_helper.from_array(B, 'B')],
+)
+model = helper.make_model(graph, opset_imports=[helper.make_opsetid('', 13)])
+onnx.checker.check_model(model)
+onnx.save(model, 'network.onnx')
+
+# inputs are multiples of 1/128, so they are exact at ezkl's default input scale
+x = np.array([[-40, 20, -90, 60]], dtype=np.float64) / 128
+logits = x @ W.T.astype(np.float64) + B
+label = logits.argmax(axis=1)
+
+json.dump({
+    'input_data': [x.flatten().tolist()],
+    'input_shapes': [[1, 4]],
+    'output_data': [logits.flatten().tolist(), label.astype(np.float64).tolist()],
+}, open('input.json', 'w'), indent=4)
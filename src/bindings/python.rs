@@ -4,19 +4,23 @@ use crate::circuit::modules::poseidon::{
     PoseidonChip,
 };
 use crate::circuit::modules::Module;
-use crate::circuit::{CheckMode, Tolerance};
+use crate::circuit::{AvgPoolMode, CheckMode, ConvStrategy, ReluImpl, Tolerance};
 use crate::commands::*;
 use crate::fieldutils::{felt_to_integer_rep, integer_rep_to_felt, IntegerRep};
+use crate::graph::input_source::MemoryInputSource;
 use crate::graph::modules::POSEIDON_LEN_GRAPH;
+use crate::graph::output_check::OutputTolerance;
 use crate::graph::TestDataSource;
 use crate::graph::{
-    quantize_float, scale_to_multiplier, GraphCircuit, GraphSettings, Model, Visibility,
+    quantize_float, scale_to_multiplier, GraphCircuit, GraphSettings, Model, ParamPlacement,
+    Visibility,
 };
 use crate::pfsys::evm::aggregation_kzg::AggregationCircuit;
 use crate::pfsys::{
     load_pk, load_vk, save_params, save_vk, srs::gen_srs as ezkl_gen_srs, srs::load_srs_prover,
     ProofType, TranscriptType,
 };
+use crate::tensor::Tensor;
 use crate::Commitments;
 use crate::RunArgs;
 use halo2_proofs::poly::ipa::commitment::IPACommitmentScheme;
@@ -27,8 +31,9 @@ use pyo3::prelude::*;
 use pyo3::wrap_pyfunction;
 use pyo3_log;
 use snark_verifier::util::arithmetic::PrimeField;
+use std::collections::HashMap;
+use std::path::PathBuf;
 use std::str::FromStr;
-use std::{fs::File, path::PathBuf};
 
 type PyFelt = String;
 
@@ -42,6 +47,15 @@ enum PyTestDataSource {
     OnChain,
 }
 
+/// The data gen_witness reads a model's inputs from
+#[derive(FromPyObject)]
+enum PyInputData {
+    /// Each input's name mapped to its flattened values and its dims
+    Tensors(HashMap<String, (Vec<f64>, Vec<usize>)>),
+    /// A path to a .json data file, a .npy file or a directory of <input name>.npy files
+    Path(PathBuf),
+}
+
 impl From<PyTestDataSource> for TestDataSource {
     fn from(py_test_data_source: PyTestDataSource) -> Self {
         match py_test_data_source {
@@ -153,6 +167,9 @@ struct PyRunArgs {
     /// int: The denominator in the fixed point representation used when quantizing inputs
     pub input_scale: crate::Scale,
     #[pyo3(get, set)]
+    /// tuple[float, float] | None: the range of the float inputs, eg. (0, 255) for raw pixels, which the input scale is lowered to fit
+    pub input_range: Option<(f64, f64)>,
+    #[pyo3(get, set)]
     /// int:  The denominator in the fixed point representation used when quantizing parameters
     pub param_scale: crate::Scale,
     #[pyo3(get, set)]
@@ -177,6 +194,9 @@ struct PyRunArgs {
     /// string: accepts `public`, `private`, `fixed`, `hashed/public`, `hashed/private`, `polycommit`
     pub param_visibility: Visibility,
     #[pyo3(get, set)]
+    /// list[tuple[str, str]]: per layer overrides of where the params a layer consumes live, accepts `fixed`, `advice`, `committed`
+    pub param_placement: Vec<(String, ParamPlacement)>,
+    #[pyo3(get, set)]
     /// list[tuple[str, int]]: Hand-written parser for graph variables, eg. batch_size=1
     pub variables: Vec<(String, usize)>,
     #[pyo3(get, set)]
@@ -186,6 +206,64 @@ struct PyRunArgs {
     /// bool: Should constants with 0.0 fraction be rebased to scale 0
     pub rebase_frac_zero_constants: bool,
     #[pyo3(get, set)]
+    /// bool: lower multiplications by the reciprocal of a whole number to divisions by it
+    pub whole_number_divisors: bool,
+    #[pyo3(get, set)]
+    /// bool: Rescale every conv and matmul output back to the working scale so that all activations share a single scale
+    pub uniform_scale: bool,
+    #[pyo3(get, set)]
+    /// str: Path to a JSON file mapping unsupported elementwise op names to explicit lookup tables
+    pub custom_tables: Option<PathBuf>,
+    #[pyo3(get, set)]
+    /// str: how convolutions are laid out, accepts `direct`, `im2col`
+    pub conv_strategy: ConvStrategy,
+    #[pyo3(get, set)]
+    /// str: how average pooling divides by the window size, accepts `exact`, `approx`
+    pub avgpool_mode: AvgPoolMode,
+    #[pyo3(get, set)]
+    /// str: how relus are laid out, accepts `lookup`, `poly`
+    pub relu_impl: ReluImpl,
+    #[pyo3(get, set)]
+    /// list[tuple[str, str]]: per node overrides of `relu_impl`
+    pub node_relu_impl: Vec<(String, ReluImpl)>,
+    #[pyo3(get, set)]
+    /// int: Newton–Raphson steps refining reciprocals from a lookup table seed, 0 range checks them exactly
+    pub recip_refinements: usize,
+    #[pyo3(get, set)]
+    /// float | None: lay out matmuls by weights with at least this fraction of zeros sparsely
+    pub sparse_weights_threshold: Option<f64>,
+    #[pyo3(get, set)]
+    /// int | None: budget of advice columns, calibration picks the fewest rows whose layout fits it.
+    /// Layers aren't tiled to fit it, models too wide even at the largest logrows fail
+    pub max_advice_cols: Option<usize>,
+    #[pyo3(get, set)]
+    /// int | None: budget of public instances, settings making more values public fail to generate
+    pub max_instances: Option<usize>,
+    #[pyo3(get, set)]
+    /// bool: fold back-to-back linear layers by constant weights into one before quantizing
+    pub fuse_linear: bool,
+    #[pyo3(get, set)]
+    /// bool: fold inference batch norms into the convs before them before quantizing
+    pub fold_batch_norms: bool,
+    #[pyo3(get, set)]
+    /// bool: reserve a public instance for the hash of a context each proof is bound to
+    pub bind_context: bool,
+    #[pyo3(get, set)]
+    /// int | None: depth of the Merkle tree of a dataset the input is proven to be a row of
+    pub dataset_depth: Option<usize>,
+    #[pyo3(get, set)]
+    /// list[tuple[int, int]]: `[start, end)` ranges of the flattened inputs constrained to be one-hot
+    pub categorical_groups: Vec<(usize, usize)>,
+    #[pyo3(get, set)]
+    /// tuple[int, float] | None: replace the output by a bit proving its element `class` exceeds `threshold`
+    pub threshold_check: Option<(usize, f64)>,
+    #[pyo3(get, set)]
+    /// float: warn of constants clipping more than this fraction of their values when quantized
+    pub clip_warn_threshold: f64,
+    #[pyo3(get, set)]
+    /// bool: fail witness generation, rather than warn, on outputs past their calibrated output_max
+    pub strict_output_max: bool,
+    #[pyo3(get, set)]
     /// str: check mode, accepts `safe`, `unsafe`
     pub check_mode: CheckMode,
     #[pyo3(get, set)]
@@ -214,6 +292,7 @@ impl From<PyRunArgs> for RunArgs {
         RunArgs {
             tolerance: Tolerance::from(py_run_args.tolerance),
             input_scale: py_run_args.input_scale,
+            input_range: py_run_args.input_range,
             param_scale: py_run_args.param_scale,
             num_inner_cols: py_run_args.num_inner_cols,
             scale_rebase_multiplier: py_run_args.scale_rebase_multiplier,
@@ -222,9 +301,29 @@ impl From<PyRunArgs> for RunArgs {
             input_visibility: py_run_args.input_visibility,
             output_visibility: py_run_args.output_visibility,
             param_visibility: py_run_args.param_visibility,
+            param_placement: py_run_args.param_placement,
             variables: py_run_args.variables,
             div_rebasing: py_run_args.div_rebasing,
             rebase_frac_zero_constants: py_run_args.rebase_frac_zero_constants,
+            whole_number_divisors: py_run_args.whole_number_divisors,
+            uniform_scale: py_run_args.uniform_scale,
+            custom_tables: py_run_args.custom_tables,
+            conv_strategy: py_run_args.conv_strategy,
+            avgpool_mode: py_run_args.avgpool_mode,
+            relu_impl: py_run_args.relu_impl,
+            node_relu_impl: py_run_args.node_relu_impl,
+            recip_refinements: py_run_args.recip_refinements,
+            sparse_weights_threshold: py_run_args.sparse_weights_threshold,
+            max_advice_cols: py_run_args.max_advice_cols,
+            max_instances: py_run_args.max_instances,
+            fuse_linear: py_run_args.fuse_linear,
+            fold_batch_norms: py_run_args.fold_batch_norms,
+            bind_context: py_run_args.bind_context,
+            dataset_depth: py_run_args.dataset_depth,
+            categorical_groups: py_run_args.categorical_groups,
+            threshold_check: py_run_args.threshold_check,
+            clip_warn_threshold: py_run_args.clip_warn_threshold,
+            strict_output_max: py_run_args.strict_output_max,
             check_mode: py_run_args.check_mode,
             commitment: Some(py_run_args.commitment.into()),
             decomp_base: py_run_args.decomp_base,
@@ -238,6 +337,7 @@ impl Into<PyRunArgs> for RunArgs {
         PyRunArgs {
             tolerance: self.tolerance.val,
             input_scale: self.input_scale,
+            input_range: self.input_range,
             param_scale: self.param_scale,
             num_inner_cols: self.num_inner_cols,
             scale_rebase_multiplier: self.scale_rebase_multiplier,
@@ -246,9 +346,29 @@ impl Into<PyRunArgs> for RunArgs {
             input_visibility: self.input_visibility,
             output_visibility: self.output_visibility,
             param_visibility: self.param_visibility,
+            param_placement: self.param_placement,
             variables: self.variables,
             div_rebasing: self.div_rebasing,
             rebase_frac_zero_constants: self.rebase_frac_zero_constants,
+            whole_number_divisors: self.whole_number_divisors,
+            uniform_scale: self.uniform_scale,
+            custom_tables: self.custom_tables,
+            conv_strategy: self.conv_strategy,
+            avgpool_mode: self.avgpool_mode,
+            relu_impl: self.relu_impl,
+            node_relu_impl: self.node_relu_impl,
+            recip_refinements: self.recip_refinements,
+            sparse_weights_threshold: self.sparse_weights_threshold,
+            max_advice_cols: self.max_advice_cols,
+            max_instances: self.max_instances,
+            fuse_linear: self.fuse_linear,
+            fold_batch_norms: self.fold_batch_norms,
+            bind_context: self.bind_context,
+            dataset_depth: self.dataset_depth,
+            categorical_groups: self.categorical_groups,
+            threshold_check: self.threshold_check,
+            clip_warn_threshold: self.clip_warn_threshold,
+            strict_output_max: self.strict_output_max,
             check_mode: self.check_mode,
             commitment: self.commitment.into(),
             decomp_base: self.decomp_base,
@@ -732,8 +852,7 @@ fn gen_vk_from_pk_aggr(path_to_pk: PathBuf, vk_output_path: PathBuf) -> PyResult
 ))]
 fn table(model: PathBuf, py_run_args: Option<PyRunArgs>) -> PyResult<String> {
     let run_args: RunArgs = py_run_args.unwrap_or_else(PyRunArgs::new).into();
-    let mut reader = File::open(model).map_err(|_| PyIOError::new_err("Failed to open model"))?;
-    let result = Model::new(&mut reader, &run_args);
+    let result = Model::from_run_args(&run_args, &model);
 
     match result {
         Ok(m) => Ok(m.table_nodes()),
@@ -840,7 +959,7 @@ fn gen_settings(
 ) -> Result<bool, PyErr> {
     let run_args: RunArgs = py_run_args.unwrap_or_else(PyRunArgs::new).into();
 
-    crate::execute::gen_circuit_settings(model, output, run_args).map_err(|e| {
+    crate::execute::gen_circuit_settings(model, output, run_args, false).map_err(|e| {
         let err_str = format!("Failed to generate settings: {}", e);
         PyRuntimeError::new_err(err_str)
     })?;
@@ -929,8 +1048,8 @@ fn calibrate_settings(
 ///
 /// Arguments
 /// ---------
-/// data: str
-///     Path to the data file
+/// data: str | dict[str, tuple[list[float], list[int]]]
+///     Path to the data file, or each input's name mapped to its flattened values and dims, so tensors already in memory don't need writing out
 ///
 /// model: str
 ///     Path to the compiled model file
@@ -944,33 +1063,78 @@ fn calibrate_settings(
 /// srs_path: str
 ///     Path to the SRS file
 ///
+/// context_hash: str
+///     A context to bind the proof to, if the circuit was compiled with `bind_context`
+///
+/// dataset: str
+///     Path to the dataset commitment (generated using `commit_dataset`) the input is a row of, if the circuit was compiled with `dataset_depth`
+///
+/// dataset_index: int
+///     The index of the input's row in the dataset
+///
 /// Returns
 /// -------
 /// dict
 ///     Python object containing the witness values
 ///
 #[pyfunction(signature = (
-    data=PathBuf::from(DEFAULT_DATA),
+    data=PyInputData::Path(PathBuf::from(DEFAULT_DATA)),
     model=PathBuf::from(DEFAULT_COMPILED_CIRCUIT),
     output=PathBuf::from(DEFAULT_WITNESS),
     vk_path=None,
     srs_path=None,
+    context_hash=None,
+    dataset=None,
+    dataset_index=None,
 ))]
+#[allow(clippy::too_many_arguments)]
 fn gen_witness(
     py: Python,
-    data: PathBuf,
+    data: PyInputData,
     model: PathBuf,
     output: Option<PathBuf>,
     vk_path: Option<PathBuf>,
     srs_path: Option<PathBuf>,
+    context_hash: Option<String>,
+    dataset: Option<PathBuf>,
+    dataset_index: Option<usize>,
 ) -> PyResult<Bound<'_, PyAny>> {
     pyo3_asyncio::tokio::future_into_py(py, async move {
-        let output = crate::execute::gen_witness(model, data, output, vk_path, srs_path)
-            .await
-            .map_err(|e| {
-                let err_str = format!("Failed to generate witness: {}", e);
-                PyRuntimeError::new_err(err_str)
-            })?;
+        let dataset = dataset.zip(dataset_index);
+        let output = match data {
+            PyInputData::Path(data) => {
+                crate::execute::gen_witness(
+                    model,
+                    data,
+                    output,
+                    vk_path,
+                    srs_path,
+                    false,
+                    context_hash,
+                    dataset,
+                )
+                .await
+            }
+            PyInputData::Tensors(tensors) => (|| {
+                let mut source = MemoryInputSource::new();
+                for (name, (values, dims)) in tensors {
+                    source.insert(&name, Tensor::new(Some(&values), &dims)?);
+                }
+                crate::execute::gen_witness_from(
+                    GraphCircuit::load(model)?,
+                    &source,
+                    output,
+                    vk_path,
+                    srs_path,
+                    context_hash,
+                    dataset,
+                )
+            })(),
+        }
+        .map_err(|e| {
+            let err_str = format!("Failed to generate witness: {}", e);
+            PyRuntimeError::new_err(err_str)
+        })?;
         Python::with_gil(|py| Ok(output.to_object(py)))
     })
 }
@@ -985,6 +1149,15 @@ fn gen_witness(
 /// model: str
 ///     Path to the compiled model file
 ///
+/// expected_output: str
+///     Path to a json file of the float outputs (`output_data`) to check the outputs against, e.g. the model's input.json
+///
+/// output_atol: float
+///     The absolute error allowed on each expected output, one quantization step at the output's scale if None
+///
+/// output_rtol: float
+///     The error allowed on each expected output, relative to its value
+///
 /// Returns
 /// -------
 /// bool
@@ -992,9 +1165,22 @@ fn gen_witness(
 #[pyfunction(signature = (
     witness=PathBuf::from(DEFAULT_WITNESS),
     model=PathBuf::from(DEFAULT_COMPILED_CIRCUIT),
+    expected_output=None,
+    output_atol=None,
+    output_rtol=DEFAULT_OUTPUT_RTOL.parse().unwrap(),
 ))]
-fn mock(witness: PathBuf, model: PathBuf) -> PyResult<bool> {
-    crate::execute::mock(model, witness).map_err(|e| {
+fn mock(
+    witness: PathBuf,
+    model: PathBuf,
+    expected_output: Option<PathBuf>,
+    output_atol: Option<f64>,
+    output_rtol: f64,
+) -> PyResult<bool> {
+    let output_tolerance = OutputTolerance {
+        abs: output_atol,
+        rel: output_rtol,
+    };
+    crate::execute::mock(model, witness, expected_output, output_tolerance).map_err(|e| {
         let err_str = format!("Failed to run mock: {}", e);
         PyRuntimeError::new_err(err_str)
     })?;
@@ -1116,6 +1302,9 @@ fn setup(
 /// srs_path: str
 ///     Path to the SRS file
 ///
+/// timeout: int
+///     Seconds after which the proof is aborted
+///
 /// Returns
 /// -------
 /// bool
@@ -1127,6 +1316,7 @@ fn setup(
     proof_path=None,
     proof_type=ProofType::default(),
     srs_path=None,
+    timeout=None,
 ))]
 fn prove(
     witness: PathBuf,
@@ -1135,6 +1325,7 @@ fn prove(
     proof_path: Option<PathBuf>,
     proof_type: ProofType,
     srs_path: Option<PathBuf>,
+    timeout: Option<u64>,
 ) -> PyResult<PyObject> {
     let snark = crate::execute::prove(
         witness,
@@ -1144,6 +1335,8 @@ fn prove(
         srs_path,
         proof_type,
         CheckMode::UNSAFE,
+        timeout.map(std::time::Duration::from_secs),
+        None,
     )
     .map_err(|e| {
         let err_str = format!("Failed to run prove: {}", e);
@@ -1172,6 +1365,27 @@ fn prove(
 /// non_reduced_srs: bool
 ///     Whether to reduce the number of SRS logrows to the number of instances rather than the number of logrows used for proofs (only works if the srs were generated in the same ceremony)
 ///
+/// context_hash: str
+///     The context the proof must be bound to, if the circuit was compiled with `bind_context`
+///
+/// weights_root: str
+///     Path to the weights root (generated using `commit_weights`) the proof must commit to, required if the params are merkle committed
+///
+/// dataset: str
+///     Path to the dataset commitment (generated using `commit_dataset`) the proof must open a row of, if the circuit was compiled with `dataset_depth`
+///
+/// instances: str
+///     Path to a json file of the public inputs and outputs (`input_data`, `output_data`) to check the proof against, in place of the ones it carries
+///
+/// expected_output: str
+///     Path to a json file of the float outputs (`output_data`) to check the proof's public outputs against, e.g. the model's input.json
+///
+/// output_atol: float
+///     The absolute error allowed on each expected output, one quantization step at the output's scale if None
+///
+/// output_rtol: float
+///     The error allowed on each expected output, relative to its value
+///
 /// Returns
 /// -------
 /// bool
@@ -1182,20 +1396,50 @@ fn prove(
     vk_path=PathBuf::from(DEFAULT_VK),
     srs_path=None,
     reduced_srs=DEFAULT_USE_REDUCED_SRS_FOR_VERIFICATION.parse::<bool>().unwrap(),
+    context_hash=None,
+    weights_root=None,
+    dataset=None,
+    instances=None,
+    expected_output=None,
+    output_atol=None,
+    output_rtol=DEFAULT_OUTPUT_RTOL.parse().unwrap(),
 ))]
+#[allow(clippy::too_many_arguments)]
 fn verify(
     proof_path: PathBuf,
     settings_path: PathBuf,
     vk_path: PathBuf,
     srs_path: Option<PathBuf>,
     reduced_srs: bool,
+    context_hash: Option<String>,
+    weights_root: Option<PathBuf>,
+    dataset: Option<PathBuf>,
+    instances: Option<PathBuf>,
+    expected_output: Option<PathBuf>,
+    output_atol: Option<f64>,
+    output_rtol: f64,
 ) -> Result<bool, PyErr> {
-    crate::execute::verify(proof_path, settings_path, vk_path, srs_path, reduced_srs).map_err(
-        |e| {
-            let err_str = format!("Failed to run verify: {}", e);
-            PyRuntimeError::new_err(err_str)
-        },
-    )?;
+    let output_tolerance = OutputTolerance {
+        abs: output_atol,
+        rel: output_rtol,
+    };
+    crate::execute::verify(
+        proof_path,
+        settings_path,
+        vk_path,
+        srs_path,
+        reduced_srs,
+        context_hash,
+        weights_root,
+        dataset,
+        instances,
+        expected_output,
+        output_tolerance,
+    )
+    .map_err(|e| {
+        let err_str = format!("Failed to run verify: {}", e);
+        PyRuntimeError::new_err(err_str)
+    })?;
 
     Ok(true)
 }
@@ -1305,6 +1549,70 @@ fn compile_circuit(
     Ok(true)
 }
 
+/// Computes the Merkle root a compiled model's params are committed to under `merkle` param visibility
+///
+/// Arguments
+/// ---------
+/// compiled_circuit: str
+///     Path to the compiled circuit
+///
+/// output: str
+///     Path to save the root to
+///
+/// Returns
+/// -------
+/// str
+///     The root, as a hex field element
+///
+#[pyfunction(signature = (
+    compiled_circuit=PathBuf::from(DEFAULT_COMPILED_CIRCUIT),
+    output=PathBuf::from(DEFAULT_WEIGHTS_ROOT),
+))]
+fn commit_weights(compiled_circuit: PathBuf, output: PathBuf) -> Result<String, PyErr> {
+    let root = crate::execute::commit_weights(compiled_circuit, output).map_err(|e| {
+        let err_str = format!("Failed to commit weights: {}", e);
+        PyRuntimeError::new_err(err_str)
+    })?;
+
+    Ok(root)
+}
+
+/// Commits to a dataset of inputs with a Poseidon Merkle tree, whose rows circuits compiled with `dataset_depth` prove their input is one of
+///
+/// Arguments
+/// ---------
+/// data: str
+///     Path to a json list of the dataset's rows, each in the format of a data file's `input_data`
+///
+/// compiled_circuit: str
+///     Path to the compiled circuit
+///
+/// output: str
+///     Path to save the root and each row's opening to
+///
+/// Returns
+/// -------
+/// str
+///     The root, as a hex field element
+///
+#[pyfunction(signature = (
+    data,
+    compiled_circuit=PathBuf::from(DEFAULT_COMPILED_CIRCUIT),
+    output=PathBuf::from(DEFAULT_DATASET_COMMITMENT),
+))]
+fn commit_dataset(
+    data: PathBuf,
+    compiled_circuit: PathBuf,
+    output: PathBuf,
+) -> Result<String, PyErr> {
+    let root = crate::execute::commit_dataset(data, compiled_circuit, output).map_err(|e| {
+        let err_str = format!("Failed to commit dataset: {}", e);
+        PyRuntimeError::new_err(err_str)
+    })?;
+
+    Ok(root)
+}
+
 /// Creates an aggregated proof
 ///
 /// Arguments
@@ -1543,6 +1851,53 @@ fn create_evm_verifier(
     })
 }
 
+/// Exports a standalone rust crate verifying single proofs of the circuit, which only depends on the halo2 verifier
+///
+/// Arguments
+/// ---------
+/// vk_path: str
+///     The path to the verification key file
+///
+/// settings_path: str
+///     The path to the settings file
+///
+/// out: str
+///     The directory to write the crate to
+///
+/// crate_name: str
+///     The name of the exported crate
+///
+/// srs_path: str
+///     The path to the SRS file
+///
+/// Returns
+/// -------
+/// bool
+///
+#[pyfunction(signature = (
+    vk_path=PathBuf::from(DEFAULT_VK),
+    settings_path=PathBuf::from(DEFAULT_SETTINGS),
+    out=PathBuf::from(DEFAULT_VERIFIER_CRATE),
+    crate_name=String::from(DEFAULT_VERIFIER_CRATE_NAME),
+    srs_path=None,
+))]
+fn export_verifier(
+    vk_path: PathBuf,
+    settings_path: PathBuf,
+    out: PathBuf,
+    crate_name: String,
+    srs_path: Option<PathBuf>,
+) -> PyResult<bool> {
+    crate::execute::export_verifier(vk_path, srs_path, settings_path, out, crate_name).map_err(
+        |e| {
+            let err_str = format!("Failed to run export_verifier: {}", e);
+            PyRuntimeError::new_err(err_str)
+        },
+    )?;
+
+    Ok(true)
+}
+
 /// Creates an Evm VK artifact. This command generated a VK with circuit specific meta data encoding in memory for use by the reusable H2 verifier.
 /// This is useful for deploying verifier that were otherwise too big to fit on chain and required aggregation.
 ///
@@ -1951,8 +2306,11 @@ fn ezkl(_py: Python<'_>, m: &PyModule) -> PyResult<()> {
     m.add_function(wrap_pyfunction!(mock_aggregate, m)?)?;
     m.add_function(wrap_pyfunction!(setup_aggregate, m)?)?;
     m.add_function(wrap_pyfunction!(compile_circuit, m)?)?;
+    m.add_function(wrap_pyfunction!(commit_weights, m)?)?;
+    m.add_function(wrap_pyfunction!(commit_dataset, m)?)?;
     m.add_function(wrap_pyfunction!(verify_aggr, m)?)?;
     m.add_function(wrap_pyfunction!(create_evm_verifier, m)?)?;
+    m.add_function(wrap_pyfunction!(export_verifier, m)?)?;
     m.add_function(wrap_pyfunction!(create_evm_vka, m)?)?;
     m.add_function(wrap_pyfunction!(deploy_evm, m)?)?;
     m.add_function(wrap_pyfunction!(deploy_da_evm, m)?)?;
@@ -25,7 +25,9 @@ use crate::{
     pfsys::{
         create_proof_circuit,
         evm::aggregation_kzg::{AggregationCircuit, PoseidonTranscript},
-        verify_proof_circuit, TranscriptType,
+        read_key_header,
+        verify::verify_inference_proof,
+        verify_proof_circuit, write_key_header, TranscriptType,
     },
     tensor::TensorType,
     CheckMode, Commitments, EZKLError as InnerEZKLError,
@@ -141,7 +143,8 @@ pub(crate) fn gen_vk(
     .map_err(|e| EZKLError::InternalError(format!("Failed to create verifying key: {}", e)))?;
 
     let mut serialized_vk = Vec::new();
-    vk.write(&mut serialized_vk, halo2_proofs::SerdeFormat::RawBytes)
+    write_key_header(&mut serialized_vk)
+        .and_then(|_| vk.write(&mut serialized_vk, halo2_proofs::SerdeFormat::RawBytes))
         .map_err(|e| {
             EZKLError::InternalError(format!("Failed to serialize verifying key: {}", e))
         })?;
@@ -163,6 +166,7 @@ pub(crate) fn gen_pk(
         .map_err(|e| EZKLError::InternalError(format!("Failed to deserialize circuit: {}", e)))?;
 
     let mut reader = BufReader::new(&vk[..]);
+    read_key_header(&mut reader, "verification key").map_err(InnerEZKLError::from)?;
     let vk = VerifyingKey::<G1Affine>::read::<_, GraphCircuit>(
         &mut reader,
         halo2_proofs::SerdeFormat::RawBytes,
@@ -174,7 +178,8 @@ pub(crate) fn gen_pk(
         .map_err(|e| EZKLError::InternalError(format!("Failed to create proving key: {}", e)))?;
 
     let mut serialized_pk = Vec::new();
-    pk.write(&mut serialized_pk, halo2_proofs::SerdeFormat::RawBytes)
+    write_key_header(&mut serialized_pk)
+        .and_then(|_| pk.write(&mut serialized_pk, halo2_proofs::SerdeFormat::RawBytes))
         .map_err(|e| EZKLError::InternalError(format!("Failed to serialize proving key: {}", e)))?;
 
     Ok(serialized_pk)
@@ -191,74 +196,18 @@ pub(crate) fn verify(
     let circuit_settings: GraphSettings = serde_json::from_slice(&settings[..])
         .map_err(|e| EZKLError::InternalError(format!("Failed to deserialize settings: {}", e)))?;
 
-    let proof: crate::pfsys::Snark<Fr, G1Affine> = serde_json::from_slice(&proof[..])
+    let snark: crate::pfsys::Snark<Fr, G1Affine> = serde_json::from_slice(&proof[..])
         .map_err(|e| EZKLError::InternalError(format!("Failed to deserialize proof: {}", e)))?;
 
-    let mut reader = BufReader::new(&vk[..]);
-    let vk = VerifyingKey::<G1Affine>::read::<_, GraphCircuit>(
-        &mut reader,
-        halo2_proofs::SerdeFormat::RawBytes,
-        circuit_settings.clone(),
-    )
-    .map_err(|e| EZKLError::InternalError(format!("Failed to deserialize vk: {}", e)))?;
-
-    let orig_n = 1 << circuit_settings.run_args.logrows;
-    let commitment = circuit_settings.run_args.commitment.into();
-
-    let mut reader = BufReader::new(&srs[..]);
-    let result = match commitment {
-        Commitments::KZG => {
-            let params: ParamsKZG<Bn256> = get_params(&mut reader)?;
-            let strategy = KZGSingleStrategy::new(params.verifier_params());
-            match proof.transcript_type {
-                TranscriptType::EVM => verify_proof_circuit::<
-                    VerifierSHPLONK<'_, Bn256>,
-                    KZGCommitmentScheme<Bn256>,
-                    KZGSingleStrategy<_>,
-                    _,
-                    EvmTranscript<G1Affine, _, _, _>,
-                >(&proof, &params, &vk, strategy, orig_n),
-                TranscriptType::Poseidon => {
-                    verify_proof_circuit::<
-                        VerifierSHPLONK<'_, Bn256>,
-                        KZGCommitmentScheme<Bn256>,
-                        KZGSingleStrategy<_>,
-                        _,
-                        PoseidonTranscript<NativeLoader, _>,
-                    >(&proof, &params, &vk, strategy, orig_n)
-                }
-            }
-        }
-        Commitments::IPA => {
-            let params: ParamsIPA<_> = get_params(&mut reader)?;
-            let strategy = IPASingleStrategy::new(params.verifier_params());
-            match proof.transcript_type {
-                TranscriptType::EVM => verify_proof_circuit::<
-                    VerifierIPA<_>,
-                    IPACommitmentScheme<G1Affine>,
-                    IPASingleStrategy<_>,
-                    _,
-                    EvmTranscript<G1Affine, _, _, _>,
-                >(&proof, &params, &vk, strategy, orig_n),
-                TranscriptType::Poseidon => {
-                    verify_proof_circuit::<
-                        VerifierIPA<_>,
-                        IPACommitmentScheme<G1Affine>,
-                        IPASingleStrategy<_>,
-                        _,
-                        PoseidonTranscript<NativeLoader, _>,
-                    >(&proof, &params, &vk, strategy, orig_n)
-                }
-            }
-        }
-    };
+    let verified = verify_inference_proof(&circuit_settings, &vk, &srs, &proof, &snark.instances)
+        .map_err(InnerEZKLError::from)?;
 
-    match result {
-        Ok(_) => Ok(true),
-        Err(e) => Err(EZKLError::InternalError(format!(
-            "Verification failed: {}",
-            e
-        ))),
+    if verified {
+        Ok(true)
+    } else {
+        Err(EZKLError::InternalError(
+            "Verification failed: the proof does not verify against its instances".to_string(),
+        ))
     }
 }
 
@@ -273,8 +222,10 @@ pub(crate) fn verify_aggr(
 ) -> Result<bool, EZKLError> {
     let proof: crate::pfsys::Snark<Fr, G1Affine> = serde_json::from_slice(&proof[..])
         .map_err(|e| EZKLError::InternalError(format!("Failed to deserialize proof: {}", e)))?;
+    proof.check_version().map_err(InnerEZKLError::from)?;
 
     let mut reader = BufReader::new(&vk[..]);
+    read_key_header(&mut reader, "verification key").map_err(InnerEZKLError::from)?;
     let vk = VerifyingKey::<G1Affine>::read::<_, AggregationCircuit>(
         &mut reader,
         halo2_proofs::SerdeFormat::RawBytes,
@@ -363,6 +314,7 @@ pub(crate) fn prove(
     let data: GraphWitness = serde_json::from_slice(&witness[..]).map_err(InnerEZKLError::from)?;
 
     let mut reader = BufReader::new(&pk[..]);
+    read_key_header(&mut reader, "proving key").map_err(InnerEZKLError::from)?;
     let pk = ProvingKey::<G1Affine>::read::<_, GraphCircuit>(
         &mut reader,
         halo2_proofs::SerdeFormat::RawBytes,
@@ -485,6 +437,7 @@ pub(crate) fn vk_validation(vk: Vec<u8>, settings: Vec<u8>) -> Result<bool, EZKL
         serde_json::from_slice(&settings[..]).map_err(InnerEZKLError::from)?;
 
     let mut reader = BufReader::new(&vk[..]);
+    read_key_header(&mut reader, "verification key").map_err(InnerEZKLError::from)?;
     let _ = VerifyingKey::<G1Affine>::read::<_, GraphCircuit>(
         &mut reader,
         halo2_proofs::SerdeFormat::RawBytes,
@@ -502,6 +455,7 @@ pub(crate) fn pk_validation(pk: Vec<u8>, settings: Vec<u8>) -> Result<bool, EZKL
         serde_json::from_slice(&settings[..]).map_err(InnerEZKLError::from)?;
 
     let mut reader = BufReader::new(&pk[..]);
+    read_key_header(&mut reader, "proving key").map_err(InnerEZKLError::from)?;
     let _ = ProvingKey::<G1Affine>::read::<_, GraphCircuit>(
         &mut reader,
         halo2_proofs::SerdeFormat::RawBytes,
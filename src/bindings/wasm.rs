@@ -145,6 +145,8 @@ pub fn kzgCommit(
     let mut reader = std::io::BufReader::new(&vk[..]);
     let circuit_settings: GraphSettings = serde_json::from_slice(&settings[..])
         .map_err(|e| JsError::new(&format!("Failed to deserialize settings: {}", e)))?;
+    crate::pfsys::read_key_header(&mut reader, "verification key")
+        .map_err(|e| JsError::new(&format!("Failed to deserialize vk: {}", e)))?;
     let vk = VerifyingKey::<G1Affine>::read::<_, GraphCircuit>(
         &mut reader,
         halo2_proofs::SerdeFormat::RawBytes,
@@ -114,6 +114,75 @@ impl<S: Spec<Fp, WIDTH, RATE> + Sync, const WIDTH: usize, const RATE: usize, con
             instance,
         )
     }
+
+    /// Hashes `input` like [Module::layout], returning its assigned cells and the cell holding their
+    /// digest. The digest is never constrained to an instance, so callers can hash it further.
+    pub fn layout_digest(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        input: &[ValTensor<Fp>],
+        constants: &mut ConstantsMap<Fp>,
+    ) -> Result<(ValTensor<Fp>, AssignedCell<Fp, Fp>), ModuleError> {
+        let (mut input_cells, zero_val) = self.layout_inputs(layouter, input, constants)?;
+        // extract the values from the input cells
+        let mut assigned_input: Tensor<ValType<Fp>> =
+            input_cells.iter().map(|e| ValType::from(e.clone())).into();
+        let len = assigned_input.len();
+
+        let start_time = instant::Instant::now();
+
+        let mut one_iter = false;
+        // do the Tree dance baby
+        while input_cells.len() > 1 || !one_iter {
+            let hashes: Result<Vec<AssignedCell<Fp, Fp>>, ModuleError> = input_cells
+                .chunks(L)
+                .enumerate()
+                .map(|(i, block)| {
+                    let _start_time = instant::Instant::now();
+
+                    let mut block = block.to_vec();
+                    let remainder = block.len() % L;
+
+                    if remainder != 0 {
+                        block.extend(vec![zero_val.clone(); L - remainder]);
+                    }
+
+                    let pow5_chip = Pow5Chip::construct(self.config.pow5_config.clone());
+                    // initialize the hasher
+                    let hasher = Hash::<_, _, S, ConstantLength<L>, WIDTH, RATE>::init(
+                        pow5_chip,
+                        layouter.namespace(|| "block_hasher"),
+                    )?;
+
+                    let hash = hasher.hash(
+                        layouter.namespace(|| "hash"),
+                        block.to_vec().try_into().map_err(|_| Error::Synthesis)?,
+                    );
+
+                    if i == 0 {
+                        log::trace!("block (L={:?}) took: {:?}", L, _start_time.elapsed());
+                    }
+
+                    hash
+                })
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| e.into());
+
+            log::trace!("hashes (N={:?}) took: {:?}", len, start_time.elapsed());
+            one_iter = true;
+            input_cells = hashes?;
+        }
+
+        let duration = start_time.elapsed();
+        log::trace!("layout (N={:?}) took: {:?}", len, duration);
+
+        assigned_input.reshape(input[0].dims()).map_err(|e| {
+            log::error!("reshape failed: {:?}", e);
+            Error::Synthesis
+        })?;
+
+        Ok((assigned_input.into(), input_cells[0].clone()))
+    }
 }
 
 impl<S: Spec<Fp, WIDTH, RATE> + Sync, const WIDTH: usize, const RATE: usize, const L: usize>
@@ -295,68 +364,7 @@ impl<S: Spec<Fp, WIDTH, RATE> + Sync, const WIDTH: usize, const RATE: usize, con
         row_offset: usize,
         constants: &mut ConstantsMap<Fp>,
     ) -> Result<ValTensor<Fp>, ModuleError> {
-        let (mut input_cells, zero_val) = self.layout_inputs(layouter, input, constants)?;
-        // extract the values from the input cells
-        let mut assigned_input: Tensor<ValType<Fp>> =
-            input_cells.iter().map(|e| ValType::from(e.clone())).into();
-        let len = assigned_input.len();
-
-        let start_time = instant::Instant::now();
-
-        let mut one_iter = false;
-        // do the Tree dance baby
-        while input_cells.len() > 1 || !one_iter {
-            let hashes: Result<Vec<AssignedCell<Fp, Fp>>, ModuleError> = input_cells
-                .chunks(L)
-                .enumerate()
-                .map(|(i, block)| {
-                    let _start_time = instant::Instant::now();
-
-                    let mut block = block.to_vec();
-                    let remainder = block.len() % L;
-
-                    if remainder != 0 {
-                        block.extend(vec![zero_val.clone(); L - remainder]);
-                    }
-
-                    let pow5_chip = Pow5Chip::construct(self.config.pow5_config.clone());
-                    // initialize the hasher
-                    let hasher = Hash::<_, _, S, ConstantLength<L>, WIDTH, RATE>::init(
-                        pow5_chip,
-                        layouter.namespace(|| "block_hasher"),
-                    )?;
-
-                    let hash = hasher.hash(
-                        layouter.namespace(|| "hash"),
-                        block.to_vec().try_into().map_err(|_| Error::Synthesis)?,
-                    );
-
-                    if i == 0 {
-                        log::trace!("block (L={:?}) took: {:?}", L, _start_time.elapsed());
-                    }
-
-                    hash
-                })
-                .collect::<Result<Vec<_>, _>>()
-                .map_err(|e| e.into());
-
-            log::trace!("hashes (N={:?}) took: {:?}", len, start_time.elapsed());
-            one_iter = true;
-            input_cells = hashes?;
-        }
-
-        let duration = start_time.elapsed();
-        log::trace!("layout (N={:?}) took: {:?}", len, duration);
-
-        let result = Tensor::from(input_cells.iter().map(|e| ValType::from(e.clone())));
-
-        let output = match result[0].clone() {
-            ValType::PrevAssigned(v) => v,
-            _ => {
-                log::error!("wrong input type, must be previously assigned");
-                return Err(Error::Synthesis.into());
-            }
-        };
+        let (assigned_input, output) = self.layout_digest(layouter, input, constants)?;
 
         if let Some(instance) = self.config.instance {
             layouter.assign_region(
@@ -374,14 +382,9 @@ impl<S: Spec<Fp, WIDTH, RATE> + Sync, const WIDTH: usize, const RATE: usize, con
                 },
             )?;
 
-            assigned_input.reshape(input[0].dims()).map_err(|e| {
-                log::error!("reshape failed: {:?}", e);
-                Error::Synthesis
-            })?;
-
-            Ok(assigned_input.into())
+            Ok(assigned_input)
         } else {
-            Ok(result.into())
+            Ok(Tensor::from([ValType::from(output)].into_iter()).into())
         }
     }
 
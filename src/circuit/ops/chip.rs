@@ -23,6 +23,7 @@ use crate::{
         table::{Range, RangeCheck, Table},
         utils,
     },
+    fieldutils::IntegerRep,
     tensor::{Tensor, TensorType, ValTensor, VarTensor},
 };
 use std::{collections::BTreeMap, marker::PhantomData};
@@ -76,6 +77,161 @@ impl FromStr for CheckMode {
     }
 }
 
+#[allow(missing_docs)]
+/// An enum representing how convolutions are laid out: a dot product per output read straight off
+/// the image, or an unfolding of the image patches contracted against the kernel in one matmul
+#[derive(
+    Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize, Default, Copy,
+)]
+pub enum ConvStrategy {
+    #[default]
+    Direct,
+    Im2col,
+}
+
+impl std::fmt::Display for ConvStrategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConvStrategy::Direct => write!(f, "direct"),
+            ConvStrategy::Im2col => write!(f, "im2col"),
+        }
+    }
+}
+
+#[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
+impl ToFlags for ConvStrategy {
+    /// Convert the struct to a subcommand string
+    fn to_flags(&self) -> Vec<String> {
+        vec![format!("{}", self)]
+    }
+}
+
+impl From<String> for ConvStrategy {
+    fn from(value: String) -> Self {
+        Self::from_str(value.as_str()).unwrap()
+    }
+}
+
+impl FromStr for ConvStrategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "direct" => Ok(ConvStrategy::Direct),
+            "im2col" => Ok(ConvStrategy::Im2col),
+            _ => Err("Invalid value for ConvStrategy".to_string()),
+        }
+    }
+}
+
+#[allow(missing_docs)]
+/// An enum representing how average pooling divides by the window size: exactly, or by
+/// the nearest power of two, which folds into the output scale at no cost but is lossy
+/// when the window size isn't a power of two
+#[derive(
+    Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize, Default, Copy,
+)]
+pub enum AvgPoolMode {
+    #[default]
+    Exact,
+    Approx,
+}
+
+impl AvgPoolMode {
+    /// How much dividing a sum over `window_size` elements raises its scale by. Exact division
+    /// leaves the scale as is, approximate division reinterprets the sum at a higher scale.
+    pub fn scale_shift(&self, window_size: usize) -> crate::Scale {
+        match self {
+            AvgPoolMode::Exact => 0,
+            AvgPoolMode::Approx => (window_size as f64).log2().round() as crate::Scale,
+        }
+    }
+}
+
+impl std::fmt::Display for AvgPoolMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AvgPoolMode::Exact => write!(f, "exact"),
+            AvgPoolMode::Approx => write!(f, "approx"),
+        }
+    }
+}
+
+#[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
+impl ToFlags for AvgPoolMode {
+    /// Convert the struct to a subcommand string
+    fn to_flags(&self) -> Vec<String> {
+        vec![format!("{}", self)]
+    }
+}
+
+impl From<String> for AvgPoolMode {
+    fn from(value: String) -> Self {
+        Self::from_str(value.as_str()).unwrap()
+    }
+}
+
+impl FromStr for AvgPoolMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "exact" => Ok(AvgPoolMode::Exact),
+            "approx" => Ok(AvgPoolMode::Approx),
+            _ => Err("Invalid value for AvgPoolMode".to_string()),
+        }
+    }
+}
+
+#[allow(missing_docs)]
+/// An enum representing how relus are laid out: by the sign of the input's decomposition into
+/// range checked limbs, or by a witnessed sign bit, constrained to be boolean, whose product with
+/// the input is the output. The latter range checks `2 * output - input`, the input's magnitude, over
+/// the lookup range rather than decomposing it, which is cheaper when the lookup range is small
+#[derive(
+    Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize, Default, Copy,
+)]
+pub enum ReluImpl {
+    #[default]
+    Lookup,
+    Poly,
+}
+
+impl std::fmt::Display for ReluImpl {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReluImpl::Lookup => write!(f, "lookup"),
+            ReluImpl::Poly => write!(f, "poly"),
+        }
+    }
+}
+
+#[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
+impl ToFlags for ReluImpl {
+    /// Convert the struct to a subcommand string
+    fn to_flags(&self) -> Vec<String> {
+        vec![format!("{}", self)]
+    }
+}
+
+impl From<String> for ReluImpl {
+    fn from(value: String) -> Self {
+        Self::from_str(value.as_str()).unwrap()
+    }
+}
+
+impl FromStr for ReluImpl {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "lookup" => Ok(ReluImpl::Lookup),
+            "poly" => Ok(ReluImpl::Poly),
+            _ => Err("Invalid value for ReluImpl".to_string()),
+        }
+    }
+}
+
 #[allow(missing_docs)]
 /// An enum representing the tolerance we can accept for the accumulated arguments, either absolute or percentage
 #[derive(Clone, Default, Debug, PartialEq, PartialOrd, Serialize, Deserialize, Copy)]
@@ -150,6 +306,40 @@ impl<'source> FromPyObject<'source> for CheckMode {
     }
 }
 
+#[cfg(feature = "python-bindings")]
+/// Converts ConvStrategy into a PyObject (Required for ConvStrategy to be compatible with Python)
+impl IntoPy<PyObject> for ConvStrategy {
+    fn into_py(self, py: Python) -> PyObject {
+        self.to_string().to_object(py)
+    }
+}
+
+#[cfg(feature = "python-bindings")]
+/// Obtains ConvStrategy from PyObject (Required for ConvStrategy to be compatible with Python)
+impl<'source> FromPyObject<'source> for ConvStrategy {
+    fn extract(ob: &'source PyAny) -> PyResult<Self> {
+        let trystr = <PyString as PyTryFrom>::try_from(ob)?;
+        ConvStrategy::from_str(&trystr.to_string()).map_err(PyValueError::new_err)
+    }
+}
+
+#[cfg(feature = "python-bindings")]
+/// Converts AvgPoolMode into a PyObject (Required for AvgPoolMode to be compatible with Python)
+impl IntoPy<PyObject> for AvgPoolMode {
+    fn into_py(self, py: Python) -> PyObject {
+        self.to_string().to_object(py)
+    }
+}
+
+#[cfg(feature = "python-bindings")]
+/// Obtains AvgPoolMode from PyObject (Required for AvgPoolMode to be compatible with Python)
+impl<'source> FromPyObject<'source> for AvgPoolMode {
+    fn extract(ob: &'source PyAny) -> PyResult<Self> {
+        let trystr = <PyString as PyTryFrom>::try_from(ob)?;
+        AvgPoolMode::from_str(&trystr.to_string()).map_err(PyValueError::new_err)
+    }
+}
+
 #[cfg(feature = "python-bindings")]
 /// Converts Tolerance into a PyObject (Required for Tolerance to be compatible with Python)
 impl IntoPy<PyObject> for Tolerance {
@@ -312,6 +502,10 @@ impl<F: PrimeField + TensorType + PartialOrd> RangeChecks<F> {
     }
 }
 
+/// The lookup range of a [BaseConfig] before the model's is known, e.g. while calibration
+/// measures the inputs of its lookups
+pub const UNBOUNDED_RANGE: Range = (IntegerRep::MIN, IntegerRep::MAX);
+
 /// Configuration for an accumulated arg.
 #[derive(Clone, Debug, Default)]
 pub struct BaseConfig<F: PrimeField + TensorType + PartialOrd> {
@@ -327,6 +521,9 @@ pub struct BaseConfig<F: PrimeField + TensorType + PartialOrd> {
     pub shuffles: Shuffles,
     /// Activate sanity checks
     pub check_mode: CheckMode,
+    /// The range the lookup tables span, which also bounds the inputs of ops constrained without
+    /// a table of their own. Unbounded until the model's lookup range is set.
+    pub lookup_range: Range,
     _marker: PhantomData<F>,
 }
 
@@ -340,6 +537,7 @@ impl<F: PrimeField + TensorType + PartialOrd + std::hash::Hash> BaseConfig<F> {
             shuffles: Shuffles::dummy(col_size, num_inner_cols),
             range_checks: RangeChecks::dummy(col_size, num_inner_cols),
             check_mode: CheckMode::SAFE,
+            lookup_range: UNBOUNDED_RANGE,
             _marker: PhantomData,
         }
     }
@@ -479,6 +677,7 @@ impl<F: PrimeField + TensorType + PartialOrd + std::hash::Hash> BaseConfig<F> {
             shuffles: Shuffles::default(),
             range_checks: RangeChecks::default(),
             check_mode,
+            lookup_range: UNBOUNDED_RANGE,
             _marker: PhantomData,
         }
     }
@@ -511,8 +710,13 @@ impl<F: PrimeField + TensorType + PartialOrd + std::hash::Hash> BaseConfig<F> {
         // we borrow mutably twice so we need to do this dance
 
         let table = if !self.static_lookups.tables.contains_key(nl) {
-            // as all tables have the same input we see if there's another table who's input we can reuse
-            let table = if let Some(table) = self.static_lookups.tables.values().next() {
+            // tables over the same range have the same input so we see if there's another table who's input we can reuse
+            let table = if let Some(table) = self
+                .static_lookups
+                .tables
+                .values()
+                .find(|t| t.range == lookup_range)
+            {
                 Table::<F>::configure(
                     cs,
                     lookup_range,
@@ -931,17 +1135,19 @@ impl<F: PrimeField + TensorType + PartialOrd + std::hash::Hash> BaseConfig<F> {
 
     /// layout_tables must be called before layout.
     pub fn layout_tables(&mut self, layouter: &mut impl Layouter<F>) -> Result<(), CircuitError> {
-        for (i, table) in self.static_lookups.tables.values_mut().enumerate() {
+        // input columns are shared between tables over the same range, so only assign them once
+        let mut assigned_inputs = vec![];
+        for table in self.static_lookups.tables.values_mut() {
+            let preassigned_input = assigned_inputs.contains(&table.table_inputs);
             if !table.is_assigned {
                 debug!(
                     "laying out table for {}",
                     crate::circuit::ops::Op::<F>::as_string(&table.nonlinearity)
                 );
-                if i == 0 {
-                    table.layout(layouter, false)?;
-                } else {
-                    table.layout(layouter, true)?;
-                }
+                table.layout(layouter, preassigned_input)?;
+            }
+            if !preassigned_input {
+                assigned_inputs.push(table.table_inputs.clone());
             }
         }
         Ok(())
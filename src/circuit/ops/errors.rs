@@ -97,4 +97,10 @@ pub enum CircuitError {
     /// Invalid scale
     #[error("negative scale for an op that requires positive inputs {0}")]
     NegativeScale(String),
+    /// A bias not at the scale of the products it is added to
+    #[error("bias of {0} is at scale {1}, the products it is added to are at scale {2}")]
+    BiasScaleMismatch(String, crate::Scale, crate::Scale),
+    /// A witness log that can't be written or read
+    #[error("[witness log] {0}")]
+    WitnessLog(String),
 }
@@ -0,0 +1,581 @@
+use halo2_proofs::circuit::Value;
+use halo2curves::bn256::Fr as Fp;
+use itertools::Itertools;
+
+use super::{
+    chip::{BaseConfig, ConvStrategy},
+    layouts,
+    poly::SparsityPattern,
+    region::{RegionCtx, RegionSettings},
+    CircuitError, Op,
+};
+use crate::tensor::{ops::crop_negative_padding, Tensor, TensorError, ValTensor};
+
+/// The column size of the configs gadgets are measured on, large enough that nothing wraps.
+const MEASURE_LOGROWS: usize = 24;
+
+/// Spatial output dims of a window sliding over `image` (the spatial dims only), as in conv and
+/// pooling layers. The window, padding, stride and dilation must each cover every spatial dim.
+/// ```
+/// use ezkl::circuit::ops::estimate::window_output_dims;
+///
+/// let dims = window_output_dims(&[7, 6], &[3, 3], &[(1, 1), (0, 0)], &[2, 1], &[1, 2]).unwrap();
+/// assert_eq!(dims, vec![4, 2]);
+///
+/// // a stride given for the height only doesn't quietly leave the width unstrided
+/// assert!(window_output_dims(&[7, 6], &[3, 3], &[(1, 1), (0, 0)], &[2], &[1, 2]).is_err());
+/// ```
+pub fn window_output_dims(
+    image: &[usize],
+    window: &[usize],
+    padding: &[(usize, usize)],
+    stride: &[usize],
+    dilation: &[usize],
+) -> Result<Vec<usize>, TensorError> {
+    let rank = image.len();
+    if [window.len(), padding.len(), stride.len(), dilation.len()] != [rank; 4] {
+        return Err(TensorError::DimMismatch(format!(
+            "window {:?}, padding {:?}, stride {:?} and dilation {:?} over spatial dims {:?}",
+            window, padding, stride, dilation, image
+        )));
+    }
+    (0..rank)
+        .map(|i| {
+            let extent = dilation[i] * (window[i].max(1) - 1) + 1;
+            (padding[i].0 + image[i] + padding[i].1)
+                .checked_sub(extent)
+                .ok_or_else(|| TensorError::Overflow("window".to_string()))?
+                .checked_div(stride[i])
+                .ok_or_else(|| TensorError::Overflow("window".to_string()))?
+                .checked_add(1)
+                .ok_or_else(|| TensorError::Overflow("window".to_string()))
+        })
+        .collect()
+}
+
+/// For each output position along a dim, how many of the window's taps land on the image rather
+/// than on padding.
+fn window_taps(
+    image: usize,
+    window: usize,
+    padding: (usize, usize),
+    stride: usize,
+    dilation: usize,
+    output: usize,
+) -> Vec<usize> {
+    (0..output)
+        .map(|o| {
+            (0..window)
+                .filter(|k| {
+                    let at = o * stride + k * dilation;
+                    at >= padding.0 && at < padding.0 + image
+                })
+                .count()
+        })
+        .collect()
+}
+
+/// Estimates how many cells ([RegionCtx::linear_coord]) ops take up when laid out, from the shapes
+/// of their inputs alone. Linear ops are costed by formula. Gadgets built on decompositions are
+/// measured once on a handful of elements and scaled up.
+///
+/// Estimates assume each op starts on a fresh row and that no input has been assigned yet, as in
+/// a dummy layout. They ignore constant zero weights, which the layouts skip, so they are upper
+/// bounds for linear ops.
+#[derive(Clone, Debug)]
+pub struct Estimator {
+    num_inner_cols: usize,
+    settings: RegionSettings,
+}
+
+impl Estimator {
+    /// An estimator for circuits with `num_inner_cols` columns per advice block, decomposing with
+    /// `decomp_base` and `decomp_legs`.
+    pub fn new(num_inner_cols: usize, decomp_base: usize, decomp_legs: usize) -> Self {
+        Self {
+            num_inner_cols,
+            settings: RegionSettings::all_false(decomp_base, decomp_legs),
+        }
+    }
+
+    /// The rows `assignments` cells take up.
+    pub fn rows(&self, assignments: usize) -> usize {
+        assignments.div_ceil(self.num_inner_cols)
+    }
+
+    fn align(&self, assignments: usize) -> usize {
+        self.rows(assignments) * self.num_inner_cols
+    }
+
+    /// A dot product over `len` elements, which takes up whole rows.
+    pub fn dot(&self, len: usize) -> usize {
+        self.align(len)
+    }
+
+    /// A pairwise add, sub or mult over `len` elements.
+    pub fn pairwise(&self, len: usize) -> usize {
+        len
+    }
+
+    /// An einsum of tensors with dims `inputs`. Only einsums laid out as one dot product per
+    /// output element (such as matmuls) are costed.
+    pub fn einsum(&self, inputs: &[Vec<usize>], equation: &str) -> Option<usize> {
+        let (inputs_eq, output_eq) = equation.split_once("->")?;
+        let inputs_eq = inputs_eq.split(',').collect::<Vec<_>>();
+        if inputs.len() != 2 || inputs_eq.len() != 2 {
+            return None;
+        }
+        let mut sizes = std::collections::HashMap::new();
+        for (eq, dims) in inputs_eq.iter().zip(inputs) {
+            if eq.len() != dims.len() {
+                return None;
+            }
+            for (c, d) in eq.chars().zip(dims) {
+                sizes.insert(c, *d);
+            }
+        }
+        let in_both = |c: &char| inputs_eq.iter().all(|eq| eq.contains(*c));
+        // a dot product per output element needs every index not summed over to be kept
+        if sizes.keys().any(|c| !in_both(c) && !output_eq.contains(*c)) {
+            return None;
+        }
+        let contracted = sizes
+            .iter()
+            .filter(|(c, _)| !output_eq.contains(**c))
+            .map(|(_, d)| *d)
+            .product::<usize>();
+        let outputs = output_eq
+            .chars()
+            .map(|c| sizes.get(&c).copied().unwrap_or(1))
+            .product::<usize>();
+        Some(outputs * self.dot(contracted))
+    }
+
+    /// A matmul (see [layouts::sparse_matmul]) by a constant operand with nonzero entries where
+    /// `pattern` has them, with `out_len` outputs. Each output is a dot product over its feature's
+    /// nonzero entries only.
+    pub fn sparse_matmul(&self, out_len: usize, pattern: &SparsityPattern) -> usize {
+        let per_feature = out_len / pattern.num_rows().max(1);
+        let dots = (0..pattern.num_rows())
+            .map(|row| self.dot(pattern.row(row).len()))
+            .sum::<usize>();
+        per_feature * dots
+    }
+
+    /// A conv (see [layouts::conv] and [layouts::conv_im2col]) of a (batch,) channels first image
+    /// by `kernel`. Taps landing on padding are constant zeros, which the dot products skip.
+    #[allow(clippy::too_many_arguments)]
+    pub fn conv(
+        &self,
+        image: &[usize],
+        kernel: &[usize],
+        padding: &[(isize, isize)],
+        stride: &[usize],
+        group: usize,
+        has_bias: bool,
+        strategy: ConvStrategy,
+    ) -> Result<usize, CircuitError> {
+        let mut image = image.to_vec();
+        if image.len() == kernel.len() - 1 {
+            image.insert(0, 1);
+        }
+        let (image_len, kernel_len) = (
+            image.iter().product::<usize>(),
+            kernel.iter().product::<usize>(),
+        );
+        // the image and kernel are assigned side by side before the loop over outputs
+        let assign = self.align(image_len.max(kernel_len));
+
+        let (kept, padding) = crop_negative_padding(&image, padding)?;
+        let spatial = kept[2..].iter().map(|r| r.len()).collect::<Vec<_>>();
+        let dilation = vec![1; spatial.len()];
+        let slides = window_output_dims(&spatial, &kernel[2..], &padding, stride, &dilation)?;
+        let taps = (0..spatial.len())
+            .map(|i| {
+                window_taps(
+                    spatial[i],
+                    kernel[i + 2],
+                    padding[i],
+                    stride[i],
+                    dilation[i],
+                    slides[i],
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let channels_per_group = image[1] / group.max(1);
+        let dots = taps
+            .iter()
+            .map(|t| t.iter().copied())
+            .multi_cartesian_product()
+            .map(|t| self.dot(channels_per_group * t.iter().product::<usize>()))
+            .sum::<usize>();
+        let outputs_per_position = image[0] * kernel[0];
+        let num_outputs = outputs_per_position * slides.iter().product::<usize>();
+
+        let bias = match (has_bias, strategy) {
+            (false, _) => 0,
+            // direct convs add the bias to each output on a row of its own
+            (true, ConvStrategy::Direct) => self.align(1) * num_outputs,
+            (true, ConvStrategy::Im2col) => self.pairwise(num_outputs),
+        };
+        Ok(assign + outputs_per_position * dots + bias)
+    }
+
+    /// A sum (or average) pool (see [layouts::sumpool]), laid out as a conv by a unit kernel per
+    /// batch and channel. `divide` is set when averages are divided exactly.
+    pub fn sumpool(
+        &self,
+        image: &[usize],
+        padding: &[(usize, usize)],
+        stride: &[usize],
+        kernel_shape: &[usize],
+        divide: bool,
+    ) -> Result<usize, CircuitError> {
+        let spatial = &image[2..];
+        let mut kernel = vec![1, 1];
+        kernel.extend(kernel_shape);
+        let mut single = vec![1, 1];
+        single.extend(spatial);
+        let conv_padding = padding
+            .iter()
+            .map(|(before, after)| (*before as isize, *after as isize))
+            .collect::<Vec<_>>();
+        let per_channel = self.conv(
+            &single,
+            &kernel,
+            &conv_padding,
+            stride,
+            1,
+            false,
+            ConvStrategy::Direct,
+        )?;
+        let mut total = image[0] * image[1] * per_channel;
+
+        if divide {
+            let dilation = vec![1; spatial.len()];
+            let slides = window_output_dims(spatial, kernel_shape, padding, stride, &dilation)?;
+            let num_outputs = image[0] * image[1] * slides.iter().product::<usize>();
+            let divisor = Fp::from(kernel_shape.iter().product::<usize>() as u64);
+            total += self.scale_up(num_outputs, 1, |config, region, values| {
+                layouts::loop_div(config, region, &[values[0].clone()], divisor).map(|_| ())
+            })?;
+        }
+        Ok(total)
+    }
+
+    /// A max pool (see [layouts::max_pool]): a max over each window, which is measured.
+    pub fn max_pool(
+        &self,
+        image: &[usize],
+        padding: &[(usize, usize)],
+        stride: &[usize],
+        pool_dims: &[usize],
+    ) -> Result<usize, CircuitError> {
+        let dilation = vec![1; pool_dims.len()];
+        let slides = window_output_dims(&image[2..], pool_dims, padding, stride, &dilation)?;
+        let num_windows = image[0] * image[1] * slides.iter().product::<usize>();
+        let window_len = pool_dims.iter().product::<usize>();
+        let per_window = self.measure(window_len, 1, |config, region, values| {
+            layouts::max(config, region, &[values[0].clone()]).map(|_| ())
+        })?;
+        Ok(num_windows * per_window)
+    }
+
+    /// An elementwise `op` over `num_inputs` tensors of `len` elements each.
+    pub fn elementwise(
+        &self,
+        op: &dyn Op<Fp>,
+        num_inputs: usize,
+        len: usize,
+    ) -> Result<usize, CircuitError> {
+        self.scale_up(len, num_inputs, |config, region, values| {
+            op.layout(config, region, values).map(|_| ())
+        })
+    }
+
+    /// Measures `layout` over 1 and 2 elements and extrapolates linearly to `len`.
+    fn scale_up(
+        &self,
+        len: usize,
+        num_inputs: usize,
+        layout: impl Fn(
+            &mut BaseConfig<Fp>,
+            &mut RegionCtx<Fp>,
+            &[ValTensor<Fp>],
+        ) -> Result<(), CircuitError>,
+    ) -> Result<usize, CircuitError> {
+        if len == 0 {
+            return Ok(0);
+        }
+        let one = self.measure(1, num_inputs, &layout)?;
+        let two = self.measure(2, num_inputs, &layout)?;
+        let per_element = two.saturating_sub(one);
+        Ok(one.saturating_sub(per_element) + per_element * len)
+    }
+
+    /// The cells `layout` takes up on `num_inputs` unknown tensors of `len` elements.
+    fn measure(
+        &self,
+        len: usize,
+        num_inputs: usize,
+        layout: impl Fn(
+            &mut BaseConfig<Fp>,
+            &mut RegionCtx<Fp>,
+            &[ValTensor<Fp>],
+        ) -> Result<(), CircuitError>,
+    ) -> Result<usize, CircuitError> {
+        let mut config = BaseConfig::dummy(MEASURE_LOGROWS, self.num_inner_cols);
+        let mut region = RegionCtx::new_dummy(0, self.num_inner_cols, self.settings.clone());
+        let values = (0..num_inputs)
+            .map(|_| Tensor::new(Some(&vec![Value::<Fp>::unknown(); len]), &[len]))
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter()
+            .map(ValTensor::from)
+            .collect::<Vec<_>>();
+        layout(&mut config, &mut region, &values)?;
+        Ok(region.linear_coord())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuit::ops::{chip::AvgPoolMode, hybrid::HybridOp, poly::PolyOp};
+    use crate::fieldutils::IntegerRep;
+
+    /// Nonzero values, so the only zeros the layouts skip are padding.
+    fn values(dims: &[usize]) -> ValTensor<Fp> {
+        let len = dims.iter().product::<usize>();
+        let data = (0..len)
+            .map(|i| (i % 7) as IntegerRep + 1)
+            .collect::<Vec<_>>();
+        ValTensor::from_integer_rep_tensor(Tensor::new(Some(&data), dims).unwrap())
+    }
+
+    /// The cells `op` actually takes up on fresh inputs of `dims`.
+    fn actual(op: &dyn Op<Fp>, dims: &[Vec<usize>], num_inner_cols: usize) -> usize {
+        let mut config = BaseConfig::dummy(MEASURE_LOGROWS, num_inner_cols);
+        let mut region =
+            RegionCtx::new_dummy(0, num_inner_cols, RegionSettings::all_true(16384, 2));
+        let inputs = dims.iter().map(|d| values(d)).collect::<Vec<_>>();
+        op.layout(&mut config, &mut region, &inputs).unwrap();
+        region.linear_coord()
+    }
+
+    #[test]
+    fn conv_estimates_match_layout() {
+        // image, kernel, padding, stride, group
+        let shapes: Vec<(
+            Vec<usize>,
+            Vec<usize>,
+            Vec<(isize, isize)>,
+            Vec<usize>,
+            usize,
+        )> = vec![
+            (
+                vec![1, 2, 6, 6],
+                vec![3, 2, 3, 3],
+                vec![(1, 1); 2],
+                vec![1, 1],
+                1,
+            ),
+            (
+                vec![1, 2, 6, 6],
+                vec![3, 2, 3, 3],
+                vec![(1, 1); 2],
+                vec![2, 2],
+                1,
+            ),
+            (
+                vec![1, 4, 7, 5],
+                vec![4, 2, 3, 2],
+                vec![(0, 1), (2, 0)],
+                vec![2, 1],
+                2,
+            ),
+            (
+                vec![2, 1, 5, 5],
+                vec![2, 1, 2, 2],
+                vec![(0, 0); 2],
+                vec![3, 3],
+                1,
+            ),
+            (
+                vec![2, 5, 5],
+                vec![2, 2, 2, 2],
+                vec![(-1, 0), (1, -1)],
+                vec![1, 2],
+                1,
+            ),
+        ];
+        for (image, kernel, padding, stride, group) in shapes {
+            for num_inner_cols in [1, 2, 3] {
+                let estimator = Estimator::new(num_inner_cols, 16384, 2);
+                for (strategy, has_bias) in [ConvStrategy::Direct, ConvStrategy::Im2col]
+                    .into_iter()
+                    .cartesian_product([false, true])
+                {
+                    let op = PolyOp::Conv {
+                        padding: padding.clone(),
+                        stride: stride.clone(),
+                        group,
+                        channels_last: false,
+                        strategy,
+                    };
+                    let mut dims = vec![image.clone(), kernel.clone()];
+                    if has_bias {
+                        dims.push(vec![kernel[0]]);
+                    }
+                    let estimate = estimator
+                        .conv(
+                            &image, &kernel, &padding, &stride, group, has_bias, strategy,
+                        )
+                        .unwrap();
+                    assert_eq!(
+                        estimate,
+                        actual(&op, &dims, num_inner_cols),
+                        "{:?} * {:?} padded by {:?}, stride {:?}, {} cols, {}, bias {}",
+                        image,
+                        kernel,
+                        padding,
+                        stride,
+                        num_inner_cols,
+                        strategy,
+                        has_bias
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn pool_estimates_match_layout() {
+        // image, padding, stride, window
+        let shapes: Vec<(Vec<usize>, Vec<(usize, usize)>, Vec<usize>, Vec<usize>)> = vec![
+            (vec![1, 1, 4, 4], vec![(0, 0); 2], vec![2, 2], vec![2, 2]),
+            (vec![1, 2, 5, 5], vec![(1, 1); 2], vec![2, 2], vec![3, 3]),
+            (
+                vec![2, 1, 6, 4],
+                vec![(0, 1), (1, 0)],
+                vec![3, 1],
+                vec![2, 2],
+            ),
+        ];
+        for (image, padding, stride, window) in shapes {
+            for num_inner_cols in [1, 2] {
+                let estimator = Estimator::new(num_inner_cols, 16384, 2);
+                let sumpool = HybridOp::SumPool {
+                    padding: padding.clone(),
+                    stride: stride.clone(),
+                    kernel_shape: window.clone(),
+                    normalized: false,
+                    channels_last: false,
+                    avgpool_mode: AvgPoolMode::Exact,
+                };
+                assert_eq!(
+                    estimator
+                        .sumpool(&image, &padding, &stride, &window, false)
+                        .unwrap(),
+                    actual(&sumpool, &[image.clone()], num_inner_cols),
+                    "sumpool {:?} padded by {:?}, stride {:?}",
+                    image,
+                    padding,
+                    stride
+                );
+
+                // gadgets built on decompositions flush rows depending on where they start, so
+                // their measured costs only hold up to the columns left over on each row
+                let max_pool = HybridOp::MaxPool {
+                    padding: padding.clone(),
+                    stride: stride.clone(),
+                    pool_dims: window.clone(),
+                    channels_last: false,
+                };
+                let (estimate, actual) = (
+                    estimator
+                        .max_pool(&image, &padding, &stride, &window)
+                        .unwrap(),
+                    actual(&max_pool, &[image.clone()], num_inner_cols),
+                );
+                assert!(
+                    estimate.abs_diff(actual) * 5 <= actual,
+                    "max pool {:?} padded by {:?}, stride {:?}: estimated {}, laid out {}",
+                    image,
+                    padding,
+                    stride,
+                    estimate,
+                    actual
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn affine_and_eltwise_estimates_match_layout() {
+        for num_inner_cols in [1, 2, 5] {
+            let estimator = Estimator::new(num_inner_cols, 16384, 2);
+            // inputs, equation, output length, bias length
+            for (dims, equation, out_len, bias) in [
+                (vec![vec![3, 7], vec![7, 4]], "ij,jk->ik", 12, 4),
+                (vec![vec![1, 10], vec![10, 2]], "ij,jk->ik", 2, 2),
+                (vec![vec![2, 3, 5], vec![2, 5, 2]], "bij,bjk->bik", 12, 2),
+            ] {
+                let affine = PolyOp::Affine {
+                    equation: equation.to_string(),
+                };
+                let mut inputs = dims.clone();
+                inputs.push(vec![bias]);
+                assert_eq!(
+                    estimator.einsum(&dims, equation).unwrap() + estimator.pairwise(out_len),
+                    actual(&affine, &inputs, num_inner_cols),
+                    "{} over {:?} with {} cols",
+                    equation,
+                    dims,
+                    num_inner_cols
+                );
+            }
+
+            // a per-channel scale over a [channels, height, width] activation
+            let (activation, scale) = (vec![3, 4, 5], vec![3, 1, 1]);
+            for op in [PolyOp::Add, PolyOp::Sub, PolyOp::Mult] {
+                assert_eq!(
+                    estimator.pairwise(60),
+                    actual(&op, &[activation.clone(), scale.clone()], num_inner_cols),
+                    "{} by a per-channel operand with {} cols",
+                    op.as_string(),
+                    num_inner_cols
+                );
+            }
+
+            // a [7, 4] operand with only every third entry nonzero, and a feature with none
+            let weights = (0..28)
+                .map(|i| Fp::from((i % 3 == 0 && i % 4 != 3) as u64))
+                .collect::<Vec<_>>();
+            let weights = Tensor::new(Some(&weights), &[7, 4]).unwrap();
+            let pattern = SparsityPattern::new(&weights, 0).unwrap();
+            assert!(pattern.row(3).is_empty());
+            let sparse = PolyOp::SparseMatMul {
+                equation: "ij,jk->ik".to_string(),
+                pattern: pattern.clone(),
+            };
+            assert_eq!(
+                estimator.sparse_matmul(12, &pattern),
+                actual(&sparse, &[vec![3, 7], vec![7, 4]], num_inner_cols),
+                "sparse matmul with {} cols",
+                num_inner_cols
+            );
+
+            for len in [1, 6, 11] {
+                assert_eq!(
+                    estimator.pairwise(len),
+                    actual(&PolyOp::Add, &[vec![len], vec![len]], num_inner_cols)
+                );
+                assert_eq!(
+                    estimator.elementwise(&PolyOp::Mult, 2, len).unwrap(),
+                    actual(&PolyOp::Mult, &[vec![len], vec![len]], num_inner_cols)
+                );
+            }
+        }
+    }
+}
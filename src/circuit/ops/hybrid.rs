@@ -1,7 +1,7 @@
 use super::*;
 use crate::{
-    circuit::{layouts, utils, Tolerance},
-    fieldutils::integer_rep_to_felt,
+    circuit::{layouts, lookup::Reciprocal, utils, AvgPoolMode, Tolerance},
+    fieldutils::{integer_rep_to_felt, IntegerRep},
     graph::multiplier_to_scale,
     tensor::{self, Tensor, TensorType, ValTensor},
 };
@@ -33,6 +33,7 @@ pub enum HybridOp {
         input_scale: utils::F32,
         output_scale: utils::F32,
     },
+    RefinedRecip(DivConfig),
     Div {
         denom: utils::F32,
         use_range_check_for_int: bool,
@@ -48,11 +49,14 @@ pub enum HybridOp {
         stride: Vec<usize>,
         kernel_shape: Vec<usize>,
         normalized: bool,
+        channels_last: bool,
+        avgpool_mode: AvgPoolMode,
     },
     MaxPool {
         padding: Vec<(usize, usize)>,
         stride: Vec<usize>,
         pool_dims: Vec<usize>,
+        channels_last: bool,
     },
     ReduceMin {
         axes: Vec<usize>,
@@ -66,6 +70,11 @@ pub enum HybridOp {
         input_scale: utils::F32,
         output_scale: utils::F32,
         axes: Vec<usize>,
+        #[serde(default)]
+        refinements: usize,
+        /// whether the input is masked, see [crate::circuit::ops::layouts::softmax]
+        #[serde(default)]
+        masked: bool,
     },
     RangeCheck(Tolerance),
     Greater,
@@ -86,6 +95,74 @@ pub enum HybridOp {
         dim: usize,
         num_classes: usize,
     },
+    Threshold {
+        class: usize,
+        threshold: IntegerRep,
+    },
+}
+
+/// Division by activations, as the reciprocal of the denominators refined by Newton–Raphson steps
+/// from a lookup table seed, see `layouts::refined_recip`.
+///
+/// Denominators `b` come in at `input_scale`, standing for `β = b / input_scale`, and their
+/// reciprocals go out at `output_scale`. The seed is read from a [LookupOp::Recip] table indexed by
+/// `b' = round(b / seed_divisor)`, so that the table spans `seed_divisor` times fewer inputs than
+/// the denominators do, at the cost of a seed off by a relative error of up to `1 / (2|b'|)`. Each
+/// refinement squares the relative error of the last, then rounds to `output_scale`, so one or two
+/// of them recover the precision the table gave up: see [DivConfig::error_bound].
+///
+/// Denominators that round to `b' = 0`, i.e. `|b| < seed_divisor / 2`, divide to 0.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DivConfig {
+    /// The scale of the denominators
+    pub input_scale: utils::F32,
+    /// The scale of the reciprocals
+    pub output_scale: utils::F32,
+    /// What denominators are divided by, and rounded, to index the seed table
+    pub seed_divisor: IntegerRep,
+    /// The number of Newton–Raphson steps refining the seed
+    pub refinements: usize,
+}
+
+impl DivConfig {
+    /// Seeds from a table indexed by the denominators rounded to the upper half of the `n` bits of
+    /// `input_scale`. The seeds of denominators of at least 1 in magnitude are then off by a
+    /// relative error of about `2^-(n / 2 + 1)`, and a single refinement brings their reciprocals
+    /// to within about a unit of exact at an `output_scale` no finer than `input_scale`.
+    pub fn new(input_scale: utils::F32, output_scale: utils::F32, refinements: usize) -> Self {
+        let bits = multiplier_to_scale(input_scale.0 as f64).max(0);
+        Self {
+            input_scale,
+            output_scale,
+            seed_divisor: 1 << (bits / 2),
+            refinements,
+        }
+    }
+
+    /// The seed table, indexed by the denominators divided by `seed_divisor`.
+    pub fn seed(&self) -> Reciprocal {
+        Reciprocal {
+            input_scale: (self.input_scale.0 / self.seed_divisor as f32).into(),
+            output_scale: self.output_scale,
+        }
+    }
+
+    /// Bounds how far the reciprocal laid out for the denominator `denom` is from the exact
+    /// `output_scale * input_scale / denom`, in units of `output_scale`.
+    ///
+    /// With the seed off by a relative error of `e_0 <= 1 / (2|b'|) + 1 / (2|r|)`, for `r` the
+    /// exact reciprocal, each refinement is off by `e_{k+1} <= e_k^2 + 1 / |r|`: its products are
+    /// exact and its rescaling rounds to within a unit. The bound is `|r| * e_refinements`.
+    pub fn error_bound(&self, denom: IntegerRep) -> f64 {
+        let exact = (self.output_scale.0 as f64 * self.input_scale.0 as f64 / denom as f64).abs();
+        let coarse = (denom as f64 / self.seed_divisor as f64).round().abs();
+        if coarse == 0.0 {
+            return exact;
+        }
+        let seed_error = 1.0 / (2.0 * coarse) + 1.0 / (2.0 * exact);
+        let error = (0..self.refinements).fold(seed_error, |e, _| e * e + 1.0 / exact);
+        exact * error
+    }
 }
 
 impl<F: PrimeField + TensorType + PartialOrd + std::hash::Hash> Op<F> for HybridOp {
@@ -128,6 +205,10 @@ impl<F: PrimeField + TensorType + PartialOrd + std::hash::Hash> Op<F> for Hybrid
                 "RECIP (input_scale={}, output_scale={})",
                 input_scale, output_scale
             ),
+            HybridOp::RefinedRecip(div) => format!(
+                "REFINED_RECIP (input_scale={}, output_scale={}, seed_divisor={}, refinements={})",
+                div.input_scale, div.output_scale, div.seed_divisor, div.refinements
+            ),
             HybridOp::Div {
                 denom,
                 use_range_check_for_int,
@@ -140,9 +221,11 @@ impl<F: PrimeField + TensorType + PartialOrd + std::hash::Hash> Op<F> for Hybrid
                 stride,
                 kernel_shape,
                 normalized,
+                channels_last,
+                avgpool_mode,
             } => format!(
-                "SUMPOOL (padding={:?}, stride={:?}, kernel_shape={:?}, normalized={})",
-                padding, stride, kernel_shape, normalized
+                "SUMPOOL (padding={:?}, stride={:?}, kernel_shape={:?}, normalized={}, channels_last={}, avgpool_mode={})",
+                padding, stride, kernel_shape, normalized, channels_last, avgpool_mode
             ),
             HybridOp::ReduceMax { axes } => format!("REDUCEMAX (axes={:?})", axes),
             HybridOp::ReduceArgMax { dim } => format!("REDUCEARGMAX (dim={})", dim),
@@ -150,9 +233,10 @@ impl<F: PrimeField + TensorType + PartialOrd + std::hash::Hash> Op<F> for Hybrid
                 padding,
                 stride,
                 pool_dims,
+                channels_last,
             } => format!(
-                "MaxPool (padding={:?}, stride={:?}, pool_dims={:?})",
-                padding, stride, pool_dims
+                "MaxPool (padding={:?}, stride={:?}, pool_dims={:?}, channels_last={})",
+                padding, stride, pool_dims, channels_last
             ),
             HybridOp::ReduceMin { axes } => format!("REDUCEMIN (axes={:?})", axes),
             HybridOp::ReduceArgMin { dim } => format!("REDUCEARGMIN (dim={})", dim),
@@ -160,10 +244,12 @@ impl<F: PrimeField + TensorType + PartialOrd + std::hash::Hash> Op<F> for Hybrid
                 input_scale,
                 output_scale,
                 axes,
+                refinements,
+                masked,
             } => {
                 format!(
-                    "SOFTMAX (input_scale={}, output_scale={}, axes={:?})",
-                    input_scale, output_scale, axes
+                    "SOFTMAX (input_scale={}, output_scale={}, axes={:?}, refinements={}, masked={})",
+                    input_scale, output_scale, axes, refinements, masked
                 )
             }
             HybridOp::RangeCheck(p) => format!("RANGECHECK (tol={:?})", p),
@@ -179,6 +265,9 @@ impl<F: PrimeField + TensorType + PartialOrd + std::hash::Hash> Op<F> for Hybrid
             HybridOp::OneHot { dim, num_classes } => {
                 format!("ONEHOT (dim={}, num_classes={})", dim, num_classes)
             }
+            HybridOp::Threshold { class, threshold } => {
+                format!("THRESHOLD (class={}, threshold={})", class, threshold)
+            }
         }
     }
 
@@ -208,15 +297,28 @@ impl<F: PrimeField + TensorType + PartialOrd + std::hash::Hash> Op<F> for Hybrid
                 stride,
                 kernel_shape,
                 normalized,
-            } => layouts::sumpool(
-                config,
-                region,
-                values[..].try_into()?,
-                padding,
-                stride,
-                kernel_shape,
-                *normalized,
-            )?,
+                channels_last,
+                avgpool_mode,
+            } => {
+                // approximate normalization happens in the output scale, the sum is left as is
+                let divide = *normalized && *avgpool_mode == AvgPoolMode::Exact;
+                let sumpool = |region: &mut RegionCtx<F>, values: &[ValTensor<F>]| {
+                    layouts::sumpool(
+                        config,
+                        region,
+                        values,
+                        padding,
+                        stride,
+                        kernel_shape,
+                        divide,
+                    )
+                };
+                if *channels_last {
+                    layouts::channels_last(config, region, values, stride.len(), sumpool)?
+                } else {
+                    sumpool(region, values)?
+                }
+            }
             HybridOp::Recip {
                 input_scale,
                 output_scale,
@@ -227,6 +329,9 @@ impl<F: PrimeField + TensorType + PartialOrd + std::hash::Hash> Op<F> for Hybrid
                 integer_rep_to_felt(input_scale.0 as i128),
                 integer_rep_to_felt(output_scale.0 as i128),
             )?,
+            HybridOp::RefinedRecip(div) => {
+                layouts::refined_recip(config, region, values[..].try_into()?, div)?
+            }
             HybridOp::Div {
                 denom,
                 use_range_check_for_int,
@@ -260,14 +365,26 @@ impl<F: PrimeField + TensorType + PartialOrd + std::hash::Hash> Op<F> for Hybrid
                 padding,
                 stride,
                 pool_dims,
-            } => layouts::max_pool(
-                config,
-                region,
-                values[..].try_into()?,
-                padding,
-                stride,
-                pool_dims,
-            )?,
+                channels_last,
+            } => {
+                let max_pool = |region: &mut RegionCtx<F>,
+                                values: &[ValTensor<F>]|
+                 -> Result<ValTensor<F>, CircuitError> {
+                    layouts::max_pool(
+                        config,
+                        region,
+                        values.try_into()?,
+                        padding,
+                        stride,
+                        pool_dims,
+                    )
+                };
+                if *channels_last {
+                    layouts::channels_last(config, region, values, stride.len(), max_pool)?
+                } else {
+                    max_pool(region, values)?
+                }
+            }
             HybridOp::ReduceMax { axes } => {
                 layouts::max_axes(config, region, values[..].try_into()?, axes)?
             }
@@ -284,6 +401,8 @@ impl<F: PrimeField + TensorType + PartialOrd + std::hash::Hash> Op<F> for Hybrid
                 input_scale,
                 output_scale,
                 axes,
+                refinements,
+                masked,
             } => layouts::softmax_axes(
                 config,
                 region,
@@ -291,6 +410,8 @@ impl<F: PrimeField + TensorType + PartialOrd + std::hash::Hash> Op<F> for Hybrid
                 *input_scale,
                 *output_scale,
                 axes,
+                *refinements,
+                *masked,
             )?,
             HybridOp::RangeCheck(tol) => layouts::range_check_percent(
                 config,
@@ -312,6 +433,9 @@ impl<F: PrimeField + TensorType + PartialOrd + std::hash::Hash> Op<F> for Hybrid
             HybridOp::OneHot { dim, num_classes } => {
                 layouts::one_hot_axis(config, region, values[..].try_into()?, *num_classes, *dim)?
             }
+            HybridOp::Threshold { class, threshold } => {
+                layouts::threshold(config, region, values[..].try_into()?, *class, *threshold)?
+            }
         }))
     }
 
@@ -323,10 +447,18 @@ impl<F: PrimeField + TensorType + PartialOrd + std::hash::Hash> Op<F> for Hybrid
             | HybridOp::LessEqual { .. }
             | HybridOp::ReduceArgMax { .. }
             | HybridOp::OneHot { .. }
+            | HybridOp::Threshold { .. }
             | HybridOp::ReduceArgMin { .. } => 0,
             HybridOp::Softmax { output_scale, .. } | HybridOp::Recip { output_scale, .. } => {
                 multiplier_to_scale(output_scale.0 as f64)
             }
+            HybridOp::RefinedRecip(div) => multiplier_to_scale(div.output_scale.0 as f64),
+            HybridOp::SumPool {
+                kernel_shape,
+                normalized: true,
+                avgpool_mode,
+                ..
+            } => in_scales[0] + avgpool_mode.scale_shift(kernel_shape.iter().product()),
             _ => in_scales[0],
         };
         Ok(scale)
@@ -15,7 +15,10 @@ use maybe_rayon::{
 
 use self::tensor::{create_constant_tensor, create_zero_tensor};
 
-use super::{chip::BaseConfig, region::RegionCtx};
+use super::{
+    chip::{BaseConfig, UNBOUNDED_RANGE},
+    region::RegionCtx,
+};
 use crate::{
     circuit::{ops::base::BaseOp, utils},
     fieldutils::{felt_to_integer_rep, integer_rep_to_felt, IntegerRep},
@@ -28,6 +31,7 @@ use crate::{
 
 use super::*;
 use crate::circuit::ops::lookup::LookupOp;
+use crate::circuit::ops::poly::SparsityPattern;
 
 /// Same as div but splits the division into N parts
 pub(crate) fn loop_div<F: PrimeField + TensorType + PartialOrd + std::hash::Hash>(
@@ -227,7 +231,59 @@ pub(crate) fn recip<F: PrimeField + TensorType + PartialOrd + std::hash::Hash>(
     Ok(claimed_output)
 }
 
+/// Reciprocal of a tensor of denominators, seeded from a lookup table and refined by
+/// Newton–Raphson steps, see [hybrid::DivConfig] for the scales and error bounds.
+///
+/// Each step takes the estimate `y` of `1 / b` to `y * (2 - b * y)`, which squares its relative
+/// error. At the scales the values are laid out at, `b * y` is at `input_scale * output_scale`
+/// and the refined estimate is rescaled back to `output_scale` in two divisions, by `input_scale`
+/// then by `output_scale`, so that neither needs a range check wider than its own scale.
+pub(crate) fn refined_recip<F: PrimeField + TensorType + PartialOrd + std::hash::Hash>(
+    config: &BaseConfig<F>,
+    region: &mut RegionCtx<F>,
+    value: &[ValTensor<F>; 1],
+    div: &hybrid::DivConfig,
+) -> Result<ValTensor<F>, CircuitError> {
+    let denom = value[0].clone();
+
+    // the seed table is indexed by the denominators at a coarser scale
+    let coarse = loop_div(
+        config,
+        region,
+        &[denom.clone()],
+        integer_rep_to_felt(div.seed_divisor),
+    )?;
+    let mut recip = nonlinearity(config, region, &[coarse], &LookupOp::Recip(div.seed()))?;
+
+    let input_scale = integer_rep_to_felt(div.input_scale.0 as IntegerRep);
+    let output_scale = integer_rep_to_felt(div.output_scale.0 as IntegerRep);
+    // 2 at the scale of b * y
+    let two = create_constant_tensor(F::from(2) * input_scale * output_scale, 1);
+
+    for _ in 0..div.refinements {
+        let product = pairwise(
+            config,
+            region,
+            &[denom.clone(), recip.clone()],
+            BaseOp::Mult,
+        )?;
+        let correction = pairwise(config, region, &[two.clone(), product], BaseOp::Sub)?;
+        let refined = pairwise(config, region, &[recip, correction], BaseOp::Mult)?;
+        let refined = loop_div(config, region, &[refined], input_scale)?;
+        recip = loop_div(config, region, &[refined], output_scale)?;
+    }
+
+    Ok(recip)
+}
+
 /// Dot product of two tensors.
+///
+/// This is the only multiply-accumulate layout: einsum (and so affine and matmul), sparse matmuls
+/// and decompositions all reduce onto it. Convs do too, as one `"i,i->"` einsum per output element
+/// under the default [super::chip::ConvStrategy::Direct], or as a single einsum over the unfolded
+/// image patches under [super::chip::ConvStrategy::Im2col]. It runs over the `DotInit` / `Dot`
+/// running accumulator gates, which [BaseConfig] configures once per column set, for operand
+/// slices of any length.
 /// ```
 /// use ezkl::tensor::Tensor;
 /// use ezkl::fieldutils::IntegerRep;
@@ -756,6 +812,128 @@ pub fn einsum<F: PrimeField + TensorType + PartialOrd + std::hash::Hash>(
     Ok(output)
 }
 
+/// Multiplies an input by a 2D operand as in `equation` (see [SparsityPattern::contracted_axis]),
+/// laying out only the products with the operand entries recorded in `pattern`. Every other entry
+/// of the operand is assumed to be zero.
+/// ```
+/// use ezkl::tensor::Tensor;
+/// use ezkl::fieldutils::IntegerRep;
+/// use ezkl::circuit::ops::layouts::sparse_matmul;
+/// use ezkl::circuit::ops::poly::SparsityPattern;
+/// use halo2curves::bn256::Fr as Fp;
+/// use ezkl::circuit::region::RegionCtx;
+/// use ezkl::circuit::region::RegionSettings;
+/// use ezkl::circuit::BaseConfig;
+/// use ezkl::tensor::ValTensor;
+///
+/// let dummy_config = BaseConfig::dummy(12, 2);
+/// let mut dummy_region = RegionCtx::new_dummy(0,2,RegionSettings::all_true(128,2));
+///
+/// let x = ValTensor::from_integer_rep_tensor(Tensor::<IntegerRep>::new(
+///    Some(&[2, 1, 2, 1, 1, 1]),
+///  &[2, 3],
+/// ).unwrap());
+/// let w = Tensor::<IntegerRep>::new(Some(&[2, 0, 0, 1, 0, 0]), &[3, 2]).unwrap();
+/// let pattern = SparsityPattern::new(&w.map(ezkl::fieldutils::integer_rep_to_felt::<Fp>), 0).unwrap();
+/// let w = ValTensor::from_integer_rep_tensor(w);
+/// let result = sparse_matmul::<Fp>(&dummy_config, &mut dummy_region, &[x, w], "ij,jk->ik", &pattern).unwrap();
+/// let expected = Tensor::<IntegerRep>::new(Some(&[4, 1, 2, 1]), &[2, 2]).unwrap();
+/// assert_eq!(result.int_evals().unwrap(), expected);
+/// ```
+pub fn sparse_matmul<F: PrimeField + TensorType + PartialOrd + std::hash::Hash>(
+    config: &BaseConfig<F>,
+    region: &mut RegionCtx<F>,
+    values: &[ValTensor<F>; 2],
+    equation: &str,
+    pattern: &SparsityPattern,
+) -> Result<ValTensor<F>, CircuitError> {
+    let contracted_axis =
+        SparsityPattern::contracted_axis(equation).ok_or(CircuitError::InvalidEinsum)?;
+    let (inputs_eq, output_eq) = equation
+        .split_once("->")
+        .ok_or(CircuitError::InvalidEinsum)?;
+    let (input_eq, weights_eq) = inputs_eq
+        .split_once(',')
+        .ok_or(CircuitError::InvalidEinsum)?;
+    let weights_eq = weights_eq.chars().collect::<Vec<_>>();
+    let feature_axis = output_eq
+        .find(weights_eq[1 - contracted_axis])
+        .ok_or(CircuitError::InvalidEinsum)?;
+
+    let [input, weights] = values;
+    let weights_dims = weights.dims();
+    if input.dims().len() != input_eq.len()
+        || weights_dims.len() != 2
+        || pattern.num_rows() != weights_dims[1 - contracted_axis]
+        || pattern.num_cols != weights_dims[contracted_axis]
+    {
+        return Err(TensorError::DimMismatch("sparse matmul".to_string()).into());
+    }
+    let contracted_len = input_eq
+        .find(weights_eq[contracted_axis])
+        .map(|i| input.dims()[i]);
+    if contracted_len != Some(pattern.num_cols) {
+        return Err(TensorError::DimMismatch("sparse matmul".to_string()).into());
+    }
+
+    let output_shape = output_eq
+        .chars()
+        .map(|c| match input_eq.find(c) {
+            Some(i) => input.dims()[i],
+            None => pattern.num_rows(),
+        })
+        .collect::<Vec<_>>();
+    let mut output: Tensor<ValType<F>> = Tensor::new(None, &output_shape)?;
+
+    let cartesian_coord = output_shape
+        .iter()
+        .map(|d| 0..*d)
+        .multi_cartesian_product()
+        .collect::<Vec<_>>();
+
+    let weights = weights.get_inner_tensor()?;
+
+    let inner_loop_function = |i: usize, region: &mut RegionCtx<'_, F>| {
+        let coord = &cartesian_coord[i];
+        let row = coord[feature_axis];
+        let cols = pattern.row(row);
+        if cols.is_empty() {
+            return Ok(ValType::Constant(F::ZERO));
+        }
+
+        // the input entries feeding this output, along the contracted axis
+        let slice = input_eq
+            .chars()
+            .enumerate()
+            .map(|(j, c)| match output_eq.find(c) {
+                Some(o) => coord[o]..coord[o] + 1,
+                None => 0..input.dims()[j],
+            })
+            .collect::<Vec<_>>();
+        let input_row = input.get_slice(&slice)?;
+        let input_row = input_row.get_inner_tensor()?;
+
+        let xs: Tensor<ValType<F>> = cols.iter().map(|c| input_row[*c].clone()).collect();
+        let ws: Tensor<ValType<F>> = cols
+            .iter()
+            .map(|c| {
+                if contracted_axis == 0 {
+                    weights.get(&[*c, row])
+                } else {
+                    weights.get(&[row, *c])
+                }
+            })
+            .collect();
+
+        Ok(dot(config, region, &[xs.into(), ws.into()])?.get_inner_tensor()?[0].clone())
+    };
+
+    region.flush()?;
+    region.apply_in_loop(&mut output, inner_loop_function)?;
+
+    Ok(output.into())
+}
+
 fn _sort_ascending<F: PrimeField + TensorType + PartialOrd + std::hash::Hash>(
     config: &BaseConfig<F>,
     region: &mut RegionCtx<F>,
@@ -950,6 +1128,80 @@ fn one_hot<F: PrimeField + TensorType + PartialOrd + std::hash::Hash>(
     Ok(assigned_output)
 }
 
+/// Constrains a flat group of cells to a one-hot vector at a fixed point `scale`: every cell is
+/// 0 or `scale` and the cells sum to `scale`. Returns the group's 0/1 entries.
+pub(crate) fn one_hot_group<F: PrimeField + TensorType + PartialOrd + std::hash::Hash>(
+    config: &BaseConfig<F>,
+    region: &mut RegionCtx<F>,
+    values: &[ValTensor<F>; 1],
+    scale: F,
+) -> Result<ValTensor<F>, CircuitError> {
+    let input = values[0].clone();
+    let is_assigned = !input.any_unknowns()?;
+
+    let entries: ValTensor<F> = if is_assigned {
+        let multiplier = felt_to_integer_rep(scale);
+        input
+            .int_evals()?
+            .par_iter()
+            .map(|x| Value::known(integer_rep_to_felt(*x / multiplier)))
+            .collect::<Tensor<_>>()
+    } else {
+        Tensor::new(
+            Some(&vec![Value::<F>::unknown(); input.len()]),
+            &[input.len()],
+        )?
+    }
+    .into();
+
+    // now assert all entries are 0 or 1
+    let entries = boolean_identity(config, region, &[entries], true)?;
+
+    // and that they are the input once rescaled
+    let rescaled = if scale == F::ONE {
+        entries.clone()
+    } else {
+        let multiplier = create_constant_tensor(scale, 1);
+        let multiplier = region.assign(&config.custom_gates.inputs[1], &multiplier)?;
+        region.increment(multiplier.len());
+        pairwise(config, region, &[entries.clone(), multiplier], BaseOp::Mult)?
+    };
+    enforce_equality(config, region, &[input, rescaled])?;
+
+    let sum = sum(config, region, &[entries.clone()])?;
+    // assert sum is 1
+    enforce_equality(config, region, &[create_unit_tensor(1), sum])?;
+
+    Ok(entries)
+}
+
+/// Constrains the hashed pairs of a Merkle path: at each level the path's node is the left of
+/// the pair when the level's bit is 0 and its right when it is 1. The bits are constrained to be
+/// boolean. Returns the index they compose, the leaf's level being the least significant bit.
+pub(crate) fn merkle_path<F: PrimeField + TensorType + PartialOrd + std::hash::Hash>(
+    config: &BaseConfig<F>,
+    region: &mut RegionCtx<F>,
+    values: &[ValTensor<F>; 4],
+) -> Result<ValTensor<F>, CircuitError> {
+    let [nodes, lefts, rights, bits] = values;
+    let bits = boolean_identity(config, region, &[bits.clone()], true)?;
+
+    // node = left + bit * (right - left)
+    let diff = pairwise(
+        config,
+        region,
+        &[rights.clone(), lefts.clone()],
+        BaseOp::Sub,
+    )?;
+    let offset = pairwise(config, region, &[bits.clone(), diff], BaseOp::Mult)?;
+    let picked = pairwise(config, region, &[lefts.clone(), offset], BaseOp::Add)?;
+    enforce_equality(config, region, &[nodes.clone(), picked])?;
+
+    let mut powers = Tensor::from((0..bits.len()).map(|i| ValType::Constant(F::from(1u64 << i))));
+    powers.set_visibility(&crate::graph::Visibility::Fixed);
+    dot(config, region, &[bits, powers.into()])
+}
+
 /// Dynamic lookup
 pub(crate) fn dynamic_lookup<F: PrimeField + TensorType + PartialOrd + std::hash::Hash>(
     config: &BaseConfig<F>,
@@ -2290,7 +2542,10 @@ pub(crate) fn pairwise<F: PrimeField + TensorType + PartialOrd + std::hash::Hash
     let (mut lhs, mut rhs) = (values[0].clone(), values[1].clone());
 
     let broadcasted_shape = get_broadcasted_shape(lhs.dims(), rhs.dims())?;
-
+    // an operand broadcast over the other is expanded in place. a constant one (such as a folded
+    // per-channel scale or bias) still takes only one fixed cell per distinct value, as constants
+    // are deduplicated when assigned, so its copies across the broadcast extent all point back to
+    // those cells.
     lhs.expand(&broadcasted_shape)?;
     rhs.expand(&broadcasted_shape)?;
 
@@ -2584,6 +2839,36 @@ pub fn greater_equal<F: PrimeField + TensorType + PartialOrd + std::hash::Hash>(
     greater(config, region, &[lhs_plus_one, rhs])
 }
 
+/// Constrains element `class` of the (flattened) input to be strictly greater than `threshold`.
+/// Returns the comparison bit, which a valid proof forces to be 1.
+/// # Arguments
+/// * `a` - Tensor
+/// * `class` - index of the element to compare
+/// * `threshold` - the quantized threshold
+pub(crate) fn threshold<F: PrimeField + TensorType + PartialOrd + std::hash::Hash>(
+    config: &BaseConfig<F>,
+    region: &mut RegionCtx<F>,
+    values: &[ValTensor<F>; 1],
+    class: usize,
+    threshold: IntegerRep,
+) -> Result<ValTensor<F>, CircuitError> {
+    let mut input = values[0].clone();
+    input.flatten();
+    if class >= input.len() {
+        return Err(CircuitError::DimMismatch(format!(
+            "threshold class {} for output of length {}",
+            class,
+            input.len()
+        )));
+    }
+    input.slice(&0, &class, &(class + 1))?;
+
+    let threshold = create_constant_tensor(integer_rep_to_felt(threshold), 1);
+    let is_greater = greater(config, region, &[input, threshold])?;
+
+    enforce_equality(config, region, &[is_greater, create_unit_tensor(1)])
+}
+
 /// Less than to operation.
 /// # Arguments
 /// * `a` - Tensor
@@ -3083,13 +3368,24 @@ pub fn sumpool<F: PrimeField + TensorType + PartialOrd + std::hash::Hash>(
         .collect::<Vec<_>>();
 
     let mut res = vec![];
+    let padding = padding
+        .iter()
+        .map(|(before, after)| (*before as isize, *after as isize))
+        .collect::<Vec<_>>();
 
     cartesian_coord
         .iter()
         .map(|coord| {
             let (b, i) = (coord[0], coord[1]);
             let input = values[0].get_slice(&[b..b + 1, i..i + 1])?;
-            let output = conv(config, region, &[input, kernel.clone()], padding, stride, 1)?;
+            let output = conv(
+                config,
+                region,
+                &[input, kernel.clone()],
+                &padding,
+                stride,
+                1,
+            )?;
             res.push(output);
             Ok(())
         })
@@ -3149,19 +3445,13 @@ pub fn max_pool<F: PrimeField + TensorType + PartialOrd + std::hash::Hash>(
     let mut padded_image = image.clone();
     padded_image.pad(padding.to_vec(), 2)?;
 
-    let slides = image_dims[2..]
-        .iter()
-        .enumerate()
-        .map(|(i, d)| {
-            let d = padding[i].0 + d + padding[i].1;
-            d.checked_sub(pool_dims[i])
-                .ok_or_else(|| TensorError::Overflow("conv".to_string()))?
-                .checked_div(stride[i])
-                .ok_or_else(|| TensorError::Overflow("conv".to_string()))?
-                .checked_add(1)
-                .ok_or_else(|| TensorError::Overflow("conv".to_string()))
-        })
-        .collect::<Result<Vec<_>, TensorError>>()?;
+    let slides = estimate::window_output_dims(
+        &image_dims[2..],
+        pool_dims,
+        padding,
+        stride,
+        &vec![1; stride.len()],
+    )?;
 
     let mut output_dims = vec![batch, input_channels];
     output_dims.extend(slides);
@@ -3279,10 +3569,15 @@ pub fn max_pool<F: PrimeField + TensorType + PartialOrd + std::hash::Hash>(
 ///     Some(&[3, 1, 1, 5]),
 ///     &[1, 1, 2, 2],
 /// ).unwrap());
-/// let result = deconv::<Fp>(&dummy_config, &mut dummy_region, &[x, k], &vec![(0, 0); 2], &vec![0;2], &vec![2; 2], 1).unwrap();
+/// let result = deconv::<Fp>(&dummy_config, &mut dummy_region, &[x.clone(), k.clone()], &vec![(0, 0); 2], &vec![0;2], &vec![2; 2], 1).unwrap();
 /// let expected = Tensor::<IntegerRep>::new(Some(&[6, 2, 12, 4, 2, 10, 4, 20, 0, 0, 3, 1, 0, 0, 1, 5]), &[1, 1, 4, 4]).unwrap();
 /// assert_eq!(result.int_evals().unwrap(), expected);
 ///
+/// // output padding past the end padding appends zeros
+/// let result = deconv::<Fp>(&dummy_config, &mut dummy_region, &[x, k], &vec![(0, 0); 2], &vec![1;2], &vec![2; 2], 1).unwrap();
+/// let expected = Tensor::<IntegerRep>::new(Some(&[6, 2, 12, 4, 0, 2, 10, 4, 20, 0, 0, 0, 3, 1, 0, 0, 0, 1, 5, 0, 0, 0, 0, 0, 0]), &[1, 1, 5, 5]).unwrap();
+/// assert_eq!(result.int_evals().unwrap(), expected);
+///
 /// let x = ValTensor::from_integer_rep_tensor(Tensor::<IntegerRep>::new(
 ///     Some(&[2, 4, 0, 1]),
 ///     &[1, 1, 2, 2],
@@ -3355,6 +3650,20 @@ pub fn deconv<
         .into());
     }
 
+    // each spatial dim of the kernel needs its own stride and padding, or the dims without
+    // them would be left unexpanded
+    let num_spatial = kernel.dims().len().saturating_sub(2);
+    if [padding.len(), output_padding.len(), stride.len()] != [num_spatial; 3] {
+        return Err(TensorError::DimMismatch(format!(
+            "deconv by a kernel of shape {:?} with padding {:?}, output padding {:?} and stride {:?}",
+            kernel.dims(),
+            padding,
+            output_padding,
+            stride
+        ))
+        .into());
+    }
+
     let null_val = ValType::Constant(F::ZERO);
 
     let mut expanded_image = image.clone();
@@ -3366,10 +3675,11 @@ pub fn deconv<
     expanded_image.pad(
         kernel.dims()[2..]
             .iter()
-            .map(|d| (d - 1, d - 1))
+            .zip(output_padding)
+            .map(|(d, p)| (d - 1, d - 1 + p))
             .collect::<Vec<_>>(),
         2,
-    )?; // pad to the kernel size
+    )?; // pad to the kernel size, plus the output padding at the end
 
     // flip order
     let channel_coord = (0..kernel.dims()[0])
@@ -3382,7 +3692,7 @@ pub fn deconv<
         .enumerate()
         .map(|(i, d)| {
             if i >= 2 {
-                padding[i - 2].0..d - padding[i - 2].1 + output_padding[i - 2]
+                padding[i - 2].0..d - padding[i - 2].1
             } else {
                 0..*d
             }
@@ -3510,7 +3820,7 @@ pub fn conv<
     config: &BaseConfig<F>,
     region: &mut RegionCtx<F>,
     values: &[ValTensor<F>],
-    padding: &[(usize, usize)],
+    padding: &[(isize, isize)],
     stride: &[usize],
     num_groups: usize,
 ) -> Result<ValTensor<F>, CircuitError> {
@@ -3543,11 +3853,17 @@ pub fn conv<
         region.increment(*assigned_len.iter().max().unwrap());
     }
 
-    // if image is 3d add a dummy batch dimension
+    // if the image has no batch dimension add a dummy one
     if image.dims().len() == kernel.dims().len() - 1 {
-        image.reshape(&[1, image.dims()[0], image.dims()[1], image.dims()[2]])?;
+        let mut dims = vec![1];
+        dims.extend(image.dims());
+        image.reshape(&dims)?;
     }
 
+    // negative padding crops the image, which only selects wires and adds no constraints
+    let (kept, padding) = tensor::ops::crop_negative_padding(image.dims(), padding)?;
+    let image = image.get_slice(&kept)?;
+
     let image_dims = image.dims();
     let kernel_dims = kernel.dims();
 
@@ -3565,19 +3881,13 @@ pub fn conv<
         input_channels
     );
 
-    let slides = image_dims[2..]
-        .iter()
-        .enumerate()
-        .map(|(i, d)| {
-            let d = padding[i].0 + d + padding[i].1;
-            d.checked_sub(kernel_dims[i + 2])
-                .ok_or_else(|| TensorError::Overflow("conv".to_string()))?
-                .checked_div(stride[i])
-                .ok_or_else(|| TensorError::Overflow("conv".to_string()))?
-                .checked_add(1)
-                .ok_or_else(|| TensorError::Overflow("conv".to_string()))
-        })
-        .collect::<Result<Vec<_>, TensorError>>()?;
+    let slides = estimate::window_output_dims(
+        &image_dims[2..],
+        &kernel_dims[2..],
+        &padding,
+        stride,
+        &vec![1; stride.len()],
+    )?;
 
     log::debug!("slides: {:?}", slides);
 
@@ -3683,6 +3993,161 @@ pub fn conv<
     Ok(output)
 }
 
+/// Convolution laid out as a single matmul: the padded image is unfolded into one patch per
+/// output position and the patches are contracted against the flattened kernel with [einsum].
+/// Produces the same output as [conv], with the bias added in one pass over the whole output.
+/// # Examples
+/// ```
+/// use ezkl::tensor::Tensor;
+/// use ezkl::fieldutils::IntegerRep;
+/// use ezkl::circuit::ops::layouts::conv_im2col;
+/// use halo2curves::bn256::Fr as Fp;
+/// use ezkl::circuit::region::RegionCtx;
+/// use ezkl::circuit::region::RegionSettings;
+/// use ezkl::circuit::BaseConfig;
+/// use ezkl::tensor::ValTensor;
+///
+/// let dummy_config = BaseConfig::dummy(12, 2);
+/// let mut dummy_region = RegionCtx::new_dummy(0,2,RegionSettings::all_true(128,2));
+///
+/// let x = ValTensor::from_integer_rep_tensor(Tensor::<IntegerRep>::new(
+///     Some(&[5, 2, 3, 0, 4, -1, 3, 1, 6, 5, 2, 3, 0, 4, -1, 3, 1, 6]),
+///     &[1, 2, 3, 3],
+/// ).unwrap());
+/// let k = ValTensor::from_integer_rep_tensor(Tensor::<IntegerRep>::new(
+///     Some(&[5, 1, 1, 1, 5, 2, 1, 1]),
+///     &[2, 1, 2, 2],
+/// ).unwrap());
+/// let b = ValTensor::from_integer_rep_tensor(Tensor::<IntegerRep>::new(
+///     Some(&[1, 1]),
+///     &[2],
+/// ).unwrap());
+///
+/// let result = conv_im2col::<Fp>(&dummy_config, &mut dummy_region, &[x, k, b], &vec![(0, 0); 2], &vec![1;2], 2).unwrap();
+/// let expected =  Tensor::<IntegerRep>::new(Some(&[32, 17, 9, 27, 34, 20, 13, 26]), &[1, 2, 2, 2]).unwrap();
+/// assert_eq!(result.int_evals().unwrap(), expected);
+/// ```
+///
+pub fn conv_im2col<
+    F: PrimeField + TensorType + PartialOrd + std::hash::Hash + std::marker::Send + std::marker::Sync,
+>(
+    config: &BaseConfig<F>,
+    region: &mut RegionCtx<F>,
+    values: &[ValTensor<F>],
+    padding: &[(isize, isize)],
+    stride: &[usize],
+    num_groups: usize,
+) -> Result<ValTensor<F>, CircuitError> {
+    let has_bias = values.len() == 3;
+    let (mut image, mut kernel) = (values[0].clone(), values[1].clone());
+
+    if stride.iter().any(|&s| s == 0) {
+        return Err(TensorError::DimMismatch(
+            "non-positive stride is not supported for conv".to_string(),
+        )
+        .into());
+    }
+
+    // the patches duplicate image cells, so the image and kernel are assigned first to pin the copies
+    let mut assigned_len = vec![];
+    if !kernel.all_prev_assigned() {
+        kernel = region.assign(&config.custom_gates.inputs[0], &kernel)?;
+        assigned_len.push(kernel.len());
+    }
+    if !image.all_prev_assigned() {
+        image = region.assign(&config.custom_gates.inputs[1], &image)?;
+        assigned_len.push(image.len());
+    }
+    if !assigned_len.is_empty() {
+        // safe to unwrap since we've just checked it has at least one element
+        region.increment(*assigned_len.iter().max().unwrap());
+    }
+
+    // if the image has no batch dimension add a dummy one
+    if image.dims().len() == kernel.dims().len() - 1 {
+        let mut dims = vec![1];
+        dims.extend(image.dims());
+        image.reshape(&dims)?;
+    }
+
+    // negative padding crops the image, which only selects wires and adds no constraints
+    let (kept, padding) = tensor::ops::crop_negative_padding(image.dims(), padding)?;
+    let image = image.get_slice(&kept)?;
+
+    let image_dims = image.dims().to_vec();
+    let kernel_dims = kernel.dims().to_vec();
+
+    let mut padded_image = image.clone();
+    padded_image.pad(padding.to_vec(), 2)?;
+
+    let batch_size = image_dims[0];
+    let input_channels = image_dims[1];
+    let output_channels = kernel_dims[0];
+
+    let slides = estimate::window_output_dims(
+        &image_dims[2..],
+        &kernel_dims[2..],
+        &padding,
+        stride,
+        &vec![1; stride.len()],
+    )?;
+
+    let input_channels_per_group = input_channels / num_groups;
+    let output_channels_per_group = output_channels / num_groups;
+
+    if output_channels_per_group == 0 || input_channels_per_group == 0 {
+        return Err(TensorError::DimMismatch(format!(
+            "Given groups={}, expected input channels and output channels to be divisible by groups, but got input_channels={}, output_channels={}",
+            num_groups, input_channels, output_channels
+        ))
+        .into());
+    }
+
+    let num_patches = slides.iter().product::<usize>();
+    let patch_len = input_channels_per_group * kernel_dims[2..].iter().product::<usize>();
+
+    // unfold the padded image into a [batch, group, patch, patch_len] tensor
+    let mut patches = Vec::with_capacity(batch_size * num_groups * num_patches * patch_len);
+    for (batch, group) in (0..batch_size).cartesian_product(0..num_groups) {
+        let start_channel = group * input_channels_per_group;
+        let end_channel = start_channel + input_channels_per_group;
+        for coord in slides.iter().map(|s| 0..*s).multi_cartesian_product() {
+            let mut slices = vec![batch..batch + 1, start_channel..end_channel];
+            for (i, stride) in stride.iter().enumerate() {
+                let coord = coord[i] * stride;
+                slices.push(coord..(coord + kernel_dims[2 + i]));
+            }
+            let patch = padded_image.get_slice(&slices)?;
+            patches.extend(patch.get_inner_tensor()?.iter().cloned());
+        }
+    }
+    let patches: ValTensor<F> = Tensor::new(
+        Some(&patches),
+        &[batch_size, num_groups, num_patches, patch_len],
+    )?
+    .into();
+
+    kernel.reshape(&[num_groups, output_channels_per_group, patch_len])?;
+
+    let mut output = einsum(config, region, &[patches, kernel], "bgpj,gkj->bgkp")?;
+
+    let mut dims = vec![batch_size, output_channels];
+    dims.extend(slides.iter().cloned());
+    output.reshape(&dims)?;
+
+    if has_bias {
+        let mut bias = values[2].clone();
+        let mut bias_dims = vec![1; dims.len()];
+        if bias.len() > 1 {
+            bias_dims[1] = output_channels;
+        }
+        bias.reshape(&bias_dims)?;
+        output = pairwise(config, region, &[output, bias], BaseOp::Add)?;
+    }
+
+    Ok(output)
+}
+
 /// Power accumulated layout
 pub(crate) fn pow<F: PrimeField + TensorType + PartialOrd + std::hash::Hash>(
     config: &BaseConfig<F>,
@@ -3708,7 +4173,7 @@ pub(crate) fn rescale<F: PrimeField + TensorType + PartialOrd + std::hash::Hash>
 ) -> Result<Vec<ValTensor<F>>, CircuitError> {
     let mut rescaled_inputs = vec![];
     for (i, ri) in values.iter().enumerate() {
-        if scales[i].1 == 1 {
+        if scales[i].1 == 1 || ri.is_empty() {
             rescaled_inputs.push(ri.clone());
             continue;
         }
@@ -3742,6 +4207,83 @@ pub(crate) fn move_axis<F: PrimeField + TensorType + PartialOrd + std::hash::Has
     Ok(t)
 }
 
+/// Runs a channels-first (NCHW) spatial layout over a channels-last (NHWC) image.
+/// The trailing channel dim of the image is moved in front of its `num_spatial` spatial dims
+/// before calling `layout`, and moved back to the end of the output afterwards.
+/// # Examples
+/// ```
+/// use ezkl::tensor::Tensor;
+/// use ezkl::fieldutils::IntegerRep;
+/// use ezkl::circuit::ops::layouts::{channels_last, conv};
+/// use halo2curves::bn256::Fr as Fp;
+/// use ezkl::circuit::region::RegionCtx;
+/// use ezkl::circuit::region::RegionSettings;
+/// use ezkl::circuit::BaseConfig;
+/// use ezkl::tensor::ValTensor;
+///
+/// let dummy_config = BaseConfig::dummy(12, 2);
+/// let mut dummy_region = RegionCtx::new_dummy(0,2,RegionSettings::all_true(128,2));
+///
+/// let x = ValTensor::from_integer_rep_tensor(Tensor::<IntegerRep>::new(
+///     Some(&[5, 5, 2, 2, 3, 3, 0, 0, 4, 4, -1, -1, 3, 3, 1, 1, 6, 6]),
+///     &[1, 3, 3, 2],
+/// ).unwrap());
+/// let k = ValTensor::from_integer_rep_tensor(Tensor::<IntegerRep>::new(
+///     Some(&[5, 1, 1, 1, 5, 2, 1, 1]),
+///     &[2, 1, 2, 2],
+/// ).unwrap());
+/// let b = ValTensor::from_integer_rep_tensor(Tensor::<IntegerRep>::new(
+///     Some(&[1, 1]),
+///     &[2],
+/// ).unwrap());
+///
+/// let result = channels_last::<Fp, _>(&dummy_config, &mut dummy_region, &[x, k, b], 2, |region, values| {
+///     conv(&dummy_config, region, values, &vec![(0, 0); 2], &vec![1;2], 2)
+/// }).unwrap();
+/// let expected = Tensor::<IntegerRep>::new(Some(&[32, 34, 17, 20, 9, 13, 27, 26]), &[1, 2, 2, 2]).unwrap();
+/// assert_eq!(result.int_evals().unwrap(), expected);
+/// ```
+pub fn channels_last<
+    F: PrimeField + TensorType + PartialOrd + std::hash::Hash,
+    L: FnOnce(&mut RegionCtx<F>, &[ValTensor<F>]) -> Result<ValTensor<F>, CircuitError>,
+>(
+    config: &BaseConfig<F>,
+    region: &mut RegionCtx<F>,
+    values: &[ValTensor<F>],
+    num_spatial: usize,
+    layout: L,
+) -> Result<ValTensor<F>, CircuitError> {
+    let mut values = values.to_vec();
+
+    // instances can't be permuted so we copy them into advice first
+    if values[0].is_instance() {
+        values[0] = region.assign(&config.custom_gates.inputs[0], &values[0])?;
+        region.increment(values[0].len());
+    }
+
+    let rank = values[0].dims().len();
+    if rank <= num_spatial {
+        return Err(TensorError::DimMismatch(format!(
+            "channels last image of shape {:?} has no channel dim",
+            values[0].dims()
+        ))
+        .into());
+    }
+    values[0].move_axis(rank - 1, rank - 1 - num_spatial)?;
+
+    let mut output = layout(region, &values)?;
+
+    let out_rank = output.dims().len();
+    output.move_axis(out_rank - 1 - num_spatial, out_rank - 1)?;
+    // remove the dummy batch dimension the channels first layout may have added
+    if out_rank > rank {
+        let dims = output.dims()[out_rank - rank..].to_vec();
+        output.reshape(&dims)?;
+    }
+
+    Ok(output)
+}
+
 /// resize layout
 pub(crate) fn resize<F: PrimeField + TensorType + PartialOrd + std::hash::Hash>(
     config: &BaseConfig<F>,
@@ -4083,6 +4625,39 @@ pub(crate) fn nonlinearity<F: PrimeField + TensorType + PartialOrd + std::hash::
     Ok(output)
 }
 
+/// The smallest input at `scale` that tanh rounds to `scale`, i.e to 1, at and past which (and
+/// at and below its negation) tanh's outputs are saturated
+pub(crate) fn tanh_saturation(scale: utils::F32) -> IntegerRep {
+    let scale = scale.0 as f64;
+    // tanh(x / scale) * scale rounds to scale once tanh(x / scale) >= 1 - 0.5 / scale
+    (scale * (1.0 - 0.5 / scale).atanh()).ceil() as IntegerRep
+}
+
+/// Tanh layout: where the lookup range reaches past the inputs tanh saturates at, the input is
+/// clamped to them before its lookup, so that inputs of any magnitude come out as ±1. A side of
+/// the range short of saturation can't hold the clamped inputs, so isn't clamped.
+pub(crate) fn tanh<F: PrimeField + TensorType + PartialOrd + std::hash::Hash>(
+    config: &BaseConfig<F>,
+    region: &mut RegionCtx<F>,
+    values: &[ValTensor<F>; 1],
+    scale: utils::F32,
+) -> Result<ValTensor<F>, CircuitError> {
+    let saturation = tanh_saturation(scale);
+    let (min, max) = config.lookup_range;
+
+    let mut clamped = values[0].clone();
+    if min <= -saturation {
+        let lower = create_constant_tensor(integer_rep_to_felt(-saturation), 1);
+        clamped = max_comp(config, region, &[clamped, lower])?;
+    }
+    if max >= saturation {
+        let upper = create_constant_tensor(integer_rep_to_felt(saturation), 1);
+        clamped = min_comp(config, region, &[clamped, upper])?;
+    }
+
+    nonlinearity(config, region, &[clamped], &LookupOp::Tanh { scale })
+}
+
 /// Argmax
 pub(crate) fn argmax<F: PrimeField + TensorType + PartialOrd + std::hash::Hash>(
     config: &BaseConfig<F>,
@@ -4964,6 +5539,64 @@ pub(crate) fn abs<F: PrimeField + TensorType + PartialOrd + std::hash::Hash>(
     pairwise(config, region, &[values[0].clone(), sign], BaseOp::Mult)
 }
 
+/// Relu by a witnessed sign bit `s`, constrained to be boolean, with the output `s * x`. The bit
+/// is tied to the sign of `x` by range checking `2 * s * x - x` over `[0, bound]`, `bound` being
+/// the magnitude of the config's lookup range: it is `|x|` when `s` is right and `-|x|` when it
+/// isn't.
+pub(crate) fn sign_bit_relu<F: PrimeField + TensorType + PartialOrd + std::hash::Hash>(
+    config: &BaseConfig<F>,
+    region: &mut RegionCtx<F>,
+    values: &[ValTensor<F>; 1],
+) -> Result<ValTensor<F>, CircuitError> {
+    let input = values[0].clone();
+    // the inputs are bounded like those of a lookup, so calibration covers them
+    region.update_max_min_lookup_inputs(values)?;
+
+    let mut sign_bit: ValTensor<F> = if !input.any_unknowns()? {
+        input
+            .int_evals()?
+            .par_iter()
+            .map(|x| Value::known(if *x > 0 { F::ONE } else { F::ZERO }))
+            .collect::<Tensor<Value<F>>>()
+            .into()
+    } else {
+        Tensor::new(
+            Some(&vec![Value::<F>::unknown(); input.len()]),
+            &[input.len()],
+        )?
+        .into()
+    };
+    sign_bit.reshape(input.dims())?;
+    let sign_bit = boolean_identity(config, region, &[sign_bit], true)?;
+
+    let output = pairwise(config, region, &[input.clone(), sign_bit], BaseOp::Mult)?;
+    let doubled = pairwise(
+        config,
+        region,
+        &[output.clone(), output.clone()],
+        BaseOp::Add,
+    )?;
+    let magnitude = pairwise(config, region, &[doubled, input], BaseOp::Sub)?;
+
+    // calibration lays the model out under an unbounded lookup range to measure the inputs, the
+    // check is sized once it has settled on a range
+    let (min, max) = config.lookup_range;
+    if config.lookup_range != UNBOUNDED_RANGE {
+        range_check(
+            config,
+            region,
+            &[magnitude],
+            &(0, min.saturating_neg().max(max)),
+        )?;
+    } else if !region.is_dummy() {
+        return Err(CircuitError::RangeCheckNotConfigured(
+            "the sign bit relu's magnitudes, as the lookup range is unbounded".to_string(),
+        ));
+    }
+
+    Ok(output)
+}
+
 pub(crate) fn leaky_relu<F: PrimeField + TensorType + PartialOrd + std::hash::Hash>(
     config: &BaseConfig<F>,
     region: &mut RegionCtx<F>,
@@ -5129,12 +5762,22 @@ pub(crate) fn softmax_axes<F: PrimeField + TensorType + PartialOrd + std::hash::
     input_scale: utils::F32,
     output_scale: utils::F32,
     axes: &[usize],
+    refinements: usize,
+    masked: bool,
 ) -> Result<ValTensor<F>, CircuitError> {
     let soft_max_at_scale = move |config: &BaseConfig<F>,
                                   region: &mut RegionCtx<F>,
                                   values: &[ValTensor<F>; 1]|
           -> Result<ValTensor<F>, CircuitError> {
-        softmax(config, region, values, input_scale, output_scale)
+        softmax(
+            config,
+            region,
+            values,
+            input_scale,
+            output_scale,
+            refinements,
+            masked,
+        )
     };
 
     let output = multi_dim_axes_op(config, region, values, axes, soft_max_at_scale)?;
@@ -5142,13 +5785,15 @@ pub(crate) fn softmax_axes<F: PrimeField + TensorType + PartialOrd + std::hash::
     Ok(output)
 }
 
-/// percent func
+/// percent func, with the reciprocal of the sum refined by `refinements` Newton–Raphson steps
+/// from a lookup table seed if there are any, see [refined_recip]
 pub(crate) fn percent<F: PrimeField + TensorType + PartialOrd + std::hash::Hash>(
     config: &BaseConfig<F>,
     region: &mut RegionCtx<F>,
     values: &[ValTensor<F>; 1],
     input_scale: utils::F32,
     output_scale: utils::F32,
+    refinements: usize,
 ) -> Result<ValTensor<F>, CircuitError> {
     let is_assigned = values[0].all_prev_assigned();
     let mut input = values[0].clone();
@@ -5161,13 +5806,19 @@ pub(crate) fn percent<F: PrimeField + TensorType + PartialOrd + std::hash::Hash>
 
     let input_felt_scale = F::from(input_scale.0 as u64);
     let output_felt_scale = F::from(output_scale.0 as u64);
-    let inv_denom = recip(
-        config,
-        region,
-        &[denom],
-        input_felt_scale,
-        output_felt_scale,
-    )?;
+    let inv_denom = if refinements > 0 {
+        // the sum is at least 1, so its seed is well within the table's precision
+        let div = hybrid::DivConfig::new(input_scale, output_scale, refinements);
+        refined_recip(config, region, &[denom], &div)?
+    } else {
+        recip(
+            config,
+            region,
+            &[denom],
+            input_felt_scale,
+            output_felt_scale,
+        )?
+    };
     // product of num * (1 / denom) = 2*output_scale
     let percent = pairwise(config, region, &[input, inv_denom], BaseOp::Mult)?;
 
@@ -5175,7 +5826,10 @@ pub(crate) fn percent<F: PrimeField + TensorType + PartialOrd + std::hash::Hash>
     loop_div(config, region, &[percent], input_felt_scale)
 }
 
-/// Applies softmax
+/// Applies softmax. The reciprocal of the sum of exponentials is range checked to be exact, or
+/// with `refinements` > 0 refined by that many Newton–Raphson steps from a lookup table seed. A
+/// `masked` input has its rebased entries saturated at the largest the exponential rounds to 0,
+/// so that masked out entries come out as exactly 0.
 /// # Examples
 /// ```
 /// use ezkl::tensor::Tensor;
@@ -5194,7 +5848,7 @@ pub(crate) fn percent<F: PrimeField + TensorType + PartialOrd + std::hash::Hash>
 ///     Some(&[2, 2, 3, 2, 2, 0]),
 ///     &[2, 3],
 /// ).unwrap());
-/// let result = softmax::<Fp>(&dummy_config, &mut dummy_region, &[x], 128.0.into(), (128.0 * 128.0).into()).unwrap();
+/// let result = softmax::<Fp>(&dummy_config, &mut dummy_region, &[x], 128.0.into(), (128.0 * 128.0).into(), 0, false).unwrap();
 /// // doubles the scale of the input
 /// let expected = Tensor::<IntegerRep>::new(Some(&[2734, 2734, 2756, 2734, 2734, 2691]), &[2, 3]).unwrap();
 /// assert_eq!(result.int_evals().unwrap(), expected);
@@ -5205,11 +5859,21 @@ pub fn softmax<F: PrimeField + TensorType + PartialOrd + std::hash::Hash>(
     values: &[ValTensor<F>; 1],
     input_scale: utils::F32,
     output_scale: utils::F32,
+    refinements: usize,
+    masked: bool,
 ) -> Result<ValTensor<F>, CircuitError> {
     // get the max then subtract it
     let max_val = max(config, region, values)?;
     // rebase the input to 0
-    let sub = pairwise(config, region, &[values[0].clone(), max_val], BaseOp::Sub)?;
+    let mut sub = pairwise(config, region, &[values[0].clone(), max_val], BaseOp::Sub)?;
+    if masked {
+        // saturate at the largest input the exponential rounds to 0, so that masked (very
+        // negative) entries come out as exactly 0 and the exp table only has to cover
+        // [saturation, 0]
+        let saturation = (input_scale.0 as f64 * (0.5 / input_scale.0 as f64).ln()).floor() - 1.0;
+        let saturation = create_constant_tensor(integer_rep_to_felt(saturation as IntegerRep), 1);
+        sub = max_comp(config, region, &[sub, saturation])?;
+    }
     // elementwise exponential
     let ex = nonlinearity(
         config,
@@ -5218,7 +5882,14 @@ pub fn softmax<F: PrimeField + TensorType + PartialOrd + std::hash::Hash>(
         &LookupOp::Exp { scale: input_scale },
     )?;
 
-    percent(config, region, &[ex.clone()], input_scale, output_scale)
+    percent(
+        config,
+        region,
+        &[ex.clone()],
+        input_scale,
+        output_scale,
+        refinements,
+    )
 }
 
 /// Checks that the percent error between the expected public output and the actual output value
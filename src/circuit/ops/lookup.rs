@@ -15,6 +15,7 @@ use halo2curves::ff::PrimeField;
 #[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Deserialize, Serialize)]
 pub enum LookupOp {
     Div { denom: utils::F32 },
+    Recip(Reciprocal),
     IsOdd,
     Sqrt { scale: utils::F32 },
     Rsqrt { scale: utils::F32 },
@@ -36,6 +37,94 @@ pub enum LookupOp {
     Erf { scale: utils::F32 },
     Pow { scale: utils::F32, a: utils::F32 },
     HardSwish { scale: utils::F32 },
+    Mish { scale: utils::F32 },
+    QuantizeLinear(Quantization),
+    DequantizeLinear(Dequantization),
+    Custom { name: String, table: CustomTable },
+}
+
+/// Onnx's `QuantizeLinear` onto 8 bit codes, which are integers and so laid out at scale 0.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Deserialize, Serialize)]
+pub struct Quantization {
+    /// The scale of the inputs
+    pub scale: utils::F32,
+    /// What inputs are multiplied by before rounding, the reciprocal of the onnx scale
+    pub multiplier: utils::F32,
+    /// The code zero is quantized to
+    pub zero_point: i32,
+    /// Whether codes are int8 rather than uint8
+    pub signed: bool,
+}
+
+/// Onnx's `DequantizeLinear` of 8 bit codes back to the values they stand for.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Deserialize, Serialize)]
+pub struct Dequantization {
+    /// The scale of the codes
+    pub scale: utils::F32,
+    /// What codes are multiplied by once offset by the zero point, the onnx scale
+    pub multiplier: utils::F32,
+    /// The code standing for zero
+    pub zero_point: i32,
+    /// The scale of the outputs
+    pub output_scale: utils::F32,
+}
+
+/// The reciprocal of each input, with that of 0 taken to be 0, as a seed for
+/// `layouts::refined_recip` to refine.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Deserialize, Serialize)]
+pub struct Reciprocal {
+    /// The scale of the inputs
+    pub input_scale: utils::F32,
+    /// The scale of the outputs
+    pub output_scale: utils::F32,
+}
+
+/// A user supplied lookup table for an elementwise op with no builtin lookup.
+/// Maps each quantized input in `[start, start + outputs.len())` to a quantized output at the same scale.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Deserialize, Serialize)]
+pub struct CustomTable {
+    /// The scale of both the inputs and outputs
+    pub scale: crate::Scale,
+    /// The first input covered by the table
+    pub start: IntegerRep,
+    /// The output for each input, starting at `start`
+    pub outputs: Vec<IntegerRep>,
+}
+
+impl CustomTable {
+    /// Tabulates `f` over every input in `range`, with inputs and outputs quantized at `scale`.
+    pub fn from_fn(range: Range, scale: crate::Scale, f: impl Fn(f64) -> f64) -> Self {
+        let mult = crate::graph::scale_to_multiplier(scale);
+        let outputs = (range.0..=range.1)
+            .map(|x| (f(x as f64 / mult) * mult).round() as IntegerRep)
+            .collect();
+        Self {
+            scale,
+            start: range.0,
+            outputs,
+        }
+    }
+
+    /// Whether every input in `range` has an entry in the table
+    pub fn covers(&self, range: Range) -> bool {
+        range.0 >= self.start && range.1 < self.start + self.outputs.len() as IntegerRep
+    }
+
+    /// Looks up the output for `x`, if the table covers it
+    pub fn get(&self, x: IntegerRep) -> Option<IntegerRep> {
+        let idx = usize::try_from(x.checked_sub(self.start)?).ok()?;
+        self.outputs.get(idx).copied()
+    }
+
+    /// Looks up the output for `x`, saturating to the first or last entry for inputs the table doesn't cover.
+    /// Lookup tables are padded past the lookup range, so those rows still need a value.
+    pub fn eval(&self, x: IntegerRep) -> IntegerRep {
+        match self.get(x) {
+            Some(y) => y,
+            None if x < self.start => self.outputs.first().copied().unwrap_or(0),
+            None => self.outputs.last().copied().unwrap_or(0),
+        }
+    }
 }
 
 impl LookupOp {
@@ -52,6 +141,7 @@ impl LookupOp {
             LookupOp::Pow { scale, a } => format!("pow_{}_{}", scale, a),
             LookupOp::IsOdd => "is_odd".to_string(),
             LookupOp::Div { denom } => format!("div_{}", denom),
+            LookupOp::Recip(r) => format!("recip_{}_{}", r.input_scale, r.output_scale),
             LookupOp::Sigmoid { scale } => format!("sigmoid_{}", scale),
             LookupOp::Sqrt { scale } => format!("sqrt_{}", scale),
             LookupOp::Rsqrt { scale } => format!("rsqrt_{}", scale),
@@ -71,6 +161,16 @@ impl LookupOp {
             LookupOp::ATanh { scale } => format!("atanh_{}", scale),
             LookupOp::Tanh { scale } => format!("tanh_{}", scale),
             LookupOp::HardSwish { scale } => format!("hardswish_{}", scale),
+            LookupOp::Mish { scale } => format!("mish_{}", scale),
+            LookupOp::QuantizeLinear(q) => format!(
+                "quantize_linear_{}_{}_{}_{}",
+                q.scale, q.multiplier, q.zero_point, q.signed
+            ),
+            LookupOp::DequantizeLinear(d) => format!(
+                "dequantize_linear_{}_{}_{}_{}",
+                d.scale, d.multiplier, d.zero_point, d.output_scale
+            ),
+            LookupOp::Custom { name, .. } => format!("custom_{}", name),
         }
     }
 
@@ -89,6 +189,14 @@ impl LookupOp {
                 LookupOp::Div { denom } => Ok::<_, TensorError>(
                     tensor::ops::nonlinearities::const_div(&x, f32::from(*denom).into()),
                 ),
+                LookupOp::Recip(r) => {
+                    let (input_scale, output_scale) = (r.input_scale.into(), r.output_scale.into());
+                    Ok::<_, TensorError>(tensor::ops::nonlinearities::recip_or_zero(
+                        &x,
+                        input_scale,
+                        output_scale,
+                    ))
+                }
                 LookupOp::Sigmoid { scale } => {
                     Ok::<_, TensorError>(tensor::ops::nonlinearities::sigmoid(&x, scale.into()))
                 }
@@ -146,6 +254,28 @@ impl LookupOp {
                 LookupOp::HardSwish { scale } => {
                     Ok::<_, TensorError>(tensor::ops::nonlinearities::hardswish(&x, scale.into()))
                 }
+                LookupOp::Mish { scale } => {
+                    Ok::<_, TensorError>(tensor::ops::nonlinearities::mish(&x, scale.into()))
+                }
+                LookupOp::QuantizeLinear(q) => {
+                    Ok::<_, TensorError>(tensor::ops::nonlinearities::quantize_linear(
+                        &x,
+                        q.scale.into(),
+                        q.multiplier.into(),
+                        q.zero_point,
+                        q.signed,
+                    ))
+                }
+                LookupOp::DequantizeLinear(d) => {
+                    Ok::<_, TensorError>(tensor::ops::nonlinearities::dequantize_linear(
+                        &x,
+                        d.scale.into(),
+                        d.multiplier.into(),
+                        d.zero_point,
+                        d.output_scale.into(),
+                    ))
+                }
+                LookupOp::Custom { table, .. } => Ok::<_, TensorError>(x.map(|v| table.eval(v))),
             }?;
 
         let output = res.map(|x| integer_rep_to_felt(x));
@@ -166,6 +296,10 @@ impl<F: PrimeField + TensorType + PartialOrd + std::hash::Hash> Op<F> for Lookup
             LookupOp::IsOdd => "IS_ODD".to_string(),
             LookupOp::Pow { a, scale } => format!("POW(scale={}, exponent={})", scale, a),
             LookupOp::Div { denom, .. } => format!("DIV(denom={})", denom),
+            LookupOp::Recip(r) => format!(
+                "RECIP(input_scale={}, output_scale={})",
+                r.input_scale, r.output_scale
+            ),
             LookupOp::Ln { scale } => format!("LN(scale={})", scale),
             LookupOp::Sigmoid { scale } => format!("SIGMOID(scale={})", scale),
             LookupOp::Sqrt { scale } => format!("SQRT(scale={})", scale),
@@ -185,6 +319,16 @@ impl<F: PrimeField + TensorType + PartialOrd + std::hash::Hash> Op<F> for Lookup
             LookupOp::Sinh { scale } => format!("SINH(scale={})", scale),
             LookupOp::ASinh { scale } => format!("ASINH(scale={})", scale),
             LookupOp::HardSwish { scale } => format!("HARDSWISH(scale={})", scale),
+            LookupOp::Mish { scale } => format!("MISH(scale={})", scale),
+            LookupOp::QuantizeLinear(q) => format!(
+                "QUANTIZE_LINEAR(scale={}, multiplier={}, zero_point={}, signed={})",
+                q.scale, q.multiplier, q.zero_point, q.signed
+            ),
+            LookupOp::DequantizeLinear(d) => format!(
+                "DEQUANTIZE_LINEAR(scale={}, multiplier={}, zero_point={}, output_scale={})",
+                d.scale, d.multiplier, d.zero_point, d.output_scale
+            ),
+            LookupOp::Custom { name, .. } => format!("CUSTOM({})", name),
         }
     }
 
@@ -194,17 +338,22 @@ impl<F: PrimeField + TensorType + PartialOrd + std::hash::Hash> Op<F> for Lookup
         region: &mut RegionCtx<F>,
         values: &[ValTensor<F>],
     ) -> Result<Option<ValTensor<F>>, CircuitError> {
-        Ok(Some(layouts::nonlinearity(
-            config,
-            region,
-            values[..].try_into()?,
-            self,
-        )?))
+        let values = values[..].try_into()?;
+        Ok(Some(match self {
+            LookupOp::Tanh { scale } => layouts::tanh(config, region, values, *scale)?,
+            _ => layouts::nonlinearity(config, region, values, self)?,
+        }))
     }
 
     /// Returns the scale of the output of the operation.
     fn out_scale(&self, inputs_scale: Vec<crate::Scale>) -> Result<crate::Scale, CircuitError> {
         let scale = match self {
+            // codes are integers
+            LookupOp::QuantizeLinear(_) => 0,
+            LookupOp::DequantizeLinear(d) => {
+                crate::graph::multiplier_to_scale(d.output_scale.0 as f64)
+            }
+            LookupOp::Recip(r) => crate::graph::multiplier_to_scale(r.output_scale.0 as f64),
             _ => inputs_scale[0],
         };
         Ok(scale)
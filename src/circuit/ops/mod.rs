@@ -1,9 +1,10 @@
 use std::any::Any;
+use std::sync::OnceLock;
 
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    graph::quantize_tensor,
+    graph::{quantize_float, quantize_tensor, Visibility},
     tensor::{self, Tensor, TensorType, ValTensor},
 };
 use halo2curves::ff::PrimeField;
@@ -16,6 +17,8 @@ pub mod base;
 pub mod chip;
 ///
 pub mod errors;
+/// Estimates of the cells ops take up, from the shapes of their inputs
+pub mod estimate;
 ///
 pub mod hybrid;
 /// Layouts for specific functions (composed of base ops)
@@ -26,6 +29,8 @@ pub mod lookup;
 pub mod poly;
 ///
 pub mod region;
+/// A log of the advice cells assigned while laying out a circuit, to replay a failing witness
+pub mod witness_log;
 
 pub use errors::CircuitError;
 
@@ -194,8 +199,12 @@ impl<F: PrimeField + TensorType + PartialOrd + std::hash::Hash> Op<F> for Input
 }
 
 /// An unknown operation.
-#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
-pub struct Unknown;
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Unknown {
+    /// The onnx op type it was parsed from, empty for placeholders
+    #[serde(default)]
+    pub op: String,
+}
 
 impl<F: PrimeField + TensorType + PartialOrd + std::hash::Hash> Op<F> for Unknown {
     fn out_scale(&self, _: Vec<crate::Scale>) -> Result<crate::Scale, CircuitError> {
@@ -206,7 +215,11 @@ impl<F: PrimeField + TensorType + PartialOrd + std::hash::Hash> Op<F> for Unknow
     }
 
     fn as_string(&self) -> String {
-        "Unknown".into()
+        if self.op.is_empty() {
+            "Unknown".into()
+        } else {
+            format!("Unknown({})", self.op)
+        }
     }
     fn layout(
         &self,
@@ -222,37 +235,139 @@ impl<F: PrimeField + TensorType + PartialOrd + std::hash::Hash> Op<F> for Unknow
     }
 }
 
-///
-#[derive(Clone, Debug, Serialize, Deserialize)]
+/// A constant tensor. Constants parsed from a model are quantized on first access rather than
+/// up front, so that flows which only need the graph's structure never allocate the quantized
+/// values.
+#[derive(Clone, Debug)]
 pub struct Constant<F: PrimeField + TensorType + PartialOrd + std::hash::Hash> {
-    ///
-    pub quantized_values: Tensor<F>,
+    quantized_values: OnceLock<Tensor<F>>,
     ///
     pub raw_values: Tensor<f32>,
+    scale: crate::Scale,
+    visibility: Visibility,
     ///
-    #[serde(skip)]
     pub pre_assigned_val: Option<ValTensor<F>>,
+    clipped: usize,
 }
 
 impl<F: PrimeField + TensorType + PartialOrd + std::hash::Hash> Constant<F> {
     ///
     pub fn new(quantized_values: Tensor<F>, raw_values: Tensor<f32>) -> Self {
         Self {
-            quantized_values,
+            scale: quantized_values.scale().unwrap_or_default(),
+            visibility: quantized_values.visibility().unwrap_or_default(),
+            quantized_values: OnceLock::from(quantized_values),
             raw_values,
             pre_assigned_val: None,
+            clipped: 0,
+        }
+    }
+
+    /// A constant whose raw values are only quantized (at `scale`) when they are first accessed.
+    pub fn lazy(
+        raw_values: Tensor<f32>,
+        scale: crate::Scale,
+        visibility: &Visibility,
+    ) -> Result<Self, CircuitError> {
+        check_quantizable(&raw_values, scale)?;
+        Ok(Self {
+            quantized_values: OnceLock::new(),
+            raw_values,
+            scale,
+            visibility: visibility.clone(),
+            pre_assigned_val: None,
+            clipped: 0,
+        })
+    }
+
+    /// A [Constant::lazy] constant whose raw values are first clamped to `[-bound, bound]`,
+    /// counting the finite values that get clipped. Infinite values are masks (e.g the `-inf`
+    /// of an additive attention mask), which the bound stands in for rather than clips.
+    pub fn saturating(
+        raw_values: Tensor<f32>,
+        bound: f32,
+        scale: crate::Scale,
+        visibility: &Visibility,
+    ) -> Result<Self, CircuitError> {
+        let clipped = raw_values
+            .iter()
+            .filter(|x| x.is_finite() && x.abs() > bound)
+            .count();
+        let raw_values = raw_values.map(|x| x.clamp(-bound, bound));
+        Ok(Self {
+            clipped,
+            ..Self::lazy(raw_values, scale, visibility)?
+        })
+    }
+
+    /// How many of the raw values were clipped when the constant was parsed, see
+    /// [Constant::saturating].
+    pub fn clipped(&self) -> usize {
+        self.clipped
+    }
+
+    /// The quantized values, quantizing the raw values if this hasn't happened yet.
+    pub fn quantized_values(&self) -> Result<&Tensor<F>, CircuitError> {
+        if let Some(quantized) = self.quantized_values.get() {
+            return Ok(quantized);
+        }
+        let quantized = quantize_tensor(self.raw_values.clone(), self.scale, &self.visibility)?;
+        Ok(self.quantized_values.get_or_init(|| quantized))
+    }
+
+    /// The quantized values, if they have been quantized already. Only meant for changing their
+    /// layout (reshaping, permuting), alongside the raw values.
+    pub fn quantized_values_mut(&mut self) -> Option<&mut Tensor<F>> {
+        self.quantized_values.get_mut()
+    }
+
+    /// Whether the quantized values have been computed.
+    pub fn is_quantized(&self) -> bool {
+        self.quantized_values.get().is_some()
+    }
+
+    /// The dims of the constant, without quantizing it.
+    pub fn dims(&self) -> &[usize] {
+        match self.quantized_values.get() {
+            Some(quantized) => quantized.dims(),
+            None => self.raw_values.dims(),
         }
     }
+
+    /// The scale the constant is quantized at.
+    pub fn scale(&self) -> crate::Scale {
+        self.scale
+    }
+
+    /// The visibility of the quantized values.
+    pub fn visibility(&self) -> &Visibility {
+        &self.visibility
+    }
+
     /// Rebase the scale of the constant
     pub fn rebase_scale(&mut self, new_scale: crate::Scale) -> Result<(), CircuitError> {
-        let visibility = self.quantized_values.visibility().unwrap();
-        self.quantized_values = quantize_tensor(self.raw_values.clone(), new_scale, &visibility)?;
+        let visibility = self.visibility.clone();
+        self.requantize(new_scale, &visibility)
+    }
+
+    /// Quantize the raw values again at a new scale and visibility.
+    pub fn requantize(
+        &mut self,
+        new_scale: crate::Scale,
+        visibility: &Visibility,
+    ) -> Result<(), CircuitError> {
+        check_quantizable(&self.raw_values, new_scale)?;
+        self.scale = new_scale;
+        self.visibility = visibility.clone();
+        self.quantized_values = OnceLock::new();
         Ok(())
     }
 
-    /// Empty raw value
-    pub fn empty_raw_value(&mut self) {
+    /// Empty raw value, quantizing the constant first if needed.
+    pub fn empty_raw_value(&mut self) -> Result<(), CircuitError> {
+        self.quantized_values()?;
         self.raw_values = Tensor::new(None, &[0]).unwrap();
+        Ok(())
     }
 
     /// Pre-assign a value
@@ -261,6 +376,44 @@ impl<F: PrimeField + TensorType + PartialOrd + std::hash::Hash> Constant<F> {
     }
 }
 
+fn check_quantizable(raw_values: &Tensor<f32>, scale: crate::Scale) -> Result<(), CircuitError> {
+    raw_values
+        .iter()
+        .try_for_each(|x| quantize_float(&(*x).into(), 0.0, scale).map(|_| ()))?;
+    Ok(())
+}
+
+/// Mirrors the layout of the derived implementation, from before constants were quantized lazily.
+#[derive(Serialize, Deserialize)]
+#[serde(rename = "Constant")]
+struct SerializedConstant<Q, R> {
+    quantized_values: Q,
+    raw_values: R,
+}
+
+impl<F: PrimeField + TensorType + PartialOrd + std::hash::Hash + Serialize> Serialize
+    for Constant<F>
+{
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        SerializedConstant {
+            quantized_values: self
+                .quantized_values()
+                .map_err(<S::Error as serde::ser::Error>::custom)?,
+            raw_values: &self.raw_values,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de, F: PrimeField + TensorType + PartialOrd + std::hash::Hash + Deserialize<'de>>
+    Deserialize<'de> for Constant<F>
+{
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let c = SerializedConstant::<Tensor<F>, Tensor<f32>>::deserialize(deserializer)?;
+        Ok(Constant::new(c.quantized_values, c.raw_values))
+    }
+}
+
 impl<
         F: PrimeField
             + TensorType
@@ -275,7 +428,7 @@ impl<
     }
 
     fn as_string(&self) -> String {
-        format!("CONST (scale={})", self.quantized_values.scale().unwrap())
+        format!("CONST (scale={})", self.scale)
     }
     fn layout(
         &self,
@@ -286,7 +439,7 @@ impl<
         let value = if let Some(value) = &self.pre_assigned_val {
             value.clone()
         } else {
-            self.quantized_values.clone().try_into()?
+            self.quantized_values()?.clone().try_into()?
         };
         // we gotta constrain it once if its used multiple times
         Ok(Some(layouts::identity(config, region, &[value])?))
@@ -297,7 +450,7 @@ impl<
     }
 
     fn out_scale(&self, _: Vec<crate::Scale>) -> Result<crate::Scale, CircuitError> {
-        Ok(self.quantized_values.scale().unwrap())
+        Ok(self.scale)
     }
 
     fn is_constant(&self) -> bool {
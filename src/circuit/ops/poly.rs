@@ -2,12 +2,111 @@ use crate::{
     circuit::{
         layouts,
         utils::{self, F32},
+        ConvStrategy,
     },
     tensor::{self, Tensor, TensorError},
 };
 
 use super::{base::BaseOp, *};
 
+/// Where the nonzero entries of a constant 2D matmul operand are, in compressed sparse row form:
+/// output feature `i` only depends on the contracted indices `cols[row_ptr[i]..row_ptr[i + 1]]`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SparsityPattern {
+    /// Where each output feature's entries start in `cols`, followed by the number of entries
+    pub row_ptr: Vec<usize>,
+    /// The contracted index of each nonzero entry
+    pub cols: Vec<usize>,
+    /// The length of the contracted axis
+    pub num_cols: usize,
+}
+
+impl SparsityPattern {
+    /// For an einsum `equation` multiplying an input by a 2D operand (e.g. `mk,kn->mn` or `mk,nk->mn`),
+    /// the axis of the 2D operand that is summed over. `None` for any other equation.
+    pub fn contracted_axis(equation: &str) -> Option<usize> {
+        let (inputs, output) = equation.split_once("->")?;
+        let (x, w) = inputs.split_once(',')?;
+        let w = w.chars().collect::<Vec<_>>();
+        if w.len() != 2 {
+            return None;
+        }
+        let axis = w
+            .iter()
+            .position(|c| x.contains(*c) && !output.contains(*c))?;
+        let (k, n) = (w[axis], w[1 - axis]);
+
+        let unique = |s: &str| s.chars().all(|c| s.matches(c).count() == 1);
+        // every other input axis has to be carried over to the output, alongside the operand's
+        if !unique(x)
+            || !unique(output)
+            || x.contains(n)
+            || output.len() != x.len()
+            || !x
+                .chars()
+                .filter(|c| *c != k)
+                .chain([n])
+                .all(|c| output.contains(c))
+        {
+            return None;
+        }
+        Some(axis)
+    }
+
+    /// Records the nonzero entries of the 2D `weights`, whose `contracted_axis` is summed over.
+    pub fn new<F: PrimeField + TensorType>(
+        weights: &Tensor<F>,
+        contracted_axis: usize,
+    ) -> Result<Self, TensorError> {
+        let dims = weights.dims();
+        if dims.len() != 2 || contracted_axis > 1 {
+            return Err(TensorError::DimMismatch("sparsity pattern".to_string()));
+        }
+        let (num_rows, num_cols) = (dims[1 - contracted_axis], dims[contracted_axis]);
+
+        let mut row_ptr = vec![0];
+        let mut cols = vec![];
+        for row in 0..num_rows {
+            for col in 0..num_cols {
+                let idx = if contracted_axis == 0 {
+                    [col, row]
+                } else {
+                    [row, col]
+                };
+                if weights.get(&idx) != F::ZERO {
+                    cols.push(col);
+                }
+            }
+            row_ptr.push(cols.len());
+        }
+
+        Ok(Self {
+            row_ptr,
+            cols,
+            num_cols,
+        })
+    }
+
+    /// The number of output features
+    pub fn num_rows(&self) -> usize {
+        self.row_ptr.len() - 1
+    }
+
+    /// The contracted indices output feature `row` depends on
+    pub fn row(&self, row: usize) -> &[usize] {
+        &self.cols[self.row_ptr[row]..self.row_ptr[row + 1]]
+    }
+
+    /// The fraction of entries that are zero
+    pub fn sparsity(&self) -> f64 {
+        let len = self.num_rows() * self.num_cols;
+        if len == 0 {
+            return 0.0;
+        }
+        1.0 - self.cols.len() as f64 / len as f64
+    }
+}
+
 #[allow(missing_docs)]
 /// An enum representing the operations that can be expressed as arithmetic (non lookup) operations.
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -18,6 +117,8 @@ pub enum PolyOp {
         slope: utils::F32,
         scale: i32,
     },
+    /// A relu by a boolean sign bit, with inputs bounded in magnitude by the lookup range
+    ReLU,
     GatherElements {
         dim: usize,
         constant_idx: Option<Tensor<usize>>,
@@ -39,10 +140,19 @@ pub enum PolyOp {
     Einsum {
         equation: String,
     },
+    Affine {
+        equation: String,
+    },
+    SparseMatMul {
+        equation: String,
+        pattern: SparsityPattern,
+    },
     Conv {
-        padding: Vec<(usize, usize)>,
+        padding: Vec<(isize, isize)>,
         stride: Vec<usize>,
         group: usize,
+        channels_last: bool,
+        strategy: ConvStrategy,
     },
     Downsample {
         axis: usize,
@@ -119,6 +229,7 @@ impl<
     fn as_string(&self) -> String {
         match &self {
             PolyOp::LeakyReLU { slope: a, .. } => format!("LEAKYRELU (slope={})", a),
+            PolyOp::ReLU => "RELU".to_string(),
             PolyOp::Abs => "ABS".to_string(),
             PolyOp::Sign => "SIGN".to_string(),
             PolyOp::GatherElements { dim, constant_idx } => format!(
@@ -149,6 +260,12 @@ impl<
             PolyOp::Resize { .. } => "RESIZE".into(),
             PolyOp::Iff => "IFF".into(),
             PolyOp::Einsum { equation, .. } => format!("EINSUM {}", equation),
+            PolyOp::Affine { equation } => format!("AFFINE {}", equation),
+            PolyOp::SparseMatMul { equation, pattern } => format!(
+                "SPARSEMATMUL {} (sparsity={:.2})",
+                equation,
+                pattern.sparsity()
+            ),
             PolyOp::Identity { out_scale } => {
                 format!("IDENTITY (out_scale={:?})", out_scale)
             }
@@ -165,10 +282,12 @@ impl<
                 stride,
                 padding,
                 group,
+                channels_last,
+                strategy,
             } => {
                 format!(
-                    "CONV (stride={:?}, padding={:?}, group={})",
-                    stride, padding, group
+                    "CONV (stride={:?}, padding={:?}, group={}, channels_last={}, strategy={})",
+                    stride, padding, group, channels_last, strategy
                 )
             }
             PolyOp::DeConv {
@@ -207,6 +326,7 @@ impl<
             PolyOp::LeakyReLU { slope, scale } => {
                 layouts::leaky_relu(config, region, values[..].try_into()?, slope, scale)?
             }
+            PolyOp::ReLU => layouts::sign_bit_relu(config, region, values[..].try_into()?)?,
             PolyOp::MultiBroadcastTo { shape } => {
                 layouts::expand(config, region, values[..].try_into()?, shape)?
             }
@@ -232,6 +352,24 @@ impl<
             PolyOp::Neg => layouts::neg(config, region, values[..].try_into()?)?,
             PolyOp::Iff => layouts::iff(config, region, values[..].try_into()?)?,
             PolyOp::Einsum { equation } => layouts::einsum(config, region, values, equation)?,
+            PolyOp::Affine { equation } => {
+                let res = layouts::einsum(config, region, &values[..2], equation)?;
+                layouts::pairwise(config, region, &[res, values[2].clone()], BaseOp::Add)?
+            }
+            PolyOp::SparseMatMul { equation, pattern } => {
+                let res = layouts::sparse_matmul(
+                    config,
+                    region,
+                    values[..2].try_into()?,
+                    equation,
+                    pattern,
+                )?;
+                if values.len() == 3 {
+                    layouts::pairwise(config, region, &[res, values[2].clone()], BaseOp::Add)?
+                } else {
+                    res
+                }
+            }
             PolyOp::Sum { axes } => {
                 layouts::sum_axes(config, region, values[..].try_into()?, axes)?
             }
@@ -242,14 +380,23 @@ impl<
                 padding,
                 stride,
                 group,
-            } => layouts::conv(
-                config,
-                region,
-                values[..].try_into()?,
-                padding,
-                stride,
-                *group,
-            )?,
+                channels_last,
+                strategy,
+            } => {
+                let conv = |region: &mut RegionCtx<F>, values: &[ValTensor<F>]| match strategy {
+                    ConvStrategy::Direct => {
+                        layouts::conv(config, region, values, padding, stride, *group)
+                    }
+                    ConvStrategy::Im2col => {
+                        layouts::conv_im2col(config, region, values, padding, stride, *group)
+                    }
+                };
+                if *channels_last {
+                    layouts::channels_last(config, region, values, stride.len(), conv)?
+                } else {
+                    conv(region, values)?
+                }
+            }
             PolyOp::GatherElements { dim, constant_idx } => {
                 if let Some(idx) = constant_idx {
                     tensor::ops::gather_elements(values[0].get_inner_tensor()?, idx, *dim)?.into()
@@ -353,28 +500,25 @@ impl<
                 }
                 scale
             }
-            PolyOp::Prod { len_prod, .. } => in_scales[0] * (*len_prod as crate::Scale),
-            PolyOp::Sum { .. } => in_scales[0],
-            PolyOp::Conv { .. } => {
-                let input_scale = in_scales[0];
-                let kernel_scale = in_scales[1];
-                let output_scale = input_scale + kernel_scale;
-                if in_scales.len() == 3 {
-                    let bias_scale = in_scales[2];
-                    assert_eq!(output_scale, bias_scale);
-                }
-                output_scale
-            }
-            PolyOp::DeConv { .. } => {
-                let input_scale = in_scales[0];
-                let kernel_scale = in_scales[1];
-                let output_scale = input_scale + kernel_scale;
-                if in_scales.len() == 3 {
-                    let bias_scale = in_scales[2];
-                    assert_eq!(output_scale, bias_scale);
+            PolyOp::Affine { .. }
+            | PolyOp::Conv { .. }
+            | PolyOp::DeConv { .. }
+            | PolyOp::SparseMatMul { .. } => {
+                let output_scale = in_scales[0] + in_scales[1];
+                // the bias is added to the accumulated products, before any rebasing
+                if let Some(bias_scale) = in_scales.get(2) {
+                    if *bias_scale != output_scale {
+                        return Err(CircuitError::BiasScaleMismatch(
+                            Op::<F>::as_string(self),
+                            *bias_scale,
+                            output_scale,
+                        ));
+                    }
                 }
                 output_scale
             }
+            PolyOp::Prod { len_prod, .. } => in_scales[0] * (*len_prod as crate::Scale),
+            PolyOp::Sum { .. } => in_scales[0],
             PolyOp::Add => {
                 let scale_a = in_scales[0];
                 let scale_b = in_scales[1];
@@ -149,6 +149,8 @@ pub struct RegionStatistics {
     pub used_lookups: HashSet<LookupOp>,
     /// the current set of used range checks
     pub used_range_checks: HashSet<Range>,
+    /// the current min and max of the inputs to each lookup
+    pub lookup_ranges: HashMap<LookupOp, Range>,
 }
 
 impl RegionStatistics {
@@ -160,6 +162,15 @@ impl RegionStatistics {
         self.used_lookups.extend(other.used_lookups.clone());
         self.used_range_checks
             .extend(other.used_range_checks.clone());
+        for (op, range) in other.lookup_ranges.iter() {
+            self.update_lookup_range(op, *range);
+        }
+    }
+
+    /// widen the recorded input range of a lookup
+    pub fn update_lookup_range(&mut self, op: &LookupOp, range: Range) {
+        let entry = self.lookup_ranges.entry(op.clone()).or_insert((0, 0));
+        *entry = (entry.0.min(range.0), entry.1.max(range.1));
     }
 }
 
@@ -459,16 +470,21 @@ impl<'a, F: PrimeField + TensorType + PartialOrd + std::hash::Hash> RegionCtx<'a
         Ok(())
     }
 
-    /// Update the max and min from inputs
-    pub fn update_max_min_lookup_inputs(
-        &mut self,
-        inputs: &[ValTensor<F>],
-    ) -> Result<(), CircuitError> {
+    fn min_max_inputs(inputs: &[ValTensor<F>]) -> Result<Range, CircuitError> {
         let (mut min, mut max) = (0, 0);
         for i in inputs {
             max = max.max(i.int_evals()?.into_iter().max().unwrap_or_default());
             min = min.min(i.int_evals()?.into_iter().min().unwrap_or_default());
         }
+        Ok((min, max))
+    }
+
+    /// Update the max and min from inputs
+    pub fn update_max_min_lookup_inputs(
+        &mut self,
+        inputs: &[ValTensor<F>],
+    ) -> Result<(), CircuitError> {
+        let (min, max) = Self::min_max_inputs(inputs)?;
         self.statistics.max_lookup_inputs = self.statistics.max_lookup_inputs.max(max);
         self.statistics.min_lookup_inputs = self.statistics.min_lookup_inputs.min(min);
         Ok(())
@@ -508,6 +524,8 @@ impl<'a, F: PrimeField + TensorType + PartialOrd + std::hash::Hash> RegionCtx<'a
         lookup: LookupOp,
         inputs: &[ValTensor<F>],
     ) -> Result<(), CircuitError> {
+        let range = Self::min_max_inputs(inputs)?;
+        self.statistics.update_lookup_range(&lookup, range);
         self.statistics.used_lookups.insert(lookup);
         self.update_max_min_lookup_inputs(inputs)
     }
@@ -558,6 +576,11 @@ impl<'a, F: PrimeField + TensorType + PartialOrd + std::hash::Hash> RegionCtx<'a
         self.statistics.used_lookups.clone()
     }
 
+    /// get the input range of each used lookup
+    pub fn lookup_ranges(&self) -> HashMap<LookupOp, Range> {
+        self.statistics.lookup_ranges.clone()
+    }
+
     /// get used range checks
     pub fn used_range_checks(&self) -> HashSet<Range> {
         self.statistics.used_range_checks.clone()
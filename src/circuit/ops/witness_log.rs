@@ -0,0 +1,217 @@
+//! A log of the advice cells assigned while laying out a circuit, to inspect and replay the
+//! witness of a proof that failed or didn't verify.
+//!
+//! Logging is set per thread. While [record] is on, every value assigned to an advice column is
+//! mirrored into a [WitnessLog] along with its region, column and row, and the node being laid out.
+//! While [replay] is on, the values assigned are swapped for the logged ones instead, so a mock
+//! prove runs the circuit's constraints against exactly the witness that was logged.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::Path;
+
+use halo2_proofs::circuit::Value;
+use halo2_proofs::plonk::{Advice, Column};
+use halo2curves::ff::PrimeField;
+use serde::{Deserialize, Serialize};
+
+use super::CircuitError;
+
+/// An advice cell assigned while laying out a circuit.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LoggedCell {
+    /// Index of the cell's region in [WitnessLog::regions]
+    pub region: usize,
+    /// The node being laid out when the cell was assigned, if any
+    pub node: Option<usize>,
+    /// Index of the advice column
+    pub column: usize,
+    /// Row of the cell, relative to the start of its region
+    pub row: usize,
+    /// The value assigned, as the field element's repr
+    pub value: Vec<u8>,
+}
+
+impl LoggedCell {
+    /// The value assigned to the cell.
+    pub fn value<F: PrimeField>(&self) -> Option<F> {
+        let mut repr = F::Repr::default();
+        if repr.as_ref().len() != self.value.len() {
+            return None;
+        }
+        repr.as_mut().copy_from_slice(&self.value);
+        F::from_repr(repr).into()
+    }
+
+    /// Overwrites the value assigned to the cell.
+    pub fn set_value<F: PrimeField>(&mut self, value: F) {
+        self.value = value.to_repr().as_ref().to_vec();
+    }
+}
+
+/// The advice cells assigned while laying out a circuit, in the order they were first assigned.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WitnessLog {
+    /// The names of the regions cells were assigned in
+    pub regions: Vec<String>,
+    /// The cells assigned
+    pub cells: Vec<LoggedCell>,
+}
+
+impl WitnessLog {
+    /// Writes the log to `path`.
+    pub fn save(&self, path: &Path) -> Result<(), CircuitError> {
+        let writer = std::io::BufWriter::new(std::fs::File::create(path)?);
+        bincode::serialize_into(writer, self).map_err(|e| CircuitError::WitnessLog(e.to_string()))
+    }
+
+    /// Reads a log written by [WitnessLog::save].
+    pub fn load(path: &Path) -> Result<Self, CircuitError> {
+        let reader = std::io::BufReader::new(std::fs::File::open(path)?);
+        bincode::deserialize_from(reader).map_err(|e| CircuitError::WitnessLog(e.to_string()))
+    }
+
+    /// The nodes which assigned cells at `row` of a region, in ascending order. A model is laid
+    /// out in a single region, which is the one cells are logged in.
+    pub fn nodes_at(&self, row: usize) -> Vec<usize> {
+        let mut nodes = self
+            .cells
+            .iter()
+            .filter(|c| c.row == row)
+            .filter_map(|c| c.node)
+            .collect::<Vec<_>>();
+        nodes.sort();
+        nodes.dedup();
+        nodes
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Mode {
+    Record,
+    Replay,
+}
+
+/// The log of the thread and where the layout is at. Layouts run region closures more than once,
+/// so cells are looked up by their position rather than appended.
+#[derive(Debug)]
+struct State {
+    mode: Mode,
+    log: WitnessLog,
+    positions: HashMap<(usize, usize, usize), usize>,
+    region: usize,
+    node: Option<usize>,
+}
+
+thread_local!(
+    static WITNESS_LOG: RefCell<Option<State>> = const { RefCell::new(None) }
+);
+
+fn start(mode: Mode, log: WitnessLog) {
+    let positions = log
+        .cells
+        .iter()
+        .enumerate()
+        .map(|(i, c)| ((c.region, c.column, c.row), i))
+        .collect();
+    WITNESS_LOG.with(|state| {
+        *state.borrow_mut() = Some(State {
+            mode,
+            log,
+            positions,
+            region: 0,
+            node: None,
+        })
+    });
+}
+
+/// Starts logging the advice cells assigned on this thread, discarding any log in progress.
+pub fn record() {
+    start(Mode::Record, WitnessLog::default());
+}
+
+/// Starts assigning the values of `log` to the advice cells it holds on this thread, in place
+/// of the values the layout computes.
+pub fn replay(log: WitnessLog) {
+    start(Mode::Replay, log);
+}
+
+/// Stops logging or replaying on this thread, returning the log.
+pub fn finish() -> Option<WitnessLog> {
+    WITNESS_LOG.with(|state| state.borrow_mut().take().map(|s| s.log))
+}
+
+/// Whether cells are being logged or replayed on this thread.
+pub fn is_active() -> bool {
+    WITNESS_LOG.with(|state| state.borrow().is_some())
+}
+
+/// Sets the region cells are being assigned in.
+pub(crate) fn enter_region(name: &str) {
+    WITNESS_LOG.with(|state| {
+        if let Some(state) = state.borrow_mut().as_mut() {
+            state.region = match state.log.regions.iter().position(|r| r == name) {
+                Some(region) => region,
+                None => {
+                    state.log.regions.push(name.to_string());
+                    state.log.regions.len() - 1
+                }
+            };
+        }
+    });
+}
+
+/// Sets the node cells are being assigned for.
+pub(crate) fn enter_node(node: Option<usize>) {
+    WITNESS_LOG.with(|state| {
+        if let Some(state) = state.borrow_mut().as_mut() {
+            state.node = node;
+        }
+    });
+}
+
+/// Logs `value` as assigned to `row` of `column`, returning the value to assign: `value` itself
+/// unless it's being replayed and the log holds the cell.
+pub(crate) fn log_advice<F: PrimeField>(
+    column: Column<Advice>,
+    row: usize,
+    value: Value<F>,
+) -> Value<F> {
+    WITNESS_LOG.with(|state| {
+        let mut state = state.borrow_mut();
+        let state = match state.as_mut() {
+            Some(state) => state,
+            None => return value,
+        };
+        let key = (state.region, column.index(), row);
+        match state.mode {
+            Mode::Record => {
+                let (region, node) = (state.region, state.node);
+                value.map(|v| {
+                    let cell = LoggedCell {
+                        region,
+                        node,
+                        column: column.index(),
+                        row,
+                        value: v.to_repr().as_ref().to_vec(),
+                    };
+                    match state.positions.get(&key) {
+                        Some(i) => state.log.cells[*i] = cell,
+                        None => {
+                            state.positions.insert(key, state.log.cells.len());
+                            state.log.cells.push(cell);
+                        }
+                    }
+                });
+                value
+            }
+            Mode::Replay => match state.positions.get(&key) {
+                Some(i) => match state.log.cells[*i].value() {
+                    Some(v) => Value::known(v),
+                    None => value,
+                },
+                None => value,
+            },
+        }
+    })
+}
@@ -97,6 +97,111 @@ mod matmul {
     }
 }
 
+#[cfg(test)]
+mod affine {
+
+    use super::*;
+
+    const K: usize = 9;
+    const LEN: usize = 3;
+
+    #[derive(Clone)]
+    struct AffineCircuit<F: PrimeField + TensorType + PartialOrd> {
+        inputs: [ValTensor<F>; 3],
+        _marker: PhantomData<F>,
+    }
+
+    impl Circuit<F> for AffineCircuit<F> {
+        type Config = BaseConfig<F>;
+        type FloorPlanner = SimpleFloorPlanner;
+        type Params = TestParams;
+
+        fn without_witnesses(&self) -> Self {
+            self.clone()
+        }
+
+        fn configure(cs: &mut ConstraintSystem<F>) -> Self::Config {
+            let a = VarTensor::new_advice(cs, K, 1, LEN * LEN);
+            let b = VarTensor::new_advice(cs, K, 1, LEN * LEN);
+            let output = VarTensor::new_advice(cs, K, 1, LEN * LEN);
+            Self::Config::configure(cs, &[a, b], &output, CheckMode::SAFE)
+        }
+
+        fn synthesize(
+            &self,
+            mut config: Self::Config,
+            mut layouter: impl Layouter<F>,
+        ) -> Result<(), Error> {
+            layouter
+                .assign_region(
+                    || "",
+                    |region| {
+                        let mut region = RegionCtx::new(region, 0, 1, 128, 2);
+                        let equation = "ij,jk->ik".to_string();
+                        let fused = config
+                            .layout(
+                                &mut region,
+                                &self.inputs,
+                                Box::new(PolyOp::Affine {
+                                    equation: equation.clone(),
+                                }),
+                            )
+                            .map_err(|_| Error::Synthesis)?
+                            .unwrap();
+
+                        // the unfused matmul + add it replaces
+                        let matmul = config
+                            .layout(
+                                &mut region,
+                                &self.inputs[..2],
+                                Box::new(PolyOp::Einsum { equation }),
+                            )
+                            .map_err(|_| Error::Synthesis)?
+                            .unwrap();
+                        let unfused = config
+                            .layout(
+                                &mut region,
+                                &[matmul, self.inputs[2].clone()],
+                                Box::new(PolyOp::Add),
+                            )
+                            .map_err(|_| Error::Synthesis)?
+                            .unwrap();
+
+                        layouts::enforce_equality(&config, &mut region, &[fused, unfused])
+                            .map_err(|_| Error::Synthesis)?;
+                        Ok(())
+                    },
+                )
+                .unwrap();
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn affinecircuit() {
+        // parameters
+        let mut a =
+            Tensor::from((0..(LEN + 1) * LEN).map(|i| Value::known(F::from((i + 1) as u64))));
+        a.reshape(&[LEN, LEN + 1]).unwrap();
+
+        let mut w = Tensor::from((0..(LEN + 1) * 2).map(|i| Value::known(F::from((i + 1) as u64))));
+        w.reshape(&[LEN + 1, 2]).unwrap();
+
+        // a bias per output column, broadcast over the rows
+        let mut b = Tensor::from((0..2).map(|i| Value::known(F::from((i + 7) as u64))));
+        b.reshape(&[1, 2]).unwrap();
+
+        let circuit = AffineCircuit::<F> {
+            inputs: [ValTensor::from(a), ValTensor::from(w), ValTensor::from(b)],
+            _marker: PhantomData,
+        };
+
+        let prover = MockProver::run(K as u32, &circuit, vec![]).unwrap();
+        prover.assert_satisfied();
+    }
+}
+
 #[cfg(test)]
 mod matmul_col_overflow_double_col {
     use super::*;
@@ -1061,6 +1166,8 @@ mod conv {
                                     padding: vec![(1, 1); 2],
                                     stride: vec![2; 2],
                                     group: 1,
+                                    channels_last: false,
+                                    strategy: ConvStrategy::Direct,
                                 }),
                             )
                             .map_err(|_| Error::Synthesis)
@@ -1153,6 +1260,197 @@ mod conv {
     }
 }
 
+#[cfg(test)]
+#[cfg(all(
+    feature = "ezkl",
+    not(all(target_arch = "wasm32", target_os = "unknown"))
+))]
+mod conv_fixed_bias {
+
+    use halo2_proofs::poly::kzg::{
+        commitment::KZGCommitmentScheme,
+        multiopen::{ProverSHPLONK, VerifierSHPLONK},
+        strategy::SingleStrategy,
+    };
+    use snark_verifier::system::halo2::transcript::evm::EvmTranscript;
+
+    use super::*;
+
+    const K: usize = 8;
+    const LEN: usize = 10;
+
+    #[derive(Clone)]
+    struct ConvCircuit<F: PrimeField + TensorType + PartialOrd> {
+        inputs: Vec<ValTensor<F>>,
+        _marker: PhantomData<F>,
+    }
+
+    impl Circuit<F> for ConvCircuit<F> {
+        type Config = BaseConfig<F>;
+        type FloorPlanner = SimpleFloorPlanner;
+        type Params = TestParams;
+
+        fn without_witnesses(&self) -> Self {
+            self.clone()
+        }
+
+        fn configure(cs: &mut ConstraintSystem<F>) -> Self::Config {
+            let a = VarTensor::new_advice(cs, K, 1, LEN * LEN * LEN);
+            let b = VarTensor::new_advice(cs, K, 1, LEN * LEN * LEN);
+            let output = VarTensor::new_advice(cs, K, 1, LEN * LEN * LEN);
+            let _constant = VarTensor::constant_cols(cs, K, LEN * LEN, false);
+            Self::Config::configure(cs, &[a, b], &output, CheckMode::SAFE)
+        }
+
+        fn synthesize(
+            &self,
+            mut config: Self::Config,
+            mut layouter: impl Layouter<F>,
+        ) -> Result<(), Error> {
+            layouter
+                .assign_region(
+                    || "",
+                    |region| {
+                        let mut region = RegionCtx::new(region, 0, 1, 128, 2);
+                        config
+                            .layout(
+                                &mut region,
+                                &self.inputs,
+                                Box::new(PolyOp::Conv {
+                                    padding: vec![(1, 1); 2],
+                                    stride: vec![2; 2],
+                                    group: 1,
+                                    channels_last: false,
+                                    strategy: ConvStrategy::Direct,
+                                }),
+                            )
+                            .map_err(|_| Error::Synthesis)
+                    },
+                )
+                .unwrap();
+            Ok(())
+        }
+    }
+
+    fn conv_circuit(bias: &[u64]) -> ConvCircuit<F> {
+        let kernel_height = 2;
+        let kernel_width = 2;
+        let image_height = 5;
+        let image_width = 5;
+        let in_channels = 3;
+        let out_channels = bias.len();
+
+        let mut image =
+            Tensor::from((0..in_channels * image_height * image_width).map(|i| F::from(i as u64)));
+        image
+            .reshape(&[1, in_channels, image_height, image_width])
+            .unwrap();
+        image.set_visibility(&crate::graph::Visibility::Private);
+
+        // both the kernel and the bias are baked into the fixed columns at keygen
+        let mut kernels = Tensor::from(
+            (0..{ out_channels * in_channels * kernel_height * kernel_width })
+                .map(|i| F::from(i as u64)),
+        );
+        kernels
+            .reshape(&[out_channels, in_channels, kernel_height, kernel_width])
+            .unwrap();
+        kernels.set_visibility(&crate::graph::Visibility::Fixed);
+
+        let mut bias = Tensor::from(bias.iter().map(|b| F::from(*b)));
+        bias.set_visibility(&crate::graph::Visibility::Fixed);
+
+        ConvCircuit::<F> {
+            inputs: vec![
+                ValTensor::try_from(image).unwrap(),
+                ValTensor::try_from(kernels).unwrap(),
+                ValTensor::try_from(bias).unwrap(),
+            ],
+            _marker: PhantomData,
+        }
+    }
+
+    fn prove(
+        circuit: ConvCircuit<F>,
+        params: &halo2_proofs::poly::kzg::commitment::ParamsKZG<halo2curves::bn256::Bn256>,
+        pk: &halo2_proofs::plonk::ProvingKey<halo2curves::bn256::G1Affine>,
+    ) -> Result<crate::pfsys::Snark<F, halo2curves::bn256::G1Affine>, crate::pfsys::PfsysError>
+    {
+        crate::pfsys::create_proof_circuit::<
+            KZGCommitmentScheme<_>,
+            _,
+            ProverSHPLONK<_>,
+            VerifierSHPLONK<_>,
+            SingleStrategy<_>,
+            _,
+            EvmTranscript<_, _, _, _>,
+            EvmTranscript<_, _, _, _>,
+        >(
+            circuit,
+            vec![],
+            params,
+            pk,
+            // use safe mode to verify that the proof is correct
+            CheckMode::SAFE,
+            crate::Commitments::KZG,
+            crate::pfsys::TranscriptType::EVM,
+            None,
+            None,
+        )
+    }
+
+    #[test]
+    fn conv_bias_is_bound_at_keygen() {
+        let circuit = conv_circuit(&[1, 2]);
+
+        let params = crate::pfsys::srs::gen_srs::<KZGCommitmentScheme<_>>(K as u32);
+
+        let pk = crate::pfsys::create_keys::<
+            KZGCommitmentScheme<halo2curves::bn256::Bn256>,
+            ConvCircuit<F>,
+        >(&circuit, &params, true)
+        .unwrap();
+
+        assert!(prove(circuit, &params, &pk).is_ok());
+
+        // a prover can't swap in a different bias once the keys are generated
+        let tampered = conv_circuit(&[3, 4]);
+        assert!(prove(tampered, &params, &pk).is_err());
+    }
+
+    #[test]
+    fn proof_reports_phase_timings() {
+        use crate::pfsys::Timings;
+        use std::time::Duration;
+
+        let circuit = conv_circuit(&[1, 2]);
+
+        let params = crate::pfsys::srs::gen_srs::<KZGCommitmentScheme<_>>(K as u32);
+
+        let pk = crate::pfsys::create_keys::<
+            KZGCommitmentScheme<halo2curves::bn256::Bn256>,
+            ConvCircuit<F>,
+        >(&circuit, &params, true)
+        .unwrap();
+
+        let snark = prove(circuit, &params, &pk).unwrap();
+        assert!(snark.timings.prove > Duration::ZERO);
+        // safe mode verifies the proof straight after creating it
+        assert!(snark.timings.verify > Duration::ZERO);
+        // the other phases run outside of the proof system
+        assert_eq!(
+            snark.timings.total(),
+            snark.timings.prove + snark.timings.verify
+        );
+
+        // the timings are saved with the proof
+        let saved: Timings =
+            serde_json::from_value(serde_json::to_value(&snark).unwrap()["timings"].clone())
+                .unwrap();
+        assert_eq!(saved, snark.timings);
+    }
+}
+
 #[cfg(test)]
 #[cfg(all(
     feature = "ezkl",
@@ -1215,6 +1513,8 @@ mod conv_col_ultra_overflow {
                                     padding: vec![(1, 1); 2],
                                     stride: vec![2; 2],
                                     group: 1,
+                                    channels_last: false,
+                                    strategy: ConvStrategy::Direct,
                                 }),
                             )
                             .map_err(|_| Error::Synthesis)
@@ -1372,6 +1672,8 @@ mod conv_relu_col_ultra_overflow {
                                     padding: vec![(1, 1); 2],
                                     stride: vec![2; 2],
                                     group: 1,
+                                    channels_last: false,
+                                    strategy: ConvStrategy::Direct,
                                 }),
                             )
                             .map_err(|_| Error::Synthesis);
@@ -1464,6 +1766,51 @@ mod conv_relu_col_ultra_overflow {
     }
 }
 
+#[cfg(test)]
+#[cfg(all(
+    feature = "ezkl",
+    not(all(target_arch = "wasm32", target_os = "unknown"))
+))]
+mod lookup_table_ranges {
+
+    use super::*;
+
+    const K: usize = 6;
+
+    #[test]
+    fn narrow_tables_use_fewer_columns() {
+        let mut cs = ConstraintSystem::<F>::default();
+        let a = VarTensor::new_advice(&mut cs, K, 1, 8);
+        let b = VarTensor::new_advice(&mut cs, K, 1, 8);
+        let output = VarTensor::new_advice(&mut cs, K, 1, 8);
+        let mut config =
+            BaseConfig::configure(&mut cs, &[a.clone(), b.clone()], &output, CheckMode::SAFE);
+
+        let wide = LookupOp::Sigmoid { scale: 1.0.into() };
+        let narrow = LookupOp::Tanh { scale: 1.0.into() };
+        let also_narrow = LookupOp::Exp { scale: 1.0.into() };
+
+        config
+            .configure_lookup(&mut cs, &a, &output, &b, (-1024, 1024), K, &wide)
+            .unwrap();
+        config
+            .configure_lookup(&mut cs, &a, &output, &b, (-16, 16), K, &narrow)
+            .unwrap();
+        config
+            .configure_lookup(&mut cs, &a, &output, &b, (-16, 16), K, &also_narrow)
+            .unwrap();
+
+        let tables = &config.static_lookups.tables;
+        assert!(tables[&narrow].table_inputs.len() < tables[&wide].table_inputs.len());
+        assert_ne!(tables[&narrow].table_inputs, tables[&wide].table_inputs);
+        // tables over the same range still share their inputs
+        assert_eq!(
+            tables[&narrow].table_inputs,
+            tables[&also_narrow].table_inputs
+        );
+    }
+}
+
 #[cfg(test)]
 mod add_w_shape_casting {
     use super::*;
@@ -1532,11 +1879,15 @@ mod add_w_shape_casting {
 }
 
 #[cfg(test)]
-mod add {
+mod mult_per_channel {
     use super::*;
+    use crate::tensor::ValType;
 
-    const K: usize = 4;
-    const LEN: usize = 4;
+    const K: usize = 5;
+    const C: usize = 3;
+    const H: usize = 2;
+    const W: usize = 2;
+    const LEN: usize = C * H * W;
 
     #[derive(Clone)]
     struct MyCircuit<F: PrimeField + TensorType + PartialOrd> {
@@ -1557,6 +1908,7 @@ mod add {
             let a = VarTensor::new_advice(cs, K, 1, LEN);
             let b = VarTensor::new_advice(cs, K, 1, LEN);
             let output = VarTensor::new_advice(cs, K, 1, LEN);
+            let _constant = VarTensor::constant_cols(cs, K, C, false);
 
             Self::Config::configure(cs, &[a, b], &output, CheckMode::SAFE)
         }
@@ -1572,7 +1924,7 @@ mod add {
                     |region| {
                         let mut region = RegionCtx::new(region, 0, 1, 128, 2);
                         config
-                            .layout(&mut region, &self.inputs.clone(), Box::new(PolyOp::Add))
+                            .layout(&mut region, &self.inputs.clone(), Box::new(PolyOp::Mult))
                             .map_err(|_| Error::Synthesis)
                     },
                 )
@@ -1581,26 +1933,139 @@ mod add {
         }
     }
 
-    #[test]
-    fn addcircuit() {
-        // parameters
-        let a = Tensor::from((0..LEN).map(|i| Value::known(F::from(i as u64 + 1))));
-
-        let b = Tensor::from((0..LEN).map(|i| Value::known(F::from(i as u64 + 1))));
+    // a [C, H, W] activation and a constant (such as a folded scale) for each channel
+    fn inputs() -> [ValTensor<F>; 2] {
+        let mut a = Tensor::from((0..C * H * W).map(|i| Value::known(F::from(i as u64 + 1))));
+        a.reshape(&[C, H, W]).unwrap();
+        let mut b = Tensor::from((0..C).map(|i| ValType::Constant(F::from(i as u64 + 2))));
+        b.reshape(&[C, 1, 1]).unwrap();
+        [ValTensor::from(a), ValTensor::from(b)]
+    }
 
+    #[test]
+    fn multcircuit() {
         let circuit = MyCircuit::<F> {
-            inputs: [ValTensor::from(a), ValTensor::from(b)],
+            inputs: inputs(),
             _marker: PhantomData,
         };
 
         let prover = MockProver::run(K as u32, &circuit, vec![]).unwrap();
         prover.assert_satisfied();
     }
-}
-
-#[cfg(test)]
-mod dynamic_lookup {
-    use super::*;
+
+    #[test]
+    fn matches_expanded_operand() {
+        let mut config = BaseConfig::dummy(K, 1);
+        let [a, b] = inputs();
+        let mut expanded = b.clone();
+        expanded.expand(&[C, H, W]).unwrap();
+        // the broadcast operand holds a constant for every output
+        assert_eq!(expanded.get_const_indices().len(), C * H * W);
+
+        let settings = ops::region::RegionSettings::all_true(128, 2);
+        let mut region = RegionCtx::new_dummy(0, 1, settings.clone());
+        let per_channel = PolyOp::Mult
+            .layout(&mut config, &mut region, &[a.clone(), b])
+            .unwrap()
+            .unwrap();
+        // but only takes a fixed cell for each channel, and no advice beyond the op's own rows
+        assert_eq!(region.total_constants(), C);
+        assert_eq!(region.linear_coord(), LEN);
+
+        let mut region = RegionCtx::new_dummy(0, 1, settings);
+        let naive = PolyOp::Mult
+            .layout(&mut config, &mut region, &[a, expanded])
+            .unwrap()
+            .unwrap();
+        assert_eq!(per_channel.int_evals().unwrap(), naive.int_evals().unwrap());
+    }
+
+    #[test]
+    fn witness_operand_is_expanded_in_place() {
+        let mut config = BaseConfig::dummy(K, 1);
+        let [a, _] = inputs();
+        let mut b = Tensor::from((0..C).map(|i| Value::known(F::from(i as u64 + 2))));
+        b.reshape(&[C, 1, 1]).unwrap();
+
+        let mut region = RegionCtx::new_dummy(0, 1, ops::region::RegionSettings::all_true(128, 2));
+        PolyOp::Mult
+            .layout(&mut config, &mut region, &[a, ValTensor::from(b)])
+            .unwrap();
+        assert_eq!(region.total_constants(), 0);
+        assert_eq!(region.linear_coord(), LEN);
+    }
+}
+
+#[cfg(test)]
+mod add {
+    use super::*;
+
+    const K: usize = 4;
+    const LEN: usize = 4;
+
+    #[derive(Clone)]
+    struct MyCircuit<F: PrimeField + TensorType + PartialOrd> {
+        inputs: [ValTensor<F>; 2],
+        _marker: PhantomData<F>,
+    }
+
+    impl Circuit<F> for MyCircuit<F> {
+        type Config = BaseConfig<F>;
+        type FloorPlanner = SimpleFloorPlanner;
+        type Params = TestParams;
+
+        fn without_witnesses(&self) -> Self {
+            self.clone()
+        }
+
+        fn configure(cs: &mut ConstraintSystem<F>) -> Self::Config {
+            let a = VarTensor::new_advice(cs, K, 1, LEN);
+            let b = VarTensor::new_advice(cs, K, 1, LEN);
+            let output = VarTensor::new_advice(cs, K, 1, LEN);
+
+            Self::Config::configure(cs, &[a, b], &output, CheckMode::SAFE)
+        }
+
+        fn synthesize(
+            &self,
+            mut config: Self::Config,
+            mut layouter: impl Layouter<F>,
+        ) -> Result<(), Error> {
+            layouter
+                .assign_region(
+                    || "",
+                    |region| {
+                        let mut region = RegionCtx::new(region, 0, 1, 128, 2);
+                        config
+                            .layout(&mut region, &self.inputs.clone(), Box::new(PolyOp::Add))
+                            .map_err(|_| Error::Synthesis)
+                    },
+                )
+                .unwrap();
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn addcircuit() {
+        // parameters
+        let a = Tensor::from((0..LEN).map(|i| Value::known(F::from(i as u64 + 1))));
+
+        let b = Tensor::from((0..LEN).map(|i| Value::known(F::from(i as u64 + 1))));
+
+        let circuit = MyCircuit::<F> {
+            inputs: [ValTensor::from(a), ValTensor::from(b)],
+            _marker: PhantomData,
+        };
+
+        let prover = MockProver::run(K as u32, &circuit, vec![]).unwrap();
+        prover.assert_satisfied();
+    }
+}
+
+#[cfg(test)]
+mod dynamic_lookup {
+    use super::*;
 
     const K: usize = 6;
     const LEN: usize = 4;
@@ -2397,10 +2862,13 @@ mod relu {
     };
 
     const K: u32 = 8;
+    const SLOPE_SCALE: i32 = 7;
 
     #[derive(Clone)]
     struct ReLUCircuit<F: PrimeField + TensorType + PartialOrd> {
         pub input: ValTensor<F>,
+        pub slope: f32,
+        pub scale: i32,
     }
 
     impl Circuit<F> for ReLUCircuit<F> {
@@ -2453,8 +2921,8 @@ mod relu {
                                 &mut region,
                                 &[self.input.clone()],
                                 Box::new(PolyOp::LeakyReLU {
-                                    slope: 0.0.into(),
-                                    scale: 1,
+                                    slope: self.slope.into(),
+                                    scale: self.scale,
                                 }),
                             )
                             .unwrap())
@@ -2473,11 +2941,167 @@ mod relu {
 
         let circuit = ReLUCircuit::<F> {
             input: ValTensor::from(input),
+            slope: 0.0,
+            scale: 1,
+        };
+
+        let prover = MockProver::run(K, &circuit, vec![]).unwrap();
+        prover.assert_satisfied();
+    }
+
+    #[test]
+    fn leaky_relu_scales_negative_inputs_by_alpha() {
+        let input = [-3_i128, -1, 0, 2]
+            .iter()
+            .map(|x| Value::known(crate::fieldutils::integer_rep_to_felt::<F>(*x)))
+            .collect::<Tensor<_>>();
+        let circuit = ReLUCircuit::<F> {
+            input: ValTensor::from(input),
+            slope: 0.2,
+            scale: SLOPE_SCALE,
+        };
+
+        let prover = MockProver::run(K, &circuit, vec![]).unwrap();
+        prover.assert_satisfied();
+
+        // the slope is quantized to 26 / 2^7 and the positive side rescaled to the same 2^7
+        let mut config = BaseConfig::dummy(K as usize, 1);
+        let settings = ops::region::RegionSettings::all_true(2, 2);
+        let mut region = RegionCtx::new_dummy(0, 1, settings);
+        let op = PolyOp::LeakyReLU {
+            slope: circuit.slope.into(),
+            scale: circuit.scale,
         };
+        let output = op
+            .layout(&mut config, &mut region, &[circuit.input.clone()])
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            output.int_evals().unwrap(),
+            Tensor::new(Some(&[-78, -26, 0, 256]), &[4]).unwrap()
+        );
+        assert_eq!(Op::<F>::out_scale(&op, vec![0]).unwrap(), SLOPE_SCALE);
+    }
+}
+
+#[cfg(test)]
+mod sign_bit_relu {
+    use super::*;
+    use halo2_proofs::{
+        circuit::{Layouter, SimpleFloorPlanner, Value},
+        dev::MockProver,
+        plonk::{Circuit, ConstraintSystem, Error},
+    };
+
+    const K: u32 = 8;
+    const BOUND: i128 = 16;
+
+    fn input() -> ValTensor<F> {
+        let values = [-3_i128, 0, 5, -16, 16, 1]
+            .iter()
+            .map(|x| Value::known(crate::fieldutils::integer_rep_to_felt::<F>(*x)))
+            .collect::<Tensor<_>>();
+        ValTensor::from(values)
+    }
+
+    #[derive(Clone)]
+    struct ReLUCircuit<F: PrimeField + TensorType + PartialOrd> {
+        pub input: ValTensor<F>,
+    }
+
+    impl Circuit<F> for ReLUCircuit<F> {
+        type Config = BaseConfig<F>;
+        type FloorPlanner = SimpleFloorPlanner;
+        type Params = TestParams;
+
+        fn without_witnesses(&self) -> Self {
+            self.clone()
+        }
+
+        fn configure(cs: &mut ConstraintSystem<F>) -> Self::Config {
+            let advices = (0..3)
+                .map(|_| VarTensor::new_advice(cs, K as usize, 1, 3))
+                .collect::<Vec<_>>();
+
+            let mut config = BaseConfig::configure(
+                cs,
+                &[advices[0].clone(), advices[1].clone()],
+                &advices[2],
+                CheckMode::SAFE,
+            );
+
+            config
+                .configure_range_check(cs, &advices[0], &advices[1], (0, BOUND), K as usize)
+                .unwrap();
+            config.lookup_range = (-BOUND, BOUND);
+
+            let _constant = VarTensor::constant_cols(cs, K as usize, 8, false);
+
+            config
+        }
+
+        fn synthesize(
+            &self,
+            mut config: Self::Config,
+            mut layouter: impl Layouter<F>,
+        ) -> Result<(), Error> {
+            config.layout_range_checks(&mut layouter).unwrap();
+            layouter
+                .assign_region(
+                    || "",
+                    |region| {
+                        let mut region = RegionCtx::new(region, 0, 1, 2, 2);
+                        Ok(config
+                            .layout(&mut region, &[self.input.clone()], Box::new(PolyOp::ReLU))
+                            .unwrap())
+                    },
+                )
+                .unwrap();
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn relucircuit() {
+        let circuit = ReLUCircuit::<F> { input: input() };
 
         let prover = MockProver::run(K, &circuit, vec![]).unwrap();
         prover.assert_satisfied();
     }
+
+    #[test]
+    fn matches_decomposed_relu() {
+        let mut config = BaseConfig::dummy(K as usize, 1);
+        let settings = ops::region::RegionSettings::all_true(128, 2);
+
+        // the magnitudes are range checked over the lookup range, once it's known
+        let mut region = RegionCtx::new_dummy(0, 1, settings.clone());
+        PolyOp::ReLU
+            .layout(&mut config, &mut region, &[input()])
+            .unwrap();
+        assert!(region.used_range_checks().is_empty());
+
+        config.lookup_range = (-BOUND, BOUND);
+        let mut region = RegionCtx::new_dummy(0, 1, settings.clone());
+        let sign_bit = PolyOp::ReLU
+            .layout(&mut config, &mut region, &[input()])
+            .unwrap()
+            .unwrap();
+        assert_eq!(region.used_range_checks(), [(0, BOUND)].into());
+        let mut region = RegionCtx::new_dummy(0, 1, settings);
+        let decomposed = PolyOp::LeakyReLU {
+            slope: 0.0.into(),
+            scale: 1,
+        }
+        .layout(&mut config, &mut region, &[input()])
+        .unwrap()
+        .unwrap();
+        assert_eq!(
+            sign_bit.int_evals().unwrap(),
+            decomposed.int_evals().unwrap()
+        );
+    }
 }
 
 #[cfg(test)]
@@ -2485,25 +3109,31 @@ mod relu {
     feature = "ezkl",
     not(all(target_arch = "wasm32", target_os = "unknown"))
 ))]
-mod lookup_ultra_overflow {
+mod custom_lookup {
     use super::*;
+    use crate::circuit::ops::lookup::CustomTable;
     use halo2_proofs::{
         circuit::{Layouter, SimpleFloorPlanner, Value},
+        dev::MockProver,
         plonk::{Circuit, ConstraintSystem, Error},
-        poly::kzg::{
-            commitment::KZGCommitmentScheme,
-            multiopen::{ProverSHPLONK, VerifierSHPLONK},
-            strategy::SingleStrategy,
-        },
     };
-    use snark_verifier::system::halo2::transcript::evm::EvmTranscript;
+
+    const K: u32 = 8;
+    const RANGE: crate::circuit::table::Range = (-64, 64);
+
+    fn cube_root() -> LookupOp {
+        LookupOp::Custom {
+            name: "CubeRoot".to_string(),
+            table: CustomTable::from_fn(RANGE, 3, f64::cbrt),
+        }
+    }
 
     #[derive(Clone)]
-    struct SigmoidCircuit<F: PrimeField + TensorType + PartialOrd> {
+    struct CubeRootCircuit<F: PrimeField + TensorType + PartialOrd> {
         pub input: ValTensor<F>,
     }
 
-    impl Circuit<F> for SigmoidCircuit<F> {
+    impl Circuit<F> for CubeRootCircuit<F> {
         type Config = BaseConfig<F>;
         type FloorPlanner = SimpleFloorPlanner;
         type Params = TestParams;
@@ -2514,11 +3144,9 @@ mod lookup_ultra_overflow {
 
         fn configure(cs: &mut ConstraintSystem<F>) -> Self::Config {
             let advices = (0..3)
-                .map(|_| VarTensor::new_advice(cs, 4, 1, 3))
+                .map(|_| VarTensor::new_advice(cs, K as usize, 1, 4))
                 .collect::<Vec<_>>();
 
-            let nl = LookupOp::Sigmoid { scale: 1.0.into() };
-
             let mut config = BaseConfig::default();
 
             config
@@ -2527,9 +3155,9 @@ mod lookup_ultra_overflow {
                     &advices[0],
                     &advices[1],
                     &advices[2],
-                    (-1024, 1024),
-                    4,
-                    &nl,
+                    RANGE,
+                    K as usize,
+                    &cube_root(),
                 )
                 .unwrap();
             config
@@ -2547,11 +3175,7 @@ mod lookup_ultra_overflow {
                     |region| {
                         let mut region = RegionCtx::new(region, 0, 1, 128, 2);
                         config
-                            .layout(
-                                &mut region,
-                                &[self.input.clone()],
-                                Box::new(LookupOp::Sigmoid { scale: 1.0.into() }),
-                            )
+                            .layout(&mut region, &[self.input.clone()], Box::new(cube_root()))
                             .map_err(|_| Error::Synthesis)
                     },
                 )
@@ -2562,49 +3186,688 @@ mod lookup_ultra_overflow {
     }
 
     #[test]
-    #[ignore]
-    fn sigmoidcircuit() {
-        // get some logs fam
-        crate::logger::init_logger();
-        // parameters
-        let a = Tensor::from((0..4).map(|i| Value::known(F::from(i + 1))));
-
-        let circuit = SigmoidCircuit::<F> {
-            input: ValTensor::from(a),
+    fn custom_table_evaluates() {
+        let op = cube_root();
+        let LookupOp::Custom { table, .. } = &op else {
+            unreachable!()
         };
+        assert!(table.covers(RANGE));
+        assert!(!table.covers((-65, 64)));
+        assert!(!table.covers((-64, 65)));
 
-        let params = crate::pfsys::srs::gen_srs::<
-            halo2_proofs::poly::kzg::commitment::KZGCommitmentScheme<_>,
-        >(4_u32);
+        let input =
+            Tensor::new(Some(&[F::from(8), F::from(64), -F::from(8), F::ZERO]), &[4]).unwrap();
+        let output = op.f(&[input]).unwrap().output;
+        let expected =
+            Tensor::new(Some(&[F::from(8), F::from(16), -F::from(8), F::ZERO]), &[4]).unwrap();
+        assert_eq!(output, expected);
 
-        let pk = crate::pfsys::create_keys::<
-            halo2_proofs::poly::kzg::commitment::KZGCommitmentScheme<halo2curves::bn256::Bn256>,
-            SigmoidCircuit<F>,
-        >(&circuit, &params, true)
-        .unwrap();
+        // inputs past the table (e.g. in padded table rows) saturate to the edge entries
+        assert_eq!(table.eval(1000), table.eval(64));
+        assert_eq!(table.eval(-1000), table.eval(-64));
+    }
 
-        let prover = crate::pfsys::create_proof_circuit::<
-            KZGCommitmentScheme<_>,
-            _,
-            ProverSHPLONK<_>,
-            VerifierSHPLONK<_>,
-            SingleStrategy<_>,
-            _,
-            EvmTranscript<_, _, _, _>,
-            EvmTranscript<_, _, _, _>,
-        >(
-            circuit.clone(),
-            vec![],
-            &params,
-            &pk,
-            // use safe mode to verify that the proof is correct
-            CheckMode::SAFE,
-            crate::Commitments::KZG,
-            crate::pfsys::TranscriptType::EVM,
-            None,
-            None,
+    #[test]
+    fn custom_table_circuit() {
+        let input = Tensor::from(
+            [8_u64, 64, 1, 27]
+                .into_iter()
+                .map(|i| Value::known(F::from(i))),
         );
 
-        assert!(prover.is_ok());
+        let circuit = CubeRootCircuit::<F> {
+            input: ValTensor::from(input),
+        };
+
+        let prover = MockProver::run(K, &circuit, vec![]).unwrap();
+        prover.assert_satisfied();
+    }
+}
+
+#[cfg(test)]
+#[cfg(all(
+    feature = "ezkl",
+    not(all(target_arch = "wasm32", target_os = "unknown"))
+))]
+mod tanh_saturation {
+    use super::*;
+    use crate::fieldutils::IntegerRep;
+    use halo2_proofs::{
+        circuit::{Layouter, SimpleFloorPlanner, Value},
+        dev::MockProver,
+        plonk::{Circuit, ConstraintSystem, Error},
+    };
+
+    const K: u32 = 10;
+    const SCALE: f32 = 4.0;
+    // just covers the inputs tanh saturates at for SCALE
+    const RANGE: crate::circuit::table::Range = (-8, 8);
+
+    #[derive(Clone)]
+    struct TanhCircuit<F: PrimeField + TensorType + PartialOrd> {
+        pub input: ValTensor<F>,
+        pub expected: Tensor<IntegerRep>,
+    }
+
+    impl Circuit<F> for TanhCircuit<F> {
+        type Config = BaseConfig<F>;
+        type FloorPlanner = SimpleFloorPlanner;
+        type Params = TestParams;
+
+        fn without_witnesses(&self) -> Self {
+            self.clone()
+        }
+
+        fn configure(cs: &mut ConstraintSystem<F>) -> Self::Config {
+            let advices = (0..3)
+                .map(|_| VarTensor::new_advice(cs, K as usize, 1, 64))
+                .collect::<Vec<_>>();
+
+            let mut config = BaseConfig::configure(
+                cs,
+                &[advices[0].clone(), advices[1].clone()],
+                &advices[2],
+                CheckMode::SAFE,
+            );
+
+            config
+                .configure_lookup(
+                    cs,
+                    &advices[0],
+                    &advices[1],
+                    &advices[2],
+                    RANGE,
+                    K as usize,
+                    &LookupOp::Tanh {
+                        scale: SCALE.into(),
+                    },
+                )
+                .unwrap();
+            config.lookup_range = RANGE;
+
+            config
+                .configure_range_check(cs, &advices[0], &advices[1], (-1, 1), K as usize)
+                .unwrap();
+
+            config
+                .configure_range_check(cs, &advices[0], &advices[1], (0, 1), K as usize)
+                .unwrap();
+
+            let _constant = VarTensor::constant_cols(cs, K as usize, 8, false);
+
+            config
+        }
+
+        fn synthesize(
+            &self,
+            mut config: Self::Config,
+            mut layouter: impl Layouter<F>,
+        ) -> Result<(), Error> {
+            config.layout_tables(&mut layouter).unwrap();
+            config.layout_range_checks(&mut layouter).unwrap();
+            layouter
+                .assign_region(
+                    || "",
+                    |region| {
+                        let mut region = RegionCtx::new(region, 0, 1, 2, 8);
+                        let output = config
+                            .layout(
+                                &mut region,
+                                &[self.input.clone()],
+                                Box::new(LookupOp::Tanh {
+                                    scale: SCALE.into(),
+                                }),
+                            )
+                            .map_err(|_| Error::Synthesis)?
+                            .unwrap();
+                        assert_eq!(output.int_evals().unwrap(), self.expected);
+                        Ok(())
+                    },
+                )
+                .unwrap();
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn saturation_is_where_tanh_rounds_to_one() {
+        let saturation = crate::circuit::layouts::tanh_saturation(SCALE.into());
+        let inputs = Tensor::new(Some(&[saturation - 1, saturation]), &[2]).unwrap();
+        let outputs = crate::tensor::ops::nonlinearities::tanh(&inputs, SCALE.into());
+        assert_eq!(outputs[0], SCALE as IntegerRep - 1);
+        assert_eq!(outputs[1], SCALE as IntegerRep);
+        assert!(saturation <= RANGE.1);
+    }
+
+    #[test]
+    fn inputs_past_the_table_saturate() {
+        let input = [100, -100, 3, 0, -3, 7];
+        let expected = Tensor::new(Some(&[4, -4, 3, 0, -3, 4]), &[6]).unwrap();
+
+        let circuit = TanhCircuit::<F> {
+            input: ValTensor::from(Tensor::from(
+                input
+                    .into_iter()
+                    .map(|i| Value::known(crate::fieldutils::integer_rep_to_felt::<F>(i))),
+            )),
+            expected,
+        };
+
+        let prover = MockProver::run(K, &circuit, vec![]).unwrap();
+        prover.assert_satisfied();
+    }
+
+    #[test]
+    fn clamps_only_the_sides_of_the_range_past_saturation() {
+        let saturation = crate::circuit::layouts::tanh_saturation(SCALE.into());
+        let rows = |lookup_range| {
+            let mut config = BaseConfig::dummy(K as usize, 1);
+            config.lookup_range = lookup_range;
+            let input: ValTensor<F> = Tensor::from(
+                [-3, 0, 3]
+                    .into_iter()
+                    .map(|i| Value::known(crate::fieldutils::integer_rep_to_felt::<F>(i))),
+            )
+            .into();
+            let mut region =
+                RegionCtx::new_dummy(0, 1, ops::region::RegionSettings::all_true(2, 8));
+            LookupOp::Tanh {
+                scale: SCALE.into(),
+            }
+            .layout(&mut config, &mut region, &[input])
+            .unwrap();
+            region.linear_coord()
+        };
+
+        let unclamped = rows((1 - saturation, saturation - 1));
+        let one_side = rows((1 - saturation, saturation));
+        assert!(unclamped < one_side);
+        assert!(one_side < rows(RANGE));
+        assert_eq!(rows(RANGE), rows((-saturation, saturation)));
+    }
+}
+
+#[cfg(test)]
+mod threshold {
+    use super::*;
+    use crate::circuit::ops::hybrid::HybridOp;
+    use halo2_proofs::{
+        circuit::{Layouter, SimpleFloorPlanner, Value},
+        dev::MockProver,
+        plonk::{Circuit, ConstraintSystem, Error},
+    };
+
+    const K: u32 = 8;
+
+    #[derive(Clone)]
+    struct ThresholdCircuit<F: PrimeField + TensorType + PartialOrd> {
+        pub input: ValTensor<F>,
+        pub class: usize,
+        pub threshold: crate::fieldutils::IntegerRep,
+    }
+
+    impl Circuit<F> for ThresholdCircuit<F> {
+        type Config = BaseConfig<F>;
+        type FloorPlanner = SimpleFloorPlanner;
+        type Params = TestParams;
+
+        fn without_witnesses(&self) -> Self {
+            self.clone()
+        }
+
+        fn configure(cs: &mut ConstraintSystem<F>) -> Self::Config {
+            let advices = (0..3)
+                .map(|_| VarTensor::new_advice(cs, 8, 1, 3))
+                .collect::<Vec<_>>();
+
+            let mut config = BaseConfig::configure(
+                cs,
+                &[advices[0].clone(), advices[1].clone()],
+                &advices[2],
+                CheckMode::SAFE,
+            );
+
+            config
+                .configure_range_check(cs, &advices[0], &advices[1], (-1, 1), K as usize)
+                .unwrap();
+
+            config
+                .configure_range_check(cs, &advices[0], &advices[1], (0, 1), K as usize)
+                .unwrap();
+
+            let _constant = VarTensor::constant_cols(cs, K as usize, 8, false);
+
+            config
+        }
+
+        fn synthesize(
+            &self,
+            mut config: Self::Config,
+            mut layouter: impl Layouter<F>,
+        ) -> Result<(), Error> {
+            config.layout_range_checks(&mut layouter).unwrap();
+            layouter
+                .assign_region(
+                    || "",
+                    |region| {
+                        let mut region = RegionCtx::new(region, 0, 1, 2, 2);
+                        Ok(config
+                            .layout(
+                                &mut region,
+                                &[self.input.clone()],
+                                Box::new(HybridOp::Threshold {
+                                    class: self.class,
+                                    threshold: self.threshold,
+                                }),
+                            )
+                            .unwrap())
+                    },
+                )
+                .unwrap();
+
+            Ok(())
+        }
+    }
+
+    fn threshold_circuit(threshold: crate::fieldutils::IntegerRep) -> ThresholdCircuit<F> {
+        let input: Tensor<Value<F>> = Tensor::new(
+            Some(&[0, 3, 1, 2].map(|x| Value::<F>::known(F::from(x as u64)))),
+            &[1, 4],
+        )
+        .unwrap();
+
+        ThresholdCircuit::<F> {
+            input: ValTensor::from(input),
+            class: 1,
+            threshold,
+        }
+    }
+
+    #[test]
+    fn thresholdcircuit() {
+        let circuit = threshold_circuit(2);
+        let prover = MockProver::run(K, &circuit, vec![]).unwrap();
+        prover.assert_satisfied();
+    }
+
+    #[test]
+    fn thresholdcircuit_unsatisfied() {
+        // the output for the class equals the threshold, so doesn't exceed it
+        let circuit = threshold_circuit(3);
+        let prover = MockProver::run(K, &circuit, vec![]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+}
+
+#[cfg(test)]
+#[cfg(all(
+    feature = "ezkl",
+    not(all(target_arch = "wasm32", target_os = "unknown"))
+))]
+mod lookup_ultra_overflow {
+    use super::*;
+    use halo2_proofs::{
+        circuit::{Layouter, SimpleFloorPlanner, Value},
+        plonk::{Circuit, ConstraintSystem, Error},
+        poly::kzg::{
+            commitment::KZGCommitmentScheme,
+            multiopen::{ProverSHPLONK, VerifierSHPLONK},
+            strategy::SingleStrategy,
+        },
+    };
+    use snark_verifier::system::halo2::transcript::evm::EvmTranscript;
+
+    #[derive(Clone)]
+    struct SigmoidCircuit<F: PrimeField + TensorType + PartialOrd> {
+        pub input: ValTensor<F>,
+    }
+
+    impl Circuit<F> for SigmoidCircuit<F> {
+        type Config = BaseConfig<F>;
+        type FloorPlanner = SimpleFloorPlanner;
+        type Params = TestParams;
+
+        fn without_witnesses(&self) -> Self {
+            self.clone()
+        }
+
+        fn configure(cs: &mut ConstraintSystem<F>) -> Self::Config {
+            let advices = (0..3)
+                .map(|_| VarTensor::new_advice(cs, 4, 1, 3))
+                .collect::<Vec<_>>();
+
+            let nl = LookupOp::Sigmoid { scale: 1.0.into() };
+
+            let mut config = BaseConfig::default();
+
+            config
+                .configure_lookup(
+                    cs,
+                    &advices[0],
+                    &advices[1],
+                    &advices[2],
+                    (-1024, 1024),
+                    4,
+                    &nl,
+                )
+                .unwrap();
+            config
+        }
+
+        fn synthesize(
+            &self,
+            mut config: Self::Config,
+            mut layouter: impl Layouter<F>, // layouter is our 'write buffer' for the circuit
+        ) -> Result<(), Error> {
+            config.layout_tables(&mut layouter).unwrap();
+            layouter
+                .assign_region(
+                    || "",
+                    |region| {
+                        let mut region = RegionCtx::new(region, 0, 1, 128, 2);
+                        config
+                            .layout(
+                                &mut region,
+                                &[self.input.clone()],
+                                Box::new(LookupOp::Sigmoid { scale: 1.0.into() }),
+                            )
+                            .map_err(|_| Error::Synthesis)
+                    },
+                )
+                .unwrap();
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    #[ignore]
+    fn sigmoidcircuit() {
+        // get some logs fam
+        crate::logger::init_logger();
+        // parameters
+        let a = Tensor::from((0..4).map(|i| Value::known(F::from(i + 1))));
+
+        let circuit = SigmoidCircuit::<F> {
+            input: ValTensor::from(a),
+        };
+
+        let params = crate::pfsys::srs::gen_srs::<
+            halo2_proofs::poly::kzg::commitment::KZGCommitmentScheme<_>,
+        >(4_u32);
+
+        let pk = crate::pfsys::create_keys::<
+            halo2_proofs::poly::kzg::commitment::KZGCommitmentScheme<halo2curves::bn256::Bn256>,
+            SigmoidCircuit<F>,
+        >(&circuit, &params, true)
+        .unwrap();
+
+        let prover = crate::pfsys::create_proof_circuit::<
+            KZGCommitmentScheme<_>,
+            _,
+            ProverSHPLONK<_>,
+            VerifierSHPLONK<_>,
+            SingleStrategy<_>,
+            _,
+            EvmTranscript<_, _, _, _>,
+            EvmTranscript<_, _, _, _>,
+        >(
+            circuit.clone(),
+            vec![],
+            &params,
+            &pk,
+            // use safe mode to verify that the proof is correct
+            CheckMode::SAFE,
+            crate::Commitments::KZG,
+            crate::pfsys::TranscriptType::EVM,
+            None,
+            None,
+        );
+
+        assert!(prover.is_ok());
+    }
+}
+
+#[cfg(test)]
+#[cfg(all(
+    feature = "ezkl",
+    not(all(target_arch = "wasm32", target_os = "unknown"))
+))]
+mod refined_recip {
+    use super::*;
+    use crate::circuit::ops::hybrid::{DivConfig, HybridOp};
+    use crate::fieldutils::{integer_rep_to_felt, IntegerRep};
+    use halo2_proofs::{
+        circuit::{Layouter, SimpleFloorPlanner, Value},
+        dev::MockProver,
+        plonk::{Circuit, ConstraintSystem, Error},
+    };
+
+    const K: u32 = 10;
+    // 4 bits, so the seed table is indexed by denominators rounded to multiples of 4
+    const SCALE: f32 = 16.0;
+
+    fn div_config(scale: f32, refinements: usize) -> DivConfig {
+        DivConfig::new(scale.into(), scale.into(), refinements)
+    }
+
+    fn known(values: &[IntegerRep]) -> ValTensor<F> {
+        ValTensor::from(
+            values
+                .iter()
+                .map(|x| Value::known(integer_rep_to_felt::<F>(*x)))
+                .collect::<Tensor<_>>(),
+        )
+    }
+
+    /// Lays `op` out on `input` without a prover, for its values.
+    fn dummy_layout(op: HybridOp, input: ValTensor<F>) -> Tensor<IntegerRep> {
+        let mut config = BaseConfig::dummy(12, 2);
+        let settings = ops::region::RegionSettings::all_true(128, 2);
+        let mut region = RegionCtx::new_dummy(0, 2, settings);
+        op.layout(&mut config, &mut region, &[input])
+            .unwrap()
+            .unwrap()
+            .int_evals()
+            .unwrap()
+    }
+
+    #[derive(Clone)]
+    struct RecipCircuit<F: PrimeField + TensorType + PartialOrd> {
+        pub input: ValTensor<F>,
+        pub expected: Tensor<IntegerRep>,
+    }
+
+    impl Circuit<F> for RecipCircuit<F> {
+        type Config = BaseConfig<F>;
+        type FloorPlanner = SimpleFloorPlanner;
+        type Params = TestParams;
+
+        fn without_witnesses(&self) -> Self {
+            self.clone()
+        }
+
+        fn configure(cs: &mut ConstraintSystem<F>) -> Self::Config {
+            let advices = (0..3)
+                .map(|_| VarTensor::new_advice(cs, K as usize, 1, 64))
+                .collect::<Vec<_>>();
+
+            let mut config = BaseConfig::configure(
+                cs,
+                &[advices[0].clone(), advices[1].clone()],
+                &advices[2],
+                CheckMode::SAFE,
+            );
+
+            let div = div_config(SCALE, 2);
+            config
+                .configure_lookup(
+                    cs,
+                    &advices[0],
+                    &advices[1],
+                    &advices[2],
+                    (-64, 64),
+                    K as usize,
+                    &LookupOp::Recip(div.seed()),
+                )
+                .unwrap();
+
+            // rounding to the seed table's inputs, then rescaling each refinement
+            for bracket in [div.seed_divisor / 2, SCALE as IntegerRep / 2] {
+                config
+                    .configure_range_check(
+                        cs,
+                        &advices[0],
+                        &advices[1],
+                        (-bracket, bracket),
+                        K as usize,
+                    )
+                    .unwrap();
+            }
+
+            let _constant = VarTensor::constant_cols(cs, K as usize, 8, false);
+
+            config
+        }
+
+        fn synthesize(
+            &self,
+            mut config: Self::Config,
+            mut layouter: impl Layouter<F>,
+        ) -> Result<(), Error> {
+            config.layout_tables(&mut layouter).unwrap();
+            config.layout_range_checks(&mut layouter).unwrap();
+            layouter
+                .assign_region(
+                    || "",
+                    |region| {
+                        let mut region = RegionCtx::new(region, 0, 1, 2, 8);
+                        let output = config
+                            .layout(
+                                &mut region,
+                                &[self.input.clone()],
+                                Box::new(HybridOp::RefinedRecip(div_config(SCALE, 2))),
+                            )
+                            .map_err(|_| Error::Synthesis)?
+                            .unwrap();
+                        assert_eq!(output.int_evals().unwrap(), self.expected);
+                        Ok(())
+                    },
+                )
+                .unwrap();
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn refinements_recover_the_precision_of_a_coarse_seed() {
+        // exactly 256 / b: 16, 11.13, -6.4, 2.56, 36.57, 0, 1.02, -85.33. the seeds of the small
+        // denominators, read at 7 / 4 -> 2 and -3 / 4 -> -1, are 32 and -64
+        let circuit = RecipCircuit::<F> {
+            input: known(&[16, 23, -40, 100, 7, 0, 250, -3]),
+            expected: Tensor::new(Some(&[16, 11, -6, 3, 37, 0, 1, -85]), &[8]).unwrap(),
+        };
+
+        let prover = MockProver::run(K, &circuit, vec![]).unwrap();
+        prover.assert_satisfied();
+    }
+
+    #[test]
+    fn reciprocals_are_within_their_error_bounds() {
+        let scale = 128.0;
+        let denoms = (-4000..=4000).collect::<Vec<IntegerRep>>();
+        let exact = |b: IntegerRep| match b {
+            0 => 0.0,
+            b => (scale * scale) as f64 / b as f64,
+        };
+
+        let mut worst = vec![];
+        for refinements in 0..3 {
+            let div = div_config(scale, refinements);
+            let output = dummy_layout(HybridOp::RefinedRecip(div), known(&denoms));
+            let mut worst_past_one: f64 = 0.0;
+            for (b, y) in denoms.iter().zip(output.iter()) {
+                let error = (*y as f64 - exact(*b)).abs();
+                assert!(
+                    error <= div.error_bound(*b) + 1e-9,
+                    "1 / {} at {} refinements: {} vs {}",
+                    b,
+                    refinements,
+                    y,
+                    exact(*b)
+                );
+                if b.abs() >= scale as IntegerRep {
+                    worst_past_one = worst_past_one.max(error);
+                }
+            }
+            worst.push(worst_past_one);
+        }
+
+        // past a denominator of 1 the seed alone is off by units, a single refinement rounds to
+        // within one
+        assert!(worst[0] > 2.0, "{:?}", worst);
+        assert!(worst[1] < 1.0 && worst[2] < 1.0, "{:?}", worst);
+    }
+
+    #[test]
+    fn refined_softmax_matches_exact_softmax() {
+        let input = known(&[2, 2, 3, 2, 2, 0, -300, 40]);
+        let softmax = |refinements| {
+            dummy_layout(
+                HybridOp::Softmax {
+                    input_scale: 128.0.into(),
+                    output_scale: (128.0 * 128.0).into(),
+                    axes: vec![0],
+                    refinements,
+                    masked: false,
+                },
+                input.clone(),
+            )
+        };
+        let exact = softmax(0);
+        for (refined, exact) in softmax(1).iter().zip(exact.iter()) {
+            assert!((refined - exact).abs() <= 3, "{} vs {}", refined, exact);
+        }
+    }
+}
+
+#[cfg(test)]
+#[cfg(all(
+    feature = "ezkl",
+    not(all(target_arch = "wasm32", target_os = "unknown"))
+))]
+mod mish_table {
+    use super::*;
+    use crate::fieldutils::{felt_to_integer_rep, integer_rep_to_felt, IntegerRep};
+
+    #[test]
+    fn entries_are_within_half_a_step_of_mish() {
+        let mish = |x: f64| x * x.exp().ln_1p().tanh();
+        for scale in [1.0, 16.0, 128.0] {
+            // the signed domain of a table over 12 bits
+            let range = (-(1 << 11) as IntegerRep, (1 << 11) as IntegerRep);
+            let inputs = Tensor::from((range.0..=range.1).map(integer_rep_to_felt::<F>));
+            let outputs = LookupOp::Mish {
+                scale: (scale as f32).into(),
+            }
+            .f::<F>(&[inputs])
+            .unwrap()
+            .output;
+            for (x, y) in (range.0..=range.1).zip(outputs.iter()) {
+                let expected = mish(x as f64 / scale);
+                let got = felt_to_integer_rep(*y) as f64 / scale;
+                // a tie could round either way, hence the slack on top of the half step
+                assert!(
+                    (got - expected).abs() <= 0.5 / scale + 1e-9,
+                    "mish({}) at scale {}: {} vs {}",
+                    x,
+                    scale,
+                    got,
+                    expected
+                );
+            }
+        }
     }
 }
@@ -13,7 +13,7 @@ use std::path::PathBuf;
 use std::str::FromStr;
 use tosubcommand::{ToFlags, ToSubcommand};
 
-use crate::{pfsys::ProofType, Commitments, RunArgs};
+use crate::{graph::OutputFormat, pfsys::ProofType, Commitments, RunArgs};
 
 use crate::circuit::CheckMode;
 use crate::graph::TestDataSource;
@@ -41,6 +41,8 @@ pub const DEFAULT_VK_AGGREGATED: &str = "vk_aggr.key";
 pub const DEFAULT_PROOF: &str = "proof.json";
 /// The default path to the proof file for aggregated proofs
 pub const DEFAULT_PROOF_AGGREGATED: &str = "proof_aggr.json";
+/// The default directory for the proofs of a batch split into chunks
+pub const DEFAULT_BATCH_PROOF_DIR: &str = "batch_proofs";
 /// Default for whether to split proofs
 pub const DEFAULT_SPLIT: &str = "false";
 /// Default verifier abi
@@ -91,10 +93,28 @@ pub const DEFAULT_VK_ABI: &str = "vk.abi";
 pub const DEFAULT_SCALE_REBASE_MULTIPLIERS: &str = "1,2,10";
 /// Default use reduced srs for verification
 pub const DEFAULT_USE_REDUCED_SRS_FOR_VERIFICATION: &str = "false";
+/// Default error allowed on expected outputs, relative to their values
+pub const DEFAULT_OUTPUT_RTOL: &str = "0.01";
 /// Default only check for range check rebase
 pub const DEFAULT_ONLY_RANGE_CHECK_REBASE: &str = "false";
+/// Default for whether to compare a trace against float execution
+pub const DEFAULT_COMPARE_FLOAT: &str = "false";
+/// Default for whether to check the laid out witness against the forward pass
+pub const DEFAULT_CHECK_WITNESS: &str = "false";
+/// Default for whether to compare the cost of each relu implementation
+pub const DEFAULT_COMPARE_RELU_IMPLS: &str = "false";
 /// Default commitment
 pub const DEFAULT_COMMITMENT: &str = "kzg";
+/// Default path to save a model's weights root
+pub const DEFAULT_WEIGHTS_ROOT: &str = "weights_root.json";
+/// Default path to save a dataset's commitment
+pub const DEFAULT_DATASET_COMMITMENT: &str = "dataset_commitment.json";
+/// Default directory to export a standalone verifier crate to
+pub const DEFAULT_VERIFIER_CRATE: &str = "verifier";
+/// Default name of an exported verifier crate
+pub const DEFAULT_VERIFIER_CRATE_NAME: &str = "model_verifier";
+/// Default path to the advice cells logged while proving
+pub const DEFAULT_WITNESS_LOG: &str = "witness.log";
 
 #[cfg(feature = "python-bindings")]
 /// Converts TranscriptType into a PyObject (Required for TranscriptType to be compatible with Python)
@@ -421,11 +441,55 @@ pub enum Commands {
         #[clap(flatten)]
         args: RunArgs,
     },
+    /// Prints the metadata embedded in a key or proof file (ops, shapes, scales, run args, version).
+    /// Its hash catches accidental corruption, not tampering: the metadata isn't bound into the keys or proof
+    Inspect {
+        /// The path to the proving key, verification key or proof file
+        #[arg(value_hint = clap::ValueHint::FilePath)]
+        artifact: PathBuf,
+    },
+    /// Decodes the public outputs of a proof using the metadata embedded in it, so it needs no model or settings file
+    DecodeOutputs {
+        /// The path to the proof file (generated using the prove command)
+        #[arg(long, default_value = DEFAULT_PROOF, value_hint = clap::ValueHint::FilePath)]
+        proof_path: Option<PathBuf>,
+        /// Decode the outputs as field elements, rescaled floats, or the argmax labels of a classifier.
+        /// Give a single format for all outputs, or a comma separated format per output, e.g. `float,labels`
+        #[arg(long, default_value = "field", value_enum, value_delimiter = ',', value_hint = clap::ValueHint::Other)]
+        output_format: Vec<OutputFormat>,
+    },
+    /// Runs the quantized forward pass on an input and reports every node's outputs
+    Trace {
+        /// The path to the .onnx model file
+        #[arg(short = 'M', long, default_value = DEFAULT_MODEL, value_hint = clap::ValueHint::FilePath)]
+        model: Option<PathBuf>,
+        /// The path to the .json data file
+        #[arg(short = 'D', long, default_value = DEFAULT_DATA, value_hint = clap::ValueHint::FilePath)]
+        data: Option<PathBuf>,
+        /// Path to write the trace to, as json
+        #[arg(short = 'O', long, value_hint = clap::ValueHint::FilePath)]
+        output: Option<PathBuf>,
+        /// Also run the model in floating point and report each node's largest divergence from it
+        #[arg(long, default_value = DEFAULT_COMPARE_FLOAT, action = clap::ArgAction::SetTrue)]
+        compare_float: Option<bool>,
+        /// proving arguments
+        #[clap(flatten)]
+        args: RunArgs,
+    },
+    /// Lists each op type of a model with its node count and whether it's supported, checked against the nodes' attributes
+    Coverage {
+        /// The path to the .onnx model file
+        #[arg(short = 'M', long, default_value = DEFAULT_MODEL, value_hint = clap::ValueHint::FilePath)]
+        model: Option<PathBuf>,
+        /// Path to write the report to, as json
+        #[arg(short = 'O', long, value_hint = clap::ValueHint::FilePath)]
+        output: Option<PathBuf>,
+    },
 
     /// Generates the witness from an input file.
     GenWitness {
-        /// The path to the .json data file
-        #[arg(short = 'D', long, default_value = DEFAULT_DATA, value_hint = clap::ValueHint::FilePath)]
+        /// The path to the .json data file, to a .npy file or to a directory of <input name>.npy files
+        #[arg(short = 'D', long, default_value = DEFAULT_DATA, value_hint = clap::ValueHint::AnyPath)]
         data: Option<PathBuf>,
         /// The path to the compiled model file (generated using the compile-circuit command)
         #[arg(short = 'M', long, default_value = DEFAULT_COMPILED_CIRCUIT, value_hint = clap::ValueHint::FilePath)]
@@ -439,6 +503,18 @@ pub enum Commands {
         /// Path to the srs file (optional - solely used to generate kzg commits)
         #[arg(short = 'P', long, value_hint = clap::ValueHint::FilePath)]
         srs_path: Option<PathBuf>,
+        /// Record every node's outputs in the witness, so that proving with it checks each laid out node against them
+        #[arg(long, default_value = DEFAULT_CHECK_WITNESS, action = clap::ArgAction::SetTrue)]
+        check_witness: Option<bool>,
+        /// A context (e.g a request id) to bind the proof to, whose Poseidon hash becomes a public instance (requires a circuit compiled with --bind-context)
+        #[arg(long, value_hint = clap::ValueHint::Other)]
+        context_hash: Option<String>,
+        /// The dataset commitment (generated using the commit-dataset command) the input is a row of, if the circuit was compiled with --dataset-depth
+        #[arg(long, requires = "dataset_index", value_hint = clap::ValueHint::FilePath)]
+        dataset: Option<PathBuf>,
+        /// The index of the input's row in the dataset, which becomes a public instance
+        #[arg(long, requires = "dataset", value_hint = clap::ValueHint::Other)]
+        dataset_index: Option<usize>,
     },
 
     /// Produces the proving hyperparameters, from run-args
@@ -449,6 +525,9 @@ pub enum Commands {
         /// The path to generate the circuit settings .json file to
         #[arg(short = 'O', long, default_value = DEFAULT_SETTINGS, value_hint = clap::ValueHint::FilePath)]
         settings_path: Option<PathBuf>,
+        /// Also lay out a model with relus under each relu implementation and report what each costs
+        #[arg(long, default_value = DEFAULT_COMPARE_RELU_IMPLS, action = clap::ArgAction::SetTrue)]
+        compare_relu_impls: Option<bool>,
         /// proving arguments
         #[clap(flatten)]
         args: RunArgs,
@@ -523,12 +602,34 @@ pub enum Commands {
     },
     /// Loads model and input and runs mock prover (for testing)
     Mock {
-        /// The path to the .json witness file (generated using the gen-witness command)
+        /// The path to the .json witness file (generated using the gen-witness command), or to inputs read by name as gen-witness reads them (a .npy file or a directory of <input name>.npy files)
+        #[arg(short = 'W', long, default_value = DEFAULT_WITNESS, value_hint = clap::ValueHint::AnyPath)]
+        witness: Option<PathBuf>,
+        /// The path to the compiled model file (generated using the compile-circuit command)
+        #[arg(short = 'M', long, default_value = DEFAULT_COMPILED_CIRCUIT, value_hint = clap::ValueHint::FilePath)]
+        model: Option<PathBuf>,
+        /// The path to a .json file of the float outputs (`output_data`) to check the outputs against, e.g. the model's input.json
+        #[arg(long, value_hint = clap::ValueHint::FilePath)]
+        expected_output: Option<PathBuf>,
+        /// The absolute error allowed on each expected output, one quantization step at the output's scale if unset
+        #[arg(long, requires = "expected_output", value_hint = clap::ValueHint::Other)]
+        output_atol: Option<f64>,
+        /// The error allowed on each expected output, relative to its value
+        #[arg(long, default_value = DEFAULT_OUTPUT_RTOL, value_hint = clap::ValueHint::Other)]
+        output_rtol: Option<f64>,
+    },
+
+    /// Mock proves a circuit against the advice cells logged by prove --debug-witness and reports the first constraint they violate, with the nodes it belongs to
+    Replay {
+        /// The path to the .json witness file the log was recorded with
         #[arg(short = 'W', long, default_value = DEFAULT_WITNESS, value_hint = clap::ValueHint::FilePath)]
         witness: Option<PathBuf>,
         /// The path to the compiled model file (generated using the compile-circuit command)
         #[arg(short = 'M', long, default_value = DEFAULT_COMPILED_CIRCUIT, value_hint = clap::ValueHint::FilePath)]
         model: Option<PathBuf>,
+        /// The path to the log of advice cells (generated using prove --debug-witness)
+        #[arg(long, default_value = DEFAULT_WITNESS_LOG, value_hint = clap::ValueHint::FilePath)]
+        log: Option<PathBuf>,
     },
 
     /// Mock aggregate proofs
@@ -619,6 +720,27 @@ pub enum Commands {
         #[arg(short = 'S', long, default_value = DEFAULT_SETTINGS, value_hint = clap::ValueHint::FilePath)]
         settings_path: Option<PathBuf>,
     },
+    /// Computes the Merkle root a compiled model's params are committed to under `--param-visibility merkle`
+    CommitWeights {
+        /// The path to the compiled model file (generated using the compile-circuit command)
+        #[arg(short = 'M', long, default_value = DEFAULT_COMPILED_CIRCUIT, value_hint = clap::ValueHint::FilePath)]
+        compiled_circuit: Option<PathBuf>,
+        /// The path to save the root to
+        #[arg(short = 'O', long, default_value = DEFAULT_WEIGHTS_ROOT, value_hint = clap::ValueHint::FilePath)]
+        output: Option<PathBuf>,
+    },
+    /// Commits to a dataset of inputs with a Poseidon Merkle tree, whose rows circuits compiled with `--dataset-depth` prove their input is one of
+    CommitDataset {
+        /// The path to a .json list of the dataset's rows, each in the format of a data file's `input_data`
+        #[arg(short = 'D', long, value_hint = clap::ValueHint::FilePath)]
+        data: PathBuf,
+        /// The path to the compiled model file (generated using the compile-circuit command)
+        #[arg(short = 'M', long, default_value = DEFAULT_COMPILED_CIRCUIT, value_hint = clap::ValueHint::FilePath)]
+        compiled_circuit: Option<PathBuf>,
+        /// The path to save the root and each row's opening to
+        #[arg(short = 'O', long, default_value = DEFAULT_DATASET_COMMITMENT, value_hint = clap::ValueHint::FilePath)]
+        output: Option<PathBuf>,
+    },
     /// Creates pk and vk
     Setup {
         /// The path to the compiled model file (generated using the compile-circuit command)
@@ -716,6 +838,43 @@ pub enum Commands {
         /// run sanity checks during calculations (safe or unsafe)
         #[arg(long, default_value = DEFAULT_CHECKMODE, value_hint = clap::ValueHint::Other)]
         check_mode: Option<CheckMode>,
+        /// Abort the proof if it hasn't finished after this many seconds
+        #[arg(long, value_hint = clap::ValueHint::Other)]
+        timeout: Option<u64>,
+        /// Log every advice cell assigned while proving to this file, to replay it with the replay command if the proof fails or doesn't verify. Slows proving down
+        #[arg(long, value_hint = clap::ValueHint::FilePath)]
+        debug_witness: Option<PathBuf>,
+    },
+        /// Splits a batch larger than the circuit's batch size into chunks and proves each chunk with the same key
+    #[command(name = "prove-batch")]
+    ProveBatch {
+        /// The path to the .json data file, holding any number of samples
+        #[arg(short = 'D', long, default_value = DEFAULT_DATA, value_hint = clap::ValueHint::FilePath)]
+        data: Option<PathBuf>,
+        /// The path to the compiled model file (generated using the compile-circuit command)
+        #[arg(short = 'M', long, default_value = DEFAULT_COMPILED_CIRCUIT, value_hint = clap::ValueHint::FilePath)]
+        compiled_circuit: Option<PathBuf>,
+        /// The path to load the desired proving key file (generated using the setup command)
+        #[arg(long, default_value = DEFAULT_PK, value_hint = clap::ValueHint::FilePath)]
+        pk_path: Option<PathBuf>,
+        /// The directory to output the chunks' witnesses and proofs, and a manifest listing them, to
+        #[arg(long, default_value = DEFAULT_BATCH_PROOF_DIR, value_hint = clap::ValueHint::DirPath)]
+        proof_dir: Option<PathBuf>,
+        /// The path to SRS, if None will use ~/.ezkl/srs/kzg{logrows}.srs
+        #[arg(long, value_hint = clap::ValueHint::FilePath)]
+        srs_path: Option<PathBuf>,
+        #[arg(
+            long,
+            require_equals = true,
+            num_args = 0..=1,
+            default_value_t = ProofType::Single,
+            value_enum,
+            value_hint = clap::ValueHint::Other
+        )]
+        proof_type: ProofType,
+        /// run sanity checks during calculations (safe or unsafe)
+        #[arg(long, default_value = DEFAULT_CHECKMODE, value_hint = clap::ValueHint::Other)]
+        check_mode: Option<CheckMode>,
     },
         /// Encodes a proof into evm calldata
     #[command(name = "encode-evm-calldata")]
@@ -751,6 +910,25 @@ pub enum Commands {
         /// Whether the to render the verifier as reusable or not. If true, you will need to deploy a VK artifact, passing it as part of the calldata to the verifier.
         #[arg(long, default_value = DEFAULT_RENDER_REUSABLE, action = clap::ArgAction::SetTrue)]
         reusable: Option<bool>,
+    },
+        /// Exports a standalone rust crate verifying single proofs of the circuit, depending only on the halo2 verifier
+    #[command(name = "export-verifier")]
+    ExportVerifier {
+        /// The path to SRS, if None will use ~/.ezkl/srs/kzg{logrows}.srs
+        #[arg(long, value_hint = clap::ValueHint::FilePath)]
+        srs_path: Option<PathBuf>,
+        /// The path to load circuit settings .json file from (generated using the gen-settings command)
+        #[arg(short = 'S', long, default_value = DEFAULT_SETTINGS, value_hint = clap::ValueHint::FilePath)]
+        settings_path: Option<PathBuf>,
+        /// The path to load the desired verification key file
+        #[arg(long, default_value = DEFAULT_VK, value_hint = clap::ValueHint::FilePath)]
+        vk_path: Option<PathBuf>,
+        /// The directory to write the crate to
+        #[arg(long, default_value = DEFAULT_VERIFIER_CRATE, value_hint = clap::ValueHint::DirPath)]
+        out: Option<PathBuf>,
+        /// The name of the exported crate
+        #[arg(long, default_value = DEFAULT_VERIFIER_CRATE_NAME, value_hint = clap::ValueHint::Other)]
+        crate_name: Option<String>,
     },
         /// Creates an Evm verifier artifact for a single proof to be used by the reusable verifier
     #[command(name = "create-evm-vka")]
@@ -837,6 +1015,27 @@ pub enum Commands {
         /// Reduce SRS logrows to the number of instances rather than the number of logrows used for proofs (only works if the srs were generated in the same ceremony)
         #[arg(long, default_value = DEFAULT_USE_REDUCED_SRS_FOR_VERIFICATION, action = clap::ArgAction::SetTrue)]
         reduced_srs: Option<bool>,
+        /// The context the proof must be bound to, if the circuit was compiled with --bind-context
+        #[arg(long, value_hint = clap::ValueHint::Other)]
+        context_hash: Option<String>,
+        /// The weights root (generated using the commit-weights command) the proof must commit to, required if the params are merkle committed
+        #[arg(long, value_hint = clap::ValueHint::FilePath)]
+        weights_root: Option<PathBuf>,
+        /// The dataset commitment (generated using the commit-dataset command) the proof must open a row of, if the circuit was compiled with --dataset-depth
+        #[arg(long, value_hint = clap::ValueHint::FilePath)]
+        dataset: Option<PathBuf>,
+        /// The path to a .json file of the public inputs and outputs (`input_data`, `output_data`) the proof is checked against, in place of the ones it carries
+        #[arg(long, value_hint = clap::ValueHint::FilePath)]
+        instances: Option<PathBuf>,
+        /// The path to a .json file of the float outputs (`output_data`) to check the outputs against, e.g. the model's input.json
+        #[arg(long, value_hint = clap::ValueHint::FilePath)]
+        expected_output: Option<PathBuf>,
+        /// The absolute error allowed on each expected output, one quantization step at the output's scale if unset
+        #[arg(long, requires = "expected_output", value_hint = clap::ValueHint::Other)]
+        output_atol: Option<f64>,
+        /// The error allowed on each expected output, relative to its value
+        #[arg(long, default_value = DEFAULT_OUTPUT_RTOL, value_hint = clap::ValueHint::Other)]
+        output_rtol: Option<f64>,
     },
     /// Verifies an aggregate proof, returning accept or reject
     VerifyAggr {
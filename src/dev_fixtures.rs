@@ -0,0 +1,1603 @@
+//! Small onnx models built in code, so tests can generate the fixtures they need rather than
+//! depend on files written by scripts run out of tree.
+//!
+//! Models are encoded with the protobuf writer the graph passes use, covering the parts of the
+//! onnx schema the fixtures need. Weights and inputs are drawn from a seeded generator, so every
+//! run writes the same bytes.
+
+use crate::graph::errors::GraphError;
+use crate::graph::input::{DataSource, FileSourceInner, GraphData};
+use crate::graph::proto::{
+    write_field, write_varint, ATTRIBUTE_F, ATTRIBUTE_GRAPH, ATTRIBUTE_I, ATTRIBUTE_INTS,
+    ATTRIBUTE_NAME, ATTRIBUTE_S, ATTRIBUTE_TYPE, DIM_VALUE, FLOAT, GRAPH_INITIALIZER, GRAPH_INPUT,
+    GRAPH_NAME, GRAPH_NODE, GRAPH_OUTPUT, INT64, MODEL_GRAPH, MODEL_IR_VERSION, MODEL_OPSET_IMPORT,
+    MODEL_PRODUCER_NAME, NODE_ATTRIBUTE, NODE_INPUT, NODE_NAME, NODE_OP_TYPE, NODE_OUTPUT,
+    OPSET_DOMAIN, OPSET_VERSION, SHAPE_DIM, TENSOR_DATA_TYPE, TENSOR_DIMS, TENSOR_NAME,
+    TENSOR_RAW_DATA, TENSOR_TYPE_ELEM_TYPE, TENSOR_TYPE_SHAPE, TYPE_TENSOR_TYPE, VALUE_INFO_NAME,
+    VALUE_INFO_TYPE, WIRE_FIXED32, WIRE_LEN, WIRE_VARINT,
+};
+use std::path::{Path, PathBuf};
+
+/// The onnx opset fixtures are written against.
+pub const OPSET: i64 = 13;
+
+/// The directory fixtures are written to, `target/fixtures` under the target dir in use.
+pub fn fixtures_dir() -> PathBuf {
+    std::env::var_os("CARGO_TARGET_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| Path::new(env!("CARGO_MANIFEST_DIR")).join("target"))
+        .join("fixtures")
+}
+
+/// A protobuf message under construction, holding its encoded fields.
+#[derive(Clone, Debug, Default, PartialEq)]
+struct Message(Vec<u8>);
+
+impl Message {
+    fn field(mut self, number: u64, wire_type: u64, value: &[u8]) -> Self {
+        write_field(&mut self.0, number, wire_type, value);
+        self
+    }
+
+    /// An int field, negative values encoded as their two's complement as protobuf does.
+    fn int(self, number: u64, value: i64) -> Self {
+        let mut varint = vec![];
+        write_varint(&mut varint, value as u64);
+        self.field(number, WIRE_VARINT, &varint)
+    }
+
+    fn float(self, number: u64, value: f32) -> Self {
+        self.field(number, WIRE_FIXED32, &value.to_le_bytes())
+    }
+
+    fn string(self, number: u64, value: &str) -> Self {
+        self.field(number, WIRE_LEN, value.as_bytes())
+    }
+
+    fn message(self, number: u64, value: Message) -> Self {
+        self.field(number, WIRE_LEN, &value.0)
+    }
+}
+
+/// An attribute of an onnx node.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Attribute {
+    /// A single int, e.g. `transB`
+    Int(i64),
+    /// A list of ints, e.g. `pads`
+    Ints(Vec<i64>),
+    /// A single float, e.g. `alpha`
+    Float(f32),
+    /// A string, e.g. `auto_pad`
+    String(String),
+    /// A subgraph, e.g. the `then_branch` of an `If`
+    Graph(OnnxModel),
+}
+
+impl Attribute {
+    fn encode(&self, name: &str) -> Message {
+        // each kind of value along with its AttributeProto.AttributeType
+        let attr = Message::default().string(ATTRIBUTE_NAME, name);
+        match self {
+            Attribute::Float(f) => attr.float(ATTRIBUTE_F, *f).int(ATTRIBUTE_TYPE, 1),
+            Attribute::Int(i) => attr.int(ATTRIBUTE_I, *i).int(ATTRIBUTE_TYPE, 2),
+            Attribute::String(s) => attr.string(ATTRIBUTE_S, s).int(ATTRIBUTE_TYPE, 3),
+            Attribute::Graph(g) => attr
+                .message(ATTRIBUTE_GRAPH, g.graph())
+                .int(ATTRIBUTE_TYPE, 5),
+            Attribute::Ints(ints) => ints
+                .iter()
+                .fold(attr, |a, i| a.int(ATTRIBUTE_INTS, *i))
+                .int(ATTRIBUTE_TYPE, 7),
+        }
+    }
+}
+
+/// An onnx model built node by node, with float inputs, outputs and initializers.
+#[derive(Clone, Debug, PartialEq)]
+pub struct OnnxModel {
+    name: String,
+    nodes: Vec<Message>,
+    initializers: Vec<Message>,
+    inputs: Vec<(String, Vec<usize>)>,
+    outputs: Vec<(String, Vec<usize>)>,
+}
+
+impl OnnxModel {
+    /// An empty model whose graph is called `name`.
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            nodes: vec![],
+            initializers: vec![],
+            inputs: vec![],
+            outputs: vec![],
+        }
+    }
+
+    /// Adds a graph input.
+    pub fn input(&mut self, name: &str, dims: &[usize]) -> &mut Self {
+        self.inputs.push((name.to_string(), dims.to_vec()));
+        self
+    }
+
+    /// Adds a graph output, which some node must produce.
+    pub fn output(&mut self, name: &str, dims: &[usize]) -> &mut Self {
+        self.outputs.push((name.to_string(), dims.to_vec()));
+        self
+    }
+
+    /// Adds a constant float tensor.
+    pub fn initializer(&mut self, name: &str, dims: &[usize], values: &[f32]) -> &mut Self {
+        assert_eq!(dims.iter().product::<usize>(), values.len(), "{}", name);
+        let data = values
+            .iter()
+            .flat_map(|v| v.to_le_bytes())
+            .collect::<Vec<_>>();
+        let tensor = dims
+            .iter()
+            .fold(Message::default(), |t, d| t.int(TENSOR_DIMS, *d as i64))
+            .int(TENSOR_DATA_TYPE, FLOAT as i64)
+            .string(TENSOR_NAME, name)
+            .field(TENSOR_RAW_DATA, WIRE_LEN, &data);
+        self.initializers.push(tensor);
+        self
+    }
+
+    /// Adds a constant int64 tensor, such as the target shape of a Reshape.
+    pub fn int_initializer(&mut self, name: &str, dims: &[usize], values: &[i64]) -> &mut Self {
+        assert_eq!(dims.iter().product::<usize>(), values.len(), "{}", name);
+        let data = values
+            .iter()
+            .flat_map(|v| v.to_le_bytes())
+            .collect::<Vec<_>>();
+        let tensor = dims
+            .iter()
+            .fold(Message::default(), |t, d| t.int(TENSOR_DIMS, *d as i64))
+            .int(TENSOR_DATA_TYPE, INT64 as i64)
+            .string(TENSOR_NAME, name)
+            .field(TENSOR_RAW_DATA, WIRE_LEN, &data);
+        self.initializers.push(tensor);
+        self
+    }
+
+    /// Adds a node, named after its op and position in the graph.
+    pub fn node(
+        &mut self,
+        op: &str,
+        inputs: &[&str],
+        outputs: &[&str],
+        attributes: &[(&str, Attribute)],
+    ) -> &mut Self {
+        let name = format!("{}_{}", op.to_lowercase(), self.nodes.len());
+        let mut node = Message::default();
+        for input in inputs {
+            node = node.string(NODE_INPUT, input);
+        }
+        for output in outputs {
+            node = node.string(NODE_OUTPUT, output);
+        }
+        node = node.string(NODE_NAME, &name).string(NODE_OP_TYPE, op);
+        for (attr_name, attr) in attributes {
+            node = node.message(NODE_ATTRIBUTE, attr.encode(attr_name));
+        }
+        self.nodes.push(node);
+        self
+    }
+
+    fn value_info(name: &str, dims: &[usize]) -> Message {
+        let shape = dims.iter().fold(Message::default(), |s, d| {
+            s.message(SHAPE_DIM, Message::default().int(DIM_VALUE, *d as i64))
+        });
+        let tensor_type = Message::default()
+            .int(TENSOR_TYPE_ELEM_TYPE, FLOAT as i64)
+            .message(TENSOR_TYPE_SHAPE, shape);
+        Message::default().string(VALUE_INFO_NAME, name).message(
+            VALUE_INFO_TYPE,
+            Message::default().message(TYPE_TENSOR_TYPE, tensor_type),
+        )
+    }
+
+    fn graph(&self) -> Message {
+        let mut graph = Message::default();
+        for node in &self.nodes {
+            graph = graph.message(GRAPH_NODE, node.clone());
+        }
+        graph = graph.string(GRAPH_NAME, &self.name);
+        for initializer in &self.initializers {
+            graph = graph.message(GRAPH_INITIALIZER, initializer.clone());
+        }
+        for (name, dims) in &self.inputs {
+            graph = graph.message(GRAPH_INPUT, Self::value_info(name, dims));
+        }
+        for (name, dims) in &self.outputs {
+            graph = graph.message(GRAPH_OUTPUT, Self::value_info(name, dims));
+        }
+        graph
+    }
+
+    /// Encodes the model as an onnx ModelProto.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let opset = Message::default()
+            .string(OPSET_DOMAIN, "")
+            .int(OPSET_VERSION, OPSET);
+        Message::default()
+            .int(MODEL_IR_VERSION, 8)
+            .string(MODEL_PRODUCER_NAME, "ezkl")
+            .message(MODEL_GRAPH, self.graph())
+            .message(MODEL_OPSET_IMPORT, opset)
+            .0
+    }
+}
+
+/// A seeded generator for weights and inputs. Values are small multiples of a power of two, so
+/// they're exact at ezkl's default scales.
+#[derive(Clone, Debug)]
+pub struct Lcg(u64);
+
+impl Lcg {
+    /// A generator seeded with `seed`.
+    pub fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    /// The next value, a multiple of `1 / denominator` in `[-8 / denominator, 8 / denominator]`.
+    pub fn next_value(&mut self, denominator: f32) -> f32 {
+        self.0 = self
+            .0
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        ((self.0 >> 33) % 17) as f32 / denominator - 8.0 / denominator
+    }
+
+    /// `len` values, see [Lcg::next_value].
+    pub fn values(&mut self, len: usize, denominator: f32) -> Vec<f32> {
+        (0..len).map(|_| self.next_value(denominator)).collect()
+    }
+}
+
+/// A generated model, and the input it's run on.
+#[derive(Clone, Debug)]
+pub struct Fixture {
+    /// Names the fixture's directory, unique to the model's parameters
+    pub name: String,
+    /// The model
+    pub model: OnnxModel,
+    /// The dims of each of the model's inputs
+    pub input_shapes: Vec<Vec<usize>>,
+}
+
+impl Fixture {
+    /// A seeded input for each of the model's inputs, in multiples of 1/16.
+    pub fn input_data(&self) -> GraphData {
+        let mut rng = Lcg::new(0);
+        let inputs = self
+            .input_shapes
+            .iter()
+            .map(|dims| {
+                rng.values(dims.iter().product(), 16.0)
+                    .into_iter()
+                    .map(|x| FileSourceInner::new_float(x as f64))
+                    .collect()
+            })
+            .collect();
+        GraphData::new(DataSource::File(inputs))
+    }
+
+    /// A seeded input of raw 8-bit pixel values for each of the model's inputs, whole numbers
+    /// in `[0, 255]`.
+    pub fn pixel_data(&self) -> GraphData {
+        let mut rng = Lcg::new(0);
+        let inputs = self
+            .input_shapes
+            .iter()
+            .map(|dims| {
+                rng.values(dims.iter().product(), 1.0)
+                    .into_iter()
+                    .map(|x| FileSourceInner::new_float(((x as f64 + 8.0) * 255.0 / 16.0).round()))
+                    .collect()
+            })
+            .collect();
+        GraphData::new(DataSource::File(inputs))
+    }
+
+    /// Writes the fixture's `network.onnx` and `input.json` to its directory under
+    /// [fixtures_dir], returning the directory.
+    pub fn write(&self) -> Result<PathBuf, GraphError> {
+        let dir = fixtures_dir().join(&self.name);
+        std::fs::create_dir_all(&dir).map_err(|e| {
+            GraphError::ReadWriteFileError(dir.display().to_string(), e.to_string())
+        })?;
+        // tests running at once may write the same fixture, so each write lands whole
+        let write = |file: &str, bytes: &[u8]| {
+            let path = dir.join(file);
+            let tmp = dir.join(format!("{}.{}.tmp", file, std::process::id()));
+            std::fs::write(&tmp, bytes)
+                .and_then(|_| std::fs::rename(&tmp, &path))
+                .map_err(|e| {
+                    GraphError::ReadWriteFileError(path.display().to_string(), e.to_string())
+                })
+        };
+        write("network.onnx", &self.model.to_bytes())?;
+        write("input.json", &serde_json::to_vec(&self.input_data())?)?;
+        Ok(dir)
+    }
+}
+
+fn name_of(prefix: &str, params: &[usize]) -> String {
+    params
+        .iter()
+        .fold(prefix.to_string(), |name, p| format!("{}_{}", name, p))
+}
+
+fn ints(values: &[usize]) -> Attribute {
+    Attribute::Ints(values.iter().map(|v| *v as i64).collect())
+}
+
+/// Adds a Conv of `kernel` with its seeded weights and bias, returning the output's name.
+fn conv(
+    model: &mut OnnxModel,
+    rng: &mut Lcg,
+    input: &str,
+    channels: (usize, usize),
+    kernel: usize,
+    attributes: &[(&str, Attribute)],
+) -> String {
+    let idx = model.nodes.len();
+    let (weight, bias, output) = (
+        format!("w{}", idx),
+        format!("b{}", idx),
+        format!("conv{}", idx),
+    );
+    let (c_in, c_out) = channels;
+    let dims = [c_out, c_in, kernel, kernel];
+    model.initializer(&weight, &dims, &rng.values(dims.iter().product(), 16.0));
+    model.initializer(&bias, &[c_out], &rng.values(c_out, 16.0));
+    let mut attributes = attributes.to_vec();
+    attributes.push(("kernel_shape", ints(&[kernel, kernel])));
+    model.node("Conv", &[input, &weight, &bias], &[&output], &attributes);
+    output
+}
+
+/// Adds a Relu, returning the output's name.
+fn relu(model: &mut OnnxModel, input: &str) -> String {
+    let output = format!("relu{}", model.nodes.len());
+    model.node("Relu", &[input], &[&output], &[]);
+    output
+}
+
+/// An MLP taking `[1, dims[0]]` through MatMul, Add and Relu layers of each of the following
+/// widths, without a Relu on the last.
+pub fn mlp(dims: &[usize]) -> Fixture {
+    assert!(dims.len() > 1, "an mlp needs an input and output width");
+    let name = name_of("mlp", dims);
+    let mut rng = Lcg::new(dims.len() as u64);
+    let mut model = OnnxModel::new(&name);
+    model.input("input", &[1, dims[0]]);
+    let mut x = "input".to_string();
+    for (i, pair) in dims.windows(2).enumerate() {
+        let (weight, bias, matmul, add) = (
+            format!("w{}", i),
+            format!("b{}", i),
+            format!("matmul{}", i),
+            format!("add{}", i),
+        );
+        model.initializer(&weight, pair, &rng.values(pair[0] * pair[1], 16.0));
+        model.initializer(&bias, &[pair[1]], &rng.values(pair[1], 16.0));
+        model.node("MatMul", &[&x, &weight], &[&matmul], &[]);
+        model.node("Add", &[&matmul, &bias], &[&add], &[]);
+        x = match i + 2 == dims.len() {
+            true => add,
+            false => relu(&mut model, &add),
+        };
+    }
+    model.output(&x, &[1, dims[dims.len() - 1]]);
+    Fixture {
+        name,
+        model,
+        input_shapes: vec![vec![1, dims[0]]],
+    }
+}
+
+/// A chain of `depth` Relus over a `[1, width]` input, the deepest a model gets for its width.
+pub fn relu_chain(width: usize, depth: usize) -> Fixture {
+    let name = name_of("relu_chain", &[width, depth]);
+    let mut model = OnnxModel::new(&name);
+    model.input("input", &[1, width]);
+    let mut x = "input".to_string();
+    for _ in 0..depth {
+        x = relu(&mut model, &x);
+    }
+    model.output(&x, &[1, width]);
+    Fixture {
+        name,
+        model,
+        input_shapes: vec![vec![1, width]],
+    }
+}
+
+/// Convs of a square `kernel`, each followed by a Relu, taking a `[1, channels[0], hw, hw]` image
+/// to each of the following channel counts. Convs are padded to keep the image size.
+pub fn conv_stack(channels: &[usize], hw: usize, kernel: usize) -> Fixture {
+    assert!(
+        channels.len() > 1,
+        "a conv stack needs an input and output channel count"
+    );
+    assert!(kernel % 2 == 1, "an odd kernel keeps the image size");
+    let name = name_of("conv_stack", &[channels, &[hw, kernel][..]].concat());
+    let mut rng = Lcg::new(channels.len() as u64);
+    let mut model = OnnxModel::new(&name);
+    model.input("input", &[1, channels[0], hw, hw]);
+    let mut x = "input".to_string();
+    let pad = kernel / 2;
+    for pair in channels.windows(2) {
+        let out = conv(
+            &mut model,
+            &mut rng,
+            &x,
+            (pair[0], pair[1]),
+            kernel,
+            &[("pads", ints(&[pad; 4]))],
+        );
+        x = relu(&mut model, &out);
+    }
+    model.output(&x, &[1, channels[channels.len() - 1], hw, hw]);
+    Fixture {
+        name,
+        model,
+        input_shapes: vec![vec![1, channels[0], hw, hw]],
+    }
+}
+
+/// A 3x3 conv from `channels.0` to `channels.1` channels on a `[1, channels.0, hw, hw]` image,
+/// followed by a BatchNormalization in inference mode and a Relu, as PyTorch exports a conv block
+/// in eval mode.
+pub fn conv_batch_norm(channels: (usize, usize), hw: usize) -> Fixture {
+    let (c_in, c_out) = channels;
+    let name = name_of("conv_batch_norm", &[c_in, c_out, hw]);
+    let mut rng = Lcg::new(10);
+    let mut model = OnnxModel::new(&name);
+    model.input("input", &[1, c_in, hw, hw]);
+    let x = conv(
+        &mut model,
+        &mut rng,
+        "input",
+        channels,
+        3,
+        &[("pads", ints(&[1; 4]))],
+    );
+    let gamma = rng.values(c_out, 8.0);
+    let beta = rng.values(c_out, 16.0);
+    let mean = rng.values(c_out, 16.0);
+    // variances are positive
+    let var = rng
+        .values(c_out, 16.0)
+        .into_iter()
+        .map(|v| v.abs() + 0.25)
+        .collect::<Vec<_>>();
+    for (param, values) in [
+        ("gamma", gamma),
+        ("beta", beta),
+        ("mean", mean),
+        ("var", var),
+    ] {
+        model.initializer(param, &[c_out], &values);
+    }
+    model.node(
+        "BatchNormalization",
+        &[&x, "gamma", "beta", "mean", "var"],
+        &["normalized"],
+        &[("epsilon", Attribute::Float(1e-5))],
+    );
+    let x = relu(&mut model, "normalized");
+    model.output(&x, &[1, c_out, hw, hw]);
+    Fixture {
+        name,
+        model,
+        input_shapes: vec![vec![1, c_in, hw, hw]],
+    }
+}
+
+/// A residual block on a `[1, channels, hw, hw]` image: two 3x3 convs with a Relu between them,
+/// the input added back in, and a final Relu.
+pub fn residual_block(channels: usize, hw: usize) -> Fixture {
+    let name = name_of("residual_block", &[channels, hw]);
+    let mut rng = Lcg::new(channels as u64);
+    let mut model = OnnxModel::new(&name);
+    let dims = [1, channels, hw, hw];
+    model.input("input", &dims);
+    let pads = [("pads", ints(&[1; 4]))];
+    let x = conv(
+        &mut model,
+        &mut rng,
+        "input",
+        (channels, channels),
+        3,
+        &pads,
+    );
+    let x = relu(&mut model, &x);
+    let x = conv(&mut model, &mut rng, &x, (channels, channels), 3, &pads);
+    model.node("Add", &[&x, "input"], &["skip"], &[]);
+    let x = relu(&mut model, "skip");
+    model.output(&x, &dims);
+    Fixture {
+        name,
+        model,
+        input_shapes: vec![dims.to_vec()],
+    }
+}
+
+/// The simplest skip connection, `relu(conv(x)) + x`, on a `[1, channels, hw, hw]` image: the
+/// Add reads the Relu's output at the conv's scale and the input at the input's.
+pub fn conv_skip(channels: usize, hw: usize) -> Fixture {
+    let name = name_of("conv_skip", &[channels, hw]);
+    let mut rng = Lcg::new(11);
+    let mut model = OnnxModel::new(&name);
+    let dims = [1, channels, hw, hw];
+    model.input("input", &dims);
+    let x = conv(
+        &mut model,
+        &mut rng,
+        "input",
+        (channels, channels),
+        3,
+        &[("pads", ints(&[1; 4]))],
+    );
+    let x = relu(&mut model, &x);
+    model.node("Add", &[&x, "input"], &["output"], &[]);
+    model.output("output", &dims);
+    Fixture {
+        name,
+        model,
+        input_shapes: vec![dims.to_vec()],
+    }
+}
+
+/// A DenseNet block on a `[1, channels, hw, hw]` image, each layer reading the channel-wise
+/// Concat of everything before it: `f1 = relu(conv(x))`, `f2 = relu(conv([x, f1]))`, with the
+/// `[1, 3 * channels, hw, hw]` output `[x, f1, f2]`. The Concats take the input at the input's
+/// scale alongside conv features at the conv's.
+pub fn dense_block(channels: usize, hw: usize) -> Fixture {
+    let name = name_of("dense_block", &[channels, hw]);
+    let mut rng = Lcg::new(15);
+    let mut model = OnnxModel::new(&name);
+    let dims = [1, channels, hw, hw];
+    model.input("input", &dims);
+    let pads = [("pads", ints(&[1; 4]))];
+    let axis = [("axis", Attribute::Int(1))];
+    let x = conv(
+        &mut model,
+        &mut rng,
+        "input",
+        (channels, channels),
+        3,
+        &pads,
+    );
+    let f1 = relu(&mut model, &x);
+    model.node("Concat", &["input", &f1], &["stacked"], &axis);
+    let x = conv(
+        &mut model,
+        &mut rng,
+        "stacked",
+        (2 * channels, channels),
+        3,
+        &pads,
+    );
+    let f2 = relu(&mut model, &x);
+    model.node("Concat", &["input", &f1, &f2], &["output"], &axis);
+    model.output("output", &[1, 3 * channels, hw, hw]);
+    Fixture {
+        name,
+        model,
+        input_shapes: vec![dims.to_vec()],
+    }
+}
+
+/// A Gemm with a transposed `[out, in]` weight, as torch exports linear layers.
+pub fn gemm_trans_b(in_features: usize, out_features: usize) -> Fixture {
+    let name = name_of("gemm_trans_b", &[in_features, out_features]);
+    let mut rng = Lcg::new(1);
+    let mut model = OnnxModel::new(&name);
+    model.input("input", &[1, in_features]);
+    model.initializer(
+        "w",
+        &[out_features, in_features],
+        &rng.values(out_features * in_features, 16.0),
+    );
+    model.initializer("b", &[out_features], &rng.values(out_features, 16.0));
+    model.node(
+        "Gemm",
+        &["input", "w", "b"],
+        &["output"],
+        &[("transB", Attribute::Int(1))],
+    );
+    model.output("output", &[1, out_features]);
+    Fixture {
+        name,
+        model,
+        input_shapes: vec![vec![1, in_features]],
+    }
+}
+
+/// A 3x3 conv with a dilation of 2, padded to keep the `[1, channels, hw, hw]` image size. ezkl
+/// doesn't lay out dilated convs, so this is only loaded to check that it's rejected.
+pub fn dilated_conv(channels: usize, hw: usize) -> Fixture {
+    let name = name_of("dilated_conv", &[channels, hw]);
+    let mut rng = Lcg::new(2);
+    let mut model = OnnxModel::new(&name);
+    let dims = [1, channels, hw, hw];
+    model.input("input", &dims);
+    let x = conv(
+        &mut model,
+        &mut rng,
+        "input",
+        (channels, channels),
+        3,
+        &[("dilations", ints(&[2, 2])), ("pads", ints(&[2; 4]))],
+    );
+    model.output(&x, &dims);
+    Fixture {
+        name,
+        model,
+        input_shapes: vec![dims.to_vec()],
+    }
+}
+
+/// A 2x2 conv with `SAME_UPPER` auto padding, which pads the one row and column an even kernel
+/// needs after the image rather than before it.
+pub fn same_padding_conv(channels: usize, hw: usize) -> Fixture {
+    let name = name_of("same_padding_conv", &[channels, hw]);
+    let mut rng = Lcg::new(3);
+    let mut model = OnnxModel::new(&name);
+    let dims = [1, channels, hw, hw];
+    model.input("input", &dims);
+    let x = conv(
+        &mut model,
+        &mut rng,
+        "input",
+        (channels, channels),
+        2,
+        &[("auto_pad", Attribute::String("SAME_UPPER".to_string()))],
+    );
+    model.output(&x, &dims);
+    Fixture {
+        name,
+        model,
+        input_shapes: vec![dims.to_vec()],
+    }
+}
+
+/// A 3x3 conv of a `[1, channels, hw, hw]` image padded by a Pad of `mode` before it, as some
+/// exporters emit it, rather than by its own `pads`. Seeded as [padded_conv] is, so constant
+/// padding computes the same conv.
+pub fn pad_conv(channels: usize, hw: usize, mode: &str) -> Fixture {
+    let name = format!("{}_{}", name_of("pad_conv", &[channels, hw]), mode);
+    let mut rng = Lcg::new(17);
+    let mut model = OnnxModel::new(&name);
+    let dims = [1, channels, hw, hw];
+    model.input("input", &dims);
+    model.int_initializer("pads", &[8], &[0, 0, 1, 1, 0, 0, 1, 1]);
+    model.node(
+        "Pad",
+        &["input", "pads"],
+        &["padded"],
+        &[("mode", Attribute::String(mode.to_string()))],
+    );
+    let x = conv(&mut model, &mut rng, "padded", (channels, channels), 3, &[]);
+    model.output(&x, &dims);
+    Fixture {
+        name,
+        model,
+        input_shapes: vec![dims.to_vec()],
+    }
+}
+
+/// The conv of [pad_conv] with its padding built in.
+pub fn padded_conv(channels: usize, hw: usize) -> Fixture {
+    let name = name_of("padded_conv", &[channels, hw]);
+    let mut rng = Lcg::new(17);
+    let mut model = OnnxModel::new(&name);
+    let dims = [1, channels, hw, hw];
+    model.input("input", &dims);
+    let x = conv(
+        &mut model,
+        &mut rng,
+        "input",
+        (channels, channels),
+        3,
+        &[("pads", ints(&[1; 4]))],
+    );
+    model.output(&x, &dims);
+    Fixture {
+        name,
+        model,
+        input_shapes: vec![dims.to_vec()],
+    }
+}
+
+/// An unpadded 3x3 conv of the center crop of a `[1, channels, hw, hw]` image, a Slice
+/// dropping its outer rows and columns.
+pub fn cropped_conv(channels: usize, hw: usize) -> Fixture {
+    assert!(hw > 4, "the crop needs room for a 3x3 kernel");
+    let name = name_of("cropped_conv", &[channels, hw]);
+    let mut rng = Lcg::new(24);
+    let mut model = OnnxModel::new(&name);
+    let dims = [1, channels, hw, hw];
+    model.input("input", &dims);
+    let end = (hw - 1) as i64;
+    model.int_initializer("starts", &[2], &[1, 1]);
+    model.int_initializer("ends", &[2], &[end, end]);
+    model.int_initializer("axes", &[2], &[2, 3]);
+    model.node(
+        "Slice",
+        &["input", "starts", "ends", "axes"],
+        &["cropped"],
+        &[],
+    );
+    let x = conv(
+        &mut model,
+        &mut rng,
+        "cropped",
+        (channels, channels),
+        3,
+        &[],
+    );
+    model.output(&x, &[1, channels, hw - 4, hw - 4]);
+    Fixture {
+        name,
+        model,
+        input_shapes: vec![dims.to_vec()],
+    }
+}
+
+/// A padded 3x3 conv of a channels-last `[1, hw, hw, channels]` image, as tf2onnx exports it: a
+/// Transpose to channels-first before the Conv and one back to channels-last after it.
+pub fn channels_last_conv(channels: usize, hw: usize) -> Fixture {
+    let name = name_of("channels_last_conv", &[channels, hw]);
+    let mut rng = Lcg::new(25);
+    let mut model = OnnxModel::new(&name);
+    let dims = [1, hw, hw, channels];
+    model.input("input", &dims);
+    model.node(
+        "Transpose",
+        &["input"],
+        &["nchw"],
+        &[("perm", ints(&[0, 3, 1, 2]))],
+    );
+    let x = conv(
+        &mut model,
+        &mut rng,
+        "nchw",
+        (channels, channels),
+        3,
+        &[("pads", ints(&[1; 4]))],
+    );
+    model.node(
+        "Transpose",
+        &[&x],
+        &["output"],
+        &[("perm", ints(&[0, 2, 3, 1]))],
+    );
+    model.output("output", &dims);
+    Fixture {
+        name,
+        model,
+        input_shapes: vec![dims.to_vec()],
+    }
+}
+
+/// A padded 3x3 conv of a `[1, c_in, hw, hw]` image whose kernel is stored HWIO, as TensorFlow
+/// lays it out, and Transposed to the OIHW the Conv reads. tract folds the Transpose, so the conv
+/// sees an OIHW constant.
+pub fn hwio_conv(channels: (usize, usize), hw: usize) -> Fixture {
+    let (c_in, c_out) = channels;
+    let name = name_of("hwio_conv", &[c_in, c_out, hw]);
+    let mut rng = Lcg::new(26);
+    let mut model = OnnxModel::new(&name);
+    model.input("input", &[1, c_in, hw, hw]);
+    let dims = [3, 3, c_in, c_out];
+    model.initializer("w_hwio", &dims, &rng.values(dims.iter().product(), 16.0));
+    model.initializer("b", &[c_out], &rng.values(c_out, 16.0));
+    model.node(
+        "Transpose",
+        &["w_hwio"],
+        &["w"],
+        &[("perm", ints(&[3, 2, 0, 1]))],
+    );
+    model.node(
+        "Conv",
+        &["input", "w", "b"],
+        &["output"],
+        &[("kernel_shape", ints(&[3, 3])), ("pads", ints(&[1; 4]))],
+    );
+    model.output("output", &[1, c_out, hw, hw]);
+    Fixture {
+        name,
+        model,
+        input_shapes: vec![vec![1, c_in, hw, hw]],
+    }
+}
+
+/// A padded 3x3 conv of a `[1, c_in, hw, hw]` image split into `group` groups, by a
+/// `[1, c_out, c_in / group, 3, 3]` kernel with the spurious leading unit dim some exporters put
+/// on conv weights.
+pub fn unit_dim_kernel_conv(channels: (usize, usize), group: usize, hw: usize) -> Fixture {
+    let (c_in, c_out) = channels;
+    let name = name_of("unit_dim_kernel_conv", &[c_in, c_out, group, hw]);
+    let mut rng = Lcg::new(28);
+    let mut model = OnnxModel::new(&name);
+    model.input("input", &[1, c_in, hw, hw]);
+    let dims = [1, c_out, c_in / group, 3, 3];
+    model.initializer("w", &dims, &rng.values(dims.iter().product(), 16.0));
+    model.initializer("b", &[c_out], &rng.values(c_out, 16.0));
+    model.node(
+        "Conv",
+        &["input", "w", "b"],
+        &["output"],
+        &[
+            ("group", Attribute::Int(group as i64)),
+            ("kernel_shape", ints(&[3, 3])),
+            ("pads", ints(&[1; 4])),
+        ],
+    );
+    model.output("output", &[1, c_out, hw, hw]);
+    Fixture {
+        name,
+        model,
+        input_shapes: vec![vec![1, c_in, hw, hw]],
+    }
+}
+
+/// A padded 3x3 conv on a `[1, channels, hw, hw]` image followed by Mish, decomposed into
+/// `x * tanh(softplus(x))` as frameworks without a Mish op export it.
+pub fn conv_mish(channels: usize, hw: usize) -> Fixture {
+    let name = name_of("conv_mish", &[channels, hw]);
+    let mut rng = Lcg::new(4);
+    let mut model = OnnxModel::new(&name);
+    let dims = [1, channels, hw, hw];
+    model.input("input", &dims);
+    let x = conv(
+        &mut model,
+        &mut rng,
+        "input",
+        (channels, channels),
+        3,
+        &[("pads", ints(&[1; 4]))],
+    );
+    model.node("Softplus", &[&x], &["softplus"], &[]);
+    model.node("Tanh", &["softplus"], &["tanh"], &[]);
+    model.node("Mul", &[&x, "tanh"], &["mish"], &[]);
+    model.output("mish", &dims);
+    Fixture {
+        name,
+        model,
+        input_shapes: vec![dims.to_vec()],
+    }
+}
+
+/// Two padded 3x3 convs on a `[1, channels, hw, hw]` image, the first followed by a Sigmoid and
+/// the second by a Tanh: a CNN with two lookups, the Tanh's over the narrower inputs.
+pub fn conv_sigmoid_tanh(channels: usize, hw: usize) -> Fixture {
+    let name = name_of("conv_sigmoid_tanh", &[channels, hw]);
+    let mut rng = Lcg::new(27);
+    let mut model = OnnxModel::new(&name);
+    let dims = [1, channels, hw, hw];
+    model.input("input", &dims);
+    let x = conv(
+        &mut model,
+        &mut rng,
+        "input",
+        (channels, channels),
+        3,
+        &[("pads", ints(&[1; 4]))],
+    );
+    model.node("Sigmoid", &[&x], &["sigmoid"], &[]);
+    let x = conv(
+        &mut model,
+        &mut rng,
+        "sigmoid",
+        (channels, channels),
+        3,
+        &[("pads", ints(&[1; 4]))],
+    );
+    model.node("Tanh", &[&x], &["output"], &[]);
+    model.output("output", &dims);
+    Fixture {
+        name,
+        model,
+        input_shapes: vec![dims.to_vec()],
+    }
+}
+
+/// A pool `op` of a square `kernel` and `stride` on a `[1, channels, hw, hw]` image
+fn pool(
+    op: &str,
+    prefix: &str,
+    channels: usize,
+    hw: usize,
+    kernel: usize,
+    stride: usize,
+) -> Fixture {
+    let name = name_of(prefix, &[channels, hw, kernel, stride]);
+    let mut model = OnnxModel::new(&name);
+    model.input("input", &[1, channels, hw, hw]);
+    model.node(
+        op,
+        &["input"],
+        &["output"],
+        &[
+            ("kernel_shape", ints(&[kernel, kernel])),
+            ("strides", ints(&[stride, stride])),
+        ],
+    );
+    let out = (hw - kernel) / stride + 1;
+    model.output("output", &[1, channels, out, out]);
+    Fixture {
+        name,
+        model,
+        input_shapes: vec![vec![1, channels, hw, hw]],
+    }
+}
+
+/// A MaxPool of a square `kernel` and `stride` on a `[1, channels, hw, hw]` image, as between the
+/// conv blocks of a LeNet.
+pub fn max_pool(channels: usize, hw: usize, kernel: usize, stride: usize) -> Fixture {
+    pool("MaxPool", "max_pool", channels, hw, kernel, stride)
+}
+
+/// An AveragePool of a square `kernel` and `stride` on a `[1, channels, hw, hw]` image, whose
+/// division by the window size changes the output's scale.
+pub fn avg_pool(channels: usize, hw: usize, kernel: usize, stride: usize) -> Fixture {
+    pool("AveragePool", "avg_pool", channels, hw, kernel, stride)
+}
+
+/// A ResNet-style classifier head: a GlobalAveragePool of a `[1, channels, hw, hw]` feature map
+/// down to `[1, channels, 1, 1]`, flattened into a Gemm to `classes` logits.
+pub fn global_avg_pool_head(channels: usize, hw: usize, classes: usize) -> Fixture {
+    let name = name_of("global_avg_pool_head", &[channels, hw, classes]);
+    let mut rng = Lcg::new(6);
+    let mut model = OnnxModel::new(&name);
+    model.input("input", &[1, channels, hw, hw]);
+    model.initializer(
+        "w",
+        &[classes, channels],
+        &rng.values(classes * channels, 16.0),
+    );
+    model.initializer("b", &[classes], &rng.values(classes, 16.0));
+    model.node("GlobalAveragePool", &["input"], &["pooled"], &[]);
+    model.node("Flatten", &["pooled"], &["features"], &[]);
+    model.node(
+        "Gemm",
+        &["features", "w", "b"],
+        &["logits"],
+        &[("transB", Attribute::Int(1))],
+    );
+    model.output("logits", &[1, classes]);
+    Fixture {
+        name,
+        model,
+        input_shapes: vec![vec![1, channels, hw, hw]],
+    }
+}
+
+/// An MNIST classifier ending in a Softmax: a strided 5x5 Conv of a `[1, 1, 28, 28]` digit to
+/// `channels`, a Relu and a 2x2 MaxPool, flattened into a Gemm to ten logits and their Softmax.
+pub fn mnist_classifier(channels: usize) -> Fixture {
+    let name = name_of("mnist_classifier", &[channels]);
+    let mut rng = Lcg::new(7);
+    let mut model = OnnxModel::new(&name);
+    model.input("input", &[1, 1, 28, 28]);
+    let out = conv(
+        &mut model,
+        &mut rng,
+        "input",
+        (1, channels),
+        5,
+        &[("strides", ints(&[2, 2]))],
+    );
+    let x = relu(&mut model, &out);
+    model.node(
+        "MaxPool",
+        &[&x],
+        &["pooled"],
+        &[("kernel_shape", ints(&[2, 2])), ("strides", ints(&[2, 2]))],
+    );
+    model.node("Flatten", &["pooled"], &["features"], &[]);
+    let features = channels * 6 * 6;
+    model.initializer("w", &[10, features], &rng.values(10 * features, 16.0));
+    model.initializer("b", &[10], &rng.values(10, 16.0));
+    model.node(
+        "Gemm",
+        &["features", "w", "b"],
+        &["logits"],
+        &[("transB", Attribute::Int(1))],
+    );
+    model.node(
+        "Softmax",
+        &["logits"],
+        &["probabilities"],
+        &[("axis", Attribute::Int(1))],
+    );
+    model.output("probabilities", &[1, 10]);
+    Fixture {
+        name,
+        model,
+        input_shapes: vec![vec![1, 1, 28, 28]],
+    }
+}
+
+/// A CIFAR-style classifier of raw `[1, 3, hw, hw]` pixels, see [Fixture::pixel_data]: scaled
+/// down by 1/64, a padded 3x3 Conv to `channels` and a Relu, globally average pooled and
+/// flattened into a Gemm to `classes` logits.
+pub fn cifar_classifier(channels: usize, hw: usize, classes: usize) -> Fixture {
+    let name = name_of("cifar_classifier", &[channels, hw, classes]);
+    let mut rng = Lcg::new(16);
+    let mut model = OnnxModel::new(&name);
+    model.input("input", &[1, 3, hw, hw]);
+    model.initializer("norm", &[1], &[1.0 / 64.0]);
+    model.node("Mul", &["input", "norm"], &["normalized"], &[]);
+    let out = conv(
+        &mut model,
+        &mut rng,
+        "normalized",
+        (3, channels),
+        3,
+        &[("pads", ints(&[1; 4]))],
+    );
+    let x = relu(&mut model, &out);
+    model.node("GlobalAveragePool", &[&x], &["pooled"], &[]);
+    model.node("Flatten", &["pooled"], &["features"], &[]);
+    model.initializer(
+        "w",
+        &[classes, channels],
+        &rng.values(classes * channels, 16.0),
+    );
+    model.initializer("b", &[classes], &rng.values(classes, 16.0));
+    model.node(
+        "Gemm",
+        &["features", "w", "b"],
+        &["logits"],
+        &[("transB", Attribute::Int(1))],
+    );
+    model.output("logits", &[1, classes]);
+    Fixture {
+        name,
+        model,
+        input_shapes: vec![vec![1, 3, hw, hw]],
+    }
+}
+
+/// The classic CNN head: a padded 3x3 conv of a `[1, 1, hw, hw]` image to `channels` and a Relu,
+/// reshaped to `[1, -1]` and into a Gemm to `classes`. The reshape target infers the flattened
+/// width, `channels * hw * hw`, and keeps the leading batch dim of 1.
+pub fn conv_reshape_head(channels: usize, hw: usize, classes: usize) -> Fixture {
+    let name = name_of("conv_reshape_head", &[channels, hw, classes]);
+    let mut rng = Lcg::new(13);
+    let mut model = OnnxModel::new(&name);
+    model.input("input", &[1, 1, hw, hw]);
+    let x = conv(
+        &mut model,
+        &mut rng,
+        "input",
+        (1, channels),
+        3,
+        &[("pads", ints(&[1; 4]))],
+    );
+    let x = relu(&mut model, &x);
+    model.int_initializer("shape", &[2], &[1, -1]);
+    model.node("Reshape", &[&x, "shape"], &["features"], &[]);
+    let features = channels * hw * hw;
+    model.initializer(
+        "w",
+        &[classes, features],
+        &rng.values(classes * features, 64.0),
+    );
+    model.initializer("b", &[classes], &rng.values(classes, 16.0));
+    model.node(
+        "Gemm",
+        &["features", "w", "b"],
+        &["output"],
+        &[("transB", Attribute::Int(1))],
+    );
+    model.output("output", &[1, classes]);
+    Fixture {
+        name,
+        model,
+        input_shapes: vec![vec![1, 1, hw, hw]],
+    }
+}
+
+/// A head exported from a channels-last framework: a `[1, channels, hw, hw]` feature map
+/// Transposed to `[1, hw, hw, channels]` before it's reshaped to `[1, -1]` and into a Gemm to
+/// `classes`, so the Gemm sees the features in channels-last order.
+pub fn channels_last_head(channels: usize, hw: usize, classes: usize) -> Fixture {
+    let name = name_of("channels_last_head", &[channels, hw, classes]);
+    let mut rng = Lcg::new(18);
+    let mut model = OnnxModel::new(&name);
+    model.input("input", &[1, channels, hw, hw]);
+    model.node(
+        "Transpose",
+        &["input"],
+        &["transposed"],
+        &[("perm", ints(&[0, 2, 3, 1]))],
+    );
+    model.int_initializer("shape", &[2], &[1, -1]);
+    model.node("Reshape", &["transposed", "shape"], &["features"], &[]);
+    let features = channels * hw * hw;
+    model.initializer(
+        "w",
+        &[classes, features],
+        &rng.values(classes * features, 64.0),
+    );
+    model.initializer("b", &[classes], &rng.values(classes, 16.0));
+    model.node(
+        "Gemm",
+        &["features", "w", "b"],
+        &["output"],
+        &[("transB", Attribute::Int(1))],
+    );
+    model.output("output", &[1, classes]);
+    Fixture {
+        name,
+        model,
+        input_shapes: vec![vec![1, channels, hw, hw]],
+    }
+}
+
+/// A Gemm of a `[in_features]` input to `out_features` between an Unsqueeze adding the batch
+/// axis and a Squeeze removing it again, as exporters targeting opset 13 wrap Gemms of
+/// unbatched inputs.
+pub fn squeezed_gemm(in_features: usize, out_features: usize) -> Fixture {
+    let name = name_of("squeezed_gemm", &[in_features, out_features]);
+    let mut rng = Lcg::new(19);
+    let mut model = OnnxModel::new(&name);
+    model.input("input", &[in_features]);
+    model.int_initializer("axes", &[1], &[0]);
+    model.node("Unsqueeze", &["input", "axes"], &["batched"], &[]);
+    model.initializer(
+        "w",
+        &[out_features, in_features],
+        &rng.values(out_features * in_features, 16.0),
+    );
+    model.initializer("b", &[out_features], &rng.values(out_features, 16.0));
+    model.node(
+        "Gemm",
+        &["batched", "w", "b"],
+        &["logits"],
+        &[("transB", Attribute::Int(1))],
+    );
+    model.node("Squeeze", &["logits", "axes"], &["output"], &[]);
+    model.output("output", &[out_features]);
+    Fixture {
+        name,
+        model,
+        input_shapes: vec![vec![in_features]],
+    }
+}
+
+/// A per-channel scaling of a `[1, channels, hw, hw]` image by a `[channels, 1, 1]` constant,
+/// gated by the sigmoid of a padded 3x3 conv of it: a Mul by a constant broadcast over the
+/// channels, then a Mul of two activations.
+pub fn channel_gate(channels: usize, hw: usize) -> Fixture {
+    let name = name_of("channel_gate", &[channels, hw]);
+    let mut rng = Lcg::new(20);
+    let mut model = OnnxModel::new(&name);
+    let dims = [1, channels, hw, hw];
+    model.input("input", &dims);
+    model.initializer("scale", &[channels, 1, 1], &rng.values(channels, 4.0));
+    model.node("Mul", &["input", "scale"], &["scaled"], &[]);
+    let x = conv(
+        &mut model,
+        &mut rng,
+        "scaled",
+        (channels, channels),
+        3,
+        &[("pads", ints(&[1; 4]))],
+    );
+    model.node("Sigmoid", &[&x], &["gate"], &[]);
+    model.node("Mul", &["scaled", "gate"], &["output"], &[]);
+    model.output("output", &dims);
+    Fixture {
+        name,
+        model,
+        input_shapes: vec![dims.to_vec()],
+    }
+}
+
+/// A Gemm of a `[1, in_features]` input centered by a Sub of its mean of 128, as normalization
+/// baked into a graph is exported, whose `out_features` logits are subtracted from a constant:
+/// activation minus constant, then constant minus activation.
+pub fn mean_centered_gemm(in_features: usize, out_features: usize) -> Fixture {
+    let name = name_of("mean_centered_gemm", &[in_features, out_features]);
+    let mut rng = Lcg::new(21);
+    let mut model = OnnxModel::new(&name);
+    model.input("input", &[1, in_features]);
+    model.initializer("mean", &[1], &[128.0]);
+    model.node("Sub", &["input", "mean"], &["centered"], &[]);
+    model.initializer(
+        "w",
+        &[out_features, in_features],
+        &rng.values(out_features * in_features, 64.0),
+    );
+    model.initializer("b", &[out_features], &rng.values(out_features, 16.0));
+    model.node(
+        "Gemm",
+        &["centered", "w", "b"],
+        &["logits"],
+        &[("transB", Attribute::Int(1))],
+    );
+    model.initializer("offset", &[out_features], &rng.values(out_features, 16.0));
+    model.node("Sub", &["offset", "logits"], &["output"], &[]);
+    model.output("output", &[1, out_features]);
+    Fixture {
+        name,
+        model,
+        input_shapes: vec![vec![1, in_features]],
+    }
+}
+
+/// A Gemm of `[1, in_features]` raw pixels, see [Fixture::pixel_data], normalized by a Div by
+/// 255 to `out_features`.
+pub fn normalized_gemm(in_features: usize, out_features: usize) -> Fixture {
+    let name = name_of("normalized_gemm", &[in_features, out_features]);
+    let mut rng = Lcg::new(22);
+    let mut model = OnnxModel::new(&name);
+    model.input("input", &[1, in_features]);
+    model.initializer("max", &[1], &[255.0]);
+    model.node("Div", &["input", "max"], &["normalized"], &[]);
+    model.initializer(
+        "w",
+        &[out_features, in_features],
+        &rng.values(out_features * in_features, 16.0),
+    );
+    model.initializer("b", &[out_features], &rng.values(out_features, 16.0));
+    model.node(
+        "Gemm",
+        &["normalized", "w", "b"],
+        &["output"],
+        &[("transB", Attribute::Int(1))],
+    );
+    model.output("output", &[1, out_features]);
+    Fixture {
+        name,
+        model,
+        input_shapes: vec![vec![1, in_features]],
+    }
+}
+
+/// A padded 3x3 conv of a `[1, channels, hw, hw]` image, of which a Slice keeps indices 1 and 2
+/// along `axis`: the channels, rows or columns for an `axis` of 1, 2 or 3.
+pub fn sliced_conv(axis: usize, channels: usize, hw: usize) -> Fixture {
+    assert!(
+        (1..4).contains(&axis),
+        "slice a channel, row or column axis"
+    );
+    let name = name_of("sliced_conv", &[axis, channels, hw]);
+    let mut rng = Lcg::new(23);
+    let mut model = OnnxModel::new(&name);
+    let dims = [1, channels, hw, hw];
+    model.input("input", &dims);
+    let x = conv(
+        &mut model,
+        &mut rng,
+        "input",
+        (channels, channels),
+        3,
+        &[("pads", ints(&[1; 4]))],
+    );
+    model.int_initializer("starts", &[1], &[1]);
+    model.int_initializer("ends", &[1], &[3]);
+    model.int_initializer("axes", &[1], &[axis as i64]);
+    model.node("Slice", &[&x, "starts", "ends", "axes"], &["output"], &[]);
+    let mut out_dims = dims;
+    out_dims[axis] = 2;
+    model.output("output", &out_dims);
+    Fixture {
+        name,
+        model,
+        input_shapes: vec![dims.to_vec()],
+    }
+}
+
+/// The ReduceSum over `axes` of the Relu of a `[1, channels, hw, hw]` image, over all of them if
+/// `axes` is empty, keeping the reduced axes as 1s if `keepdims`.
+pub fn relu_sum(axes: &[usize], keepdims: bool, channels: usize, hw: usize) -> Fixture {
+    relu_reduce("ReduceSum", "relu_sum", axes, keepdims, channels, hw)
+}
+
+/// The ReduceMean over `axes` of the Relu of a `[1, channels, hw, hw]` image, see [relu_sum].
+pub fn relu_mean(axes: &[usize], keepdims: bool, channels: usize, hw: usize) -> Fixture {
+    relu_reduce("ReduceMean", "relu_mean", axes, keepdims, channels, hw)
+}
+
+/// The reduction `op` of the Relu of an image, named after `prefix`, see [relu_sum].
+fn relu_reduce(
+    op: &str,
+    prefix: &str,
+    axes: &[usize],
+    keepdims: bool,
+    channels: usize,
+    hw: usize,
+) -> Fixture {
+    let mut params = vec![keepdims as usize, channels, hw];
+    params.extend(axes);
+    let name = name_of(prefix, &params);
+    let mut model = OnnxModel::new(&name);
+    let dims = [1, channels, hw, hw];
+    model.input("input", &dims);
+    let x = relu(&mut model, "input");
+    let mut inputs = vec![x.as_str()];
+    let mut attributes = vec![("keepdims", Attribute::Int(keepdims as i64))];
+    if !axes.is_empty() {
+        // as of opset 13 ReduceSum takes its axes as an input, the other reductions still as an
+        // attribute
+        let axes = axes.iter().map(|a| *a as i64).collect::<Vec<_>>();
+        if op == "ReduceSum" {
+            model.int_initializer("axes", &[axes.len()], &axes);
+            inputs.push("axes");
+        } else {
+            attributes.push(("axes", Attribute::Ints(axes)));
+        }
+    }
+    model.node(op, &inputs, &["output"], &attributes);
+    let out_dims = dims
+        .iter()
+        .enumerate()
+        .filter_map(|(i, d)| {
+            if !axes.is_empty() && !axes.contains(&i) {
+                Some(*d)
+            } else if keepdims {
+                Some(1)
+            } else {
+                None
+            }
+        })
+        .collect::<Vec<_>>();
+    model.output("output", &out_dims);
+    Fixture {
+        name,
+        model,
+        input_shapes: vec![dims.to_vec()],
+    }
+}
+
+/// Two MatMuls without a bias, as tract gets them from `x @ W` and from `F.linear` without one:
+/// the `[1, in_features]` input by a transposed `[hidden, in_features]` weight, then, weight
+/// first, a `[out_features, hidden]` weight by those features transposed into a column.
+pub fn bias_free_matmuls(in_features: usize, hidden: usize, out_features: usize) -> Fixture {
+    let name = name_of("bias_free_matmuls", &[in_features, hidden, out_features]);
+    let mut rng = Lcg::new(14);
+    let mut model = OnnxModel::new(&name);
+    model.input("input", &[1, in_features]);
+    model.initializer(
+        "w0",
+        &[hidden, in_features],
+        &rng.values(hidden * in_features, 16.0),
+    );
+    model.initializer(
+        "w1",
+        &[out_features, hidden],
+        &rng.values(out_features * hidden, 16.0),
+    );
+    let perm = [("perm", ints(&[1, 0]))];
+    model.node("Transpose", &["w0"], &["w0_t"], &perm);
+    model.node("MatMul", &["input", "w0_t"], &["features"], &[]);
+    model.node("Transpose", &["features"], &["column"], &perm);
+    model.node("MatMul", &["w1", "column"], &["output"], &[]);
+    model.output("output", &[out_features, 1]);
+    Fixture {
+        name,
+        model,
+        input_shapes: vec![vec![1, in_features]],
+    }
+}
+
+/// A feature extractor and a personalized head, to place their params apart: a Gemm named
+/// `gemm_0` to `hidden` features and a Relu, then a Gemm named `gemm_2` to `out_features`.
+pub fn mixed_placement(in_features: usize, hidden: usize, out_features: usize) -> Fixture {
+    let name = name_of("mixed_placement", &[in_features, hidden, out_features]);
+    let mut rng = Lcg::new(8);
+    let mut model = OnnxModel::new(&name);
+    model.input("input", &[1, in_features]);
+    for (i, (from, to)) in [(in_features, hidden), (hidden, out_features)]
+        .into_iter()
+        .enumerate()
+    {
+        model.initializer(
+            &format!("w{}", i),
+            &[to, from],
+            &rng.values(to * from, 16.0),
+        );
+        model.initializer(&format!("b{}", i), &[to], &rng.values(to, 16.0));
+    }
+    model.node(
+        "Gemm",
+        &["input", "w0", "b0"],
+        &["features"],
+        &[("transB", Attribute::Int(1))],
+    );
+    let x = relu(&mut model, "features");
+    model.node(
+        "Gemm",
+        &[&x, "w1", "b1"],
+        &["output"],
+        &[("transB", Attribute::Int(1))],
+    );
+    model.output("output", &[1, out_features]);
+    Fixture {
+        name,
+        model,
+        input_shapes: vec![vec![1, in_features]],
+    }
+}
+
+/// A MatMul by `w` whose every fourth weight is an outlier, far past what the decomposition
+/// range holds at the default scales, which a quarter of the weights can't be quantized into.
+pub fn outlier_matmul(in_features: usize, out_features: usize) -> Fixture {
+    let name = name_of("outlier_matmul", &[in_features, out_features]);
+    let mut rng = Lcg::new(9);
+    let mut model = OnnxModel::new(&name);
+    model.input("input", &[1, in_features]);
+    let weights = rng
+        .values(in_features * out_features, 16.0)
+        .into_iter()
+        .enumerate()
+        .map(|(i, w)| if i % 4 == 0 { w.signum() * 1e7 } else { w })
+        .collect::<Vec<_>>();
+    model.initializer("w", &[in_features, out_features], &weights);
+    model.node("MatMul", &["input", "w"], &["output"], &[]);
+    model.output("output", &[1, out_features]);
+    Fixture {
+        name,
+        model,
+        input_shapes: vec![vec![1, in_features]],
+    }
+}
+
+/// A conv, a Relu and a conv with a dilation of 2 on a `[1, channels, hw, hw]` image, followed by
+/// an LRN, which ezkl has no layout for: a model only part of which is supported.
+pub fn mixed_support(channels: usize, hw: usize) -> Fixture {
+    let name = name_of("mixed_support", &[channels, hw]);
+    let mut rng = Lcg::new(12);
+    let mut model = OnnxModel::new(&name);
+    let dims = [1, channels, hw, hw];
+    model.input("input", &dims);
+    let x = conv(
+        &mut model,
+        &mut rng,
+        "input",
+        (channels, channels),
+        3,
+        &[("pads", ints(&[1; 4]))],
+    );
+    let x = relu(&mut model, &x);
+    let x = conv(
+        &mut model,
+        &mut rng,
+        &x,
+        (channels, channels),
+        3,
+        &[("dilations", ints(&[2, 2])), ("pads", ints(&[2; 4]))],
+    );
+    model.node("LRN", &[&x], &["output"], &[("size", Attribute::Int(3))]);
+    model.output("output", &dims);
+    Fixture {
+        name,
+        model,
+        input_shapes: vec![dims.to_vec()],
+    }
+}
+
+/// A linear head of `out_features` next to a head pruned down to no outputs, as model surgery
+/// leaves them: the pruned head's `[1, 0]` output goes through a Relu and is also concatenated
+/// onto the live head's.
+pub fn pruned_head(in_features: usize, out_features: usize) -> Fixture {
+    let name = name_of("pruned_head", &[in_features, out_features]);
+    let mut rng = Lcg::new(5);
+    let mut model = OnnxModel::new(&name);
+    model.input("input", &[1, in_features]);
+    model.initializer(
+        "w",
+        &[out_features, in_features],
+        &rng.values(out_features * in_features, 16.0),
+    );
+    model.initializer("b", &[out_features], &rng.values(out_features, 16.0));
+    model.initializer("pruned_w", &[0, in_features], &[]);
+    model.initializer("pruned_b", &[0], &[]);
+    let gemm = [("transB", Attribute::Int(1))];
+    model.node("Gemm", &["input", "w", "b"], &["logits"], &gemm);
+    model.node(
+        "Gemm",
+        &["input", "pruned_w", "pruned_b"],
+        &["pruned"],
+        &gemm,
+    );
+    let pruned = relu(&mut model, "pruned");
+    model.node(
+        "Concat",
+        &["logits", "pruned"],
+        &["heads"],
+        &[("axis", Attribute::Int(1))],
+    );
+    model.output("heads", &[1, out_features]);
+    model.output(&pruned, &[1, 0]);
+    Fixture {
+        name,
+        model,
+        input_shapes: vec![vec![1, in_features]],
+    }
+}
+
+/// The elementwise division of one `[1, len]` input by another, kept to at least 1 in magnitude
+/// as `numerator / (|denominator| + 1)`: a division by activations rather than by a constant.
+pub fn ratio(len: usize) -> Fixture {
+    let name = name_of("ratio", &[len]);
+    let mut model = OnnxModel::new(&name);
+    model.input("numerator", &[1, len]);
+    model.input("denominator", &[1, len]);
+    model.initializer("one", &[1], &[1.0]);
+    model.node("Abs", &["denominator"], &["abs"], &[]);
+    model.node("Add", &["abs", "one"], &["shifted"], &[]);
+    model.node("Div", &["numerator", "shifted"], &["output"], &[]);
+    model.output("output", &[1, len]);
+    Fixture {
+        name,
+        model,
+        input_shapes: vec![vec![1, len], vec![1, len]],
+    }
+}
+
+/// The Shrink of a `[1, len]` input (`x ± 0.125` past `±0.25`, else 0), an elementwise op with no
+/// builtin lookup, so it needs a custom table.
+pub fn shrink(len: usize) -> Fixture {
+    let name = name_of("shrink", &[len]);
+    let mut model = OnnxModel::new(&name);
+    model.input("input", &[1, len]);
+    model.node(
+        "Shrink",
+        &["input"],
+        &["output"],
+        &[
+            ("bias", Attribute::Float(0.125)),
+            ("lambd", Attribute::Float(0.25)),
+        ],
+    );
+    model.output("output", &[1, len]);
+    Fixture {
+        name,
+        model,
+        input_shapes: vec![vec![1, len]],
+    }
+}
+
+/// Models for ops ezkl has got wrong before.
+pub fn regression_cases() -> Vec<Fixture> {
+    vec![gemm_trans_b(4, 3), same_padding_conv(2, 5)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ints_encode_as_protobuf_does() {
+        assert_eq!(Message::default().int(1, 300).0, vec![0x08, 0xac, 0x02]);
+        // negative ints take all ten bytes
+        let msg = Message::default().int(1, -1);
+        assert_eq!(msg.0.len(), 11);
+        assert_eq!(msg.0[10], 0x01);
+    }
+
+    #[test]
+    fn fixtures_are_reproducible() {
+        assert_eq!(
+            mlp(&[4, 8, 3]).model.to_bytes(),
+            mlp(&[4, 8, 3]).model.to_bytes()
+        );
+        assert_eq!(
+            serde_json::to_string(&residual_block(2, 4).input_data()).unwrap(),
+            serde_json::to_string(&residual_block(2, 4).input_data()).unwrap()
+        );
+        assert_ne!(mlp(&[4, 8, 3]).name, mlp(&[4, 3]).name);
+    }
+}
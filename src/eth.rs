@@ -384,7 +384,9 @@ pub async fn deploy_da_verifier_via_solidity(
         }
     }
 
-    if settings.run_args.param_visibility.is_hashed() {
+    if settings.run_args.param_visibility.is_hashed()
+        || settings.run_args.param_visibility.is_merkle()
+    {
         return Err(EvmVerificationError::InvalidVisibility.into());
     }
 
@@ -1,21 +1,32 @@
 use crate::circuit::region::RegionSettings;
-use crate::circuit::CheckMode;
+use crate::circuit::witness_log::{self, WitnessLog};
+use crate::circuit::{CheckMode, ReluImpl};
 use crate::commands::CalibrationTarget;
 use crate::eth::{deploy_contract_via_solidity, deploy_da_verifier_via_solidity};
 #[allow(unused_imports)]
 use crate::eth::{fix_da_sol, get_contract_artifacts, verify_proof_via_solidity};
+use crate::graph::builder::fitting_input_scale;
+use crate::graph::coverage::CoverageReport;
+use crate::graph::dataset::DatasetCommitment;
 use crate::graph::input::GraphData;
-use crate::graph::{GraphCircuit, GraphSettings, GraphWitness, Model};
+use crate::graph::input_source::{InputSource, NpyInputSource};
+use crate::graph::output_check::{check_output_tensors, load_expected_outputs, OutputTolerance};
+use crate::graph::modules::merkle_root;
+use crate::graph::{
+    errors::GraphError, ArtifactMetadata, CancellationToken, GraphCircuit, GraphSettings,
+    GraphWitness, InstanceData, Interrupt, Model, OnnxModelBuilder, OutputFormat, Visibility,
+};
 use crate::graph::{TestDataSource, TestSources};
 use crate::pfsys::evm::aggregation_kzg::{AggregationCircuit, PoseidonTranscript};
+use crate::pfsys::verify::verify_inference_proof;
+use crate::pfsys::{append_metadata, bound_context, read_metadata, save_vk, srs::*, PfsysError};
 use crate::pfsys::{
     create_keys, load_pk, load_vk, save_params, save_pk, Snark, StrategyType, TranscriptType,
 };
 use crate::pfsys::{
     create_proof_circuit, swap_proof_commitments_polycommit, verify_proof_circuit, ProofSplitCommit,
 };
-use crate::pfsys::{save_vk, srs::*};
-use crate::tensor::TensorError;
+use crate::tensor::{Tensor, TensorError};
 use crate::EZKL_BUF_CAPACITY;
 use crate::{commands::*, EZKLError};
 use crate::{Commitments, RunArgs};
@@ -48,7 +59,7 @@ use itertools::Itertools;
 use log::debug;
 use log::{info, trace, warn};
 use serde::de::DeserializeOwned;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use snark_verifier::loader::native::NativeLoader;
 use snark_verifier::system::halo2::compile;
 use snark_verifier::system::halo2::transcript::evm::EvmTranscript;
@@ -88,6 +99,9 @@ pub enum ExecutionError {
     /// Prover error
     #[error("[mock] {0}")]
     MockProverError(String),
+    /// The circuit's public instances can't be checked by an exported verifier
+    #[error("[export-verifier] {0}")]
+    ExportVerifier(String),
 }
 
 lazy_static::lazy_static! {
@@ -122,14 +136,40 @@ pub async fn run(command: Commands) -> Result<String, EZKLError> {
             commitment,
         } => get_srs_cmd(srs_path, settings_path, logrows, commitment).await,
         Commands::Table { model, args } => table(model.unwrap_or(DEFAULT_MODEL.into()), args),
+        Commands::Inspect { artifact } => inspect(artifact),
+        Commands::DecodeOutputs {
+            proof_path,
+            output_format,
+        } => decode_outputs(proof_path.unwrap_or(DEFAULT_PROOF.into()), output_format),
+        Commands::Trace {
+            model,
+            data,
+            output,
+            compare_float,
+            args,
+        } => {
+            trace(
+                model.unwrap_or(DEFAULT_MODEL.into()),
+                data.unwrap_or(DEFAULT_DATA.into()),
+                output,
+                compare_float.unwrap_or(DEFAULT_COMPARE_FLOAT.parse().unwrap()),
+                args,
+            )
+            .await
+        }
+        Commands::Coverage { model, output } => {
+            coverage(model.unwrap_or(DEFAULT_MODEL.into()), output)
+        }
         Commands::GenSettings {
             model,
             settings_path,
+            compare_relu_impls,
             args,
         } => gen_circuit_settings(
             model.unwrap_or(DEFAULT_MODEL.into()),
             settings_path.unwrap_or(DEFAULT_SETTINGS.into()),
             args,
+            compare_relu_impls.unwrap_or(DEFAULT_COMPARE_RELU_IMPLS.parse().unwrap()),
         ),
         Commands::CalibrateSettings {
             model,
@@ -160,18 +200,45 @@ pub async fn run(command: Commands) -> Result<String, EZKLError> {
             output,
             vk_path,
             srs_path,
+            check_witness,
+            context_hash,
+            dataset,
+            dataset_index,
         } => gen_witness(
             compiled_circuit.unwrap_or(DEFAULT_COMPILED_CIRCUIT.into()),
             data.unwrap_or(DEFAULT_DATA.into()),
             Some(output.unwrap_or(DEFAULT_WITNESS.into())),
             vk_path,
             srs_path,
+            check_witness.unwrap_or(DEFAULT_CHECK_WITNESS.parse().unwrap()),
+            context_hash,
+            dataset.zip(dataset_index),
         )
         .await
         .map(|e| serde_json::to_string(&e).unwrap()),
-        Commands::Mock { model, witness } => mock(
+        Commands::Mock {
+            model,
+            witness,
+            expected_output,
+            output_atol,
+            output_rtol,
+        } => mock(
             model.unwrap_or(DEFAULT_MODEL.into()),
             witness.unwrap_or(DEFAULT_WITNESS.into()),
+            expected_output,
+            OutputTolerance {
+                abs: output_atol,
+                rel: output_rtol.unwrap_or(DEFAULT_OUTPUT_RTOL.parse().unwrap()),
+            },
+        ),
+        Commands::Replay {
+            witness,
+            model,
+            log,
+        } => replay(
+            model.unwrap_or(DEFAULT_COMPILED_CIRCUIT.into()),
+            witness.unwrap_or(DEFAULT_WITNESS.into()),
+            log.unwrap_or(DEFAULT_WITNESS_LOG.into()),
         ),
         Commands::CreateEvmVerifier {
             vk_path,
@@ -191,6 +258,19 @@ pub async fn run(command: Commands) -> Result<String, EZKLError> {
             )
             .await
         }
+        Commands::ExportVerifier {
+            vk_path,
+            srs_path,
+            settings_path,
+            out,
+            crate_name,
+        } => export_verifier(
+            vk_path.unwrap_or(DEFAULT_VK.into()),
+            srs_path,
+            settings_path.unwrap_or(DEFAULT_SETTINGS.into()),
+            out.unwrap_or(DEFAULT_VERIFIER_CRATE.into()),
+            crate_name.unwrap_or(DEFAULT_VERIFIER_CRATE_NAME.into()),
+        ),
         Commands::EncodeEvmCalldata {
             proof_path,
             calldata_path,
@@ -263,6 +343,22 @@ pub async fn run(command: Commands) -> Result<String, EZKLError> {
             compiled_circuit.unwrap_or(DEFAULT_COMPILED_CIRCUIT.into()),
             settings_path.unwrap_or(DEFAULT_SETTINGS.into()),
         ),
+        Commands::CommitWeights {
+            compiled_circuit,
+            output,
+        } => commit_weights(
+            compiled_circuit.unwrap_or(DEFAULT_COMPILED_CIRCUIT.into()),
+            output.unwrap_or(DEFAULT_WEIGHTS_ROOT.into()),
+        ),
+        Commands::CommitDataset {
+            data,
+            compiled_circuit,
+            output,
+        } => commit_dataset(
+            data,
+            compiled_circuit.unwrap_or(DEFAULT_COMPILED_CIRCUIT.into()),
+            output.unwrap_or(DEFAULT_DATASET_COMMITMENT.into()),
+        ),
         Commands::Setup {
             compiled_circuit,
             srs_path,
@@ -319,6 +415,8 @@ pub async fn run(command: Commands) -> Result<String, EZKLError> {
             srs_path,
             proof_type,
             check_mode,
+            timeout,
+            debug_witness,
         } => prove(
             witness.unwrap_or(DEFAULT_WITNESS.into()),
             compiled_circuit.unwrap_or(DEFAULT_COMPILED_CIRCUIT.into()),
@@ -327,7 +425,28 @@ pub async fn run(command: Commands) -> Result<String, EZKLError> {
             srs_path,
             proof_type,
             check_mode.unwrap_or(DEFAULT_CHECKMODE.parse().unwrap()),
+            timeout.map(Duration::from_secs),
+            debug_witness,
+        )
+        .map(|e| serde_json::to_string(&e).unwrap()),
+        Commands::ProveBatch {
+            data,
+            compiled_circuit,
+            pk_path,
+            proof_dir,
+            srs_path,
+            proof_type,
+            check_mode,
+        } => prove_batch(
+            data.unwrap_or(DEFAULT_DATA.into()),
+            compiled_circuit.unwrap_or(DEFAULT_COMPILED_CIRCUIT.into()),
+            pk_path.unwrap_or(DEFAULT_PK.into()),
+            proof_dir.unwrap_or(DEFAULT_BATCH_PROOF_DIR.into()),
+            srs_path,
+            proof_type,
+            check_mode.unwrap_or(DEFAULT_CHECKMODE.parse().unwrap()),
         )
+        .await
         .map(|e| serde_json::to_string(&e).unwrap()),
         Commands::MockAggregate {
             aggregation_snarks,
@@ -386,12 +505,28 @@ pub async fn run(command: Commands) -> Result<String, EZKLError> {
             vk_path,
             srs_path,
             reduced_srs,
+            context_hash,
+            weights_root,
+            dataset,
+            instances,
+            expected_output,
+            output_atol,
+            output_rtol,
         } => verify(
             proof_path.unwrap_or(DEFAULT_PROOF.into()),
             settings_path.unwrap_or(DEFAULT_SETTINGS.into()),
             vk_path.unwrap_or(DEFAULT_VK.into()),
             srs_path,
             reduced_srs.unwrap_or(DEFAULT_USE_REDUCED_SRS_FOR_VERIFICATION.parse().unwrap()),
+            context_hash,
+            weights_root,
+            dataset,
+            instances,
+            expected_output,
+            OutputTolerance {
+                abs: output_atol,
+                rel: output_rtol.unwrap_or(DEFAULT_OUTPUT_RTOL.parse().unwrap()),
+            },
         )
         .map(|e| serde_json::to_string(&e).unwrap()),
         Commands::VerifyAggr {
@@ -706,17 +841,175 @@ pub(crate) fn table(model: PathBuf, run_args: RunArgs) -> Result<String, EZKLErr
     Ok(String::new())
 }
 
+pub(crate) async fn trace(
+    model_path: PathBuf,
+    data: PathBuf,
+    output: Option<PathBuf>,
+    compare_float: bool,
+    run_args: RunArgs,
+) -> Result<String, EZKLError> {
+    let data = GraphData::from_path(data)?;
+    let mut circuit = GraphCircuit::from_run_args(&run_args, &model_path)?;
+    let inputs = circuit.load_graph_input(&data).await?;
+    let res = circuit.model().forward_with_trace(
+        &inputs,
+        &run_args,
+        RegionSettings::all_true(run_args.decomp_base, run_args.decomp_legs),
+    )?;
+    let mut trace = res.trace.unwrap_or_default();
+
+    if compare_float {
+        let float = Model::run_onnx_trace(
+            &run_args,
+            &model_path,
+            &data,
+            circuit.model().graph.input_shapes()?,
+        )?;
+        trace.compare(&float);
+    }
+
+    let mut report = format!(
+        "{:>6} {:<32} {:<24} {:>8} {:>14}",
+        "idx", "name", "op", "scale", "max divergence"
+    );
+    for (name, node) in trace.nodes.iter().sorted_by_key(|(_, n)| n.idx) {
+        let divergence = node
+            .max_divergence
+            .map_or("-".to_string(), |d| format!("{:.6}", d));
+        let scales = format!("{:?}", node.scales);
+        report.push_str(&format!(
+            "\n{:>6} {:<32} {:<24} {:>8} {:>14}",
+            node.idx, name, node.op, scales, divergence
+        ));
+    }
+    info!("\n{}", report);
+
+    if let Some(output) = output {
+        trace.save(&output)?;
+    }
+    Ok(report)
+}
+
+pub(crate) fn coverage(model: PathBuf, output: Option<PathBuf>) -> Result<String, EZKLError> {
+    let report = CoverageReport::new(&std::fs::read(&model)?)?;
+    info!("\n{}", report.table());
+
+    let json = serde_json::to_string_pretty(&report)?;
+    if let Some(output) = output {
+        std::fs::write(output, &json)?;
+    }
+    Ok(json)
+}
+
+pub(crate) fn inspect(artifact: PathBuf) -> Result<String, EZKLError> {
+    // keys carry their metadata in a trailer, proofs in their json
+    let metadata = match read_metadata(&artifact)? {
+        Some(metadata) => metadata,
+        None => {
+            let snark = Snark::<Fr, G1Affine>::load::<KZGCommitmentScheme<Bn256>>(&artifact)?;
+            snark
+                .metadata()?
+                .ok_or_else(|| {
+                    PfsysError::Metadata(format!("{} has no metadata", artifact.display()))
+                })?
+                .to_string()
+        }
+    };
+    let metadata: serde_json::Value = serde_json::from_str(&metadata)?;
+    let pretty = serde_json::to_string_pretty(&metadata)?;
+    info!("\n {}", pretty);
+    Ok(pretty)
+}
+
+pub(crate) fn decode_outputs(
+    proof_path: PathBuf,
+    output_format: Vec<OutputFormat>,
+) -> Result<String, EZKLError> {
+    let snark = Snark::<Fr, G1Affine>::load::<KZGCommitmentScheme<Bn256>>(&proof_path)?;
+    let metadata = snark
+        .metadata()?
+        .ok_or_else(|| PfsysError::Metadata(format!("{} has no metadata", proof_path.display())))?;
+    let metadata: ArtifactMetadata = serde_json::from_str(metadata)?;
+    let instances = snark.instances.concat();
+    let decoded = metadata.decode_outputs(&instances, &output_format)?;
+    let pretty = serde_json::to_string_pretty(&decoded)?;
+    info!("\n {}", pretty);
+    Ok(pretty)
+}
+
 pub(crate) async fn gen_witness(
     compiled_circuit_path: PathBuf,
     data: PathBuf,
     output: Option<PathBuf>,
     vk_path: Option<PathBuf>,
     srs_path: Option<PathBuf>,
+    check_witness: bool,
+    context: Option<String>,
+    dataset: Option<(PathBuf, usize)>,
 ) -> Result<GraphWitness, EZKLError> {
     // these aren't real values so the sanity checks are mostly meaningless
 
     let mut circuit = GraphCircuit::load(compiled_circuit_path)?;
+    circuit.check_witness = check_witness;
+
+    if let Some(source) = npy_input_source(&circuit, &data)? {
+        return gen_witness_from(
+            circuit, &source, output, vk_path, srs_path, context, dataset,
+        );
+    }
     let data: GraphData = GraphData::from_path(data)?;
+    let input = circuit.load_graph_input(&data).await?;
+    witness_of(circuit, input, output, vk_path, srs_path, context, dataset)
+}
+
+/// Reads .npy inputs by name, from a directory holding a <name>.npy file per input or from a
+/// single .npy file for a model taking one input. Any other data isn't read by name.
+fn npy_input_source(
+    circuit: &GraphCircuit,
+    data: &Path,
+) -> Result<Option<NpyInputSource>, EZKLError> {
+    if data.is_dir() {
+        return Ok(Some(NpyInputSource::from_dir(data)));
+    }
+    if !data.extension().is_some_and(|ext| ext == "npy") {
+        return Ok(None);
+    }
+    let names = circuit.model().graph.input_names()?;
+    if names.len() != 1 {
+        return Err(GraphError::MisformedParams(format!(
+            "the model takes {} inputs, pass a directory holding a .npy file per input",
+            names.len()
+        ))
+        .into());
+    }
+    Ok(Some(NpyInputSource::from_file(&names[0], data)))
+}
+
+/// Generates a witness for `circuit` from inputs read by name from `source`, e.g. tensors a
+/// caller already holds in memory, as [gen_witness] does for files.
+pub(crate) fn gen_witness_from(
+    mut circuit: GraphCircuit,
+    source: &dyn InputSource,
+    output: Option<PathBuf>,
+    vk_path: Option<PathBuf>,
+    srs_path: Option<PathBuf>,
+    context: Option<String>,
+    dataset: Option<(PathBuf, usize)>,
+) -> Result<GraphWitness, EZKLError> {
+    let input = circuit.load_graph_input_from(source)?;
+    witness_of(circuit, input, output, vk_path, srs_path, context, dataset)
+}
+
+/// Runs `circuit` forward on its quantized `input`, then binds and saves the witness
+fn witness_of(
+    mut circuit: GraphCircuit,
+    mut input: Vec<Tensor<Fr>>,
+    output: Option<PathBuf>,
+    vk_path: Option<PathBuf>,
+    srs_path: Option<PathBuf>,
+    context: Option<String>,
+    dataset: Option<(PathBuf, usize)>,
+) -> Result<GraphWitness, EZKLError> {
     let settings = circuit.settings().clone();
 
     let vk = if let Some(vk) = vk_path {
@@ -728,10 +1021,6 @@ pub(crate) async fn gen_witness(
         None
     };
 
-        let mut input = circuit.load_graph_input(&data).await?;
-    #[cfg(any(not(feature = "ezkl"), target_arch = "wasm32"))]
-    let mut input = circuit.load_graph_input(&data)?;
-
     // if any of the settings have kzg visibility then we need to load the srs
 
     let commitment: Commitments = settings.run_args.commitment.into();
@@ -740,7 +1029,7 @@ pub(crate) async fn gen_witness(
         RegionSettings::all_true(settings.run_args.decomp_base, settings.run_args.decomp_legs);
 
     let start_time = Instant::now();
-    let witness = if settings.module_requires_polycommit() {
+    let mut witness = if settings.module_requires_polycommit() {
         if get_srs_path(settings.run_args.logrows, srs_path.clone(), commitment).exists() {
             match Commitments::from(settings.run_args.commitment) {
                 Commitments::KZG => {
@@ -789,6 +1078,8 @@ pub(crate) async fn gen_witness(
         )?
     };
 
+    witness.forward_time = Some(start_time.elapsed());
+
     // print each variable tuple (symbol, value) as symbol=value
     trace!(
         "witness generation {:?} took {:?}",
@@ -802,6 +1093,25 @@ pub(crate) async fn gen_witness(
         start_time.elapsed()
     );
 
+    // the circuit was sized for the calibration data, inputs far outside of it may not prove
+    circuit.enforce_output_max(&witness)?;
+
+    if let Some(context) = context {
+        if !settings.run_args.bind_context {
+            return Err(GraphError::UnboundContext.into());
+        }
+        // the proof's metadata is bound along with the context, so it can't be swapped after the fact
+        let metadata = serde_json::to_string(&circuit.artifact_metadata()?)?;
+        witness.set_context(&bound_context(context.as_bytes(), Some(&metadata)));
+    }
+
+    if let Some((path, index)) = dataset {
+        if settings.run_args.dataset_depth.is_none() {
+            return Err(GraphError::UnexpectedDataset.into());
+        }
+        witness.set_dataset_opening(&DatasetCommitment::from_path(path)?, index)?;
+    }
+
     if let Some(output_path) = output {
         witness.save(output_path)?;
     }
@@ -812,15 +1122,80 @@ pub(crate) async fn gen_witness(
     Ok(witness)
 }
 
+/// Logs the rows of each lookup table, which shrink once calibration fits each op's range
+fn report_lookup_tables(settings: &GraphSettings) {
+    let tables = settings.lookup_table_rows();
+    info!(
+        "lookup tables: {} rows over {} tables",
+        tables.iter().map(|(_, rows)| rows).sum::<usize>(),
+        tables.len()
+    );
+    for (op, rows) in tables {
+        info!(
+            "{} table: {} rows",
+            crate::circuit::ops::Op::<Fr>::as_string(&op),
+            rows
+        );
+    }
+}
+
 /// Generate a circuit settings file
 pub(crate) fn gen_circuit_settings(
     model_path: PathBuf,
     params_output: PathBuf,
     run_args: RunArgs,
+    compare_relu_impls: bool,
 ) -> Result<String, EZKLError> {
-    let circuit = GraphCircuit::from_run_args(&run_args, &model_path)?;
+    let circuit = OnnxModelBuilder::new(&model_path)
+        .run_args(run_args)
+        .build()?;
     let params = circuit.settings();
     params.save(&params_output)?;
+
+    let estimate = circuit.estimate_resources()?;
+    info!(
+        "rows: {} estimated from shapes, {} laid out; peak live values: {}; public instances: {}; halo2: {}",
+        estimate.num_rows,
+        params.num_rows,
+        estimate.peak_live_values,
+        params.num_instances(),
+        circuit.circuit_stats()
+    );
+    for clip in estimate.clip_rates.iter().take(3) {
+        info!(
+            "{} clipped {} of its {} values ({:.2}%) when quantized",
+            clip.node,
+            clip.clipped,
+            clip.total,
+            clip.rate() * 100.0
+        );
+    }
+    report_lookup_tables(params);
+
+    // relus can be laid out either way; showing what each would cost takes a layout of each
+    if compare_relu_impls && circuit.model().graph.nodes.values().any(|n| n.is_relu()) {
+        for relu_impl in [ReluImpl::Lookup, ReluImpl::Poly] {
+            let circuit = OnnxModelBuilder::new(&model_path)
+                .run_args(RunArgs {
+                    relu_impl,
+                    node_relu_impl: vec![],
+                    ..params.run_args.clone()
+                })
+                .build()?;
+            let settings = circuit.settings();
+            info!(
+                "relus as {}: {} rows laid out, {} advice columns, range check tables of {:?} rows",
+                relu_impl,
+                settings.num_rows,
+                circuit.circuit_stats().num_advice_columns,
+                settings
+                    .required_range_checks
+                    .iter()
+                    .map(|(min, max)| max - min + 1)
+                    .collect::<Vec<_>>()
+            );
+        }
+    }
     Ok(String::new())
 }
 
@@ -1041,6 +1416,29 @@ pub(crate) async fn calibrate(
             .collect::<Vec<(crate::Scale, crate::Scale)>>();
     }
 
+    // inputs of a known range cap the input scales tried, so that they fit the lookup range the
+    // settings were generated with, see [RunArgs::input_range]
+    if let Some(input_range) = settings.run_args.input_range {
+        let lookup_range = settings.run_args.lookup_range;
+        range_grid = range_grid
+            .iter()
+            .map(|(a, b)| {
+                fitting_input_scale(input_range, lookup_range, *a)
+                    .map(|a| (a, *b))
+                    .ok_or_else(|| {
+                        GraphError::InvalidRunArgs(format!(
+                            "inputs in [{}, {}] don't fit the lookup range {:?} at any input scale",
+                            input_range.0, input_range.1, lookup_range
+                        ))
+                    })
+            })
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter()
+            .sorted()
+            .dedup()
+            .collect();
+    }
+
     let range_grid = range_grid
         .iter()
         .cartesian_product(scale_rebase_multiplier.iter())
@@ -1078,7 +1476,6 @@ pub(crate) async fn calibrate(
             scale_rebase_multiplier,
             div_rebasing,
         );
-        forward_pass_res.insert(key, vec![]);
 
         let local_run_args = RunArgs {
             input_scale,
@@ -1101,57 +1498,12 @@ pub(crate) async fn calibrate(
             _ => None,
         };
 
-        let mut circuit = match GraphCircuit::from_run_args(&local_run_args, &model_path) {
-            Ok(c) => c,
-            Err(e) => {
-                error!("circuit creation from run args failed: {:?}", e);
-                pb.inc(1);
-                num_failed += 1;
-                continue;
-            }
-        };
-
-        let forward_res = chunks
-            .iter()
-            .map(|chunk| {
-                let chunk = chunk.clone();
-
-                let data = circuit
-                    .load_graph_from_file_exclusively(&chunk)
-                    .map_err(|e| format!("failed to load circuit inputs: {}", e))?;
-
-                let forward_res = circuit
-                    .forward::<KZGCommitmentScheme<Bn256>>(
-                        &mut data.clone(),
-                        None,
-                        None,
-                        RegionSettings::all_true(
-                            settings.run_args.decomp_base,
-                            settings.run_args.decomp_legs,
-                        ),
-                    )
-                    .map_err(|e| format!("failed to forward: {}", e))?;
-
-                // push result to the hashmap
-                forward_pass_res
-                    .get_mut(&key)
-                    .ok_or("key not found")?
-                    .push(forward_res);
-
-                Ok(()) as Result<(), String>
-            })
-            .collect::<Result<Vec<()>, String>>();
-
-        match forward_res {
-            Ok(_) => (),
-            // typically errors will be due to the circuit overflowing the i64 limit
-            Err(e) => {
-                error!("forward pass failed: {:?}", e);
-                pb.inc(1);
-                num_failed += 1;
-                continue;
-            }
-        }
+        let calibrated = OnnxModelBuilder::new(&model_path)
+            .run_args(local_run_args)
+            .calibrate_with(chunks.clone())
+            .lookup_safety_margin(lookup_safety_margin)
+            .max_logrows(max_logrows)
+            .build_with_witnesses();
 
         // drop the gag
         #[cfg(all(not(not(feature = "ezkl")), unix))]
@@ -1159,64 +1511,49 @@ pub(crate) async fn calibrate(
         #[cfg(all(not(not(feature = "ezkl")), unix))]
         drop(_g);
 
-        let result = forward_pass_res.get(&key).ok_or("key not found")?;
-
-        let min_lookup_range = result
-            .iter()
-            .map(|x| x.min_lookup_inputs)
-            .min()
-            .unwrap_or(0);
-
-        let max_lookup_range = result
-            .iter()
-            .map(|x| x.max_lookup_inputs)
-            .max()
-            .unwrap_or(0);
-
-        let max_range_size = result.iter().map(|x| x.max_range_size).max().unwrap_or(0);
-
-        let res = circuit.calc_min_logrows(
-            (min_lookup_range, max_lookup_range),
-            max_range_size,
-            max_logrows,
-            lookup_safety_margin,
-        );
-
-        if res.is_ok() {
-            let new_settings = circuit.settings().clone();
-
-            let found_run_args = RunArgs {
-                input_scale: new_settings.run_args.input_scale,
-                param_scale: new_settings.run_args.param_scale,
-                div_rebasing: new_settings.run_args.div_rebasing,
-                lookup_range: new_settings.run_args.lookup_range,
-                logrows: new_settings.run_args.logrows,
-                scale_rebase_multiplier: new_settings.run_args.scale_rebase_multiplier,
-                ..settings.run_args.clone()
-            };
+        match calibrated {
+            Ok((circuit, witnesses)) => {
+                forward_pass_res.insert(key, witnesses);
+                let new_settings = circuit.settings().clone();
+
+                let found_run_args = RunArgs {
+                    input_scale: new_settings.run_args.input_scale,
+                    param_scale: new_settings.run_args.param_scale,
+                    div_rebasing: new_settings.run_args.div_rebasing,
+                    lookup_range: new_settings.run_args.lookup_range,
+                    logrows: new_settings.run_args.logrows,
+                    scale_rebase_multiplier: new_settings.run_args.scale_rebase_multiplier,
+                    ..settings.run_args.clone()
+                };
 
-            let found_settings = GraphSettings {
-                run_args: found_run_args,
-                required_lookups: new_settings.required_lookups,
-                required_range_checks: new_settings.required_range_checks,
-                model_output_scales: new_settings.model_output_scales,
-                model_input_scales: new_settings.model_input_scales,
-                num_rows: new_settings.num_rows,
-                total_assignments: new_settings.total_assignments,
-                total_const_size: new_settings.total_const_size,
-                total_dynamic_col_size: new_settings.total_dynamic_col_size,
-                ..settings.clone()
-            };
+                let found_settings = GraphSettings {
+                    run_args: found_run_args,
+                    required_lookups: new_settings.required_lookups,
+                    required_range_checks: new_settings.required_range_checks,
+                    lookup_ranges: new_settings.lookup_ranges,
+                    output_max: new_settings.output_max,
+                    model_output_scales: new_settings.model_output_scales,
+                    model_input_scales: new_settings.model_input_scales,
+                    num_rows: new_settings.num_rows,
+                    total_assignments: new_settings.total_assignments,
+                    total_const_size: new_settings.total_const_size,
+                    total_dynamic_col_size: new_settings.total_dynamic_col_size,
+                    ..settings.clone()
+                };
 
-            found_params.push(found_settings.clone());
+                found_params.push(found_settings.clone());
 
-            debug!(
-                "found settings: \n {}",
-                found_settings.as_json()?.to_colored_json_auto()?
-            );
-            num_passed += 1;
-        } else {
-            num_failed += 1;
+                debug!(
+                    "found settings: \n {}",
+                    found_settings.as_json()?.to_colored_json_auto()?
+                );
+                num_passed += 1;
+            }
+            // typically errors will be due to the circuit overflowing the i64 limit
+            Err(e) => {
+                error!("calibration failed: {:?}", e);
+                num_failed += 1;
+            }
         }
 
         pb.inc(1);
@@ -1341,6 +1678,7 @@ pub(crate) async fn calibrate(
         best_params.run_args.logrows = reduction;
     }
 
+    report_lookup_tables(&best_params);
     best_params.save(&settings_path)?;
 
     debug!("Saved parameters.");
@@ -1351,11 +1689,17 @@ pub(crate) async fn calibrate(
 pub(crate) fn mock(
     compiled_circuit_path: PathBuf,
     data_path: PathBuf,
+    expected_output: Option<PathBuf>,
+    output_tolerance: OutputTolerance,
 ) -> Result<String, EZKLError> {
     // mock should catch any issues by default so we set it to safe
     let mut circuit = GraphCircuit::load(compiled_circuit_path)?;
 
-    let data = GraphWitness::from_path(data_path)?;
+    // inputs read by name are run forward first, anything else is a witness file
+    let data = match npy_input_source(&circuit, &data_path)? {
+        Some(source) => gen_witness_from(circuit.clone(), &source, None, None, None, None, None)?,
+        None => GraphWitness::from_path(data_path)?,
+    };
 
     circuit.load_graph_witness(&data)?;
 
@@ -1371,9 +1715,46 @@ pub(crate) fn mock(
     .map_err(|e| ExecutionError::MockProverError(e.to_string()))?;
 
     prover.verify().map_err(ExecutionError::VerifyError)?;
+
+    if let Some(path) = expected_output {
+        let expected = load_expected_outputs(&path)?;
+        let outputs = data.outputs.iter().map(Vec::as_slice).collect::<Vec<_>>();
+        let check = check_output_tensors(
+            &outputs,
+            &expected,
+            &circuit.settings().model_output_scales,
+            &output_tolerance,
+        )?;
+        info!("expected outputs: {}", check);
+        check.into_result()?;
+    }
     Ok(String::new())
 }
 
+pub(crate) fn replay(
+    compiled_circuit_path: PathBuf,
+    data_path: PathBuf,
+    log_path: PathBuf,
+) -> Result<String, EZKLError> {
+    let mut circuit = GraphCircuit::load(compiled_circuit_path)?;
+    let data = GraphWitness::from_path(data_path)?;
+    circuit.load_graph_witness(&data)?;
+    let public_inputs = circuit.prepare_public_inputs(&data)?;
+    let log = WitnessLog::load(&log_path)?;
+
+    info!("replaying {} advice cells", log.cells.len());
+    match circuit.replay_witness_log(&log, public_inputs)? {
+        Some(failure) => {
+            warn!("{}", failure);
+            Ok(failure.to_string())
+        }
+        None => {
+            info!("the logged witness satisfies every constraint");
+            Ok(String::new())
+        }
+    }
+}
+
 pub(crate) async fn create_evm_verifier(
     vk_path: PathBuf,
     srs_path: Option<PathBuf>,
@@ -1418,6 +1799,133 @@ pub(crate) async fn create_evm_verifier(
     Ok(String::new())
 }
 
+const VERIFIER_CRATE_MANIFEST: &str = include_str!("../templates/verifier_crate/Cargo.toml.tmpl");
+const VERIFIER_CRATE_LIB: &str = include_str!("../templates/verifier_crate/lib.rs.tmpl");
+const VERIFIER_CRATE_EXAMPLE: &str = include_str!("../templates/verifier_crate/verify.rs.tmpl");
+/// ezkl's own manifest, whose git pins the exported verifier crate takes so that the two verify
+/// with the same halo2 and snark-verifier
+const EZKL_MANIFEST: &str = include_str!("../Cargo.toml");
+
+/// The git source (`git = "..."` and its `rev`, `branch`, `tag` or `package`) ezkl's manifest
+/// gives `name` in the table at `section`, eg. `["patch", "https://github.com/zkonduit/halo2"]`.
+fn manifest_source(section: &[&str], name: &str) -> Result<String, EZKLError> {
+    let manifest = EZKL_MANIFEST.parse::<toml::Value>().map_err(|e| {
+        ExecutionError::ExportVerifier(format!("failed to parse ezkl's manifest: {}", e))
+    })?;
+    let dependency = section
+        .iter()
+        .try_fold(&manifest, |table, key| table.get(key))
+        .and_then(|table| table.get(name))
+        .filter(|dependency| dependency.get("git").is_some());
+    dependency
+        .map(|dependency| {
+            ["git", "rev", "branch", "tag", "package"]
+                .iter()
+                .filter_map(|key| dependency.get(key).map(|value| format!("{} = {}", key, value)))
+                .join(", ")
+        })
+        .ok_or_else(|| {
+            ExecutionError::ExportVerifier(format!(
+                "ezkl's manifest has no git source for {} under {}",
+                name,
+                section.join(".")
+            ))
+            .into()
+        })
+}
+
+pub(crate) fn export_verifier(
+    vk_path: PathBuf,
+    srs_path: Option<PathBuf>,
+    settings_path: PathBuf,
+    out: PathBuf,
+    crate_name: String,
+) -> Result<String, EZKLError> {
+    let settings = GraphSettings::load(&settings_path)?;
+    let run_args = &settings.run_args;
+    // the exported verify takes the model's outputs, so they must be all it has to check
+    if Commitments::from(run_args.commitment) != Commitments::KZG {
+        return Err(
+            ExecutionError::ExportVerifier("only kzg commitments are supported".into()).into(),
+        );
+    }
+    if run_args.output_visibility != Visibility::Public
+        || run_args.input_visibility.is_public()
+        || run_args.bind_context
+        || run_args.dataset_depth.is_some()
+        || settings.module_sizes.num_instances().iter().sum::<usize>() > 0
+    {
+        return Err(ExecutionError::ExportVerifier(
+            "the model's outputs must be its only public instances".into(),
+        )
+        .into());
+    }
+
+    let params = load_params_verifier::<KZGCommitmentScheme<Bn256>>(
+        srs_path,
+        run_args.logrows,
+        Commitments::KZG,
+    )?;
+    let num_instance = settings.total_instances();
+    let output_scales = settings.model_output_scales.clone();
+    let vk = load_vk::<KZGCommitmentScheme<Bn256>, GraphCircuit>(vk_path, settings)?;
+    let protocol = compile(
+        &params,
+        &vk,
+        Config::kzg().with_num_instance(num_instance.clone()),
+    );
+    let deciding_key = (params.get_g()[0], params.g2(), params.s_g2());
+
+    let mut features = vec!["\"derive_serde\""];
+    if cfg!(feature = "mv-lookup") {
+        features.push("\"mv-lookup\"");
+    }
+    let manifest = VERIFIER_CRATE_MANIFEST
+        .replace("{{name}}", &crate_name)
+        .replace(
+            "{{halo2curves_source}}",
+            &manifest_source(&["dependencies"], "halo2curves")?,
+        )
+        .replace(
+            "{{snark_verifier_source}}",
+            &manifest_source(&["dependencies"], "snark-verifier")?,
+        )
+        .replace(
+            "{{halo2_proofs_source}}",
+            &manifest_source(
+                &["patch", "https://github.com/zkonduit/halo2"],
+                "halo2_proofs",
+            )?,
+        )
+        .replace("{{snark_verifier_features}}", &features.join(", "));
+    let lib = VERIFIER_CRATE_LIB
+        .replace("{{output_scales}}", &format!("{:?}", output_scales))
+        .replace("{{output_lens}}", &format!("{:?}", num_instance));
+    let example = VERIFIER_CRATE_EXAMPLE.replace("{{crate_ident}}", &crate_name.replace('-', "_"));
+
+    for dir in ["src", "examples", "artifacts"] {
+        std::fs::create_dir_all(out.join(dir))?;
+    }
+    std::fs::write(out.join("Cargo.toml"), manifest)?;
+    std::fs::write(out.join("src/lib.rs"), lib)?;
+    std::fs::write(out.join("examples/verify.rs"), example)?;
+    serde_json::to_writer(
+        File::create(out.join("artifacts/protocol.json"))?,
+        &protocol,
+    )?;
+    serde_json::to_writer(
+        File::create(out.join("artifacts/deciding_key.json"))?,
+        &deciding_key,
+    )?;
+    info!(
+        "exported verifier crate {} to {}",
+        crate_name,
+        out.display()
+    );
+
+    Ok(String::new())
+}
+
 pub(crate) async fn create_evm_vka(
     vk_path: PathBuf,
     srs_path: Option<PathBuf>,
@@ -1726,6 +2234,52 @@ pub(crate) fn compile_circuit(
     Ok(String::new())
 }
 
+/// Commits to a dataset of inputs for circuits compiled with a `dataset_depth`, saving the root
+/// and each row's opening, see [DatasetCommitment]
+pub(crate) fn commit_dataset(
+    data: PathBuf,
+    compiled_circuit: PathBuf,
+    output: PathBuf,
+) -> Result<String, EZKLError> {
+    let mut circuit = GraphCircuit::load(compiled_circuit)?;
+    let depth = circuit
+        .settings()
+        .run_args
+        .dataset_depth
+        .ok_or(GraphError::UnexpectedDataset)?;
+    let file = std::fs::File::open(&data)?;
+    let rows: Vec<GraphData> = serde_json::from_reader(std::io::BufReader::new(file))?;
+    // the rows are quantized as gen-witness quantizes inputs, so they hash to the same leaves
+    let rows = rows
+        .iter()
+        .map(|row| {
+            let inputs = circuit.load_graph_from_file_exclusively(row)?;
+            Ok(inputs.iter().flat_map(|t| t.to_vec()).collect())
+        })
+        .collect::<Result<Vec<Vec<Fr>>, GraphError>>()?;
+    let commitment = DatasetCommitment::new(&rows, depth)?;
+    commitment.save(output)?;
+    let root = crate::pfsys::field_to_string(&commitment.root);
+    info!("dataset root: {} ({} rows)", root, rows.len());
+    Ok(root)
+}
+
+/// Saves the Merkle root of the compiled model's params, see [crate::graph::modules::merkle_root]
+pub(crate) fn commit_weights(
+    compiled_circuit: PathBuf,
+    output: PathBuf,
+) -> Result<String, EZKLError> {
+    let circuit = GraphCircuit::load(compiled_circuit)?;
+    if !circuit.settings().run_args.param_visibility.is_merkle() {
+        warn!("the circuit's params aren't merkle committed, so its proofs won't carry this root");
+    }
+    let root = merkle_root(&circuit.model().get_all_params()?);
+    std::fs::write(&output, serde_json::to_string(&root)?)?;
+    let root = crate::pfsys::field_to_string(&root);
+    info!("weights root: {}", root);
+    Ok(root)
+}
+
 pub(crate) fn setup(
     compiled_circuit: PathBuf,
     srs_path: Option<PathBuf>,
@@ -1746,6 +2300,7 @@ pub(crate) fn setup(
     let logrows = circuit.settings().run_args.logrows;
     let commitment: Commitments = circuit.settings().run_args.commitment.into();
 
+    let now = Instant::now();
     let pk = match commitment {
         Commitments::KZG => {
             let params = load_params_prover::<KZGCommitmentScheme<Bn256>>(
@@ -1772,8 +2327,18 @@ pub(crate) fn setup(
             )?
         }
     };
+    let keygen_time = now.elapsed();
     save_vk::<G1Affine>(&vk_path, pk.get_vk())?;
     save_pk::<G1Affine>(&pk_path, &pk)?;
+
+    // the keygen time is reported with the proofs made from the keys
+    let metadata = ArtifactMetadata {
+        keygen_time: Some(keygen_time),
+        ..circuit.artifact_metadata()?
+    };
+    let metadata = serde_json::to_string(&metadata)?;
+    append_metadata(&vk_path, &metadata)?;
+    append_metadata(&pk_path, &metadata)?;
     Ok(String::new())
 }
 
@@ -1834,26 +2399,47 @@ pub(crate) fn prove(
     srs_path: Option<PathBuf>,
     proof_type: ProofType,
     check_mode: CheckMode,
+    timeout: Option<Duration>,
+    debug_witness: Option<PathBuf>,
 ) -> Result<Snark<Fr, G1Affine>, EZKLError> {
+    let interrupt = timeout.map(|t| Interrupt::new(CancellationToken::new(), Some(t)));
+    let now = Instant::now();
     let data = GraphWitness::from_path(data_path)?;
     let mut circuit = GraphCircuit::load(compiled_circuit_path)?;
+    circuit.interrupt = interrupt.clone();
+    // keys predating the keygen time in their metadata report it as zero
+    let keygen_time = match read_metadata(&pk_path)? {
+        Some(metadata) => serde_json::from_str::<ArtifactMetadata>(&metadata)?
+            .keygen_time
+            .unwrap_or_default(),
+        None => Duration::ZERO,
+    };
+    let mut load_time = now.elapsed();
 
+    let now = Instant::now();
     circuit.load_graph_witness(&data)?;
 
     let pretty_public_inputs = circuit.pretty_public_inputs(&data)?;
     let public_inputs = circuit.prepare_public_inputs(&data)?;
+    let witness_time = now.elapsed();
 
     let circuit_settings = circuit.settings().clone();
+    let metadata = serde_json::to_string(&circuit.artifact_metadata()?)?;
 
     let strategy: StrategyType = proof_type.into();
     let transcript: TranscriptType = proof_type.into();
+    let forward_time = data.forward_time.unwrap_or_default();
     let proof_split_commits: Option<ProofSplitCommit> = data.into();
 
     let commitment = circuit_settings.run_args.commitment.into();
     let logrows = circuit_settings.run_args.logrows;
+    if debug_witness.is_some() {
+        witness_log::record();
+    }
     // creates and verifies the proof
-    let mut snark = match commitment {
+    let snark = match commitment {
         Commitments::KZG => {
+            let now = Instant::now();
             let pk =
                 load_pk::<KZGCommitmentScheme<Bn256>, GraphCircuit>(pk_path, circuit.params())?;
 
@@ -1862,6 +2448,7 @@ pub(crate) fn prove(
                 logrows,
                 Commitments::KZG,
             )?;
+            load_time += now.elapsed();
             match strategy {
                 StrategyType::Single => create_proof_circuit::<
                     KZGCommitmentScheme<Bn256>,
@@ -1914,6 +2501,7 @@ pub(crate) fn prove(
             }
         }
         Commitments::IPA => {
+            let now = Instant::now();
             let pk =
                 load_pk::<IPACommitmentScheme<G1Affine>, GraphCircuit>(pk_path, circuit.params())?;
 
@@ -1922,6 +2510,7 @@ pub(crate) fn prove(
                 circuit_settings.run_args.logrows,
                 Commitments::IPA,
             )?;
+            load_time += now.elapsed();
             match strategy {
                 StrategyType::Single => create_proof_circuit::<
                     IPACommitmentScheme<G1Affine>,
@@ -1972,9 +2561,34 @@ pub(crate) fn prove(
                 }
             }
         }
-    }?;
+    };
+    // the log is kept whether or not the proof went through, a failed one is what it's for
+    if let (Some(path), Some(log)) = (&debug_witness, witness_log::finish()) {
+        log.save(path)?;
+        info!(
+            "logged {} advice cells to {}",
+            log.cells.len(),
+            path.display()
+        );
+    }
+    let mut snark = match snark {
+        Ok(snark) => snark,
+        Err(e) => {
+            // a timed out layout surfaces from halo2 as a synthesis error
+            if let Some(interrupt) = &interrupt {
+                interrupt.check()?;
+            }
+            return Err(e.into());
+        }
+    };
 
     snark.pretty_public_inputs = pretty_public_inputs;
+    snark.timings.load = load_time;
+    snark.timings.witness = witness_time;
+    snark.timings.forward = forward_time;
+    snark.timings.keygen = keygen_time;
+    info!("proof phase timings: {:?}", snark.timings);
+    snark.set_metadata(metadata);
 
     if let Some(proof_path) = proof_path {
         snark.save(&proof_path)?;
@@ -1983,6 +2597,111 @@ pub(crate) fn prove(
     Ok(snark)
 }
 
+/// A chunk of a batch proven by `prove-batch`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BatchChunk {
+    /// Index of the chunk's first sample in the batch
+    pub start: usize,
+    /// Number of samples of the batch the chunk holds, a short last chunk repeats its last sample
+    pub num_samples: usize,
+    /// The chunk's input data
+    pub data: PathBuf,
+    /// The chunk's witness
+    pub witness: PathBuf,
+    /// The chunk's proof
+    pub proof: PathBuf,
+}
+
+/// The proofs a batch was split into by `prove-batch`, all made with the same proving key.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BatchManifest {
+    /// Number of samples in the batch
+    pub num_samples: usize,
+    /// Number of samples per proof, the circuit's batch size
+    pub chunk_size: usize,
+    /// The chunks in batch order
+    pub chunks: Vec<BatchChunk>,
+}
+
+/// The name of the manifest `prove-batch` writes to its proof directory
+pub const BATCH_MANIFEST: &str = "manifest.json";
+
+pub(crate) async fn prove_batch(
+    data: PathBuf,
+    compiled_circuit_path: PathBuf,
+    pk_path: PathBuf,
+    proof_dir: PathBuf,
+    srs_path: Option<PathBuf>,
+    proof_type: ProofType,
+    check_mode: CheckMode,
+) -> Result<BatchManifest, EZKLError> {
+    let circuit = GraphCircuit::load(compiled_circuit_path.clone())?;
+    let chunk_size = circuit
+        .settings()
+        .run_args
+        .variables
+        .iter()
+        .find(|(name, _)| name == "batch_size")
+        .map(|(_, size)| *size)
+        .unwrap_or(1);
+    let input_shapes = circuit.model().graph.input_shapes()?;
+
+    let data = GraphData::from_path(data)?;
+    let chunks = data.split_into_chunks(input_shapes, chunk_size).await?;
+    info!("proving {} chunks of {} samples", chunks.len(), chunk_size);
+
+    std::fs::create_dir_all(&proof_dir)?;
+    let mut manifest = BatchManifest {
+        num_samples: 0,
+        chunk_size,
+        chunks: vec![],
+    };
+    for (i, (chunk, num_samples)) in chunks.into_iter().enumerate() {
+        let chunk_data = proof_dir.join(format!("input_{}.json", i));
+        let witness = proof_dir.join(format!("witness_{}.json", i));
+        let proof = proof_dir.join(format!("proof_{}.json", i));
+
+        chunk.save(chunk_data.clone())?;
+        gen_witness(
+            compiled_circuit_path.clone(),
+            chunk_data.clone(),
+            Some(witness.clone()),
+            None,
+            srs_path.clone(),
+            false,
+            None,
+            None,
+        )
+        .await?;
+        prove(
+            witness.clone(),
+            compiled_circuit_path.clone(),
+            pk_path.clone(),
+            Some(proof.clone()),
+            srs_path.clone(),
+            proof_type,
+            check_mode,
+            None,
+            None,
+        )?;
+        debug!("proved chunk {} ({} samples)", i, num_samples);
+
+        manifest.num_samples += num_samples;
+        manifest.chunks.push(BatchChunk {
+            start: i * chunk_size,
+            num_samples,
+            data: chunk_data,
+            witness,
+            proof,
+        });
+    }
+
+    let writer = BufWriter::new(File::create(proof_dir.join(BATCH_MANIFEST))?);
+    serde_json::to_writer(writer, &manifest)?;
+
+    Ok(manifest)
+}
+
 pub(crate) fn swap_proof_commitments_cmd(
     proof_path: PathBuf,
     witness: PathBuf,
@@ -2281,89 +3000,105 @@ pub(crate) fn aggregate(
     Ok(snark)
 }
 
+#[allow(clippy::too_many_arguments)]
 pub(crate) fn verify(
     proof_path: PathBuf,
     settings_path: PathBuf,
     vk_path: PathBuf,
     srs_path: Option<PathBuf>,
     reduced_srs: bool,
+    context: Option<String>,
+    weights_root: Option<PathBuf>,
+    dataset: Option<PathBuf>,
+    instances: Option<PathBuf>,
+    expected_output: Option<PathBuf>,
+    output_tolerance: OutputTolerance,
 ) -> Result<bool, EZKLError> {
     let circuit_settings = GraphSettings::load(&settings_path)?;
 
     let logrows = circuit_settings.run_args.logrows;
-    let commitment = circuit_settings.run_args.commitment.into();
-
-    match commitment {
-        Commitments::KZG => {
-            let proof = Snark::load::<KZGCommitmentScheme<Bn256>>(&proof_path)?;
-            let params: ParamsKZG<Bn256> = if reduced_srs {
-                // only need G_0 for the verification with shplonk
-                load_params_verifier::<KZGCommitmentScheme<Bn256>>(srs_path, 1, Commitments::KZG)?
-            } else {
-                load_params_verifier::<KZGCommitmentScheme<Bn256>>(
-                    srs_path,
-                    logrows,
-                    Commitments::KZG,
-                )?
-            };
-            match proof.transcript_type {
-                TranscriptType::EVM => {
-                    verify_commitment::<
-                        KZGCommitmentScheme<Bn256>,
-                        VerifierSHPLONK<'_, Bn256>,
-                        _,
-                        KZGSingleStrategy<_>,
-                        EvmTranscript<G1Affine, _, _, _>,
-                        GraphCircuit,
-                        _,
-                    >(proof_path, circuit_settings, vk_path, &params, logrows)
-                }
-                TranscriptType::Poseidon => {
-                    verify_commitment::<
-                        KZGCommitmentScheme<Bn256>,
-                        VerifierSHPLONK<'_, Bn256>,
-                        _,
-                        KZGSingleStrategy<_>,
-                        PoseidonTranscript<NativeLoader, _>,
-                        GraphCircuit,
-                        _,
-                    >(proof_path, circuit_settings, vk_path, &params, logrows)
-                }
-            }
+    let commitment: Commitments = circuit_settings.run_args.commitment.into();
+    let mut proof = Snark::<Fr, G1Affine>::load::<KZGCommitmentScheme<Bn256>>(&proof_path)?;
+    let context = match &context {
+        Some(context) => Some(bound_context(context.as_bytes(), proof.metadata()?)),
+        None => None,
+    };
+    let weights_root = match weights_root {
+        Some(path) => Some(serde_json::from_str::<Fr>(&std::fs::read_to_string(path)?)?),
+        None => None,
+    };
+    let dataset_root = match dataset {
+        Some(path) => Some(DatasetCommitment::from_path(path)?.root),
+        None => None,
+    };
+    let instance_data = match instances {
+        Some(path) => Some(InstanceData::from_path(path)?),
+        None => None,
+    };
+    let expected_outputs = match expected_output {
+        Some(path) => Some(load_expected_outputs(&path)?),
+        None => None,
+    };
+    // the claimed public inputs and outputs, if supplied, replace the ones the proof carries
+    let check_instances = |instances: &mut [Vec<Fr>]| -> Result<(), GraphError> {
+        if let Some(data) = &instance_data {
+            circuit_settings.replace_instances(instances, data)?;
         }
-        Commitments::IPA => {
-            let proof = Snark::load::<IPACommitmentScheme<G1Affine>>(&proof_path)?;
-            let params: ParamsIPA<_> = load_params_verifier::<IPACommitmentScheme<G1Affine>>(
-                srs_path,
-                logrows,
-                Commitments::IPA,
+        let instances = instances.concat();
+        circuit_settings.check_context(&instances, context.as_deref())?;
+        circuit_settings.check_weights_root(&instances, weights_root)?;
+        if let Some(root) = dataset_root {
+            let index = circuit_settings.check_dataset_root(&instances, root)?;
+            info!("the proof opens row {} of the dataset", index);
+        }
+        if let Some(expected) = &expected_outputs {
+            let check = check_output_tensors(
+                &circuit_settings.output_instances(&instances)?,
+                expected,
+                &circuit_settings.model_output_scales,
+                &output_tolerance,
             )?;
-            match proof.transcript_type {
-                TranscriptType::EVM => {
-                    verify_commitment::<
-                        IPACommitmentScheme<G1Affine>,
-                        VerifierIPA<_>,
-                        _,
-                        IPASingleStrategy<_>,
-                        EvmTranscript<G1Affine, _, _, _>,
-                        GraphCircuit,
-                        _,
-                    >(proof_path, circuit_settings, vk_path, &params, logrows)
-                }
-                TranscriptType::Poseidon => {
-                    verify_commitment::<
-                        IPACommitmentScheme<G1Affine>,
-                        VerifierIPA<_>,
-                        _,
-                        IPASingleStrategy<_>,
-                        PoseidonTranscript<NativeLoader, _>,
-                        GraphCircuit,
-                        _,
-                    >(proof_path, circuit_settings, vk_path, &params, logrows)
-                }
-            }
+            info!("expected outputs: {}", check);
+            check.into_result()?;
         }
+        Ok(())
+    };
+
+    check_instances(&mut proof.instances)?;
+
+    let proof_bytes =
+        std::fs::read(&proof_path).map_err(|e| PfsysError::LoadProof(format!("{}", e)))?;
+    let vk_bytes = std::fs::read(&vk_path).map_err(|e| PfsysError::LoadVk(format!("{}", e)))?;
+    let params_bytes = if reduced_srs && commitment == Commitments::KZG {
+        // only need G_0 for the verification with shplonk
+        let params = load_params_verifier::<KZGCommitmentScheme<Bn256>>(srs_path, 1, commitment)?;
+        let mut buf = vec![];
+        params.write(&mut buf)?;
+        buf
+    } else {
+        let srs_path = get_srs_path(logrows, srs_path, commitment);
+        std::fs::read(&srs_path).map_err(|_| SrsError::LoadError(srs_path))?
+    };
+
+    let now = Instant::now();
+    let verified = verify_inference_proof(
+        &circuit_settings,
+        &vk_bytes,
+        &params_bytes,
+        &proof_bytes,
+        &proof.instances,
+    )?;
+    let elapsed = now.elapsed();
+    info!(
+        "verify took {}.{}",
+        elapsed.as_secs(),
+        elapsed.subsec_millis()
+    );
+    info!("verified: {}", verified);
+    if !verified {
+        return Err(PfsysError::ProofRejected.into());
     }
+    Ok(true)
 }
 
 fn verify_commitment<
@@ -2376,7 +3111,7 @@ fn verify_commitment<
     C: Circuit<<Scheme as CommitmentScheme>::Scalar, Params = Params>,
     Params,
 >(
-    proof_path: PathBuf,
+    proof: &Snark<Scheme::Scalar, Scheme::Curve>,
     settings: Params,
     vk_path: PathBuf,
     params: &'a Scheme::ParamsVerifier,
@@ -2391,14 +3126,11 @@ where
     Scheme::Curve: SerdeObject + Serialize + DeserializeOwned,
     Scheme::ParamsVerifier: 'a,
 {
-    let proof = Snark::load::<Scheme>(&proof_path)?;
-
     let strategy = Strategy::new(params);
     let vk = load_vk::<Scheme, C>(vk_path, settings)?;
     let now = Instant::now();
 
-    let result =
-        verify_proof_circuit::<V, _, _, _, TR>(&proof, params, &vk, strategy, 1 << logrows);
+    let result = verify_proof_circuit::<V, _, _, _, TR>(proof, params, &vk, strategy, 1 << logrows);
 
     let elapsed = now.elapsed();
     info!(
@@ -2440,18 +3172,16 @@ pub(crate) fn verify_aggr(
                     EvmTranscript<_, _, _, _>,
                     AggregationCircuit,
                     _,
-                >(proof_path, (), vk_path, &params, logrows),
-                TranscriptType::Poseidon => {
-                    verify_commitment::<
-                        KZGCommitmentScheme<Bn256>,
-                        VerifierSHPLONK<'_, Bn256>,
-                        _,
-                        KZGAccumulatorStrategy<_>,
-                        PoseidonTranscript<NativeLoader, _>,
-                        AggregationCircuit,
-                        _,
-                    >(proof_path, (), vk_path, &params, logrows)
-                }
+                >(&proof, (), vk_path, &params, logrows),
+                TranscriptType::Poseidon => verify_commitment::<
+                    KZGCommitmentScheme<Bn256>,
+                    VerifierSHPLONK<'_, Bn256>,
+                    _,
+                    KZGAccumulatorStrategy<_>,
+                    PoseidonTranscript<NativeLoader, _>,
+                    AggregationCircuit,
+                    _,
+                >(&proof, (), vk_path, &params, logrows),
             }
         }
         Commitments::IPA => {
@@ -2470,18 +3200,16 @@ pub(crate) fn verify_aggr(
                     EvmTranscript<_, _, _, _>,
                     AggregationCircuit,
                     _,
-                >(proof_path, (), vk_path, &params, logrows),
-                TranscriptType::Poseidon => {
-                    verify_commitment::<
-                        IPACommitmentScheme<G1Affine>,
-                        VerifierIPA<_>,
-                        _,
-                        IPAAccumulatorStrategy<_>,
-                        PoseidonTranscript<NativeLoader, _>,
-                        AggregationCircuit,
-                        _,
-                    >(proof_path, (), vk_path, &params, logrows)
-                }
+                >(&proof, (), vk_path, &params, logrows),
+                TranscriptType::Poseidon => verify_commitment::<
+                    IPACommitmentScheme<G1Affine>,
+                    VerifierIPA<_>,
+                    _,
+                    IPAAccumulatorStrategy<_>,
+                    PoseidonTranscript<NativeLoader, _>,
+                    AggregationCircuit,
+                    _,
+                >(&proof, (), vk_path, &params, logrows),
             }
         }
     }
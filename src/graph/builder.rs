@@ -0,0 +1,569 @@
+use std::path::PathBuf;
+
+use halo2_proofs::poly::kzg::commitment::KZGCommitmentScheme;
+use halo2curves::bn256::Bn256;
+
+use super::errors::GraphError;
+use super::input::{DataSource, GraphData};
+use super::{
+    scale_to_multiplier, GraphCircuit, GraphWitness, Model, NodeType, SupportedOp, Visibility,
+};
+use crate::circuit::region::RegionSettings;
+use crate::circuit::table::Range;
+use crate::fieldutils::IntegerRep;
+use crate::RunArgs;
+use log::warn;
+
+/// The default lookup safety margin, matching the CLI's `calibrate-settings`
+const DEFAULT_LOOKUP_SAFETY_MARGIN: f64 = 2.0;
+
+/// The bits the lookup range needs above the scale: a sign bit and one integer bit, so that
+/// values up to 2 in magnitude fit. Larger inputs lower the input scale, see [RunArgs::input_range]
+const MIN_BITS_ABOVE_SCALE: u32 = 2;
+
+/// Builds a [GraphCircuit] from an onnx model, ready to be configured and laid out.
+///
+/// Starts from the default [RunArgs] (or the ones passed to [OnnxModelBuilder::run_args]) and
+/// applies each setting on top of them. Settings are only checked against each other in
+/// [OnnxModelBuilder::build], which is also where the model is loaded and calibrated.
+///
+/// ```ignore
+/// let circuit = OnnxModelBuilder::new("network.onnx")
+///     .scale(10)
+///     .bits(16)
+///     .visibility(Visibility::Hashed { hash_is_public: true, outlets: vec![] }, Visibility::Fixed)
+///     .calibrate_with(batches)
+///     .build()?;
+/// ```
+#[derive(Clone, Debug)]
+pub struct OnnxModelBuilder {
+    model_path: PathBuf,
+    run_args: RunArgs,
+    bits: Option<u32>,
+    calibration: Option<Vec<GraphData>>,
+    lookup_safety_margin: f64,
+    max_logrows: Option<u32>,
+    allowed_unconstrained: Vec<String>,
+    fit_scale_to_bits: bool,
+}
+
+impl OnnxModelBuilder {
+    /// Starts a builder for the onnx model at `model_path`
+    pub fn new(model_path: impl Into<PathBuf>) -> Self {
+        Self {
+            model_path: model_path.into(),
+            run_args: RunArgs::default(),
+            bits: None,
+            calibration: None,
+            lookup_safety_margin: DEFAULT_LOOKUP_SAFETY_MARGIN,
+            max_logrows: None,
+            allowed_unconstrained: vec![],
+            fit_scale_to_bits: false,
+        }
+    }
+
+    /// Replaces the [RunArgs] the other settings are applied to
+    pub fn run_args(mut self, run_args: RunArgs) -> Self {
+        self.run_args = run_args;
+        self
+    }
+
+    /// Sets the fixed point scale (as a power of two) of the inputs and params
+    pub fn scale(mut self, scale: crate::Scale) -> Self {
+        self.run_args.input_scale = scale;
+        self.run_args.param_scale = scale;
+        self
+    }
+
+    /// Sets the lookup range to signed `bits`-bit integers, i.e. `[-2^(bits-1), 2^(bits-1)]`
+    pub fn bits(mut self, bits: u32) -> Self {
+        self.bits = Some(bits);
+        self
+    }
+
+    /// Lowers the scale to fit [OnnxModelBuilder::bits] when it leaves too little room above it,
+    /// and rebases back to that scale after every op rather than letting scales grow. Meant for
+    /// quick prototyping proofs on a small lookup range, at the cost of accuracy: the lowered scale
+    /// is logged along with how far the nodes diverge from float execution on the first batch of
+    /// calibration data, see `ezkl trace --compare-float`.
+    pub fn fit_scale_to_bits(mut self) -> Self {
+        self.fit_scale_to_bits = true;
+        self
+    }
+
+    /// Sets the range of the model's float inputs, see [RunArgs::input_range]. When unset, it is
+    /// taken from the calibration data if there is any.
+    pub fn input_range(mut self, min: f64, max: f64) -> Self {
+        self.run_args.input_range = Some((min, max));
+        self
+    }
+
+    /// Sets the visibility of the model's inputs and outputs, and of its params
+    pub fn visibility(mut self, io: Visibility, params: Visibility) -> Self {
+        self.run_args.input_visibility = io.clone();
+        self.run_args.output_visibility = io;
+        self.run_args.param_visibility = params;
+        self
+    }
+
+    /// Calibrates the lookup range, logrows and per-node output bounds on `data` at build time,
+    /// each item being one batch of inputs. Overrides [OnnxModelBuilder::bits].
+    pub fn calibrate_with(mut self, data: impl IntoIterator<Item = GraphData>) -> Self {
+        self.calibration = Some(data.into_iter().collect());
+        self
+    }
+
+    /// Sets how much the calibrated ranges are widened by, see `calibrate-settings`
+    pub fn lookup_safety_margin(mut self, margin: f64) -> Self {
+        self.lookup_safety_margin = margin;
+        self
+    }
+
+    /// Caps the logrows calibration can pick
+    pub fn max_logrows(mut self, max_logrows: Option<u32>) -> Self {
+        self.max_logrows = max_logrows;
+        self
+    }
+
+    /// Lets the given onnx op types through the up-front check for ops the circuit doesn't support.
+    /// Other unsupported ops fail [OnnxModelBuilder::build] before any layout, naming the node;
+    /// allowed ones are left to the layout, which only succeeds if they never need constraining.
+    pub fn allow_unconstrained(mut self, ops: &[&str]) -> Self {
+        self.allowed_unconstrained
+            .extend(ops.iter().map(|op| op.to_string()));
+        self
+    }
+
+    /// Checks the settings, loads the model and calibrates it if there is calibration data
+    pub fn build(self) -> Result<GraphCircuit, GraphError> {
+        Ok(self.build_with_witnesses()?.0)
+    }
+
+    /// Like [OnnxModelBuilder::build], also returning the witness of each calibration batch
+    pub fn build_with_witnesses(self) -> Result<(GraphCircuit, Vec<GraphWitness>), GraphError> {
+        let mut run_args = self.run_args;
+        let mut lowered_scale = None;
+        if let Some(bits) = self.bits {
+            let scale = std::cmp::max(run_args.input_scale, run_args.param_scale);
+            let fitted = bits as i64 - MIN_BITS_ABOVE_SCALE as i64;
+            if fitted < scale as i64 && self.fit_scale_to_bits && fitted >= 0 {
+                let fitted = fitted as crate::Scale;
+                run_args.input_scale = std::cmp::min(run_args.input_scale, fitted);
+                run_args.param_scale = std::cmp::min(run_args.param_scale, fitted);
+                run_args.scale_rebase_multiplier = 1;
+                lowered_scale = Some((scale, fitted));
+            } else if bits as i64 <= scale as i64 {
+                return Err(GraphError::InvalidRunArgs(format!(
+                    "bits ({}) must be larger than the scale ({})",
+                    bits, scale
+                )));
+            } else if fitted < scale as i64 {
+                return Err(GraphError::InvalidRunArgs(format!(
+                    "bits ({}) must be at least the scale ({}) plus {} to hold the inputs, lower the scale or fit it to the bits",
+                    bits, scale, MIN_BITS_ABOVE_SCALE
+                )));
+            }
+            if !(2..IntegerRep::BITS).contains(&bits) {
+                return Err(GraphError::InvalidRunArgs(format!(
+                    "bits ({}) must be between 2 and {}",
+                    bits,
+                    IntegerRep::BITS - 1
+                )));
+            }
+            let half: IntegerRep = 1 << (bits - 1);
+            run_args.lookup_range = (-half, half);
+        }
+        let input_range = run_args
+            .input_range
+            .or_else(|| self.calibration.as_deref().and_then(float_input_range));
+        // calibrating refits the lookup range to the witnesses, so only the bits bound it then
+        if let Some(range) =
+            input_range.filter(|_| self.bits.is_some() || self.calibration.is_none())
+        {
+            match fitting_input_scale(range, run_args.lookup_range, run_args.input_scale) {
+                Some(scale) if scale < run_args.input_scale => {
+                    warn!(
+                        "lowered the input scale from {} to {} so that inputs in [{}, {}] fit the lookup range {:?}",
+                        run_args.input_scale, scale, range.0, range.1, run_args.lookup_range
+                    );
+                    run_args.input_scale = scale;
+                }
+                Some(_) => {}
+                None => {
+                    return Err(GraphError::InvalidRunArgs(format!(
+                        "inputs in [{}, {}] don't fit the lookup range {:?} at any input scale, widen it or raise the bits",
+                        range.0, range.1, run_args.lookup_range
+                    )));
+                }
+            }
+        }
+        if matches!(&self.calibration, Some(data) if data.is_empty()) {
+            return Err(GraphError::InvalidRunArgs(
+                "calibration needs at least one batch of data".into(),
+            ));
+        }
+        run_args.validate().map_err(GraphError::InvalidRunArgs)?;
+
+        let model = Model::from_run_args(&run_args, &self.model_path)?;
+        check_unconstrained(&model, &self.allowed_unconstrained)?;
+
+        let mut circuit = GraphCircuit::new(model, &run_args)?;
+        let witnesses = match &self.calibration {
+            Some(data) => calibrate(
+                &mut circuit,
+                data,
+                self.lookup_safety_margin,
+                self.max_logrows,
+            )?,
+            None => vec![],
+        };
+        circuit.check_advice_width()?;
+
+        if let Some((scale, fitted)) = lowered_scale {
+            let divergence = match self.calibration.as_ref().and_then(|data| data.first()) {
+                Some(data) => node_divergence(&mut circuit, &self.model_path, data)?
+                    .map_or("unknown".to_string(), |d| format!("{:.6}", d)),
+                None => "unknown without calibration data".to_string(),
+            };
+            warn!(
+                "lowered the scale from {} to {} to fit {} bits, nodes diverge from float execution by up to {}",
+                scale,
+                fitted,
+                self.bits.unwrap_or_default(),
+                divergence
+            );
+        }
+        Ok((circuit, witnesses))
+    }
+}
+
+/// The largest scale, up to `scale`, at which inputs in `range` quantize within `lookup_range`
+pub(crate) fn fitting_input_scale(
+    (min, max): (f64, f64),
+    lookup_range: Range,
+    scale: crate::Scale,
+) -> Option<crate::Scale> {
+    let fits = |s: crate::Scale| {
+        let multiplier = scale_to_multiplier(s);
+        min * multiplier >= lookup_range.0 as f64 && max * multiplier <= lookup_range.1 as f64
+    };
+    if fits(scale) {
+        return Some(scale);
+    }
+    (0..scale).rev().find(|s| fits(*s))
+}
+
+/// The range of the float inputs of every batch in `data`, if they hold any
+fn float_input_range(data: &[GraphData]) -> Option<(f64, f64)> {
+    data.iter()
+        .filter(|batch| batch.input_data_int.is_none())
+        .filter_map(|batch| match &batch.input_data {
+            DataSource::File(inputs) => Some(inputs),
+            _ => None,
+        })
+        .flatten()
+        .flatten()
+        .filter(|x| x.is_float())
+        .map(|x| x.to_float())
+        .fold(None, |range, x| match range {
+            Some((min, max)) => Some((f64::min(min, x), f64::max(max, x))),
+            None => Some((x, x)),
+        })
+}
+
+/// The largest divergence of any node's outputs from float execution of the model on `data`
+fn node_divergence(
+    circuit: &mut GraphCircuit,
+    model_path: &std::path::Path,
+    data: &GraphData,
+) -> Result<Option<f32>, GraphError> {
+    let run_args = circuit.settings().run_args.clone();
+    let inputs = circuit.load_graph_from_file_exclusively(data)?;
+    let res = circuit.model().forward_with_trace(
+        &inputs,
+        &run_args,
+        RegionSettings::all_true(run_args.decomp_base, run_args.decomp_legs),
+    )?;
+    let mut trace = res.trace.unwrap_or_default();
+    let float = Model::run_onnx_trace(
+        &run_args,
+        model_path,
+        data,
+        circuit.model().graph.input_shapes()?,
+    )?;
+    trace.compare(&float);
+    Ok(trace
+        .nodes
+        .values()
+        .filter_map(|n| n.max_divergence)
+        .reduce(f32::max))
+}
+
+/// Fails on the first node whose op the circuit can't constrain, unless its op is in `allowed`
+fn check_unconstrained(model: &Model, allowed: &[String]) -> Result<(), GraphError> {
+    for node in model.graph.nodes.values() {
+        if let NodeType::Node(n) = node {
+            if let SupportedOp::Unknown(op) = &n.opkind {
+                if !allowed.contains(&op.op) {
+                    return Err(GraphError::UnconstrainedOp(n.idx, op.op.clone()));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Runs every batch through the circuit and fits its settings to what they saw
+fn calibrate(
+    circuit: &mut GraphCircuit,
+    data: &[GraphData],
+    lookup_safety_margin: f64,
+    max_logrows: Option<u32>,
+) -> Result<Vec<GraphWitness>, GraphError> {
+    let run_args = circuit.settings().run_args.clone();
+    let witnesses = data
+        .iter()
+        .map(|batch| {
+            let mut inputs = circuit.load_graph_from_file_exclusively(batch)?;
+            circuit.forward::<KZGCommitmentScheme<Bn256>>(
+                &mut inputs,
+                None,
+                None,
+                RegionSettings::all_true(run_args.decomp_base, run_args.decomp_legs),
+            )
+        })
+        .collect::<Result<Vec<_>, GraphError>>()?;
+    circuit.fit_to_witnesses(&witnesses, lookup_safety_margin, max_logrows)?;
+    Ok(witnesses)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::tests::{prove, setup, verify};
+
+    const RELU: &str = "examples/onnx/1l_relu/network.onnx";
+
+    fn build_err(builder: OnnxModelBuilder) -> String {
+        match builder.build() {
+            Ok(_) => panic!("expected the build to fail"),
+            Err(e) => e.to_string(),
+        }
+    }
+
+    #[test]
+    fn builds_with_settings_applied() {
+        let circuit = OnnxModelBuilder::new(RELU)
+            .scale(5)
+            .bits(12)
+            .visibility(Visibility::Public, Visibility::Fixed)
+            .build()
+            .unwrap();
+        let run_args = &circuit.settings().run_args;
+        assert_eq!((run_args.input_scale, run_args.param_scale), (5, 5));
+        assert_eq!(run_args.lookup_range, (-2048, 2048));
+        assert_eq!(run_args.input_visibility, Visibility::Public);
+        assert_eq!(run_args.output_visibility, Visibility::Public);
+        assert_eq!(run_args.param_visibility, Visibility::Fixed);
+    }
+
+    #[test]
+    fn bits_must_exceed_the_scale() {
+        let err = build_err(OnnxModelBuilder::new(RELU).scale(16).bits(12));
+        assert!(
+            err.contains("bits (12) must be larger than the scale (16)"),
+            "{}",
+            err
+        );
+        let err = build_err(OnnxModelBuilder::new(RELU).scale(7).bits(200));
+        assert!(err.contains("must be between 2 and 127"), "{}", err);
+    }
+
+    #[test]
+    fn bits_must_leave_room_above_the_scale() {
+        let err = build_err(OnnxModelBuilder::new(RELU).scale(7).bits(8));
+        assert!(
+            err.contains("bits (8) must be at least the scale (7) plus 2"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn small_bits_fit_the_mlp() {
+        let data = GraphData::from_path("examples/onnx/1l_mlp/input.json".into()).unwrap();
+        let mut circuit = OnnxModelBuilder::new("examples/onnx/1l_mlp/network.onnx")
+            .scale(7)
+            .bits(8)
+            .fit_scale_to_bits()
+            .build()
+            .unwrap();
+        let run_args = circuit.settings().run_args.clone();
+        assert_eq!((run_args.input_scale, run_args.param_scale), (6, 6));
+        assert_eq!(run_args.scale_rebase_multiplier, 1);
+        assert_eq!(run_args.lookup_range, (-128, 128));
+
+        let mut inputs = circuit.load_graph_from_file_exclusively(&data).unwrap();
+        let witness = circuit
+            .forward::<KZGCommitmentScheme<Bn256>>(
+                &mut inputs,
+                None,
+                None,
+                RegionSettings::all_true(run_args.decomp_base, run_args.decomp_legs),
+            )
+            .unwrap();
+        circuit.load_graph_witness(&witness).unwrap();
+        let public_inputs = circuit.prepare_public_inputs(&witness).unwrap();
+        halo2_proofs::dev::MockProver::run(run_args.logrows, &circuit, vec![public_inputs.clone()])
+            .unwrap()
+            .assert_satisfied();
+
+        // and the lossy circuit proves and verifies with real keys
+        let (params, pk) = setup(&circuit);
+        let snark = prove(&circuit, public_inputs, &params, &pk);
+        assert!(verify(&snark, &params, pk.get_vk()).is_ok());
+    }
+
+    #[test]
+    fn params_cannot_be_public() {
+        let err = build_err(
+            OnnxModelBuilder::new(RELU).visibility(Visibility::Private, Visibility::Public),
+        );
+        assert!(err.contains("params cannot be public"), "{}", err);
+    }
+
+    #[test]
+    fn calibration_needs_data() {
+        let err = build_err(OnnxModelBuilder::new(RELU).calibrate_with(vec![]));
+        assert!(err.contains("at least one batch"), "{}", err);
+    }
+
+    #[test]
+    fn calibration_fits_the_lookup_range() {
+        let data = GraphData::from_path("examples/onnx/1l_relu/input.json".into()).unwrap();
+        let (circuit, witnesses) = OnnxModelBuilder::new(RELU)
+            .run_args(RunArgs {
+                lookup_range: (IntegerRep::MIN, IntegerRep::MAX),
+                ..RunArgs::default()
+            })
+            .calibrate_with(vec![data])
+            .build_with_witnesses()
+            .unwrap();
+        assert_eq!(witnesses.len(), 1);
+        let (min, max) = circuit.settings().run_args.lookup_range;
+        assert!(
+            min > IntegerRep::MIN && max < IntegerRep::MAX,
+            "{:?}",
+            (min, max)
+        );
+    }
+
+    #[test]
+    fn settings_and_calibration_fit_the_input_range() {
+        use crate::commands::CalibrationTarget;
+        use crate::graph::GraphSettings;
+
+        let fixture = crate::dev_fixtures::cifar_classifier(2, 8, 4);
+        let dir = fixture.write().unwrap();
+        let (model, data, settings) = (
+            dir.join("network.onnx"),
+            dir.join("pixels.json"),
+            dir.join("settings.json"),
+        );
+        fixture.pixel_data().save(data.clone()).unwrap();
+        let run_args = RunArgs {
+            input_range: Some((0.0, 255.0)),
+            lookup_range: (-8192, 8192),
+            ..RunArgs::default()
+        };
+        crate::execute::gen_circuit_settings(model.clone(), settings.clone(), run_args, false)
+            .unwrap();
+        assert_eq!(
+            GraphSettings::load(&settings).unwrap().run_args.input_scale,
+            5
+        );
+
+        // none of the scales tried fit, so each is lowered to the one that does
+        let calibrated = tokio::runtime::Runtime::new()
+            .unwrap()
+            .block_on(crate::execute::calibrate(
+                model,
+                data,
+                settings,
+                CalibrationTarget::default(),
+                DEFAULT_LOOKUP_SAFETY_MARGIN,
+                Some(vec![7, 8]),
+                vec![1],
+                false,
+                None,
+            ))
+            .unwrap();
+        assert_eq!(calibrated.run_args.input_scale, 5);
+    }
+
+    #[test]
+    fn circuit_stats_of_the_mlp() {
+        let circuit = OnnxModelBuilder::new("examples/onnx/1l_mlp/network.onnx")
+            .build()
+            .unwrap();
+        let stats = circuit.circuit_stats();
+        let settings = circuit.settings();
+        let (num_inner_cols, logrows) =
+            (settings.run_args.num_inner_cols, settings.run_args.logrows);
+        // only the outputs are public
+        assert_eq!(stats.num_instance_columns, 1, "{}", stats);
+        // the two inputs and the output of the base gates, each a single block of columns
+        assert_eq!(stats.num_advice_columns, 3 * num_inner_cols, "{}", stats);
+        assert_eq!(stats.num_advice_columns, settings.advice_width(logrows));
+        // add, sub, mult and is-boolean per column, and the six accumulating ops of the block
+        assert_eq!(stats.num_gates, 4 * num_inner_cols + 6, "{}", stats);
+
+        // at 2^17 rows every table and range check fits in one column. Tables over the same range
+        // share their input column, and the constants take one more
+        let lookup_ranges = settings
+            .required_lookups
+            .iter()
+            .map(|op| {
+                settings
+                    .lookup_range(op)
+                    .unwrap_or(settings.run_args.lookup_range)
+            })
+            .collect::<std::collections::HashSet<_>>();
+        let num_tables = settings.required_lookups.len() + settings.required_range_checks.len();
+        assert!(num_tables > 0, "{:?}", settings.required_lookups);
+        assert_eq!(
+            stats.num_fixed_columns,
+            (settings.total_const_size > 0) as usize + lookup_ranges.len() + num_tables,
+            "{}",
+            stats
+        );
+        // each column of the block looks up each table. mv-lookup merges lookups into the same
+        // table up to the circuit's degree
+        #[cfg(not(feature = "mv-lookup"))]
+        assert_eq!(stats.num_lookups, num_inner_cols * num_tables, "{}", stats);
+        #[cfg(feature = "mv-lookup")]
+        assert!(
+            (num_tables..=num_inner_cols * num_tables).contains(&stats.num_lookups),
+            "{}",
+            stats
+        );
+        assert_eq!(stats.num_shuffles, 0);
+
+        // a 3 -> 4 gemm and its relu are laid out in far fewer rows than the circuit has
+        assert!(
+            settings.num_rows > 0 && settings.num_rows < 1 << 10,
+            "{}",
+            settings.num_rows
+        );
+        assert!(stats.minimum_rows <= 1 << logrows, "{}", stats);
+        // configuring is deterministic, so the counts are stable across runs
+        let again = circuit.circuit_stats();
+        assert_eq!(
+            crate::graph::CircuitStats {
+                configure_time: stats.configure_time,
+                ..again
+            },
+            stats
+        );
+    }
+}
@@ -0,0 +1,295 @@
+use super::errors::GraphError;
+use super::proto::{
+    name_of, parse, read_varint, rewrite, string, write_field, ATTRIBUTE_GRAPH, ATTRIBUTE_GRAPHS,
+    ATTRIBUTE_NAME, ATTRIBUTE_TENSOR, GRAPH_INITIALIZER, GRAPH_NODE, GRAPH_OUTPUT, NODE_ATTRIBUTE,
+    NODE_INPUT, NODE_NAME, NODE_OP_TYPE, NODE_OUTPUT, TENSOR_INT32_DATA, TENSOR_NAME,
+    TENSOR_RAW_DATA, VALUE_INFO_NAME, WIRE_LEN,
+};
+use log::info;
+use std::collections::{BTreeMap, HashMap};
+
+/// Subgraph bearing ops the rest of the loader knows how to lay out.
+const SUPPORTED_SUBGRAPH_OPS: [&str; 1] = ["Scan"];
+
+/// The parts of a `NodeProto` the pass looks at.
+struct Node<'a> {
+    name: String,
+    op: String,
+    inputs: Vec<String>,
+    outputs: Vec<String>,
+    /// Each graph the node holds, by the name of the attribute holding it.
+    subgraphs: Vec<(String, &'a [u8])>,
+    /// The `value` of a `Constant`.
+    value: Option<&'a [u8]>,
+}
+
+impl<'a> Node<'a> {
+    fn decode(msg: &'a [u8]) -> Result<Self, GraphError> {
+        let mut node = Node {
+            name: String::new(),
+            op: String::new(),
+            inputs: vec![],
+            outputs: vec![],
+            subgraphs: vec![],
+            value: None,
+        };
+        for field in parse(msg)? {
+            match field.number {
+                NODE_INPUT => node.inputs.push(string(field.value)),
+                NODE_OUTPUT => node.outputs.push(string(field.value)),
+                NODE_NAME => node.name = string(field.value),
+                NODE_OP_TYPE => node.op = string(field.value),
+                NODE_ATTRIBUTE => {
+                    let fields = parse(field.value)?;
+                    let name = fields
+                        .iter()
+                        .find(|f| f.number == ATTRIBUTE_NAME)
+                        .map(|f| string(f.value))
+                        .unwrap_or_default();
+                    for field in fields {
+                        match field.number {
+                            ATTRIBUTE_GRAPH | ATTRIBUTE_GRAPHS => {
+                                node.subgraphs.push((name.clone(), field.value))
+                            }
+                            ATTRIBUTE_TENSOR if name == "value" => node.value = Some(field.value),
+                            _ => {}
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(node)
+    }
+
+    /// How the node is referred to in logs and errors.
+    fn label(&self) -> &str {
+        match self.name.is_empty() {
+            true => self.outputs.first().map_or("", |o| o.as_str()),
+            false => &self.name,
+        }
+    }
+}
+
+/// The first element of a condition tensor, if it holds one.
+fn truth(tensor: &[u8]) -> Result<Option<bool>, GraphError> {
+    for field in parse(tensor)? {
+        match field.number {
+            TENSOR_RAW_DATA => return Ok(field.value.first().map(|b| *b != 0)),
+            TENSOR_INT32_DATA => return Ok(Some(read_varint(field.value, &mut 0)? != 0)),
+            _ => {}
+        }
+    }
+    Ok(None)
+}
+
+/// Counts the ops of `graph`, and of the graphs nested in it.
+fn op_inventory(graph: &[u8], ops: &mut BTreeMap<String, usize>) -> Result<(), GraphError> {
+    for field in parse(graph)?.iter().filter(|f| f.number == GRAPH_NODE) {
+        let node = Node::decode(field.value)?;
+        *ops.entry(node.op).or_default() += 1;
+        for (_, subgraph) in node.subgraphs {
+            op_inventory(subgraph, ops)?;
+        }
+    }
+    Ok(())
+}
+
+/// Renames the values a node (and any graph nested in it) reads and writes.
+fn rename_node(
+    node: &[u8],
+    renames: &HashMap<String, String>,
+    prefix: Option<&str>,
+) -> Result<Vec<u8>, GraphError> {
+    rewrite(node, |field| match field.number {
+        NODE_INPUT | NODE_OUTPUT => Ok(renames.get(&string(field.value)).map(|n| n.clone().into())),
+        NODE_NAME => Ok(prefix.map(|p| format!("{}/{}", p, string(field.value)).into())),
+        NODE_ATTRIBUTE => rewrite(field.value, |field| match field.number {
+            ATTRIBUTE_GRAPH | ATTRIBUTE_GRAPHS => {
+                rewrite(field.value, |field| match field.number {
+                    GRAPH_NODE => rename_node(field.value, renames, None).map(Some),
+                    _ => Ok(None),
+                })
+                .map(Some)
+            }
+            _ => Ok(None),
+        })
+        .map(Some),
+        _ => Ok(None),
+    })
+}
+
+/// The nodes and initializers that replace an `If` by its `branch`, writing the branch's outputs
+/// to the `If`'s. Values the branch defines are prefixed with the `If`'s name so they can't clash
+/// with those of the enclosing graph.
+fn inline_branch(
+    node: &Node,
+    branch: &[u8],
+    num_inlined: &mut usize,
+) -> Result<(Vec<Vec<u8>>, Vec<Vec<u8>>), GraphError> {
+    let inlined = inline_graph(branch, num_inlined)?;
+    let fields = parse(inlined.as_deref().unwrap_or(branch))?;
+    let prefix = node.label();
+
+    let mut renames = HashMap::new();
+    for field in &fields {
+        let defined = match field.number {
+            GRAPH_NODE => Node::decode(field.value)?.outputs,
+            GRAPH_INITIALIZER => vec![name_of(field.value, TENSOR_NAME)?],
+            _ => continue,
+        };
+        for name in defined.into_iter().filter(|n| !n.is_empty()) {
+            renames.insert(name.clone(), format!("{}/{}", prefix, name));
+        }
+    }
+
+    let (mut nodes, mut initializers) = (vec![], vec![]);
+    for field in &fields {
+        match field.number {
+            GRAPH_NODE => nodes.push(rename_node(field.value, &renames, Some(prefix))?),
+            GRAPH_INITIALIZER => initializers.push(rewrite(field.value, |field| {
+                Ok(match field.number {
+                    TENSOR_NAME => renames.get(&string(field.value)).map(|n| n.clone().into()),
+                    _ => None,
+                })
+            })?),
+            _ => {}
+        }
+    }
+
+    let branch_outputs = fields
+        .iter()
+        .filter(|f| f.number == GRAPH_OUTPUT)
+        .map(|f| name_of(f.value, VALUE_INFO_NAME))
+        .collect::<Result<Vec<_>, _>>()?;
+    for (i, (from, to)) in branch_outputs.iter().zip(&node.outputs).enumerate() {
+        let mut identity = vec![];
+        let from = renames.get(from).unwrap_or(from);
+        write_field(&mut identity, NODE_INPUT, WIRE_LEN, from.as_bytes());
+        write_field(&mut identity, NODE_OUTPUT, WIRE_LEN, to.as_bytes());
+        let name = format!("{}/output{}", prefix, i);
+        write_field(&mut identity, NODE_NAME, WIRE_LEN, name.as_bytes());
+        write_field(&mut identity, NODE_OP_TYPE, WIRE_LEN, b"Identity");
+        nodes.push(identity);
+    }
+    Ok((nodes, initializers))
+}
+
+fn inline_graph(graph: &[u8], num_inlined: &mut usize) -> Result<Option<Vec<u8>>, GraphError> {
+    let fields = parse(graph)?;
+    let mut conditions = HashMap::new();
+    for field in &fields {
+        let (name, tensor) = match field.number {
+            GRAPH_INITIALIZER => (name_of(field.value, TENSOR_NAME)?, field.value),
+            GRAPH_NODE => match Node::decode(field.value)? {
+                Node {
+                    op,
+                    outputs,
+                    value: Some(value),
+                    ..
+                } if op == "Constant" && outputs.len() == 1 => (outputs[0].clone(), value),
+                _ => continue,
+            },
+            _ => continue,
+        };
+        if let Some(truth) = truth(tensor)? {
+            conditions.insert(name, truth);
+        }
+    }
+
+    let (mut nodes, mut initializers) = (vec![], vec![]);
+    let mut changed = false;
+    for field in fields.iter().filter(|f| f.number == GRAPH_NODE) {
+        let node = Node::decode(field.value)?;
+        let condition = node.inputs.first().and_then(|c| conditions.get(c));
+        match (node.op.as_str(), condition) {
+            ("If", Some(condition)) => {
+                let branch_name = if *condition {
+                    "then_branch"
+                } else {
+                    "else_branch"
+                };
+                let branch = node
+                    .subgraphs
+                    .iter()
+                    .find(|(name, _)| name == branch_name)
+                    .map(|(_, graph)| *graph)
+                    .ok_or_else(|| {
+                        GraphError::MisformedParams(format!(
+                            "If {} has no {}",
+                            node.label(),
+                            branch_name
+                        ))
+                    })?;
+                info!("inlining the {} of If {}", branch_name, node.label());
+                let (branch_nodes, branch_initializers) =
+                    inline_branch(&node, branch, num_inlined)?;
+                nodes.extend(branch_nodes);
+                initializers.extend(branch_initializers);
+                *num_inlined += 1;
+                changed = true;
+            }
+            _ => nodes.push(field.value.to_vec()),
+        }
+    }
+    if !changed {
+        return Ok(None);
+    }
+
+    let mut out = Vec::with_capacity(graph.len());
+    let mut nodes_written = false;
+    for field in &fields {
+        match field.number {
+            GRAPH_NODE if nodes_written => {}
+            GRAPH_NODE => {
+                for node in &nodes {
+                    write_field(&mut out, GRAPH_NODE, WIRE_LEN, node);
+                }
+                nodes_written = true;
+            }
+            _ => write_field(&mut out, field.number, field.wire_type, field.value),
+        }
+    }
+    for initializer in &initializers {
+        write_field(&mut out, GRAPH_INITIALIZER, WIRE_LEN, initializer);
+    }
+    Ok(Some(out))
+}
+
+/// Replaces each `If` whose condition is a constant by the branch it selects, then checks no
+/// control flow is left that would only be decided when the model runs: circuits have a fixed
+/// layout, so `If`s on computed conditions and `Loop`s can't be proven. The error names the op
+/// and the ops of its subgraphs, which then need refactoring out of the model. Returns the
+/// rewritten graph, or `None` if there was nothing to inline.
+pub fn inline_constant_ifs(graph: &[u8]) -> Result<Option<Vec<u8>>, GraphError> {
+    let mut num_inlined = 0;
+    let inlined = inline_graph(graph, &mut num_inlined)?;
+    if num_inlined > 0 {
+        info!("inlined {} constant If branches", num_inlined);
+    }
+
+    for node in parse(inlined.as_deref().unwrap_or(graph))?
+        .iter()
+        .filter(|f| f.number == GRAPH_NODE)
+    {
+        let node = Node::decode(node.value)?;
+        if node.subgraphs.is_empty() || SUPPORTED_SUBGRAPH_OPS.contains(&node.op.as_str()) {
+            continue;
+        }
+        let mut ops = BTreeMap::new();
+        for (_, subgraph) in &node.subgraphs {
+            op_inventory(subgraph, &mut ops)?;
+        }
+        let inventory = ops
+            .iter()
+            .map(|(op, count)| format!("{} x{}", op, count))
+            .collect::<Vec<_>>()
+            .join(", ");
+        return Err(GraphError::DynamicControlFlow(
+            node.op.clone(),
+            node.label().to_string(),
+            inventory,
+        ));
+    }
+    Ok(inlined)
+}
@@ -0,0 +1,409 @@
+use super::errors::GraphError;
+use super::proto::{
+    name_of, parse, read_varint, string, ATTRIBUTE_GRAPH, ATTRIBUTE_GRAPHS, ATTRIBUTE_INTS,
+    ATTRIBUTE_NAME, ATTRIBUTE_S, GRAPH_INITIALIZER, GRAPH_NODE, MODEL_GRAPH, NODE_ATTRIBUTE,
+    NODE_INPUT, NODE_OP_TYPE, NODE_OUTPUT, TENSOR_NAME, WIRE_LEN, WIRE_VARINT,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use tabled::{Table, Tabled};
+
+/// The onnx ops ezkl lays out with no restrictions on their attributes, either directly, as the
+/// ops tract expands them into, or after the rewrite passes that run before tract loads a model.
+const SUPPORTED_OPS: &[&str] = &[
+    "Abs",
+    "Acos",
+    "Acosh",
+    "Add",
+    "And",
+    "ArgMax",
+    "ArgMin",
+    "Asin",
+    "Asinh",
+    "Atan",
+    "Atanh",
+    "AveragePool",
+    "BatchNormalization",
+    "BitShift",
+    "BlackmanWindow",
+    "Cast",
+    "Ceil",
+    "Celu",
+    "Clip",
+    "Concat",
+    "Constant",
+    "ConstantOfShape",
+    "Cos",
+    "Cosh",
+    "DequantizeLinear",
+    "Div",
+    "Dropout",
+    "Einsum",
+    "Elu",
+    "Equal",
+    "Erf",
+    "Exp",
+    "Expand",
+    "EyeLike",
+    "Flatten",
+    "Floor",
+    "GRU",
+    "Gather",
+    "GatherElements",
+    "GatherND",
+    "Gelu",
+    "Gemm",
+    "GlobalAveragePool",
+    "Greater",
+    "GreaterOrEqual",
+    "HardSigmoid",
+    "HardSwish",
+    "Hardmax",
+    "Identity",
+    "InstanceNormalization",
+    "LSTM",
+    "LayerNormalization",
+    "LeakyRelu",
+    "Less",
+    "LessOrEqual",
+    "LinearClassifier",
+    "LinearRegressor",
+    "Log",
+    "LogSoftmax",
+    "LpPool",
+    "MatMul",
+    "Max",
+    "Mean",
+    "Min",
+    "Mish",
+    "Mod",
+    "Mul",
+    "Neg",
+    "Normalizer",
+    "Not",
+    "OneHot",
+    "Or",
+    "PRelu",
+    "Pow",
+    "QLinearConv",
+    "QLinearMatMul",
+    "QuantizeLinear",
+    "RNN",
+    "Range",
+    "Reciprocal",
+    "ReduceL1",
+    "ReduceL2",
+    "ReduceLogSumExp",
+    "ReduceMax",
+    "ReduceMean",
+    "ReduceMin",
+    "ReduceProd",
+    "ReduceSum",
+    "ReduceSumSquare",
+    "Relu",
+    "Reshape",
+    "Round",
+    "Scan",
+    "ScatterElements",
+    "ScatterND",
+    "Selu",
+    "Shape",
+    "Sigmoid",
+    "Sign",
+    "Sin",
+    "Sinh",
+    "Slice",
+    "Softmax",
+    "Softplus",
+    "Softsign",
+    "Split",
+    "Sqrt",
+    "Squeeze",
+    "Sub",
+    "Sum",
+    "Tan",
+    "Tanh",
+    "TopK",
+    "Transpose",
+    "Trilu",
+    "Unsqueeze",
+    "Where",
+    "Xor",
+    "ZipMap",
+];
+
+/// The parts of a `NodeProto` the restrictions look at.
+struct Node<'a> {
+    op: String,
+    inputs: Vec<String>,
+    outputs: Vec<String>,
+    ints: HashMap<String, Vec<u64>>,
+    strings: HashMap<String, String>,
+    subgraphs: Vec<&'a [u8]>,
+}
+
+impl<'a> Node<'a> {
+    fn decode(msg: &'a [u8]) -> Result<Self, GraphError> {
+        let mut node = Node {
+            op: String::new(),
+            inputs: vec![],
+            outputs: vec![],
+            ints: HashMap::new(),
+            strings: HashMap::new(),
+            subgraphs: vec![],
+        };
+        for field in parse(msg)? {
+            match field.number {
+                NODE_INPUT => node.inputs.push(string(field.value)),
+                NODE_OUTPUT => node.outputs.push(string(field.value)),
+                NODE_OP_TYPE => node.op = string(field.value),
+                NODE_ATTRIBUTE => {
+                    let (mut name, mut s, mut ints) = (String::new(), None, vec![]);
+                    for field in parse(field.value)? {
+                        match (field.number, field.wire_type) {
+                            (ATTRIBUTE_NAME, _) => name = string(field.value),
+                            (ATTRIBUTE_S, _) => s = Some(string(field.value)),
+                            (ATTRIBUTE_INTS, WIRE_VARINT) => {
+                                ints.push(read_varint(field.value, &mut 0)?)
+                            }
+                            (ATTRIBUTE_INTS, WIRE_LEN) => {
+                                let mut pos = 0;
+                                while pos < field.value.len() {
+                                    ints.push(read_varint(field.value, &mut pos)?);
+                                }
+                            }
+                            (ATTRIBUTE_GRAPH | ATTRIBUTE_GRAPHS, _) => {
+                                node.subgraphs.push(field.value)
+                            }
+                            _ => {}
+                        }
+                    }
+                    if let Some(s) = s {
+                        node.strings.insert(name.clone(), s);
+                    }
+                    node.ints.insert(name, ints);
+                }
+                _ => {}
+            }
+        }
+        Ok(node)
+    }
+}
+
+/// A limitation of the support for an op, which some of its nodes may run into.
+struct Restriction {
+    /// What isn't supported, e.g. `dilation≠1 unsupported`
+    what: &'static str,
+    /// Whether a node runs into it, given the tensors in scope that hold constants
+    trips: fn(&Node, &HashSet<String>) -> bool,
+}
+
+fn dilated(node: &Node, _: &HashSet<String>) -> bool {
+    node.ints
+        .get("dilations")
+        .is_some_and(|d| d.iter().any(|d| *d != 1))
+}
+
+fn returns_indices(node: &Node, _: &HashSet<String>) -> bool {
+    node.outputs.get(1).is_some_and(|o| !o.is_empty())
+}
+
+fn not_nearest(node: &Node, _: &HashSet<String>) -> bool {
+    node.strings.get("mode").is_some_and(|m| m != "nearest")
+}
+
+fn not_constant_mode(node: &Node, _: &HashSet<String>) -> bool {
+    node.strings.get("mode").is_some_and(|m| m != "constant")
+}
+
+fn dynamic_condition(node: &Node, constants: &HashSet<String>) -> bool {
+    node.inputs.first().is_some_and(|c| !constants.contains(c))
+}
+
+const UNIT_DILATIONS: Restriction = Restriction {
+    what: "dilation≠1 unsupported",
+    trips: dilated,
+};
+
+const CONV: &[Restriction] = &[UNIT_DILATIONS];
+
+const MAX_POOL: &[Restriction] = &[
+    UNIT_DILATIONS,
+    Restriction {
+        what: "indices output unsupported",
+        trips: returns_indices,
+    },
+];
+
+const RESIZE: &[Restriction] = &[Restriction {
+    what: "interpolation other than nearest unsupported",
+    trips: not_nearest,
+}];
+
+const PAD: &[Restriction] = &[Restriction {
+    what: "modes other than constant unsupported",
+    trips: not_constant_mode,
+}];
+
+// constant conditions are inlined, see [super::control_flow]
+const IF: &[Restriction] = &[Restriction {
+    what: "conditions decided at run time unsupported",
+    trips: dynamic_condition,
+}];
+
+/// The restrictions on an op's nodes, `None` if the op isn't supported at all.
+fn restrictions(op: &str) -> Option<&'static [Restriction]> {
+    match op {
+        "Conv" | "ConvTranspose" => Some(CONV),
+        "MaxPool" => Some(MAX_POOL),
+        "Resize" | "Upsample" => Some(RESIZE),
+        "Pad" => Some(PAD),
+        "If" => Some(IF),
+        op if SUPPORTED_OPS.contains(&op) => Some(&[]),
+        _ => None,
+    }
+}
+
+/// How much of an op ezkl supports.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Support {
+    /// Every node of the op can be laid out
+    Supported,
+    /// Some nodes of the op run into one of its restrictions
+    Partial,
+    /// The op can't be laid out
+    Unsupported,
+}
+
+impl std::fmt::Display for Support {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Support::Supported => write!(f, "supported"),
+            Support::Partial => write!(f, "partial"),
+            Support::Unsupported => write!(f, "unsupported"),
+        }
+    }
+}
+
+/// The support for one op type of a model.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct OpCoverage {
+    /// The onnx op type
+    pub op: String,
+    /// The number of nodes of the op, subgraphs included
+    pub count: usize,
+    /// How much of the op is supported
+    pub support: Support,
+    /// The restrictions the op's nodes run into
+    pub restrictions: Vec<String>,
+}
+
+impl Tabled for OpCoverage {
+    const LENGTH: usize = 4;
+
+    fn headers() -> Vec<std::borrow::Cow<'static, str>> {
+        ["op", "nodes", "support", "restrictions"]
+            .into_iter()
+            .map(std::borrow::Cow::Borrowed)
+            .collect()
+    }
+
+    fn fields(&self) -> Vec<std::borrow::Cow<'_, str>> {
+        vec![
+            std::borrow::Cow::Borrowed(self.op.as_str()),
+            std::borrow::Cow::Owned(self.count.to_string()),
+            std::borrow::Cow::Owned(self.support.to_string()),
+            std::borrow::Cow::Owned(
+                self.restrictions
+                    .iter()
+                    .map(|r| format!("{}: {}", self.op, r))
+                    .collect::<Vec<_>>()
+                    .join("; "),
+            ),
+        ]
+    }
+}
+
+/// Which of an onnx model's ops ezkl supports, checked against the nodes' attributes.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CoverageReport {
+    /// Each distinct op type of the model, by name
+    pub ops: Vec<OpCoverage>,
+    /// Whether every node is supported, i.e. the model can be proven today
+    pub provable: bool,
+}
+
+impl CoverageReport {
+    /// Reports on the ops of the onnx model `model`, without loading it.
+    pub fn new(model: &[u8]) -> Result<Self, GraphError> {
+        let mut ops = BTreeMap::new();
+        for field in parse(model)? {
+            if field.number == MODEL_GRAPH {
+                cover_graph(field.value, &HashSet::new(), &mut ops)?;
+            }
+        }
+        let ops = ops.into_values().collect::<Vec<OpCoverage>>();
+        let provable = ops.iter().all(|o| o.support == Support::Supported);
+        Ok(Self { ops, provable })
+    }
+
+    /// The report as a table, one row per op.
+    pub fn table(&self) -> String {
+        let mut table = Table::new(&self.ops);
+        table.with(tabled::settings::Style::modern());
+        format!(
+            "{}\nprovable today: {}",
+            table,
+            if self.provable { "yes" } else { "no" }
+        )
+    }
+}
+
+/// Adds the nodes of a graph, whose nodes can also read the constants `outer` of the graphs
+/// enclosing it, to `ops`.
+fn cover_graph(
+    graph: &[u8],
+    outer: &HashSet<String>,
+    ops: &mut BTreeMap<String, OpCoverage>,
+) -> Result<(), GraphError> {
+    let mut nodes = vec![];
+    let mut constants = outer.clone();
+    for field in parse(graph)? {
+        match field.number {
+            GRAPH_NODE => nodes.push(Node::decode(field.value)?),
+            GRAPH_INITIALIZER => {
+                constants.insert(name_of(field.value, TENSOR_NAME)?);
+            }
+            _ => {}
+        }
+    }
+    for node in nodes.iter().filter(|n| n.op == "Constant") {
+        constants.extend(node.outputs.iter().cloned());
+    }
+
+    for node in &nodes {
+        let entry = ops.entry(node.op.clone()).or_insert_with(|| OpCoverage {
+            op: node.op.clone(),
+            count: 0,
+            support: Support::Supported,
+            restrictions: vec![],
+        });
+        entry.count += 1;
+        match restrictions(&node.op) {
+            None => entry.support = Support::Unsupported,
+            Some(restrictions) => {
+                for r in restrictions.iter().filter(|r| (r.trips)(node, &constants)) {
+                    entry.support = entry.support.max(Support::Partial);
+                    if !entry.restrictions.iter().any(|w| w == r.what) {
+                        entry.restrictions.push(r.what.to_string());
+                    }
+                }
+            }
+        }
+        for subgraph in &node.subgraphs {
+            cover_graph(subgraph, &constants, ops)?;
+        }
+    }
+    Ok(())
+}
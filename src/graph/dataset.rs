@@ -0,0 +1,213 @@
+use halo2_proofs::circuit::Value;
+use halo2curves::bn256::Fr as Fp;
+use halo2curves::ff::Field;
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+use super::errors::GraphError;
+use super::modules::ModulePoseidon;
+use crate::circuit::modules::Module;
+use crate::tensor::{Tensor, ValTensor, ValType};
+use crate::EZKL_BUF_CAPACITY;
+
+/// The deepest dataset tree a circuit can open a row of, so that row indices fit in a `u32`
+pub const MAX_DATASET_DEPTH: usize = 32;
+
+/// The leaf a dataset row (a model's quantized inputs, flattened and concatenated) is committed
+/// to: its [ModulePoseidon] hash, as the circuit hashes the model's inputs.
+pub fn row_leaf(row: &[Fp]) -> Fp {
+    ModulePoseidon::run(row.to_vec()).unwrap()[0][0]
+}
+
+/// The parent of two nodes of a dataset tree
+fn hash_pair(left: Fp, right: Fp) -> Fp {
+    ModulePoseidon::run(vec![left, right]).unwrap()[0][0]
+}
+
+/// A path from a dataset row's leaf to the root of the tree committing to the dataset
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct DatasetOpening {
+    /// The row's index in the dataset, whose bits pick the side of the path at each level
+    pub index: usize,
+    /// The sibling of the path's node at each level, from the leaf's up
+    pub siblings: Vec<Fp>,
+}
+
+impl DatasetOpening {
+    /// Whether the path's node is the right of its pair at each level, from the leaf's up
+    pub fn bits(&self) -> Vec<bool> {
+        (0..self.siblings.len())
+            .map(|i| (self.index >> i) & 1 == 1)
+            .collect()
+    }
+
+    /// The (left, right) pairs hashed on the way from `leaf` to the root
+    pub fn pairs(&self, leaf: Fp) -> Vec<(Fp, Fp)> {
+        let mut node = leaf;
+        self.siblings
+            .iter()
+            .zip(self.bits())
+            .map(|(sibling, is_right)| {
+                let pair = if is_right {
+                    (*sibling, node)
+                } else {
+                    (node, *sibling)
+                };
+                node = hash_pair(pair.0, pair.1);
+                pair
+            })
+            .collect()
+    }
+
+    /// The root the path from `leaf` leads to
+    pub fn root(&self, leaf: Fp) -> Fp {
+        self.pairs(leaf)
+            .last()
+            .map_or(leaf, |(left, right)| hash_pair(*left, *right))
+    }
+
+    /// The public instances opening `row` takes: the dataset's root, then the row's index
+    pub fn instances(&self, row: &[Fp]) -> [Fp; 2] {
+        [self.root(row_leaf(row)), Fp::from(self.index as u64)]
+    }
+}
+
+/// A dataset committed to by a binary Poseidon Merkle tree over its rows' leaves, padded with
+/// zero leaves to `2^depth`, along with each row's opening. Proofs of circuits compiled with a
+/// `dataset_depth` open one of its rows, exposing only the root and the row's index.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct DatasetCommitment {
+    /// The depth of the tree
+    pub depth: usize,
+    /// The root of the tree
+    pub root: Fp,
+    /// The opening of each row, in the dataset's order
+    pub openings: Vec<DatasetOpening>,
+}
+
+impl DatasetCommitment {
+    /// Commits to `rows`, each a model's quantized inputs flattened and concatenated
+    pub fn new(rows: &[Vec<Fp>], depth: usize) -> Result<Self, GraphError> {
+        if rows.is_empty() || rows.len() > 1 << depth {
+            return Err(GraphError::InvalidDataset(
+                depth,
+                format!("can't commit to {} rows", rows.len()),
+            ));
+        }
+        // the root of an all-zero subtree at each level, padding the levels' odd nodes
+        let mut zeros = vec![Fp::ZERO];
+        for i in 0..depth {
+            zeros.push(hash_pair(zeros[i], zeros[i]));
+        }
+
+        let mut levels = vec![rows.iter().map(|row| row_leaf(row)).collect_vec()];
+        for zero in zeros.iter().take(depth) {
+            let level = levels.last().unwrap();
+            let parents = level
+                .chunks(2)
+                .map(|pair| hash_pair(pair[0], *pair.get(1).unwrap_or(zero)))
+                .collect_vec();
+            levels.push(parents);
+        }
+
+        let openings = (0..rows.len())
+            .map(|index| DatasetOpening {
+                index,
+                siblings: levels
+                    .iter()
+                    .zip(&zeros)
+                    .take(depth)
+                    .enumerate()
+                    .map(|(i, (level, zero))| *level.get((index >> i) ^ 1).unwrap_or(zero))
+                    .collect(),
+            })
+            .collect();
+
+        Ok(DatasetCommitment {
+            depth,
+            root: levels[depth][0],
+            openings,
+        })
+    }
+
+    /// The opening of row `index`, checking it is the row `row`
+    pub fn opening(&self, index: usize, row: &[Fp]) -> Result<&DatasetOpening, GraphError> {
+        let opening = self.openings.get(index).ok_or_else(|| {
+            GraphError::InvalidDataset(
+                self.depth,
+                format!("only has {} rows, not {}", self.openings.len(), index + 1),
+            )
+        })?;
+        if opening.root(row_leaf(row)) != self.root {
+            return Err(GraphError::DatasetRowMismatch(
+                index,
+                format!("{:?}", self.root),
+            ));
+        }
+        Ok(opening)
+    }
+
+    /// Load a dataset commitment from a file
+    pub fn from_path(path: std::path::PathBuf) -> Result<Self, GraphError> {
+        let file = std::fs::File::open(path.clone()).map_err(|e| {
+            GraphError::ReadWriteFileError(path.display().to_string(), e.to_string())
+        })?;
+        let reader = std::io::BufReader::with_capacity(*EZKL_BUF_CAPACITY, file);
+        serde_json::from_reader(reader).map_err(|e| e.into())
+    }
+
+    /// Save the dataset commitment to a file
+    pub fn save(&self, path: std::path::PathBuf) -> Result<(), GraphError> {
+        let file = std::fs::File::create(path.clone()).map_err(|e| {
+            GraphError::ReadWriteFileError(path.display().to_string(), e.to_string())
+        })?;
+        let writer = std::io::BufWriter::with_capacity(*EZKL_BUF_CAPACITY, file);
+        serde_json::to_writer(writer, &self).map_err(|e| e.into())
+    }
+}
+
+/// The cells of a dataset opening: the Poseidon module hashes the row into its leaf and each
+/// level's pair into the next node, and the model's region constrains the pairs into a path
+/// (see the `merkle_path` layout).
+#[derive(Clone, Debug)]
+pub struct DatasetPath {
+    /// The path's node at each level, from the row's leaf up
+    pub nodes: ValTensor<Fp>,
+    /// The left of each level's hashed pair
+    pub lefts: ValTensor<Fp>,
+    /// The right of each level's hashed pair
+    pub rights: ValTensor<Fp>,
+    /// Whether the node is the right of its pair at each level, i.e the bits of the row's index
+    pub bits: ValTensor<Fp>,
+    /// The hash of the last pair, the dataset's root
+    pub root: ValTensor<Fp>,
+}
+
+impl DatasetPath {
+    /// A path of unknown values, to lay out a circuit opening a tree of `depth` without witnesses
+    pub fn unknown(depth: usize) -> Self {
+        let unknown =
+            || -> ValTensor<Fp> { vec![ValType::Value(Value::<Fp>::unknown()); depth].into() };
+        DatasetPath {
+            nodes: unknown(),
+            lefts: unknown(),
+            rights: unknown(),
+            bits: unknown(),
+            root: vec![ValType::Value(Value::<Fp>::unknown())].into(),
+        }
+    }
+
+    /// The bits of the row's index `opening` picks the path's sides by, to witness in the
+    /// model's region. They are unknown when laying out without witnesses.
+    pub fn witness_bits(opening: Option<&DatasetOpening>, depth: usize) -> ValTensor<Fp> {
+        let bits = match opening {
+            Some(opening) => opening
+                .bits()
+                .into_iter()
+                .map(|bit| Value::known(Fp::from(bit as u64)))
+                .collect_vec(),
+            None => vec![Value::unknown(); depth],
+        };
+        Tensor::from(bits.into_iter()).into()
+    }
+}
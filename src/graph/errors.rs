@@ -29,6 +29,9 @@ pub enum GraphError {
     /// A node has missing parameters
     #[error("a node is has misformed params: {0}")]
     MisformedParams(String),
+    /// A Pad pads with something other than constant zeros
+    #[error("unsupported pad mode in graph node {0} ({1}), only constant padding with zeros is supported")]
+    UnsupportedPadMode(usize, String),
     /// Error in the configuration of the visibility of variables
     #[error("there should be at least one set of public variables")]
     Visibility,
@@ -69,6 +72,9 @@ pub enum GraphError {
     /// Public visibility for params is deprecated
     #[error("public visibility for params is deprecated, please use `fixed` instead")]
     ParamsPublicVisibility,
+    /// Merkle visibility for inputs or outputs
+    #[error("merkle visibility is only supported for params")]
+    MerkleVisibility,
     /// Slice length mismatch
     #[error("slice length mismatch: {0}")]
     SliceLengthMismatch(#[from] std::array::TryFromSliceError),
@@ -143,4 +149,102 @@ pub enum GraphError {
     /// Invalid RunArg
     #[error("invalid RunArgs: {0}")]
     InvalidRunArgs(String),
+    /// A laid out node diverges from its integer reference
+    #[error("node {0} ({1}) diverges from its integer reference")]
+    ReferenceMismatch(usize, String),
+    /// A laid out node's values differ from those of the forward pass
+    #[error("node {0} ({1}) was laid out with values that differ from its forward pass")]
+    WitnessMismatch(usize, String),
+    /// Control flow that is only decided when the model runs
+    #[error("{0} node {1} has control flow decided at run time, which can't be laid out as a circuit; its subgraphs use {2}")]
+    DynamicControlFlow(String, String, String),
+    /// Pre-quantized inputs at a finer scale than the model takes
+    #[error("integer input {0} has scale {1}, above the model's input scale {2}")]
+    InputScaleMismatch(usize, crate::Scale, crate::Scale),
+    /// Input data that can't be shaped to the model's input
+    #[error("input {0} was given with shape {1:?}, which can't be shaped to the model's input shape {2:?}")]
+    InputShapeMismatch(usize, Vec<usize>, Vec<usize>),
+    /// No logrows lets the model's layout fit the advice column budget
+    #[error("the model needs {0} advice columns even at logrows {1}, above the max_advice_cols budget of {2}; widest nodes: {3}")]
+    AdviceWidthExceeded(usize, u32, usize, String),
+    /// The circuit makes more values public than the instance budget allows
+    #[error("the circuit has {0} public instances, above the max_instances budget of {1}{2}")]
+    InstanceBudgetExceeded(usize, usize, String),
+    /// Outputs that aren't among a proof's instances can't be decoded from it
+    #[error("outputs are {0}, so the proof does not carry their values")]
+    NonPublicOutputs(String),
+    /// The circuit binds a context but none was supplied
+    #[error("the circuit was compiled with bind_context, so a context must be supplied")]
+    MissingContext,
+    /// A context was supplied to a circuit without an instance for it
+    #[error("a context was supplied but the circuit wasn't compiled with bind_context")]
+    UnboundContext,
+    /// A proof bound to a different context than the one supplied
+    #[error("the proof is bound to context hash {0}, not {1}, the hash of the supplied context")]
+    ContextMismatch(String, String),
+    /// A weights root was supplied to a circuit that doesn't commit to its params
+    #[error("a weights root was supplied but the params aren't merkle committed")]
+    UncommittedWeights,
+    /// The params are merkle committed but no weights root was supplied
+    #[error("the params are merkle committed, so a weights root must be supplied")]
+    MissingWeightsRoot,
+    /// A proof committing to other params than the supplied root
+    #[error("the proof commits to weights root {0}, not {1}")]
+    WeightsRootMismatch(String, String),
+    /// The circuit opens a dataset row but no opening was supplied
+    #[error("the circuit was compiled with dataset_depth, so a dataset opening must be supplied")]
+    MissingDatasetOpening,
+    /// A dataset opening was supplied to a circuit that doesn't open a dataset row
+    #[error("a dataset was supplied but the circuit wasn't compiled with dataset_depth")]
+    UnexpectedDataset,
+    /// A dataset too large for, or an opening of another depth than, the tree
+    #[error("a dataset tree of depth {0} {1}")]
+    InvalidDataset(usize, String),
+    /// The input isn't the dataset row the opening is for
+    #[error("the input isn't row {0} of the dataset committed to by root {1}")]
+    DatasetRowMismatch(usize, String),
+    /// A proof opening a row of another dataset than the supplied one
+    #[error("the proof opens a row of dataset root {0}, not {1}")]
+    DatasetRootMismatch(String, String),
+    /// A node's output went past the bound the circuit was calibrated for
+    #[error("node {0} ({1}) output {2} exceeds its calibrated output_max {3}")]
+    OutputMaxExceeded(
+        usize,
+        String,
+        crate::fieldutils::IntegerRep,
+        crate::fieldutils::IntegerRep,
+    ),
+    /// The model holds an op the circuit can't constrain and that wasn't allowed
+    #[error("node {0} is an unsupported {1} op, see allow_unconstrained")]
+    UnconstrainedOp(usize, String),
+    /// A categorical input group is out of the model's inputs or mixes scales
+    #[error("invalid categorical group {0}->{1}: {2}")]
+    InvalidCategoricalGroup(usize, usize, String),
+    /// The prove was cancelled or ran past its timeout
+    #[error("the prove was cancelled or timed out")]
+    Cancelled,
+    /// Instance data supplied at verify time that doesn't fit the circuit's public instances
+    #[error("invalid instance data: {0}")]
+    InvalidInstanceData(String),
+    /// The onnx graph has duplicate node names, dangling inputs or cycles
+    #[error("malformed onnx graph: {0}")]
+    MalformedGraph(String),
+    /// An input couldn't be read from an [super::input_source::InputSource]
+    #[error("failed to read input {0}: {1}")]
+    InputSourceRead(String, String),
+    /// Outputs that aren't within tolerance of the expected ones
+    #[error("{0} of {1} outputs aren't within tolerance of the expected ones, worst: {2}")]
+    OutputsOutOfTolerance(usize, usize, String),
+    /// Output formats that are neither one per output nor a single shared one
+    #[error("{0} output formats were given for {1} outputs, give one per output or a single one")]
+    OutputFormatCount(usize, usize),
+    /// An op that would turn an empty input into a non-empty output
+    #[error("node {0} ({1}) takes the empty output of node {2} but its own output isn't empty")]
+    EmptyInput(usize, String, usize),
+    /// A constant with values past the decomposition range, other than an additive mask
+    #[error("constant node {0} has {1} values outside the decomposition range [-{2}, {2}], lower the param scale or raise decomp_base or decomp_legs")]
+    ConstantOutOfRange(usize, usize, f32),
+    /// An empty model input or output that would be hashed or committed to
+    #[error("{0} {1} is empty, so it can't be {2}; make it public or private")]
+    EmptyTensorVisibility(String, usize, String),
 }
@@ -0,0 +1,130 @@
+use super::errors::GraphError;
+use super::proto::{
+    malformed, parse, read_varint, rewrite_changed, write_field, ATTRIBUTE_GRAPH, ATTRIBUTE_GRAPHS,
+    ATTRIBUTE_TENSOR, ATTRIBUTE_TENSORS, ENTRY_KEY, ENTRY_VALUE, EXTERNAL, GRAPH_INITIALIZER,
+    GRAPH_NODE, MODEL_GRAPH, NODE_ATTRIBUTE, TENSOR_DATA_LOCATION, TENSOR_EXTERNAL_DATA,
+    TENSOR_RAW_DATA, WIRE_LEN,
+};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Component, Path};
+
+/// The tensor with its external data read into `raw_data`, or `None` if it holds its data itself.
+fn inline_tensor(
+    tensor: &[u8],
+    dir: Option<&Path>,
+    num_inlined: &mut usize,
+) -> Result<Option<Vec<u8>>, GraphError> {
+    let fields = parse(tensor)?;
+    let is_external = fields.iter().any(|f| {
+        f.number == TENSOR_DATA_LOCATION && read_varint(f.value, &mut 0).ok() == Some(EXTERNAL)
+    });
+    if !is_external {
+        return Ok(None);
+    }
+
+    let (mut location, mut offset, mut length) = (None, 0, None);
+    for entry in fields.iter().filter(|f| f.number == TENSOR_EXTERNAL_DATA) {
+        let entry = parse(entry.value)?;
+        let get = |number| {
+            entry
+                .iter()
+                .find(|f| f.number == number)
+                .map(|f| String::from_utf8_lossy(f.value).to_string())
+                .unwrap_or_default()
+        };
+        let (key, value) = (get(ENTRY_KEY), get(ENTRY_VALUE));
+        let parse_int = |v: &str| {
+            v.parse::<u64>()
+                .map_err(|_| malformed(&format!("external data {} {}", key, v)))
+        };
+        match key.as_str() {
+            "location" => location = Some(value),
+            "offset" => offset = parse_int(&value)?,
+            "length" => length = Some(parse_int(&value)?),
+            _ => {}
+        }
+    }
+
+    let dir = dir.ok_or_else(|| {
+        GraphError::MisformedParams(
+            "the model stores tensors in external data files, load it from its path".to_string(),
+        )
+    })?;
+    let location = location.ok_or_else(|| malformed("external data without a location"))?;
+    if !Path::new(&location)
+        .components()
+        .all(|c| matches!(c, Component::Normal(_)))
+    {
+        return Err(GraphError::MisformedParams(format!(
+            "external data location {} must be relative to the model's directory",
+            location
+        )));
+    }
+
+    let path = dir.join(&location);
+    let io_err = |e: std::io::Error| {
+        GraphError::ReadWriteFileError(path.display().to_string(), e.to_string())
+    };
+    let mut file = std::fs::File::open(&path).map_err(io_err)?;
+    file.seek(SeekFrom::Start(offset)).map_err(io_err)?;
+    let mut data = vec![];
+    match length {
+        Some(length) => {
+            data.resize(length as usize, 0);
+            file.read_exact(&mut data).map_err(io_err)?;
+        }
+        None => {
+            file.read_to_end(&mut data).map_err(io_err)?;
+        }
+    }
+
+    let mut out = Vec::with_capacity(tensor.len() + data.len());
+    for f in fields.iter().filter(|f| {
+        ![TENSOR_RAW_DATA, TENSOR_EXTERNAL_DATA, TENSOR_DATA_LOCATION].contains(&f.number)
+    }) {
+        write_field(&mut out, f.number, f.wire_type, f.value);
+    }
+    write_field(&mut out, TENSOR_RAW_DATA, WIRE_LEN, &data);
+    *num_inlined += 1;
+    Ok(Some(out))
+}
+
+fn inline_graph(
+    graph: &[u8],
+    dir: Option<&Path>,
+    num_inlined: &mut usize,
+) -> Result<Option<Vec<u8>>, GraphError> {
+    rewrite_changed(graph, |field| match field.number {
+        GRAPH_INITIALIZER => inline_tensor(field.value, dir, num_inlined),
+        GRAPH_NODE => rewrite_changed(field.value, |field| match field.number {
+            NODE_ATTRIBUTE => rewrite_changed(field.value, |field| match field.number {
+                ATTRIBUTE_TENSOR | ATTRIBUTE_TENSORS => {
+                    inline_tensor(field.value, dir, num_inlined)
+                }
+                ATTRIBUTE_GRAPH | ATTRIBUTE_GRAPHS => inline_graph(field.value, dir, num_inlined),
+                _ => Ok(None),
+            }),
+            _ => Ok(None),
+        }),
+        _ => Ok(None),
+    })
+}
+
+/// Moves every tensor an onnx model keeps in external data files (as exported for models over
+/// 2GB, or with `save_as_external_data`) back into the model, so it can be parsed on its own.
+/// Locations are resolved against `dir`, which should be the model's directory. A model holding
+/// all of its tensors itself is returned as is.
+pub fn inline_external_data(model: Vec<u8>, dir: Option<&Path>) -> Result<Vec<u8>, GraphError> {
+    let mut num_inlined = 0;
+    let inlined = rewrite_changed(&model, |field| match field.number {
+        MODEL_GRAPH => inline_graph(field.value, dir, &mut num_inlined),
+        _ => Ok(None),
+    })?;
+    match inlined {
+        Some(inlined) => {
+            log::debug!("loaded {} tensors from external data", num_inlined);
+            Ok(inlined)
+        }
+        None => Ok(model),
+    }
+}
@@ -434,15 +434,133 @@ impl<'de> Deserialize<'de> for DataSource {
 /// Input to graph as a datasource
 /// Always use JSON serialization for GraphData. Seriously.
 #[derive(Clone, Debug, Deserialize, Default, PartialEq)]
+#[serde(try_from = "RawGraphData")]
 pub struct GraphData {
     /// Inputs to the model / computational graph (can be empty vectors if inputs are coming from on-chain).
+    #[serde(default)]
     pub input_data: DataSource,
     /// Outputs of the model / computational graph (can be empty vectors if outputs are coming from on-chain).
     pub output_data: Option<DataSource>,
+    /// Inputs already quantized to integers at `input_scale`, which are loaded instead of `input_data`.
+    #[serde(default)]
+    pub input_data_int: Option<Vec<Vec<i32>>>,
+    /// The scale `input_data_int` was quantized at.
+    #[serde(default)]
+    pub input_scale: Option<crate::Scale>,
+    /// The dims of each input in `input_data` when it was given as a nested array rather than a
+    /// flat list, checked against the model's input shapes when loading.
+    #[serde(skip)]
+    pub input_dims: Option<Vec<Vec<usize>>>,
 }
 
 impl UnwindSafe for GraphData {}
 
+/// [GraphData] as it is laid out in JSON, where each input in `input_data` is either a flat list
+/// or a nested array in the input's shape.
+#[derive(Deserialize)]
+struct RawGraphData {
+    #[serde(default)]
+    input_data: Option<Box<serde_json::value::RawValue>>,
+    output_data: Option<DataSource>,
+    #[serde(default)]
+    input_data_int: Option<Vec<Vec<i32>>>,
+    #[serde(default)]
+    input_scale: Option<crate::Scale>,
+}
+
+impl TryFrom<RawGraphData> for GraphData {
+    type Error = String;
+
+    fn try_from(raw: RawGraphData) -> Result<Self, Self::Error> {
+        let mut input_dims = None;
+        let input_data = match raw.input_data {
+            None => DataSource::default(),
+            Some(json) => match serde_json::from_str::<DataSource>(json.get()) {
+                Ok(source) => source,
+                Err(_) => {
+                    let inputs: Vec<serde_json::Value> =
+                        serde_json::from_str(json.get()).map_err(|e| e.to_string())?;
+                    let mut data = vec![];
+                    let mut dims = vec![];
+                    for input in &inputs {
+                        let input_dims = nested_dims(input);
+                        let mut flat = vec![];
+                        flatten_nested(input, &input_dims, &mut flat)?;
+                        data.push(flat);
+                        dims.push(input_dims);
+                    }
+                    input_dims = Some(dims);
+                    DataSource::File(data)
+                }
+            },
+        };
+        Ok(GraphData {
+            input_data,
+            output_data: raw.output_data,
+            input_data_int: raw.input_data_int,
+            input_scale: raw.input_scale,
+            input_dims,
+        })
+    }
+}
+
+/// The dims of a nested JSON array, read off its first element at each depth
+fn nested_dims(value: &serde_json::Value) -> Vec<usize> {
+    let mut dims = vec![];
+    let mut value = value;
+    while let serde_json::Value::Array(items) = value {
+        dims.push(items.len());
+        match items.first() {
+            Some(first) => value = first,
+            None => break,
+        }
+    }
+    dims
+}
+
+/// Flattens a nested JSON array in row-major order, rejecting arrays that don't match `dims`
+fn flatten_nested(
+    value: &serde_json::Value,
+    dims: &[usize],
+    flat: &mut Vec<FileSourceInner>,
+) -> Result<(), String> {
+    match (value, dims.split_first()) {
+        (serde_json::Value::Array(items), Some((len, rest))) if items.len() == *len => items
+            .iter()
+            .try_for_each(|item| flatten_nested(item, rest, flat)),
+        (serde_json::Value::Array(_), _) | (_, Some(_)) => {
+            Err("nested input arrays must be rectangular".to_string())
+        }
+        (value, None) => {
+            flat.push(serde_json::from_str(&value.to_string()).map_err(|e| e.to_string())?);
+            Ok(())
+        }
+    }
+}
+
+/// Checks an input of `len` elements against the `shape` the model takes. Flat inputs are
+/// reshaped so only their element count has to match; nested inputs given with `dims` must
+/// also match the shape, up to leading unit dims such as a batch of one.
+pub fn check_input_shape(
+    idx: usize,
+    len: usize,
+    dims: Option<&[usize]>,
+    shape: &[usize],
+) -> Result<(), GraphError> {
+    let strip = |dims: &[usize]| -> Vec<usize> {
+        dims.iter().copied().skip_while(|d| *d == 1).collect()
+    };
+    let matches = match dims {
+        Some(dims) => strip(dims) == strip(shape),
+        None => len == shape.iter().product::<usize>(),
+    };
+    if !matches {
+        let given = dims.map(|dims| dims.to_vec()).unwrap_or(vec![len]);
+        return Err(GraphError::InputShapeMismatch(idx, given, shape.to_vec()));
+    }
+    Ok(())
+}
+
 impl GraphData {
     // not wasm
     #[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
@@ -452,7 +570,16 @@ impl GraphData {
         shapes: &[Vec<usize>],
         datum_types: &[tract_onnx::prelude::DatumType],
     ) -> Result<TVec<TValue>, GraphError> {
+        self.check_input_shapes(shapes)?;
         let mut inputs = TVec::new();
+        if let Some(int_data) = self.dequantized_int_data() {
+            for (i, input) in int_data.iter().enumerate() {
+                let tt = TractTensor::from_shape(&shapes[i], input)?;
+                let tt = tt.cast_to_dt(datum_types[i])?;
+                inputs.push(tt.into_owned().into());
+            }
+            return Ok(inputs);
+        }
         match &self.input_data {
             DataSource::File(data) => {
                 for (i, input) in data.iter().enumerate() {
@@ -480,9 +607,48 @@ impl GraphData {
         GraphData {
             input_data,
             output_data: None,
+            input_data_int: None,
+            input_scale: None,
+            input_dims: None,
         }
     }
 
+    /// Create a new GraphData from inputs that were already quantized to integers at `scale`
+    pub fn from_int(input_data_int: Vec<Vec<i32>>, scale: crate::Scale) -> Self {
+        GraphData {
+            input_data: DataSource::default(),
+            output_data: None,
+            input_data_int: Some(input_data_int),
+            input_scale: Some(scale),
+            input_dims: None,
+        }
+    }
+
+    /// Checks each supplied input against the shape the model takes, see [check_input_shape]
+    pub fn check_input_shapes(&self, shapes: &[Vec<usize>]) -> Result<(), GraphError> {
+        if let Some(int_data) = &self.input_data_int {
+            for (i, (input, shape)) in int_data.iter().zip(shapes).enumerate() {
+                check_input_shape(i, input.len(), None, shape)?;
+            }
+        } else if let DataSource::File(data) = &self.input_data {
+            for (i, (input, shape)) in data.iter().zip(shapes).enumerate() {
+                let dims = self.input_dims.as_ref().map(|dims| dims[i].as_slice());
+                check_input_shape(i, input.len(), dims, shape)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The integer inputs as the floats they represent
+    pub fn dequantized_int_data(&self) -> Option<Vec<Vec<f64>>> {
+        let multiplier = crate::graph::scale_to_multiplier(self.input_scale.unwrap_or(0));
+        self.input_data_int.as_ref().map(|data| {
+            data.iter()
+                .map(|input| input.iter().map(|x| *x as f64 / multiplier).collect())
+                .collect()
+        })
+    }
+
     /// Load the model input from a file
     pub fn from_path(path: std::path::PathBuf) -> Result<Self, GraphError> {
         let reader = std::fs::File::open(&path).map_err(|e| {
@@ -508,22 +674,16 @@ impl GraphData {
         Ok(())
     }
 
-    ///
-    pub async fn split_into_batches(
-        &self,
-        input_shapes: Vec<Vec<usize>>,
-    ) -> Result<Vec<Self>, GraphError> {
-        // split input data into batches
-        let mut batched_inputs = vec![];
-
-        let iterable = match self {
+    /// The input data as file data, fetching it if it lives in a database
+    async fn file_data(&self) -> Result<FileSource, GraphError> {
+        Ok(match self {
             GraphData {
                 input_data: DataSource::File(data),
-                output_data: _,
+                ..
             } => data.clone(),
             GraphData {
                 input_data: DataSource::OnChain(_),
-                output_data: _,
+                ..
             } => {
                 return Err(GraphError::InvalidDims(
                     0,
@@ -533,9 +693,20 @@ impl GraphData {
             #[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
             GraphData {
                 input_data: DataSource::DB(data),
-                output_data: _,
+                ..
             } => data.fetch_and_format_as_file().await?,
-        };
+        })
+    }
+
+    ///
+    pub async fn split_into_batches(
+        &self,
+        input_shapes: Vec<Vec<usize>>,
+    ) -> Result<Vec<Self>, GraphError> {
+        // split input data into batches
+        let mut batched_inputs = vec![];
+
+        let iterable = self.file_data().await?;
 
         for (i, shape) in input_shapes.iter().enumerate() {
             // ensure the input is evenly divisible by batch_size
@@ -588,6 +759,77 @@ impl GraphData {
 
         Ok(batches)
     }
+
+    /// Splits data holding any number of samples into chunks of `batch_size` samples, each the size
+    /// of the circuit's inputs (whose shapes include the batch dimension). A short last chunk is
+    /// filled by repeating its last sample. Returns each chunk with the number of samples it holds.
+    pub async fn split_into_chunks(
+        &self,
+        input_shapes: Vec<Vec<usize>>,
+        batch_size: usize,
+    ) -> Result<Vec<(Self, usize)>, GraphError> {
+        let mut data = self.file_data().await?;
+        if data.len() != input_shapes.len() {
+            return Err(GraphError::InvalidDims(
+                0,
+                format!(
+                    "data has {} inputs, the circuit has {}",
+                    data.len(),
+                    input_shapes.len()
+                ),
+            ));
+        }
+
+        let mut num_samples = None;
+        for (i, (input, shape)) in data.iter_mut().zip(&input_shapes).enumerate() {
+            let input_size = shape.iter().product::<usize>();
+            if batch_size == 0 || input_size == 0 || input_size % batch_size != 0 {
+                return Err(GraphError::InvalidDims(
+                    i,
+                    format!(
+                        "input of shape {:?} is not a batch of {}",
+                        shape, batch_size
+                    ),
+                ));
+            }
+            let sample_size = input_size / batch_size;
+            if input.is_empty() || input.len() % sample_size != 0 {
+                return Err(GraphError::InvalidDims(
+                    i,
+                    format!(
+                        "data length {} is not a multiple of the sample size {}",
+                        input.len(),
+                        sample_size
+                    ),
+                ));
+            }
+            let samples = input.len() / sample_size;
+            if num_samples.replace(samples).is_some_and(|n| n != samples) {
+                return Err(GraphError::InvalidDims(
+                    i,
+                    "inputs hold different numbers of samples".to_string(),
+                ));
+            }
+
+            let last = input[input.len() - sample_size..].to_vec();
+            while input.len() % input_size != 0 {
+                input.extend_from_slice(&last);
+            }
+        }
+        let num_samples = num_samples.unwrap_or(0);
+
+        let chunks = GraphData::new(DataSource::File(data))
+            .split_into_batches(input_shapes)
+            .await?;
+        Ok(chunks
+            .into_iter()
+            .enumerate()
+            .map(|(i, chunk)| {
+                let samples = num_samples.saturating_sub(i * batch_size).min(batch_size);
+                (chunk, samples)
+            })
+            .collect())
+    }
 }
 
 #[cfg(feature = "python-bindings")]
@@ -644,10 +886,40 @@ impl Serialize for GraphData {
         let mut state = serializer.serialize_struct("GraphData", 4)?;
         state.serialize_field("input_data", &self.input_data)?;
         state.serialize_field("output_data", &self.output_data)?;
+        if let Some(input_data_int) = &self.input_data_int {
+            state.serialize_field("input_data_int", input_data_int)?;
+            state.serialize_field("input_scale", &self.input_scale)?;
+        }
         state.end()
     }
 }
 
+/// The public inputs and outputs a proof claims, supplied to `verify` apart from the proof.
+/// Each tensor is flattened and its values are floats, quantized as `round(value * 2^scale)` at
+/// the scale the circuit settings record for it (negative values wrapping around the field
+/// modulus), as in the witness.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct InstanceData {
+    /// One tensor per model input, if the inputs are public
+    #[serde(default)]
+    pub input_data: Vec<Vec<f64>>,
+    /// One tensor per model output, if the outputs are public
+    #[serde(default)]
+    pub output_data: Vec<Vec<f64>>,
+}
+
+impl InstanceData {
+    /// Load the instance data from a file
+    pub fn from_path(path: std::path::PathBuf) -> Result<Self, GraphError> {
+        let buf = std::fs::read_to_string(&path).map_err(|e| {
+            GraphError::ReadWriteFileError(path.display().to_string(), e.to_string())
+        })?;
+        serde_json::from_str(&buf)
+            .map_err(|e| GraphError::InvalidInstanceData(format!("{}: {}", path.display(), e)))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -691,6 +963,31 @@ mod tests {
         assert_eq!(graph_input3, file);
     }
 
+    #[test]
+    fn test_nested_input_data_flattens_with_dims() {
+        const JSON: &str = r#"{"input_data":[[[0.5,1],[2,true]],[3.0]],"output_data":null}"#;
+        let data = serde_json::from_str::<GraphData>(JSON).unwrap();
+        assert_eq!(
+            data.input_data,
+            DataSource::File(vec![
+                vec![
+                    FileSourceInner::Float(0.5),
+                    FileSourceInner::Float(1.0),
+                    FileSourceInner::Float(2.0),
+                    FileSourceInner::Bool(true),
+                ],
+                vec![FileSourceInner::Float(3.0)],
+            ])
+        );
+        assert_eq!(data.input_dims, Some(vec![vec![2, 2], vec![1]]));
+        data.check_input_shapes(&[vec![1, 2, 2], vec![1]]).unwrap();
+        assert!(data.check_input_shapes(&[vec![4], vec![1]]).is_err());
+
+        // ragged arrays have no shape to check
+        const RAGGED: &str = r#"{"input_data":[[[0.5,1],[2]]]}"#;
+        assert!(serde_json::from_str::<GraphData>(RAGGED).is_err());
+    }
+
     //  test for the compatibility with the serialized elements from the mclbn256 library
     #[test]
     fn test_python_compat() {
@@ -0,0 +1,273 @@
+use super::errors::GraphError;
+use super::input::{DataSource, GraphData};
+use crate::tensor::Tensor;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Somewhere a model's inputs can be read from by name, one at a time, rather than from a single
+/// json file holding all of them. See [super::GraphCircuit::load_graph_input_from].
+pub trait InputSource {
+    /// Reads the input the model calls `name`, with the dims it's stored with. Values are read at
+    /// double precision, so `<f8` data isn't narrowed before it's quantized.
+    fn read_input(&self, name: &str) -> Result<Tensor<f64>, GraphError>;
+}
+
+fn read_error(name: &str, reason: impl ToString) -> GraphError {
+    GraphError::InputSourceRead(name.to_string(), reason.to_string())
+}
+
+/// The file inputs of a [GraphData], which holds them in the order the model takes them.
+pub struct JsonInputSource {
+    data: GraphData,
+    names: Vec<String>,
+}
+
+impl JsonInputSource {
+    /// Reads `data`'s inputs, the model naming them `names` in order.
+    pub fn new(data: GraphData, names: Vec<String>) -> Self {
+        Self { data, names }
+    }
+
+    /// Reads the inputs of a json file like those `gen-witness` takes.
+    pub fn from_path(path: PathBuf, names: Vec<String>) -> Result<Self, GraphError> {
+        Ok(Self::new(GraphData::from_path(path)?, names))
+    }
+}
+
+impl InputSource for JsonInputSource {
+    fn read_input(&self, name: &str) -> Result<Tensor<f64>, GraphError> {
+        let idx = self
+            .names
+            .iter()
+            .position(|n| n == name)
+            .ok_or_else(|| read_error(name, "the model has no input of that name"))?;
+        let DataSource::File(inputs) = &self.data.input_data else {
+            return Err(read_error(name, "only file data can be read by name"));
+        };
+        let input = inputs
+            .get(idx)
+            .ok_or_else(|| read_error(name, format!("the data holds {} inputs", inputs.len())))?;
+        let values = input.iter().map(|x| x.to_float()).collect::<Vec<_>>();
+        let dims = match &self.data.input_dims {
+            Some(dims) => dims[idx].clone(),
+            None => vec![values.len()],
+        };
+        Ok(Tensor::new(Some(&values), &dims)?)
+    }
+}
+
+/// Inputs stored as numpy `.npy` files named after them, read only once they're asked for.
+pub struct NpyInputSource {
+    files: HashMap<String, PathBuf>,
+    dir: Option<PathBuf>,
+}
+
+impl NpyInputSource {
+    /// Reads each input from `<name>.npy` in `dir`.
+    pub fn from_dir(dir: impl AsRef<Path>) -> Self {
+        Self {
+            files: HashMap::new(),
+            dir: Some(dir.as_ref().to_path_buf()),
+        }
+    }
+
+    /// Reads the input `name` from the `.npy` file at `path`.
+    pub fn from_file(name: &str, path: impl AsRef<Path>) -> Self {
+        Self {
+            files: HashMap::from([(name.to_string(), path.as_ref().to_path_buf())]),
+            dir: None,
+        }
+    }
+
+    fn path_of(&self, name: &str) -> Option<PathBuf> {
+        match self.files.get(name) {
+            Some(path) => Some(path.clone()),
+            None => self
+                .dir
+                .as_ref()
+                .map(|dir| dir.join(format!("{}.npy", name))),
+        }
+    }
+}
+
+impl InputSource for NpyInputSource {
+    fn read_input(&self, name: &str) -> Result<Tensor<f64>, GraphError> {
+        let path = self
+            .path_of(name)
+            .ok_or_else(|| read_error(name, "no .npy file was given for it"))?;
+        let bytes = std::fs::read(&path)
+            .map_err(|e| read_error(name, format!("{}: {}", path.display(), e)))?;
+        let (values, dims) = parse_npy(&bytes).map_err(|e| read_error(name, e))?;
+        Ok(Tensor::new(Some(&values), &dims)?)
+    }
+}
+
+/// Inputs already held in memory, e.g. ones a caller streamed in from elsewhere.
+#[derive(Clone, Debug, Default)]
+pub struct MemoryInputSource {
+    inputs: HashMap<String, Tensor<f64>>,
+}
+
+impl MemoryInputSource {
+    /// An empty source.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the input `name`, replacing any input already held under that name.
+    pub fn insert(&mut self, name: &str, input: Tensor<f64>) {
+        self.inputs.insert(name.to_string(), input);
+    }
+}
+
+impl From<HashMap<String, Tensor<f64>>> for MemoryInputSource {
+    fn from(inputs: HashMap<String, Tensor<f64>>) -> Self {
+        Self { inputs }
+    }
+}
+
+impl InputSource for MemoryInputSource {
+    fn read_input(&self, name: &str) -> Result<Tensor<f64>, GraphError> {
+        self.inputs
+            .get(name)
+            .cloned()
+            .ok_or_else(|| read_error(name, "no such input is held"))
+    }
+}
+
+/// Parses a little endian `.npy` array of floats or ints, in c order, into its values and dims.
+fn parse_npy(bytes: &[u8]) -> Result<(Vec<f64>, Vec<usize>), String> {
+    if bytes.len() < 10 || &bytes[..6] != b"\x93NUMPY" {
+        return Err("not a .npy file".to_string());
+    }
+    // version 1 headers give their length in 2 bytes, later ones in 4
+    let (header_len, start) = match bytes[6] {
+        1 => (u16::from_le_bytes([bytes[8], bytes[9]]) as usize, 10),
+        _ if bytes.len() >= 12 => (
+            u32::from_le_bytes([bytes[8], bytes[9], bytes[10], bytes[11]]) as usize,
+            12,
+        ),
+        _ => return Err("truncated .npy header".to_string()),
+    };
+    let header = bytes
+        .get(start..start + header_len)
+        .ok_or("truncated .npy header")?;
+    let header = std::str::from_utf8(header).map_err(|e| e.to_string())?;
+    let data = &bytes[start + header_len..];
+
+    let descr = header_value(header, "descr")?
+        .trim_matches(|c| c == '\'' || c == '"')
+        .to_string();
+    if header_value(header, "fortran_order")? != "False" {
+        return Err("fortran ordered arrays aren't supported".to_string());
+    }
+    let shape = header_value(header, "shape")?;
+    let dims = shape
+        .trim_matches(|c| c == '(' || c == ')')
+        .split(',')
+        .map(|d| d.trim())
+        .filter(|d| !d.is_empty())
+        .map(|d| d.parse::<usize>().map_err(|e| e.to_string()))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let values: Vec<f64> = match descr.as_str() {
+        "<f4" => data
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes(b.try_into().unwrap()) as f64)
+            .collect(),
+        "<f8" => data
+            .chunks_exact(8)
+            .map(|b| f64::from_le_bytes(b.try_into().unwrap()))
+            .collect(),
+        "<i4" => data
+            .chunks_exact(4)
+            .map(|b| i32::from_le_bytes(b.try_into().unwrap()) as f64)
+            .collect(),
+        "<i8" => data
+            .chunks_exact(8)
+            .map(|b| i64::from_le_bytes(b.try_into().unwrap()) as f64)
+            .collect(),
+        other => return Err(format!("unsupported dtype {}", other)),
+    };
+    let len = dims.iter().product::<usize>();
+    if values.len() != len {
+        return Err(format!(
+            "shape {:?} needs {} values, the file holds {}",
+            dims,
+            len,
+            values.len()
+        ));
+    }
+    Ok((values, dims))
+}
+
+/// The raw value of `key` in a `.npy` header, which is a python dict literal such as
+/// `{'descr': '<f4', 'fortran_order': False, 'shape': (1, 3), }`.
+fn header_value<'a>(header: &'a str, key: &str) -> Result<&'a str, String> {
+    let missing = || format!(".npy header has no {}", key);
+    let start = header.find(&format!("'{}'", key)).ok_or_else(missing)?;
+    let rest = header[start + key.len() + 2..].trim_start();
+    let rest = rest.strip_prefix(':').ok_or_else(missing)?.trim_start();
+    let end = match rest.starts_with('(') {
+        true => rest.find(')').map(|i| i + 1),
+        false => rest.find(|c| c == ',' || c == '}'),
+    };
+    Ok(rest[..end.ok_or_else(missing)?].trim())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn npy(descr: &str, shape: &str, data: &[u8]) -> Vec<u8> {
+        let mut header = format!(
+            "{{'descr': '{}', 'fortran_order': False, 'shape': {}, }}",
+            descr, shape
+        );
+        // numpy pads the header so the data is 64 byte aligned
+        while (10 + header.len() + 1) % 64 != 0 {
+            header.push(' ');
+        }
+        header.push('\n');
+        let mut bytes = b"\x93NUMPY\x01\x00".to_vec();
+        bytes.extend((header.len() as u16).to_le_bytes());
+        bytes.extend(header.as_bytes());
+        bytes.extend(data);
+        bytes
+    }
+
+    #[test]
+    fn npy_arrays_are_parsed() {
+        let data = [0.5f32, -1.0, 2.25, 3.0, 0.0, 7.5]
+            .iter()
+            .flat_map(|x| x.to_le_bytes())
+            .collect::<Vec<_>>();
+        let (values, dims) = parse_npy(&npy("<f4", "(2, 3)", &data)).unwrap();
+        assert_eq!(dims, vec![2, 3]);
+        assert_eq!(values, vec![0.5, -1.0, 2.25, 3.0, 0.0, 7.5]);
+
+        let data = [4i64, -2]
+            .iter()
+            .flat_map(|x| x.to_le_bytes())
+            .collect::<Vec<_>>();
+        let (values, dims) = parse_npy(&npy("<i8", "(2,)", &data)).unwrap();
+        assert_eq!(dims, vec![2]);
+        assert_eq!(values, vec![4.0, -2.0]);
+
+        // doubles keep the precision an f32 would round away
+        let x = 0.1 + 1e-12;
+        let (values, _) = parse_npy(&npy("<f8", "(1,)", &x.to_le_bytes())).unwrap();
+        assert_eq!(values, vec![x]);
+    }
+
+    #[test]
+    fn bad_npy_arrays_are_rejected() {
+        let data = 1.0f64.to_le_bytes();
+        assert!(parse_npy(&npy("<f8", "(2,)", &data)).is_err());
+        assert!(parse_npy(&npy(">f8", "(1,)", &data)).is_err());
+        let mut fortran = npy("<f8", "(1,)", &data);
+        let at = fortran.windows(5).position(|w| w == b"False").unwrap();
+        fortran[at..at + 5].copy_from_slice(b"True ");
+        assert!(parse_npy(&fortran).is_err());
+    }
+}
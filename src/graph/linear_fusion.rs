@@ -0,0 +1,573 @@
+use super::errors::GraphError;
+use super::proto::{
+    f32_le, name_of, parse, read_varint, read_varints, string, write_field, write_varint,
+    ATTRIBUTE_F, ATTRIBUTE_GRAPH, ATTRIBUTE_GRAPHS, ATTRIBUTE_I, ATTRIBUTE_INTS, ATTRIBUTE_NAME,
+    FLOAT, GRAPH_INITIALIZER, GRAPH_INPUT, GRAPH_NODE, GRAPH_OUTPUT, GRAPH_VALUE_INFO,
+    NODE_ATTRIBUTE, NODE_INPUT, NODE_NAME, NODE_OP_TYPE, NODE_OUTPUT, TENSOR_DATA_TYPE,
+    TENSOR_DIMS, TENSOR_FLOAT_DATA, TENSOR_NAME, TENSOR_RAW_DATA, VALUE_INFO_NAME, WIRE_FIXED32,
+    WIRE_LEN, WIRE_VARINT,
+};
+use log::{debug, info};
+use std::collections::{HashMap, HashSet};
+
+#[derive(Default)]
+struct Attribute {
+    f: Option<f32>,
+    i: Option<i64>,
+    ints: Vec<i64>,
+}
+
+/// The parts of a `NodeProto` the pass looks at. Nodes read from the model are written back as `raw`.
+struct Node {
+    raw: Option<Vec<u8>>,
+    name: String,
+    op: String,
+    inputs: Vec<String>,
+    outputs: Vec<String>,
+    attributes: HashMap<String, Attribute>,
+    has_subgraph: bool,
+}
+
+impl Node {
+    fn decode(msg: &[u8]) -> Result<Self, GraphError> {
+        let mut node = Node {
+            raw: Some(msg.to_vec()),
+            name: String::new(),
+            op: String::new(),
+            inputs: vec![],
+            outputs: vec![],
+            attributes: HashMap::new(),
+            has_subgraph: false,
+        };
+        for field in parse(msg)? {
+            match field.number {
+                NODE_INPUT => node.inputs.push(string(field.value)),
+                NODE_OUTPUT => node.outputs.push(string(field.value)),
+                NODE_NAME => node.name = string(field.value),
+                NODE_OP_TYPE => node.op = string(field.value),
+                NODE_ATTRIBUTE => {
+                    let (mut name, mut attribute) = (String::new(), Attribute::default());
+                    let mut ints = vec![];
+                    for field in parse(field.value)? {
+                        match (field.number, field.wire_type) {
+                            (ATTRIBUTE_NAME, _) => name = string(field.value),
+                            (ATTRIBUTE_F, WIRE_FIXED32) => attribute.f = Some(f32_le(field.value)),
+                            (ATTRIBUTE_I, WIRE_VARINT) => {
+                                attribute.i = Some(read_varint(field.value, &mut 0)? as i64)
+                            }
+                            (ATTRIBUTE_INTS, _) => {
+                                read_varints(field.wire_type, field.value, &mut ints)?
+                            }
+                            (ATTRIBUTE_GRAPH | ATTRIBUTE_GRAPHS, _) => node.has_subgraph = true,
+                            _ => {}
+                        }
+                    }
+                    attribute.ints = ints.into_iter().map(|i| i as i64).collect();
+                    node.attributes.insert(name, attribute);
+                }
+                _ => {}
+            }
+        }
+        Ok(node)
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        if let Some(raw) = &self.raw {
+            return raw.clone();
+        }
+        let mut out = vec![];
+        for input in &self.inputs {
+            write_field(&mut out, NODE_INPUT, WIRE_LEN, input.as_bytes());
+        }
+        for output in &self.outputs {
+            write_field(&mut out, NODE_OUTPUT, WIRE_LEN, output.as_bytes());
+        }
+        write_field(&mut out, NODE_NAME, WIRE_LEN, self.name.as_bytes());
+        write_field(&mut out, NODE_OP_TYPE, WIRE_LEN, self.op.as_bytes());
+        out
+    }
+
+    /// The node with new inputs and outputs, keeping the rest of it (e.g its attributes) as is.
+    fn rewired(&self, inputs: &[String], outputs: &[String]) -> Result<Node, GraphError> {
+        let mut out = vec![];
+        for input in inputs {
+            write_field(&mut out, NODE_INPUT, WIRE_LEN, input.as_bytes());
+        }
+        for output in outputs {
+            write_field(&mut out, NODE_OUTPUT, WIRE_LEN, output.as_bytes());
+        }
+        for field in parse(&self.encode())? {
+            if field.number != NODE_INPUT && field.number != NODE_OUTPUT {
+                write_field(&mut out, field.number, field.wire_type, field.value);
+            }
+        }
+        Node::decode(&out)
+    }
+
+    fn int(&self, name: &str, default: i64) -> i64 {
+        self.attributes
+            .get(name)
+            .and_then(|a| a.i)
+            .unwrap_or(default)
+    }
+
+    fn float(&self, name: &str, default: f32) -> f32 {
+        self.attributes
+            .get(name)
+            .and_then(|a| a.f)
+            .unwrap_or(default)
+    }
+
+    fn ints(&self, name: &str) -> &[i64] {
+        self.attributes
+            .get(name)
+            .map(|a| a.ints.as_slice())
+            .unwrap_or(&[])
+    }
+}
+
+/// A float initializer, row major.
+struct Tensor {
+    dims: Vec<usize>,
+    values: Vec<f32>,
+}
+
+/// Decodes an initializer's name, and its values if it holds floats.
+fn decode_tensor(msg: &[u8]) -> Result<(String, Option<Tensor>), GraphError> {
+    let (mut name, mut dims, mut data_type) = (String::new(), vec![], 0);
+    let (mut raw, mut floats) = (None, vec![]);
+    for field in parse(msg)? {
+        match (field.number, field.wire_type) {
+            (TENSOR_DIMS, _) => read_varints(field.wire_type, field.value, &mut dims)?,
+            (TENSOR_DATA_TYPE, WIRE_VARINT) => data_type = read_varint(field.value, &mut 0)?,
+            (TENSOR_FLOAT_DATA, WIRE_FIXED32) => floats.push(f32_le(field.value)),
+            (TENSOR_FLOAT_DATA, WIRE_LEN) => floats.extend(field.value.chunks_exact(4).map(f32_le)),
+            (TENSOR_NAME, _) => name = string(field.value),
+            (TENSOR_RAW_DATA, _) => raw = Some(field.value),
+            _ => {}
+        }
+    }
+    let dims = dims.into_iter().map(|d| d as usize).collect::<Vec<_>>();
+    let values = match raw {
+        Some(raw) => raw.chunks_exact(4).map(f32_le).collect(),
+        None => floats,
+    };
+    let is_float = data_type == FLOAT && values.len() == dims.iter().product::<usize>();
+    Ok((name, is_float.then_some(Tensor { dims, values })))
+}
+
+fn encode_tensor(name: &str, tensor: &Tensor) -> Vec<u8> {
+    let mut out = vec![];
+    let mut varint = |number, value| {
+        let mut encoded = vec![];
+        write_varint(&mut encoded, value);
+        write_field(&mut out, number, WIRE_VARINT, &encoded);
+    };
+    for d in &tensor.dims {
+        varint(TENSOR_DIMS, *d as u64);
+    }
+    varint(TENSOR_DATA_TYPE, FLOAT);
+    write_field(&mut out, TENSOR_NAME, WIRE_LEN, name.as_bytes());
+    let raw = tensor
+        .values
+        .iter()
+        .flat_map(|v| v.to_le_bytes())
+        .collect::<Vec<_>>();
+    write_field(&mut out, TENSOR_RAW_DATA, WIRE_LEN, &raw);
+    out
+}
+
+/// Transposes a row major `[rows, cols]` matrix.
+fn transpose(values: &[f32], rows: usize, cols: usize) -> Vec<f32> {
+    (0..rows * cols)
+        .map(|idx| values[(idx % rows) * cols + idx / rows])
+        .collect()
+}
+
+/// A layer computing `x W + b` over its input's last (or, for conv, channel) axis, `W` being `[fan_in, fan_out]`.
+struct Linear {
+    weight: Vec<f32>,
+    fan_in: usize,
+    fan_out: usize,
+    bias: Option<Vec<f32>>,
+}
+
+/// The layer `node` computes, if it is a `MatMul`, `Gemm` or 1x1 `Conv` by constant float weights.
+fn as_linear(node: &Node, tensors: &HashMap<String, Tensor>) -> Option<Linear> {
+    let weight = tensors.get(node.inputs.get(1)?)?;
+    let bias = match node.inputs.get(2).filter(|b| !b.is_empty()) {
+        Some(b) => Some(tensors.get(b)?),
+        None => None,
+    };
+    let dims = &weight.dims;
+    let (weight, fan_in, fan_out) = match node.op.as_str() {
+        "MatMul" if dims.len() == 2 => (weight.values.clone(), dims[0], dims[1]),
+        "Gemm"
+            if dims.len() == 2
+                && node.float("alpha", 1.0) == 1.0
+                && node.float("beta", 1.0) == 1.0
+                && node.int("transA", 0) == 0 =>
+        {
+            if node.int("transB", 0) != 0 {
+                (
+                    transpose(&weight.values, dims[0], dims[1]),
+                    dims[1],
+                    dims[0],
+                )
+            } else {
+                (weight.values.clone(), dims[0], dims[1])
+            }
+        }
+        // a pointwise conv is a linear map over the channels
+        "Conv"
+            if dims.len() > 2
+                && dims[2..].iter().all(|d| *d == 1)
+                && node.int("group", 1) == 1
+                && node.ints("strides").iter().all(|s| *s == 1)
+                && node.ints("dilations").iter().all(|d| *d == 1)
+                && node.ints("pads").iter().all(|p| *p == 0) =>
+        {
+            (
+                transpose(&weight.values, dims[0], dims[1]),
+                dims[1],
+                dims[0],
+            )
+        }
+        _ => return None,
+    };
+    let bias = match bias {
+        Some(b) if b.values.len() == fan_out && b.dims.iter().filter(|d| **d != 1).count() <= 1 => {
+            Some(b.values.clone())
+        }
+        Some(_) => return None,
+        None => None,
+    };
+    Some(Linear {
+        weight,
+        fan_in,
+        fan_out,
+        bias,
+    })
+}
+
+/// The single layer applying `first` then `second`: `x W1 W2 + (b1 W2 + b2)`.
+fn compose(first: &Linear, second: &Linear) -> Option<Linear> {
+    if first.fan_out != second.fan_in {
+        return None;
+    }
+    let (n, k, m) = (first.fan_in, first.fan_out, second.fan_out);
+    // through the second layer's weights
+    let project = |row: &[f32], j: usize| -> f64 {
+        (0..k)
+            .map(|l| row[l] as f64 * second.weight[l * m + j] as f64)
+            .sum()
+    };
+    let weight = (0..n * m)
+        .map(|idx| project(&first.weight[(idx / m) * k..], idx % m) as f32)
+        .collect();
+    let bias = match (&first.bias, &second.bias) {
+        (None, None) => None,
+        (b1, b2) => Some(
+            (0..m)
+                .map(|j| {
+                    let carried = b1.as_ref().map_or(0.0, |b1| project(b1, j));
+                    (carried + b2.as_ref().map_or(0.0, |b2| b2[j] as f64)) as f32
+                })
+                .collect(),
+        ),
+    };
+    Some(Linear {
+        weight,
+        fan_in: n,
+        fan_out: m,
+        bias,
+    })
+}
+
+/// The indices of a layer and the following layer it can be folded into, and the folded layer
+/// with its new initializers.
+type Fusion = (usize, usize, Node, Vec<(String, Tensor)>);
+
+/// Finds a layer and the following layer it can be folded into, if any.
+type FindFusion = fn(&[Node], &HashMap<String, Tensor>, &HashSet<String>) -> Option<Fusion>;
+
+/// The pairs of nodes `(a, b)` where `b`'s first input is the only output of `a`, which nothing
+/// else reads, so that `a` can be folded into `b`.
+fn foldable_pairs<'a>(
+    nodes: &'a [Node],
+    graph_outputs: &'a HashSet<String>,
+) -> impl Iterator<Item = (usize, usize)> + 'a {
+    let mut uses = HashMap::<&str, usize>::new();
+    for input in nodes.iter().flat_map(|n| &n.inputs) {
+        *uses.entry(input.as_str()).or_default() += 1;
+    }
+
+    nodes.iter().enumerate().filter_map(move |(b, second)| {
+        let input = second.inputs.first()?;
+        if uses[input.as_str()] != 1 || graph_outputs.contains(input) {
+            return None;
+        }
+        let a = nodes
+            .iter()
+            .position(|n| n.outputs.len() == 1 && &n.outputs[0] == input)?;
+        Some((a, b))
+    })
+}
+
+/// A name for a new initializer of the layer writing `output`, clashing with no other.
+fn unique_name(tensors: &HashMap<String, Tensor>, output: &str, suffix: &str) -> String {
+    let mut name = format!("{}_fused_{}", output, suffix);
+    while tensors.contains_key(&name) {
+        name.push('_');
+    }
+    name
+}
+
+fn find_fusion(
+    nodes: &[Node],
+    tensors: &HashMap<String, Tensor>,
+    graph_outputs: &HashSet<String>,
+) -> Option<Fusion> {
+    for (a, b) in foldable_pairs(nodes, graph_outputs) {
+        let (first, second) = (&nodes[a], &nodes[b]);
+        if first.op != second.op {
+            continue;
+        }
+        let fused = match (as_linear(first, tensors), as_linear(second, tensors)) {
+            (Some(l1), Some(l2)) => match compose(&l1, &l2) {
+                Some(fused) => fused,
+                None => continue,
+            },
+            _ => continue,
+        };
+
+        let unique = |suffix: &str| unique_name(tensors, &second.outputs[0], suffix);
+        let (n, m) = (fused.fan_in, fused.fan_out);
+        let weight = if second.op == "Conv" {
+            let mut dims = vec![m, n];
+            dims.extend(&tensors[&first.inputs[1]].dims[2..]);
+            Tensor {
+                dims,
+                values: transpose(&fused.weight, n, m),
+            }
+        } else {
+            Tensor {
+                dims: vec![n, m],
+                values: fused.weight,
+            }
+        };
+        let mut new_tensors = vec![(unique("weight"), weight)];
+        if let Some(bias) = fused.bias {
+            let bias = Tensor {
+                dims: vec![m],
+                values: bias,
+            };
+            new_tensors.push((unique("bias"), bias));
+        }
+
+        let mut inputs = vec![first.inputs[0].clone()];
+        inputs.extend(new_tensors.iter().map(|(name, _)| name.clone()));
+        let node = Node {
+            raw: None,
+            name: format!("{}_{}", first.name, second.name),
+            op: second.op.clone(),
+            inputs,
+            outputs: second.outputs.clone(),
+            attributes: HashMap::new(),
+            has_subgraph: false,
+        };
+        return Some((a, b, node, new_tensors));
+    }
+    None
+}
+
+/// The indices of a `Conv` by constant float weights and the `BatchNormalization` in inference
+/// mode that follows it, and the single `Conv` computing both.
+fn find_batch_norm_fold(
+    nodes: &[Node],
+    tensors: &HashMap<String, Tensor>,
+    graph_outputs: &HashSet<String>,
+) -> Option<Fusion> {
+    for (a, b) in foldable_pairs(nodes, graph_outputs) {
+        let (conv, bn) = (&nodes[a], &nodes[b]);
+        if conv.op != "Conv"
+            || bn.op != "BatchNormalization"
+            || bn.outputs.len() != 1
+            || bn.int("training_mode", 0) != 0
+        {
+            continue;
+        }
+        let weight = match conv.inputs.get(1).and_then(|w| tensors.get(w)) {
+            Some(weight) if weight.dims.len() > 2 => weight,
+            _ => continue,
+        };
+        let channels = weight.dims[0];
+        let per_channel = |name: Option<&String>| {
+            tensors
+                .get(name?)
+                .filter(|t| t.values.len() == channels)
+                .map(|t| t.values.iter().map(|v| *v as f64).collect::<Vec<_>>())
+        };
+        let bias = match conv.inputs.get(2).filter(|b| !b.is_empty()) {
+            Some(b) => match per_channel(Some(b)) {
+                Some(bias) => bias,
+                None => continue,
+            },
+            None => vec![0.0; channels],
+        };
+        let (gamma, beta, mean, var) = match (
+            per_channel(bn.inputs.get(1)),
+            per_channel(bn.inputs.get(2)),
+            per_channel(bn.inputs.get(3)),
+            per_channel(bn.inputs.get(4)),
+        ) {
+            (Some(gamma), Some(beta), Some(mean), Some(var)) => (gamma, beta, mean, var),
+            _ => continue,
+        };
+
+        let epsilon = bn.float("epsilon", 1e-5) as f64;
+        let multipliers = (0..channels)
+            .map(|c| gamma[c] / (var[c] + epsilon).sqrt())
+            .collect::<Vec<_>>();
+        // the kernel's output channels are its leading axis
+        let kernel_len = weight.values.len() / channels;
+        let weight = Tensor {
+            dims: weight.dims.clone(),
+            values: weight
+                .values
+                .iter()
+                .enumerate()
+                .map(|(i, w)| (*w as f64 * multipliers[i / kernel_len]) as f32)
+                .collect(),
+        };
+        let bias = Tensor {
+            dims: vec![channels],
+            values: (0..channels)
+                .map(|c| ((bias[c] - mean[c]) * multipliers[c] + beta[c]) as f32)
+                .collect(),
+        };
+
+        let new_tensors = vec![
+            (unique_name(tensors, &bn.outputs[0], "weight"), weight),
+            (unique_name(tensors, &bn.outputs[0], "bias"), bias),
+        ];
+        let mut inputs = vec![conv.inputs[0].clone()];
+        inputs.extend(new_tensors.iter().map(|(name, _)| name.clone()));
+        let node = match conv.rewired(&inputs, &bn.outputs) {
+            Ok(node) => node,
+            Err(_) => continue,
+        };
+        return Some((a, b, node, new_tensors));
+    }
+    None
+}
+
+fn fuse_graph(
+    graph: &[u8],
+    find: FindFusion,
+    num_fused: &mut usize,
+) -> Result<Option<Vec<u8>>, GraphError> {
+    let fields = parse(graph)?;
+    let mut nodes = vec![];
+    let mut tensors = HashMap::new();
+    let mut graph_outputs = HashSet::new();
+    for field in &fields {
+        match field.number {
+            GRAPH_NODE => nodes.push(Node::decode(field.value)?),
+            GRAPH_INITIALIZER => {
+                if let (name, Some(tensor)) = decode_tensor(field.value)? {
+                    tensors.insert(name, tensor);
+                }
+            }
+            GRAPH_OUTPUT => {
+                graph_outputs.insert(name_of(field.value, VALUE_INFO_NAME)?);
+            }
+            _ => {}
+        }
+    }
+    // a subgraph could read the output of a layer that would be folded away
+    if nodes.iter().any(|n| n.has_subgraph) {
+        debug!("not fusing layers of a graph with subgraphs");
+        return Ok(None);
+    }
+
+    let mut removed = HashSet::new();
+    let mut added = vec![];
+    while let Some((a, b, node, new_tensors)) = find(&nodes, &tensors, &graph_outputs) {
+        info!(
+            "fusing {} {} into the {} {} that follows it",
+            nodes[a].op, nodes[a].name, nodes[b].op, nodes[b].name
+        );
+        removed.extend(nodes[a].inputs.iter().skip(1).cloned());
+        removed.extend(nodes[b].inputs.iter().cloned());
+        for (name, tensor) in new_tensors {
+            added.push(name.clone());
+            tensors.insert(name, tensor);
+        }
+        nodes[b] = node;
+        nodes.remove(a);
+        *num_fused += 1;
+    }
+    if added.is_empty() {
+        return Ok(None);
+    }
+
+    let mut used = graph_outputs;
+    used.extend(nodes.iter().flat_map(|n| n.inputs.iter().cloned()));
+    let is_dropped = |name: String| removed.contains(&name) && !used.contains(&name);
+
+    let mut out = Vec::with_capacity(graph.len());
+    let mut nodes_written = false;
+    for field in &fields {
+        match field.number {
+            GRAPH_NODE if nodes_written => {}
+            GRAPH_NODE => {
+                for node in &nodes {
+                    write_field(&mut out, GRAPH_NODE, WIRE_LEN, &node.encode());
+                }
+                nodes_written = true;
+            }
+            GRAPH_INITIALIZER if is_dropped(name_of(field.value, TENSOR_NAME)?) => {}
+            GRAPH_INPUT | GRAPH_VALUE_INFO
+                if is_dropped(name_of(field.value, VALUE_INFO_NAME)?) => {}
+            _ => write_field(&mut out, field.number, field.wire_type, field.value),
+        }
+    }
+    for name in added.iter().filter(|name| used.contains(*name)) {
+        write_field(
+            &mut out,
+            GRAPH_INITIALIZER,
+            WIRE_LEN,
+            &encode_tensor(name, &tensors[name]),
+        );
+    }
+    Ok(Some(out))
+}
+
+/// Folds each `MatMul`, `Gemm` or 1x1 `Conv` by constant weights whose output only feeds a
+/// following layer of the same kind into that layer, multiplying out their float weights and
+/// carrying the first bias through the second. Chains are folded down to a single layer. Returns
+/// the rewritten graph, or `None` if nothing was fused.
+pub fn fuse_linear(graph: &[u8]) -> Result<Option<Vec<u8>>, GraphError> {
+    let mut num_fused = 0;
+    let fused = fuse_graph(graph, find_fusion, &mut num_fused)?;
+    info!("fused {} pairs of linear layers", num_fused);
+    Ok(fused)
+}
+
+/// Folds each `BatchNormalization` in inference mode that only reads the output of a `Conv` by
+/// constant float weights into that `Conv`, scaling each output channel's kernel by
+/// `gamma / sqrt(var + epsilon)` and moving its bias to match. The folded `Conv` is quantized like
+/// any other, so the normalization costs no constraints. Returns the rewritten graph, or `None`
+/// if nothing was folded.
+pub fn fold_batch_norms(graph: &[u8]) -> Result<Option<Vec<u8>>, GraphError> {
+    let mut num_folded = 0;
+    let folded = fuse_graph(graph, find_batch_norm_fold, &mut num_folded)?;
+    if num_folded > 0 {
+        info!(
+            "folded {} batch norms into the convs before them",
+            num_folded
+        );
+    }
+    Ok(folded)
+}
@@ -1,46 +1,87 @@
+/// A builder for circuits from onnx models, with the settings the CLI exposes.
+#[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
+pub mod builder;
+/// Inlining of constant onnx `If`s, and detection of the control flow circuits can't express.
+#[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
+pub mod control_flow;
+/// Reports of which of an onnx model's ops are supported, checked against their attributes.
+#[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
+pub mod coverage;
+/// Commitments to datasets whose rows a circuit proves its input is one of.
+pub mod dataset;
+/// Loading of onnx tensors stored in external data files.
+#[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
+pub mod external_data;
 /// Representations of a computational graph's inputs.
 pub mod input;
+/// Sources a model's inputs can be read from by name, such as .npy files.
+pub mod input_source;
+/// Folding of back-to-back linear layers in onnx models.
+#[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
+pub mod linear_fusion;
 /// Crate for defining a computational graph and building a ZK-circuit from it.
 pub mod model;
 /// Representations of a computational graph's modules.
 pub mod modules;
 /// Inner elements of a computational graph that represent a single operation / constraints.
 pub mod node;
+/// Comparison of a circuit's outputs against expected float outputs, within a tolerance.
+pub mod output_check;
 /// postgres helper functions
 #[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
 pub mod postgres;
+/// Just enough of the protobuf wire format to walk and rewrite onnx models.
+#[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
+pub(crate) mod proto;
+/// Proving of a circuit's witnesses that can be cancelled or time out.
+pub mod prover;
+/// Lowering of onnx's integer `QLinearConv` and `QLinearMatMul` onto float ops over their codes.
+#[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
+pub mod qlinear;
+/// Lowering of the linear models sklearn exports onto standard onnx ops.
+#[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
+pub mod sklearn;
 /// Helper functions
 pub mod utilities;
+/// Checks that onnx graphs are well formed before they're loaded.
+#[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
+pub mod validation;
 /// Representations of a computational graph's variables.
 pub mod vars;
 
 /// errors for the graph
 pub mod errors;
 
+#[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
+pub use builder::OnnxModelBuilder;
 #[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
 use colored_json::ToColoredJson;
 #[cfg(all(not(not(feature = "ezkl")), unix))]
 use gag::Gag;
 use halo2_proofs::plonk::VerifyingKey;
 use halo2_proofs::poly::commitment::CommitmentScheme;
-pub use input::DataSource;
+pub use input::{DataSource, InstanceData};
 use itertools::Itertools;
+pub use prover::{CancellationToken, Interrupt, ProveOptions, Prover};
 #[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
 use tosubcommand::ToFlags;
 
+use self::dataset::{DatasetCommitment, DatasetOpening};
 use self::errors::GraphError;
 #[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
 use self::input::OnChainSource;
-use self::input::{FileSource, GraphData};
+use self::input::{check_input_shape, FileSource, FileSourceInner, GraphData};
+use self::input_source::InputSource;
 use self::modules::{GraphModules, ModuleConfigs, ModuleForwardResult, ModuleSizes};
 use crate::circuit::lookup::LookupOp;
 use crate::circuit::modules::ModulePlanner;
 use crate::circuit::region::{ConstantsMap, RegionSettings};
 use crate::circuit::table::{num_cols_required, Range, Table, RESERVED_BLINDING_ROWS_PAD};
+use crate::circuit::witness_log::{self, WitnessLog};
 use crate::circuit::{CheckMode, InputType};
-use crate::fieldutils::{felt_to_f64, IntegerRep};
+use crate::fieldutils::{felt_to_f64, felt_to_integer_rep, integer_rep_to_felt, IntegerRep};
 use crate::pfsys::PrettyElements;
-use crate::tensor::{Tensor, ValTensor};
+use crate::tensor::{Tensor, ValTensor, VarTensor};
 use crate::{RunArgs, EZKL_BUF_CAPACITY};
 
 use halo2_proofs::{
@@ -107,11 +148,53 @@ pub const RESERVED_BLINDING_ROWS: usize = ASSUMED_BLINDING_FACTORS + RESERVED_BL
 use std::cell::RefCell;
 
 thread_local!(
-    /// This is a global variable that holds the settings for the graph
+    /// This is a global variable that holds the settings for the graph, set when the graph is laid out
     /// This is used to pass settings to the layouter and other parts of the circuit without needing to heavily modify the Halo2 API in a new fork
     pub static GLOBAL_SETTINGS: RefCell<Option<GraphSettings>> = const { RefCell::new(None) }
 );
 
+/// A node whose output went past the `output_max` it was calibrated for
+#[derive(Clone, Debug, PartialEq)]
+pub struct OutputMaxExceeded {
+    /// The node's index
+    pub node: usize,
+    /// The node's op
+    pub op: String,
+    /// The largest output magnitude the node produced
+    pub observed: IntegerRep,
+    /// The largest output magnitude the node was calibrated for
+    pub output_max: IntegerRep,
+}
+
+/// The violated constraint a replay of a witness log reports, see [GraphCircuit::replay_witness_log]
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReplayFailure {
+    /// The violation, as the mock prover reports it
+    pub failure: String,
+    /// The row of its region the violation is at, if it's in a region
+    pub row: Option<usize>,
+    /// The nodes which assigned cells on that row, with their ops
+    pub nodes: Vec<(usize, String)>,
+}
+
+impl std::fmt::Display for ReplayFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.failure)?;
+        if let Some(row) = self.row {
+            let nodes = match self.nodes.is_empty() {
+                true => "no node".to_string(),
+                false => self
+                    .nodes
+                    .iter()
+                    .map(|(idx, op)| format!("node {} ({})", idx, op))
+                    .join(", "),
+            };
+            write!(f, "\nat row {}, laid out by {}", row, nodes)?;
+        }
+        Ok(())
+    }
+}
+
 /// Result from a forward pass
 #[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
 pub struct GraphWitness {
@@ -133,6 +216,24 @@ pub struct GraphWitness {
     pub min_lookup_inputs: IntegerRep,
     /// max range check size
     pub max_range_size: IntegerRep,
+    /// min and max input of each lookup
+    #[serde(default)]
+    pub lookup_ranges: Vec<(LookupOp, Range)>,
+    /// largest output magnitude of each node
+    #[serde(default)]
+    pub node_output_max: Vec<(usize, IntegerRep)>,
+    /// every node's outputs, recorded when the circuit checks its witness
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trace: Option<ForwardTrace>,
+    /// the hash of the context the proof is bound to, see [RunArgs::bind_context]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context_hash: Option<Fp>,
+    /// the opening of the dataset row the inputs are, see [RunArgs::dataset_depth]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dataset_opening: Option<DatasetOpening>,
+    /// how long `gen-witness` took to run the forward pass, reported with the proof
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub forward_time: Option<std::time::Duration>,
 }
 
 impl GraphWitness {
@@ -161,9 +262,36 @@ impl GraphWitness {
             max_lookup_inputs: 0,
             min_lookup_inputs: 0,
             max_range_size: 0,
+            lookup_ranges: vec![],
+            node_output_max: vec![],
+            trace: None,
+            context_hash: None,
+            dataset_opening: None,
+            forward_time: None,
         }
     }
 
+    /// Binds the proof to a context, see [RunArgs::bind_context]
+    pub fn set_context(&mut self, context: &[u8]) {
+        self.context_hash = Some(modules::context_hash(context));
+    }
+
+    /// Opens the inputs as row `index` of a committed dataset, see [RunArgs::dataset_depth]
+    pub fn set_dataset_opening(
+        &mut self,
+        dataset: &DatasetCommitment,
+        index: usize,
+    ) -> Result<(), GraphError> {
+        let opening = dataset.opening(index, &self.dataset_row())?;
+        self.dataset_opening = Some(opening.clone());
+        Ok(())
+    }
+
+    /// The inputs flattened and concatenated, as a dataset row
+    pub fn dataset_row(&self) -> Vec<Fp> {
+        self.inputs.iter().flatten().cloned().collect()
+    }
+
     /// Generate the rescaled elements for the witness
     pub fn generate_rescaled_elements(
         &mut self,
@@ -428,6 +556,12 @@ pub struct GraphSettings {
     pub required_lookups: Vec<LookupOp>,
     /// required range_checks
     pub required_range_checks: Vec<Range>,
+    /// calibrated input range of each lookup, ops not listed here use `run_args.lookup_range`
+    #[serde(default)]
+    pub lookup_ranges: Vec<(LookupOp, Range)>,
+    /// calibrated bound on the output magnitude of each node, see [GraphCircuit::check_output_max]
+    #[serde(default)]
+    pub output_max: Vec<(usize, IntegerRep)>,
     /// check mode
     pub check_mode: CheckMode,
     /// ezkl version used
@@ -439,6 +573,26 @@ pub struct GraphSettings {
 }
 
 impl GraphSettings {
+    /// The calibrated table range for a lookup op (if any)
+    pub fn lookup_range(&self, op: &LookupOp) -> Option<Range> {
+        self.lookup_ranges
+            .iter()
+            .find(|(o, _)| o == op)
+            .map(|(_, range)| *range)
+    }
+
+    /// The rows of each required lookup's table, over its calibrated range if it has one and
+    /// over `run_args.lookup_range` otherwise
+    pub fn lookup_table_rows(&self) -> Vec<(LookupOp, usize)> {
+        self.required_lookups
+            .iter()
+            .map(|op| {
+                let range = self.lookup_range(op).unwrap_or(self.run_args.lookup_range);
+                (op.clone(), (range.1 - range.0 + 1) as usize)
+            })
+            .collect()
+    }
+
     /// Calc the number of rows required for lookup tables
     pub fn lookup_log_rows(&self) -> u32 {
         ((self.run_args.lookup_range.1 - self.run_args.lookup_range.0) as f32)
@@ -528,6 +682,44 @@ impl GraphSettings {
         instances
     }
 
+    /// The number of public instance rows a proof carries, over all instance columns
+    pub fn num_instances(&self) -> usize {
+        self.total_instances().iter().sum()
+    }
+
+    /// Fails if the circuit has more public instances than `max_instances`, suggesting hashing the
+    /// public inputs or outputs, which makes each of them a single instance
+    pub fn check_instance_budget(&self) -> Result<(), GraphError> {
+        let Some(budget) = self.run_args.max_instances else {
+            return Ok(());
+        };
+        let num_instances = self.num_instances();
+        if num_instances <= budget {
+            return Ok(());
+        }
+        let public = [
+            ("input", &self.run_args.input_visibility),
+            ("output", &self.run_args.output_visibility),
+        ]
+        .into_iter()
+        .filter(|(_, visibility)| visibility.is_public())
+        .map(|(kind, _)| format!("--{}-visibility hashed/public", kind))
+        .collect_vec();
+        let hint = if public.is_empty() {
+            String::new()
+        } else {
+            format!(
+                "; try hashing them with {} to make each a single instance",
+                public.join(" ")
+            )
+        };
+        Err(GraphError::InstanceBudgetExceeded(
+            num_instances,
+            budget,
+            hint,
+        ))
+    }
+
     /// calculate the log2 of the total number of instances
     pub fn log2_total_instances(&self) -> u32 {
         let sum = self.total_instances().iter().sum::<usize>();
@@ -544,6 +736,272 @@ impl GraphSettings {
         std::cmp::max((sum as f64).log2().ceil() as u32, 1)
     }
 
+    /// The range of the model's own instances among the (flattened) instances: its public inputs,
+    /// then its public outputs, then an opened dataset row's root and index and the context hash
+    /// if it takes them. Only the hashes of hashed outputs come after them.
+    pub fn model_instance_range(&self) -> std::ops::Range<usize> {
+        let trailing = if self.run_args.output_visibility.is_hashed_public() {
+            self.model_output_scales.len()
+        } else {
+            0
+        };
+        let end = self.total_instances().iter().sum::<usize>() - trailing;
+        let len = self
+            .model_instance_shapes
+            .iter()
+            .map(|shape| shape.iter().product::<usize>())
+            .sum::<usize>();
+        end - len..end
+    }
+
+    /// The index of the context hash among the (flattened) instances, if the circuit binds one.
+    /// It is the model's last instance, so only the hashes of hashed outputs come after it.
+    pub fn context_instance(&self) -> Option<usize> {
+        if !self.run_args.bind_context {
+            return None;
+        }
+        Some(self.model_instance_range().end - 1)
+    }
+
+    /// Quantizes claimed public inputs and outputs (see [InstanceData]) into the instances they
+    /// take, checking them against the tensors the circuit makes public and their lengths
+    pub fn quantize_instance_data(&self, data: &InstanceData) -> Result<Vec<Fp>, GraphError> {
+        let mut shapes = self.model_instance_shapes.iter();
+        let mut instances = vec![];
+        for (kind, is_public, tensors, scales) in [
+            (
+                "input",
+                self.run_args.input_visibility.is_public(),
+                &data.input_data,
+                &self.model_input_scales,
+            ),
+            (
+                "output",
+                self.run_args.output_visibility.is_public(),
+                &data.output_data,
+                &self.model_output_scales,
+            ),
+        ] {
+            if !is_public {
+                if !tensors.is_empty() {
+                    return Err(GraphError::InvalidInstanceData(format!(
+                        "{}_data was supplied but the {}s aren't public",
+                        kind, kind
+                    )));
+                }
+                continue;
+            }
+            if tensors.len() != scales.len() {
+                return Err(GraphError::InvalidInstanceData(format!(
+                    "expected {} {} tensors, got {}",
+                    scales.len(),
+                    kind,
+                    tensors.len()
+                )));
+            }
+            for (i, (tensor, scale)) in tensors.iter().zip(scales).enumerate() {
+                let shape = shapes.next().cloned().unwrap_or_default();
+                if tensor.len() != shape.iter().product::<usize>() {
+                    return Err(GraphError::InvalidInstanceData(format!(
+                        "{} {} has {} values, expected {} for shape {:?}",
+                        kind,
+                        i,
+                        tensor.len(),
+                        shape.iter().product::<usize>(),
+                        shape
+                    )));
+                }
+                for (j, value) in tensor.iter().enumerate() {
+                    let quantized = quantize_float(value, 0.0, *scale)
+                        .ok()
+                        .filter(|_| value.is_finite())
+                        .ok_or_else(|| {
+                            GraphError::InvalidInstanceData(format!(
+                                "value {} of {} {} ({}) can't be represented at scale {}",
+                                j, kind, i, value, scale
+                            ))
+                        })?;
+                    instances.push(integer_rep_to_felt(quantized));
+                }
+            }
+        }
+        Ok(instances)
+    }
+
+    /// Overwrites the model's public input and output instances among `instances` (one vec per
+    /// instance column, as in a proof) with claimed ones, see [GraphSettings::quantize_instance_data]
+    pub fn replace_instances(
+        &self,
+        instances: &mut [Vec<Fp>],
+        data: &InstanceData,
+    ) -> Result<(), GraphError> {
+        let quantized = self.quantize_instance_data(data)?;
+        let expected = self.total_instances().iter().sum::<usize>();
+        let found = instances.iter().map(Vec::len).sum::<usize>();
+        if found != expected {
+            return Err(GraphError::InvalidInstanceData(format!(
+                "the proof has {} instances but the settings lay out {}",
+                found, expected
+            )));
+        }
+        let start = self.model_instance_range().start;
+        for (instance, value) in instances.iter_mut().flatten().skip(start).zip(quantized) {
+            *instance = value;
+        }
+        Ok(())
+    }
+
+    /// The indices of the opened dataset row's root and index among the (flattened) instances, if
+    /// the circuit opens one. They come right before any context hash.
+    pub fn dataset_instances(&self) -> Option<[usize; 2]> {
+        self.run_args.dataset_depth?;
+        let end = self.model_instance_range().end - self.run_args.bind_context as usize;
+        Some([end - 2, end - 1])
+    }
+
+    /// Checks that a proof's (flattened) instances open a row of the dataset with Merkle root
+    /// `root`, e.g as published with `commit-dataset`. Returns the index of the opened row.
+    pub fn check_dataset_root(&self, instances: &[Fp], root: Fp) -> Result<usize, GraphError> {
+        let [root_idx, index_idx] = self
+            .dataset_instances()
+            .ok_or(GraphError::UnexpectedDataset)?;
+        let opened = instances
+            .get(root_idx)
+            .ok_or(GraphError::MissingInstances)?;
+        if *opened != root {
+            return Err(GraphError::DatasetRootMismatch(
+                format!("{:?}", opened),
+                format!("{:?}", root),
+            ));
+        }
+        let index = instances
+            .get(index_idx)
+            .ok_or(GraphError::MissingInstances)?;
+        Ok(felt_to_integer_rep(*index) as usize)
+    }
+
+    /// Checks that a proof's (flattened) instances are bound to `context`, if the circuit binds one
+    pub fn check_context(
+        &self,
+        instances: &[Fp],
+        context: Option<&[u8]>,
+    ) -> Result<(), GraphError> {
+        match (self.context_instance(), context) {
+            (None, None) => Ok(()),
+            (None, Some(_)) => Err(GraphError::UnboundContext),
+            (Some(_), None) => Err(GraphError::MissingContext),
+            (Some(idx), Some(context)) => {
+                let bound = instances.get(idx).ok_or(GraphError::MissingInstances)?;
+                let expected = modules::context_hash(context);
+                if *bound != expected {
+                    return Err(GraphError::ContextMismatch(
+                        format!("{:?}", bound),
+                        format!("{:?}", expected),
+                    ));
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// The index of the params' Merkle root among the (flattened) instances under `merkle` param
+    /// visibility. Only the hashes of hashed inputs come before it.
+    pub fn weights_root_instance(&self) -> Option<usize> {
+        if !self.run_args.param_visibility.is_merkle() {
+            return None;
+        }
+        if self.run_args.input_visibility.is_hashed_public() {
+            Some(self.model_input_scales.len())
+        } else {
+            Some(0)
+        }
+    }
+
+    /// Checks that a proof's (flattened) instances commit to the params with Merkle root `root`,
+    /// e.g as published with `commit-weights`, if the circuit commits to one. A proof whose params
+    /// are committed to can't be checked without the root, as it could have been made for any params
+    pub fn check_weights_root(&self, instances: &[Fp], root: Option<Fp>) -> Result<(), GraphError> {
+        match (self.weights_root_instance(), root) {
+            (None, None) => Ok(()),
+            (None, Some(_)) => Err(GraphError::UncommittedWeights),
+            (Some(_), None) => Err(GraphError::MissingWeightsRoot),
+            (Some(idx), Some(root)) => {
+                let committed = instances.get(idx).ok_or(GraphError::MissingInstances)?;
+                if *committed != root {
+                    return Err(GraphError::WeightsRootMismatch(
+                        format!("{:?}", committed),
+                        format!("{:?}", root),
+                    ));
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// The public output tensors among a proof's (flattened) instances, which follow the public
+    /// inputs among the model's instances
+    pub fn output_instances<'a>(&self, instances: &'a [Fp]) -> Result<Vec<&'a [Fp]>, GraphError> {
+        if !self.run_args.output_visibility.is_public() {
+            return Err(GraphError::InvalidInstanceData(
+                "the outputs aren't public".to_string(),
+            ));
+        }
+        let lens = self
+            .model_instance_shapes
+            .iter()
+            .map(|shape| shape.iter().product::<usize>())
+            .collect::<Vec<_>>();
+        let num_inputs = match self.run_args.input_visibility.is_public() {
+            true => self.model_input_scales.len(),
+            false => 0,
+        };
+        let mut start =
+            self.model_instance_range().start + lens.iter().take(num_inputs).sum::<usize>();
+        let mut outputs = vec![];
+        for len in lens
+            .iter()
+            .skip(num_inputs)
+            .take(self.model_output_scales.len())
+        {
+            outputs.push(
+                instances
+                    .get(start..start + len)
+                    .ok_or(GraphError::MissingInstances)?,
+            );
+            start += len;
+        }
+        Ok(outputs)
+    }
+
+    /// The rows of each column left for assignments at `logrows`, see [VarTensor::max_rows]
+    fn usable_rows(&self, logrows: u32) -> usize {
+        let blinding = self
+            .num_blinding_factors
+            .unwrap_or(ASSUMED_BLINDING_FACTORS);
+        2usize.pow(logrows).saturating_sub(blinding + 1).max(1)
+    }
+
+    /// The advice columns [ModelVars] allocates to hold `assignments` model cells at `logrows`
+    pub fn advice_width_for(&self, assignments: usize, logrows: u32) -> usize {
+        let num_inner_cols = self.run_args.num_inner_cols;
+        let block_size = self.usable_rows(logrows) * num_inner_cols;
+        3 * num_inner_cols * VarTensor::num_blocks_for(assignments, block_size)
+    }
+
+    /// The advice columns [ModelVars] allocates for the model at `logrows`
+    pub fn advice_width(&self, logrows: u32) -> usize {
+        let mut width = self.advice_width_for(self.total_assignments, logrows);
+        if self.requires_dynamic_lookup() || self.requires_shuffle() {
+            let num_cols = if self.requires_dynamic_lookup() { 3 } else { 2 };
+            width += num_cols
+                * VarTensor::num_blocks_for(
+                    self.dynamic_lookup_and_shuffle_col_size(),
+                    self.usable_rows(logrows),
+                );
+        }
+        width
+    }
+
     /// save params to file
     pub fn save(&self, path: &std::path::PathBuf) -> Result<(), std::io::Error> {
         // buf writer
@@ -601,11 +1059,13 @@ impl GraphSettings {
         !self.module_sizes.max_constraints() > 0
     }
 
-    /// if any visibility is encrypted or hashed
+    /// if any visibility is encrypted or hashed, or a dataset row is hashed into its opening
     pub fn module_requires_fixed(&self) -> bool {
         self.run_args.input_visibility.is_hashed()
             || self.run_args.output_visibility.is_hashed()
             || self.run_args.param_visibility.is_hashed()
+            || self.run_args.param_visibility.is_merkle()
+            || self.run_args.dataset_depth.is_some()
     }
 
     /// requires dynamic lookup
@@ -632,6 +1092,27 @@ pub struct GraphConfig {
     model_config: ModelConfig,
     module_configs: ModuleConfigs,
     circuit_size: CircuitSize,
+    /// The settings configured with, including the blinding factors of the constraint system
+    settings: GraphSettings,
+    configure_time: std::time::Duration,
+}
+
+impl GraphConfig {
+    /// What halo2 reports about `cs` once this config has been configured on it.
+    pub fn circuit_stats(&self, cs: &ConstraintSystem<Fp>) -> CircuitStats {
+        CircuitStats {
+            num_advice_columns: cs.num_advice_columns(),
+            num_fixed_columns: cs.num_fixed_columns(),
+            num_instance_columns: cs.num_instance_columns(),
+            num_selectors: cs.num_selectors(),
+            num_gates: cs.gates().len(),
+            num_lookups: cs.lookups().len(),
+            num_shuffles: cs.shuffles().len(),
+            minimum_rows: cs.minimum_rows(),
+            degree: cs.degree(),
+            configure_time: self.configure_time,
+        }
+    }
 }
 
 /// Defines the circuit for a computational graph / model loaded from a `.onnx` file.
@@ -643,6 +1124,206 @@ pub struct CoreCircuit {
     pub settings: GraphSettings,
 }
 
+/// A summary of a circuit, embedded in its key and proof artifacts so verifiers can see what they're verifying.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct ArtifactMetadata {
+    /// The ezkl version that produced the artifact
+    pub version: String,
+    /// The ops in the model, in graph order
+    pub ops: Vec<String>,
+    /// The shapes of the model's inputs
+    pub input_shapes: Vec<Vec<usize>>,
+    /// The shapes of the model's outputs
+    pub output_shapes: Vec<Vec<usize>>,
+    /// The scales of the model's inputs
+    pub input_scales: Vec<crate::Scale>,
+    /// The scales of the model's outputs
+    pub output_scales: Vec<crate::Scale>,
+    /// The run args the circuit was compiled with, including visibilities
+    pub run_args: RunArgs,
+    /// How each of the model's outputs is laid out and encoded, empty in artifacts predating it
+    #[serde(default)]
+    pub outputs: Vec<OutputMetadata>,
+    /// The number of public instances a proof carries, 0 in artifacts predating it
+    #[serde(default)]
+    pub num_instances: usize,
+    /// The name of each of the model's constants and the visibility it was given, so verifiers
+    /// can tell which layers' params are fixed in the keys, committed to, or private advice.
+    /// Empty in artifacts predating it
+    #[serde(default)]
+    pub params: Vec<(String, Visibility)>,
+    /// How long `setup` took to generate the keys, only set in the keys' metadata
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keygen_time: Option<std::time::Duration>,
+}
+
+/// How one of a model's outputs is laid out and encoded, so each output can be decoded on its own
+/// terms, e.g. a regression head at a fine scale next to a classifier's integer labels.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct OutputMetadata {
+    /// The name of the node producing the output
+    pub name: String,
+    /// The dims of the output
+    pub dims: Vec<usize>,
+    /// The scale the output is quantized at
+    pub out_scale: crate::Scale,
+    /// Whether and how the output is made public
+    pub visibility: Visibility,
+}
+
+/// How to decode the public outputs of a proof
+#[derive(Copy, Clone, Default, Debug, PartialEq, Eq, PartialOrd, Serialize, Deserialize)]
+#[cfg_attr(
+    all(feature = "ezkl", not(target_arch = "wasm32")),
+    derive(clap::ValueEnum)
+)]
+pub enum OutputFormat {
+    /// The field elements, as they appear in the instances
+    #[default]
+    Field,
+    /// Mapped back to signed integers and rescaled to floats
+    Float,
+    /// The argmax over the last axis of each output, i.e. a classifier's predicted labels
+    Labels,
+}
+
+impl std::fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OutputFormat::Field => write!(f, "field"),
+            OutputFormat::Float => write!(f, "float"),
+            OutputFormat::Labels => write!(f, "labels"),
+        }
+    }
+}
+
+#[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
+impl ToFlags for OutputFormat {
+    fn to_flags(&self) -> Vec<String> {
+        vec![format!("{}", self)]
+    }
+}
+
+/// A public output of a proof, decoded as per an [OutputFormat]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum DecodedOutput {
+    /// The output's field elements as 0x strings
+    Field(Vec<String>),
+    /// The output as floats
+    Float(Vec<f64>),
+    /// The label of each row of the output
+    Labels(Vec<usize>),
+}
+
+/// A decoded public output, named after the node producing it
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct NamedOutput {
+    /// The name of the node producing the output
+    pub name: String,
+    /// The decoded values
+    #[serde(flatten)]
+    pub decoded: DecodedOutput,
+}
+
+impl ArtifactMetadata {
+    /// The layout of each output, falling back to the shared output shapes, scales and visibility
+    /// for artifacts that don't record it per output.
+    pub fn output_layout(&self) -> Vec<OutputMetadata> {
+        if !self.outputs.is_empty() {
+            return self.outputs.clone();
+        }
+        self.output_shapes
+            .iter()
+            .zip(&self.output_scales)
+            .enumerate()
+            .map(|(i, (dims, out_scale))| OutputMetadata {
+                name: format!("output_{}", i),
+                dims: dims.clone(),
+                out_scale: *out_scale,
+                visibility: self.run_args.output_visibility.clone(),
+            })
+            .collect()
+    }
+
+    /// Decodes the public outputs at the tail of a proof's (flattened) instances, using the
+    /// per output dims, scales and visibilities recorded in the metadata rather than the model.
+    /// `formats` holds either one format per output, or a single one they all share.
+    pub fn decode_outputs(
+        &self,
+        instances: &[Fp],
+        formats: &[OutputFormat],
+    ) -> Result<Vec<NamedOutput>, GraphError> {
+        let layout = self.output_layout();
+        let formats = match formats.len() {
+            1 => vec![formats[0]; layout.len()],
+            n if n == layout.len() => formats.to_vec(),
+            n => return Err(GraphError::OutputFormatCount(n, layout.len())),
+        };
+        if let Some(output) = layout.iter().find(|o| !o.visibility.is_public()) {
+            return Err(GraphError::NonPublicOutputs(format!(
+                "{} ({})",
+                output.visibility, output.name
+            )));
+        }
+        let lens = layout
+            .iter()
+            .map(|o| o.dims.iter().product::<usize>())
+            .collect::<Vec<_>>();
+        let total = lens.iter().sum::<usize>();
+        // the outputs are laid out last, bar any dataset opening and context hash, see
+        // [GraphCircuit::prepare_public_inputs]
+        let end = instances.len().saturating_sub(
+            self.run_args.bind_context as usize
+                + 2 * self.run_args.dataset_depth.is_some() as usize,
+        );
+        if end < total {
+            return Err(GraphError::MissingInstances);
+        }
+        let mut start = end - total;
+
+        Ok(layout
+            .into_iter()
+            .zip(lens)
+            .zip(formats)
+            .map(|((output, len), format)| {
+                start += len;
+                let values = &instances[start - len..start];
+                let decoded = match format {
+                    OutputFormat::Field => {
+                        DecodedOutput::Field(values.iter().map(|x| format!("{:?}", x)).collect())
+                    }
+                    OutputFormat::Float => DecodedOutput::Float(
+                        values
+                            .iter()
+                            .map(|x| dequantize(*x, output.out_scale, 0.))
+                            .collect(),
+                    ),
+                    OutputFormat::Labels => {
+                        let classes = output.dims.last().copied().unwrap_or(1).max(1);
+                        DecodedOutput::Labels(
+                            values
+                                .chunks(classes)
+                                .map(|row| {
+                                    // ties go to the lowest index
+                                    (0..row.len())
+                                        .rev()
+                                        .max_by_key(|i| felt_to_integer_rep(row[*i]))
+                                        .unwrap_or(0)
+                                })
+                                .collect(),
+                        )
+                    }
+                };
+                NamedOutput {
+                    name: output.name,
+                    decoded,
+                }
+            })
+            .collect())
+    }
+}
+
 /// Defines the circuit for a computational graph / model loaded from a `.onnx` file.
 #[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct GraphCircuit {
@@ -650,6 +1331,15 @@ pub struct GraphCircuit {
     pub core: CoreCircuit,
     /// The witness data for the model.
     pub graph_witness: GraphWitness,
+    /// Whether to record every node's outputs in the forward pass and check the laid out values against them.
+    #[serde(skip)]
+    pub check_witness: bool,
+    /// Stops the layout between nodes once it fires, see [Prover].
+    #[serde(skip)]
+    pub interrupt: Option<Interrupt>,
+    /// Inputs that had values clipped when they were last loaded, see [GraphCircuit::clip_rates].
+    #[serde(skip)]
+    pub input_clip_rates: Vec<ClipRate>,
 }
 
 impl GraphCircuit {
@@ -665,6 +1355,34 @@ impl GraphCircuit {
     pub fn model(&self) -> &Model {
         &self.core.model
     }
+    /// Summarizes the circuit for embedding in key and proof artifacts
+    pub fn artifact_metadata(&self) -> Result<ArtifactMetadata, GraphError> {
+        let graph = &self.model().graph;
+        Ok(ArtifactMetadata {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            ops: graph.nodes.values().map(|n| n.as_str()).collect(),
+            input_shapes: graph.input_shapes()?,
+            output_shapes: graph.output_shapes()?,
+            input_scales: self.settings().model_input_scales.clone(),
+            output_scales: self.settings().model_output_scales.clone(),
+            run_args: self.settings().run_args.clone(),
+            outputs: graph
+                .output_names()?
+                .into_iter()
+                .zip(graph.output_shapes()?)
+                .zip(&self.settings().model_output_scales)
+                .map(|((name, dims), out_scale)| OutputMetadata {
+                    name,
+                    dims,
+                    out_scale: *out_scale,
+                    visibility: self.settings().run_args.output_visibility.clone(),
+                })
+                .collect(),
+            num_instances: self.settings().num_instances(),
+            params: self.model().param_visibilities(),
+            keygen_time: None,
+        })
+    }
     ///
     pub fn save(&self, path: std::path::PathBuf) -> Result<(), GraphError> {
         let f = std::fs::File::create(&path).map_err(|e| {
@@ -746,7 +1464,11 @@ pub struct TestOnChainData {
 
 impl GraphCircuit {
     ///
-    pub fn new(model: Model, run_args: &RunArgs) -> Result<GraphCircuit, GraphError> {
+    pub fn new(mut model: Model, run_args: &RunArgs) -> Result<GraphCircuit, GraphError> {
+        model.quantize_constants()?;
+        if let Some(threshold) = run_args.sparse_weights_threshold {
+            model.sparsify_weights(threshold)?;
+        }
         // // placeholder dummy inputs - must call prepare_public_inputs to load data afterwards
         let mut inputs: Vec<Vec<Fp>> = vec![];
         for shape in model.graph.input_shapes()? {
@@ -763,19 +1485,56 @@ impl GraphCircuit {
                 num_params += shape.iter().product::<usize>();
             }
         }
+        // merkle params hash each tensor into its own leaf, other modules hash them all at once
+        let params_shapes = if run_args.param_visibility.is_merkle() {
+            model.const_shapes()
+        } else {
+            vec![vec![num_params]]
+        };
+
+        // modules take no instances for empty tensors, so hashes and commitments wouldn't line up
+        // with the model's inputs and outputs if some of them were empty
+        for (kind, visibility, shapes) in [
+            (
+                "input",
+                &run_args.input_visibility,
+                model.graph.input_shapes()?,
+            ),
+            (
+                "output",
+                &run_args.output_visibility,
+                model.graph.output_shapes()?,
+            ),
+        ] {
+            if !(visibility.is_hashed() || visibility.is_polycommit()) {
+                continue;
+            }
+            if let Some(i) = shapes.iter().position(|s| s.iter().product::<usize>() == 0) {
+                return Err(GraphError::EmptyTensorVisibility(
+                    kind.to_string(),
+                    i,
+                    visibility.to_string(),
+                ));
+            }
+        }
 
-        let sizes = GraphModules::num_constraints_and_instances(
+        let mut sizes = GraphModules::num_constraints_and_instances(
             model.graph.input_shapes()?,
-            vec![vec![num_params]],
+            params_shapes,
             model.graph.output_shapes()?,
             VarVisibility::from_args(run_args)?,
         );
 
+        if let Some(depth) = run_args.dataset_depth {
+            GraphModules::num_dataset_constraints(model.graph.input_shapes()?, depth, &mut sizes);
+        }
+
         // number of instances used by modules
         settings.module_sizes = sizes.clone();
 
         // as they occupy independent rows
         settings.num_rows = std::cmp::max(settings.num_rows, sizes.max_constraints());
+        settings.check_instance_budget()?;
 
         let core = CoreCircuit {
             model,
@@ -785,15 +1544,22 @@ impl GraphCircuit {
         Ok(GraphCircuit {
             core,
             graph_witness: GraphWitness::new(inputs, vec![]),
+            check_witness: false,
+            interrupt: None,
+            input_clip_rates: vec![],
         })
     }
 
     ///
     pub fn new_from_settings(
-        model: Model,
+        mut model: Model,
         mut settings: GraphSettings,
         check_mode: CheckMode,
     ) -> Result<GraphCircuit, GraphError> {
+        model.quantize_constants()?;
+        if let Some(threshold) = settings.run_args.sparse_weights_threshold {
+            model.sparsify_weights(threshold)?;
+        }
         // placeholder dummy inputs - must call prepare_public_inputs to load data afterwards
         let mut inputs: Vec<Vec<Fp>> = vec![];
         for shape in model.graph.input_shapes()? {
@@ -813,6 +1579,9 @@ impl GraphCircuit {
         Ok(GraphCircuit {
             core,
             graph_witness: GraphWitness::new(inputs, vec![]),
+            check_witness: false,
+            interrupt: None,
+            input_clip_rates: vec![],
         })
     }
 
@@ -845,11 +1614,30 @@ impl GraphCircuit {
         }
 
         // if the outputs are public, we add them to the public inputs
-        if self.settings().run_args.output_visibility.is_public() {
+        let outputs_are_public = self.settings().run_args.output_visibility.is_public();
+        if outputs_are_public {
             public_inputs.extend(self.graph_witness.outputs.clone().into_iter().flatten());
+        }
+
+        // an opened dataset row's root and index follow the public outputs
+        if self.settings().run_args.dataset_depth.is_some() {
+            let opening = data
+                .dataset_opening
+                .as_ref()
+                .ok_or(GraphError::MissingDatasetOpening)?;
+            public_inputs.extend(opening.instances(&data.dataset_row()));
+        }
+
+        // the context hash is the model's last instance, after its public inputs and outputs
+        if self.settings().run_args.bind_context {
+            public_inputs.push(data.context_hash.ok_or(GraphError::MissingContext)?);
+        }
+
         // if the outputs are processed, we add the processed outputs to the public inputs
-        } else if let Some(processed_outputs) = &data.processed_outputs {
-            public_inputs.extend(processed_outputs.get_instances().into_iter().flatten());
+        if !outputs_are_public {
+            if let Some(processed_outputs) = &data.processed_outputs {
+                public_inputs.extend(processed_outputs.get_instances().into_iter().flatten());
+            }
         }
 
         if public_inputs.len() < 11 {
@@ -911,6 +1699,10 @@ impl GraphCircuit {
         let shapes = self.model().graph.input_shapes()?;
         let scales = self.model().graph.get_input_scales();
         let input_types = self.model().graph.get_input_types()?;
+        data.check_input_shapes(&shapes)?;
+        if let Some(int_data) = &data.input_data_int {
+            return self.load_int_data(int_data, data.input_scale, &shapes, &scales);
+        }
         self.process_data_source(&data.input_data, shapes, scales, input_types)
     }
 
@@ -924,6 +1716,10 @@ impl GraphCircuit {
         let input_types = self.model().graph.get_input_types()?;
         debug!("input scales: {:?}", scales);
 
+        data.check_input_shapes(&shapes)?;
+        if let Some(int_data) = &data.input_data_int {
+            return self.load_int_data(int_data, data.input_scale, &shapes, &scales);
+        }
         match &data.input_data {
             DataSource::File(file_data) => {
                 self.load_file_data(file_data, &shapes, scales, input_types)
@@ -943,6 +1739,10 @@ impl GraphCircuit {
         let input_types = self.model().graph.get_input_types()?;
         debug!("input scales: {:?}", scales);
 
+        data.check_input_shapes(&shapes)?;
+        if let Some(int_data) = &data.input_data_int {
+            return self.load_int_data(int_data, data.input_scale, &shapes, &scales);
+        }
         self.process_data_source(&data.input_data, shapes, scales, input_types)
             .await
     }
@@ -993,6 +1793,31 @@ impl GraphCircuit {
         }
     }
 
+    /// Loads the model's inputs by name from `source`, e.g. a directory of .npy files or tensors
+    /// already held in memory, and quantizes them as file data is.
+    pub fn load_graph_input_from(
+        &mut self,
+        source: &dyn InputSource,
+    ) -> Result<Vec<Tensor<Fp>>, GraphError> {
+        let shapes = self.model().graph.input_shapes()?;
+        let scales = self.model().graph.get_input_scales();
+        let input_types = self.model().graph.get_input_types()?;
+        let names = self.model().graph.input_names()?;
+
+        let mut file_data: FileSource = vec![];
+        for (i, (name, shape)) in names.iter().zip(&shapes).enumerate() {
+            let input = source.read_input(name)?;
+            check_input_shape(i, input.len(), Some(input.dims()), shape)?;
+            file_data.push(
+                input
+                    .iter()
+                    .map(|x| FileSourceInner::new_float(*x))
+                    .collect(),
+            );
+        }
+        self.load_file_data(&file_data, &shapes, scales, input_types)
+    }
+
     /// Prepare on chain test data
     #[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
     pub async fn load_on_chain_data(
@@ -1017,7 +1842,9 @@ impl GraphCircuit {
         Ok(inputs)
     }
 
-    ///
+    /// Quantizes float inputs at their scales. Values past the decomposition range at an input's
+    /// scale are clipped to it, and the inputs that had values clipped are kept for
+    /// [GraphCircuit::clip_rates].
     pub fn load_file_data(
         &mut self,
         file_data: &FileSource,
@@ -1025,28 +1852,113 @@ impl GraphCircuit {
         scales: Vec<crate::Scale>,
         input_types: Vec<InputType>,
     ) -> Result<Vec<Tensor<Fp>>, GraphError> {
+        let run_args = &self.settings().run_args;
+        let (range, threshold) = (
+            (run_args.decomp_base as f64).powi(run_args.decomp_legs as i32) / 2.0,
+            run_args.clip_warn_threshold,
+        );
+        let names = self.model().graph.input_names()?;
+        let mut input_clip_rates = vec![];
         // quantize the supplied data using the provided scale.
         let mut data: Vec<Tensor<Fp>> = vec![];
-        for (((d, shape), scale), input_type) in file_data
+        for (i, (((d, shape), scale), input_type)) in file_data
             .iter()
             .zip(shapes)
             .zip(scales)
             .zip(input_types.iter())
+            .enumerate()
         {
-            let t: Vec<Fp> = d
-                .par_iter()
+            check_input_shape(i, d.len(), None, shape)?;
+            let bound = range / scale_to_multiplier(scale);
+            let mut clipped = 0;
+            let d = d
+                .iter()
                 .map(|x| {
                     let mut x = x.clone();
                     x.as_type(input_type);
-                    x.to_field(scale)
+                    if let FileSourceInner::Float(f) = &mut x {
+                        if f.abs() > bound {
+                            clipped += 1;
+                            *f = f.clamp(-bound, bound);
+                        }
+                    }
+                    x
                 })
-                .collect();
+                .collect::<Vec<_>>();
+            if clipped > 0 {
+                input_clip_rates.push(ClipRate {
+                    node: names
+                        .get(i)
+                        .cloned()
+                        .unwrap_or_else(|| format!("input {}", i)),
+                    clipped,
+                    total: d.len(),
+                });
+            }
+            let t: Vec<Fp> = d.par_iter().map(|x| x.to_field(scale)).collect();
 
             let mut t: Tensor<Fp> = t.into_iter().into();
             t.reshape(shape)?;
 
             data.push(t);
         }
+        report_clipping(input_clip_rates.iter().filter(|c| c.rate() > threshold));
+        self.input_clip_rates = input_clip_rates;
+        Ok(data)
+    }
+
+    /// The constants that had values clipped when the model was parsed (see [Model::clip_rates])
+    /// and the inputs that had values clipped when they were last loaded, the worst first.
+    pub fn clip_rates(&self) -> Vec<ClipRate> {
+        let mut rates = self.model().clip_rates();
+        rates.extend(self.input_clip_rates.iter().cloned());
+        rates.sort_by(|a, b| b.rate().total_cmp(&a.rate()));
+        rates
+    }
+
+    /// The model's [Model::estimate_resources], counting the clipping of the inputs last loaded
+    /// along with the constants'.
+    pub fn estimate_resources(&self) -> Result<ResourceEstimate, GraphError> {
+        let mut estimate = self.model().estimate_resources(&self.settings().run_args)?;
+        estimate.clip_rates = self.clip_rates();
+        Ok(estimate)
+    }
+
+    /// Loads inputs that were already quantized to integers at `scale` as is, bypassing
+    /// quantization. Inputs at a lower scale than the model expects are rescaled exactly;
+    /// a higher scale would need rounding and is an error.
+    pub fn load_int_data(
+        &self,
+        int_data: &[Vec<i32>],
+        scale: Option<crate::Scale>,
+        shapes: &[Vec<usize>],
+        scales: &[crate::Scale],
+    ) -> Result<Vec<Tensor<Fp>>, GraphError> {
+        let scale = scale.ok_or_else(|| {
+            GraphError::MissingParams("input_scale for input_data_int".to_string())
+        })?;
+        if int_data.len() != shapes.len() {
+            return Err(GraphError::MisformedParams(format!(
+                "the model takes {} inputs, got {} integer inputs",
+                shapes.len(),
+                int_data.len()
+            )));
+        }
+        let mut data: Vec<Tensor<Fp>> = vec![];
+        for (i, ((d, shape), expected)) in int_data.iter().zip(shapes).zip(scales).enumerate() {
+            if scale > *expected {
+                return Err(GraphError::InputScaleMismatch(i, scale, *expected));
+            }
+            let multiplier = scale_to_multiplier(expected - scale) as IntegerRep;
+            let mut t: Tensor<Fp> = d
+                .iter()
+                .map(|x| integer_rep_to_felt(*x as IntegerRep * multiplier))
+                .collect_vec()
+                .into_iter()
+                .into();
+            t.reshape(shape)?;
+            data.push(t);
+        }
         Ok(data)
     }
 
@@ -1108,6 +2020,7 @@ impl GraphCircuit {
         let max_logrows = max_logrows.unwrap_or(MAX_PUBLIC_SRS);
         let max_logrows = std::cmp::min(max_logrows, MAX_PUBLIC_SRS);
         let mut max_logrows = std::cmp::max(max_logrows, MIN_LOGROWS);
+        let hard_max_logrows = max_logrows;
         let mut min_logrows = MIN_LOGROWS;
 
         let safe_lookup_range = Self::calc_safe_lookup_range(min_max_lookup, lookup_safety_margin);
@@ -1174,16 +2087,41 @@ impl GraphCircuit {
             return Err(GraphError::ExtendedKTooLarge(max_logrows));
         }
 
-        let logrows = max_logrows;
+        let mut logrows = max_logrows;
 
-        let model = self.model().clone();
-        let settings_mut = self.settings_mut();
-        settings_mut.run_args.lookup_range = safe_lookup_range;
-        settings_mut.run_args.logrows = logrows;
+        // with an advice column budget, trade rows for width: take the fewest rows that fit it
+        let max_advice_cols = self.settings().run_args.max_advice_cols;
+        let hard_max_logrows = std::cmp::max(hard_max_logrows, min_logrows);
+        if let Some(budget) = max_advice_cols {
+            logrows = (min_logrows..=hard_max_logrows)
+                .find(|k| {
+                    self.settings().advice_width(*k) <= budget
+                        && self.extended_k_is_small_enough(*k, safe_lookup_range, max_range_size)
+                })
+                .ok_or_else(|| self.advice_width_exceeded(budget, hard_max_logrows))?;
+        }
 
-        *settings_mut = GraphCircuit::new(model, &settings_mut.run_args)?
-            .settings()
-            .clone();
+        loop {
+            let model = self.model().clone();
+            let settings_mut = self.settings_mut();
+            settings_mut.run_args.lookup_range = safe_lookup_range;
+            settings_mut.run_args.logrows = logrows;
+
+            *settings_mut = GraphCircuit::new(model, &settings_mut.run_args)?
+                .settings()
+                .clone();
+
+            match max_advice_cols {
+                // the layout at the new logrows can duplicate a few more cells across columns
+                Some(budget) if self.settings().advice_width(logrows) > budget => {
+                    if logrows >= hard_max_logrows {
+                        return Err(self.advice_width_exceeded(budget, logrows));
+                    }
+                    logrows += 1;
+                }
+                _ => break,
+            }
+        }
 
         debug!(
             "setting lookup_range to: {:?}, setting logrows to: {}",
@@ -1194,38 +2132,287 @@ impl GraphCircuit {
         Ok(())
     }
 
-    fn extended_k_is_small_enough(
-        &self,
-        k: u32,
-        safe_lookup_range: Range,
-        max_range_size: IntegerRep,
-    ) -> bool {
-        // if num cols is too large then the extended k is too large
-        if Self::calc_num_cols(safe_lookup_range.1 - safe_lookup_range.0, k) > MAX_NUM_LOOKUP_COLS
-            || Self::calc_num_cols(max_range_size, k) > MAX_NUM_LOOKUP_COLS
-        {
-            return false;
+    /// Fails if the model's layout at its logrows takes more advice columns than `max_advice_cols`
+    pub fn check_advice_width(&self) -> Result<(), GraphError> {
+        let settings = self.settings();
+        match settings.run_args.max_advice_cols {
+            Some(budget) if settings.advice_width(settings.run_args.logrows) > budget => {
+                Err(self.advice_width_exceeded(budget, settings.run_args.logrows))
+            }
+            _ => Ok(()),
         }
+    }
 
-        let mut settings = self.settings().clone();
-        settings.run_args.lookup_range = safe_lookup_range;
-        settings.run_args.logrows = k;
-        settings.required_range_checks = vec![(0, max_range_size)];
-        let mut cs = ConstraintSystem::default();
-        // if unix get a gag
-        #[cfg(all(not(not(feature = "ezkl")), unix))]
-        let _r = match Gag::stdout() {
-            Ok(g) => Some(g),
-            _ => None,
-        };
-        #[cfg(all(not(not(feature = "ezkl")), unix))]
-        let _g = match Gag::stderr() {
-            Ok(g) => Some(g),
-            _ => None,
+    /// Reports the width the model needs at `logrows` and the nodes that take up the most of it
+    fn advice_width_exceeded(&self, budget: usize, logrows: u32) -> GraphError {
+        let settings = self.settings();
+        let mut nodes = match self.model().estimate_resources(&settings.run_args) {
+            Ok(estimate) => estimate.nodes,
+            Err(e) => return e,
+        };
+        nodes.sort_by_key(|(_, assignments)| std::cmp::Reverse(*assignments));
+        let widest = nodes
+            .iter()
+            .take(3)
+            .map(|(idx, assignments)| {
+                format!(
+                    "node {} ({}) needs {}",
+                    idx,
+                    self.model().graph.nodes[idx].as_str(),
+                    settings.advice_width_for(*assignments, logrows)
+                )
+            })
+            .join(", ");
+        GraphError::AdviceWidthExceeded(settings.advice_width(logrows), logrows, budget, widest)
+    }
+
+    /// Fits the lookup range, logrows, per-op lookup ranges and per-node output bounds to the
+    /// largest values seen across `witnesses`, widened by the lookup safety margin.
+    pub fn fit_to_witnesses(
+        &mut self,
+        witnesses: &[GraphWitness],
+        lookup_safety_margin: f64,
+        max_logrows: Option<u32>,
+    ) -> Result<(), GraphError> {
+        let min_lookup_range = witnesses
+            .iter()
+            .map(|x| x.min_lookup_inputs)
+            .min()
+            .unwrap_or(0);
+        let max_lookup_range = witnesses
+            .iter()
+            .map(|x| x.max_lookup_inputs)
+            .max()
+            .unwrap_or(0);
+        let max_range_size = witnesses
+            .iter()
+            .map(|x| x.max_range_size)
+            .max()
+            .unwrap_or(0);
+
+        let mut lookup_ranges: std::collections::BTreeMap<LookupOp, Range> = Default::default();
+        for (op, range) in witnesses.iter().flat_map(|x| x.lookup_ranges.iter()) {
+            let entry = lookup_ranges.entry(op.clone()).or_insert(*range);
+            *entry = (entry.0.min(range.0), entry.1.max(range.1));
+        }
+        let lookup_ranges = lookup_ranges.into_iter().collect::<Vec<_>>();
+
+        let mut node_output_max: std::collections::BTreeMap<usize, IntegerRep> = Default::default();
+        for (idx, max) in witnesses.iter().flat_map(|x| x.node_output_max.iter()) {
+            let entry = node_output_max.entry(*idx).or_insert(*max);
+            *entry = (*entry).max(*max);
+        }
+        let node_output_max = node_output_max.into_iter().collect::<Vec<_>>();
+
+        self.calc_min_logrows(
+            (min_lookup_range, max_lookup_range),
+            max_range_size,
+            max_logrows,
+            lookup_safety_margin,
+        )?;
+        self.set_lookup_ranges(&lookup_ranges, lookup_safety_margin);
+        self.set_output_max(&node_output_max, lookup_safety_margin);
+        Ok(())
+    }
+
+    /// Narrows the table of each lookup op to its calibrated input range (padded by the safety margin).
+    /// Tables never exceed the global `lookup_range`.
+    pub fn set_lookup_ranges(
+        &mut self,
+        lookup_ranges: &[(LookupOp, Range)],
+        lookup_safety_margin: f64,
+    ) {
+        let global_range = self.settings().run_args.lookup_range;
+        let lookup_ranges = lookup_ranges
+            .iter()
+            .map(|(op, range)| {
+                let safe_range = Self::calc_safe_lookup_range(*range, lookup_safety_margin);
+                (
+                    op.clone(),
+                    (
+                        safe_range.0.max(global_range.0),
+                        safe_range.1.min(global_range.1),
+                    ),
+                )
+            })
+            .collect();
+        self.settings_mut().lookup_ranges = lookup_ranges;
+    }
+
+    /// Sets each node's `output_max` to the largest output magnitude seen while calibrating,
+    /// widened by the lookup safety margin.
+    pub fn set_output_max(
+        &mut self,
+        node_output_max: &[(usize, IntegerRep)],
+        lookup_safety_margin: f64,
+    ) {
+        self.settings_mut().output_max = node_output_max
+            .iter()
+            .map(|(idx, max)| {
+                (
+                    *idx,
+                    (lookup_safety_margin * *max as f64).ceil() as IntegerRep,
+                )
+            })
+            .collect();
+    }
+
+    /// Checks each node's largest output in `witness` against the `output_max` it was calibrated
+    /// for, nodes without one aren't checked. Every node past its bound is passed to `on_exceeded`
+    /// if there is one, otherwise the first is returned as an error.
+    pub fn check_output_max(
+        &self,
+        witness: &GraphWitness,
+        mut on_exceeded: Option<&mut dyn FnMut(&OutputMaxExceeded)>,
+    ) -> Result<(), GraphError> {
+        let output_max: std::collections::HashMap<_, _> =
+            self.settings().output_max.iter().cloned().collect();
+        for (idx, observed) in &witness.node_output_max {
+            let max = match output_max.get(idx) {
+                Some(max) if observed > max => *max,
+                _ => continue,
+            };
+            let exceeded = OutputMaxExceeded {
+                node: *idx,
+                op: self
+                    .model()
+                    .graph
+                    .nodes
+                    .get(idx)
+                    .map(|n| n.as_str())
+                    .unwrap_or_default(),
+                observed: *observed,
+                output_max: max,
+            };
+            match on_exceeded.as_mut() {
+                Some(callback) => callback(&exceeded),
+                None => {
+                    return Err(GraphError::OutputMaxExceeded(
+                        exceeded.node,
+                        exceeded.op,
+                        exceeded.observed,
+                        exceeded.output_max,
+                    ))
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks `witness` against the calibrated output bounds, see [GraphCircuit::check_output_max]:
+    /// a warning for each node past its bound, or an error for the first if
+    /// [RunArgs::strict_output_max] is set.
+    pub fn enforce_output_max(&self, witness: &GraphWitness) -> Result<(), GraphError> {
+        if self.settings().run_args.strict_output_max {
+            return self.check_output_max(witness, None);
+        }
+        self.check_output_max(
+            witness,
+            Some(&mut |e: &OutputMaxExceeded| {
+                warn!(
+                    "node {} ({}) output {} exceeds its calibrated output_max {}",
+                    e.node, e.op, e.observed, e.output_max
+                )
+            }),
+        )
+    }
+
+    /// Mock proves the circuit with the advice values of `log` in place of those the layout
+    /// computes, returning the violated constraint at the earliest row, if any, along with the
+    /// nodes laid out on that row. Later violations are usually cells reading a bad one.
+    ///
+    /// The circuit needs the witness the log was recorded with loaded, for the cells the log
+    /// doesn't hold such as the instances.
+    #[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
+    pub fn replay_witness_log(
+        &self,
+        log: &WitnessLog,
+        public_inputs: Vec<Fp>,
+    ) -> Result<Option<ReplayFailure>, GraphError> {
+        use halo2_proofs::dev::{FailureLocation, MockProver, VerifyFailure};
+
+        witness_log::replay(log.clone());
+        let prover = MockProver::run(self.settings().run_args.logrows, self, vec![public_inputs]);
+        witness_log::finish();
+        let failures = match prover?.verify() {
+            Ok(()) => return Ok(None),
+            Err(failures) => failures,
+        };
+
+        let row_of = |failure: &VerifyFailure| match failure {
+            VerifyFailure::ConstraintNotSatisfied { location, .. }
+            | VerifyFailure::Lookup { location, .. }
+            | VerifyFailure::Permutation { location, .. } => match location {
+                FailureLocation::InRegion { offset, .. } => Some(*offset),
+                FailureLocation::OutsideRegion { .. } => None,
+            },
+            _ => None,
+        };
+        let first = failures
+            .iter()
+            .min_by_key(|f| row_of(f).unwrap_or(usize::MAX));
+        let failure = match first {
+            Some(failure) => failure,
+            None => return Ok(None),
+        };
+        let row = row_of(failure);
+        let nodes = row
+            .map(|row| log.nodes_at(row))
+            .unwrap_or_default()
+            .into_iter()
+            .map(|idx| {
+                let op = self.model().graph.nodes.get(&idx).map(|n| n.as_str());
+                (idx, op.unwrap_or_default())
+            })
+            .collect();
+        Ok(Some(ReplayFailure {
+            failure: failure.to_string(),
+            row,
+            nodes,
+        }))
+    }
+
+    /// Configures the circuit on a fresh constraint system and returns what halo2 reports about it.
+    pub fn circuit_stats(&self) -> CircuitStats {
+        let mut cs = ConstraintSystem::default();
+        let config = Self::configure_with_params(&mut cs, self.settings().clone());
+        #[cfg(feature = "mv-lookup")]
+        let cs = cs.chunk_lookups();
+        config.circuit_stats(&cs)
+    }
+
+    fn extended_k_is_small_enough(
+        &self,
+        k: u32,
+        safe_lookup_range: Range,
+        max_range_size: IntegerRep,
+    ) -> bool {
+        // if num cols is too large then the extended k is too large
+        if Self::calc_num_cols(safe_lookup_range.1 - safe_lookup_range.0, k) > MAX_NUM_LOOKUP_COLS
+            || Self::calc_num_cols(max_range_size, k) > MAX_NUM_LOOKUP_COLS
+        {
+            return false;
+        }
+
+        let mut settings = self.settings().clone();
+        settings.run_args.lookup_range = safe_lookup_range;
+        settings.run_args.logrows = k;
+        settings.required_range_checks = vec![(0, max_range_size)];
+        let mut cs = ConstraintSystem::default();
+        // if unix get a gag
+        #[cfg(all(not(not(feature = "ezkl")), unix))]
+        let _r = match Gag::stdout() {
+            Ok(g) => Some(g),
+            _ => None,
+        };
+        #[cfg(all(not(not(feature = "ezkl")), unix))]
+        let _g = match Gag::stderr() {
+            Ok(g) => Some(g),
+            _ => None,
         };
 
-        Self::configure_with_params(&mut cs, settings);
-
+        Self::configure_with_params(&mut cs, settings);
+
         // drop the gag
         #[cfg(all(not(not(feature = "ezkl")), unix))]
         drop(_r);
@@ -1291,8 +2478,15 @@ impl GraphCircuit {
         }
 
         if visibility.params.requires_processing() {
-            let params = self.model().get_all_params();
-            if !params.is_empty() {
+            let params = self.model().get_all_params()?;
+            if visibility.params.is_merkle() {
+                processed_params = Some(GraphModules::forward::<Scheme>(
+                    &params,
+                    &visibility.params,
+                    vk,
+                    srs,
+                )?);
+            } else if !params.is_empty() {
                 let flattened_params = Tensor::new(Some(&params), &[params.len()])?.combine()?;
                 processed_params = Some(GraphModules::forward::<Scheme>(
                     &[flattened_params],
@@ -1303,9 +2497,13 @@ impl GraphCircuit {
             }
         }
 
-        let mut model_results =
+        let mut model_results = if self.check_witness {
+            self.model()
+                .forward_with_trace(inputs, &self.settings().run_args, region_settings)?
+        } else {
             self.model()
-                .forward(inputs, &self.settings().run_args, region_settings)?;
+                .forward(inputs, &self.settings().run_args, region_settings)?
+        };
 
         if visibility.output.requires_processing() {
             let module_outlets = visibility.output.overwrites_inputs();
@@ -1350,6 +2548,12 @@ impl GraphCircuit {
             max_lookup_inputs: model_results.max_lookup_inputs,
             min_lookup_inputs: model_results.min_lookup_inputs,
             max_range_size: model_results.max_range_size,
+            lookup_ranges: model_results.lookup_ranges,
+            node_output_max: model_results.node_output_max,
+            trace: model_results.trace,
+            context_hash: None,
+            dataset_opening: None,
+            forward_time: None,
         };
 
         witness.generate_rescaled_elements(
@@ -1516,13 +2720,76 @@ impl CircuitSize {
     }
 }
 
+/// Halo2's counts for a configured circuit, the actual figures next to the estimates made from the
+/// model's shapes (see [Model::estimate_resources]).
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct CircuitStats {
+    /// advice columns, including those of the modules
+    pub num_advice_columns: usize,
+    /// fixed columns, not counting the selectors
+    pub num_fixed_columns: usize,
+    /// instance columns
+    pub num_instance_columns: usize,
+    /// selectors
+    pub num_selectors: usize,
+    /// custom gates
+    pub num_gates: usize,
+    /// lookup arguments
+    pub num_lookups: usize,
+    /// shuffle arguments
+    pub num_shuffles: usize,
+    /// the fewest rows the gates, lookups and blinding factors fit in
+    pub minimum_rows: usize,
+    /// the degree of the constraint system
+    pub degree: usize,
+    /// how long configuring the circuit took
+    pub configure_time: std::time::Duration,
+}
+
+impl std::fmt::Display for CircuitStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} advice, {} fixed and {} instance columns, {} selectors, {} gates, {} lookups, {} shuffles, degree {}, at least {} rows, configured in {:?}",
+            self.num_advice_columns,
+            self.num_fixed_columns,
+            self.num_instance_columns,
+            self.num_selectors,
+            self.num_gates,
+            self.num_lookups,
+            self.num_shuffles,
+            self.degree,
+            self.minimum_rows,
+            self.configure_time
+        )
+    }
+}
+
 impl Circuit<Fp> for GraphCircuit {
     type Config = GraphConfig;
     type FloorPlanner = ModulePlanner;
     type Params = GraphSettings;
 
     fn without_witnesses(&self) -> Self {
-        self.clone()
+        let run_args = &self.settings().run_args;
+        // fixed inputs and outputs are baked into the circuit so are needed at keygen
+        if run_args.input_visibility.is_fixed() || run_args.output_visibility.is_fixed() {
+            return self.clone();
+        }
+        // otherwise the keys should only depend on the model and its settings, so we zero out the data
+        let inputs = self
+            .graph_witness
+            .inputs
+            .iter()
+            .map(|i| vec![Fp::zero(); i.len()])
+            .collect();
+        GraphCircuit {
+            core: self.core.clone(),
+            graph_witness: GraphWitness::new(inputs, vec![]),
+            check_witness: false,
+            interrupt: None,
+            input_clip_rates: vec![],
+        }
     }
 
     fn params(&self) -> Self::Params {
@@ -1531,11 +2798,9 @@ impl Circuit<Fp> for GraphCircuit {
     }
 
     fn configure_with_params(cs: &mut ConstraintSystem<Fp>, params: Self::Params) -> Self::Config {
+        let start_time = instant::Instant::now();
         let mut params = params.clone();
         params.set_num_blinding_factors(cs.blinding_factors());
-        GLOBAL_SETTINGS.with(|settings| {
-            *settings.borrow_mut() = Some(params.clone());
-        });
         let visibility = match VarVisibility::from_args(&params.run_args) {
             Ok(v) => v,
             Err(e) => {
@@ -1584,11 +2849,16 @@ impl Circuit<Fp> for GraphCircuit {
                 .unwrap()
         );
 
-        GraphConfig {
+        let config = GraphConfig {
             model_config,
             module_configs,
             circuit_size,
-        }
+            settings: params,
+            configure_time: start_time.elapsed(),
+        };
+        debug!("circuit stats: {}", config.circuit_stats(cs));
+
+        config
     }
 
     fn configure(_: &mut ConstraintSystem<Fp>) -> Self::Config {
@@ -1598,8 +2868,21 @@ impl Circuit<Fp> for GraphCircuit {
     fn synthesize(
         &self,
         config: Self::Config,
-        mut layouter: impl Layouter<Fp>,
+        layouter: impl Layouter<Fp>,
     ) -> Result<(), PlonkError> {
+        self.layout_graph(config, layouter)?;
+        Ok(())
+    }
+}
+
+impl GraphCircuit {
+    /// Lays out the circuit, returning the model's assigned inputs and the (post-processing)
+    /// outputs, so that circuits embedding this one can add constraints on them.
+    pub fn layout_graph(
+        &self,
+        config: GraphConfig,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<ModelLayout, PlonkError> {
         // check if the circuit area is less than the max
         if !config.circuit_size.area_less_than_max() {
             error!(
@@ -1610,6 +2893,11 @@ impl Circuit<Fp> for GraphCircuit {
             return Err(PlonkError::Synthesis);
         }
 
+        // the floor planner places constants by the settings of the circuit being laid out
+        GLOBAL_SETTINGS.with(|settings| {
+            *settings.borrow_mut() = Some(config.settings.clone());
+        });
+
         trace!("Setting input in synthesize");
         let input_vis = &self.settings().run_args.input_visibility;
         let output_vis = &self.settings().run_args.output_visibility;
@@ -1679,14 +2967,53 @@ impl Circuit<Fp> for GraphCircuit {
             )?;
         }
 
+        // the model runs on the hashed dataset row, whose path it constrains to the root
+        let dataset = match self.settings().run_args.dataset_depth {
+            Some(depth) => Some(graph_modules.layout_dataset(
+                &mut layouter,
+                &mut config.module_configs,
+                &mut inputs,
+                self.graph_witness.dataset_opening.as_ref(),
+                depth,
+                &mut constants,
+            )?),
+            None => None,
+        };
+
         // now we need to assign the flattened params to the model
         let mut model = self.model().clone();
         let param_visibility = &self.settings().run_args.param_visibility;
         trace!("running params module layout");
-        if !self.model().get_all_params().is_empty() && param_visibility.requires_processing() {
-            // now we need to flatten the params
-            let consts = self.model().get_all_params();
+        let consts = self.model().get_all_params().map_err(|e| {
+            log::error!("failed to quantize params: {}", e);
+            PlonkError::Synthesis
+        })?;
+        if !consts.is_empty() && param_visibility.is_merkle() {
+            // each tensor is its own leaf, so the params are hashed without flattening them
+            let mut params = consts
+                .into_iter()
+                .map(|mut t| {
+                    t.set_visibility(param_visibility);
+                    t.try_into().map_err(|_| {
+                        log::error!("failed to convert params to valtensor");
+                        PlonkError::Synthesis
+                    })
+                })
+                .collect::<Result<Vec<ValTensor<Fp>>, PlonkError>>()?;
+
+            graph_modules.layout(
+                &mut layouter,
+                &mut config.module_configs,
+                &mut params,
+                param_visibility,
+                &mut instance_offset,
+                &mut constants,
+            )?;
 
+            trace!("replacing processed consts");
+            model.replace_consts(&params);
+        } else if !consts.is_empty() && param_visibility.requires_processing() {
+            // now we need to flatten the params
             let mut flattened_params = {
                 let mut t = Tensor::new(Some(&consts), &[consts.len()])
                     .map_err(|_| {
@@ -1733,7 +3060,23 @@ impl Circuit<Fp> for GraphCircuit {
         let mut vars = config.model_config.vars.clone();
         vars.set_initial_instance_offset(instance_offset);
 
-        let mut outputs = model
+        // keys don't depend on the context, so it is zero when laying out without witnesses
+        let context = if self.settings().run_args.bind_context {
+            let mut context =
+                Tensor::from([self.graph_witness.context_hash.unwrap_or(Fp::zero())].into_iter());
+            context.set_visibility(&Visibility::Private);
+            Some(ValTensor::try_from(context).map_err(|e| {
+                log::error!("failed to convert context hash to valtensor: {:?}", e);
+                PlonkError::Synthesis
+            })?)
+        } else {
+            None
+        };
+
+        let ModelLayout {
+            inputs: model_inputs,
+            mut outputs,
+        } = model
             .layout(
                 config.model_config.clone(),
                 &mut layouter,
@@ -1741,7 +3084,11 @@ impl Circuit<Fp> for GraphCircuit {
                 &inputs,
                 &mut vars,
                 &outputs,
+                dataset.as_ref(),
+                context.as_ref(),
                 &mut constants,
+                self.graph_witness.trace.as_ref(),
+                self.interrupt.as_ref(),
             )
             .map_err(|e| {
                 log::error!("{}", e);
@@ -1784,6 +3131,2163 @@ impl Circuit<Fp> for GraphCircuit {
             )?;
         }
 
-        Ok(())
+        Ok(ModelLayout {
+            inputs: model_inputs,
+            outputs,
+        })
+    }
+}
+
+#[cfg(all(test, feature = "ezkl", not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+    use crate::circuit::AvgPoolMode;
+    use crate::pfsys::TranscriptType;
+    use crate::pfsys::{create_keys, create_proof_circuit, srs::gen_srs, verify_proof_circuit};
+    use crate::Commitments;
+    use halo2_proofs::poly::commitment::ParamsProver;
+    use halo2_proofs::poly::kzg::{
+        commitment::{KZGCommitmentScheme, ParamsKZG},
+        multiopen::{ProverSHPLONK, VerifierSHPLONK},
+        strategy::SingleStrategy,
+    };
+    use snark_verifier::system::halo2::transcript::evm::EvmTranscript;
+
+    fn witness(circuit: &GraphCircuit, input: &[f64]) -> GraphWitness {
+        let scale = circuit.settings().run_args.input_scale;
+        let input = input
+            .iter()
+            .map(|x| crate::fieldutils::integer_rep_to_felt(quantize_float(x, 0.0, scale).unwrap()))
+            .collect_vec();
+        let run_args = &circuit.settings().run_args;
+        circuit
+            .forward::<KZGCommitmentScheme<bn256::Bn256>>(
+                &mut [Tensor::new(Some(&input), &[1, input.len()]).unwrap()],
+                None,
+                None,
+                RegionSettings::all_true(run_args.decomp_base, run_args.decomp_legs),
+            )
+            .unwrap()
+    }
+
+    /// The witness of `circuit` on the inputs of `data`.
+    pub(super) fn forward(circuit: &mut GraphCircuit, data: &GraphData) -> GraphWitness {
+        let mut inputs = circuit.load_graph_from_file_exclusively(data).unwrap();
+        let run_args = &circuit.settings().run_args;
+        circuit
+            .forward::<KZGCommitmentScheme<bn256::Bn256>>(
+                &mut inputs,
+                None,
+                None,
+                RegionSettings::all_true(run_args.decomp_base, run_args.decomp_legs),
+            )
+            .unwrap()
+    }
+
+    /// Sizes `circuit` to fit `witness`, loads it and returns the public inputs it proves against.
+    pub(super) fn load_witness(circuit: &mut GraphCircuit, witness: &GraphWitness) -> Vec<Fp> {
+        circuit
+            .calc_min_logrows(
+                (witness.min_lookup_inputs, witness.max_lookup_inputs),
+                witness.max_range_size,
+                None,
+                2.0,
+            )
+            .unwrap();
+        circuit.load_graph_witness(witness).unwrap();
+        circuit.prepare_public_inputs(witness).unwrap()
+    }
+
+    /// Mock proves `circuit` on `witness`, see [load_witness].
+    pub(super) fn mock_prove(circuit: &mut GraphCircuit, witness: &GraphWitness) {
+        let public_inputs = load_witness(circuit, witness);
+        halo2_proofs::dev::MockProver::run(
+            circuit.settings().run_args.logrows,
+            &*circuit,
+            vec![public_inputs],
+        )
+        .unwrap()
+        .assert_satisfied();
+    }
+
+    /// An SRS and proving key for `circuit` at its current logrows.
+    pub(super) fn setup(
+        circuit: &GraphCircuit,
+    ) -> (
+        ParamsKZG<bn256::Bn256>,
+        halo2_proofs::plonk::ProvingKey<G1Affine>,
+    ) {
+        let params = gen_srs::<KZGCommitmentScheme<_>>(circuit.settings().run_args.logrows);
+        let pk =
+            create_keys::<KZGCommitmentScheme<bn256::Bn256>, GraphCircuit>(circuit, &params, false)
+                .unwrap();
+        (params, pk)
+    }
+
+    /// Proves `circuit` against `public_inputs` with keys from [setup].
+    pub(super) fn prove(
+        circuit: &GraphCircuit,
+        public_inputs: Vec<Fp>,
+        params: &ParamsKZG<bn256::Bn256>,
+        pk: &halo2_proofs::plonk::ProvingKey<G1Affine>,
+    ) -> crate::pfsys::Snark<Fp, G1Affine> {
+        create_proof_circuit::<
+            KZGCommitmentScheme<_>,
+            _,
+            ProverSHPLONK<_>,
+            VerifierSHPLONK<_>,
+            SingleStrategy<_>,
+            _,
+            EvmTranscript<_, _, _, _>,
+            EvmTranscript<_, _, _, _>,
+        >(
+            circuit.clone(),
+            vec![public_inputs],
+            params,
+            pk,
+            CheckMode::UNSAFE,
+            Commitments::KZG,
+            TranscriptType::EVM,
+            None,
+            None,
+        )
+        .unwrap()
+    }
+
+    /// Verifies a proof made by [prove].
+    pub(super) fn verify(
+        snark: &crate::pfsys::Snark<Fp, G1Affine>,
+        params: &ParamsKZG<bn256::Bn256>,
+        vk: &halo2_proofs::plonk::VerifyingKey<G1Affine>,
+    ) -> Result<(), halo2_proofs::plonk::Error> {
+        let verifier_params = params.verifier_params();
+        verify_proof_circuit::<
+            VerifierSHPLONK<_>,
+            KZGCommitmentScheme<_>,
+            SingleStrategy<_>,
+            _,
+            EvmTranscript<_, _, _, _>,
+        >(
+            snark,
+            verifier_params,
+            vk,
+            SingleStrategy::new(verifier_params),
+            verifier_params.n(),
+        )
+    }
+
+    /// The error allowed against tract's float evaluation, relative to references of magnitude
+    /// at least 1: a quantization step at the default scale (1 / 128) for each of the few layers
+    /// the fixtures have, with room for the rounding of their lookups.
+    const TRACT_RTOL: f32 = 0.05;
+
+    /// Checks the dequantized outputs for an example fixture agree with tract's float evaluation,
+    /// see [assert_dir_matches_tract].
+    fn assert_matches_tract(fixture: &str, rtol: f32) {
+        assert_dir_matches_tract(&std::path::Path::new("examples/onnx").join(fixture), rtol);
+    }
+
+    /// Checks the dequantized outputs for the fixture in `dir` agree with tract's float
+    /// evaluation, to within `rtol` of each reference value (or of 1 for references smaller
+    /// than 1).
+    pub(super) fn assert_dir_matches_tract(dir: &std::path::Path, rtol: f32) {
+        let fixture = dir.display();
+        let model_path = dir.join("network.onnx");
+        let data = GraphData::from_path(dir.join("input.json")).unwrap();
+        let run_args = RunArgs::default();
+        let mut circuit = GraphCircuit::from_run_args(&run_args, &model_path).unwrap();
+
+        let witness = forward(&mut circuit, &data);
+        let scales = circuit.model().graph.get_output_scales().unwrap();
+        let outputs = witness.get_float_outputs(&scales);
+
+        let input_shapes = circuit.model().graph.input_shapes().unwrap();
+        let expected =
+            Model::run_onnx_predictions(&run_args, &model_path, &[data], input_shapes).unwrap();
+
+        assert_eq!(outputs.len(), expected[0].len(), "{}", fixture);
+        for (output, expected) in outputs.iter().zip(expected[0].iter()) {
+            assert_eq!(output.len(), expected.len(), "{}", fixture);
+            for (o, e) in output.iter().zip(expected.iter()) {
+                assert!(
+                    (o - e).abs() <= rtol * e.abs().max(1.0),
+                    "{}: {} vs {}",
+                    fixture,
+                    o,
+                    e
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn traced_nodes_track_float_execution() {
+        let dir = std::path::Path::new("examples/onnx/1l_mlp");
+        let model_path = dir.join("network.onnx");
+        let data = GraphData::from_path(dir.join("input.json")).unwrap();
+        let run_args = RunArgs::default();
+        let mut circuit = GraphCircuit::from_run_args(&run_args, &model_path).unwrap();
+
+        let inputs = circuit.load_graph_from_file_exclusively(&data).unwrap();
+        let res = circuit
+            .model()
+            .forward_with_trace(
+                &inputs,
+                &run_args,
+                RegionSettings::all_true(run_args.decomp_base, run_args.decomp_legs),
+            )
+            .unwrap();
+        let mut trace = res.trace.unwrap();
+        let (out_idx, _) = circuit.model().graph.outputs[0];
+        let (out_name, out) = trace.nodes.iter().find(|(_, n)| n.idx == out_idx).unwrap();
+        assert_eq!(out.outputs[0].len(), res.outputs[0].len());
+        let out_name = out_name.clone();
+
+        let input_shapes = circuit.model().graph.input_shapes().unwrap();
+        let float = Model::run_onnx_trace(&run_args, &model_path, &data, input_shapes).unwrap();
+        trace.compare(&float);
+        assert!(trace.nodes[&out_name].max_divergence.is_some());
+        for (name, node) in &trace.nodes {
+            if let Some(divergence) = node.max_divergence {
+                assert!(divergence < 0.1, "{} diverges by {}", name, divergence);
+            }
+        }
+
+        let dir = tempfile::Builder::new()
+            .prefix("forward_trace")
+            .tempdir()
+            .unwrap();
+        let path = dir.path().join("trace.json");
+        trace.save(&path).unwrap();
+        assert_eq!(ForwardTrace::load(&path).unwrap(), trace);
+    }
+
+    /// Mock proves 1l_mlp with its witness checked against a forward pass trace, modified by `tamper`.
+    fn mock_with_checked_witness(tamper: impl Fn(&mut ForwardTrace)) {
+        let dir = std::path::Path::new("examples/onnx/1l_mlp");
+        let data = GraphData::from_path(dir.join("input.json")).unwrap();
+        let run_args = RunArgs::default();
+        let mut circuit =
+            GraphCircuit::from_run_args(&run_args, &dir.join("network.onnx")).unwrap();
+        circuit.check_witness = true;
+
+        let mut witness = forward(&mut circuit, &data);
+        tamper(witness.trace.as_mut().unwrap());
+
+        mock_prove(&mut circuit, &witness);
+    }
+
+    #[test]
+    fn laid_out_witness_matches_forward_pass() {
+        mock_with_checked_witness(|trace| assert!(!trace.nodes.is_empty()));
+    }
+
+    #[test]
+    #[should_panic(expected = "differ from its forward pass")]
+    fn laid_out_witness_mismatch_is_reported() {
+        mock_with_checked_witness(|trace| {
+            let node = trace.nodes.values_mut().max_by_key(|n| n.idx).unwrap();
+            node.quantized[0][0] += 1;
+        });
+    }
+
+    #[test]
+    fn replaying_a_corrupted_witness_log_finds_its_node() {
+        let dir = std::path::Path::new("examples/onnx/1l_mlp");
+        let data = GraphData::from_path(dir.join("input.json")).unwrap();
+        let run_args = RunArgs::default();
+        let mut circuit =
+            GraphCircuit::from_run_args(&run_args, &dir.join("network.onnx")).unwrap();
+        let witness = forward(&mut circuit, &data);
+        let public_inputs = load_witness(&mut circuit, &witness);
+
+        witness_log::record();
+        halo2_proofs::dev::MockProver::run(
+            circuit.settings().run_args.logrows,
+            &circuit,
+            vec![public_inputs.clone()],
+        )
+        .unwrap()
+        .assert_satisfied();
+        let log = witness_log::finish().unwrap();
+        assert!(!witness_log::is_active());
+        assert_eq!(log.regions, vec!["model".to_string()]);
+
+        // the log survives a round trip to disk and replays as it was proven
+        let dir = tempfile::Builder::new()
+            .prefix("witness_log")
+            .tempdir()
+            .unwrap();
+        let path = dir.path().join("witness.log");
+        log.save(&path).unwrap();
+        let mut log = WitnessLog::load(&path).unwrap();
+        assert_eq!(
+            circuit
+                .replay_witness_log(&log, public_inputs.clone())
+                .unwrap(),
+            None
+        );
+
+        // corrupt a cell in the middle of those of the node that assigned the most
+        let node = log
+            .cells
+            .iter()
+            .filter_map(|c| c.node)
+            .counts()
+            .into_iter()
+            .max_by_key(|(_, count)| *count)
+            .unwrap()
+            .0;
+        let cells = log
+            .cells
+            .iter()
+            .positions(|c| c.node == Some(node))
+            .collect_vec();
+        let cell = &mut log.cells[cells[cells.len() / 2]];
+        cell.set_value(cell.value::<Fp>().unwrap() + Fp::from(1));
+        let row = cell.row;
+
+        let failure = circuit
+            .replay_witness_log(&log, public_inputs)
+            .unwrap()
+            .unwrap();
+        assert!(failure.row.unwrap() <= row, "{}", failure);
+        assert!(failure.nodes.iter().any(|(n, _)| *n == node), "{}", failure);
+        assert!(failure.to_string().contains(&format!("node {} (", node)));
+    }
+
+    #[test]
+    fn inputs_shape_to_the_model_or_name_both_shapes() {
+        let dir = std::path::Path::new("examples/onnx/3l_relu_conv_fc");
+        let floats = match GraphData::from_path(dir.join("input.json"))
+            .unwrap()
+            .input_data
+        {
+            DataSource::File(data) => data[0].iter().map(|x| x.to_float()).collect_vec(),
+            _ => unreachable!(),
+        };
+        let mut circuit =
+            GraphCircuit::from_run_args(&RunArgs::default(), &dir.join("network.onnx")).unwrap();
+        let mut load = |input: serde_json::Value| {
+            let data: GraphData =
+                serde_json::from_value(serde_json::json!({ "input_data": [input] })).unwrap();
+            circuit.load_graph_from_file_exclusively(&data)
+        };
+        let rows = floats.chunks(28).map(|row| row.to_vec()).collect_vec();
+
+        // the model's source is [1, 1, 28, 28]: a flat list reshapes to it, as does a nested
+        // array with or without the unit batch and channel dims
+        let expected = load(serde_json::json!(floats)).unwrap();
+        assert_eq!(expected[0].dims(), &[1, 1, 28, 28]);
+        for nested in [
+            serde_json::json!([[rows.clone()]]),
+            serde_json::json!([rows.clone()]),
+            serde_json::json!(rows.clone()),
+        ] {
+            assert_eq!(load(nested).unwrap(), expected);
+        }
+
+        // a short flat list or a nested array of the wrong shape errors with both shapes
+        match load(serde_json::json!(floats[1..])) {
+            Err(GraphError::InputShapeMismatch(0, given, shape)) => {
+                assert_eq!(given, vec![783]);
+                assert_eq!(shape, vec![1, 1, 28, 28]);
+            }
+            other => panic!("expected an input shape mismatch, got {:?}", other),
+        }
+        let transposed = floats.chunks(14).map(|row| row.to_vec()).collect_vec();
+        match load(serde_json::json!([transposed])) {
+            Err(GraphError::InputShapeMismatch(0, given, _)) => assert_eq!(given, vec![1, 56, 14]),
+            other => panic!("expected an input shape mismatch, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn int_inputs_match_prequantized_floats() {
+        let dir = std::path::Path::new("examples/onnx/1l_mlp");
+        let floats = match GraphData::from_path(dir.join("input.json"))
+            .unwrap()
+            .input_data
+        {
+            DataSource::File(data) => data
+                .iter()
+                .map(|d| d.iter().map(|x| x.to_float()).collect_vec())
+                .collect_vec(),
+            _ => unreachable!(),
+        };
+        let run_args = RunArgs::default();
+        let mut circuit =
+            GraphCircuit::from_run_args(&run_args, &dir.join("network.onnx")).unwrap();
+        // at the model's scale the ints are exactly what quantizing the floats gives; below it
+        // they are rescaled, matching floats already rounded to that coarser grid
+        for scale in [run_args.input_scale, run_args.input_scale - 2] {
+            let multiplier = scale_to_multiplier(scale);
+            let ints = floats
+                .iter()
+                .map(|d| {
+                    d.iter()
+                        .map(|x| (x * multiplier).round() as i32)
+                        .collect_vec()
+                })
+                .collect_vec();
+            let rounded = ints
+                .iter()
+                .map(|d| {
+                    d.iter()
+                        .map(|x| input::FileSourceInner::new_float(*x as f64 / multiplier))
+                        .collect_vec()
+                })
+                .collect_vec();
+
+            let from_ints = forward(&mut circuit, &GraphData::from_int(ints.clone(), scale));
+            let from_floats = forward(&mut circuit, &GraphData::new(DataSource::File(rounded)));
+            assert_eq!(from_ints.inputs, from_floats.inputs);
+            assert_eq!(from_ints.outputs, from_floats.outputs);
+
+            if scale == run_args.input_scale {
+                let finer = GraphData::from_int(ints, scale + 1);
+                assert!(matches!(
+                    circuit.load_graph_from_file_exclusively(&finer),
+                    Err(GraphError::InputScaleMismatch(0, _, _))
+                ));
+
+                mock_prove(&mut circuit, &from_ints);
+            }
+        }
+    }
+
+    #[test]
+    fn fixtures_match_tract() {
+        for fixture in [
+            "1l_mlp",
+            "1l_relu",
+            "1l_conv",
+            "1l_crop_conv",
+            "1l_average",
+            "1l_max_pool",
+            "1l_reshape",
+            "1l_flatten",
+            "1l_pad",
+            "1l_concat",
+            "1l_slice",
+            "1l_sigmoid",
+            "scalar_affine",
+        ] {
+            assert_matches_tract(fixture, TRACT_RTOL);
+        }
+        for fixture in [
+            crate::dev_fixtures::channels_last_conv(2, 6),
+            crate::dev_fixtures::hwio_conv((2, 3), 6),
+            crate::dev_fixtures::unit_dim_kernel_conv((2, 4), 2, 6),
+        ] {
+            assert_dir_matches_tract(&fixture.write().unwrap(), TRACT_RTOL);
+        }
+    }
+
+    #[test]
+    fn transposes_around_a_conv_make_it_channels_last() {
+        let dir = crate::dev_fixtures::channels_last_conv(2, 6)
+            .write()
+            .unwrap();
+        let circuit =
+            GraphCircuit::from_run_args(&RunArgs::default(), &dir.join("network.onnx")).unwrap();
+        let nodes = circuit.model().graph.nodes.values().collect::<Vec<_>>();
+        // tract swaps the conv's data format rather than keeping the Transposes around it
+        assert!(
+            nodes
+                .iter()
+                .any(|n| n.opkind().channels_last() == Some(true)),
+            "{:?}",
+            nodes.iter().map(|n| n.as_str()).collect::<Vec<_>>()
+        );
+        assert!(nodes.iter().all(|n| !n.as_str().contains("MOVEAXIS")));
+    }
+
+    #[test]
+    fn fixtures_match_int_reference() {
+        for fixture in [
+            "1l_mlp",
+            "1l_relu",
+            "1l_conv",
+            "1l_crop_conv",
+            "1l_average",
+            "1l_reshape",
+            "1l_flatten",
+            "1l_pad",
+            "1l_qlinear",
+            "qlinear_mobilenet_block",
+            "1l_concat",
+            "1l_slice",
+            "1l_sigmoid",
+            "2l_relu_sigmoid_conv",
+            "scalar_affine",
+        ] {
+            let dir = std::path::Path::new("examples/onnx").join(fixture);
+            let data = GraphData::from_path(dir.join("input.json")).unwrap();
+            let run_args = RunArgs::default();
+            let mut circuit =
+                GraphCircuit::from_run_args(&run_args, &dir.join("network.onnx")).unwrap();
+            let inputs = circuit.load_graph_from_file_exclusively(&data).unwrap();
+            circuit
+                .model()
+                .check_int_reference(
+                    &run_args,
+                    &inputs,
+                    RegionSettings::all_true(run_args.decomp_base, run_args.decomp_legs),
+                )
+                .unwrap_or_else(|e| panic!("{}: {}", fixture, e));
+        }
+    }
+
+    #[test]
+    fn conv_transpose_output_shape() {
+        let circuit = GraphCircuit::from_run_args(
+            &RunArgs::default(),
+            std::path::Path::new("examples/onnx/1l_conv_transpose_output_shape/network.onnx"),
+        )
+        .unwrap();
+        // without the explicit output_shape a 5x5 input at stride 2 and a 5x5 kernel gives 13x13
+        assert_eq!(
+            circuit.model().graph.output_shapes().unwrap(),
+            vec![vec![1, 3, 10, 10]]
+        );
+        // the 3 rows of implicit padding are split with the extra row at the start
+        let deconv = circuit
+            .model()
+            .graph
+            .nodes
+            .values()
+            .map(|n| n.as_str())
+            .find(|op| op.starts_with("DECONV"))
+            .unwrap();
+        assert!(deconv.contains("padding=[(2, 1), (2, 1)]"), "{}", deconv);
+        assert_matches_tract("1l_conv_transpose_output_shape", TRACT_RTOL);
+    }
+
+    #[test]
+    fn dummy_layout_sizes_advices() {
+        for fixture in ["1l_conv", "1l_mlp"] {
+            let dir = std::path::Path::new("examples/onnx").join(fixture);
+            let data = GraphData::from_path(dir.join("input.json")).unwrap();
+            let run_args = RunArgs::default();
+            let mut circuit =
+                GraphCircuit::from_run_args(&run_args, &dir.join("network.onnx")).unwrap();
+
+            let witness = forward(&mut circuit, &data);
+            // the real layout fits in the advices
+            mock_prove(&mut circuit, &witness);
+
+            // which are sized from the assignments counted by the dummy layout
+            let settings = circuit.settings().clone();
+            let mut cs = ConstraintSystem::<Fp>::default();
+            let config = GraphCircuit::configure_with_params(&mut cs, settings.clone());
+            for advice in config.model_config.vars.advices.iter().take(3) {
+                let block_size = advice.col_size() * advice.num_inner_cols();
+                assert_eq!(
+                    advice.num_blocks(),
+                    crate::tensor::VarTensor::num_blocks_for(
+                        settings.total_assignments,
+                        block_size
+                    ),
+                    "{}",
+                    fixture
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn configure_is_idempotent_and_side_effect_free() {
+        let dir = std::path::Path::new("examples/onnx/1l_mlp");
+        let circuit =
+            GraphCircuit::from_run_args(&RunArgs::default(), &dir.join("network.onnx")).unwrap();
+        let configure = || {
+            let mut cs = ConstraintSystem::<Fp>::default();
+            let config = GraphCircuit::configure_with_params(&mut cs, circuit.settings().clone());
+            let stats = CircuitStats {
+                configure_time: Default::default(),
+                ..config.circuit_stats(&cs)
+            };
+            (
+                format!("{:?}", config.model_config),
+                format!("{:?}", config.module_configs),
+                stats,
+            )
+        };
+
+        GLOBAL_SETTINGS.with(|settings| *settings.borrow_mut() = None);
+        let first = configure();
+        assert_eq!(configure(), first);
+        // the settings the layout needs travel in the config, nothing is left behind for the
+        // layout of another circuit to pick up
+        assert!(GLOBAL_SETTINGS.with(|settings| settings.borrow().is_none()));
+    }
+
+    #[test]
+    fn conv_im2col_matches_direct() {
+        let dir = std::path::Path::new("examples/onnx/1l_conv_3x3");
+        let data = GraphData::from_path(dir.join("input.json")).unwrap();
+        let layout = |conv_strategy| {
+            let run_args = RunArgs {
+                conv_strategy,
+                ..RunArgs::default()
+            };
+            let mut circuit =
+                GraphCircuit::from_run_args(&run_args, &dir.join("network.onnx")).unwrap();
+            let witness = forward(&mut circuit, &data);
+            (circuit, witness)
+        };
+
+        let (direct, direct_witness) = layout(crate::circuit::ConvStrategy::Direct);
+        let (mut im2col, im2col_witness) = layout(crate::circuit::ConvStrategy::Im2col);
+        assert_eq!(direct_witness.outputs, im2col_witness.outputs);
+
+        // the [1, 2, 6, 6] image convolved by a biased [3, 2, 3, 3] kernel with padding 1. The
+        // rest of the model is laid out the same way under both strategies, so each layout takes
+        // the cells its conv does on top of the same count
+        let estimator = crate::circuit::ops::estimate::Estimator::new(
+            RunArgs::default().num_inner_cols,
+            RunArgs::default().decomp_base,
+            RunArgs::default().decomp_legs,
+        );
+        let conv_cells = |strategy| {
+            estimator
+                .conv(
+                    &[1, 2, 6, 6],
+                    &[3, 2, 3, 3],
+                    &[(1, 1); 2],
+                    &[1, 1],
+                    1,
+                    true,
+                    strategy,
+                )
+                .unwrap()
+        };
+        let direct_cells = conv_cells(crate::circuit::ConvStrategy::Direct);
+        let im2col_cells = conv_cells(crate::circuit::ConvStrategy::Im2col);
+        let rest = direct.settings().total_assignments - direct_cells;
+        assert_eq!(im2col.settings().total_assignments, rest + im2col_cells);
+        assert!(
+            im2col_cells < direct_cells,
+            "{} vs {}",
+            im2col_cells,
+            direct_cells
+        );
+        assert!(im2col.settings().num_rows < direct.settings().num_rows);
+
+        mock_prove(&mut im2col, &im2col_witness);
+    }
+
+    #[test]
+    fn non_square_conv_keeps_height_and_width_apart() {
+        let dir = std::path::Path::new("examples/onnx/1l_conv_nonsquare");
+        let data = GraphData::from_path(dir.join("input.json")).unwrap();
+        let expected = match &data.output_data {
+            Some(DataSource::File(expected)) => {
+                expected[0].iter().map(|v| v.to_float()).collect::<Vec<_>>()
+            }
+            _ => unreachable!(),
+        };
+        for conv_strategy in [
+            crate::circuit::ConvStrategy::Direct,
+            crate::circuit::ConvStrategy::Im2col,
+        ] {
+            let run_args = RunArgs {
+                conv_strategy,
+                ..RunArgs::default()
+            };
+            let mut circuit =
+                GraphCircuit::from_run_args(&run_args, &dir.join("network.onnx")).unwrap();
+            let witness = forward(&mut circuit, &data);
+            // 3x16x24 convolved by a 3x5 kernel with stride (1, 2) and padding (1, 2)
+            assert_eq!(witness.outputs[0].dims(), &[1, 4, 16, 12]);
+            let scales = circuit.model().graph.get_output_scales().unwrap();
+            let outputs = witness.get_float_outputs(&scales);
+            assert_eq!(outputs[0].len(), expected.len());
+            for (got, want) in outputs[0].iter().zip(&expected) {
+                assert!((*got as f64 - want).abs() < 0.05, "{} vs {}", got, want);
+            }
+
+            mock_prove(&mut circuit, &witness);
+        }
+    }
+
+    #[test]
+    fn self_contained_models_are_read_as_is() {
+        for entry in std::fs::read_dir("examples/onnx").unwrap() {
+            let path = entry.unwrap().path().join("network.onnx");
+            if !path.exists() || path.with_extension("onnx.data").exists() {
+                continue;
+            }
+            let bytes = std::fs::read(&path).unwrap();
+            assert!(
+                external_data::inline_external_data(bytes.clone(), path.parent()).unwrap() == bytes,
+                "{} was re-encoded",
+                path.display()
+            );
+        }
+    }
+
+    #[test]
+    fn whole_number_divisors_are_opt_in() {
+        let is_division = |n: &NodeType| {
+            matches!(
+                n.opkind(),
+                SupportedOp::Hybrid(crate::circuit::hybrid::HybridOp::Div { .. })
+            )
+        };
+        let opted_in = RunArgs {
+            whole_number_divisors: true,
+            ..RunArgs::default()
+        };
+        let mut divided = 0;
+        for entry in std::fs::read_dir("examples/onnx").unwrap() {
+            let path = entry.unwrap().path().join("network.onnx");
+            // some examples only load with run args of their own
+            let Ok(model) = Model::from_run_args(&RunArgs::default(), &path) else {
+                continue;
+            };
+            // by default every multiply stays one, so examples lay out as they always have
+            assert!(
+                !model.graph.nodes.values().any(is_division),
+                "{}",
+                path.display()
+            );
+            let model = Model::from_run_args(&opted_in, &path).unwrap();
+            divided += model
+                .graph
+                .nodes
+                .values()
+                .filter(|&n| is_division(n))
+                .count();
+        }
+        // the examples do divide by whole numbers, eg. the `x / 10` of 1l_div
+        assert!(divided > 0);
+    }
+
+    #[test]
+    fn external_data_matches_self_contained() {
+        let data = GraphData::from_path("examples/onnx/1l_mlp/input.json".into()).unwrap();
+        let run_args = RunArgs::default();
+        let layout = |model: &str| {
+            let mut circuit =
+                GraphCircuit::from_run_args(&run_args, std::path::Path::new(model)).unwrap();
+            let witness = forward(&mut circuit, &data);
+            (circuit, witness)
+        };
+
+        let (inline, inline_witness) = layout("examples/onnx/1l_mlp/network.onnx");
+        let (mut external, external_witness) =
+            layout("examples/onnx/1l_mlp_external_data/network.onnx");
+        assert_eq!(inline.settings().num_rows, external.settings().num_rows);
+        assert_eq!(inline_witness.outputs, external_witness.outputs);
+
+        // without the model's directory the sidecar file can't be found
+        let bytes = std::fs::read("examples/onnx/1l_mlp_external_data/network.onnx").unwrap();
+        assert!(external_data::inline_external_data(bytes, None).is_err());
+
+        mock_prove(&mut external, &external_witness);
+    }
+
+    #[test]
+    fn inputs_read_by_name_match_json_inputs() {
+        use input_source::{JsonInputSource, MemoryInputSource};
+
+        /// Serves inputs from json data, recording which names were asked for
+        struct MockSource {
+            inner: JsonInputSource,
+            reads: RefCell<Vec<String>>,
+        }
+
+        impl InputSource for MockSource {
+            fn read_input(&self, name: &str) -> Result<Tensor<f64>, GraphError> {
+                self.reads.borrow_mut().push(name.to_string());
+                self.inner.read_input(name)
+            }
+        }
+
+        let data = GraphData::from_path("examples/onnx/1l_mlp/input.json".into()).unwrap();
+        let mut circuit = GraphCircuit::from_run_args(
+            &RunArgs::default(),
+            std::path::Path::new("examples/onnx/1l_mlp/network.onnx"),
+        )
+        .unwrap();
+        let expected = circuit.load_graph_from_file_exclusively(&data).unwrap();
+
+        let names = circuit.model().graph.input_names().unwrap();
+        let source = MockSource {
+            inner: JsonInputSource::new(data, names.clone()),
+            reads: RefCell::new(vec![]),
+        };
+        assert_eq!(circuit.load_graph_input_from(&source).unwrap(), expected);
+        assert_eq!(*source.reads.borrow(), names);
+
+        let mut memory = MemoryInputSource::new();
+        memory.insert(
+            &names[0],
+            Tensor::new(Some(&[1.5417295, 0.5346153, 1.2172532]), &[1, 3]).unwrap(),
+        );
+        assert_eq!(circuit.load_graph_input_from(&memory).unwrap(), expected);
+
+        // a source without the model's input, or holding it at another shape, is rejected
+        let err = circuit
+            .load_graph_input_from(&MemoryInputSource::new())
+            .unwrap_err();
+        assert!(matches!(err, GraphError::InputSourceRead(..)), "{}", err);
+        memory.insert(&names[0], Tensor::new(Some(&[1.0; 4]), &[2, 2]).unwrap());
+        let err = circuit.load_graph_input_from(&memory).unwrap_err();
+        assert!(matches!(err, GraphError::InputShapeMismatch(..)), "{}", err);
+
+        // and the library prover proves straight from the mock source
+        let (params, pk) = setup(&circuit);
+        let vk = pk.get_vk().clone();
+        let prover = prover::Prover::new(circuit, pk, params.clone());
+        let snark = prover
+            .prove_from(
+                &source,
+                &prover::ProveOptions::default(),
+                &prover::CancellationToken::new(),
+            )
+            .unwrap();
+        assert!(verify(&snark, &params, &vk).is_ok());
+        assert_eq!(source.reads.borrow().len(), 2 * names.len());
+    }
+
+    /// Runs a graph circuit on an input that has to match the opening of a (dummy) public
+    /// commitment, by linking the model's assigned input cells to the opened values.
+    #[derive(Clone, Debug)]
+    struct CommittedInputCircuit {
+        graph: GraphCircuit,
+    }
+
+    #[derive(Clone, Debug)]
+    struct CommittedInputConfig {
+        graph: GraphConfig,
+        opening: halo2_proofs::plonk::Column<halo2_proofs::plonk::Advice>,
+        commitment: halo2_proofs::plonk::Column<halo2_proofs::plonk::Instance>,
+    }
+
+    impl Circuit<Fp> for CommittedInputCircuit {
+        type Config = CommittedInputConfig;
+        type FloorPlanner = ModulePlanner;
+        type Params = GraphSettings;
+
+        fn without_witnesses(&self) -> Self {
+            Self {
+                graph: self.graph.without_witnesses(),
+            }
+        }
+
+        fn params(&self) -> Self::Params {
+            self.graph.params()
+        }
+
+        fn configure_with_params(
+            cs: &mut ConstraintSystem<Fp>,
+            params: Self::Params,
+        ) -> Self::Config {
+            let graph = GraphCircuit::configure_with_params(cs, params);
+            let opening = cs.advice_column();
+            cs.enable_equality(opening);
+            let commitment = cs.instance_column();
+            cs.enable_equality(commitment);
+            CommittedInputConfig {
+                graph,
+                opening,
+                commitment,
+            }
+        }
+
+        fn configure(_: &mut ConstraintSystem<Fp>) -> Self::Config {
+            unimplemented!("you should call configure_with_params instead")
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fp>,
+        ) -> Result<(), PlonkError> {
+            let layout = self
+                .graph
+                .layout_graph(config.graph, layouter.namespace(|| "graph"))?;
+            let cells = layout.inputs[0]
+                .assigned_cells()
+                .map_err(|_| PlonkError::Synthesis)?;
+            layouter.assign_region(
+                || "commitment opening",
+                |mut region| {
+                    for (i, cell) in cells.iter().enumerate() {
+                        let opened = region.assign_advice_from_instance(
+                            || "opening",
+                            config.commitment,
+                            i,
+                            config.opening,
+                            i,
+                        )?;
+                        region.constrain_equal(opened.cell(), cell.cell())?;
+                    }
+                    Ok(())
+                },
+            )
+        }
+    }
+
+    #[test]
+    fn input_cells_link_to_commitment() {
+        let run_args = RunArgs::default();
+        let mut circuit = GraphCircuit::from_run_args(
+            &run_args,
+            std::path::Path::new("examples/onnx/1l_mlp/network.onnx"),
+        )
+        .unwrap();
+        let data = GraphData::from_path("examples/onnx/1l_mlp/input.json".into()).unwrap();
+        let witness = forward(&mut circuit, &data);
+        let public_inputs = load_witness(&mut circuit, &witness);
+        let logrows = circuit.settings().run_args.logrows;
+
+        let committed = CommittedInputCircuit { graph: circuit };
+        let prove = |commitment: Vec<Fp>| {
+            halo2_proofs::dev::MockProver::run(
+                logrows,
+                &committed,
+                vec![public_inputs.clone(), commitment],
+            )
+            .unwrap()
+            .verify()
+        };
+
+        assert!(prove(witness.inputs[0].clone()).is_ok());
+
+        // a commitment to any other input can't be opened against the model's input
+        let mut other = witness.inputs[0].clone();
+        other[0] += Fp::one();
+        assert!(prove(other).is_err());
+    }
+
+    #[test]
+    fn repeated_loads_are_identical() {
+        for model in [
+            "examples/onnx/layernorm/network.onnx",
+            "examples/onnx/lstm/network.onnx",
+        ] {
+            let load = || {
+                GraphCircuit::from_run_args(&RunArgs::default(), std::path::Path::new(model))
+                    .unwrap()
+            };
+            let first = load();
+            for (idx, node) in first.model().graph.nodes.iter() {
+                assert!(
+                    node.inputs().iter().all(|(input, _)| input < idx),
+                    "{}: node {} is not in topological order",
+                    model,
+                    idx
+                );
+            }
+            for _ in 1..10 {
+                let other = load();
+                assert_eq!(first.model().table_nodes(), other.model().table_nodes());
+                assert_eq!(first.settings(), other.settings());
+            }
+        }
+    }
+
+    #[test]
+    fn one_key_proves_many_inputs() {
+        let mut circuit = GraphCircuit::from_run_args(
+            &RunArgs::default(),
+            std::path::Path::new("examples/onnx/1l_mlp/network.onnx"),
+        )
+        .unwrap();
+
+        let witnesses = [
+            [1.5417295, 0.5346153, 1.2172532],
+            [-0.25, 0.75, 0.125],
+            [0.0, -1.0, 0.5],
+        ]
+        .map(|input| witness(&circuit, &input));
+
+        let min = witnesses.iter().map(|w| w.min_lookup_inputs).min().unwrap();
+        let max = witnesses.iter().map(|w| w.max_lookup_inputs).max().unwrap();
+        let max_range_size = witnesses.iter().map(|w| w.max_range_size).max().unwrap();
+        circuit
+            .calc_min_logrows((min, max), max_range_size, None, 2.0)
+            .unwrap();
+        let (params, pk) = setup(&circuit);
+
+        for witness in witnesses.iter() {
+            circuit.load_graph_witness(witness).unwrap();
+            let public_inputs = circuit.prepare_public_inputs(witness).unwrap();
+            let snark = prove(&circuit, public_inputs, &params, &pk);
+            assert!(verify(&snark, &params, pk.get_vk()).is_ok());
+        }
+    }
+
+    #[test]
+    fn threshold_checks_prove_only_when_met() {
+        let model = std::path::Path::new("examples/onnx/1l_mlp/network.onnx");
+        let input = [1.5417295, 0.5346153, 1.2172532];
+        let circuit = GraphCircuit::from_run_args(&RunArgs::default(), model).unwrap();
+        let scales = circuit.model().graph.get_output_scales().unwrap();
+        let output = witness(&circuit, &input).get_float_outputs(&scales)[0]
+            .iter()
+            .next()
+            .copied()
+            .unwrap() as f64;
+
+        let check = |threshold: f64| {
+            let run_args = RunArgs {
+                threshold_check: Some((0, threshold)),
+                ..RunArgs::default()
+            };
+            let mut circuit = GraphCircuit::from_run_args(&run_args, model).unwrap();
+            let witness = witness(&circuit, &input);
+            // the comparison bit is all that's output
+            assert_eq!(witness.outputs, vec![vec![Fp::one()]]);
+            let public_inputs = load_witness(&mut circuit, &witness);
+            halo2_proofs::dev::MockProver::run(
+                circuit.settings().run_args.logrows,
+                &circuit,
+                vec![public_inputs],
+            )
+            .unwrap()
+            .verify()
+        };
+        assert!(check(output - 0.5).is_ok());
+        assert!(check(output + 0.5).is_err());
+    }
+
+    #[test]
+    fn calibrated_lookup_ranges_shrink_the_tables() {
+        let dir = crate::dev_fixtures::conv_sigmoid_tanh(2, 6)
+            .write()
+            .unwrap();
+        let data = GraphData::from_path(dir.join("input.json")).unwrap();
+        let (mut circuit, witnesses) = OnnxModelBuilder::new(dir.join("network.onnx"))
+            .calibrate_with([data])
+            .build_with_witnesses()
+            .unwrap();
+        let settings = circuit.settings().clone();
+        // the sigmoid's and the tanh's
+        assert!(settings.lookup_table_rows().len() >= 2);
+
+        let total_rows = |settings: &GraphSettings| {
+            settings
+                .lookup_table_rows()
+                .iter()
+                .map(|(_, rows)| rows)
+                .sum::<usize>()
+        };
+        // every table over the global lookup range, as before calibrating each op's range
+        let global = GraphSettings {
+            lookup_ranges: vec![],
+            ..settings.clone()
+        };
+        assert!(total_rows(&settings) < total_rows(&global));
+
+        // the narrower tables still hold every lookup the calibration data makes
+        circuit.load_graph_witness(&witnesses[0]).unwrap();
+        let public_inputs = circuit.prepare_public_inputs(&witnesses[0]).unwrap();
+        halo2_proofs::dev::MockProver::run(
+            settings.run_args.logrows,
+            &circuit,
+            vec![public_inputs],
+        )
+        .unwrap()
+        .assert_satisfied();
+    }
+
+    #[test]
+    fn custom_tables_prove() {
+        let dir = crate::dev_fixtures::shrink(8).write().unwrap();
+        let lookup_range = (-256, 256);
+        let shrink = |x: f64| match x {
+            x if x < -0.25 => x + 0.125,
+            x if x > 0.25 => x - 0.125,
+            _ => 0.0,
+        };
+        let tables = std::collections::BTreeMap::from([(
+            "Shrink".to_string(),
+            crate::circuit::lookup::CustomTable::from_fn(lookup_range, 7, shrink),
+        )]);
+        let tables_path = dir.join("tables.json");
+        std::fs::write(&tables_path, serde_json::to_vec(&tables).unwrap()).unwrap();
+
+        let run_args = RunArgs {
+            custom_tables: Some(tables_path),
+            lookup_range,
+            ..RunArgs::default()
+        };
+        let mut circuit =
+            GraphCircuit::from_run_args(&run_args, &dir.join("network.onnx")).unwrap();
+        assert!(circuit
+            .model()
+            .graph
+            .nodes
+            .values()
+            .any(|n| matches!(n.opkind(), SupportedOp::Nonlinear(LookupOp::Custom { .. }))));
+
+        let data = GraphData::from_path(dir.join("input.json")).unwrap();
+        let witness = forward(&mut circuit, &data);
+        let scales = circuit.model().graph.get_output_scales().unwrap();
+        let inputs = match &data.input_data {
+            DataSource::File(inputs) => inputs[0].iter().map(|x| x.to_float()).collect_vec(),
+            _ => unreachable!(),
+        };
+        for (got, x) in witness.get_float_outputs(&scales)[0].iter().zip(inputs) {
+            assert!((*got as f64 - shrink(x)).abs() < 1e-2);
+        }
+
+        let public_inputs = load_witness(&mut circuit, &witness);
+        let (params, pk) = setup(&circuit);
+        let snark = prove(&circuit, public_inputs, &params, &pk);
+        assert!(verify(&snark, &params, pk.get_vk()).is_ok());
+    }
+
+    #[test]
+    fn skip_connections_prove() {
+        let dir = crate::dev_fixtures::conv_skip(2, 4).write().unwrap();
+        let data = GraphData::from_path(dir.join("input.json")).unwrap();
+        let run_args = RunArgs::default();
+        let mut circuit =
+            GraphCircuit::from_run_args(&run_args, &dir.join("network.onnx")).unwrap();
+
+        // the add reads the model's input as well as the relu's output, rescaled to match
+        let nodes = &circuit.model().graph.nodes;
+        let input = nodes
+            .iter()
+            .find_map(|(idx, n)| match n {
+                NodeType::Node(Node {
+                    opkind: SupportedOp::Input(_),
+                    ..
+                }) => Some(*idx),
+                _ => None,
+            })
+            .unwrap();
+        let add = nodes.values().find(|n| n.as_str().contains("ADD")).unwrap();
+        assert!(add.inputs().iter().any(|(idx, _)| *idx == input));
+
+        let witness = forward(&mut circuit, &data);
+        let public_inputs = load_witness(&mut circuit, &witness);
+        let (params, pk) = setup(&circuit);
+        let snark = prove(&circuit, public_inputs, &params, &pk);
+        assert!(verify(&snark, &params, pk.get_vk()).is_ok());
+    }
+
+    #[test]
+    fn proofs_verify_only_with_their_context() {
+        let run_args = RunArgs {
+            bind_context: true,
+            ..RunArgs::default()
+        };
+        let mut circuit = GraphCircuit::from_run_args(
+            &run_args,
+            std::path::Path::new("examples/onnx/1l_mlp/network.onnx"),
+        )
+        .unwrap();
+        let mut witness = witness(&circuit, &[1.5417295, 0.5346153, 1.2172532]);
+
+        // proving needs a context to bind to
+        assert!(matches!(
+            circuit.prepare_public_inputs(&witness),
+            Err(GraphError::MissingContext)
+        ));
+        witness.set_context(b"request-42");
+        let public_inputs = load_witness(&mut circuit, &witness);
+        let settings = circuit.settings().clone();
+        assert_eq!(
+            public_inputs[settings.context_instance().unwrap()],
+            witness.context_hash.unwrap()
+        );
+
+        let (params, pk) = setup(&circuit);
+        let mut snark = prove(&circuit, public_inputs, &params, &pk);
+
+        let instances = snark.instances.concat();
+        settings
+            .check_context(&instances, Some(b"request-42"))
+            .unwrap();
+        assert!(matches!(
+            settings.check_context(&instances, Some(b"request-43")),
+            Err(GraphError::ContextMismatch(..))
+        ));
+        assert!(matches!(
+            settings.check_context(&instances, None),
+            Err(GraphError::MissingContext)
+        ));
+
+        assert!(verify(&snark, &params, pk.get_vk()).is_ok());
+
+        // swapping in another context's hash doesn't verify, the circuit constrains the slot
+        snark.instances[0][settings.context_instance().unwrap()] =
+            modules::context_hash(b"request-43");
+        assert!(verify(&snark, &params, pk.get_vk()).is_err());
+    }
+
+    #[test]
+    fn swapped_metadata_fails_the_context_check() {
+        use crate::pfsys::{bound_context, Snark};
+
+        let run_args = RunArgs {
+            bind_context: true,
+            ..RunArgs::default()
+        };
+        let mut circuit = GraphCircuit::from_run_args(
+            &run_args,
+            std::path::Path::new("examples/onnx/1l_mlp/network.onnx"),
+        )
+        .unwrap();
+        let metadata = serde_json::to_string(&circuit.artifact_metadata().unwrap()).unwrap();
+        let mut witness = witness(&circuit, &[1.5417295, 0.5346153, 1.2172532]);
+        witness.set_context(&bound_context(b"request-42", Some(&metadata)));
+        let public_inputs = load_witness(&mut circuit, &witness);
+        let settings = circuit.settings().clone();
+
+        let (params, pk) = setup(&circuit);
+        let mut snark = prove(&circuit, public_inputs, &params, &pk);
+        snark.set_metadata(metadata.clone());
+        assert!(verify(&snark, &params, pk.get_vk()).is_ok());
+
+        let check = |snark: &Snark<Fp, G1Affine>| {
+            let context = bound_context(b"request-42", snark.metadata().unwrap());
+            settings.check_context(&snark.instances.concat(), Some(&context))
+        };
+        check(&snark).unwrap();
+
+        // rehashing swapped metadata gets it past the unkeyed hash, but not past the context
+        let mut swapped = snark.clone();
+        swapped
+            .set_metadata(metadata.replace("\"input_shapes\":[[1,3]]", "\"input_shapes\":[[1,4]]"));
+        assert_ne!(swapped.metadata, snark.metadata);
+        assert!(matches!(
+            check(&swapped),
+            Err(GraphError::ContextMismatch(..))
+        ));
+
+        // and neither is stripping it
+        let mut stripped = snark.clone();
+        stripped.metadata = None;
+        stripped.metadata_hash = None;
+        assert!(matches!(
+            check(&stripped),
+            Err(GraphError::ContextMismatch(..))
+        ));
+    }
+
+    #[test]
+    fn proofs_verify_only_with_their_weights_root() {
+        let run_args = RunArgs {
+            param_visibility: Visibility::Merkle,
+            ..RunArgs::default()
+        };
+        let mut circuit = GraphCircuit::from_run_args(
+            &run_args,
+            std::path::Path::new("examples/onnx/1l_mlp/network.onnx"),
+        )
+        .unwrap();
+        let witness = witness(&circuit, &[1.5417295, 0.5346153, 1.2172532]);
+        let public_inputs = load_witness(&mut circuit, &witness);
+        let settings = circuit.settings().clone();
+
+        // the off-circuit root is the one the circuit exposes
+        let root = modules::merkle_root(&circuit.model().get_all_params().unwrap());
+        assert_eq!(
+            public_inputs[settings.weights_root_instance().unwrap()],
+            root
+        );
+
+        let (params, pk) = setup(&circuit);
+        let mut snark = prove(&circuit, public_inputs, &params, &pk);
+
+        let instances = snark.instances.concat();
+        settings.check_weights_root(&instances, Some(root)).unwrap();
+        assert!(matches!(
+            settings.check_weights_root(&instances, Some(root + Fp::one())),
+            Err(GraphError::WeightsRootMismatch(..))
+        ));
+        assert!(matches!(
+            settings.check_weights_root(&instances, None),
+            Err(GraphError::MissingWeightsRoot)
+        ));
+
+        assert!(verify(&snark, &params, pk.get_vk()).is_ok());
+
+        // a proof can't be passed off as one for other weights
+        snark.instances[0][settings.weights_root_instance().unwrap()] = root + Fp::one();
+        assert!(verify(&snark, &params, pk.get_vk()).is_err());
+    }
+
+    #[test]
+    fn placed_params_are_constrained_where_placed() {
+        let dir = crate::dev_fixtures::mixed_placement(3, 4, 2)
+            .write()
+            .unwrap();
+        let run_args = RunArgs {
+            param_visibility: Visibility::Hashed {
+                hash_is_public: true,
+                outlets: vec![],
+            },
+            // the feature extractor is public, the personalized head is only committed to
+            param_placement: vec![
+                ("gemm_0".to_string(), ParamPlacement::Fixed),
+                ("gemm_2".to_string(), ParamPlacement::Committed),
+            ],
+            ..RunArgs::default()
+        };
+        let input = [0.5, -0.25, 1.0];
+        let mut circuit =
+            GraphCircuit::from_run_args(&run_args, &dir.join("network.onnx")).unwrap();
+
+        // the metadata records which constants ended up where
+        let placements = circuit.artifact_metadata().unwrap().params;
+        let num_fixed = placements.iter().filter(|(_, v)| v.is_fixed()).count();
+        let num_hashed = placements.iter().filter(|(_, v)| v.is_hashed()).count();
+        assert!(num_fixed > 0 && num_hashed > 0, "{:?}", placements);
+        assert_eq!(num_fixed + num_hashed, placements.len(), "{:?}", placements);
+        // only the committed ones are hashed
+        assert_eq!(circuit.model().get_all_params().unwrap().len(), num_hashed);
+
+        let original = witness(&circuit, &input);
+        load_witness(&mut circuit, &original);
+        let (params, pk) = setup(&circuit);
+
+        // the circuit with one quantum added to the first weight of the given visibility
+        let perturbed = |placed: Visibility| {
+            let mut perturbed = circuit.clone();
+            let constant = perturbed
+                .core
+                .model
+                .graph
+                .nodes
+                .values_mut()
+                .find_map(|node| match node {
+                    NodeType::Node(Node {
+                        opkind: SupportedOp::Constant(c),
+                        ..
+                    }) if *c.visibility() == placed => Some(c),
+                    _ => None,
+                })
+                .unwrap();
+            constant.quantized_values_mut().unwrap()[0] += Fp::one();
+            perturbed
+        };
+        let mut fixed = perturbed(Visibility::Fixed);
+        let mut committed = perturbed(run_args.param_visibility.clone());
+
+        let prove_input =
+            |circuit: &mut GraphCircuit, pk: &halo2_proofs::plonk::ProvingKey<G1Affine>| {
+                let witness = witness(circuit, &input);
+                circuit.load_graph_witness(&witness).unwrap();
+                let public_inputs = circuit.prepare_public_inputs(&witness).unwrap();
+                let snark = prove(circuit, public_inputs, &params, pk);
+                (witness, snark)
+            };
+
+        let (_, snark) = prove_input(&mut circuit, &pk);
+        assert!(verify(&snark, &params, pk.get_vk()).is_ok());
+
+        // fixed weights are part of the keys, so a proof for other weights doesn't verify
+        let fixed_pk =
+            create_keys::<KZGCommitmentScheme<bn256::Bn256>, GraphCircuit>(&fixed, &params, false)
+                .unwrap();
+        let (_, snark) = prove_input(&mut fixed, &fixed_pk);
+        assert!(verify(&snark, &params, pk.get_vk()).is_err());
+
+        // committed weights are witnessed under the same keys, only their hash changes
+        let (witness, snark) = prove_input(&mut committed, &pk);
+        assert!(verify(&snark, &params, pk.get_vk()).is_ok());
+        assert_ne!(witness.processed_params, original.processed_params);
+    }
+
+    #[test]
+    fn artifacts_carry_metadata() {
+        use crate::pfsys::{append_metadata, load_vk, read_metadata, save_vk, Snark};
+
+        let mut circuit = GraphCircuit::from_run_args(
+            &RunArgs::default(),
+            std::path::Path::new("examples/onnx/1l_mlp/network.onnx"),
+        )
+        .unwrap();
+        let witness = witness(&circuit, &[1.5417295, 0.5346153, 1.2172532]);
+        let public_inputs = load_witness(&mut circuit, &witness);
+        let (params, pk) = setup(&circuit);
+
+        let metadata = circuit.artifact_metadata().unwrap();
+        assert_eq!(metadata.input_shapes, vec![vec![1, 3]]);
+        let metadata = serde_json::to_string(&metadata).unwrap();
+
+        let dir = tempfile::Builder::new()
+            .prefix("metadata")
+            .tempdir()
+            .unwrap();
+        let vk_path = dir.path().join("key.vk");
+        save_vk(&vk_path, pk.get_vk()).unwrap();
+        append_metadata(&vk_path, &metadata).unwrap();
+        assert_eq!(read_metadata(&vk_path).unwrap(), Some(metadata.clone()));
+        // the trailer doesn't get in the way of loading the key
+        load_vk::<KZGCommitmentScheme<bn256::Bn256>, GraphCircuit>(
+            vk_path.clone(),
+            circuit.params(),
+        )
+        .unwrap();
+
+        let inspected = crate::execute::inspect(vk_path.clone()).unwrap();
+        assert!(inspected.contains("\"input_shapes\""));
+        assert!(inspected.contains(&format!(
+            "\"logrows\": {}",
+            circuit.settings().run_args.logrows
+        )));
+
+        // swapping the metadata is caught
+        let mut bytes = std::fs::read(&vk_path).unwrap();
+        let at = bytes.len() - 100;
+        bytes[at] ^= 1;
+        std::fs::write(&vk_path, bytes).unwrap();
+        assert!(read_metadata(&vk_path).is_err());
+        assert!(crate::execute::inspect(vk_path).is_err());
+
+        let mut snark = prove(&circuit, public_inputs, &params, &pk);
+        snark.set_metadata(metadata.clone());
+
+        let proof_path = dir.path().join("proof.json");
+        snark.save(&proof_path).unwrap();
+        let inspected = crate::execute::inspect(proof_path.clone()).unwrap();
+        assert!(inspected.contains("\"output_shapes\""));
+
+        let mut tampered = Snark::<bn256::Fr, bn256::G1Affine>::load::<
+            KZGCommitmentScheme<bn256::Bn256>,
+        >(&proof_path)
+        .unwrap();
+        tampered.metadata = Some(metadata.replace("\"logrows\"", "\"logrows\" "));
+        assert!(tampered.metadata().is_err());
+        tampered.save(&proof_path).unwrap();
+        assert!(crate::execute::inspect(proof_path).is_err());
+    }
+
+    #[test]
+    fn approx_avgpool_trades_accuracy_for_rows() {
+        let path = "examples/onnx/1l_avg_pool_3x3";
+        let data = GraphData::from_path(format!("{}/input.json", path).into()).unwrap();
+        let json: serde_json::Value =
+            serde_json::from_reader(std::fs::File::open(format!("{}/input.json", path)).unwrap())
+                .unwrap();
+        let expected: Vec<f64> = serde_json::from_value(json["output_data"][0].clone()).unwrap();
+
+        let run = |avgpool_mode| {
+            // leave room for the raised output scale so it isn't rebased back down
+            let run_args = RunArgs {
+                avgpool_mode,
+                scale_rebase_multiplier: 2,
+                ..RunArgs::default()
+            };
+            let mut circuit = GraphCircuit::from_run_args(
+                &run_args,
+                std::path::Path::new(&format!("{}/network.onnx", path)),
+            )
+            .unwrap();
+            let witness = forward(&mut circuit, &data);
+            let multiplier = scale_to_multiplier(circuit.settings().model_output_scales[0]);
+            let outputs = witness.outputs[0]
+                .iter()
+                .map(|x| felt_to_f64(*x) / multiplier)
+                .collect::<Vec<_>>();
+
+            mock_prove(&mut circuit, &witness);
+            (outputs, circuit.settings().num_rows)
+        };
+
+        let (exact, exact_rows) = run(AvgPoolMode::Exact);
+        let (approx, approx_rows) = run(AvgPoolMode::Approx);
+        assert!(approx_rows < exact_rows);
+
+        // the 3x3 window is divided by 8 instead of 9
+        let factor_error = 9.0 / 8.0 - 1.0;
+        let tolerance = 0.02;
+        for ((e, a), y) in exact.iter().zip(&approx).zip(&expected) {
+            assert!((e - y).abs() < tolerance, "{} vs {}", e, y);
+            assert!(
+                (a - y).abs() < factor_error * y.abs() + tolerance,
+                "{} vs {}",
+                a,
+                y
+            );
+        }
+        assert!(approx
+            .iter()
+            .zip(&expected)
+            .any(|(a, y)| (a - y).abs() > tolerance));
+    }
+
+    #[test]
+    fn poly_relu_matches_lookup_relu() {
+        use crate::circuit::ReluImpl;
+
+        let path = "examples/onnx/1l_mlp";
+        let data = GraphData::from_path(format!("{}/input.json", path).into()).unwrap();
+        let run = |relu_impl| {
+            let run_args = RunArgs {
+                relu_impl,
+                lookup_range: (-2048, 2048),
+                ..RunArgs::default()
+            };
+            let mut circuit = GraphCircuit::from_run_args(
+                &run_args,
+                std::path::Path::new(&format!("{}/network.onnx", path)),
+            )
+            .unwrap();
+            assert!(circuit.model().graph.nodes.values().any(|n| n.is_relu()));
+            let witness = forward(&mut circuit, &data);
+            mock_prove(&mut circuit, &witness);
+            witness.outputs
+        };
+        assert_eq!(run(ReluImpl::Lookup), run(ReluImpl::Poly));
+
+        // the sign bits are range checked over the lookup range calibration settles on, rather
+        // than the unbounded one it measured the inputs under
+        let (mut circuit, mut witnesses) = OnnxModelBuilder::new(format!("{}/network.onnx", path))
+            .run_args(RunArgs {
+                relu_impl: ReluImpl::Poly,
+                ..RunArgs::default()
+            })
+            .calibrate_with([data.clone()])
+            .build_with_witnesses()
+            .unwrap();
+        let (min, max) = circuit.settings().run_args.lookup_range;
+        assert!(circuit
+            .settings()
+            .required_range_checks
+            .contains(&(0, std::cmp::max(-min, max))));
+        mock_prove(&mut circuit, &witnesses.remove(0));
+
+        // overrides apply to the relu and to the ops tract splits it into
+        let run_args = RunArgs {
+            node_relu_impl: vec![("relu".to_string(), ReluImpl::Poly)],
+            ..RunArgs::default()
+        };
+        assert_eq!(run_args.relu_impl_of("relu"), ReluImpl::Poly);
+        assert_eq!(run_args.relu_impl_of("relu.max"), ReluImpl::Poly);
+        assert_eq!(run_args.relu_impl_of("relu_1"), ReluImpl::Lookup);
+    }
+
+    #[test]
+    fn output_max_flags_out_of_distribution_inputs() {
+        let run_args = RunArgs::default();
+        let mut circuit = GraphCircuit::from_run_args(
+            &run_args,
+            std::path::Path::new("examples/onnx/1l_mlp/network.onnx"),
+        )
+        .unwrap();
+        let data = GraphData::from_path("examples/onnx/1l_mlp/input.json".into()).unwrap();
+        let inputs = circuit.load_graph_from_file_exclusively(&data).unwrap();
+        let run = |circuit: &GraphCircuit, inputs: &[Tensor<Fp>]| {
+            circuit
+                .forward::<KZGCommitmentScheme<bn256::Bn256>>(
+                    &mut inputs.to_vec(),
+                    None,
+                    None,
+                    RegionSettings::all_true(run_args.decomp_base, run_args.decomp_legs),
+                )
+                .unwrap()
+        };
+
+        // calibrate on the sample input, which then stays within bounds
+        let witness = run(&circuit, &inputs);
+        assert!(!witness.node_output_max.is_empty());
+        circuit.set_output_max(&witness.node_output_max, 1.0);
+        circuit.check_output_max(&witness, None).unwrap();
+
+        let oversized = inputs
+            .iter()
+            .map(|t| t.map(|x| x * Fp::from(10)))
+            .collect_vec();
+        let witness = run(&circuit, &oversized);
+
+        let mut exceeded = vec![];
+        circuit
+            .check_output_max(
+                &witness,
+                Some(&mut |e: &OutputMaxExceeded| exceeded.push(e.clone())),
+            )
+            .unwrap();
+        assert!(!exceeded.is_empty());
+        for e in &exceeded {
+            assert!(e.observed > e.output_max);
+            assert!(!e.op.is_empty());
+        }
+
+        assert!(matches!(
+            circuit.check_output_max(&witness, None),
+            Err(GraphError::OutputMaxExceeded(..))
+        ));
+
+        // witness generation only warns unless strict_output_max is set
+        circuit.enforce_output_max(&witness).unwrap();
+        circuit.settings_mut().run_args.strict_output_max = true;
+        assert!(matches!(
+            circuit.enforce_output_max(&witness),
+            Err(GraphError::OutputMaxExceeded(..))
+        ));
+    }
+
+    #[test]
+    fn outlier_inputs_are_clipped_and_reported() {
+        let run_args = RunArgs::default();
+        let mut circuit = GraphCircuit::from_run_args(
+            &run_args,
+            std::path::Path::new("examples/onnx/1l_mlp/network.onnx"),
+        )
+        .unwrap();
+        let graph = &circuit.model().graph;
+        let (shapes, scales, types) = (
+            graph.input_shapes().unwrap(),
+            graph.get_input_scales(),
+            graph.get_input_types().unwrap(),
+        );
+        let name = graph.input_names().unwrap()[0].clone();
+        let mut load = |values: &[f64]| {
+            let data = vec![values
+                .iter()
+                .map(|x| FileSourceInner::new_float(*x))
+                .collect()];
+            circuit
+                .load_file_data(&data, &shapes, scales.clone(), types.clone())
+                .unwrap()
+        };
+
+        // the decomposition range at the input's scale
+        let bound = (run_args.decomp_base as f64).powi(run_args.decomp_legs as i32)
+            / 2.0
+            / scale_to_multiplier(scales[0]);
+        let inputs = load(&[0.5, 4.0 * bound, -1e12]);
+        let quantize =
+            |x: f64| integer_rep_to_felt::<Fp>(quantize_float(&x, 0.0, scales[0]).unwrap());
+        assert_eq!(
+            inputs[0].iter().cloned().collect_vec(),
+            vec![quantize(0.5), quantize(bound), quantize(-bound)]
+        );
+        let clipped = ClipRate {
+            node: name,
+            clipped: 2,
+            total: 3,
+        };
+        assert_eq!(circuit.clip_rates(), vec![clipped.clone()]);
+        assert_eq!(
+            circuit.estimate_resources().unwrap().clip_rates,
+            vec![clipped]
+        );
+
+        // inputs in range clip nothing, and replace the last inputs' clipping
+        let data = GraphData::from_path("examples/onnx/1l_mlp/input.json".into()).unwrap();
+        circuit.load_graph_from_file_exclusively(&data).unwrap();
+        assert!(circuit.clip_rates().is_empty());
+    }
+
+    #[test]
+    fn advice_width_budget_trades_rows_for_columns() {
+        let path = "examples/onnx/3l_relu_conv_fc";
+        let data = GraphData::from_path(format!("{}/input.json", path).into()).unwrap();
+
+        let run = |max_advice_cols| {
+            let run_args = RunArgs {
+                max_advice_cols,
+                ..RunArgs::default()
+            };
+            let mut circuit = GraphCircuit::from_run_args(
+                &run_args,
+                std::path::Path::new(&format!("{}/network.onnx", path)),
+            )
+            .unwrap();
+            let witness = forward(&mut circuit, &data);
+            let calibrated = circuit.calc_min_logrows(
+                (witness.min_lookup_inputs, witness.max_lookup_inputs),
+                witness.max_range_size,
+                None,
+                2.0,
+            );
+            (circuit, witness, calibrated)
+        };
+
+        let num_inner_cols = RunArgs::default().num_inner_cols;
+        let mut logrows = vec![];
+        let mut outputs = vec![];
+        for blocks in [1, 2, 4] {
+            let budget = 3 * num_inner_cols * blocks;
+            let (mut circuit, witness, calibrated) = run(Some(budget));
+            calibrated.unwrap();
+            let settings = circuit.settings().clone();
+            assert!(settings.advice_width(settings.run_args.logrows) <= budget);
+            circuit.check_advice_width().unwrap();
+
+            mock_prove(&mut circuit, &witness);
+            logrows.push(settings.run_args.logrows);
+            outputs.push(witness.outputs);
+        }
+        // wider budgets buy fewer rows, without changing what's proven
+        assert!(logrows[0] > logrows[1] && logrows[1] > logrows[2]);
+        assert!(outputs.iter().all(|o| *o == outputs[0]));
+
+        // no logrows fits everything in a single column block when the max is capped below it
+        let (mut circuit, witness, _) = run(Some(3 * num_inner_cols));
+        match circuit.calc_min_logrows(
+            (witness.min_lookup_inputs, witness.max_lookup_inputs),
+            witness.max_range_size,
+            Some(logrows[2]),
+            2.0,
+        ) {
+            Err(GraphError::AdviceWidthExceeded(needed, _, budget, widest)) => {
+                assert!(needed > budget);
+                assert!(widest.starts_with("node "));
+            }
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn instance_counts_follow_visibility() {
+        let hashed = Visibility::Hashed {
+            hash_is_public: true,
+            outlets: vec![],
+        };
+        let num_instances = |model: &str, input: &Visibility, output: &Visibility| {
+            let run_args = RunArgs {
+                input_visibility: input.clone(),
+                output_visibility: output.clone(),
+                ..RunArgs::default()
+            };
+            let path = format!("examples/onnx/{}/network.onnx", model);
+            let circuit =
+                GraphCircuit::from_run_args(&run_args, std::path::Path::new(&path)).unwrap();
+            assert_eq!(
+                circuit.artifact_metadata().unwrap().num_instances,
+                circuit.settings().num_instances()
+            );
+            circuit.settings().num_instances()
+        };
+
+        // 1l_mlp takes 3 values to 4, two_heads takes 4 values to heads of 3 and 1
+        for (model, input, output, expected) in [
+            ("1l_mlp", Visibility::Private, Visibility::Public, 4),
+            ("1l_mlp", Visibility::Public, Visibility::Public, 7),
+            ("1l_mlp", Visibility::Private, hashed.clone(), 1),
+            ("1l_mlp", hashed.clone(), hashed.clone(), 2),
+            ("1l_mlp", Visibility::Private, Visibility::Private, 0),
+            ("two_heads", Visibility::Private, Visibility::Public, 4),
+            ("two_heads", Visibility::Public, Visibility::Public, 8),
+            // a hash per output
+            ("two_heads", Visibility::Private, hashed.clone(), 2),
+            ("two_heads", Visibility::Public, hashed.clone(), 6),
+        ] {
+            assert_eq!(
+                num_instances(model, &input, &output),
+                expected,
+                "{} with {} inputs and {} outputs",
+                model,
+                input,
+                output
+            );
+        }
+
+        let budgeted = |max_instances| {
+            let run_args = RunArgs {
+                input_visibility: Visibility::Public,
+                max_instances,
+                ..RunArgs::default()
+            };
+            GraphCircuit::from_run_args(
+                &run_args,
+                std::path::Path::new("examples/onnx/two_heads/network.onnx"),
+            )
+        };
+        assert!(budgeted(Some(8)).is_ok());
+        match budgeted(Some(7)) {
+            Err(e @ GraphError::InstanceBudgetExceeded(8, 7, _)) => {
+                let message = e.to_string();
+                for kind in ["input", "output"] {
+                    let hint = format!("--{}-visibility hashed/public", kind);
+                    assert!(message.contains(&hint), "{}", message);
+                }
+            }
+            other => panic!("{:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn proofs_report_each_phase_of_the_pipeline() {
+        use crate::execute;
+        use crate::pfsys::{ProofType, Snark};
+        use std::time::Duration;
+
+        let dir = tempfile::Builder::new()
+            .prefix("timings")
+            .tempdir()
+            .unwrap();
+        let path = |name: &str| dir.path().join(name);
+        let model = std::path::PathBuf::from("examples/onnx/1l_mlp/network.onnx");
+
+        execute::gen_circuit_settings(
+            model.clone(),
+            path("settings.json"),
+            RunArgs::default(),
+            false,
+        )
+        .unwrap();
+        execute::compile_circuit(model, path("network.compiled"), path("settings.json")).unwrap();
+        let logrows = GraphSettings::load(&path("settings.json"))
+            .unwrap()
+            .run_args
+            .logrows;
+        execute::gen_srs_cmd(path("kzg.srs"), logrows, Commitments::KZG).unwrap();
+        let witness = tokio::runtime::Runtime::new()
+            .unwrap()
+            .block_on(execute::gen_witness(
+                path("network.compiled"),
+                "examples/onnx/1l_mlp/input.json".into(),
+                Some(path("witness.json")),
+                None,
+                Some(path("kzg.srs")),
+                false,
+                None,
+                None,
+            ))
+            .unwrap();
+        assert!(witness.forward_time.unwrap() > Duration::ZERO);
+        execute::setup(
+            path("network.compiled"),
+            Some(path("kzg.srs")),
+            path("key.vk"),
+            path("key.pk"),
+            None,
+            false,
+        )
+        .unwrap();
+
+        let snark = execute::prove(
+            path("witness.json"),
+            path("network.compiled"),
+            path("key.pk"),
+            Some(path("proof.json")),
+            Some(path("kzg.srs")),
+            ProofType::Single,
+            CheckMode::SAFE,
+            None,
+            None,
+        )
+        .unwrap();
+        let timings = &snark.timings;
+        for (phase, time) in [
+            ("load", timings.load),
+            ("forward", timings.forward),
+            ("keygen", timings.keygen),
+            ("witness", timings.witness),
+            ("prove", timings.prove),
+            ("verify", timings.verify),
+        ] {
+            assert!(time > Duration::ZERO, "{} wasn't timed", phase);
+        }
+        assert_eq!(timings.forward, witness.forward_time.unwrap());
+
+        // and they're saved with the proof
+        let saved =
+            Snark::<Fp, G1Affine>::load::<KZGCommitmentScheme<bn256::Bn256>>(&path("proof.json"))
+                .unwrap();
+        assert_eq!(&saved.timings, timings);
+    }
+
+    #[test]
+    fn decodes_outputs_from_metadata() {
+        let run_args = RunArgs::default();
+        let metadata = ArtifactMetadata {
+            version: "test".to_string(),
+            ops: vec![],
+            input_shapes: vec![vec![1, 2]],
+            output_shapes: vec![vec![2, 3]],
+            input_scales: vec![7],
+            output_scales: vec![7],
+            run_args: run_args.clone(),
+            outputs: vec![],
+            num_instances: 8,
+            params: vec![],
+            keygen_time: None,
+        };
+        let outputs = [-1.5, 0.25, 0.5, 2.0, -0.75, -3.0];
+        // the (public) inputs come first and mustn't be decoded
+        let instances = [0.5, -0.5]
+            .iter()
+            .chain(&outputs)
+            .map(|x| integer_rep_to_felt(quantize_float(x, 0.0, 7).unwrap()))
+            .collect_vec();
+
+        // artifacts without per output metadata fall back to the shared shapes and scales
+        let floats = metadata
+            .decode_outputs(&instances, &[OutputFormat::Float])
+            .unwrap();
+        assert_eq!(
+            floats,
+            vec![NamedOutput {
+                name: "output_0".to_string(),
+                decoded: DecodedOutput::Float(outputs.to_vec()),
+            }]
+        );
+
+        let labels = metadata
+            .decode_outputs(&instances, &[OutputFormat::Labels])
+            .unwrap();
+        assert_eq!(labels[0].decoded, DecodedOutput::Labels(vec![2, 0]));
+
+        match &metadata
+            .decode_outputs(&instances, &[OutputFormat::Field])
+            .unwrap()[0]
+            .decoded
+        {
+            DecodedOutput::Field(field) => {
+                assert_eq!(field[0], format!("{:?}", instances[2]));
+                // negative values wrap around the field
+                assert_eq!(field[0], format!("{:?}", -Fp::from(192)));
+            }
+            other => panic!("{:?}", other),
+        }
+
+        assert!(matches!(
+            metadata.decode_outputs(&instances[3..], &[OutputFormat::Float]),
+            Err(GraphError::MissingInstances)
+        ));
+        assert!(matches!(
+            metadata.decode_outputs(&instances, &[OutputFormat::Float, OutputFormat::Labels]),
+            Err(GraphError::OutputFormatCount(2, 1))
+        ));
+        let private = ArtifactMetadata {
+            run_args: RunArgs {
+                output_visibility: Visibility::Private,
+                ..run_args
+            },
+            ..metadata
+        };
+        assert!(matches!(
+            private.decode_outputs(&instances, &[OutputFormat::Float]),
+            Err(GraphError::NonPublicOutputs(_))
+        ));
+    }
+
+    #[test]
+    fn decomposed_mish_is_fused_into_one_table() {
+        let dir = crate::dev_fixtures::conv_mish(2, 5).write().unwrap();
+        let model_path = dir.join("network.onnx");
+        let data = GraphData::from_path(dir.join("input.json")).unwrap();
+        let run_args = RunArgs::default();
+        let mut circuit = GraphCircuit::from_run_args(&run_args, &model_path).unwrap();
+
+        let ops = circuit
+            .model()
+            .graph
+            .nodes
+            .values()
+            .map(|n| n.as_str())
+            .collect_vec();
+        assert_eq!(
+            ops.iter().filter(|op| op.contains("MISH")).count(),
+            1,
+            "{:?}",
+            ops
+        );
+        for op in ["TANH", "EXP", "LN("] {
+            assert!(!ops.iter().any(|o| o.contains(op)), "{:?}", ops);
+        }
+
+        let witness = forward(&mut circuit, &data);
+        let scales = circuit.model().graph.get_output_scales().unwrap();
+        let outputs = witness.get_float_outputs(&scales);
+        let input_shapes = circuit.model().graph.input_shapes().unwrap();
+        let expected =
+            Model::run_onnx_predictions(&run_args, &model_path, &[data], input_shapes).unwrap();
+        for (o, e) in outputs[0].iter().zip(expected[0][0].iter()) {
+            // the conv's rounding, and the mish table's at the default scale
+            assert!((o - e).abs() <= 0.05, "{} vs {}", o, e);
+        }
+
+        mock_prove(&mut circuit, &witness);
+    }
+
+    #[test]
+    fn pruned_heads_propagate_empty_outputs() {
+        let dir = crate::dev_fixtures::pruned_head(4, 3).write().unwrap();
+        let model_path = dir.join("network.onnx");
+        let data = GraphData::from_path(dir.join("input.json")).unwrap();
+        let run_args = RunArgs::default();
+        let mut circuit = GraphCircuit::from_run_args(&run_args, &model_path).unwrap();
+        assert_eq!(
+            circuit.model().graph.output_shapes().unwrap(),
+            vec![vec![1, 3], vec![1, 0]]
+        );
+        // the empty output takes no instances
+        assert_eq!(circuit.settings().num_instances(), 3);
+
+        let witness = forward(&mut circuit, &data);
+        assert_eq!(witness.outputs[0].len(), 3);
+        assert!(witness.outputs[1].is_empty());
+
+        mock_prove(&mut circuit, &witness);
+
+        let hashed = RunArgs {
+            output_visibility: Visibility::Hashed {
+                hash_is_public: true,
+                outlets: vec![],
+            },
+            ..RunArgs::default()
+        };
+        assert!(matches!(
+            GraphCircuit::from_run_args(&hashed, &model_path),
+            Err(GraphError::EmptyTensorVisibility(_, 1, _))
+        ));
+    }
+
+    #[test]
+    fn decodes_each_head_at_its_own_scale() {
+        let circuit = GraphCircuit::from_run_args(
+            &RunArgs::default(),
+            std::path::Path::new("examples/onnx/two_heads/network.onnx"),
+        )
+        .unwrap();
+        let metadata = circuit.artifact_metadata().unwrap();
+        let layout = metadata
+            .outputs
+            .iter()
+            .map(|o| (o.name.as_str(), o.dims.clone()))
+            .collect_vec();
+        assert_eq!(layout, vec![("logits", vec![1, 3]), ("label", vec![1, 1])]);
+        // the argmax head holds integers, so it's at scale 0 unlike the logits
+        let scales = metadata.outputs.iter().map(|o| o.out_scale).collect_vec();
+        assert_eq!(scales[1], 0);
+        assert_ne!(scales[0], scales[1]);
+
+        let witness = witness(&circuit, &[-0.3125, 0.15625, -0.703125, 0.46875]);
+        let instances = circuit.prepare_public_inputs(&witness).unwrap();
+        let decoded = metadata
+            .decode_outputs(&instances, &[OutputFormat::Float])
+            .unwrap();
+        let expected = [vec![-0.5390625, -0.34765625, 0.716796875], vec![2.0]];
+        for (output, expected) in decoded.iter().zip(&expected) {
+            let DecodedOutput::Float(floats) = &output.decoded else {
+                panic!("{:?}", output);
+            };
+            for (x, y) in floats.iter().zip(expected) {
+                assert!(
+                    (x - y).abs() <= 1.0 / 128.0,
+                    "{}: {} vs {}",
+                    output.name,
+                    x,
+                    y
+                );
+            }
+        }
+
+        // a format per head: the logits' argmax is the label the other head holds
+        let decoded = metadata
+            .decode_outputs(&instances, &[OutputFormat::Labels, OutputFormat::Float])
+            .unwrap();
+        assert_eq!(decoded[0].decoded, DecodedOutput::Labels(vec![2]));
+        assert_eq!(decoded[1].decoded, DecodedOutput::Float(vec![2.0]));
+        assert!(matches!(
+            metadata.decode_outputs(&instances, &[OutputFormat::Float; 3]),
+            Err(GraphError::OutputFormatCount(3, 2))
+        ));
+    }
+
+    /// Mock proves 1l_mlp on `input`, returning the circuit's row count and the mock prover's verdict.
+    fn mock_categorical(
+        groups: Vec<(usize, usize)>,
+        input: &[f64],
+    ) -> (usize, Result<(), Vec<halo2_proofs::dev::VerifyFailure>>) {
+        let run_args = RunArgs {
+            categorical_groups: groups,
+            ..RunArgs::default()
+        };
+        let mut circuit = GraphCircuit::from_run_args(
+            &run_args,
+            std::path::Path::new("examples/onnx/1l_mlp/network.onnx"),
+        )
+        .unwrap();
+        let witness = witness(&circuit, input);
+        let public_inputs = load_witness(&mut circuit, &witness);
+        let res = halo2_proofs::dev::MockProver::run(
+            circuit.settings().run_args.logrows,
+            &circuit,
+            vec![public_inputs],
+        )
+        .unwrap()
+        .verify();
+        (circuit.settings().num_rows, res)
+    }
+
+    #[test]
+    fn categorical_groups_must_be_one_hot() {
+        let (rows, res) = mock_categorical(vec![(0, 3)], &[0.0, 1.0, 0.0]);
+        assert!(res.is_ok(), "{:?}", res);
+        let (unconstrained_rows, _) = mock_categorical(vec![], &[0.0, 1.0, 0.0]);
+        assert!(
+            rows - unconstrained_rows <= 16,
+            "{} rows",
+            rows - unconstrained_rows
+        );
+
+        // two hot
+        assert!(mock_categorical(vec![(0, 3)], &[1.0, 1.0, 0.0]).1.is_err());
+        // sums to one without being 0 or 1
+        assert!(mock_categorical(vec![(0, 3)], &[0.5, 0.5, 0.0]).1.is_err());
+    }
+
+    #[test]
+    fn proofs_open_committed_dataset_rows() {
+        let run_args = RunArgs {
+            dataset_depth: Some(4),
+            ..RunArgs::default()
+        };
+        let mut circuit = GraphCircuit::from_run_args(
+            &run_args,
+            std::path::Path::new("examples/onnx/1l_mlp/network.onnx"),
+        )
+        .unwrap();
+        // a toy dataset filling the tree's 16 leaves
+        let rows = (0..16)
+            .map(|i| vec![i as f64 / 16.0, 1.0 - i as f64 / 8.0, (i % 4) as f64 / 4.0])
+            .collect_vec();
+        let witnesses = rows.iter().map(|row| witness(&circuit, row)).collect_vec();
+        let dataset =
+            DatasetCommitment::new(&witnesses.iter().map(|w| w.dataset_row()).collect_vec(), 4)
+                .unwrap();
+        let settings = circuit.settings().clone();
+        // the 4 public outputs, then the root and the row's index
+        assert_eq!(settings.num_instances(), 4 + 2);
+        let (params, pk) = setup(&circuit);
+
+        for index in [3, 12] {
+            let mut witness = witnesses[index].clone();
+            assert!(matches!(
+                circuit.prepare_public_inputs(&witness),
+                Err(GraphError::MissingDatasetOpening)
+            ));
+            // the opening is checked against the input
+            assert!(matches!(
+                witness.set_dataset_opening(&dataset, (index + 1) % 16),
+                Err(GraphError::DatasetRowMismatch(..))
+            ));
+            witness.set_dataset_opening(&dataset, index).unwrap();
+            let public_inputs = load_witness(&mut circuit, &witness);
+            assert_eq!(
+                settings
+                    .check_dataset_root(&public_inputs, dataset.root)
+                    .unwrap(),
+                index
+            );
+            let snark = prove(&circuit, public_inputs, &params, &pk);
+            assert!(verify(&snark, &params, pk.get_vk()).is_ok());
+
+            // the proof can't be passed off as opening another index or dataset
+            let [root_idx, index_idx] = settings.dataset_instances().unwrap();
+            let mut other_index = snark.clone();
+            other_index.instances[0][index_idx] = Fp::from(index as u64 ^ 1);
+            assert!(verify(&other_index, &params, pk.get_vk()).is_err());
+            let mut other_root = snark;
+            other_root.instances[0][root_idx] = Fp::from(42);
+            assert!(verify(&other_root, &params, pk.get_vk()).is_err());
+        }
     }
 }
@@ -1,26 +1,32 @@
 use super::errors::GraphError;
-use super::extract_const_quantized_values;
 use super::node::*;
 use super::scale_to_multiplier;
 use super::vars::*;
 use super::GraphSettings;
+use super::Interrupt;
 use crate::circuit::hybrid::HybridOp;
+use crate::circuit::ops::estimate::Estimator;
 use crate::circuit::region::ConstantsMap;
 use crate::circuit::region::RegionCtx;
 use crate::circuit::region::RegionSettings;
 use crate::circuit::table::Range;
+use crate::circuit::witness_log;
+use crate::circuit::Constant;
 use crate::circuit::Input;
 use crate::circuit::InputType;
 use crate::circuit::Unknown;
-use crate::fieldutils::IntegerRep;
+use crate::fieldutils::{integer_rep_to_felt, IntegerRep};
 use crate::tensor::ValType;
 use crate::{
-    circuit::{lookup::LookupOp, BaseConfig as PolyConfig, CheckMode, Op},
+    circuit::{lookup::LookupOp, BaseConfig as PolyConfig, CheckMode, Op, Tolerance},
     tensor::{Tensor, ValTensor},
     RunArgs,
 };
 use halo2curves::bn256::Fr as Fp;
 
+#[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
+use super::canonical_node_order;
+use super::dataset::DatasetPath;
 #[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
 use super::input::GraphData;
 #[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
@@ -71,6 +77,12 @@ pub struct ForwardResult {
     pub min_lookup_inputs: IntegerRep,
     /// The max range check size
     pub max_range_size: IntegerRep,
+    /// The min and max input to each lookup operation.
+    pub lookup_ranges: Vec<(LookupOp, Range)>,
+    /// The largest output magnitude of each node.
+    pub node_output_max: Vec<(usize, IntegerRep)>,
+    /// Every node's outputs, if they were recorded.
+    pub trace: Option<ForwardTrace>,
 }
 
 impl From<DummyPassRes> for ForwardResult {
@@ -80,8 +92,125 @@ impl From<DummyPassRes> for ForwardResult {
             max_lookup_inputs: res.max_lookup_inputs,
             min_lookup_inputs: res.min_lookup_inputs,
             max_range_size: res.max_range_size,
+            lookup_ranges: res.lookup_ranges,
+            node_output_max: res.node_output_max,
+            trace: None,
+        }
+    }
+}
+
+/// The outputs of a single node in a forward pass.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct NodeTrace {
+    /// The node's index in the graph.
+    pub idx: usize,
+    /// The node's op.
+    pub op: String,
+    /// The scales of the node's outputs.
+    pub scales: Vec<crate::Scale>,
+    /// The node's outputs, dequantized.
+    pub outputs: Vec<Tensor<f32>>,
+    /// The node's outputs, as laid out.
+    pub quantized: Vec<Tensor<IntegerRep>>,
+    /// The largest absolute difference between the outputs and those of float execution, once compared.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_divergence: Option<f32>,
+}
+
+/// Every node's outputs in a quantized forward pass, keyed by node name.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct ForwardTrace {
+    /// The traced nodes.
+    pub nodes: BTreeMap<String, NodeTrace>,
+}
+
+impl ForwardTrace {
+    /// Records the outputs of every node in `results` whose values are known.
+    fn record(
+        graph: &ParsedNodes,
+        results: &BTreeMap<usize, Vec<ValTensor<Fp>>>,
+    ) -> Result<Self, GraphError> {
+        let mut nodes = BTreeMap::new();
+        for (idx, values) in results {
+            let node = match graph.nodes.get(idx) {
+                Some(node) => node,
+                None => continue,
+            };
+            if values.iter().any(|v| v.any_unknowns().unwrap_or(true)) {
+                continue;
+            }
+            let scales = node.out_scales();
+            let quantized = values
+                .iter()
+                .map(|v| v.int_evals())
+                .collect::<Result<Vec<_>, _>>()?;
+            let outputs = quantized
+                .iter()
+                .zip(scales.iter())
+                .map(|(q, scale)| {
+                    let multiplier = scale_to_multiplier(*scale);
+                    q.map(|x| (x as f64 / multiplier) as f32)
+                })
+                .collect();
+            let name = match node {
+                NodeType::Node(n) if !n.name.is_empty() => n.name.clone(),
+                _ => idx.to_string(),
+            };
+            nodes.insert(
+                name,
+                NodeTrace {
+                    idx: *idx,
+                    op: node.as_str(),
+                    scales,
+                    outputs,
+                    quantized,
+                    max_divergence: None,
+                },
+            );
+        }
+        Ok(Self { nodes })
+    }
+
+    /// Sets the `max_divergence` of each node from the `float` outputs of the node with the same name.
+    /// Outputs whose lengths differ are not compared.
+    pub fn compare(&mut self, float: &BTreeMap<String, Vec<Tensor<f32>>>) {
+        for (name, node) in self.nodes.iter_mut() {
+            let expected = match float.get(name) {
+                Some(expected) => expected,
+                None => continue,
+            };
+            for (got, want) in node.outputs.iter().zip(expected) {
+                if got.len() != want.len() {
+                    continue;
+                }
+                let divergence = got
+                    .iter()
+                    .zip(want.iter())
+                    .fold(0.0f32, |max, (g, w)| max.max((g - w).abs()));
+                node.max_divergence = Some(
+                    node.max_divergence
+                        .map_or(divergence, |m| m.max(divergence)),
+                );
+            }
         }
     }
+
+    /// Writes the trace to `path` as json.
+    pub fn save(&self, path: &std::path::Path) -> Result<(), GraphError> {
+        let file = std::fs::File::create(path).map_err(|e| {
+            GraphError::ReadWriteFileError(path.display().to_string(), e.to_string())
+        })?;
+        serde_json::to_writer(std::io::BufWriter::new(file), self)?;
+        Ok(())
+    }
+
+    /// Loads a trace written by [ForwardTrace::save].
+    pub fn load(path: &std::path::Path) -> Result<Self, GraphError> {
+        let file = std::fs::File::open(path).map_err(|e| {
+            GraphError::ReadWriteFileError(path.display().to_string(), e.to_string())
+        })?;
+        Ok(serde_json::from_reader(std::io::BufReader::new(file))?)
+    }
 }
 
 /// A circuit configuration for the entirety of a model loaded from an Onnx file.
@@ -96,6 +225,56 @@ pub struct ModelConfig {
 /// Representation of execution graph
 pub type NodeGraph = BTreeMap<usize, NodeType>;
 
+/// The cells the nodes of a model are estimated to take up, see [Model::estimate_resources].
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct ResourceEstimate {
+    /// cells over the nodes that could be estimated
+    pub assignments: usize,
+    /// rows those cells take up
+    pub num_rows: usize,
+    /// nodes that couldn't be estimated
+    pub unestimated: Vec<usize>,
+    /// cells of each node that could be estimated
+    pub nodes: Vec<(usize, usize)>,
+    /// most node output values held at once while laying out, see [Model::peak_live_values]
+    pub peak_live_values: usize,
+    /// constants that had values clipped when quantized, the worst first, see [Model::clip_rates]
+    pub clip_rates: Vec<ClipRate>,
+}
+
+/// How many of a constant's values were clipped to the decomposition range when it was parsed, or
+/// of an input's when it was loaded (see [super::GraphCircuit::clip_rates]).
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct ClipRate {
+    /// the name of the constant's node, or of the input
+    pub node: String,
+    /// values clipped
+    pub clipped: usize,
+    /// values in the constant
+    pub total: usize,
+}
+
+impl ClipRate {
+    /// The fraction of the constant's values that were clipped
+    pub fn rate(&self) -> f64 {
+        self.clipped as f64 / self.total.max(1) as f64
+    }
+}
+
+/// Warns of each of `rates`, whose clipped values quietly skew the model's outputs in proofs
+/// that still verify.
+pub(crate) fn report_clipping<'a>(rates: impl Iterator<Item = &'a ClipRate>) {
+    for clip in rates {
+        log::warn!(
+            "{} clips {} of its {} values ({:.2}%) to the decomposition range, consider a lower scale or a larger decomposition",
+            clip.node,
+            clip.clipped,
+            clip.total,
+            clip.rate() * 100.0
+        );
+    }
+}
+
 /// A struct for loading from an Onnx file and converting a computational graph to a circuit.
 #[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
 pub struct DummyPassRes {
@@ -125,6 +304,12 @@ pub struct DummyPassRes {
     pub min_lookup_inputs: IntegerRep,
     /// min range check
     pub max_range_size: IntegerRep,
+    /// min and max input of each lookup
+    pub lookup_ranges: Vec<(LookupOp, Range)>,
+    /// largest output magnitude of each node, for nodes whose outputs are known
+    pub node_output_max: Vec<(usize, IntegerRep)>,
+    /// most node output values held at once while laying out
+    pub peak_live_values: usize,
     /// outputs
     pub outputs: Vec<Tensor<Fp>>,
 }
@@ -359,7 +544,7 @@ impl NodeType {
     pub fn opkind(&self) -> SupportedOp {
         match self {
             NodeType::Node(n) => n.opkind.clone(),
-            NodeType::SubGraph { .. } => SupportedOp::Unknown(Unknown),
+            NodeType::SubGraph { .. } => SupportedOp::Unknown(Unknown::default()),
         }
     }
 
@@ -370,6 +555,22 @@ impl NodeType {
             NodeType::SubGraph { .. } => false,
         }
     }
+
+    /// check if it is a relu, however it's laid out
+    pub fn is_relu(&self) -> bool {
+        matches!(
+            self,
+            NodeType::Node(Node {
+                opkind: SupportedOp::Linear(
+                    crate::circuit::poly::PolyOp::LeakyReLU {
+                        slope: crate::circuit::utils::F32(0.0),
+                        ..
+                    } | crate::circuit::poly::PolyOp::ReLU
+                ),
+                ..
+            })
+        )
+    }
 }
 
 #[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
@@ -382,6 +583,39 @@ pub struct ParsedNodes {
 }
 
 impl ParsedNodes {
+    /// Moves every node to the index `order` maps it to, rewriting all references to it.
+    #[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
+    fn relabel(&mut self, order: &BTreeMap<usize, usize>) -> Result<(), GraphError> {
+        let new_idx = |idx: usize| order.get(&idx).copied().ok_or(GraphError::MissingNode(idx));
+        let relabel_outlets = |outlets: &mut Vec<Outlet>| {
+            outlets.iter_mut().try_for_each(|(idx, _)| {
+                *idx = new_idx(*idx)?;
+                Ok::<_, GraphError>(())
+            })
+        };
+
+        let mut nodes = BTreeMap::new();
+        for (idx, mut node) in std::mem::take(&mut self.nodes) {
+            match &mut node {
+                NodeType::Node(n) => {
+                    n.idx = new_idx(n.idx)?;
+                    relabel_outlets(&mut n.inputs)?;
+                }
+                NodeType::SubGraph { inputs, idx, .. } => {
+                    *idx = new_idx(*idx)?;
+                    relabel_outlets(inputs)?;
+                }
+            }
+            nodes.insert(new_idx(idx)?, node);
+        }
+        self.nodes = nodes;
+
+        for idx in self.inputs.iter_mut() {
+            *idx = new_idx(*idx)?;
+        }
+        relabel_outlets(&mut self.outputs)
+    }
+
     /// Returns the number of the computational graph's inputs
     pub fn num_inputs(&self) -> usize {
         let input_nodes = self.inputs.iter();
@@ -423,6 +657,36 @@ impl ParsedNodes {
         Ok(inputs)
     }
 
+    /// Returns the onnx names of the computational graph's inputs
+    pub fn input_names(&self) -> Result<Vec<String>, GraphError> {
+        self.inputs
+            .iter()
+            .map(|idx| match self.nodes.get(idx) {
+                Some(NodeType::Node(n)) => Ok(n.name.clone()),
+                _ => Err(GraphError::MissingNode(*idx)),
+            })
+            .collect()
+    }
+
+    /// Returns the names of the nodes producing the computational graph's outputs, suffixed with
+    /// the outlet for any but a node's first output
+    pub fn output_names(&self) -> Result<Vec<String>, GraphError> {
+        self.outputs
+            .iter()
+            .map(|(idx, outlet)| {
+                let name = match self.nodes.get(idx) {
+                    Some(NodeType::Node(n)) => n.name.clone(),
+                    Some(NodeType::SubGraph { .. }) => format!("subgraph_{}", idx),
+                    None => return Err(GraphError::MissingNode(*idx)),
+                };
+                Ok(match outlet {
+                    0 => name,
+                    _ => format!("{}:{}", name, outlet),
+                })
+            })
+            .collect()
+    }
+
     /// Returns the number of the computational graph's outputs
     pub fn num_outputs(&self) -> usize {
         let output_nodes = self.outputs.iter();
@@ -475,6 +739,79 @@ impl ParsedNodes {
     }
 }
 
+/// The assigned inputs and outputs of a [Model] that has been laid out.
+#[derive(Clone, Debug)]
+pub struct ModelLayout {
+    /// The inputs as assigned by the model's input nodes, which callers can constrain to be
+    /// equal to other cells (e.g the opening of a commitment to the input).
+    pub inputs: Vec<ValTensor<Fp>>,
+    /// The outputs of the model.
+    pub outputs: Vec<ValTensor<Fp>>,
+}
+
+/// The output shapes of a node in a [ShapedModel].
+#[derive(Clone, Debug, PartialEq)]
+pub struct NodeShape {
+    /// The index of the node in the graph.
+    pub idx: usize,
+    /// The name of the onnx op.
+    pub op: String,
+    /// The shapes of the node's outputs.
+    pub out_dims: Vec<Vec<usize>>,
+}
+
+/// A model whose structure and shapes have been resolved, but whose constants have not been quantized yet.
+#[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
+#[derive(Debug, Clone)]
+pub struct ShapedModel {
+    model: Graph<TypedFact, Box<dyn TypedOp>>,
+    symbol_values: SymbolValues,
+    run_args: RunArgs,
+}
+
+#[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
+impl ShapedModel {
+    /// The output shapes of every node, indexed and ordered as in the parsed [Model].
+    pub fn node_shapes(&self) -> Result<Vec<NodeShape>, GraphError> {
+        let order = canonical_node_order(&self.model);
+        let mut shapes = self
+            .model
+            .nodes()
+            .iter()
+            .map(|n| {
+                Ok(NodeShape {
+                    idx: order[&n.id],
+                    op: n.op().name().to_string(),
+                    out_dims: crate::graph::node_output_shapes(n, &self.symbol_values)?,
+                })
+            })
+            .collect::<Result<Vec<_>, GraphError>>()?;
+        shapes.sort_by_key(|s| s.idx);
+        Ok(shapes)
+    }
+
+    /// Converts the nodes into circuit ops and quantizes their constants.
+    pub fn quantize(&self) -> Result<Model, GraphError> {
+        let visibility = VarVisibility::from_args(&self.run_args)?;
+        let graph = Model::parse_tract_model(
+            &self.model,
+            &self.symbol_values,
+            &self.run_args,
+            &visibility,
+        )?;
+
+        let mut om = Model { graph, visibility };
+        if let Some((class, threshold)) = self.run_args.threshold_check {
+            om.add_threshold_check(class, threshold)?;
+        }
+
+        debug!("\n {}", om.table_nodes());
+        om.warn_clipping(self.run_args.clip_warn_threshold);
+
+        Ok(om)
+    }
+}
+
 impl Model {
     /// Creates a `Model` from a specified path to an Onnx file.
     /// # Arguments
@@ -482,13 +819,28 @@ impl Model {
     /// * `run_args` - [RunArgs]
     #[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
     pub fn new(reader: &mut dyn std::io::Read, run_args: &RunArgs) -> Result<Self, GraphError> {
+        Self::read(reader, None, run_args)
+    }
+
+    /// Creates a `Model` from an Onnx file read from `model_dir`, against which the locations of
+    /// tensors stored in external data files are resolved.
+    #[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
+    fn read(
+        reader: &mut dyn std::io::Read,
+        model_dir: Option<&std::path::Path>,
+        run_args: &RunArgs,
+    ) -> Result<Self, GraphError> {
         let visibility = VarVisibility::from_args(run_args)?;
 
-        let graph = Self::load_onnx_model(reader, run_args, &visibility)?;
+        let graph = Self::load_onnx_model(reader, model_dir, run_args, &visibility)?;
 
-        let om = Model { graph, visibility };
+        let mut om = Model { graph, visibility };
+        if let Some((class, threshold)) = run_args.threshold_check {
+            om.add_threshold_check(class, threshold)?;
+        }
 
         debug!("\n {}", om.table_nodes());
+        om.warn_clipping(run_args.clip_warn_threshold);
 
         Ok(om)
     }
@@ -520,13 +872,66 @@ impl Model {
         Ok(result)
     }
 
+    /// Appends a check that element `class` of the model's output exceeds `threshold`, see
+    /// [RunArgs::threshold_check]. The comparison bit replaces the output, so only it is exposed
+    /// by the output visibility. The threshold is a constant of the circuit, fixed at setup.
+    pub fn add_threshold_check(&mut self, class: usize, threshold: f64) -> Result<(), GraphError> {
+        let (out_idx, outlet) = match self.graph.outputs[..] {
+            [output] => output,
+            _ => {
+                return Err(GraphError::InvalidDims(
+                    self.graph.outputs.len(),
+                    "threshold checks require a single model output".to_string(),
+                ))
+            }
+        };
+
+        let out_node = self
+            .graph
+            .nodes
+            .get(&out_idx)
+            .ok_or(GraphError::MissingNode(out_idx))?;
+        let out_len = out_node.out_dims()[outlet].iter().product::<usize>();
+        if class >= out_len {
+            return Err(GraphError::InvalidDims(
+                out_idx,
+                format!("threshold class {} for output of length {}", class, out_len),
+            ));
+        }
+
+        let threshold = super::quantize_float(&threshold, 0.0, out_node.out_scales()[outlet])?;
+
+        let idx = self.graph.nodes.keys().max().map_or(0, |i| i + 1);
+        let node = Node {
+            idx,
+            opkind: SupportedOp::Hybrid(HybridOp::Threshold { class, threshold }),
+            inputs: vec![(out_idx, outlet)],
+            out_dims: vec![1],
+            out_scale: 0,
+            num_uses: 1,
+            name: "threshold".to_string(),
+        };
+        self.graph.nodes.insert(idx, NodeType::Node(node));
+        self.graph.outputs = vec![(idx, 0)];
+
+        Ok(())
+    }
+
     /// Generate model parameters for the circuit
     pub fn gen_params(
         &self,
         run_args: &RunArgs,
         check_mode: CheckMode,
     ) -> Result<GraphSettings, GraphError> {
-        let instance_shapes = self.instance_shapes()?;
+        let mut instance_shapes = self.instance_shapes()?;
+        // an opened dataset row's root and index come after the outputs
+        if run_args.dataset_depth.is_some() {
+            instance_shapes.extend([vec![1], vec![1]]);
+        }
+        // the context hash takes the last instance
+        if run_args.bind_context {
+            instance_shapes.push(vec![1]);
+        }
         #[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
         debug!(
             "{} {} {}",
@@ -557,11 +962,17 @@ impl Model {
             })
             .collect::<Result<Vec<_>, GraphError>>()?;
 
+        let start_time = instant::Instant::now();
         let res = self.dummy_layout(
             run_args,
             &inputs,
             RegionSettings::all_false(run_args.decomp_base, run_args.decomp_legs),
         )?;
+        debug!(
+            "laid out {} rows in {:?}",
+            res.num_rows,
+            start_time.elapsed()
+        );
 
         // if we're using percentage tolerance, we need to add the necessary range check ops for it.
 
@@ -574,6 +985,9 @@ impl Model {
             required_lookups: res.lookup_ops.into_iter().collect(),
             max_dynamic_input_len: res.max_dynamic_input_len,
             required_range_checks: res.range_checks.into_iter().collect(),
+            // only known once calibrated against real data
+            lookup_ranges: vec![],
+            output_max: vec![],
             model_output_scales: self.graph.get_output_scales()?,
             model_input_scales: self.graph.get_input_scales(),
             num_dynamic_lookups: res.num_dynamic_lookups,
@@ -606,13 +1020,37 @@ impl Model {
         model_inputs: &[Tensor<Fp>],
         run_args: &RunArgs,
         region_settings: RegionSettings,
+    ) -> Result<ForwardResult, GraphError> {
+        self.traced_forward(model_inputs, run_args, region_settings, false)
+    }
+
+    /// Runs a forward pass like [Model::forward], also recording every node's outputs in [ForwardResult::trace].
+    pub fn forward_with_trace(
+        &self,
+        model_inputs: &[Tensor<Fp>],
+        run_args: &RunArgs,
+        region_settings: RegionSettings,
+    ) -> Result<ForwardResult, GraphError> {
+        self.traced_forward(model_inputs, run_args, region_settings, true)
+    }
+
+    fn traced_forward(
+        &self,
+        model_inputs: &[Tensor<Fp>],
+        run_args: &RunArgs,
+        region_settings: RegionSettings,
+        record_trace: bool,
     ) -> Result<ForwardResult, GraphError> {
         let valtensor_inputs: Vec<ValTensor<Fp>> = model_inputs
             .iter()
             .map(|x| x.map(|elem| ValType::Value(Value::known(elem))).into())
             .collect();
-        let res = self.dummy_layout(run_args, &valtensor_inputs, region_settings)?;
-        Ok(res.into())
+        let (res, trace) =
+            self.dummy_pass(run_args, &valtensor_inputs, region_settings, record_trace)?;
+        Ok(ForwardResult {
+            trace,
+            ..res.into()
+        })
     }
 
     /// Loads an Onnx model from a specified path.
@@ -623,11 +1061,35 @@ impl Model {
     #[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
     fn load_onnx_using_tract(
         reader: &mut dyn std::io::Read,
+        model_dir: Option<&std::path::Path>,
         run_args: &RunArgs,
     ) -> Result<TractResult, GraphError> {
         use tract_onnx::tract_hir::internal::GenericFactoid;
 
-        let mut model = tract_onnx::onnx().model_for_read(reader)?;
+        let mut bytes = vec![];
+        reader
+            .read_to_end(&mut bytes)
+            .map_err(|e| GraphError::ReadWriteFileError("model".to_string(), e.to_string()))?;
+        let check = |graph: &[u8]| -> Result<Option<Vec<u8>>, GraphError> {
+            super::validation::check_graph(graph).map(|_| None)
+        };
+        let mut passes: Vec<super::proto::GraphPass> = vec![
+            &check,
+            &super::control_flow::inline_constant_ifs,
+            &super::qlinear::lower_qlinear,
+            &super::sklearn::lower_sklearn,
+        ];
+        if run_args.fold_batch_norms {
+            passes.push(&super::linear_fusion::fold_batch_norms);
+        }
+        if run_args.fuse_linear {
+            passes.push(&super::linear_fusion::fuse_linear);
+        }
+        // the passes see the model as stored, so tensors kept in external data files stay there
+        // through every rewrite, and are read into the model once, for tract
+        let bytes = super::proto::rewrite_graph(bytes, &passes)?;
+        let bytes = super::external_data::inline_external_data(bytes, model_dir)?;
+        let mut model = tract_onnx::onnx().model_for_read(&mut &bytes[..])?;
 
         let variables: std::collections::HashMap<String, usize> =
             std::collections::HashMap::from_iter(run_args.variables.clone());
@@ -678,36 +1140,62 @@ impl Model {
     #[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
     fn load_onnx_model(
         reader: &mut dyn std::io::Read,
+        model_dir: Option<&std::path::Path>,
         run_args: &RunArgs,
         visibility: &VarVisibility,
     ) -> Result<ParsedNodes, GraphError> {
         let start_time = instant::Instant::now();
 
-        let (model, symbol_values) = Self::load_onnx_using_tract(reader, run_args)?;
+        let (model, symbol_values) = Self::load_onnx_using_tract(reader, model_dir, run_args)?;
 
+        let parsed_nodes = Self::parse_tract_model(&model, &symbol_values, run_args, visibility)?;
+
+        let duration = start_time.elapsed();
+        trace!("model loading took: {:?}", duration);
+
+        Ok(parsed_nodes)
+    }
+
+    /// Converts a tract graph into ezkl nodes, quantizing its constants along the way.
+    #[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
+    fn parse_tract_model(
+        model: &Graph<TypedFact, Box<dyn TypedOp>>,
+        symbol_values: &SymbolValues,
+        run_args: &RunArgs,
+        visibility: &VarVisibility,
+    ) -> Result<ParsedNodes, GraphError> {
         let scales = VarScales::from_args(run_args);
-        let nodes = Self::nodes_from_graph(
-            &model,
+        let custom_tables = match &run_args.custom_tables {
+            Some(path) => super::utilities::load_custom_tables(path)?,
+            None => BTreeMap::new(),
+        };
+        let graph = Self::nodes_from_graph(
+            model,
             run_args,
             &scales,
             visibility,
-            &symbol_values,
+            symbol_values,
+            &custom_tables,
             None,
             None,
         )?;
 
-        debug!("\n {}", model);
-
-        let parsed_nodes = ParsedNodes {
-            nodes,
-            inputs: model.inputs.iter().map(|o| o.node).collect(),
-            outputs: model.outputs.iter().map(|o| (o.node, o.slot)).collect(),
-        };
+        // conv and pooling layers have to agree on where the channel dim sits
+        let data_formats = graph
+            .nodes
+            .values()
+            .filter_map(|n| n.opkind().channels_last())
+            .collect::<HashSet<_>>();
+        if data_formats.len() > 1 {
+            return Err(GraphError::MisformedParams(
+                "graph mixes channels first (NCHW) and channels last (NHWC) conv / pool layers"
+                    .to_string(),
+            ));
+        }
 
-        let duration = start_time.elapsed();
-        trace!("model loading took: {:?}", duration);
+        debug!("\n {}", model);
 
-        Ok(parsed_nodes)
+        Ok(graph)
     }
 
     /// Formats nodes (including subgraphs) into tables !
@@ -746,25 +1234,29 @@ impl Model {
         format!("{} \n{}", string, table)
     }
 
-    /// Creates ezkl nodes from a tract graph
+    /// Creates ezkl nodes from a tract graph, indexed in [canonical_node_order].
     /// # Arguments
     /// * `graph` - A tract graph.
     /// * `run_args` - [RunArgs]
     /// * `visibility` - Which inputs to the model are public and private (params, inputs, outputs) using [VarVisibility].
+    /// * `custom_tables` - The lookup tables loaded from [RunArgs::custom_tables], keyed by op name.
     /// * `input_scales` - The scales of the model's inputs.
 
     #[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
+    #[allow(clippy::too_many_arguments)]
     pub fn nodes_from_graph(
         graph: &Graph<TypedFact, Box<dyn TypedOp>>,
         run_args: &RunArgs,
         scales: &VarScales,
         visibility: &VarVisibility,
         symbol_values: &SymbolValues,
+        custom_tables: &BTreeMap<String, crate::circuit::lookup::CustomTable>,
         override_input_scales: Option<Vec<crate::Scale>>,
         override_output_scales: Option<HashMap<usize, crate::Scale>>,
-    ) -> Result<BTreeMap<usize, NodeType>, GraphError> {
+    ) -> Result<ParsedNodes, GraphError> {
         use crate::graph::node_output_shapes;
 
+        let masks = Self::additive_masks(graph);
         let mut nodes = BTreeMap::<usize, NodeType>::new();
         let mut input_idx = 0;
         for (i, n) in graph.nodes.iter().enumerate() {
@@ -846,22 +1338,17 @@ impl Model {
                         }
                     }
 
-                    let subgraph_nodes = Self::nodes_from_graph(
+                    let subgraph = Self::nodes_from_graph(
                         &model,
                         run_args,
                         scales,
                         visibility,
                         symbol_values,
+                        custom_tables,
                         Some(input_scales.clone()),
                         Some(output_scale_override),
                     )?;
 
-                    let subgraph = ParsedNodes {
-                        nodes: subgraph_nodes,
-                        inputs: model.inputs.iter().map(|o| o.node).collect(),
-                        outputs: model.outputs.iter().map(|o| (o.node, o.slot)).collect(),
-                    };
-
                     let om = Model {
                         graph: subgraph,
                         visibility: visibility.clone(),
@@ -898,8 +1385,16 @@ impl Model {
                     );
                 }
                 None => {
-                    let mut n =
-                        Node::new(n.clone(), &mut nodes, scales, i, symbol_values, run_args)?;
+                    let mut n = Node::new(
+                        n.clone(),
+                        &mut nodes,
+                        scales,
+                        i,
+                        symbol_values,
+                        run_args,
+                        &masks,
+                        custom_tables,
+                    )?;
                     if let Some(ref scales) = override_input_scales {
                         if let Some(inp) = n.opkind.get_input() {
                             let scale = scales[input_idx];
@@ -937,82 +1432,831 @@ impl Model {
                 }
             }
         }
-        Self::remove_unused_nodes(&mut nodes);
 
-        Ok(nodes)
+        let mut parsed = ParsedNodes {
+            nodes,
+            inputs: graph.inputs.iter().map(|o| o.node).collect(),
+            outputs: graph.outputs.iter().map(|o| (o.node, o.slot)).collect(),
+        };
+        // relabel before the passes below, so which of several equivalent nodes they keep
+        // doesn't depend on tract's ids either
+        parsed.relabel(&canonical_node_order(graph))?;
+
+        let output_nodes = parsed.outputs.iter().map(|o| o.0).collect_vec();
+        Self::fuse_einsum_bias(&mut parsed.nodes, &output_nodes);
+        Self::fold_crop_into_conv(&mut parsed.nodes, &output_nodes);
+        Self::fold_scalar_into_softmax(&mut parsed.nodes, &output_nodes);
+        Self::fuse_mish(&mut parsed.nodes, &output_nodes);
+        Self::dedup_constants(&mut parsed.nodes, &output_nodes);
+        Self::remove_unused_nodes(&mut parsed.nodes);
+
+        Ok(parsed)
     }
 
+    /// The constants added to the input of a `Softmax`, as additive attention masks are. These are
+    /// the only constants that may hold values past the decomposition range (`-inf`, `-1e9` and the
+    /// like), which saturate to a value the softmax maps to 0.
     #[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
-    /// Removes all nodes that are consts with 0 uses
-    fn remove_unused_nodes(nodes: &mut BTreeMap<usize, NodeType>) {
-        // remove all nodes that are consts with 0 uses now
-        nodes.retain(|_, n| match n {
-            NodeType::Node(n) => match &mut n.opkind {
-                SupportedOp::Constant(c) => {
-                    c.empty_raw_value();
-                    n.num_uses > 0
-                }
-                _ => n.num_uses > 0,
-            },
-            NodeType::SubGraph { model, .. } => {
-                Self::remove_unused_nodes(&mut model.graph.nodes);
-                true
+    fn additive_masks(graph: &Graph<TypedFact, Box<dyn TypedOp>>) -> HashSet<usize> {
+        let mut masks = HashSet::new();
+        for node in graph.nodes.iter().filter(|n| n.op().name() == "Softmax") {
+            let add = &graph.nodes[node.inputs[0].node];
+            if add.op().name() != "Add" {
+                continue;
             }
-        });
+            masks.extend(
+                add.inputs
+                    .iter()
+                    .map(|i| i.node)
+                    .filter(|i| graph.nodes[*i].op().name() == "Const"),
+            );
+        }
+        masks
     }
 
     #[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
-    /// Run tract onnx model on sample data !
-    pub fn run_onnx_predictions(
-        run_args: &RunArgs,
-        model_path: &std::path::Path,
-        data_chunks: &[GraphData],
-        input_shapes: Vec<Vec<usize>>,
-    ) -> Result<Vec<Vec<Tensor<f32>>>, GraphError> {
-        use tract_onnx::tract_core::internal::IntoArcTensor;
+    /// Fuses `MatMul -> Add(const bias)` (how transformers export linear layers) into a single [PolyOp::Affine],
+    /// so the bias is added at the product's scale before any rebasing rather than as a separate node.
+    /// Adds whose bias input is rescaled, or whose output is rebased, are fused too.
+    /// The fused op takes over the add's index and the matmul is left with 0 uses.
+    fn fuse_einsum_bias(nodes: &mut BTreeMap<usize, NodeType>, output_nodes: &[usize]) {
+        fn as_node(nodes: &BTreeMap<usize, NodeType>, idx: usize) -> Option<&Node> {
+            match nodes.get(&idx) {
+                Some(NodeType::Node(n)) => Some(n),
+                _ => None,
+            }
+        }
 
-        let mut file = std::fs::File::open(model_path).map_err(|e| {
-            GraphError::ReadWriteFileError(model_path.display().to_string(), e.to_string())
-        })?;
+        // swaps a (possibly rebased) two operand einsum for an affine op
+        fn to_affine(op: &SupportedOp) -> Option<SupportedOp> {
+            match op {
+                SupportedOp::Linear(PolyOp::Einsum { equation })
+                    if equation
+                        .split("->")
+                        .next()
+                        .map_or(false, |operands| operands.split(',').count() == 2) =>
+                {
+                    Some(SupportedOp::Linear(PolyOp::Affine {
+                        equation: equation.clone(),
+                    }))
+                }
+                SupportedOp::RebaseScale(op) => {
+                    let mut op = op.clone();
+                    op.inner = Box::new(to_affine(&op.inner)?);
+                    Some(SupportedOp::RebaseScale(op))
+                }
+                _ => None,
+            }
+        }
 
-        let (model, _) = Model::load_onnx_using_tract(&mut file, run_args)?;
+        // an add, possibly with its inputs rescaled and its output rebased: the rebase and the
+        // inputs' multipliers
+        fn as_add(op: &SupportedOp) -> Option<(Option<&RebaseScale>, Vec<(usize, u128)>)> {
+            match op {
+                SupportedOp::Linear(PolyOp::Add) => Some((None, vec![])),
+                SupportedOp::Rescaled(op)
+                    if matches!(*op.inner, SupportedOp::Linear(PolyOp::Add)) =>
+                {
+                    Some((None, op.scale.clone()))
+                }
+                SupportedOp::RebaseScale(rebase) => match as_add(&rebase.inner)? {
+                    (None, multipliers) => Some((Some(rebase), multipliers)),
+                    _ => None,
+                },
+                _ => None,
+            }
+        }
 
-        let datum_types: Vec<DatumType> = model
-            .input_outlets()?
-            .iter()
-            .map(|o| model.node(o.node).outputs[o.slot].fact.datum_type)
-            .collect();
+        let mut fusions = vec![];
+        for (idx, node) in nodes.iter() {
+            let (add, (rebase, multipliers)) = match node {
+                NodeType::Node(n) => match as_add(&n.opkind) {
+                    Some(add) => (n, add),
+                    None => continue,
+                },
+                _ => continue,
+            };
+            if add.inputs.len() != 2 {
+                continue;
+            }
+            for (matmul_input, bias_input) in [(0, 1), (1, 0)] {
+                // the bias is requantized at the product's scale below, but a rescaled product
+                // would leave the add at a higher scale than the fused op
+                if multipliers
+                    .iter()
+                    .any(|(i, multiplier)| *i == matmul_input && *multiplier != 1)
+                {
+                    continue;
+                }
+                let (matmul_idx, bias_idx) = (add.inputs[matmul_input], add.inputs[bias_input]);
+                let (matmul, bias) =
+                    match (as_node(nodes, matmul_idx.0), as_node(nodes, bias_idx.0)) {
+                        (Some(m), Some(b)) => (m, b),
+                        _ => continue,
+                    };
+                let affine = match (to_affine(&matmul.opkind), rebase) {
+                    (Some(op), None) => op,
+                    (Some(op), Some(rebase)) => {
+                        let mut rebase = rebase.clone();
+                        rebase.inner = Box::new(op);
+                        SupportedOp::RebaseScale(rebase)
+                    }
+                    (None, _) => continue,
+                };
+                let bias_is_const =
+                    matches!(&bias.opkind, SupportedOp::Constant(c) if !c.raw_values.is_empty());
+                // the bias must broadcast onto the matmul output without growing it
+                let broadcasts =
+                    crate::tensor::get_broadcasted_shape(&matmul.out_dims, &bias.out_dims)
+                        .map_or(false, |shape| {
+                            shape == matmul.out_dims && shape == add.out_dims
+                        });
+                if matmul.num_uses == 1
+                    && !output_nodes.contains(&matmul_idx.0)
+                    && matmul.inputs.len() == 2
+                    && bias.num_uses == 1
+                    && bias_is_const
+                    && broadcasts
+                {
+                    fusions.push((*idx, matmul_idx.0, bias_idx.0, affine));
+                    break;
+                }
+            }
+        }
 
-        let runnable_model = model.into_runnable()?;
-        let mut outputs = vec![];
-        for chunk in data_chunks {
-            let result = runnable_model.run(chunk.to_tract_data(&input_shapes, &datum_types)?)?;
-            outputs.push(
-                result
-                    .into_iter()
-                    .map(|t| {
-                        crate::graph::utilities::extract_tensor_value(t.into_arc_tensor()).unwrap()
-                    })
-                    .collect(),
-            );
+        if fusions.is_empty() {
+            return;
         }
-        Ok(outputs)
-    }
 
-    /// Creates a `Model` from parsed run_args
-    /// # Arguments
-    /// * `params` - A [GraphSettings] struct holding parsed CLI arguments.
-    #[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
-    pub fn from_run_args(run_args: &RunArgs, model: &std::path::Path) -> Result<Self, GraphError> {
-        let mut file = std::fs::File::open(model).map_err(|e| {
-            GraphError::ReadWriteFileError(model.display().to_string(), e.to_string())
-        })?;
-        Model::new(&mut file, run_args)
-    }
+        debug!("fusing {} matmul + bias pairs", fusions.len());
 
-    /// Configures a model for the circuit
-    /// # Arguments
-    /// * `meta` - The constraint system.
+        for (add_idx, matmul_idx, bias_idx, affine) in fusions {
+            let (product_scale, mut inputs) = match as_node(nodes, matmul_idx) {
+                Some(matmul) => match &matmul.opkind {
+                    SupportedOp::RebaseScale(op) => (op.original_scale, matmul.inputs.clone()),
+                    _ => (matmul.out_scale, matmul.inputs.clone()),
+                },
+                None => continue,
+            };
+            inputs.push((bias_idx, 0));
+
+            // the bias is folded in before rebasing, so it has to sit at the product's scale
+            if let Some(NodeType::Node(bias)) = nodes.get_mut(&bias_idx) {
+                if let SupportedOp::Constant(c) = &mut bias.opkind {
+                    if c.rebase_scale(product_scale).is_err() {
+                        continue;
+                    }
+                }
+                bias.out_scale = product_scale;
+            }
+
+            if let Some(NodeType::Node(matmul)) = nodes.get_mut(&matmul_idx) {
+                matmul.num_uses = 0;
+            }
+            if let Some(NodeType::Node(add)) = nodes.get_mut(&add_idx) {
+                add.opkind = affine;
+                add.inputs = inputs;
+            }
+        }
+    }
+
+    #[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
+    /// Folds the spatial slices cropping a conv's input into the conv as negative padding, so the
+    /// crop is wire selection in the conv's layout. Only crops of sides the conv doesn't pad are
+    /// folded, the rest keep their slices. The folded slices are left with 0 uses.
+    fn fold_crop_into_conv(nodes: &mut BTreeMap<usize, NodeType>, output_nodes: &[usize]) {
+        // the conv's padding, looking through any rescaling of its inputs or output
+        fn conv_padding(op: &mut SupportedOp) -> Option<&mut Vec<(isize, isize)>> {
+            match op {
+                SupportedOp::Linear(PolyOp::Conv {
+                    padding,
+                    channels_last: false,
+                    ..
+                }) => Some(padding),
+                SupportedOp::RebaseScale(op) => conv_padding(&mut op.inner),
+                SupportedOp::Rescaled(op) => conv_padding(&mut op.inner),
+                _ => None,
+            }
+        }
+
+        let mut folds = vec![];
+        for (idx, node) in nodes.iter() {
+            let conv = match node {
+                NodeType::Node(n) => n,
+                _ => continue,
+            };
+            let mut padding = match conv_padding(&mut conv.opkind.clone()) {
+                Some(padding) => padding.clone(),
+                None => continue,
+            };
+            let mut source = conv.inputs[0];
+            let mut slices = vec![];
+            // walk back up a chain of single use slices over the spatial dims
+            while let Some(NodeType::Node(slice)) = nodes.get(&source.0) {
+                let (axis, start, end) = match &slice.opkind {
+                    SupportedOp::Linear(PolyOp::Slice { axis, start, end })
+                        if *axis >= 2 && axis - 2 < padding.len() =>
+                    {
+                        (*axis, *start, *end)
+                    }
+                    _ => break,
+                };
+                if slice.num_uses != 1 || output_nodes.contains(&source.0) {
+                    break;
+                }
+                let input = slice.inputs[0];
+                let dim = match nodes.get(&input.0) {
+                    Some(n) => n.out_dims()[input.1][axis],
+                    None => break,
+                };
+                let (before, after) = (start as isize, dim.saturating_sub(end) as isize);
+                let side = &mut padding[axis - 2];
+                // the conv pads the cropped input, so a crop can't cancel out its padding
+                if (before > 0 && side.0 > 0) || (after > 0 && side.1 > 0) {
+                    break;
+                }
+                side.0 -= before;
+                side.1 -= after;
+                slices.push(source.0);
+                source = input;
+            }
+            if !slices.is_empty() {
+                folds.push((*idx, source, padding, slices));
+            }
+        }
+
+        for (conv_idx, source, folded, slices) in folds {
+            debug!(
+                "folding the crop of slices {:?} into conv {}, padding it by {:?}",
+                slices, conv_idx, folded
+            );
+            for slice in slices {
+                if let Some(NodeType::Node(slice)) = nodes.get_mut(&slice) {
+                    slice.num_uses = 0;
+                }
+            }
+            if let Some(NodeType::Node(conv)) = nodes.get_mut(&conv_idx) {
+                conv.inputs[0] = source;
+                if let Some(padding) = conv_padding(&mut conv.opkind) {
+                    *padding = folded;
+                }
+            }
+        }
+    }
+
+    #[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
+    /// Folds a multiply by a constant scalar feeding a softmax (how temperature scaled logits
+    /// export, divides by a constant arriving as multiplies by its reciprocal) into the scale of the
+    /// softmax's exp table: multiplying by `c` reads the same integers at `1 / c` times the scale.
+    /// This saves the multiply and its rescale, and the softmax's output scale is left as is.
+    /// A fold that would take the exp table below a scale of 1 is skipped. The multiplies are left
+    /// with 0 uses.
+    fn fold_scalar_into_softmax(nodes: &mut BTreeMap<usize, NodeType>, output_nodes: &[usize]) {
+        // the softmax's input scale, looking through any rescaling of its output
+        fn softmax_input_scale(op: &mut SupportedOp) -> Option<&mut crate::circuit::utils::F32> {
+            match op {
+                SupportedOp::Hybrid(HybridOp::Softmax { input_scale, .. }) => Some(input_scale),
+                SupportedOp::RebaseScale(op) => softmax_input_scale(&mut op.inner),
+                _ => None,
+            }
+        }
+
+        // the input `node` multiplies by a constant scalar, the scalar and the constant's node
+        fn scalar_multiply(
+            nodes: &BTreeMap<usize, NodeType>,
+            node: &Node,
+            op: &SupportedOp,
+        ) -> Option<(Outlet, f64, Option<usize>)> {
+            match op {
+                // a multiply by 1 / 2^k, parsed as reading the input at k more bits of scale
+                SupportedOp::Linear(PolyOp::Identity {
+                    out_scale: Some(out_scale),
+                }) if node.inputs.len() == 1 => {
+                    let input = node.inputs[0];
+                    let in_scale = nodes.get(&input.0)?.out_scales()[input.1];
+                    Some((input, scale_to_multiplier(in_scale - out_scale), None))
+                }
+                SupportedOp::Linear(PolyOp::Mult) if node.inputs.len() == 2 => {
+                    let scalar = |outlet: Outlet| match nodes.get(&outlet.0) {
+                        Some(NodeType::Node(Node {
+                            opkind: SupportedOp::Constant(c),
+                            ..
+                        })) if c.raw_values.len() == 1 => Some(c.raw_values[0] as f64),
+                        _ => None,
+                    };
+                    match (scalar(node.inputs[0]), scalar(node.inputs[1])) {
+                        (Some(c), None) => Some((node.inputs[1], c, Some(node.inputs[0].0))),
+                        (None, Some(c)) => Some((node.inputs[0], c, Some(node.inputs[1].0))),
+                        _ => None,
+                    }
+                }
+                SupportedOp::RebaseScale(op) => scalar_multiply(nodes, node, &op.inner),
+                _ => None,
+            }
+        }
+
+        let mut folds = vec![];
+        for (idx, node) in nodes.iter() {
+            let softmax = match node {
+                NodeType::Node(n) if softmax_input_scale(&mut n.opkind.clone()).is_some() => n,
+                _ => continue,
+            };
+            let multiply_idx = softmax.inputs[0].0;
+            let multiply = match nodes.get(&multiply_idx) {
+                Some(NodeType::Node(n)) if n.num_uses == 1 && !output_nodes.contains(&n.idx) => n,
+                _ => continue,
+            };
+            let (source, scalar, constant) =
+                match scalar_multiply(nodes, multiply, &multiply.opkind) {
+                    Some(fold) => fold,
+                    None => continue,
+                };
+            let source_scale = match nodes.get(&source.0) {
+                Some(n) => n.out_scales()[source.1],
+                None => continue,
+            };
+            let folded = scale_to_multiplier(source_scale) / scalar;
+            if scalar.is_finite() && scalar > 0.0 && folded >= 1.0 {
+                folds.push((*idx, multiply_idx, constant, source, folded));
+            }
+        }
+
+        for (softmax_idx, multiply_idx, constant, source, folded) in folds {
+            debug!(
+                "folding multiply {} into softmax {}, reading its input at a scale of {}",
+                multiply_idx, softmax_idx, folded
+            );
+            if let Some(NodeType::Node(multiply)) = nodes.get_mut(&multiply_idx) {
+                multiply.num_uses = 0;
+            }
+            if let Some(NodeType::Node(constant)) = constant.and_then(|c| nodes.get_mut(&c)) {
+                constant.num_uses = constant.num_uses.saturating_sub(1);
+            }
+            if let Some(NodeType::Node(softmax)) = nodes.get_mut(&softmax_idx) {
+                softmax.inputs[0] = source;
+                if let Some(input_scale) = softmax_input_scale(&mut softmax.opkind) {
+                    *input_scale = (folded as f32).into();
+                }
+            }
+        }
+    }
+
+    #[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
+    /// Fuses `x * tanh(softplus(x))`, how Mish exports from frameworks without a Mish op, into a
+    /// single [LookupOp::Mish] table. Softplus is matched as an op of its own and as the
+    /// `ln(exp(x) + 1)` it's decomposed into, whose exp overflows the lookup range long before
+    /// Mish would, and whose three tables each add their rounding. Only multiplies rebased back to
+    /// the scale of `x` are fused, as the nodes downstream were built for that scale. The fused op
+    /// takes over the multiply's index and the nodes it replaces are left with 0 uses.
+    fn fuse_mish(nodes: &mut BTreeMap<usize, NodeType>, output_nodes: &[usize]) {
+        // the op, looking through any rescaling of its inputs or output
+        fn inner(op: &SupportedOp) -> &SupportedOp {
+            match op {
+                SupportedOp::RebaseScale(op) => inner(&op.inner),
+                SupportedOp::Rescaled(op) => inner(&op.inner),
+                op => op,
+            }
+        }
+
+        // the node at `outlet`, if nothing but the pattern being matched uses it
+        fn intermediate<'a>(
+            nodes: &'a BTreeMap<usize, NodeType>,
+            output_nodes: &[usize],
+            outlet: Outlet,
+        ) -> Option<&'a Node> {
+            match nodes.get(&outlet.0) {
+                Some(NodeType::Node(n)) if n.num_uses == 1 && !output_nodes.contains(&n.idx) => {
+                    Some(n)
+                }
+                _ => None,
+            }
+        }
+
+        // the input of the softplus ending at `node`, the other nodes it's made of and the
+        // constant one it adds, if decomposed
+        fn softplus(
+            nodes: &BTreeMap<usize, NodeType>,
+            output_nodes: &[usize],
+            node: &Node,
+        ) -> Option<(Outlet, Vec<usize>, Option<usize>)> {
+            match inner(&node.opkind) {
+                SupportedOp::Unknown(Unknown { op }) if op == "Softplus" => {
+                    Some((*node.inputs.first()?, vec![], None))
+                }
+                SupportedOp::Nonlinear(LookupOp::Ln { .. }) => {
+                    let add = intermediate(nodes, output_nodes, node.inputs[0])?;
+                    if !matches!(inner(&add.opkind), SupportedOp::Linear(PolyOp::Add))
+                        || add.inputs.len() != 2
+                    {
+                        return None;
+                    }
+                    let is_one = |outlet: Outlet| match nodes.get(&outlet.0) {
+                        Some(NodeType::Node(Node {
+                            opkind: SupportedOp::Constant(c),
+                            ..
+                        })) => !c.raw_values.is_empty() && c.raw_values.iter().all(|v| *v == 1.0),
+                        _ => false,
+                    };
+                    let (exp, one) = match (is_one(add.inputs[0]), is_one(add.inputs[1])) {
+                        (false, true) => (add.inputs[0], add.inputs[1]),
+                        (true, false) => (add.inputs[1], add.inputs[0]),
+                        _ => return None,
+                    };
+                    let exp = intermediate(nodes, output_nodes, exp)?;
+                    match inner(&exp.opkind) {
+                        SupportedOp::Nonlinear(LookupOp::Exp { .. }) => {
+                            Some((exp.inputs[0], vec![add.idx, exp.idx], Some(one.0)))
+                        }
+                        _ => None,
+                    }
+                }
+                _ => None,
+            }
+        }
+
+        let mut fusions = vec![];
+        for (idx, node) in nodes.iter() {
+            let multiply = match node {
+                NodeType::Node(n)
+                    if matches!(inner(&n.opkind), SupportedOp::Linear(PolyOp::Mult))
+                        && n.inputs.len() == 2 =>
+                {
+                    n
+                }
+                _ => continue,
+            };
+            let (a, b) = (multiply.inputs[0], multiply.inputs[1]);
+            for (x, t) in [(a, b), (b, a)] {
+                let tanh = match intermediate(nodes, output_nodes, t) {
+                    Some(n)
+                        if matches!(
+                            inner(&n.opkind),
+                            SupportedOp::Nonlinear(LookupOp::Tanh { .. })
+                        ) =>
+                    {
+                        n
+                    }
+                    _ => continue,
+                };
+                let softplus_node = match intermediate(nodes, output_nodes, tanh.inputs[0]) {
+                    Some(n) => n,
+                    None => continue,
+                };
+                let (mut replaced, constant) = match softplus(nodes, output_nodes, softplus_node) {
+                    Some((input, replaced, constant)) if input == x => (replaced, constant),
+                    _ => continue,
+                };
+                let x_scale = match nodes.get(&x.0) {
+                    Some(n) => n.out_scales()[x.1],
+                    None => continue,
+                };
+                if multiply.out_scale != x_scale {
+                    continue;
+                }
+                replaced.extend([tanh.idx, softplus_node.idx]);
+                fusions.push((*idx, x, x_scale, replaced, constant));
+                break;
+            }
+        }
+
+        for (multiply_idx, x, x_scale, replaced, constant) in fusions {
+            debug!(
+                "fusing nodes {:?} and multiply {} into a mish of {:?}",
+                replaced, multiply_idx, x
+            );
+            for idx in replaced {
+                if let Some(NodeType::Node(n)) = nodes.get_mut(&idx) {
+                    n.num_uses = 0;
+                }
+            }
+            if let Some(NodeType::Node(constant)) = constant.and_then(|c| nodes.get_mut(&c)) {
+                constant.num_uses = constant.num_uses.saturating_sub(1);
+            }
+            // x fed both the softplus and the multiply, and now only feeds the fused op
+            if let Some(NodeType::Node(x)) = nodes.get_mut(&x.0) {
+                x.num_uses = x.num_uses.saturating_sub(1);
+            }
+            if let Some(NodeType::Node(multiply)) = nodes.get_mut(&multiply_idx) {
+                multiply.inputs = vec![x];
+                multiply.opkind = SupportedOp::Nonlinear(LookupOp::Mish {
+                    scale: scale_to_multiplier(x_scale).into(),
+                });
+            }
+        }
+    }
+
+    #[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
+    /// Collapses constants with identical content (e.g tied weights) onto a single node.
+    /// Consumers are rewired to the first such node and the duplicates are left with 0 uses.
+    fn dedup_constants(nodes: &mut BTreeMap<usize, NodeType>, output_nodes: &[usize]) {
+        use std::hash::{Hash, Hasher};
+
+        // constants are compared by their raw values so that they don't have to be quantized
+        fn as_const(node: &NodeType) -> Option<&crate::circuit::Constant<Fp>> {
+            match node {
+                NodeType::Node(Node {
+                    opkind: SupportedOp::Constant(c),
+                    ..
+                }) if !c.raw_values.is_empty() => Some(c),
+                _ => None,
+            }
+        }
+
+        // content hash -> indices of the distinct constants with that hash
+        let mut seen: HashMap<u64, Vec<usize>> = HashMap::new();
+        // duplicate idx -> canonical idx
+        let mut remap: BTreeMap<usize, usize> = BTreeMap::new();
+
+        for (idx, node) in nodes.iter() {
+            if output_nodes.contains(idx) {
+                continue;
+            }
+            let constant = match as_const(node) {
+                Some(constant) => constant,
+                None => continue,
+            };
+
+            let mut hasher = std::collections::hash_map::DefaultHasher::new();
+            constant.dims().hash(&mut hasher);
+            constant.scale().hash(&mut hasher);
+            constant
+                .raw_values
+                .iter()
+                .for_each(|v| v.to_bits().hash(&mut hasher));
+
+            let candidates = seen.entry(hasher.finish()).or_default();
+            // guard against hash collisions by comparing the full tensors
+            let canonical = candidates
+                .iter()
+                .find(|c| {
+                    as_const(&nodes[*c]).map_or(false, |other| {
+                        other.raw_values == constant.raw_values
+                            && other.scale() == constant.scale()
+                            && other.visibility() == constant.visibility()
+                    })
+                })
+                .copied();
+
+            match canonical {
+                Some(canonical) => {
+                    remap.insert(*idx, canonical);
+                }
+                None => candidates.push(*idx),
+            }
+        }
+
+        if remap.is_empty() {
+            return;
+        }
+
+        debug!("deduplicating {} constant nodes", remap.len());
+
+        for (dup, canonical) in remap.iter() {
+            let uses = nodes.get(dup).map(|n| n.num_uses()).unwrap_or(0);
+            if let Some(NodeType::Node(n)) = nodes.get_mut(dup) {
+                n.num_uses = 0;
+            }
+            if let Some(NodeType::Node(n)) = nodes.get_mut(canonical) {
+                n.num_uses += uses;
+            }
+        }
+
+        for node in nodes.values_mut() {
+            let inputs = match node {
+                NodeType::Node(n) => &mut n.inputs,
+                NodeType::SubGraph { inputs, .. } => inputs,
+            };
+            for (input_idx, _) in inputs.iter_mut() {
+                if let Some(canonical) = remap.get(input_idx) {
+                    *input_idx = *canonical;
+                }
+            }
+        }
+    }
+
+    #[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
+    /// Removes all nodes that are consts with 0 uses
+    fn remove_unused_nodes(nodes: &mut BTreeMap<usize, NodeType>) {
+        // remove all nodes that are consts with 0 uses now
+        nodes.retain(|_, n| match n {
+            NodeType::Node(n) => n.num_uses > 0,
+            NodeType::SubGraph { model, .. } => {
+                Self::remove_unused_nodes(&mut model.graph.nodes);
+                true
+            }
+        });
+    }
+
+    /// Quantizes every constant that hasn't been quantized yet and drops its raw values.
+    /// Parsing leaves constants unquantized, so flows that only inspect the graph never pay for this.
+    pub fn quantize_constants(&mut self) -> Result<(), GraphError> {
+        for node in self.graph.nodes.values_mut() {
+            match node {
+                NodeType::Node(n) => {
+                    if let SupportedOp::Constant(c) = &mut n.opkind {
+                        c.empty_raw_value()?;
+                    }
+                }
+                NodeType::SubGraph { model, .. } => model.quantize_constants()?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Swaps every matmul by a fixed constant 2D operand with at least `threshold` of its (quantized)
+    /// entries zero for a [crate::circuit::poly::PolyOp::SparseMatMul], which only lays out the
+    /// products with nonzero entries. Params of any other visibility are left dense.
+    pub fn sparsify_weights(&mut self, threshold: f64) -> Result<(), GraphError> {
+        use crate::circuit::poly::{PolyOp, SparsityPattern};
+
+        fn to_sparse(
+            op: &SupportedOp,
+            weights: &Tensor<Fp>,
+            threshold: f64,
+        ) -> Option<SupportedOp> {
+            match op {
+                SupportedOp::Linear(PolyOp::Einsum { equation })
+                | SupportedOp::Linear(PolyOp::Affine { equation }) => {
+                    let axis = SparsityPattern::contracted_axis(equation)?;
+                    let pattern = SparsityPattern::new(weights, axis).ok()?;
+                    (pattern.sparsity() >= threshold).then(|| {
+                        SupportedOp::Linear(PolyOp::SparseMatMul {
+                            equation: equation.clone(),
+                            pattern,
+                        })
+                    })
+                }
+                SupportedOp::RebaseScale(op) => {
+                    let mut op = op.clone();
+                    op.inner = Box::new(to_sparse(&op.inner, weights, threshold)?);
+                    Some(SupportedOp::RebaseScale(op))
+                }
+                SupportedOp::Rescaled(op) => {
+                    let mut op = op.clone();
+                    op.inner = Box::new(to_sparse(&op.inner, weights, threshold)?);
+                    Some(SupportedOp::Rescaled(op))
+                }
+                _ => None,
+            }
+        }
+
+        let mut sparse = vec![];
+        for (idx, node) in self.graph.nodes.iter() {
+            let n = match node {
+                NodeType::Node(n) if n.inputs.len() >= 2 => n,
+                _ => continue,
+            };
+            let weights = match self.graph.nodes.get(&n.inputs[1].0) {
+                // entries the layout skips are left unconstrained, so only weights fixed in the
+                // circuit can be sparse: hashed or committed ones could hide other values there
+                Some(NodeType::Node(w)) => match w.opkind.get_constant() {
+                    Some(c) if c.visibility().is_fixed() => c.quantized_values()?,
+                    _ => continue,
+                },
+                _ => continue,
+            };
+            if let Some(op) = to_sparse(&n.opkind, weights, threshold) {
+                debug!("laying out node {} as {}", idx, op.as_string());
+                sparse.push((*idx, op));
+            }
+        }
+
+        for (idx, op) in sparse {
+            if let Some(NodeType::Node(n)) = self.graph.nodes.get_mut(&idx) {
+                n.opkind = op;
+            }
+        }
+        for node in self.graph.nodes.values_mut() {
+            if let NodeType::SubGraph { model, .. } = node {
+                model.sparsify_weights(threshold)?;
+            }
+        }
+        Ok(())
+    }
+
+    #[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
+    /// Run tract onnx model on sample data !
+    pub fn run_onnx_predictions(
+        run_args: &RunArgs,
+        model_path: &std::path::Path,
+        data_chunks: &[GraphData],
+        input_shapes: Vec<Vec<usize>>,
+    ) -> Result<Vec<Vec<Tensor<f32>>>, GraphError> {
+        use tract_onnx::tract_core::internal::IntoArcTensor;
+
+        let mut file = std::fs::File::open(model_path).map_err(|e| {
+            GraphError::ReadWriteFileError(model_path.display().to_string(), e.to_string())
+        })?;
+
+        let (model, _) = Model::load_onnx_using_tract(&mut file, model_path.parent(), run_args)?;
+
+        let datum_types: Vec<DatumType> = model
+            .input_outlets()?
+            .iter()
+            .map(|o| model.node(o.node).outputs[o.slot].fact.datum_type)
+            .collect();
+
+        let runnable_model = model.into_runnable()?;
+        let mut outputs = vec![];
+        for chunk in data_chunks {
+            let result = runnable_model.run(chunk.to_tract_data(&input_shapes, &datum_types)?)?;
+            outputs.push(
+                result
+                    .into_iter()
+                    .map(|t| {
+                        crate::graph::utilities::extract_tensor_value(t.into_arc_tensor()).unwrap()
+                    })
+                    .collect(),
+            );
+        }
+        Ok(outputs)
+    }
+
+    #[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
+    /// Run tract onnx model on sample data, returning the float outputs of every node keyed by node name.
+    pub fn run_onnx_trace(
+        run_args: &RunArgs,
+        model_path: &std::path::Path,
+        data: &GraphData,
+        input_shapes: Vec<Vec<usize>>,
+    ) -> Result<BTreeMap<String, Vec<Tensor<f32>>>, GraphError> {
+        use tract_onnx::prelude::OutletId;
+        use tract_onnx::tract_core::internal::IntoArcTensor;
+
+        let mut file = std::fs::File::open(model_path).map_err(|e| {
+            GraphError::ReadWriteFileError(model_path.display().to_string(), e.to_string())
+        })?;
+
+        let (mut model, _) =
+            Model::load_onnx_using_tract(&mut file, model_path.parent(), run_args)?;
+
+        let datum_types: Vec<DatumType> = model
+            .input_outlets()?
+            .iter()
+            .map(|o| model.node(o.node).outputs[o.slot].fact.datum_type)
+            .collect();
+
+        // expose every node's outputs as model outputs
+        let outlets = model
+            .nodes()
+            .iter()
+            .flat_map(|n| (0..n.outputs.len()).map(move |slot| OutletId::new(n.id, slot)))
+            .collect::<Vec<_>>();
+        let names = outlets
+            .iter()
+            .map(|o| model.node(o.node).name.clone())
+            .collect::<Vec<_>>();
+        model.set_output_outlets(&outlets)?;
+
+        let result = model
+            .into_runnable()?
+            .run(data.to_tract_data(&input_shapes, &datum_types)?)?;
+        let mut outputs = BTreeMap::<String, Vec<Tensor<f32>>>::new();
+        for (name, t) in names.into_iter().zip(result) {
+            // non numeric outputs, such as shapes, have nothing to compare against
+            if let Ok(t) = crate::graph::utilities::extract_tensor_value(t.into_arc_tensor()) {
+                outputs.entry(name).or_default().push(t);
+            }
+        }
+        Ok(outputs)
+    }
+
+    /// Loads the structure and shapes of an Onnx file without quantizing any of its constants,
+    /// which is much faster for models with large weights. Call [ShapedModel::quantize] to get a full `Model`.
+    /// # Arguments
+    /// * `run_args` - [RunArgs]
+    /// * `model` - The path to the Onnx file.
+    #[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
+    pub fn load_shapes_only(
+        run_args: &RunArgs,
+        model: &std::path::Path,
+    ) -> Result<ShapedModel, GraphError> {
+        let mut file = std::fs::File::open(model).map_err(|e| {
+            GraphError::ReadWriteFileError(model.display().to_string(), e.to_string())
+        })?;
+        let (model, symbol_values) =
+            Self::load_onnx_using_tract(&mut file, model.parent(), run_args)?;
+        Ok(ShapedModel {
+            model,
+            symbol_values,
+            run_args: run_args.clone(),
+        })
+    }
+
+    /// Creates a `Model` from parsed run_args
+    /// # Arguments
+    /// * `params` - A [GraphSettings] struct holding parsed CLI arguments.
+    #[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
+    pub fn from_run_args(run_args: &RunArgs, model: &std::path::Path) -> Result<Self, GraphError> {
+        let mut file = std::fs::File::open(model).map_err(|e| {
+            GraphError::ReadWriteFileError(model.display().to_string(), e.to_string())
+        })?;
+        Model::read(&mut file, model.parent(), run_args)
+    }
+
+    /// Configures a model for the circuit
+    /// # Arguments
+    /// * `meta` - The constraint system.
     /// * `vars` - The variables for the circuit.
     /// * `settings` - [GraphSettings]
     pub fn configure(
@@ -1033,12 +2277,21 @@ impl Model {
             &vars.advices[2],
             settings.check_mode,
         );
+        base_gate.lookup_range = lookup_range;
         // set scale for HybridOp::RangeCheck and call self.conf_lookup on that op for percentage tolerance case
         let input = &vars.advices[0];
         let output = &vars.advices[2];
         let index = &vars.advices[1];
         for op in required_lookups {
-            base_gate.configure_lookup(meta, input, output, index, lookup_range, logrows, &op)?;
+            // a table over a narrower range than the global one also constrains the op's inputs to that range
+            let range = settings.lookup_range(&op).unwrap_or(lookup_range);
+            debug!(
+                "configuring {} table over {:?} ({} rows)",
+                Op::<Fp>::as_string(&op),
+                range,
+                range.1 - range.0 + 1
+            );
+            base_gate.configure_lookup(meta, input, output, index, range, logrows, &op)?;
         }
 
         for range in required_range_checks {
@@ -1071,6 +2324,8 @@ impl Model {
     /// * `inputs` - The values to feed into the circuit.
     /// * `vars` - The variables for the circuit.
     /// * `witnessed_outputs` - The values to compare against.
+    /// * `dataset` - The path opening the input's dataset row, see [RunArgs::dataset_depth].
+    /// * `context` - The context hash to bind to the model's last instance, see [RunArgs::bind_context].
     /// * `constants` - The constants for the circuit.
     pub fn layout(
         &self,
@@ -1080,8 +2335,12 @@ impl Model {
         inputs: &[ValTensor<Fp>],
         vars: &mut ModelVars<Fp>,
         witnessed_outputs: &[ValTensor<Fp>],
+        dataset: Option<&DatasetPath>,
+        context: Option<&ValTensor<Fp>>,
         constants: &mut ConstantsMap<Fp>,
-    ) -> Result<Vec<ValTensor<Fp>>, GraphError> {
+        expected: Option<&ForwardTrace>,
+        interrupt: Option<&Interrupt>,
+    ) -> Result<ModelLayout, GraphError> {
         info!("model layout...");
 
         let start_time = instant::Instant::now();
@@ -1111,8 +2370,9 @@ impl Model {
         config.base.layout_range_checks(layouter)?;
 
         let original_constants = constants.clone();
+        let dead_after = self.dead_after();
 
-        let outputs = layouter.assign_region(
+        let layout = layouter.assign_region(
             || "model",
             |region| {
                 let mut thread_safe_region = RegionCtx::new_with_constants(
@@ -1125,13 +2385,35 @@ impl Model {
                 );
                 // we need to do this as this loop is called multiple times
                 vars.set_instance_idx(instance_idx);
+                witness_log::enter_region("model");
 
+                // checking the witness compares every node's outputs, so keep them all then
+                let dead_after = match expected {
+                    Some(_) => None,
+                    None => Some(&dead_after),
+                };
                 let outputs = self
-                    .layout_nodes(&mut config, &mut thread_safe_region, &mut results)
+                    .layout_nodes(
+                        &mut config,
+                        &mut thread_safe_region,
+                        &mut results,
+                        dead_after,
+                        None,
+                        None,
+                        interrupt,
+                    )
                     .map_err(|e| {
                         error!("{}", e);
                         halo2_proofs::plonk::Error::Synthesis
                     })?;
+                witness_log::enter_node(None);
+
+                if let Some(expected) = expected {
+                    self.check_witness(expected, &results).map_err(|e| {
+                        error!("{}", e);
+                        halo2_proofs::plonk::Error::Synthesis
+                    })?;
+                }
 
                 if run_args.output_visibility.is_public() || run_args.output_visibility.is_fixed() {
                     let output_scales = self.graph.get_output_scales().map_err(|e| {
@@ -1160,6 +2442,9 @@ impl Model {
                                 }
                                 witnessed_outputs[i].clone()
                             };
+                            if output.is_empty() {
+                                return Ok(None);
+                            }
 
                             config
                                 .base
@@ -1176,27 +2461,221 @@ impl Model {
                         halo2_proofs::plonk::Error::Synthesis
                     })?;
                 }
+
+                if let Some(dataset) = dataset {
+                    self.layout_dataset_path(&config, &mut thread_safe_region, vars, dataset)
+                        .map_err(|e| {
+                            error!("{}", e);
+                            halo2_proofs::plonk::Error::Synthesis
+                        })?;
+                }
+
+                if let Some(context) = context {
+                    let instance = vars
+                        .instance
+                        .as_ref()
+                        .ok_or(GraphError::MissingInstances)
+                        .map_err(|e| {
+                            error!("{}", e);
+                            halo2_proofs::plonk::Error::Synthesis
+                        })?
+                        .clone();
+                    vars.increment_instance_idx();
+                    config
+                        .base
+                        .layout(
+                            &mut thread_safe_region,
+                            &[context.clone(), instance],
+                            Box::new(HybridOp::RangeCheck(Tolerance::default())),
+                        )
+                        .map_err(|e| {
+                            error!("{}", e);
+                            halo2_proofs::plonk::Error::Synthesis
+                        })?;
+                }
+                let inputs = self
+                    .assigned_inputs(&results)
+                    .and_then(|inputs| {
+                        self.layout_categorical_groups(
+                            &config,
+                            &mut thread_safe_region,
+                            &inputs,
+                            &run_args.categorical_groups,
+                        )?;
+                        Ok(inputs)
+                    })
+                    .map_err(|e| {
+                        error!("{}", e);
+                        halo2_proofs::plonk::Error::Synthesis
+                    })?;
+
                 // Then number of columns in the circuits
                 #[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
                 thread_safe_region.debug_report();
 
                 *constants = thread_safe_region.assigned_constants().clone();
 
-                Ok(outputs)
+                Ok(ModelLayout { inputs, outputs })
             },
         )?;
 
         let duration = start_time.elapsed();
         trace!("model layout took: {:?}", duration);
 
-        Ok(outputs)
+        Ok(layout)
+    }
+
+    /// The model's inputs, which the input nodes have replaced with their assigned values
+    fn assigned_inputs(
+        &self,
+        results: &BTreeMap<usize, Vec<ValTensor<Fp>>>,
+    ) -> Result<Vec<ValTensor<Fp>>, GraphError> {
+        self.graph
+            .inputs
+            .iter()
+            .map(|idx| Ok(results.get(idx).ok_or(GraphError::MissingResults)?[0].clone()))
+            .collect()
+    }
+
+    /// Constrains a dataset opening's pairs into a path and its root and the row index the path
+    /// takes to the next two instances
+    fn layout_dataset_path(
+        &self,
+        config: &ModelConfig,
+        region: &mut RegionCtx<Fp>,
+        vars: &mut ModelVars<Fp>,
+        dataset: &DatasetPath,
+    ) -> Result<(), GraphError> {
+        let index = crate::circuit::layouts::merkle_path(
+            &config.base,
+            region,
+            &[
+                dataset.nodes.clone(),
+                dataset.lefts.clone(),
+                dataset.rights.clone(),
+                dataset.bits.clone(),
+            ],
+        )?;
+        for value in [dataset.root.clone(), index] {
+            let instance = vars
+                .instance
+                .as_ref()
+                .ok_or(GraphError::MissingInstances)?
+                .clone();
+            vars.increment_instance_idx();
+            config.base.layout(
+                region,
+                &[value, instance],
+                Box::new(HybridOp::RangeCheck(Tolerance::default())),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Constrains each `[start, end)` group of the model's flattened and concatenated inputs to
+    /// be one-hot: every entry is 0 or 1 at its input's scale, and the entries sum to 1.
+    fn layout_categorical_groups(
+        &self,
+        config: &ModelConfig,
+        region: &mut RegionCtx<Fp>,
+        inputs: &[ValTensor<Fp>],
+        groups: &[(usize, usize)],
+    ) -> Result<(), GraphError> {
+        if groups.is_empty() {
+            return Ok(());
+        }
+        let mut cells = vec![];
+        let mut scales = vec![];
+        for (input, scale) in inputs.iter().zip(self.graph.get_input_scales()) {
+            cells.extend(input.get_inner_tensor()?.iter().cloned());
+            scales.extend(std::iter::repeat(scale).take(input.len()));
+        }
+        for &(start, end) in groups {
+            if end > cells.len() {
+                return Err(GraphError::InvalidCategoricalGroup(
+                    start,
+                    end,
+                    format!("the model only has {} inputs", cells.len()),
+                ));
+            }
+            if scales[start..end].iter().any(|s| *s != scales[start]) {
+                return Err(GraphError::InvalidCategoricalGroup(
+                    start,
+                    end,
+                    "it spans inputs of different scales".into(),
+                ));
+            }
+            let group: ValTensor<Fp> =
+                Tensor::new(Some(&cells[start..end]), &[end - start])?.into();
+            let multiplier = integer_rep_to_felt(scale_to_multiplier(scales[start]) as IntegerRep);
+            crate::circuit::layouts::one_hot_group(&config.base, region, &[group], multiplier)?;
+        }
+        Ok(())
+    }
+
+    /// The nodes whose outputs each node is the last to consume, so that they can be dropped as
+    /// soon as it's laid out. The model's inputs and outputs are always kept, and a node nothing
+    /// consumes is dropped right after it's laid out.
+    pub fn dead_after(&self) -> BTreeMap<usize, Vec<usize>> {
+        let mut last_uses = BTreeMap::new();
+        for (idx, node) in &self.graph.nodes {
+            last_uses.insert(*idx, *idx);
+            for (input, _) in node.inputs() {
+                last_uses.insert(input, *idx);
+            }
+        }
+        let mut dead_after = BTreeMap::<usize, Vec<usize>>::new();
+        for (idx, last_use) in last_uses {
+            if self.graph.inputs.contains(&idx)
+                || self.graph.outputs.iter().any(|(output, _)| *output == idx)
+            {
+                continue;
+            }
+            dead_after.entry(last_use).or_default().push(idx);
+        }
+        dead_after
     }
 
+    /// The most output values the model's nodes hold at once when laid out in order, dropping
+    /// each node's outputs once their last consumer is laid out (see [Model::dead_after]).
+    pub fn peak_live_values(&self) -> usize {
+        let len = |idx: &usize| {
+            self.graph.nodes.get(idx).map_or(0, |node| {
+                node.out_dims()
+                    .iter()
+                    .map(|dims| dims.iter().product::<usize>())
+                    .sum::<usize>()
+            })
+        };
+        let dead_after = self.dead_after();
+        let mut live = 0;
+        let mut peak = 0;
+        for idx in self.graph.nodes.keys() {
+            live += len(idx);
+            peak = peak.max(live);
+            for dead in dead_after.get(idx).into_iter().flatten() {
+                live -= len(dead);
+            }
+        }
+        peak
+    }
+
+    /// Lays out each node in order, keeping its outputs in `results`. With `dead_after` (see
+    /// [Model::dead_after]) outputs are dropped once their last consumer is laid out, and that
+    /// consumer takes their buffers rather than copying them, so that memory is bounded by the
+    /// model's width rather than its depth. The largest magnitude of each node's known outputs
+    /// is pushed to `node_output_max` before they can be dropped, and the most values `results`
+    /// holds at once is written to `peak_live_values`.
+    #[allow(clippy::too_many_arguments)]
     fn layout_nodes(
         &self,
         config: &mut ModelConfig,
         region: &mut RegionCtx<Fp>,
         results: &mut BTreeMap<usize, Vec<ValTensor<Fp>>>,
+        dead_after: Option<&BTreeMap<usize, Vec<usize>>>,
+        mut node_output_max: Option<&mut Vec<(usize, IntegerRep)>>,
+        mut peak_live_values: Option<&mut usize>,
+        interrupt: Option<&Interrupt>,
     ) -> Result<Vec<ValTensor<Fp>>, GraphError> {
         // index over results to get original inputs
         let orig_inputs: BTreeMap<usize, _> = results
@@ -1206,7 +2685,11 @@ impl Model {
             .collect();
 
         for (idx, node) in self.graph.nodes.iter() {
+            if let Some(interrupt) = interrupt {
+                interrupt.check()?;
+            }
             debug!("laying out {}: {}", idx, node.as_str(),);
+            witness_log::enter_node(Some(*idx));
             // Then number of columns in the circuits
             #[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
             region.debug_report();
@@ -1220,11 +2703,25 @@ impl Model {
                     .collect_vec()
             );
 
+            let dying = dead_after.and_then(|d| d.get(idx));
+            let inputs = node.inputs();
             let mut values: Vec<ValTensor<Fp>> = if !node.is_input() {
-                node.inputs()
+                inputs
                     .iter()
-                    .map(|(idx, outlet)| {
-                        Ok(results.get(idx).ok_or(GraphError::MissingResults)?[*outlet].clone())
+                    .enumerate()
+                    .map(|(i, (input, outlet))| {
+                        // the last read of a dying input takes its buffer
+                        let last_read = dying.is_some_and(|d| d.contains(input))
+                            && !inputs[i + 1..].iter().any(|(j, _)| j == input);
+                        match last_read {
+                            true => Ok(results
+                                .remove(input)
+                                .ok_or(GraphError::MissingResults)?
+                                .swap_remove(*outlet)),
+                            false => Ok(results.get(input).ok_or(GraphError::MissingResults)?
+                                [*outlet]
+                                .clone()),
+                        }
                     })
                     .collect::<Result<Vec<_>, GraphError>>()?
             } else {
@@ -1239,14 +2736,17 @@ impl Model {
 
             match &node {
                 NodeType::Node(n) => {
-                    let res = if node.is_constant() && node.num_uses() == 1 {
+                    let res = if n.out_dims.iter().product::<usize>() == 0 {
+                        // empty tensors, e.g. a pruned head's, take up nothing in the circuit
+                        Some(Tensor::<ValType<Fp>>::new(Some(&[]), &n.out_dims)?.into())
+                    } else if node.is_constant() && node.num_uses() == 1 {
                         log::debug!("node {} is a constant with 1 use", n.idx);
                         let mut node = n.clone();
                         let c = node
                             .opkind
                             .get_mutable_constant()
                             .ok_or(GraphError::MissingConstants)?;
-                        Some(c.quantized_values.clone().try_into()?)
+                        Some(c.quantized_values()?.clone().try_into()?)
                     } else {
                         config
                             .base
@@ -1259,10 +2759,10 @@ impl Model {
 
                     if let Some(mut vt) = res {
                         vt.reshape(&node.out_dims()[0])?;
-                        // we get the max as for fused nodes this corresponds to the node output
-                        results.insert(*idx, vec![vt.clone()]);
                         //only use with mock prover
                         debug!("------------ output node {:?}: {:?}", idx, vt.show());
+                        // we get the max as for fused nodes this corresponds to the node output
+                        results.insert(*idx, vec![vt]);
                     }
                 }
                 NodeType::SubGraph {
@@ -1309,7 +2809,15 @@ impl Model {
                                 .zip(values.clone().into_iter().map(|v| vec![v])),
                         );
 
-                        let res = model.layout_nodes(config, region, &mut subgraph_results)?;
+                        let res = model.layout_nodes(
+                            config,
+                            region,
+                            &mut subgraph_results,
+                            None,
+                            None,
+                            None,
+                            interrupt,
+                        )?;
 
                         let mut outlets = BTreeMap::new();
                         let mut stacked_outlets = BTreeMap::new();
@@ -1374,6 +2882,29 @@ impl Model {
                     results.insert(*idx, full_results);
                 }
             }
+
+            if let (Some(node_output_max), Some(values)) = (&mut node_output_max, results.get(idx))
+            {
+                if !node.is_input()
+                    && !node.is_constant()
+                    && !values.iter().any(|v| v.any_unknowns().unwrap_or(true))
+                {
+                    let mut max = 0;
+                    for v in values {
+                        max = v.int_evals()?.iter().fold(max, |max, x| max.max(x.abs()));
+                    }
+                    node_output_max.push((*idx, max));
+                }
+            }
+
+            if let Some(peak) = &mut peak_live_values {
+                let live = results.values().flatten().map(|v| v.len()).sum::<usize>();
+                **peak = (**peak).max(live);
+            }
+
+            for dead in dying.into_iter().flatten() {
+                results.remove(dead);
+            }
         }
 
         // we do this so we can support multiple passes of the same model and have deterministic results (Non-assigned inputs etc... etc...)
@@ -1393,6 +2924,266 @@ impl Model {
         Ok(outputs)
     }
 
+    /// Checks the values laid out for each node against those its forward pass `expected`,
+    /// reporting the first node that differs. Values that aren't known, as when generating keys, are skipped.
+    /// Panics rather than erroring in tests.
+    fn check_witness(
+        &self,
+        expected: &ForwardTrace,
+        results: &BTreeMap<usize, Vec<ValTensor<Fp>>>,
+    ) -> Result<(), GraphError> {
+        for (name, node) in expected.nodes.iter().sorted_by_key(|(_, n)| n.idx) {
+            let laid_out = match results.get(&node.idx) {
+                Some(laid_out) => laid_out,
+                None => continue,
+            };
+            for (values, want) in laid_out.iter().zip(&node.quantized) {
+                if values.any_unknowns()? {
+                    continue;
+                }
+                let got = values.int_evals()?;
+                if got.len() != want.len() || got.iter().zip(want.iter()).any(|(a, b)| a != b) {
+                    let err = GraphError::WitnessMismatch(node.idx, name.clone());
+                    error!(
+                        "node {} ({}) laid out {:?}, forward pass gave {:?}",
+                        node.idx,
+                        name,
+                        got.show(),
+                        want.show()
+                    );
+                    if cfg!(test) {
+                        panic!("{}", err);
+                    }
+                    return Err(err);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Lays out the model on `inputs` and checks each node against [SupportedOp::int_reference],
+    /// evaluated on the very inputs the layout saw, so the first divergent node is reported.
+    /// Nodes without a reference implementation are skipped.
+    pub fn check_int_reference(
+        &self,
+        run_args: &RunArgs,
+        inputs: &[Tensor<Fp>],
+        region_settings: RegionSettings,
+    ) -> Result<(), GraphError> {
+        let mut results = BTreeMap::<usize, Vec<ValTensor<Fp>>>::new();
+        for (input_idx, input) in self.graph.inputs.iter().zip(inputs) {
+            let input: ValTensor<Fp> = input.map(|elem| ValType::Value(Value::known(elem))).into();
+            results.insert(*input_idx, vec![input]);
+        }
+
+        let mut model_config = ModelConfig {
+            base: PolyConfig::dummy(run_args.logrows as usize, run_args.num_inner_cols),
+            vars: ModelVars::new_dummy(),
+        };
+        model_config.base.lookup_range = run_args.lookup_range;
+        let mut region = RegionCtx::new_dummy(0, run_args.num_inner_cols, region_settings);
+        self.layout_nodes(
+            &mut model_config,
+            &mut region,
+            &mut results,
+            None,
+            None,
+            None,
+            None,
+        )?;
+
+        let mut num_checked = 0;
+        for (idx, node) in self.graph.nodes.iter() {
+            let n = match node {
+                NodeType::Node(n) => n,
+                NodeType::SubGraph { .. } => continue,
+            };
+            let values = n
+                .inputs
+                .iter()
+                .map(|(i, outlet)| {
+                    Ok(results.get(i).ok_or(GraphError::MissingResults)?[*outlet].int_evals()?)
+                })
+                .collect::<Result<Vec<_>, GraphError>>()?;
+            let expected = match n.opkind.int_reference(&values)? {
+                Some(expected) => expected,
+                None => continue,
+            };
+            let res = results.get(idx).ok_or(GraphError::MissingResults)?[0].int_evals()?;
+            if res.len() != expected.len() || res.iter().zip(expected.iter()).any(|(a, b)| a != b) {
+                error!(
+                    "node {} laid out {:?}, integer reference gives {:?}",
+                    idx,
+                    res.show(),
+                    expected.show()
+                );
+                return Err(GraphError::ReferenceMismatch(*idx, n.opkind.as_string()));
+            }
+            num_checked += 1;
+        }
+        debug!("{} nodes match their integer reference", num_checked);
+
+        Ok(())
+    }
+
+    /// Estimates the cells the nodes take up from their shapes alone, without laying them out
+    /// (see [Estimator]). Subgraphs and nodes whose ops aren't costed are listed as unestimated.
+    /// These are reported by `gen-settings` and rank the nodes when a model doesn't fit
+    /// `max_advice_cols`; logrows are always picked from the rows of an actual layout.
+    pub fn estimate_resources(&self, run_args: &RunArgs) -> Result<ResourceEstimate, GraphError> {
+        let estimator = Estimator::new(
+            run_args.num_inner_cols,
+            run_args.decomp_base,
+            run_args.decomp_legs,
+        );
+        let mut estimate = ResourceEstimate::default();
+        for (idx, node) in &self.graph.nodes {
+            let n = match node {
+                NodeType::Node(n) => n,
+                NodeType::SubGraph { .. } => {
+                    estimate.unestimated.push(*idx);
+                    continue;
+                }
+            };
+            let input_dims = n
+                .inputs
+                .iter()
+                .map(|(i, slot)| {
+                    self.graph
+                        .nodes
+                        .get(i)
+                        .map(|input| input.out_dims()[*slot].clone())
+                        .ok_or(GraphError::MissingNode(*i))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            match Self::estimate_op(&estimator, &n.opkind, &input_dims, &n.out_dims)? {
+                Some(assignments) => {
+                    estimate.assignments += assignments;
+                    estimate.nodes.push((*idx, assignments));
+                }
+                None => estimate.unestimated.push(*idx),
+            }
+        }
+        estimate.num_rows = estimator.rows(estimate.assignments);
+        estimate.peak_live_values = self.peak_live_values();
+        estimate.clip_rates = self.clip_rates();
+        Ok(estimate)
+    }
+
+    fn estimate_op(
+        estimator: &Estimator,
+        op: &SupportedOp,
+        inputs: &[Vec<usize>],
+        out_dims: &[usize],
+    ) -> Result<Option<usize>, GraphError> {
+        use crate::circuit::ops::chip::AvgPoolMode;
+        use crate::circuit::poly::PolyOp;
+
+        let out_len = out_dims.iter().product::<usize>();
+        let estimate = match op {
+            SupportedOp::Input(_) | SupportedOp::Constant(_) => Some(0),
+            SupportedOp::Linear(PolyOp::Conv {
+                padding,
+                stride,
+                group,
+                channels_last: false,
+                strategy,
+            }) => Some(estimator.conv(
+                &inputs[0],
+                &inputs[1],
+                padding,
+                stride,
+                *group,
+                inputs.len() == 3,
+                *strategy,
+            )?),
+            SupportedOp::Linear(PolyOp::Einsum { equation }) => estimator.einsum(inputs, equation),
+            SupportedOp::Linear(PolyOp::Affine { equation }) => estimator
+                .einsum(&inputs[..2], equation)
+                .map(|e| e + estimator.pairwise(out_len)),
+            SupportedOp::Linear(PolyOp::SparseMatMul { pattern, .. }) => {
+                let products = estimator.sparse_matmul(out_len, pattern);
+                // any bias is added after the products
+                Some(match inputs.len() {
+                    3 => products + estimator.pairwise(out_len),
+                    _ => products,
+                })
+            }
+            SupportedOp::Linear(PolyOp::Add | PolyOp::Sub | PolyOp::Mult) => {
+                Some(estimator.pairwise(out_len))
+            }
+            // wiring only moves cells around
+            SupportedOp::Linear(
+                PolyOp::Reshape(_)
+                | PolyOp::Flatten(_)
+                | PolyOp::MoveAxis { .. }
+                | PolyOp::Slice { .. }
+                | PolyOp::Concat { .. }
+                | PolyOp::Pad(_)
+                | PolyOp::MultiBroadcastTo { .. }
+                | PolyOp::Downsample { .. },
+            ) => Some(0),
+            SupportedOp::Linear(
+                op @ (PolyOp::Abs
+                | PolyOp::Sign
+                | PolyOp::Neg
+                | PolyOp::Not
+                | PolyOp::Pow(_)
+                | PolyOp::LeakyReLU { .. }
+                | PolyOp::ReLU),
+            ) => Some(estimator.elementwise(op, 1, out_len)?),
+            SupportedOp::Nonlinear(op) => Some(estimator.elementwise(op, 1, out_len)?),
+            SupportedOp::Hybrid(HybridOp::SumPool {
+                padding,
+                stride,
+                kernel_shape,
+                normalized,
+                channels_last: false,
+                avgpool_mode,
+            }) => Some(estimator.sumpool(
+                &inputs[0],
+                padding,
+                stride,
+                kernel_shape,
+                *normalized && *avgpool_mode == AvgPoolMode::Exact,
+            )?),
+            SupportedOp::Hybrid(HybridOp::MaxPool {
+                padding,
+                stride,
+                pool_dims,
+                channels_last: false,
+            }) => Some(estimator.max_pool(&inputs[0], padding, stride, pool_dims)?),
+            SupportedOp::Hybrid(
+                op @ (HybridOp::Recip { .. }
+                | HybridOp::RefinedRecip(_)
+                | HybridOp::Div { .. }
+                | HybridOp::Round { .. }
+                | HybridOp::Floor { .. }
+                | HybridOp::Ceil { .. }),
+            ) => Some(estimator.elementwise(op, 1, out_len)?),
+            SupportedOp::RebaseScale(op) => {
+                match Self::estimate_op(estimator, &op.inner, inputs, out_dims)? {
+                    Some(inner) => Some(inner + estimator.elementwise(&op.rebase_op, 1, out_len)?),
+                    None => None,
+                }
+            }
+            SupportedOp::Rescaled(op) => {
+                // each input scaled by a multiplier other than 1 is multiplied through
+                let rescaled = op
+                    .scale
+                    .iter()
+                    .filter(|(_, multiplier)| *multiplier != 1)
+                    .filter_map(|(i, _)| inputs.get(*i))
+                    .map(|dims| estimator.pairwise(dims.iter().product()))
+                    .sum::<usize>();
+                Self::estimate_op(estimator, &op.inner, inputs, out_dims)?
+                    .map(|inner| inner + rescaled)
+            }
+            _ => None,
+        };
+        Ok(estimate)
+    }
+
     /// Assigns dummy values to the regions created when calling `configure`.
     /// # Arguments
     /// * `input_shapes` - The shapes of the inputs to the model.
@@ -1402,6 +3193,17 @@ impl Model {
         inputs: &[ValTensor<Fp>],
         region_settings: RegionSettings,
     ) -> Result<DummyPassRes, GraphError> {
+        let (res, _) = self.dummy_pass(run_args, inputs, region_settings, false)?;
+        Ok(res)
+    }
+
+    fn dummy_pass(
+        &self,
+        run_args: &RunArgs,
+        inputs: &[ValTensor<Fp>],
+        region_settings: RegionSettings,
+        record_trace: bool,
+    ) -> Result<(DummyPassRes, Option<ForwardTrace>), GraphError> {
         debug!("calculating num of constraints using dummy model layout...");
 
         let start_time = instant::Instant::now();
@@ -1414,6 +3216,7 @@ impl Model {
 
         let mut dummy_config =
             PolyConfig::dummy(run_args.logrows as usize, run_args.num_inner_cols);
+        dummy_config.lookup_range = run_args.lookup_range;
         let mut model_config = ModelConfig {
             base: dummy_config.clone(),
             vars: ModelVars::new_dummy(),
@@ -1421,7 +3224,34 @@ impl Model {
 
         let mut region = RegionCtx::new_dummy(0, run_args.num_inner_cols, region_settings);
 
-        let outputs = self.layout_nodes(&mut model_config, &mut region, &mut results)?;
+        // tracing the forward pass records every node's outputs, so keep them all then
+        let dead_after = match record_trace {
+            true => None,
+            false => Some(self.dead_after()),
+        };
+        let mut node_output_max = vec![];
+        let mut peak_live_values = 0;
+        let outputs = self.layout_nodes(
+            &mut model_config,
+            &mut region,
+            &mut results,
+            dead_after.as_ref(),
+            Some(&mut node_output_max),
+            Some(&mut peak_live_values),
+            None,
+        )?;
+        let assigned_inputs = self.assigned_inputs(&results)?;
+        self.layout_categorical_groups(
+            &model_config,
+            &mut region,
+            &assigned_inputs,
+            &run_args.categorical_groups,
+        )?;
+
+        let trace = match record_trace {
+            true => Some(ForwardTrace::record(&self.graph, &results)?),
+            false => None,
+        };
 
         if self.visibility.output.is_public() || self.visibility.output.is_fixed() {
             let output_scales = self.graph.get_output_scales()?;
@@ -1458,6 +3288,31 @@ impl Model {
             }
         }
 
+        if let Some(depth) = run_args.dataset_depth {
+            let dataset = DatasetPath::unknown(depth);
+            let index = crate::circuit::layouts::merkle_path(
+                &dummy_config,
+                &mut region,
+                &[dataset.nodes, dataset.lefts, dataset.rights, dataset.bits],
+            )?;
+            for value in [dataset.root, index] {
+                dummy_config.layout(
+                    &mut region,
+                    &[value.clone(), value],
+                    Box::new(HybridOp::RangeCheck(Tolerance::default())),
+                )?;
+            }
+        }
+
+        if run_args.bind_context {
+            let context: ValTensor<Fp> = vec![ValType::Value(Value::<Fp>::unknown())].into();
+            dummy_config.layout(
+                &mut region,
+                &[context.clone(), context],
+                Box::new(HybridOp::RangeCheck(Tolerance::default())),
+            )?;
+        }
+
         let duration = start_time.elapsed();
         trace!("dummy model layout took: {:?}", duration);
 
@@ -1483,6 +3338,9 @@ impl Model {
             max_lookup_inputs: region.max_lookup_inputs(),
             min_lookup_inputs: region.min_lookup_inputs(),
             max_range_size: region.max_range_size(),
+            lookup_ranges: region.lookup_ranges().into_iter().sorted().collect(),
+            node_output_max,
+            peak_live_values,
             num_dynamic_lookups: region.dynamic_lookup_index(),
             dynamic_lookup_col_coord: region.dynamic_lookup_col_coord(),
             num_shuffles: region.shuffle_index(),
@@ -1490,35 +3348,46 @@ impl Model {
             outputs,
         };
 
-        Ok(res)
+        Ok((res, trace))
+    }
+
+    /// Whether `constant` is one of the params the model's `params` visibility applies to. When
+    /// params are committed to, constants placed in fixed or advice columns (see
+    /// [RunArgs::param_placement]) aren't.
+    fn is_param(params: &Visibility, constant: &Constant<Fp>) -> bool {
+        !params.requires_processing() || constant.visibility().requires_processing()
     }
 
-    /// Retrieves all constants from the model.
-    pub fn get_all_params(&self) -> Vec<Tensor<Fp>> {
+    /// Retrieves the model's params, see [Model::is_param].
+    pub fn get_all_params(&self) -> Result<Vec<Tensor<Fp>>, GraphError> {
         let mut params = vec![];
         for node in self.graph.nodes.values() {
             match node {
-                NodeType::Node(_) => {
-                    if let Some(constant) = extract_const_quantized_values(node.opkind()) {
-                        params.push(constant);
+                NodeType::Node(n) => {
+                    if let Some(constant) = n.opkind.get_constant() {
+                        if Self::is_param(&self.visibility.params, constant) {
+                            params.push(constant.quantized_values()?.clone());
+                        }
                     }
                 }
                 NodeType::SubGraph { model, .. } => {
-                    params.extend(model.get_all_params());
+                    params.extend(model.get_all_params()?);
                 }
             }
         }
-        params
+        Ok(params)
     }
 
-    /// Shapes of the computational graph's constants
+    /// Shapes of the computational graph's params, see [Model::is_param].
     pub fn const_shapes(&self) -> Vec<Vec<usize>> {
         let mut const_shapes = vec![];
         for node in self.graph.nodes.values() {
             match node {
-                NodeType::Node(_) => {
-                    if let Some(constant) = extract_const_quantized_values(node.opkind()) {
-                        const_shapes.push(constant.dims().to_vec());
+                NodeType::Node(n) => {
+                    if let Some(constant) = n.opkind.get_constant() {
+                        if Self::is_param(&self.visibility.params, constant) {
+                            const_shapes.push(constant.dims().to_vec());
+                        }
                     };
                 }
                 NodeType::SubGraph { model, .. } => {
@@ -1529,17 +3398,17 @@ impl Model {
         const_shapes
     }
 
-    /// Replaces all constants in the model with the provided values (in order of indexing), returns the number of consts
+    /// Replaces the model's params (see [Model::is_param]) with the provided values (in order of indexing), returns the number of consts
     pub fn replace_consts(&mut self, consts: &[ValTensor<Fp>]) -> usize {
         let mut const_idx = 0;
         for node in self.graph.nodes.values_mut() {
             match node {
                 NodeType::Node(n) => {
                     if let SupportedOp::Constant(c) = &n.opkind {
-                        let mut op = crate::circuit::Constant::new(
-                            c.quantized_values.clone(),
-                            c.raw_values.clone(),
-                        );
+                        if !Self::is_param(&self.visibility.params, c) {
+                            continue;
+                        }
+                        let mut op = c.clone();
                         op.pre_assign(consts[const_idx].clone());
                         n.opkind = SupportedOp::Constant(op);
 
@@ -1555,6 +3424,66 @@ impl Model {
         const_idx
     }
 
+    /// The name of each of the model's constants and the visibility it was given, which differs
+    /// from the param visibility for those placed by [RunArgs::param_placement].
+    pub fn param_visibilities(&self) -> Vec<(String, Visibility)> {
+        let mut visibilities = vec![];
+        for node in self.graph.nodes.values() {
+            match node {
+                NodeType::Node(n) => {
+                    if let Some(constant) = n.opkind.get_constant() {
+                        visibilities.push((n.name.clone(), constant.visibility().clone()));
+                    }
+                }
+                NodeType::SubGraph { model, .. } => {
+                    visibilities.extend(model.param_visibilities());
+                }
+            }
+        }
+        visibilities
+    }
+
+    /// The constants that had values clipped when they were parsed (see
+    /// [crate::circuit::ops::Constant::saturating]), the worst clipping first.
+    pub fn clip_rates(&self) -> Vec<ClipRate> {
+        let mut rates = vec![];
+        for node in self.graph.nodes.values() {
+            match node {
+                NodeType::Node(n) => {
+                    if let Some(constant) = n.opkind.get_constant() {
+                        if constant.clipped() > 0 {
+                            rates.push(ClipRate {
+                                node: n.name.clone(),
+                                clipped: constant.clipped(),
+                                total: constant.dims().iter().product(),
+                            });
+                        }
+                    }
+                }
+                NodeType::SubGraph { model, .. } => {
+                    rates.extend(model.clip_rates());
+                }
+            }
+        }
+        rates.sort_by(|a, b| b.rate().total_cmp(&a.rate()));
+        rates
+    }
+
+    /// The constants clipping more than `threshold` of their values, the worst first.
+    pub fn clipping_over(&self, threshold: f64) -> Vec<ClipRate> {
+        self.clip_rates()
+            .into_iter()
+            .filter(|rate| rate.rate() > threshold)
+            .collect()
+    }
+
+    /// Warns of the constants clipping more than `threshold` of their values, which quietly skew
+    /// the model's outputs in proofs that still verify.
+    #[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
+    fn warn_clipping(&self, threshold: f64) {
+        report_clipping(self.clipping_over(threshold).iter());
+    }
+
     /// Shapes of the computational graph's public inputs (if any)
     pub fn instance_shapes(&self) -> Result<Vec<Vec<usize>>, GraphError> {
         let mut instance_shapes = vec![];
@@ -1567,3 +3496,1162 @@ impl Model {
         Ok(instance_shapes)
     }
 }
+
+#[cfg(all(test, feature = "ezkl", not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+    use crate::circuit::{poly::PolyOp, Constant};
+    use crate::graph::tests::{forward, mock_prove};
+
+    fn const_node(idx: usize, values: &[i32]) -> NodeType {
+        let quantized = values.iter().map(|v| Fp::from(*v as u64)).collect_vec();
+        let quantized = Tensor::new(Some(&quantized), &[values.len()]).unwrap();
+        let raw = Tensor::new(
+            Some(&values.iter().map(|v| *v as f32).collect_vec()),
+            &[values.len()],
+        )
+        .unwrap();
+        NodeType::Node(Node {
+            opkind: SupportedOp::Constant(Constant::new(quantized, raw)),
+            out_scale: 0,
+            inputs: vec![],
+            out_dims: vec![values.len()],
+            idx,
+            num_uses: 1,
+            name: String::new(),
+        })
+    }
+
+    #[test]
+    fn tied_constants_are_deduplicated() {
+        let mut nodes = BTreeMap::new();
+        nodes.insert(0, const_node(0, &[1, 2, 3]));
+        nodes.insert(1, const_node(1, &[1, 2, 3]));
+        nodes.insert(2, const_node(2, &[4, 5, 6]));
+        nodes.insert(
+            3,
+            NodeType::Node(Node {
+                opkind: SupportedOp::Linear(PolyOp::Add),
+                out_scale: 0,
+                inputs: vec![(0, 0), (1, 0)],
+                out_dims: vec![3],
+                idx: 3,
+                num_uses: 1,
+                name: String::new(),
+            }),
+        );
+        nodes.insert(
+            4,
+            NodeType::Node(Node {
+                opkind: SupportedOp::Linear(PolyOp::Add),
+                out_scale: 0,
+                inputs: vec![(3, 0), (2, 0)],
+                out_dims: vec![3],
+                idx: 4,
+                num_uses: 1,
+                name: String::new(),
+            }),
+        );
+
+        Model::dedup_constants(&mut nodes, &[4]);
+        Model::remove_unused_nodes(&mut nodes);
+
+        assert_eq!(nodes.values().filter(|n| n.is_constant()).count(), 2);
+        assert!(!nodes.contains_key(&1));
+        assert_eq!(nodes[&0].num_uses(), 2);
+        assert_eq!(nodes[&3].inputs(), vec![(0, 0), (0, 0)]);
+        assert_eq!(nodes[&4].inputs(), vec![(3, 0), (2, 0)]);
+    }
+
+    /// A `[3]` product of two constants (node 2) added to a fixed bias (node 3) by `add` (node 4),
+    /// after [Model::fuse_einsum_bias].
+    fn fuse_bias(add: SupportedOp) -> BTreeMap<usize, NodeType> {
+        let mut nodes = BTreeMap::new();
+        nodes.insert(0, const_node(0, &[1, 2, 3]));
+        nodes.insert(1, const_node(1, &[4, 5, 6]));
+        nodes.insert(
+            2,
+            NodeType::Node(Node {
+                opkind: SupportedOp::Linear(PolyOp::Einsum {
+                    equation: "i,i->i".to_string(),
+                }),
+                out_scale: 0,
+                inputs: vec![(0, 0), (1, 0)],
+                out_dims: vec![3],
+                idx: 2,
+                num_uses: 1,
+                name: String::new(),
+            }),
+        );
+        let mut bias = const_node(3, &[7, 8, 9]);
+        if let NodeType::Node(Node {
+            opkind: SupportedOp::Constant(c),
+            ..
+        }) = &mut bias
+        {
+            let mut quantized = c.quantized_values().unwrap().clone();
+            quantized.set_visibility(&crate::graph::Visibility::Fixed);
+            *c = Constant::new(quantized, c.raw_values.clone());
+        }
+        nodes.insert(3, bias);
+        nodes.insert(
+            4,
+            NodeType::Node(Node {
+                opkind: add,
+                out_scale: 0,
+                inputs: vec![(3, 0), (2, 0)],
+                out_dims: vec![3],
+                idx: 4,
+                num_uses: 1,
+                name: String::new(),
+            }),
+        );
+
+        Model::fuse_einsum_bias(&mut nodes, &[4]);
+        Model::remove_unused_nodes(&mut nodes);
+        nodes
+    }
+
+    #[test]
+    fn matmul_bias_is_fused() {
+        let nodes = fuse_bias(SupportedOp::Linear(PolyOp::Add));
+        assert!(!nodes.contains_key(&2));
+        assert!(matches!(
+            nodes[&4].opkind(),
+            SupportedOp::Linear(PolyOp::Affine { .. })
+        ));
+        assert_eq!(nodes[&4].inputs(), vec![(0, 0), (1, 0), (3, 0)]);
+    }
+
+    #[test]
+    fn rescaled_matmul_bias_is_fused() {
+        let rescaled = |scale| {
+            SupportedOp::Rescaled(Rescaled {
+                inner: Box::new(SupportedOp::Linear(PolyOp::Add)),
+                scale,
+            })
+        };
+        // the bias (input 0) is multiplied up to the product's scale
+        let nodes = fuse_bias(rescaled(vec![(0, 4), (1, 1)]));
+        assert!(!nodes.contains_key(&2));
+        assert!(matches!(
+            nodes[&4].opkind(),
+            SupportedOp::Linear(PolyOp::Affine { .. })
+        ));
+        assert_eq!(nodes[&4].inputs(), vec![(0, 0), (1, 0), (3, 0)]);
+
+        let rebased = fuse_bias(SupportedOp::RebaseScale(RebaseScale {
+            inner: Box::new(rescaled(vec![(0, 4), (1, 1)])),
+            rebase_op: HybridOp::Div {
+                denom: crate::circuit::utils::F32(2.0),
+                use_range_check_for_int: true,
+            },
+            target_scale: 0,
+            original_scale: 1,
+            multiplier: 2.0,
+        }));
+        match rebased[&4].opkind() {
+            SupportedOp::RebaseScale(op) => assert!(matches!(
+                *op.inner,
+                SupportedOp::Linear(PolyOp::Affine { .. })
+            )),
+            op => panic!("expected a rebased affine, got {:?}", op),
+        }
+
+        // the product multiplied up would leave the add above the product's scale
+        let nodes = fuse_bias(rescaled(vec![(0, 1), (1, 4)]));
+        assert!(nodes.contains_key(&2));
+        assert!(matches!(nodes[&4].opkind(), SupportedOp::Rescaled(_)));
+    }
+
+    fn mlp_outputs(run_args: &RunArgs) -> (Model, Vec<f64>) {
+        let model = Model::from_run_args(
+            run_args,
+            std::path::Path::new("examples/onnx/1l_mlp/network.onnx"),
+        )
+        .unwrap();
+
+        let input = [1.5417295, 0.5346153, 1.2172532]
+            .iter()
+            .map(|x| {
+                let q = crate::graph::quantize_float(x, 0.0, run_args.input_scale).unwrap();
+                crate::fieldutils::integer_rep_to_felt::<Fp>(q)
+            })
+            .collect_vec();
+        let input = Tensor::new(Some(&input), &[1, 3]).unwrap();
+
+        let res = model
+            .forward(
+                &[input],
+                run_args,
+                RegionSettings::all_true(run_args.decomp_base, run_args.decomp_legs),
+            )
+            .unwrap();
+        let scale = model.graph.get_output_scales().unwrap()[0];
+        let outputs = res.outputs[0]
+            .iter()
+            .map(|x| crate::graph::dequantize(*x, scale, 0.0))
+            .collect_vec();
+        (model, outputs)
+    }
+
+    #[test]
+    fn uniform_scale_matches_variable_scale() {
+        let run_args = RunArgs::default();
+        let (_, variable) = mlp_outputs(&run_args);
+
+        let run_args = RunArgs {
+            uniform_scale: true,
+            ..run_args
+        };
+        let (model, uniform) = mlp_outputs(&run_args);
+
+        let working_scale = VarScales::from_args(&run_args).get_max();
+        for node in model.graph.nodes.values() {
+            if let NodeType::Node(n) = node {
+                if n.opkind.is_accumulating() {
+                    assert_eq!(n.out_scale, working_scale);
+                }
+            }
+        }
+
+        assert_eq!(variable.len(), uniform.len());
+        for (v, u) in variable.iter().zip(uniform.iter()) {
+            assert!((v - u).abs() < 0.05, "{} vs {}", v, u);
+        }
+    }
+
+    #[test]
+    fn shapes_load_before_quantization() {
+        let run_args = RunArgs::default();
+        let shaped = Model::load_shapes_only(
+            &run_args,
+            std::path::Path::new("examples/onnx/1l_mlp/network.onnx"),
+        )
+        .unwrap();
+
+        let shapes = shaped.node_shapes().unwrap();
+        let input = shapes.iter().find(|s| s.op == "Source").unwrap();
+        assert_eq!(input.out_dims, vec![vec![1, 3]]);
+
+        let model = shaped.quantize().unwrap();
+        for shape in shapes {
+            if let Some(node) = model.graph.nodes.get(&shape.idx) {
+                assert_eq!(node.out_dims(), shape.out_dims, "node {}", shape.idx);
+            }
+        }
+    }
+
+    /// An onnx model applying `op` to an input and the given `(name, dims, values)` initializers.
+    fn op_onnx(
+        op: &str,
+        input_dims: &[usize],
+        output_dims: &[usize],
+        initializers: &[(&str, Vec<usize>, Vec<f32>)],
+    ) -> Vec<u8> {
+        chain_onnx(op, input_dims, output_dims, &[initializers])
+    }
+
+    /// An onnx model applying `op` once per entry of `layers`, each to the previous output and that
+    /// entry's `(name, dims, values)` initializers.
+    fn chain_onnx(
+        op: &str,
+        input_dims: &[usize],
+        output_dims: &[usize],
+        layers: &[&[(&str, Vec<usize>, Vec<f32>)]],
+    ) -> Vec<u8> {
+        let mut model = crate::dev_fixtures::OnnxModel::new(&op.to_lowercase());
+        model.input("input", input_dims);
+        for (i, initializers) in layers.iter().enumerate() {
+            let input = match i {
+                0 => "input".to_string(),
+                _ => format!("hidden{}", i - 1),
+            };
+            let output = match i + 1 == layers.len() {
+                true => "output".to_string(),
+                false => format!("hidden{}", i),
+            };
+            let mut inputs = vec![input.as_str()];
+            for (name, dims, values) in initializers.iter() {
+                model.initializer(name, dims, values);
+                inputs.push(*name);
+            }
+            model.node(op, &inputs, &[output.as_str()], &[]);
+        }
+        model.output("output", output_dims);
+        model.to_bytes()
+    }
+
+    /// An onnx model applying `op` to an input and a weight, whose `i`th (flat) entry is `weight(i)`.
+    fn weighted_onnx(
+        op: &str,
+        input_dims: &[usize],
+        weight_dims: &[usize],
+        output_dims: &[usize],
+        weight: impl Fn(usize) -> f32,
+    ) -> Vec<u8> {
+        let values = (0..weight_dims.iter().product()).map(weight).collect_vec();
+        op_onnx(
+            op,
+            input_dims,
+            output_dims,
+            &[("weight", weight_dims.to_vec(), values)],
+        )
+    }
+
+    /// An onnx model multiplying a `[1, n]` input by an `[n, m]` weight, whose `i`th (flat) entry is `weight(i)`.
+    fn matmul_onnx(n: usize, m: usize, weight: impl Fn(usize) -> f32) -> Vec<u8> {
+        weighted_onnx("MatMul", &[1, n], &[n, m], &[1, m], weight)
+    }
+
+    fn constants(model: &Model) -> Vec<&Constant<Fp>> {
+        model
+            .graph
+            .nodes
+            .values()
+            .filter_map(|n| match n {
+                NodeType::Node(n) => n.opkind.get_constant(),
+                NodeType::SubGraph { .. } => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn constants_are_quantized_lazily() {
+        let (n, m) = (512, 256);
+        let onnx = matmul_onnx(n, m, |i| ((i % 7) as f32 - 3.0) * 0.01);
+        let run_args = RunArgs::default();
+        let mut model = Model::new(&mut &onnx[..], &run_args).unwrap();
+
+        // only the raw weights are held after parsing, about as much memory as the file itself
+        let raw_bytes: usize = constants(&model)
+            .iter()
+            .map(|c| c.raw_values.len() * std::mem::size_of::<f32>())
+            .sum();
+        assert_eq!(raw_bytes, n * m * std::mem::size_of::<f32>());
+        assert!(raw_bytes <= onnx.len());
+        let num_params: usize = model
+            .const_shapes()
+            .iter()
+            .map(|s| s.iter().product::<usize>())
+            .sum();
+        assert_eq!(num_params, n * m);
+        assert!(constants(&model).iter().all(|c| !c.is_quantized()));
+
+        model.quantize_constants().unwrap();
+        for c in constants(&model) {
+            assert!(c.is_quantized());
+            assert!(c.raw_values.is_empty());
+            assert_eq!(c.quantized_values().unwrap().len(), n * m);
+        }
+    }
+
+    #[test]
+    fn sparse_weights_skip_zero_products() {
+        use crate::graph::GraphCircuit;
+        use halo2_proofs::poly::kzg::commitment::KZGCommitmentScheme;
+
+        let (n, m) = (512, 16);
+        // 90% of the weights are zero
+        let onnx = matmul_onnx(n, m, |i| {
+            if i % 10 == 0 {
+                ((i % 7) as f32 - 3.0) * 0.1 + 0.05
+            } else {
+                0.0
+            }
+        });
+        let input = (0..n)
+            .map(|i| crate::fieldutils::integer_rep_to_felt::<Fp>((i % 13) as IntegerRep - 6))
+            .collect_vec();
+
+        let build = |param_visibility, sparse_weights_threshold| {
+            let run_args = RunArgs {
+                param_visibility,
+                sparse_weights_threshold,
+                ..RunArgs::default()
+            };
+            let model = Model::new(&mut &onnx[..], &run_args).unwrap();
+            GraphCircuit::new(model, &run_args).unwrap()
+        };
+        let run = |sparse_weights_threshold| {
+            let circuit = build(Visibility::Fixed, sparse_weights_threshold);
+            let mut inputs = vec![Tensor::new(Some(&input), &[1, n]).unwrap()];
+            let run_args = &circuit.settings().run_args;
+            let witness = circuit
+                .forward::<KZGCommitmentScheme<halo2curves::bn256::Bn256>>(
+                    &mut inputs,
+                    None,
+                    None,
+                    RegionSettings::all_true(run_args.decomp_base, run_args.decomp_legs),
+                )
+                .unwrap();
+            (circuit, witness)
+        };
+        let is_sparse = |circuit: &GraphCircuit| {
+            circuit
+                .model()
+                .graph
+                .nodes
+                .values()
+                .any(|n| match n.opkind() {
+                    SupportedOp::RebaseScale(op) => {
+                        matches!(*op.inner, SupportedOp::Linear(PolyOp::SparseMatMul { .. }))
+                    }
+                    op => matches!(op, SupportedOp::Linear(PolyOp::SparseMatMul { .. })),
+                })
+        };
+        // the cells of the most costly node, the matmul
+        let matmul_cells = |circuit: &GraphCircuit| {
+            let estimate = circuit
+                .model()
+                .estimate_resources(&circuit.settings().run_args)
+                .unwrap();
+            assert!(estimate.unestimated.is_empty());
+            estimate
+                .nodes
+                .iter()
+                .map(|(_, cells)| *cells)
+                .max()
+                .unwrap()
+        };
+
+        let (dense, dense_witness) = run(None);
+        let (mut sparse, sparse_witness) = run(Some(0.8));
+        assert!(!is_sparse(&dense));
+        assert!(is_sparse(&sparse));
+        assert_eq!(dense_witness.outputs, sparse_witness.outputs);
+
+        // the cost report has the layer close to 10x smaller, and it lays out in fewer rows
+        let (dense_cells, sparse_cells) = (matmul_cells(&dense), matmul_cells(&sparse));
+        assert!(
+            sparse_cells * 8 < dense_cells,
+            "{} vs {}",
+            sparse_cells,
+            dense_cells
+        );
+        let (dense_rows, sparse_rows) = (dense.settings().num_rows, sparse.settings().num_rows);
+        assert!(
+            sparse_rows * 3 < dense_rows,
+            "{} vs {}",
+            sparse_rows,
+            dense_rows
+        );
+
+        // a threshold above the weights' sparsity keeps the dense layout
+        assert!(!is_sparse(&build(Visibility::Fixed, Some(0.95))));
+        // as do weights that aren't fixed, whose skipped entries would go unconstrained
+        for visibility in [
+            Visibility::Private,
+            Visibility::Hashed {
+                hash_is_public: false,
+                outlets: vec![],
+            },
+        ] {
+            assert!(!is_sparse(&build(visibility, Some(0.8))));
+        }
+
+        mock_prove(&mut sparse, &sparse_witness);
+    }
+
+    #[test]
+    fn wide_dot_products_fit_a_column_budget() {
+        use crate::graph::GraphCircuit;
+        use halo2_proofs::poly::kzg::commitment::KZGCommitmentScheme;
+
+        // a dot product is accumulated down the rows of its columns, so a 2048 wide layer fits a
+        // 256 column budget by taking more rows, and needs no splitting up
+        let (n, m, budget) = (2048, 4, 256);
+        let onnx = matmul_onnx(n, m, |i| ((i % 11) as f32 - 5.0) * 0.02);
+        let input = (0..n)
+            .map(|i| crate::fieldutils::integer_rep_to_felt::<Fp>((i % 13) as IntegerRep - 6))
+            .collect_vec();
+
+        let run = |max_advice_cols| {
+            let run_args = RunArgs {
+                max_advice_cols,
+                ..RunArgs::default()
+            };
+            let model = Model::new(&mut &onnx[..], &run_args).unwrap();
+            let mut circuit = GraphCircuit::new(model, &run_args).unwrap();
+            let mut inputs = vec![Tensor::new(Some(&input), &[1, n]).unwrap()];
+            let witness = circuit
+                .forward::<KZGCommitmentScheme<halo2curves::bn256::Bn256>>(
+                    &mut inputs,
+                    None,
+                    None,
+                    RegionSettings::all_true(run_args.decomp_base, run_args.decomp_legs),
+                )
+                .unwrap();
+            (circuit, witness)
+        };
+
+        let (_, reference) = run(None);
+        let (mut circuit, witness) = run(Some(budget));
+        assert_eq!(reference.outputs, witness.outputs);
+
+        mock_prove(&mut circuit, &witness);
+        let settings = circuit.settings();
+        assert!(settings.advice_width(settings.run_args.logrows) <= budget);
+    }
+
+    #[test]
+    fn conv_weights_share_fixed_columns() {
+        use crate::graph::GraphCircuit;
+        use halo2_proofs::plonk::Circuit;
+        use halo2_proofs::poly::kzg::commitment::KZGCommitmentScheme;
+
+        let c = 32;
+        let onnx = weighted_onnx("Conv", &[1, c, 3, 3], &[c, c, 3, 3], &[1, c, 1, 1], |i| {
+            ((i * 7919 % 1000) as f32 / 1000.0 - 0.5) * 0.1
+        });
+        let run_args = RunArgs {
+            param_visibility: Visibility::Fixed,
+            ..RunArgs::default()
+        };
+        let model = Model::new(&mut &onnx[..], &run_args).unwrap();
+        let mut circuit = GraphCircuit::new(model, &run_args).unwrap();
+
+        let input = (0..c * 9)
+            .map(|i| crate::fieldutils::integer_rep_to_felt::<Fp>((i % 13) as IntegerRep - 6))
+            .collect_vec();
+        let mut inputs = vec![Tensor::new(Some(&input), &[1, c, 3, 3]).unwrap()];
+        let witness = circuit
+            .forward::<KZGCommitmentScheme<halo2curves::bn256::Bn256>>(
+                &mut inputs,
+                None,
+                None,
+                RegionSettings::all_true(run_args.decomp_base, run_args.decomp_legs),
+            )
+            .unwrap();
+        circuit
+            .calc_min_logrows(
+                (witness.min_lookup_inputs, witness.max_lookup_inputs),
+                witness.max_range_size,
+                None,
+                2.0,
+            )
+            .unwrap();
+
+        // the kernel's constants are laid out down the rows of a shared pool of fixed columns
+        let mut cs = ConstraintSystem::<Fp>::default();
+        GraphCircuit::configure_with_params(&mut cs, circuit.settings().clone());
+        assert!(
+            cs.num_fixed_columns() < 100,
+            "{} fixed columns for {} kernel elements",
+            cs.num_fixed_columns(),
+            c * c * 9
+        );
+
+        circuit.load_graph_witness(&witness).unwrap();
+        let public_inputs = circuit.prepare_public_inputs(&witness).unwrap();
+        halo2_proofs::dev::MockProver::run(
+            circuit.settings().run_args.logrows,
+            &circuit,
+            vec![public_inputs],
+        )
+        .unwrap()
+        .assert_satisfied();
+    }
+
+    #[test]
+    fn bias_is_added_at_the_product_scale() {
+        use crate::graph::GraphCircuit;
+        use halo2_proofs::poly::kzg::commitment::KZGCommitmentScheme;
+
+        // a bias added at the weights' scale rather than the products' would be off by 2^7
+        let (n, m) = (18, 3);
+        let x = (0..n).map(|i| (i % 5) as f32 * 0.25 - 0.5).collect_vec();
+        let w = (0..n * m)
+            .map(|i| ((i * 37 % 11) as f32 - 5.0) * 0.05)
+            .collect_vec();
+        let b = (0..m).map(|j| 0.75 - j as f32 * 0.5).collect_vec();
+
+        let gemm = op_onnx(
+            "Gemm",
+            &[1, n],
+            &[1, m],
+            &[
+                ("weight", vec![n, m], w.clone()),
+                ("bias", vec![m], b.clone()),
+            ],
+        );
+        let gemm_expected = (0..m)
+            .map(|j| (0..n).map(|i| x[i] * w[i * m + j]).sum::<f32>() + b[j])
+            .collect_vec();
+        // a kernel covering the whole [2, 3, 3] input
+        let conv = op_onnx(
+            "Conv",
+            &[1, 2, 3, 3],
+            &[1, m, 1, 1],
+            &[
+                ("weight", vec![m, 2, 3, 3], w.clone()),
+                ("bias", vec![m], b.clone()),
+            ],
+        );
+        let conv_expected = (0..m)
+            .map(|j| (0..n).map(|i| x[i] * w[j * n + i]).sum::<f32>() + b[j])
+            .collect_vec();
+
+        for (onnx, input_dims, expected) in [
+            (gemm, vec![1, n], gemm_expected),
+            (conv, vec![1, 2, 3, 3], conv_expected),
+        ] {
+            let run_args = RunArgs::default();
+            let model = Model::new(&mut &onnx[..], &run_args).unwrap();
+            let mut circuit = GraphCircuit::new(model, &run_args).unwrap();
+            let input = x
+                .iter()
+                .map(|v| {
+                    let q = crate::graph::quantize_float(&(*v as f64), 0.0, run_args.input_scale)
+                        .unwrap();
+                    crate::fieldutils::integer_rep_to_felt::<Fp>(q)
+                })
+                .collect_vec();
+            let mut inputs = vec![Tensor::new(Some(&input), &input_dims).unwrap()];
+            let witness = circuit
+                .forward::<KZGCommitmentScheme<halo2curves::bn256::Bn256>>(
+                    &mut inputs,
+                    None,
+                    None,
+                    RegionSettings::all_true(run_args.decomp_base, run_args.decomp_legs),
+                )
+                .unwrap();
+
+            let scales = circuit.model().graph.get_output_scales().unwrap();
+            let output = &witness.get_float_outputs(&scales)[0];
+            assert_eq!(output.len(), m);
+            for (got, want) in output.iter().zip(expected.iter()) {
+                assert!((got - want).abs() < 0.05, "{} vs {}", got, want);
+            }
+        }
+    }
+
+    #[test]
+    fn fused_linear_layers_match_the_unfused_model() {
+        use crate::graph::GraphCircuit;
+        use halo2_proofs::poly::kzg::commitment::KZGCommitmentScheme;
+
+        let values = |len: usize, seed: usize| {
+            (0..len)
+                .map(|i| ((i * 37 + seed * 11) % 13) as f32 * 0.05 - 0.3)
+                .collect_vec()
+        };
+        // three chained gemms, the middle one without a bias
+        let gemms = chain_onnx(
+            "Gemm",
+            &[1, 6],
+            &[1, 3],
+            &[
+                &[
+                    ("w0", vec![6, 5], values(30, 0)),
+                    ("b0", vec![5], values(5, 1)),
+                ],
+                &[("w1", vec![5, 4], values(20, 2))],
+                &[
+                    ("w2", vec![4, 3], values(12, 3)),
+                    ("b2", vec![3], values(3, 4)),
+                ],
+            ],
+        );
+        // two pointwise convs
+        let convs = chain_onnx(
+            "Conv",
+            &[1, 3, 2, 2],
+            &[1, 2, 2, 2],
+            &[
+                &[
+                    ("w0", vec![4, 3, 1, 1], values(12, 5)),
+                    ("b0", vec![4], values(4, 6)),
+                ],
+                &[
+                    ("w1", vec![2, 4, 1, 1], values(8, 7)),
+                    ("b1", vec![2], values(2, 8)),
+                ],
+            ],
+        );
+
+        let run = |onnx: &[u8], input_dims: &[usize], fuse_linear: bool| {
+            let run_args = RunArgs {
+                fuse_linear,
+                ..RunArgs::default()
+            };
+            let model = Model::new(&mut &onnx[..], &run_args).unwrap();
+            let num_nodes = model.graph.nodes.len();
+            let mut circuit = GraphCircuit::new(model, &run_args).unwrap();
+            let input = values(input_dims.iter().product(), 9)
+                .iter()
+                .map(|v| {
+                    let q = crate::graph::quantize_float(&(*v as f64), 0.0, run_args.input_scale)
+                        .unwrap();
+                    crate::fieldutils::integer_rep_to_felt::<Fp>(q)
+                })
+                .collect_vec();
+            let mut inputs = vec![Tensor::new(Some(&input), input_dims).unwrap()];
+            let witness = circuit
+                .forward::<KZGCommitmentScheme<halo2curves::bn256::Bn256>>(
+                    &mut inputs,
+                    None,
+                    None,
+                    RegionSettings::all_true(run_args.decomp_base, run_args.decomp_legs),
+                )
+                .unwrap();
+            let scales = circuit.model().graph.get_output_scales().unwrap();
+            (num_nodes, witness.get_float_outputs(&scales)[0].clone())
+        };
+
+        for (onnx, input_dims) in [(gemms, vec![1, 6]), (convs, vec![1, 3, 2, 2])] {
+            let (unfused_nodes, unfused) = run(&onnx, &input_dims, false);
+            let (fused_nodes, fused) = run(&onnx, &input_dims, true);
+            assert!(fused_nodes < unfused_nodes);
+            assert_eq!(fused.dims(), unfused.dims());
+            for (got, want) in fused.iter().zip(unfused.iter()) {
+                assert!((got - want).abs() < 0.05, "{} vs {}", got, want);
+            }
+        }
+    }
+
+    #[test]
+    fn crop_is_folded_into_conv_padding() {
+        use crate::graph::tests::assert_dir_matches_tract;
+
+        // whether the model still slices, and the padding of each of its convs
+        let layout = |dir: &std::path::Path| {
+            let circuit = crate::graph::GraphCircuit::from_run_args(
+                &RunArgs::default(),
+                &dir.join("network.onnx"),
+            )
+            .unwrap();
+            let nodes = &circuit.model().graph.nodes;
+            let slices = nodes.values().any(|n| {
+                matches!(
+                    n,
+                    NodeType::Node(Node {
+                        opkind: SupportedOp::Linear(PolyOp::Slice { .. }),
+                        ..
+                    })
+                )
+            });
+            let paddings = nodes
+                .values()
+                .filter_map(|n| match n {
+                    NodeType::Node(n) => match &n.opkind {
+                        SupportedOp::Linear(PolyOp::Conv { padding, .. }) => Some(padding.clone()),
+                        SupportedOp::RebaseScale(op) => match &*op.inner {
+                            SupportedOp::Linear(PolyOp::Conv { padding, .. }) => {
+                                Some(padding.clone())
+                            }
+                            _ => None,
+                        },
+                        _ => None,
+                    },
+                    _ => None,
+                })
+                .collect::<Vec<_>>();
+            (slices, paddings)
+        };
+
+        // an unpadded conv crops the outer row and column off each side itself
+        let dir = crate::dev_fixtures::cropped_conv(2, 8).write().unwrap();
+        assert_eq!(layout(&dir), (false, vec![vec![(-1, -1), (-1, -1)]]));
+        assert_dir_matches_tract(&dir, 0.1);
+
+        // a padded conv pads rows 1..6 and columns 2..5 of the 6x6 input, which stay sliced
+        let dir = std::path::Path::new("examples/onnx/1l_crop_conv");
+        assert_eq!(layout(dir), (true, vec![vec![(1, 1), (1, 1)]]));
+        assert_dir_matches_tract(dir, 0.1);
+    }
+
+    #[test]
+    fn constant_if_is_inlined() {
+        use crate::graph::{input::DataSource, input::GraphData, GraphCircuit};
+
+        let dir = std::path::Path::new("examples/onnx/1l_if_const");
+        let data = GraphData::from_path(dir.join("input.json")).unwrap();
+        let run_args = RunArgs::default();
+        let mut circuit =
+            GraphCircuit::from_run_args(&run_args, &dir.join("network.onnx")).unwrap();
+        assert!(!circuit.model().graph.nodes.values().any(|n| matches!(
+            n,
+            NodeType::Node(Node {
+                opkind: SupportedOp::Unknown(_),
+                ..
+            })
+        )));
+
+        let witness = forward(&mut circuit, &data);
+        let scales = circuit.model().graph.get_output_scales().unwrap();
+        let expected = match data.output_data {
+            Some(DataSource::File(expected)) => expected,
+            _ => unreachable!(),
+        };
+        // the fixture takes the then branch, a matmul and relu; the else branch would be a sigmoid
+        for (got, want) in witness.get_float_outputs(&scales)[0]
+            .iter()
+            .zip(&expected[0])
+        {
+            assert!(
+                (*got as f64 - want.to_float()).abs() < 0.05,
+                "{} vs {:?}",
+                got,
+                want
+            );
+        }
+    }
+
+    #[test]
+    fn qlinear_ops_match_their_integer_reference() {
+        use crate::graph::{input::DataSource, input::GraphData, GraphCircuit};
+
+        let dir = std::path::Path::new("examples/onnx/1l_qlinear");
+        let data = GraphData::from_path(dir.join("input.json")).unwrap();
+        let run_args = RunArgs::default();
+        let mut circuit =
+            GraphCircuit::from_run_args(&run_args, &dir.join("network.onnx")).unwrap();
+        let requantizations = circuit
+            .model()
+            .graph
+            .nodes
+            .values()
+            .filter(|n| {
+                matches!(
+                    n,
+                    NodeType::Node(Node {
+                        opkind: SupportedOp::Nonlinear(LookupOp::QuantizeLinear(_)),
+                        ..
+                    })
+                )
+            })
+            .count();
+        // the input's, then one after each of the conv and matmul
+        assert_eq!(requantizations, 3);
+
+        let witness = forward(&mut circuit, &data);
+        let scales = circuit.model().graph.get_output_scales().unwrap();
+        let expected = match data.output_data {
+            Some(DataSource::File(expected)) => expected,
+            _ => unreachable!(),
+        };
+        // outputs are codes dequantized by 0.0917, so landing within half of it means every
+        // code matches
+        for (got, want) in witness.get_float_outputs(&scales)[0]
+            .iter()
+            .zip(&expected[0])
+        {
+            assert!(
+                (*got as f64 - want.to_float()).abs() < 0.0917 / 2.0,
+                "{} vs {:?}",
+                got,
+                want
+            );
+        }
+    }
+
+    #[test]
+    fn qlinear_mobilenet_block_matches_onnxruntime() {
+        use crate::graph::{input::DataSource, input::GraphData, GraphCircuit};
+
+        let dir = std::path::Path::new("examples/onnx/qlinear_mobilenet_block");
+        let data = GraphData::from_path(dir.join("input.json")).unwrap();
+        let run_args = RunArgs::default();
+        let mut circuit =
+            GraphCircuit::from_run_args(&run_args, &dir.join("network.onnx")).unwrap();
+        let requantizations = circuit
+            .model()
+            .graph
+            .nodes
+            .values()
+            .filter(|n| {
+                matches!(
+                    n,
+                    NodeType::Node(Node {
+                        opkind: SupportedOp::Nonlinear(LookupOp::QuantizeLinear(_)),
+                        ..
+                    })
+                )
+            })
+            .count();
+        // the input's, then one after each of the expand, depthwise and projection convs
+        assert_eq!(requantizations, 4);
+
+        let witness = forward(&mut circuit, &data);
+        let scales = circuit.model().graph.get_output_scales().unwrap();
+        let expected = match data.output_data {
+            Some(DataSource::File(expected)) => expected,
+            _ => unreachable!(),
+        };
+        // the expected outputs are onnxruntime's, dequantized by 0.05, so landing within half of
+        // it means every code of the block matches
+        assert_eq!(expected[0].len(), 4 * 4 * 4);
+        for (got, want) in witness.get_float_outputs(&scales)[0]
+            .iter()
+            .zip(&expected[0])
+        {
+            assert!(
+                (*got as f64 - want.to_float()).abs() < 0.05 / 2.0,
+                "{} vs {:?}",
+                got,
+                want
+            );
+        }
+    }
+
+    #[test]
+    fn sklearn_logistic_regression_outputs_its_probabilities() {
+        use crate::graph::{input::DataSource, input::GraphData, GraphCircuit};
+
+        let dir = std::path::Path::new("examples/onnx/sklearn_logistic_regression");
+        let data = GraphData::from_path(dir.join("input.json")).unwrap();
+        let run_args = RunArgs::default();
+        let mut circuit =
+            GraphCircuit::from_run_args(&run_args, &dir.join("network.onnx")).unwrap();
+        // the class label output is dropped, leaving the zipmapped probabilities
+        assert_eq!(circuit.model().graph.outputs.len(), 1);
+
+        let witness = forward(&mut circuit, &data);
+        let scales = circuit.model().graph.get_output_scales().unwrap();
+        let expected = match data.output_data {
+            Some(DataSource::File(expected)) => expected,
+            _ => unreachable!(),
+        };
+        for (got, want) in witness.get_float_outputs(&scales)[0]
+            .iter()
+            .zip(&expected[0])
+        {
+            assert!(
+                (*got as f64 - want.to_float()).abs() < 0.02,
+                "{} vs {:?}",
+                got,
+                want
+            );
+        }
+    }
+
+    #[test]
+    fn masked_attention_scores_softmax_to_exact_zeros() {
+        use crate::graph::{input::DataSource, input::GraphData, GraphCircuit};
+
+        let dir = std::path::Path::new("examples/onnx/1l_attention_mask");
+        let data = GraphData::from_path(dir.join("input.json")).unwrap();
+        let run_args = RunArgs::default();
+        let mut circuit =
+            GraphCircuit::from_run_args(&run_args, &dir.join("network.onnx")).unwrap();
+        let witness = forward(&mut circuit, &data);
+        // the -inf mask saturates rather than blowing up the exp table
+        assert!(witness.min_lookup_inputs > -(1 << 12));
+        // without counting as clipped
+        assert!(circuit.model().clip_rates().is_empty());
+
+        let scales = circuit.model().graph.get_output_scales().unwrap();
+        let expected = match data.output_data {
+            Some(DataSource::File(expected)) => expected,
+            _ => unreachable!(),
+        };
+        let outputs = witness.get_float_outputs(&scales);
+        for (i, (got, want)) in outputs[0].iter().zip(&expected[0]).enumerate() {
+            if i % 4 > i / 4 {
+                assert_eq!(*got, 0.0, "masked score {} leaked through", i);
+            } else {
+                assert!(
+                    (*got as f64 - want.to_float()).abs() < 0.05,
+                    "{} vs {:?}",
+                    got,
+                    want
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn only_softmaxes_of_masked_inputs_saturate() {
+        use crate::graph::GraphCircuit;
+
+        fn masked(op: &SupportedOp) -> Option<bool> {
+            match op {
+                SupportedOp::Hybrid(HybridOp::Softmax { masked, .. }) => Some(*masked),
+                SupportedOp::RebaseScale(op) => masked(&op.inner),
+                _ => None,
+            }
+        }
+
+        let softmaxes = |model: &str| {
+            let path = format!("examples/onnx/{}/network.onnx", model);
+            let circuit =
+                GraphCircuit::from_run_args(&RunArgs::default(), std::path::Path::new(&path))
+                    .unwrap();
+            circuit
+                .model()
+                .graph
+                .nodes
+                .values()
+                .filter_map(|n| match n {
+                    NodeType::Node(n) => masked(&n.opkind),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(softmaxes("1l_attention_mask"), vec![true]);
+        assert_eq!(softmaxes("1l_softmax"), vec![false]);
+    }
+
+    #[test]
+    fn constant_softmax_temperature_is_folded_into_the_exp_scale() {
+        use crate::graph::{input::DataSource, input::GraphData, GraphCircuit};
+
+        fn softmax_input_scale(op: &SupportedOp) -> Option<f32> {
+            match op {
+                SupportedOp::Hybrid(HybridOp::Softmax { input_scale, .. }) => Some(input_scale.0),
+                SupportedOp::RebaseScale(op) => softmax_input_scale(&op.inner),
+                _ => None,
+            }
+        }
+
+        let dir = std::path::Path::new("examples/onnx/1l_softmax_temperature");
+        let data = GraphData::from_path(dir.join("input.json")).unwrap();
+        let run_args = RunArgs::default();
+        let mut circuit =
+            GraphCircuit::from_run_args(&run_args, &dir.join("network.onnx")).unwrap();
+
+        // (whether the softmax reads a multiply, its input scale) in output order
+        let nodes = &circuit.model().graph.nodes;
+        let softmaxes = circuit
+            .model()
+            .graph
+            .outputs
+            .iter()
+            .map(|(idx, _)| match &nodes[idx] {
+                NodeType::Node(n) => {
+                    let reads_multiply = matches!(
+                        &nodes[&n.inputs[0].0],
+                        NodeType::Node(Node {
+                            opkind: SupportedOp::RebaseScale(_)
+                                | SupportedOp::Linear(PolyOp::Mult | PolyOp::Identity { .. }),
+                            ..
+                        })
+                    );
+                    (reads_multiply, softmax_input_scale(&n.opkind).unwrap())
+                }
+                _ => unreachable!(),
+            })
+            .collect::<Vec<_>>();
+        let default_scale = scale_to_multiplier(run_args.input_scale) as f32;
+        assert_eq!(
+            softmaxes,
+            vec![
+                (false, default_scale * 2.0),
+                (false, default_scale / 1.5),
+                // the scale is an input, so stays a multiply
+                (true, default_scale),
+            ]
+        );
+
+        let witness = forward(&mut circuit, &data);
+        let scales = circuit.model().graph.get_output_scales().unwrap();
+        let expected = match data.output_data {
+            Some(DataSource::File(expected)) => expected,
+            _ => unreachable!(),
+        };
+        for (outputs, expected) in witness.get_float_outputs(&scales).iter().zip(&expected) {
+            for (got, want) in outputs.iter().zip(expected) {
+                assert!(
+                    (*got as f64 - want.to_float()).abs() < 0.02,
+                    "{} vs {:?}",
+                    got,
+                    want
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn layout_drops_outputs_after_their_last_consumer() {
+        let run_args = RunArgs::default();
+        let layout = |depth| {
+            let onnx = crate::dev_fixtures::relu_chain(4, depth).model.to_bytes();
+            let model = Model::new(&mut &onnx[..], &run_args).unwrap();
+            let values: [IntegerRep; 4] = [1, -1, 0, 1];
+            let input: ValTensor<Fp> = Tensor::new(Some(&values), &[1, 4])
+                .unwrap()
+                .map(|v| ValType::Value(Value::known(integer_rep_to_felt(v))))
+                .into();
+            let mut results = BTreeMap::from([(model.graph.inputs[0], vec![input])]);
+            let mut model_config = ModelConfig {
+                base: PolyConfig::dummy(run_args.logrows as usize, run_args.num_inner_cols),
+                vars: ModelVars::new_dummy(),
+            };
+            let mut region = RegionCtx::new_dummy(
+                0,
+                run_args.num_inner_cols,
+                RegionSettings::all_true(run_args.decomp_base, run_args.decomp_legs),
+            );
+            let mut peak = 0;
+            model
+                .layout_nodes(
+                    &mut model_config,
+                    &mut region,
+                    &mut results,
+                    Some(&model.dead_after()),
+                    None,
+                    Some(&mut peak),
+                    None,
+                )
+                .unwrap();
+            let static_peak = model.peak_live_values();
+            (results.into_keys().collect_vec(), peak, static_peak)
+        };
+
+        // only the input and the last Relu are left, each Relu having taken the one before it
+        let (kept, peak, static_peak) = layout(20);
+        assert_eq!(kept.len(), 2);
+        assert_eq!(peak, 2 * 4);
+        assert!(peak <= static_peak);
+        let (kept, shallow_peak, _) = layout(5);
+        assert_eq!(kept.len(), 2);
+        assert_eq!(shallow_peak, peak);
+    }
+
+    #[test]
+    fn dynamic_if_reports_its_subgraph_ops() {
+        use crate::dev_fixtures::{Attribute, OnnxModel};
+
+        let branch = |name: &str, ops: &[&str]| {
+            let mut graph = OnnxModel::new(name);
+            for (i, op) in ops.iter().enumerate() {
+                let input = match i {
+                    0 => "input".to_string(),
+                    _ => format!("{}{}", name, i - 1),
+                };
+                let output = format!("{}{}", name, i);
+                graph.node(op, &[input.as_str()], &[output.as_str()], &[]);
+            }
+            graph.output(&format!("{}{}", name, ops.len() - 1), &[1, 3]);
+            Attribute::Graph(graph)
+        };
+
+        // the condition is computed from the input, so neither branch can be picked at load time
+        let onnx = OnnxModel::new("gate")
+            .input("input", &[1, 3])
+            .node("ReduceSum", &["input"], &["sum"], &[])
+            .node("Greater", &["sum", "sum"], &["cond"], &[])
+            .node(
+                "If",
+                &["cond"],
+                &["output"],
+                &[
+                    ("then_branch", branch("then", &["Relu", "Relu", "Sigmoid"])),
+                    ("else_branch", branch("else", &["Neg"])),
+                ],
+            )
+            .output("output", &[1, 3])
+            .to_bytes();
+
+        match Model::new(&mut &onnx[..], &RunArgs::default()) {
+            Err(GraphError::DynamicControlFlow(op, name, inventory)) => {
+                assert_eq!(op, "If");
+                assert_eq!(name, "if_2");
+                assert_eq!(inventory, "Neg x1, Relu x2, Sigmoid x1");
+            }
+            Err(e) => panic!("unexpected error {}", e),
+            Ok(_) => panic!("a dynamic If loaded"),
+        }
+    }
+}
@@ -3,14 +3,16 @@ use crate::circuit::modules::poseidon::spec::{PoseidonSpec, POSEIDON_RATE, POSEI
 use crate::circuit::modules::poseidon::{PoseidonChip, PoseidonConfig};
 use crate::circuit::modules::Module;
 use crate::circuit::region::ConstantsMap;
-use crate::tensor::{Tensor, ValTensor};
-use halo2_proofs::circuit::Layouter;
+use crate::tensor::{Tensor, ValTensor, ValType};
+use halo2_proofs::circuit::{Layouter, Value};
 use halo2_proofs::plonk::{Column, ConstraintSystem, Error, Instance, VerifyingKey};
 use halo2_proofs::poly::commitment::CommitmentScheme;
 use halo2curves::bn256::{Fr as Fp, G1Affine};
+use halo2curves::ff::PrimeField;
 use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 
+use super::dataset::{row_leaf, DatasetOpening, DatasetPath};
 use super::errors::GraphError;
 use super::{VarVisibility, Visibility};
 
@@ -19,6 +21,9 @@ pub const POSEIDON_LEN_GRAPH: usize = 32;
 /// Poseidon number of instances
 pub const POSEIDON_INSTANCES: usize = 1;
 
+/// Bytes of a context packed into each field element, few enough that it stays below the modulus
+const CONTEXT_BYTES_PER_ELEMENT: usize = 31;
+
 /// Poseidon module type
 pub type ModulePoseidon =
     PoseidonChip<PoseidonSpec, POSEIDON_WIDTH, POSEIDON_RATE, POSEIDON_LEN_GRAPH>;
@@ -63,12 +68,14 @@ impl ModuleConfigs {
     ) {
         if (visibility.input.is_hashed()
             || visibility.output.is_hashed()
-            || visibility.params.is_hashed())
+            || visibility.params.is_hashed()
+            || visibility.params.is_merkle())
             && module_size.poseidon.1[0] > 0
         {
             if visibility.input.is_hashed_public()
                 || visibility.output.is_hashed_public()
                 || visibility.params.is_hashed_public()
+                || visibility.params.is_merkle()
             {
                 if let Some(inst) = self.instance {
                     self.poseidon = Some(ModulePoseidon::configure_with_optional_instance(
@@ -87,6 +94,10 @@ impl ModuleConfigs {
                 self.poseidon = Some(ModulePoseidon::configure_with_optional_instance(cs, None));
             }
         };
+        // a dataset opening hashes the inputs and its path without any module instances
+        if self.poseidon.is_none() && module_size.poseidon.0 > 0 {
+            self.poseidon = Some(ModulePoseidon::configure_with_optional_instance(cs, None));
+        }
     }
 }
 
@@ -102,7 +113,7 @@ pub struct ModuleForwardResult {
 impl ModuleForwardResult {
     /// Get the result
     pub fn get_result(&self, vis: Visibility) -> Vec<Vec<Fp>> {
-        if vis.is_hashed() {
+        if vis.is_hashed() || vis.is_merkle() {
             self.poseidon_hash
                 .clone()
                 .unwrap()
@@ -124,6 +135,34 @@ impl ModuleForwardResult {
     }
 }
 
+/// The Poseidon hash a proof binds a caller-supplied context to (e.g a request id or a chain
+/// address). The bytes are packed little-endian into field elements followed by their length, so
+/// contexts differing only in trailing zero bytes hash differently.
+pub fn context_hash(context: &[u8]) -> Fp {
+    let mut message = context
+        .chunks(CONTEXT_BYTES_PER_ELEMENT)
+        .map(|chunk| {
+            let mut repr = [0u8; 32];
+            repr[..chunk.len()].copy_from_slice(chunk);
+            Fp::from_repr(repr).unwrap()
+        })
+        .collect_vec();
+    message.push(Fp::from(context.len() as u64));
+    ModulePoseidon::run(message).unwrap()[0][0]
+}
+
+/// The Merkle root a model's params are committed to under `merkle` param visibility. Each
+/// non-empty param tensor, in the order of [crate::graph::Model::get_all_params], is a leaf
+/// hashed with [ModulePoseidon]; the root is the Poseidon tree hash of the leaves.
+pub fn merkle_root(params: &[Tensor<Fp>]) -> Fp {
+    let leaves = params
+        .iter()
+        .filter(|x| !x.is_empty())
+        .map(|x| ModulePoseidon::run(x.to_vec()).unwrap()[0][0])
+        .collect_vec();
+    ModulePoseidon::run(leaves).unwrap()[0][0]
+}
+
 #[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
 ///
 pub struct ModuleSizes {
@@ -191,6 +230,36 @@ impl GraphModules {
             }
         }
     }
+    /// A leaf per non-empty param tensor, then the hash of the leaves into the single root instance
+    fn num_merkle_constraints(shapes: Vec<Vec<usize>>, sizes: &mut ModuleSizes) {
+        let leaves = shapes
+            .iter()
+            .map(|shape| shape.iter().product::<usize>())
+            .filter(|len| *len > 0)
+            .collect_vec();
+        if !leaves.is_empty() {
+            for len in &leaves {
+                sizes.poseidon.0 += ModulePoseidon::num_rows(*len);
+            }
+            sizes.poseidon.0 += ModulePoseidon::num_rows(leaves.len());
+            sizes.poseidon.1[0] += 1;
+        }
+    }
+
+    /// The rows of hashing the inputs into a dataset row's leaf and each level of its opening's
+    /// path. The root is a model instance, so the digests take no module instances.
+    pub fn num_dataset_constraints(
+        input_shapes: Vec<Vec<usize>>,
+        depth: usize,
+        sizes: &mut ModuleSizes,
+    ) {
+        let row_len = input_shapes
+            .iter()
+            .map(|shape| shape.iter().product::<usize>())
+            .sum::<usize>();
+        sizes.poseidon.0 += ModulePoseidon::num_rows(row_len) + depth * ModulePoseidon::num_rows(2);
+    }
+
     /// Get the number of constraints and instances for the module
     pub fn num_constraints_and_instances(
         input_shapes: Vec<Vec<usize>>,
@@ -201,7 +270,11 @@ impl GraphModules {
         let mut module_sizes = ModuleSizes::new();
 
         Self::num_constraint_given_shapes(visibility.input, input_shapes, &mut module_sizes);
-        Self::num_constraint_given_shapes(visibility.params, params_shapes, &mut module_sizes);
+        if visibility.params.is_merkle() {
+            Self::num_merkle_constraints(params_shapes, &mut module_sizes);
+        } else {
+            Self::num_constraint_given_shapes(visibility.params, params_shapes, &mut module_sizes);
+        }
         Self::num_constraint_given_shapes(visibility.output, output_shapes, &mut module_sizes);
 
         module_sizes
@@ -287,9 +360,122 @@ impl GraphModules {
             // If the module is encrypted, then we need to encrypt the inputs
         }
 
+        // Hash each tensor into a leaf and only expose the root of the leaves
+        if element_visibility.is_merkle() && !values.is_empty() {
+            if let Some(config) = &mut configs.poseidon {
+                layouter.assign_region(|| "_enter_module_0", |_| Ok(()))?;
+                let chip = ModulePoseidon::new(config.clone());
+                let mut leaves = vec![];
+                for value in values.iter_mut().filter(|x| !x.is_empty()) {
+                    let (assigned, leaf) = chip
+                        .layout_digest(layouter, &[value.clone()], constants)
+                        .map_err(|e| {
+                            log::error!("failed to hash merkle leaf: {:?}", e);
+                            Error::Synthesis
+                        })?;
+                    // the model uses the hashed cells, so it runs on the committed params
+                    *value = assigned;
+                    leaves.push(ValType::from(leaf));
+                }
+                let mut root = vec![Tensor::from(leaves.into_iter()).into()];
+                Self::layout_module(&chip, layouter, &mut root, instance_offset, constants)?;
+            } else {
+                log::error!("Poseidon config not initialized");
+                return Err(Error::Synthesis);
+            }
+        }
+
         Ok(())
     }
 
+    /// Hashes the inputs (flattened and concatenated) into a dataset row's leaf and each level
+    /// of `opening`'s path, replacing the inputs with the hashed cells so that the model runs on
+    /// the opened row. The opening is `None` when laying out without witnesses.
+    pub fn layout_dataset(
+        &mut self,
+        layouter: &mut impl Layouter<Fp>,
+        configs: &mut ModuleConfigs,
+        inputs: &mut [ValTensor<Fp>],
+        opening: Option<&DatasetOpening>,
+        depth: usize,
+        constants: &mut ConstantsMap<Fp>,
+    ) -> Result<DatasetPath, Error> {
+        let config = configs.poseidon.as_ref().ok_or_else(|| {
+            log::error!("Poseidon config not initialized");
+            Error::Synthesis
+        })?;
+        layouter.assign_region(|| "_enter_module_0", |_| Ok(()))?;
+        let chip = ModulePoseidon::new(config.clone());
+
+        let cells = inputs
+            .iter()
+            .map(|x| x.get_inner_tensor().map(|t| t.to_vec()))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| Error::Synthesis)?
+            .concat();
+        let row = cells
+            .iter()
+            .map(|c| c.get_felt_eval())
+            .collect::<Option<Vec<_>>>();
+        let (assigned, leaf) = chip
+            .layout_digest(
+                layouter,
+                &[Tensor::from(cells.into_iter()).into()],
+                constants,
+            )
+            .map_err(|e| {
+                log::error!("failed to hash dataset row: {:?}", e);
+                Error::Synthesis
+            })?;
+        let mut start = 0;
+        for input in inputs.iter_mut() {
+            let end = start + input.len();
+            let mut hashed = assigned
+                .get_slice(&[start..end])
+                .map_err(|_| Error::Synthesis)?;
+            hashed.reshape(input.dims()).map_err(|_| Error::Synthesis)?;
+            *input = hashed;
+            start = end;
+        }
+
+        // the pairs are known only when laying out a witnessed opening
+        let pairs = match (opening, row) {
+            (Some(opening), Some(row)) => opening
+                .pairs(row_leaf(&row))
+                .into_iter()
+                .map(|(l, r)| [Value::known(l), Value::known(r)])
+                .collect_vec(),
+            _ => vec![[Value::unknown(); 2]; depth],
+        };
+        let mut nodes = vec![ValType::from(leaf)];
+        let (mut lefts, mut rights) = (vec![], vec![]);
+        for pair in pairs {
+            let (assigned, digest) = chip
+                .layout_digest(
+                    layouter,
+                    &[Tensor::from(pair.into_iter()).into()],
+                    constants,
+                )
+                .map_err(|e| {
+                    log::error!("failed to hash dataset path: {:?}", e);
+                    Error::Synthesis
+                })?;
+            let pair = assigned.get_inner_tensor().map_err(|_| Error::Synthesis)?;
+            lefts.push(pair[0].clone());
+            rights.push(pair[1].clone());
+            nodes.push(ValType::from(digest));
+        }
+        let root = nodes.pop().ok_or(Error::Synthesis)?;
+
+        Ok(DatasetPath {
+            nodes: Tensor::from(nodes.into_iter()).into(),
+            lefts: Tensor::from(lefts.into_iter()).into(),
+            rights: Tensor::from(rights.into_iter()).into(),
+            bits: DatasetPath::witness_bits(opening, depth),
+            root: Tensor::from([root].into_iter()).into(),
+        })
+    }
+
     /// Run forward pass
     pub fn forward<Scheme: CommitmentScheme<Scalar = Fp, Curve = G1Affine>>(
         inputs: &[Tensor<Scheme::Scalar>],
@@ -300,6 +486,10 @@ impl GraphModules {
         let mut poseidon_hash = None;
         let mut polycommit = None;
 
+        if element_visibility.is_merkle() && !inputs.is_empty() {
+            poseidon_hash = Some(vec![merkle_root(inputs)]);
+        }
+
         if element_visibility.is_hashed() {
             let field_elements = inputs.iter().fold(vec![], |mut acc, x| {
                 let res = ModulePoseidon::run(x.to_vec()).unwrap()[0].clone();
@@ -338,3 +528,83 @@ impl GraphModules {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use halo2_proofs::poly::kzg::commitment::KZGCommitmentScheme;
+    use halo2curves::bn256::Bn256;
+
+    fn hashed_output_visibility() -> VarVisibility {
+        VarVisibility {
+            input: Visibility::Private,
+            params: Visibility::Fixed,
+            output: Visibility::Hashed {
+                hash_is_public: true,
+                outlets: vec![],
+            },
+        }
+    }
+
+    #[test]
+    fn hashed_output_uses_single_instance() {
+        // e.g a 32x32 segmentation map
+        let sizes = GraphModules::num_constraints_and_instances(
+            vec![vec![1, 3]],
+            vec![],
+            vec![vec![1, 32, 32]],
+            hashed_output_visibility(),
+        );
+
+        assert_eq!(sizes.num_instances(), vec![1]);
+        assert!(sizes.max_constraints() > 0);
+    }
+
+    #[test]
+    fn hashed_output_digest_recomputes_from_preimage() {
+        let outputs: Tensor<Fp> = (0..1024u64).map(Fp::from).into();
+
+        let res = GraphModules::forward::<KZGCommitmentScheme<Bn256>>(
+            &[outputs.clone()],
+            &hashed_output_visibility().output,
+            None,
+            None,
+        )
+        .unwrap();
+
+        let instances = res.get_instances();
+        assert_eq!(instances.len(), 1);
+        assert_eq!(instances[0].len(), 1);
+
+        // the verifier only needs the claimed outputs to recompute the digest
+        let recomputed = ModulePoseidon::run(outputs.to_vec()).unwrap()[0].clone();
+        assert_eq!(recomputed, instances[0]);
+    }
+
+    #[test]
+    fn context_hash_separates_contexts() {
+        let hash = context_hash(b"request-42");
+        assert_eq!(hash, context_hash(b"request-42"));
+        assert_ne!(hash, context_hash(b"request-43"));
+        // the length is hashed in, so padding doesn't collide
+        assert_ne!(context_hash(b""), context_hash(&[0]));
+        assert_ne!(context_hash(&[1; 31]), context_hash(&[1; 32]));
+    }
+
+    #[test]
+    fn merkle_root_hashes_each_tensor_as_a_leaf() {
+        let weights: Tensor<Fp> = (0..6u64).map(Fp::from).into();
+        let bias: Tensor<Fp> = (6..8u64).map(Fp::from).into();
+        let root = merkle_root(&[weights.clone(), bias.clone()]);
+
+        let leaves = vec![
+            ModulePoseidon::run(weights.to_vec()).unwrap()[0][0],
+            ModulePoseidon::run(bias.to_vec()).unwrap()[0][0],
+        ];
+        assert_eq!(root, ModulePoseidon::run(leaves).unwrap()[0][0]);
+        // the leaves are ordered, and tensor boundaries are committed to
+        assert_ne!(root, merkle_root(&[bias, weights]));
+        let flat: Tensor<Fp> = (0..8u64).map(Fp::from).into();
+        assert_ne!(root, merkle_root(&[flat]));
+    }
+}
@@ -3,8 +3,6 @@ use super::scale_to_multiplier;
 use super::utilities::node_output_shapes;
 #[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
 use super::VarScales;
-#[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
-use super::Visibility;
 use crate::circuit::hybrid::HybridOp;
 use crate::circuit::lookup::LookupOp;
 use crate::circuit::poly::PolyOp;
@@ -13,11 +11,12 @@ use crate::circuit::Constant;
 use crate::circuit::Input;
 use crate::circuit::Op;
 use crate::circuit::Unknown;
+use crate::fieldutils::{felt_to_integer_rep, integer_rep_to_felt, IntegerRep};
 #[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
 use crate::graph::errors::GraphError;
 #[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
 use crate::graph::new_op_from_onnx;
-use crate::tensor::TensorError;
+use crate::tensor::{Tensor, TensorError};
 use halo2curves::bn256::Fr as Fp;
 #[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
 use log::trace;
@@ -303,6 +302,91 @@ impl SupportedOp {
         }
     }
 
+    /// Evaluates the op directly on integer inputs, independently of its layout.
+    /// Returns `None` for ops that have no reference implementation.
+    pub fn int_reference(
+        &self,
+        inputs: &[Tensor<IntegerRep>],
+    ) -> Result<Option<Tensor<IntegerRep>>, TensorError> {
+        use crate::tensor::ops;
+
+        let refs = inputs.iter().collect::<Vec<_>>();
+        let res = match self {
+            SupportedOp::Linear(op) => match op {
+                PolyOp::Einsum { equation } => ops::einsum(equation, &refs)?,
+                PolyOp::Affine { equation } => {
+                    ops::add(&[ops::einsum(equation, &refs[..2])?, inputs[2].clone()])?
+                }
+                // the skipped products are all zero, so this matches the dense matmul
+                PolyOp::SparseMatMul { equation, .. } => {
+                    let res = ops::einsum(equation, &refs[..2])?;
+                    if inputs.len() == 3 {
+                        ops::add(&[res, inputs[2].clone()])?
+                    } else {
+                        res
+                    }
+                }
+                PolyOp::Conv {
+                    padding,
+                    stride,
+                    group,
+                    channels_last: false,
+                    ..
+                } => ops::conv(&refs, padding, stride, *group)?,
+                PolyOp::Add => ops::add(inputs)?,
+                PolyOp::Sub => ops::sub(inputs)?,
+                PolyOp::Mult => ops::mult(inputs)?,
+                PolyOp::Neg => inputs[0].map(|x| -x),
+                PolyOp::Sum { axes } => {
+                    let dims = (0..inputs[0].dims().len())
+                        .map(|i| (b'a' + i as u8) as char)
+                        .collect::<String>();
+                    let kept = dims
+                        .chars()
+                        .enumerate()
+                        .filter(|(i, _)| !axes.contains(i))
+                        .map(|(_, c)| c)
+                        .collect::<String>();
+                    ops::einsum(&format!("{}->{}", dims, kept), &refs[..1])?
+                }
+                PolyOp::Identity { .. } | PolyOp::Reshape(_) | PolyOp::Flatten(_) => {
+                    inputs[0].clone()
+                }
+                PolyOp::MoveAxis {
+                    source,
+                    destination,
+                } => inputs[0].clone().move_axis(*source, *destination)?,
+                PolyOp::Pad(padding) => ops::pad(&inputs[0], padding.clone(), 0)?,
+                PolyOp::Concat { axis } => ops::concat(&refs, *axis)?,
+                PolyOp::Slice { axis, start, end } => ops::slice(&inputs[0], axis, start, end)?,
+                _ => return Ok(None),
+            },
+            SupportedOp::Nonlinear(op) => {
+                let x = inputs[0].map(integer_rep_to_felt::<Fp>);
+                op.f(&[x])?.output.map(felt_to_integer_rep)
+            }
+            SupportedOp::Hybrid(HybridOp::Div { denom, .. }) => {
+                ops::nonlinearities::const_div(&inputs[0], denom.0 as f64)
+            }
+            SupportedOp::Rescaled(op) => {
+                let rescaled = inputs
+                    .iter()
+                    .zip(&op.scale)
+                    .map(|(x, (_, multiplier))| x.map(|v| v * *multiplier as IntegerRep))
+                    .collect::<Vec<_>>();
+                return op.inner.int_reference(&rescaled);
+            }
+            SupportedOp::RebaseScale(op) => match op.inner.int_reference(inputs)? {
+                Some(res) => {
+                    return SupportedOp::Hybrid(op.rebase_op.clone()).int_reference(&[res])
+                }
+                None => return Ok(None),
+            },
+            _ => return Ok(None),
+        };
+        Ok(Some(res))
+    }
+
     #[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
     fn homogenous_rescale(
         &self,
@@ -314,6 +398,35 @@ impl SupportedOp {
         super::homogenize_input_scales(op, in_scales, inputs_to_scale)
     }
 
+    #[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
+    /// Conv, deconv and affine ops add their bias to the accumulated products, at `input + kernel`
+    /// scale. A bias below that scale which isn't a constant (constants are quantized at the
+    /// product scale on load) is multiplied up to it in the circuit.
+    fn align_bias_scale(self, in_scales: &[crate::Scale]) -> SupportedOp {
+        let has_bias = matches!(
+            self,
+            SupportedOp::Linear(
+                PolyOp::Conv { .. }
+                    | PolyOp::DeConv { .. }
+                    | PolyOp::Affine { .. }
+                    | PolyOp::SparseMatMul { .. }
+            )
+        );
+        match in_scales {
+            [input, kernel, bias] if has_bias && *bias < input + kernel => {
+                SupportedOp::Rescaled(Rescaled {
+                    inner: Box::new(self),
+                    scale: vec![
+                        (0, 1),
+                        (1, 1),
+                        (2, scale_to_multiplier(input + kernel - bias) as u128),
+                    ],
+                })
+            }
+            _ => self,
+        }
+    }
+
     /// Since each associated value of `SupportedOp` implements `Op`, let's define a helper method to retrieve it.
     fn as_op(&self) -> &dyn Op<Fp> {
         match self {
@@ -340,6 +453,45 @@ impl SupportedOp {
             _ => false,
         }
     }
+
+    /// Whether the op concatenates its inputs, which empty ones just drop out of
+    pub fn is_concat(&self) -> bool {
+        match self {
+            SupportedOp::Linear(op) => matches!(op, PolyOp::Concat { .. }),
+            SupportedOp::Rescaled(op) => op.inner.is_concat(),
+            SupportedOp::RebaseScale(op) => op.inner.is_concat(),
+            _ => false,
+        }
+    }
+
+    /// Whether the op sums products of its inputs (conv, deconv, matmul, affine) and so compounds their scales
+    pub fn is_accumulating(&self) -> bool {
+        match self {
+            SupportedOp::Linear(op) => matches!(
+                op,
+                PolyOp::Conv { .. }
+                    | PolyOp::DeConv { .. }
+                    | PolyOp::Einsum { .. }
+                    | PolyOp::Affine { .. }
+                    | PolyOp::SparseMatMul { .. }
+            ),
+            SupportedOp::Rescaled(op) => op.inner.is_accumulating(),
+            SupportedOp::RebaseScale(op) => op.inner.is_accumulating(),
+            _ => false,
+        }
+    }
+
+    /// For conv and pooling ops, whether they expect channels last (NHWC) rather than channels first (NCHW) data
+    pub fn channels_last(&self) -> Option<bool> {
+        match self {
+            SupportedOp::Linear(PolyOp::Conv { channels_last, .. }) => Some(*channels_last),
+            SupportedOp::Hybrid(HybridOp::MaxPool { channels_last, .. })
+            | SupportedOp::Hybrid(HybridOp::SumPool { channels_last, .. }) => Some(*channels_last),
+            SupportedOp::Rescaled(op) => op.inner.channels_last(),
+            SupportedOp::RebaseScale(op) => op.inner.channels_last(),
+            _ => None,
+        }
+    }
 }
 
 impl From<Box<dyn Op<Fp>>> for SupportedOp {
@@ -376,7 +528,7 @@ impl From<Box<dyn Op<Fp>>> for SupportedOp {
 
         log::error!("Unsupported op type");
         log::warn!("defaulting to Unknown");
-        SupportedOp::Unknown(Unknown {})
+        SupportedOp::Unknown(Unknown::default())
     }
 }
 
@@ -439,6 +591,9 @@ pub struct Node {
     pub idx: usize,
     /// The node's num of uses
     pub num_uses: usize,
+    /// The name of the onnx node it was parsed from.
+    #[serde(default)]
+    pub name: String,
 }
 
 #[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
@@ -481,6 +636,8 @@ impl Node {
     /// * `other_nodes` - [BTreeMap] of other previously initialized [Node]s in the computational graph.
     /// * `public_params` - flag if parameters of model are public
     /// * `idx` - The node's unique identifier.
+    /// * `masks` - The constants of the graph that are additive masks ahead of a softmax.
+    /// * `custom_tables` - The user supplied lookup tables, keyed by op name.
     #[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
     #[allow(clippy::too_many_arguments)]
     pub fn new(
@@ -490,6 +647,8 @@ impl Node {
         idx: usize,
         symbol_values: &SymbolValues,
         run_args: &crate::RunArgs,
+        masks: &std::collections::HashSet<usize>,
+        custom_tables: &BTreeMap<String, crate::circuit::lookup::CustomTable>,
     ) -> Result<Self, GraphError> {
         trace!("Create {:?}", node);
         trace!("Create op {:?}", node.op);
@@ -533,6 +692,8 @@ impl Node {
             &mut inputs,
             symbol_values,
             run_args,
+            masks,
+            custom_tables,
         )?; // parses the op name
 
         // we can only take the inputs as mutable once -- so we need to collect them first
@@ -553,6 +714,22 @@ impl Node {
         // remove the inputs that are not used
         input_ids.retain(|(idx, _)| *idx != usize::MAX);
 
+        // the params this layer consumes live where its placement says, rather than per the
+        // global param visibility
+        if let Some(placement) = run_args.param_placement_of(&node.name) {
+            let visibility = placement.visibility(&run_args.param_visibility);
+            for (input_idx, _) in input_ids.iter() {
+                let input_node = other_nodes
+                    .get_mut(input_idx)
+                    .ok_or(GraphError::MissingInput(*input_idx))?;
+                let mut input_opkind = input_node.opkind();
+                if let Some(constant) = input_opkind.get_mutable_constant() {
+                    constant.requantize(constant.scale(), &visibility)?;
+                    input_node.replace_opkind(constant.clone_dyn().into());
+                }
+            }
+        }
+
         // rescale the inputs if necessary to get consistent fixed points
         let mut in_scales: Vec<crate::Scale> = input_ids
             .iter()
@@ -580,7 +757,6 @@ impl Node {
                     rescale_const_with_single_use(
                         constant,
                         in_scales.clone(),
-                        &run_args.param_visibility,
                         input_node.num_uses(),
                     )?;
                     input_node.replace_opkind(constant.clone_dyn().into());
@@ -591,15 +767,22 @@ impl Node {
             }
         }
 
+        opkind = opkind.align_bias_scale(&in_scales);
         opkind = opkind.homogenous_rescale(in_scales.clone())?.into();
         let mut out_scale = opkind.out_scale(in_scales.clone())?;
         // rescale the inputs if necessary to get consistent fixed points, we select the largest scale (highest precision)
         let global_scale = scales.get_max();
+        // with a uniform scale, accumulating ops are brought straight back down to the working scale
+        let rebase_multiplier = if run_args.uniform_scale && opkind.is_accumulating() {
+            1
+        } else {
+            scales.rebase_multiplier
+        };
         opkind = RebaseScale::rebase(
             opkind,
             global_scale,
             out_scale,
-            scales.rebase_multiplier,
+            rebase_multiplier,
             run_args.div_rebasing,
         );
 
@@ -614,6 +797,21 @@ impl Node {
             out_dims = vec![1];
         }
 
+        // empty tensors propagate: ops on them are skipped as their outputs are empty too. Only a
+        // concat can make something of them, other ops (e.g. a sum over an empty axis) aren't
+        // supported
+        if out_dims.iter().product::<usize>() > 0 && !opkind.is_concat() {
+            for (input_idx, outlet) in input_ids.iter() {
+                let input = inputs
+                    .iter()
+                    .find(|x| x.idx() == *input_idx)
+                    .ok_or(GraphError::MissingInput(*input_idx))?;
+                if input.out_dims()[*outlet].iter().product::<usize>() == 0 {
+                    return Err(GraphError::EmptyInput(idx, opkind.as_string(), *input_idx));
+                }
+            }
+        }
+
         Ok(Node {
             idx,
             opkind,
@@ -621,6 +819,7 @@ impl Node {
             out_dims,
             out_scale,
             num_uses,
+            name: node.name.clone(),
         })
     }
 
@@ -638,16 +837,13 @@ impl Node {
 fn rescale_const_with_single_use(
     constant: &mut Constant<Fp>,
     in_scales: Vec<crate::Scale>,
-    param_visibility: &Visibility,
     num_uses: usize,
 ) -> Result<(), GraphError> {
     if num_uses == 1 {
         let current_scale = constant.out_scale(vec![])?;
         let scale_max = in_scales.iter().max().ok_or(GraphError::MissingScale)?;
         if scale_max > &current_scale {
-            let raw_values = constant.raw_values.clone();
-            constant.quantized_values =
-                super::quantize_tensor(raw_values, *scale_max, param_visibility)?;
+            constant.rebase_scale(*scale_max)?;
         }
     }
 
@@ -0,0 +1,292 @@
+use super::errors::GraphError;
+use super::utilities::dequantize;
+use halo2curves::bn256::Fr as Fp;
+use serde::{Deserialize, Serialize};
+
+/// How far dequantized outputs may be from the expected float ones. An output passes if it's
+/// within `abs + rel * |expected|` of its expected value: the relative part scales with the
+/// output, the absolute part keeps outputs near zero from failing on rounding alone.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct OutputTolerance {
+    /// The absolute error allowed on each output, one quantization step at its scale if unset
+    pub abs: Option<f64>,
+    /// The error allowed on each output relative to its expected value
+    pub rel: f64,
+}
+
+impl Default for OutputTolerance {
+    fn default() -> Self {
+        Self {
+            abs: None,
+            rel: 0.01,
+        }
+    }
+}
+
+/// An output's distance from its expected value.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct OutputDeviation {
+    /// The index of the output tensor
+    pub tensor: usize,
+    /// The index of the value within it
+    pub index: usize,
+    /// The dequantized output
+    pub output: f32,
+    /// The expected value
+    pub expected: f32,
+    /// The absolute error
+    pub error: f32,
+    /// The error the tolerance allows for this value
+    pub allowed: f32,
+}
+
+impl std::fmt::Display for OutputDeviation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "output {}[{}] is {} against {} expected (off by {}, {} allowed)",
+            self.tensor, self.index, self.output, self.expected, self.error, self.allowed
+        )
+    }
+}
+
+/// The outcome of comparing outputs against expected ones, see [check_outputs].
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct OutputCheck {
+    /// The number of values compared
+    pub checked: usize,
+    /// The number of values out of tolerance
+    pub failures: usize,
+    /// The value with the least slack left (or the most over), if any were compared
+    pub worst: Option<OutputDeviation>,
+}
+
+impl OutputCheck {
+    /// Whether every output is within tolerance.
+    pub fn passed(&self) -> bool {
+        self.failures == 0
+    }
+
+    /// Folds the check of a further output tensor into this one.
+    pub fn merge(&mut self, other: OutputCheck) {
+        self.checked += other.checked;
+        self.failures += other.failures;
+        // a nan error (from a non finite expected value) is as bad as it gets
+        let slack = |d: &OutputDeviation| match d.allowed - d.error {
+            slack if slack.is_nan() => f32::NEG_INFINITY,
+            slack => slack,
+        };
+        if let Some(worst) = other.worst {
+            if self
+                .worst
+                .as_ref()
+                .map_or(true, |w| slack(&worst) < slack(w))
+            {
+                self.worst = Some(worst);
+            }
+        }
+    }
+
+    /// Turns a failed check into an error.
+    pub fn into_result(self) -> Result<Self, GraphError> {
+        match self.passed() {
+            true => Ok(self),
+            false => Err(GraphError::OutputsOutOfTolerance(
+                self.failures,
+                self.checked,
+                self.worst.map(|w| w.to_string()).unwrap_or_default(),
+            )),
+        }
+    }
+}
+
+impl std::fmt::Display for OutputCheck {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.passed() {
+            true => write!(f, "all {} outputs within tolerance", self.checked)?,
+            false => write!(
+                f,
+                "{} of {} outputs out of tolerance",
+                self.failures, self.checked
+            )?,
+        }
+        match &self.worst {
+            Some(worst) => write!(f, ", worst: {}", worst),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Dequantizes one output tensor's `instances` at `scale` and compares them with the `expected`
+/// floats, within `tol`. Outputs are decoded as signed, so negative ones compare as negative.
+pub fn check_outputs(
+    instances: &[Fp],
+    expected: &[f32],
+    scale: crate::Scale,
+    tol: &OutputTolerance,
+) -> Result<OutputCheck, GraphError> {
+    if instances.len() != expected.len() {
+        return Err(GraphError::InvalidInstanceData(format!(
+            "expected {} outputs, got {}",
+            expected.len(),
+            instances.len()
+        )));
+    }
+    let abs = tol.abs.unwrap_or(1.0 / super::scale_to_multiplier(scale));
+    let mut check = OutputCheck::default();
+    for (index, (instance, expected)) in instances.iter().zip(expected).enumerate() {
+        let output = dequantize(*instance, scale, 0.0);
+        let expected = *expected as f64;
+        let error = (output - expected).abs();
+        let allowed = abs + tol.rel * expected.abs();
+        // false for a non finite expected value, which never matches
+        let passed = error <= allowed;
+        check.merge(OutputCheck {
+            checked: 1,
+            failures: !passed as usize,
+            worst: Some(OutputDeviation {
+                tensor: 0,
+                index,
+                output: output as f32,
+                expected: expected as f32,
+                error: error as f32,
+                allowed: allowed as f32,
+            }),
+        });
+    }
+    Ok(check)
+}
+
+/// Compares each of `outputs` (one tensor per model output) with the `expected` floats at the
+/// output's scale, see [check_outputs].
+pub fn check_output_tensors(
+    outputs: &[&[Fp]],
+    expected: &[Vec<f32>],
+    scales: &[crate::Scale],
+    tol: &OutputTolerance,
+) -> Result<OutputCheck, GraphError> {
+    if outputs.len() != expected.len() {
+        return Err(GraphError::InvalidInstanceData(format!(
+            "expected {} output tensors, the model has {}",
+            expected.len(),
+            outputs.len()
+        )));
+    }
+    let mut check = OutputCheck::default();
+    for (tensor, ((output, expected), scale)) in
+        outputs.iter().zip(expected).zip(scales).enumerate()
+    {
+        let mut tensor_check = check_outputs(output, expected, *scale, tol)?;
+        if let Some(worst) = tensor_check.worst.as_mut() {
+            worst.tensor = tensor;
+        }
+        check.merge(tensor_check);
+    }
+    Ok(check)
+}
+
+/// The expected outputs in a .json file, as `output_data` with one array per output tensor. Other
+/// fields are ignored, so an `input.json` holding its outputs works too.
+#[derive(Deserialize)]
+struct ExpectedOutputs {
+    output_data: Vec<Vec<f32>>,
+}
+
+/// Loads expected outputs from a .json file, see [ExpectedOutputs].
+pub fn load_expected_outputs(path: &std::path::Path) -> Result<Vec<Vec<f32>>, GraphError> {
+    let buf = std::fs::read_to_string(path)
+        .map_err(|e| GraphError::ReadWriteFileError(path.display().to_string(), e.to_string()))?;
+    let expected: ExpectedOutputs = serde_json::from_str(&buf)
+        .map_err(|e| GraphError::InvalidInstanceData(format!("{}: {}", path.display(), e)))?;
+    Ok(expected.output_data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fieldutils::integer_rep_to_felt;
+
+    fn felts(ints: &[i64]) -> Vec<Fp> {
+        ints.iter().map(|x| integer_rep_to_felt(*x as _)).collect()
+    }
+
+    #[test]
+    fn outputs_within_tolerance_pass() {
+        // scale 2 decodes to quarters
+        let check = check_outputs(
+            &felts(&[4, 10, 1]),
+            &[1.0, 2.5, 0.3],
+            2,
+            &OutputTolerance::default(),
+        )
+        .unwrap();
+        assert!(check.passed(), "{}", check);
+        assert_eq!(check.checked, 3);
+        // 0.25 against 0.3 leaves the least slack
+        assert_eq!(check.worst.unwrap().index, 2);
+    }
+
+    #[test]
+    fn negative_outputs_decode_as_signed() {
+        let tol = OutputTolerance {
+            abs: Some(0.0),
+            rel: 0.0,
+        };
+        let check = check_outputs(&felts(&[-6, -1]), &[-1.5, -0.25], 2, &tol).unwrap();
+        assert!(check.passed(), "{}", check);
+
+        let check = check_outputs(&felts(&[-6]), &[1.5], 2, &tol).unwrap();
+        assert_eq!(check.failures, 1);
+        assert_eq!(check.worst.unwrap().output, -1.5);
+    }
+
+    #[test]
+    fn outputs_near_zero_rely_on_the_absolute_tolerance() {
+        // any relative tolerance on an expected zero allows no error at all
+        let rel_only = OutputTolerance {
+            abs: Some(0.0),
+            rel: 0.5,
+        };
+        let check = check_outputs(&felts(&[1]), &[0.0], 4, &rel_only).unwrap();
+        assert!(!check.passed());
+        // which one quantization step covers
+        let default = OutputTolerance {
+            abs: None,
+            rel: 0.5,
+        };
+        assert!(check_outputs(&felts(&[1]), &[0.0], 4, &default)
+            .unwrap()
+            .passed());
+        assert!(!check_outputs(&felts(&[2]), &[0.0], 4, &default)
+            .unwrap()
+            .passed());
+    }
+
+    #[test]
+    fn mismatches_are_reported() {
+        let tol = OutputTolerance::default();
+        assert!(matches!(
+            check_outputs(&felts(&[1, 2]), &[0.0], 0, &tol),
+            Err(GraphError::InvalidInstanceData(_))
+        ));
+        assert!(!check_outputs(&felts(&[1]), &[f32::NAN], 0, &tol)
+            .unwrap()
+            .passed());
+
+        let outputs = [felts(&[4]), felts(&[8, 9])];
+        let check = check_output_tensors(
+            &[&outputs[0], &outputs[1]],
+            &[vec![1.0], vec![2.0, 3.0]],
+            &[2, 2],
+            &tol,
+        )
+        .unwrap();
+        assert_eq!(check.failures, 1);
+        let worst = check.worst.clone().unwrap();
+        assert_eq!((worst.tensor, worst.index), (1, 1));
+        assert!(matches!(
+            check.into_result(),
+            Err(GraphError::OutputsOutOfTolerance(1, 3, _))
+        ));
+    }
+}
@@ -0,0 +1,248 @@
+use super::errors::GraphError;
+
+// field numbers from onnx.proto, by message
+pub(crate) const MODEL_IR_VERSION: u64 = 1;
+pub(crate) const MODEL_PRODUCER_NAME: u64 = 2;
+pub(crate) const MODEL_GRAPH: u64 = 7;
+pub(crate) const MODEL_OPSET_IMPORT: u64 = 8;
+pub(crate) const OPSET_DOMAIN: u64 = 1;
+pub(crate) const OPSET_VERSION: u64 = 2;
+pub(crate) const GRAPH_NODE: u64 = 1;
+pub(crate) const GRAPH_NAME: u64 = 2;
+pub(crate) const GRAPH_INITIALIZER: u64 = 5;
+pub(crate) const GRAPH_INPUT: u64 = 11;
+pub(crate) const GRAPH_OUTPUT: u64 = 12;
+pub(crate) const GRAPH_VALUE_INFO: u64 = 13;
+pub(crate) const NODE_INPUT: u64 = 1;
+pub(crate) const NODE_OUTPUT: u64 = 2;
+pub(crate) const NODE_NAME: u64 = 3;
+pub(crate) const NODE_OP_TYPE: u64 = 4;
+pub(crate) const NODE_ATTRIBUTE: u64 = 5;
+pub(crate) const ATTRIBUTE_NAME: u64 = 1;
+pub(crate) const ATTRIBUTE_F: u64 = 2;
+pub(crate) const ATTRIBUTE_I: u64 = 3;
+pub(crate) const ATTRIBUTE_S: u64 = 4;
+pub(crate) const ATTRIBUTE_TENSOR: u64 = 5;
+pub(crate) const ATTRIBUTE_GRAPH: u64 = 6;
+pub(crate) const ATTRIBUTE_FLOATS: u64 = 7;
+pub(crate) const ATTRIBUTE_INTS: u64 = 8;
+pub(crate) const ATTRIBUTE_STRINGS: u64 = 9;
+pub(crate) const ATTRIBUTE_TENSORS: u64 = 10;
+pub(crate) const ATTRIBUTE_GRAPHS: u64 = 11;
+pub(crate) const ATTRIBUTE_TYPE: u64 = 20;
+pub(crate) const TENSOR_DIMS: u64 = 1;
+pub(crate) const TENSOR_DATA_TYPE: u64 = 2;
+pub(crate) const TENSOR_FLOAT_DATA: u64 = 4;
+pub(crate) const TENSOR_INT32_DATA: u64 = 5;
+pub(crate) const TENSOR_NAME: u64 = 8;
+pub(crate) const TENSOR_RAW_DATA: u64 = 9;
+pub(crate) const TENSOR_EXTERNAL_DATA: u64 = 13;
+pub(crate) const TENSOR_DATA_LOCATION: u64 = 14;
+pub(crate) const ENTRY_KEY: u64 = 1;
+pub(crate) const ENTRY_VALUE: u64 = 2;
+pub(crate) const VALUE_INFO_NAME: u64 = 1;
+pub(crate) const VALUE_INFO_TYPE: u64 = 2;
+pub(crate) const TYPE_TENSOR_TYPE: u64 = 1;
+pub(crate) const TENSOR_TYPE_ELEM_TYPE: u64 = 1;
+pub(crate) const TENSOR_TYPE_SHAPE: u64 = 2;
+pub(crate) const SHAPE_DIM: u64 = 1;
+pub(crate) const DIM_VALUE: u64 = 1;
+
+// `TensorProto.DataType`s
+pub(crate) const FLOAT: u64 = 1;
+pub(crate) const UINT8: u64 = 2;
+pub(crate) const INT8: u64 = 3;
+pub(crate) const INT32: u64 = 6;
+pub(crate) const INT64: u64 = 7;
+/// `AttributeProto.AttributeType.INT`
+pub(crate) const ATTRIBUTE_INT: u64 = 2;
+/// `TensorProto.DataLocation.EXTERNAL`
+pub(crate) const EXTERNAL: u64 = 1;
+
+pub(crate) const WIRE_VARINT: u64 = 0;
+pub(crate) const WIRE_FIXED64: u64 = 1;
+pub(crate) const WIRE_LEN: u64 = 2;
+pub(crate) const WIRE_FIXED32: u64 = 5;
+
+/// A protobuf field. `value` holds the encoded varint or fixed width bytes, or the contents of a
+/// length delimited field, so untouched fields can be written back verbatim.
+pub(super) struct Field<'a> {
+    pub number: u64,
+    pub wire_type: u64,
+    pub value: &'a [u8],
+}
+
+pub(super) fn malformed(what: &str) -> GraphError {
+    GraphError::MisformedParams(format!("malformed onnx protobuf: {}", what))
+}
+
+pub(super) fn string(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes).to_string()
+}
+
+/// The value of the field `number` of a message, as a string.
+pub(super) fn name_of(msg: &[u8], number: u64) -> Result<String, GraphError> {
+    Ok(parse(msg)?
+        .iter()
+        .find(|f| f.number == number)
+        .map(|f| string(f.value))
+        .unwrap_or_default())
+}
+
+pub(super) fn read_varint(buf: &[u8], pos: &mut usize) -> Result<u64, GraphError> {
+    let mut value = 0;
+    for shift in (0..64).step_by(7) {
+        let byte = *buf.get(*pos).ok_or_else(|| malformed("truncated varint"))?;
+        *pos += 1;
+        value |= ((byte & 0x7f) as u64) << shift;
+        if byte < 0x80 {
+            return Ok(value);
+        }
+    }
+    Err(malformed("varint too long"))
+}
+
+/// Reads a repeated varint field, which may or may not be packed.
+pub(super) fn read_varints(
+    wire_type: u64,
+    value: &[u8],
+    out: &mut Vec<u64>,
+) -> Result<(), GraphError> {
+    let mut pos = 0;
+    match wire_type {
+        WIRE_VARINT => out.push(read_varint(value, &mut pos)?),
+        WIRE_LEN => {
+            while pos < value.len() {
+                out.push(read_varint(value, &mut pos)?);
+            }
+        }
+        _ => return Err(malformed("repeated varint field")),
+    }
+    Ok(())
+}
+
+/// A little endian `float`, as a `fixed32` field or an entry of packed or raw float data holds it.
+pub(super) fn f32_le(bytes: &[u8]) -> f32 {
+    f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+pub(crate) fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+pub(super) fn parse(buf: &[u8]) -> Result<Vec<Field>, GraphError> {
+    let mut fields = vec![];
+    let mut pos = 0;
+    while pos < buf.len() {
+        let key = read_varint(buf, &mut pos)?;
+        let (number, wire_type) = (key >> 3, key & 7);
+        let (start, end) = match wire_type {
+            WIRE_VARINT => {
+                let start = pos;
+                read_varint(buf, &mut pos)?;
+                (start, pos)
+            }
+            WIRE_FIXED64 => (pos, pos.saturating_add(8)),
+            WIRE_FIXED32 => (pos, pos.saturating_add(4)),
+            WIRE_LEN => {
+                let len = read_varint(buf, &mut pos)? as usize;
+                (pos, pos.saturating_add(len))
+            }
+            _ => return Err(malformed("unsupported wire type")),
+        };
+        if end > buf.len() {
+            return Err(malformed("truncated field"));
+        }
+        pos = end;
+        fields.push(Field {
+            number,
+            wire_type,
+            value: &buf[start..end],
+        });
+    }
+    Ok(fields)
+}
+
+pub(crate) fn write_field(out: &mut Vec<u8>, number: u64, wire_type: u64, value: &[u8]) {
+    write_varint(out, (number << 3) | wire_type);
+    if wire_type == WIRE_LEN {
+        write_varint(out, value.len() as u64);
+    }
+    out.extend_from_slice(value);
+}
+
+/// Re-encodes a message, replacing the contents of the length delimited fields `f` maps to `Some`.
+pub(super) fn rewrite(
+    msg: &[u8],
+    f: impl FnMut(&Field) -> Result<Option<Vec<u8>>, GraphError>,
+) -> Result<Vec<u8>, GraphError> {
+    Ok(rewrite_changed(msg, f)?.unwrap_or_else(|| msg.to_vec()))
+}
+
+/// Like [rewrite], but returns `None`, without re-encoding anything, if `f` left every field as is.
+pub(super) fn rewrite_changed(
+    msg: &[u8],
+    mut f: impl FnMut(&Field) -> Result<Option<Vec<u8>>, GraphError>,
+) -> Result<Option<Vec<u8>>, GraphError> {
+    let fields = parse(msg)?;
+    let mut replaced = Vec::with_capacity(fields.len());
+    for field in &fields {
+        replaced.push(match field.wire_type {
+            WIRE_LEN => f(field)?,
+            _ => None,
+        });
+    }
+    if replaced.iter().all(Option::is_none) {
+        return Ok(None);
+    }
+
+    let mut out = Vec::with_capacity(msg.len());
+    for (field, replaced) in fields.iter().zip(&replaced) {
+        write_field(
+            &mut out,
+            field.number,
+            field.wire_type,
+            replaced.as_deref().unwrap_or(field.value),
+        );
+    }
+    Ok(Some(out))
+}
+
+/// A pass over a model's `GraphProto`, returning the rewritten graph, or `None` if it left the
+/// graph as is.
+pub(super) type GraphPass<'a> = &'a dyn Fn(&[u8]) -> Result<Option<Vec<u8>>, GraphError>;
+
+/// Runs `passes` in turn over the graph of `model`. The model is split into its fields once, a
+/// pass leaving the graph as is copies nothing, and the model is only re-encoded, once, if some
+/// pass rewrote its graph.
+pub(super) fn rewrite_graph(model: Vec<u8>, passes: &[GraphPass]) -> Result<Vec<u8>, GraphError> {
+    let fields = parse(&model)?;
+    let graph = match fields.iter().find(|f| f.number == MODEL_GRAPH) {
+        Some(field) => field.value,
+        None => return Ok(model),
+    };
+    let mut rewritten: Option<Vec<u8>> = None;
+    for pass in passes {
+        if let Some(graph) = pass(rewritten.as_deref().unwrap_or(graph))? {
+            rewritten = Some(graph);
+        }
+    }
+    let rewritten = match rewritten {
+        Some(rewritten) => rewritten,
+        None => return Ok(model),
+    };
+
+    let mut out = Vec::with_capacity(model.len());
+    for field in &fields {
+        let value = match field.number {
+            MODEL_GRAPH => &rewritten[..],
+            _ => field.value,
+        };
+        write_field(&mut out, field.number, field.wire_type, value);
+    }
+    Ok(out)
+}
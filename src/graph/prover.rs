@@ -0,0 +1,299 @@
+#[cfg(test)]
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use halo2_proofs::plonk::ProvingKey;
+use halo2_proofs::poly::commitment::{Params, ParamsProver};
+use halo2_proofs::poly::kzg::commitment::{KZGCommitmentScheme, ParamsKZG};
+use halo2_proofs::poly::kzg::multiopen::{ProverSHPLONK, VerifierSHPLONK};
+use halo2_proofs::poly::kzg::strategy::SingleStrategy;
+use halo2_proofs::poly::VerificationStrategy;
+use halo2curves::bn256::{Bn256, Fr as Fp, G1Affine};
+use instant::Instant;
+use snark_verifier::system::halo2::transcript::evm::EvmTranscript;
+
+use super::errors::GraphError;
+use super::input_source::InputSource;
+use super::{GraphCircuit, GraphWitness};
+use crate::circuit::region::RegionSettings;
+use crate::circuit::CheckMode;
+use crate::pfsys::{create_proof_circuit, verify_proof_circuit, Snark, TranscriptType};
+use crate::{Commitments, EZKLError};
+
+/// Lets another thread cancel a [Prover::prove] that is under way.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+    // how often proves holding the token have checked it, so tests can cancel mid-layout
+    #[cfg(test)]
+    checks: Arc<AtomicUsize>,
+}
+
+impl CancellationToken {
+    /// A token that hasn't been cancelled
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancels every prove holding this token, and any later one passed it
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    /// Whether [CancellationToken::cancel] was called
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    #[cfg(test)]
+    fn checks(&self) -> usize {
+        self.checks.load(Ordering::SeqCst)
+    }
+}
+
+/// A [CancellationToken] and an optional deadline, checked by a prove between nodes and phases.
+#[derive(Clone, Debug)]
+pub struct Interrupt {
+    token: CancellationToken,
+    deadline: Option<Instant>,
+}
+
+impl Interrupt {
+    /// Interrupts when `token` is cancelled or, if there is a `timeout`, once it has elapsed from now
+    pub fn new(token: CancellationToken, timeout: Option<Duration>) -> Self {
+        Self {
+            token,
+            deadline: timeout.map(|t| Instant::now() + t),
+        }
+    }
+
+    /// Errors with [GraphError::Cancelled] if the prove should stop
+    pub fn check(&self) -> Result<(), GraphError> {
+        #[cfg(test)]
+        self.token.checks.fetch_add(1, Ordering::SeqCst);
+        let timed_out = matches!(self.deadline, Some(deadline) if Instant::now() >= deadline);
+        if self.token.is_cancelled() || timed_out {
+            return Err(GraphError::Cancelled);
+        }
+        Ok(())
+    }
+}
+
+/// Options for [Prover::prove]
+#[derive(Clone, Debug, Default)]
+pub struct ProveOptions {
+    /// Whether to verify the proof once it is made
+    pub check_mode: CheckMode,
+    /// Cancels the prove once this long has passed since it started
+    pub timeout: Option<Duration>,
+}
+
+/// Proves witnesses of a circuit with a KZG proving key, as `prove` does for single proofs.
+///
+/// A prove can be cancelled from another thread through its [CancellationToken], or time out.
+/// Both are checked before each node of the model is laid out and between the proving phases.
+/// halo2 doesn't check in while it commits to the laid out circuit, so an interrupt arriving
+/// then is only seen once that's done. Each prove works on its own copy of the circuit, so a
+/// cancelled one leaves the prover as it was.
+pub struct Prover {
+    circuit: GraphCircuit,
+    pk: ProvingKey<G1Affine>,
+    params: ParamsKZG<Bn256>,
+}
+
+impl Prover {
+    /// A prover for `circuit`, using a proving key and params made for it
+    pub fn new(circuit: GraphCircuit, pk: ProvingKey<G1Affine>, params: ParamsKZG<Bn256>) -> Self {
+        Self {
+            circuit,
+            pk,
+            params,
+        }
+    }
+
+    /// Proves `witness`, stopping with [GraphError::Cancelled] if `cancel` is cancelled or the
+    /// timeout in `options` elapses first
+    pub fn prove(
+        &self,
+        witness: &GraphWitness,
+        options: &ProveOptions,
+        cancel: &CancellationToken,
+    ) -> Result<Snark<Fp, G1Affine>, EZKLError> {
+        let interrupt = Interrupt::new(cancel.clone(), options.timeout);
+        interrupt.check()?;
+
+        let mut circuit = self.circuit.clone();
+        circuit.load_graph_witness(witness)?;
+        let pretty_public_inputs = circuit.pretty_public_inputs(witness)?;
+        let public_inputs = circuit.prepare_public_inputs(witness)?;
+        circuit.interrupt = Some(interrupt.clone());
+        interrupt.check()?;
+
+        let proof = create_proof_circuit::<
+            KZGCommitmentScheme<Bn256>,
+            _,
+            ProverSHPLONK<_>,
+            VerifierSHPLONK<_>,
+            SingleStrategy<_>,
+            _,
+            EvmTranscript<_, _, _, _>,
+            EvmTranscript<_, _, _, _>,
+        >(
+            circuit,
+            vec![public_inputs],
+            &self.params,
+            &self.pk,
+            CheckMode::UNSAFE,
+            Commitments::KZG,
+            TranscriptType::EVM,
+            None,
+            None,
+        );
+        // a cancelled layout surfaces from halo2 as a synthesis error
+        let mut snark = match proof {
+            Ok(snark) => snark,
+            Err(e) => {
+                interrupt.check()?;
+                return Err(e.into());
+            }
+        };
+        interrupt.check()?;
+
+        if options.check_mode == CheckMode::SAFE {
+            let verifier_params = self.params.verifier_params();
+            verify_proof_circuit::<
+                VerifierSHPLONK<_>,
+                KZGCommitmentScheme<_>,
+                SingleStrategy<_>,
+                _,
+                EvmTranscript<_, _, _, _>,
+            >(
+                &snark,
+                verifier_params,
+                self.pk.get_vk(),
+                SingleStrategy::new(verifier_params),
+                verifier_params.n(),
+            )
+            .map_err(GraphError::from)?;
+        }
+
+        snark.pretty_public_inputs = pretty_public_inputs;
+        Ok(snark)
+    }
+
+    /// Proves the model's inputs read by name from `source`, e.g. tensors streamed in from
+    /// storage, running the model forward for their witness first
+    pub fn prove_from(
+        &self,
+        source: &dyn InputSource,
+        options: &ProveOptions,
+        cancel: &CancellationToken,
+    ) -> Result<Snark<Fp, G1Affine>, EZKLError> {
+        let mut circuit = self.circuit.clone();
+        let mut inputs = circuit.load_graph_input_from(source)?;
+        let run_args = &circuit.settings().run_args;
+        let region_settings = RegionSettings::all_true(run_args.decomp_base, run_args.decomp_legs);
+        let witness = circuit.forward::<KZGCommitmentScheme<Bn256>>(
+            &mut inputs,
+            Some(self.pk.get_vk()),
+            Some(&self.params),
+            region_settings,
+        )?;
+        self.prove(&witness, options, cancel)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::input::GraphData;
+    use crate::graph::OnnxModelBuilder;
+    use crate::pfsys::create_keys;
+    use crate::pfsys::srs::gen_srs;
+
+    // proves check in twice before laying out the model
+    const CHECKS_BEFORE_LAYOUT: usize = 2;
+
+    // a model deep enough that its layout is still under way when a cancel arrives
+    fn prover() -> (Prover, GraphWitness) {
+        let dir = crate::dev_fixtures::relu_chain(16, 256).write().unwrap();
+        let data = GraphData::from_path(dir.join("input.json")).unwrap();
+        let (circuit, mut witnesses) = OnnxModelBuilder::new(dir.join("network.onnx"))
+            .calibrate_with(vec![data])
+            .build_with_witnesses()
+            .unwrap();
+        let params = gen_srs::<KZGCommitmentScheme<Bn256>>(circuit.settings().run_args.logrows);
+        let pk = create_keys::<KZGCommitmentScheme<Bn256>, GraphCircuit>(&circuit, &params, false)
+            .unwrap();
+        (Prover::new(circuit, pk, params), witnesses.remove(0))
+    }
+
+    fn is_cancelled(res: &Result<Snark<Fp, G1Affine>, EZKLError>) -> bool {
+        matches!(res, Err(EZKLError::GraphError(GraphError::Cancelled)))
+    }
+
+    #[test]
+    fn cancelled_prove_returns_promptly_and_the_prover_still_proves() {
+        let (prover, witness) = prover();
+        let prover = Arc::new(prover);
+        let witness = Arc::new(witness);
+
+        let cancel = CancellationToken::new();
+        let handle = {
+            let (prover, witness, cancel) = (prover.clone(), witness.clone(), cancel.clone());
+            std::thread::spawn(move || prover.prove(&witness, &ProveOptions::default(), &cancel))
+        };
+        // cancel once the first node has been laid out
+        while cancel.checks() <= CHECKS_BEFORE_LAYOUT + 1 && !handle.is_finished() {
+            std::thread::yield_now();
+        }
+        cancel.cancel();
+        let signalled = Instant::now();
+        let res = handle.join().unwrap();
+        assert!(
+            signalled.elapsed() < Duration::from_secs(1),
+            "{:?}",
+            signalled.elapsed()
+        );
+        assert!(is_cancelled(&res), "{:?}", res.err());
+
+        let uncancelled = CancellationToken::new();
+        prover
+            .prove(&witness, &ProveOptions::default(), &uncancelled)
+            .unwrap();
+        // the cancelled prove stopped partway through the layout
+        assert!(cancel.checks() > CHECKS_BEFORE_LAYOUT + 1);
+        assert!(
+            cancel.checks() < uncancelled.checks(),
+            "{} of {} checks",
+            cancel.checks(),
+            uncancelled.checks()
+        );
+    }
+
+    #[test]
+    fn prove_times_out() {
+        let (prover, witness) = prover();
+        let start = Instant::now();
+        prover
+            .prove(
+                &witness,
+                &ProveOptions::default(),
+                &CancellationToken::new(),
+            )
+            .unwrap();
+        let full = start.elapsed();
+
+        // a timeout well past the checks made before layout, but short of a whole prove
+        let options = ProveOptions {
+            timeout: Some(full / 2),
+            ..ProveOptions::default()
+        };
+        let token = CancellationToken::new();
+        let res = prover.prove(&witness, &options, &token);
+        assert!(is_cancelled(&res), "{:?}", res.err());
+        assert!(token.checks() > CHECKS_BEFORE_LAYOUT);
+    }
+}
@@ -0,0 +1,342 @@
+use super::errors::GraphError;
+use super::proto::{
+    f32_le, name_of, parse, read_varint, read_varints, string, write_field, write_varint,
+    ATTRIBUTE_I, ATTRIBUTE_INT, ATTRIBUTE_NAME, ATTRIBUTE_TYPE, FLOAT, GRAPH_INITIALIZER,
+    GRAPH_INPUT, GRAPH_NODE, INT32, INT8, NODE_ATTRIBUTE, NODE_INPUT, NODE_NAME, NODE_OP_TYPE,
+    NODE_OUTPUT, TENSOR_DATA_TYPE, TENSOR_DIMS, TENSOR_FLOAT_DATA, TENSOR_INT32_DATA, TENSOR_NAME,
+    TENSOR_RAW_DATA, UINT8, VALUE_INFO_NAME, WIRE_FIXED32, WIRE_LEN, WIRE_VARINT,
+};
+use log::info;
+use std::collections::{HashMap, HashSet};
+
+/// The integer ops lowered, and the float op each accumulates with.
+const QLINEAR_OPS: [(&str, &str); 2] = [("QLinearConv", "Conv"), ("QLinearMatMul", "MatMul")];
+
+/// The parts of a `NodeProto` the pass looks at, with its attributes kept encoded.
+struct Node {
+    name: String,
+    op: String,
+    inputs: Vec<String>,
+    outputs: Vec<String>,
+    attributes: Vec<Vec<u8>>,
+}
+
+impl Node {
+    fn decode(msg: &[u8]) -> Result<Self, GraphError> {
+        let mut node = Node {
+            name: String::new(),
+            op: String::new(),
+            inputs: vec![],
+            outputs: vec![],
+            attributes: vec![],
+        };
+        for field in parse(msg)? {
+            match field.number {
+                NODE_INPUT => node.inputs.push(string(field.value)),
+                NODE_OUTPUT => node.outputs.push(string(field.value)),
+                NODE_NAME => node.name = string(field.value),
+                NODE_OP_TYPE => node.op = string(field.value),
+                NODE_ATTRIBUTE => node.attributes.push(field.value.to_vec()),
+                _ => {}
+            }
+        }
+        Ok(node)
+    }
+
+    fn new(name: String, op: &str, inputs: Vec<String>, outputs: Vec<String>) -> Self {
+        Node {
+            name,
+            op: op.to_string(),
+            inputs,
+            outputs,
+            attributes: vec![],
+        }
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let mut out = vec![];
+        for input in &self.inputs {
+            write_field(&mut out, NODE_INPUT, WIRE_LEN, input.as_bytes());
+        }
+        for output in &self.outputs {
+            write_field(&mut out, NODE_OUTPUT, WIRE_LEN, output.as_bytes());
+        }
+        write_field(&mut out, NODE_NAME, WIRE_LEN, self.name.as_bytes());
+        write_field(&mut out, NODE_OP_TYPE, WIRE_LEN, self.op.as_bytes());
+        for attribute in &self.attributes {
+            write_field(&mut out, NODE_ATTRIBUTE, WIRE_LEN, attribute);
+        }
+        out
+    }
+
+    /// How the node is referred to in logs, errors and the names of the values it's lowered to.
+    fn label(&self) -> &str {
+        match self.name.is_empty() {
+            true => self.outputs.first().map_or("", |o| o.as_str()),
+            false => &self.name,
+        }
+    }
+}
+
+fn int_attribute(name: &str, value: u64) -> Vec<u8> {
+    let mut out = vec![];
+    write_field(&mut out, ATTRIBUTE_NAME, WIRE_LEN, name.as_bytes());
+    for (number, value) in [(ATTRIBUTE_I, value), (ATTRIBUTE_TYPE, ATTRIBUTE_INT)] {
+        let mut encoded = vec![];
+        write_varint(&mut encoded, value);
+        write_field(&mut out, number, WIRE_VARINT, &encoded);
+    }
+    out
+}
+
+/// An initializer of floats or integers, row major.
+struct Tensor {
+    dims: Vec<usize>,
+    values: Vec<f64>,
+}
+
+/// Decodes an initializer's name, and its values if they are floats or integers of the widths
+/// quantized ops use.
+fn decode_tensor(msg: &[u8]) -> Result<(String, Option<Tensor>), GraphError> {
+    let (mut name, mut dims, mut data_type) = (String::new(), vec![], 0);
+    let (mut raw, mut floats, mut ints) = (None, vec![], vec![]);
+    for field in parse(msg)? {
+        match (field.number, field.wire_type) {
+            (TENSOR_DIMS, _) => read_varints(field.wire_type, field.value, &mut dims)?,
+            (TENSOR_DATA_TYPE, WIRE_VARINT) => data_type = read_varint(field.value, &mut 0)?,
+            (TENSOR_FLOAT_DATA, WIRE_FIXED32) => floats.push(f32_le(field.value) as f64),
+            (TENSOR_FLOAT_DATA, WIRE_LEN) => {
+                floats.extend(field.value.chunks_exact(4).map(|b| f32_le(b) as f64))
+            }
+            (TENSOR_INT32_DATA, _) => read_varints(field.wire_type, field.value, &mut ints)?,
+            (TENSOR_NAME, _) => name = string(field.value),
+            (TENSOR_RAW_DATA, _) => raw = Some(field.value),
+            _ => {}
+        }
+    }
+    let values = match (data_type, raw) {
+        (FLOAT, Some(raw)) => raw.chunks_exact(4).map(|b| f32_le(b) as f64).collect(),
+        (FLOAT, None) => floats,
+        (UINT8, Some(raw)) => raw.iter().map(|b| *b as f64).collect(),
+        (INT8, Some(raw)) => raw.iter().map(|b| *b as i8 as f64).collect(),
+        (INT32, Some(raw)) => raw
+            .chunks_exact(4)
+            .map(|b| i32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64)
+            .collect(),
+        // narrower integers are stored widened to int32 (sign extended to 64 bits when negative)
+        (UINT8 | INT8 | INT32, None) => ints.into_iter().map(|i| i as i32 as f64).collect(),
+        _ => return Ok((name, None)),
+    };
+    let dims = dims.into_iter().map(|d| d as usize).collect::<Vec<_>>();
+    let complete = values.len() == dims.iter().product::<usize>();
+    Ok((name, complete.then_some(Tensor { dims, values })))
+}
+
+fn encode_tensor(name: &str, dims: &[usize], values: &[f32]) -> Vec<u8> {
+    let mut out = vec![];
+    let mut varint = |number, value| {
+        let mut encoded = vec![];
+        write_varint(&mut encoded, value);
+        write_field(&mut out, number, WIRE_VARINT, &encoded);
+    };
+    for d in dims {
+        varint(TENSOR_DIMS, *d as u64);
+    }
+    varint(TENSOR_DATA_TYPE, FLOAT);
+    write_field(&mut out, TENSOR_NAME, WIRE_LEN, name.as_bytes());
+    let raw = values
+        .iter()
+        .flat_map(|v| v.to_le_bytes())
+        .collect::<Vec<_>>();
+    write_field(&mut out, TENSOR_RAW_DATA, WIRE_LEN, &raw);
+    out
+}
+
+/// A float initializer added by the pass.
+type Initializer = (String, Vec<usize>, Vec<f32>);
+
+/// The nodes and initializers computing a `QLinearConv` or `QLinearMatMul` over its input codes.
+/// Both take `x, x_scale, x_zero_point, w, w_scale, w_zero_point, y_scale, y_zero_point`, the conv
+/// then an optional int32 bias. The codes, centered on their zero points, are accumulated exactly
+/// by the float op, then requantized onto the output's codes by a `QuantizeLinear` by
+/// `y_scale / (x_scale * w_scale)`, which is how onnxruntime computes them.
+fn lower(
+    node: &Node,
+    float_op: &str,
+    tensors: &HashMap<String, Tensor>,
+) -> Result<(Vec<Node>, Vec<Initializer>), GraphError> {
+    let misformed =
+        |what: &str| GraphError::MisformedParams(format!("{} {}: {}", node.op, node.label(), what));
+    let constant = |i: usize| {
+        node.inputs
+            .get(i)
+            .and_then(|name| tensors.get(name))
+            .ok_or_else(|| misformed(&format!("input {} must be a constant", i)))
+    };
+    let scalar = |i: usize| match constant(i)?.values.as_slice() {
+        [v] => Ok(*v),
+        _ => Err(misformed("per channel quantization is not supported")),
+    };
+    let (x_scale, x_zero_point) = (scalar(1)?, scalar(2)?);
+    let (weight, w_scale, w_zero_point) = (constant(3)?, scalar(4)?, scalar(5)?);
+    let y_scale = scalar(6)?;
+    scalar(7)?;
+
+    let name = |suffix: &str| format!("{}/{}", node.label(), suffix);
+    let mut nodes = vec![];
+    let mut cast = Node::new(
+        name("cast"),
+        "Cast",
+        vec![node.inputs[0].clone()],
+        vec![name("x")],
+    );
+    cast.attributes.push(int_attribute("to", FLOAT));
+    nodes.push(cast);
+
+    let mut initializers = vec![];
+    let mut codes = name("x");
+    if x_zero_point != 0.0 {
+        initializers.push((name("x_zero_point"), vec![], vec![x_zero_point as f32]));
+        nodes.push(Node::new(
+            name("center"),
+            "Sub",
+            vec![codes, name("x_zero_point")],
+            vec![name("x_centered")],
+        ));
+        codes = name("x_centered");
+    }
+
+    let centered_weight = weight
+        .values
+        .iter()
+        .map(|w| (w - w_zero_point) as f32)
+        .collect();
+    initializers.push((name("weight"), weight.dims.clone(), centered_weight));
+    let mut inputs = vec![codes, name("weight")];
+    if node.inputs.get(8).is_some_and(|b| !b.is_empty()) {
+        let bias = constant(8)?;
+        let values = bias.values.iter().map(|b| *b as f32).collect();
+        initializers.push((name("bias"), bias.dims.clone(), values));
+        inputs.push(name("bias"));
+    }
+    let mut accumulate = Node::new(
+        name("accumulate"),
+        float_op,
+        inputs,
+        vec![name("accumulator")],
+    );
+    accumulate.attributes = node.attributes.clone();
+    nodes.push(accumulate);
+
+    let requantize_scale = y_scale / (x_scale * w_scale);
+    if !(requantize_scale.is_normal() && requantize_scale > 0.0) {
+        return Err(misformed("scales must be positive"));
+    }
+    initializers.push((
+        name("requantize_scale"),
+        vec![],
+        vec![requantize_scale as f32],
+    ));
+    nodes.push(Node::new(
+        name("requantize"),
+        "QuantizeLinear",
+        vec![
+            name("accumulator"),
+            name("requantize_scale"),
+            node.inputs[7].clone(),
+        ],
+        node.outputs.clone(),
+    ));
+    Ok((nodes, initializers))
+}
+
+fn lower_graph(graph: &[u8], num_lowered: &mut usize) -> Result<Option<Vec<u8>>, GraphError> {
+    let fields = parse(graph)?;
+    let mut nodes = vec![];
+    let mut tensors = HashMap::new();
+    for field in &fields {
+        match field.number {
+            GRAPH_NODE => nodes.push(Node::decode(field.value)?),
+            GRAPH_INITIALIZER => {
+                if let (name, Some(tensor)) = decode_tensor(field.value)? {
+                    tensors.insert(name, tensor);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut lowered_nodes = vec![];
+    let mut added = vec![];
+    let mut removed = HashSet::new();
+    for node in nodes {
+        let float_op = match QLINEAR_OPS.iter().find(|(op, _)| *op == node.op) {
+            Some((_, float_op)) => float_op,
+            None => {
+                lowered_nodes.push(node);
+                continue;
+            }
+        };
+        info!(
+            "lowering {} {} onto a {} over its codes",
+            node.op,
+            node.label(),
+            float_op
+        );
+        let (nodes, initializers) = lower(&node, float_op, &tensors)?;
+        removed.extend(node.inputs.iter().skip(1).cloned());
+        lowered_nodes.extend(nodes);
+        added.extend(initializers);
+        *num_lowered += 1;
+    }
+    if added.is_empty() {
+        return Ok(None);
+    }
+
+    let used = lowered_nodes
+        .iter()
+        .flat_map(|n| n.inputs.iter().cloned())
+        .collect::<HashSet<_>>();
+    let is_dropped = |name: String| removed.contains(&name) && !used.contains(&name);
+
+    let mut out = Vec::with_capacity(graph.len());
+    let mut nodes_written = false;
+    for field in &fields {
+        match field.number {
+            GRAPH_NODE if nodes_written => {}
+            GRAPH_NODE => {
+                for node in &lowered_nodes {
+                    write_field(&mut out, GRAPH_NODE, WIRE_LEN, &node.encode());
+                }
+                nodes_written = true;
+            }
+            GRAPH_INITIALIZER if is_dropped(name_of(field.value, TENSOR_NAME)?) => {}
+            GRAPH_INPUT if is_dropped(name_of(field.value, VALUE_INFO_NAME)?) => {}
+            _ => write_field(&mut out, field.number, field.wire_type, field.value),
+        }
+    }
+    for (name, dims, values) in &added {
+        write_field(
+            &mut out,
+            GRAPH_INITIALIZER,
+            WIRE_LEN,
+            &encode_tensor(name, dims, values),
+        );
+    }
+    Ok(Some(out))
+}
+
+/// Lowers each `QLinearConv` and `QLinearMatMul`, as emitted by onnxruntime's static
+/// quantization, onto a `Conv` or `MatMul` over the integer codes of its input and weights, centered
+/// on their zero points, followed by the `QuantizeLinear` requantizing the accumulator. The
+/// scales and zero points are read from the model's initializers rather than inferred, and the
+/// weights stay the integers they were quantized to. Returns the rewritten graph, or `None` if it
+/// has no quantized linear ops.
+pub fn lower_qlinear(graph: &[u8]) -> Result<Option<Vec<u8>>, GraphError> {
+    let mut num_lowered = 0;
+    let lowered = lower_graph(graph, &mut num_lowered)?;
+    if num_lowered > 0 {
+        info!("lowered {} quantized linear ops", num_lowered);
+    }
+    Ok(lowered)
+}
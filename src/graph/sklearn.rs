@@ -0,0 +1,556 @@
+use super::errors::GraphError;
+use super::proto::{
+    f32_le, name_of, parse, read_varint, read_varints, string, write_field, write_varint,
+    ATTRIBUTE_FLOATS, ATTRIBUTE_I, ATTRIBUTE_INT, ATTRIBUTE_INTS, ATTRIBUTE_NAME, ATTRIBUTE_S,
+    ATTRIBUTE_STRINGS, ATTRIBUTE_TYPE, FLOAT, GRAPH_INITIALIZER, GRAPH_NODE, GRAPH_OUTPUT,
+    GRAPH_VALUE_INFO, NODE_ATTRIBUTE, NODE_INPUT, NODE_NAME, NODE_OP_TYPE, NODE_OUTPUT,
+    TENSOR_DATA_TYPE, TENSOR_DIMS, TENSOR_NAME, TENSOR_RAW_DATA, TENSOR_TYPE_ELEM_TYPE,
+    TYPE_TENSOR_TYPE, VALUE_INFO_NAME, VALUE_INFO_TYPE, WIRE_FIXED32, WIRE_LEN, WIRE_VARINT,
+};
+use log::info;
+use std::collections::{HashMap, HashSet};
+
+/// The `ai.onnx.ml` ops skl2onnx wraps linear models in. Graphs holding none of them are left alone.
+const SKLEARN_OPS: [&str; 4] = [
+    "LinearClassifier",
+    "LinearRegressor",
+    "Normalizer",
+    "ZipMap",
+];
+
+#[derive(Default)]
+struct Attribute {
+    i: Option<i64>,
+    s: Option<String>,
+    floats: Vec<f32>,
+    ints: Vec<i64>,
+    strings: Vec<String>,
+}
+
+/// The parts of a `NodeProto` the pass looks at. Nodes read from the model are written back as `raw`.
+struct Node {
+    raw: Option<Vec<u8>>,
+    name: String,
+    op: String,
+    inputs: Vec<String>,
+    outputs: Vec<String>,
+    attributes: HashMap<String, Attribute>,
+    /// Encoded attributes of the nodes the pass adds
+    new_attributes: Vec<Vec<u8>>,
+}
+
+impl Node {
+    fn decode(msg: &[u8]) -> Result<Self, GraphError> {
+        let mut node = Node::new(String::new(), "", vec![], vec![]);
+        node.raw = Some(msg.to_vec());
+        for field in parse(msg)? {
+            match field.number {
+                NODE_INPUT => node.inputs.push(string(field.value)),
+                NODE_OUTPUT => node.outputs.push(string(field.value)),
+                NODE_NAME => node.name = string(field.value),
+                NODE_OP_TYPE => node.op = string(field.value),
+                NODE_ATTRIBUTE => {
+                    let (mut name, mut attribute) = (String::new(), Attribute::default());
+                    for field in parse(field.value)? {
+                        match (field.number, field.wire_type) {
+                            (ATTRIBUTE_NAME, _) => name = string(field.value),
+                            (ATTRIBUTE_I, WIRE_VARINT) => {
+                                attribute.i = Some(read_varint(field.value, &mut 0)? as i64)
+                            }
+                            (ATTRIBUTE_S, _) => attribute.s = Some(string(field.value)),
+                            (ATTRIBUTE_FLOATS, WIRE_FIXED32) => {
+                                attribute.floats.push(f32_le(field.value))
+                            }
+                            (ATTRIBUTE_FLOATS, WIRE_LEN) => attribute
+                                .floats
+                                .extend(field.value.chunks_exact(4).map(f32_le)),
+                            (ATTRIBUTE_INTS, _) => {
+                                let mut ints = vec![];
+                                read_varints(field.wire_type, field.value, &mut ints)?;
+                                attribute.ints.extend(ints.into_iter().map(|i| i as i64));
+                            }
+                            (ATTRIBUTE_STRINGS, _) => attribute.strings.push(string(field.value)),
+                            _ => {}
+                        }
+                    }
+                    node.attributes.insert(name, attribute);
+                }
+                _ => {}
+            }
+        }
+        Ok(node)
+    }
+
+    fn new(name: String, op: &str, inputs: Vec<String>, outputs: Vec<String>) -> Self {
+        Node {
+            raw: None,
+            name,
+            op: op.to_string(),
+            inputs,
+            outputs,
+            attributes: HashMap::new(),
+            new_attributes: vec![],
+        }
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        if let Some(raw) = &self.raw {
+            return raw.clone();
+        }
+        let mut out = vec![];
+        for input in &self.inputs {
+            write_field(&mut out, NODE_INPUT, WIRE_LEN, input.as_bytes());
+        }
+        for output in &self.outputs {
+            write_field(&mut out, NODE_OUTPUT, WIRE_LEN, output.as_bytes());
+        }
+        write_field(&mut out, NODE_NAME, WIRE_LEN, self.name.as_bytes());
+        write_field(&mut out, NODE_OP_TYPE, WIRE_LEN, self.op.as_bytes());
+        for attribute in &self.new_attributes {
+            write_field(&mut out, NODE_ATTRIBUTE, WIRE_LEN, attribute);
+        }
+        out
+    }
+
+    /// How the node is referred to in logs, errors and the names of the values it's lowered to.
+    fn label(&self) -> &str {
+        match self.name.is_empty() {
+            true => self.outputs.first().map_or("", |o| o.as_str()),
+            false => &self.name,
+        }
+    }
+
+    fn misformed(&self, what: &str) -> GraphError {
+        GraphError::MisformedParams(format!("{} {}: {}", self.op, self.label(), what))
+    }
+
+    fn int(&self, name: &str, default: i64) -> i64 {
+        self.attributes
+            .get(name)
+            .and_then(|a| a.i)
+            .unwrap_or(default)
+    }
+
+    fn str(&self, name: &str, default: &str) -> String {
+        self.attributes
+            .get(name)
+            .and_then(|a| a.s.clone())
+            .unwrap_or(default.to_string())
+    }
+
+    fn floats(&self, name: &str) -> &[f32] {
+        self.attributes
+            .get(name)
+            .map(|a| a.floats.as_slice())
+            .unwrap_or(&[])
+    }
+
+    /// The number of class labels of a `LinearClassifier`, given as ints or strings.
+    fn num_classes(&self) -> usize {
+        self.attributes
+            .iter()
+            .filter(|(name, _)| name.starts_with("classlabels_"))
+            .map(|(_, a)| a.ints.len().max(a.strings.len()))
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+fn int_attribute(name: &str, value: u64) -> Vec<u8> {
+    let mut out = vec![];
+    write_field(&mut out, ATTRIBUTE_NAME, WIRE_LEN, name.as_bytes());
+    for (number, value) in [(ATTRIBUTE_I, value), (ATTRIBUTE_TYPE, ATTRIBUTE_INT)] {
+        let mut encoded = vec![];
+        write_varint(&mut encoded, value);
+        write_field(&mut out, number, WIRE_VARINT, &encoded);
+    }
+    out
+}
+
+fn encode_tensor(name: &str, dims: &[usize], values: &[f32]) -> Vec<u8> {
+    let mut out = vec![];
+    let mut varint = |number, value| {
+        let mut encoded = vec![];
+        write_varint(&mut encoded, value);
+        write_field(&mut out, number, WIRE_VARINT, &encoded);
+    };
+    for d in dims {
+        varint(TENSOR_DIMS, *d as u64);
+    }
+    varint(TENSOR_DATA_TYPE, FLOAT);
+    write_field(&mut out, TENSOR_NAME, WIRE_LEN, name.as_bytes());
+    let raw = values
+        .iter()
+        .flat_map(|v| v.to_le_bytes())
+        .collect::<Vec<_>>();
+    write_field(&mut out, TENSOR_RAW_DATA, WIRE_LEN, &raw);
+    out
+}
+
+/// A float tensor output of unknown shape, standing in for the map a `ZipMap` produced.
+fn float_value_info(name: &str) -> Vec<u8> {
+    let mut elem_type = vec![];
+    write_varint(&mut elem_type, FLOAT);
+    let mut tensor_type = vec![];
+    write_field(
+        &mut tensor_type,
+        TENSOR_TYPE_ELEM_TYPE,
+        WIRE_VARINT,
+        &elem_type,
+    );
+    let mut type_proto = vec![];
+    write_field(&mut type_proto, TYPE_TENSOR_TYPE, WIRE_LEN, &tensor_type);
+    let mut out = vec![];
+    write_field(&mut out, VALUE_INFO_NAME, WIRE_LEN, name.as_bytes());
+    write_field(&mut out, VALUE_INFO_TYPE, WIRE_LEN, &type_proto);
+    out
+}
+
+/// A float initializer added by the pass.
+type Initializer = (String, Vec<usize>, Vec<f32>);
+
+/// What the pass has learnt about the tensors of a graph as it lowers its nodes.
+#[derive(Default)]
+struct Lowering {
+    nodes: Vec<Node>,
+    initializers: Vec<Initializer>,
+    /// The width of the scores of each lowered model
+    widths: HashMap<String, usize>,
+    /// Scores whose rows already sum to 1
+    normalized: HashSet<String>,
+    /// Class labels, which are integers or strings the circuit has no use for
+    labels: HashSet<String>,
+    /// The outputs of absorbed `ZipMap`s, now holding their input's probabilities
+    absorbed: HashSet<String>,
+}
+
+impl Lowering {
+    /// A `Gemm` of the node's input by `weights`, `[outputs, features]`, plus `bias`.
+    fn gemm(&mut self, node: &Node, weights: Vec<f32>, bias: Vec<f32>, output: String) {
+        let name = |suffix: &str| format!("{}/{}", node.label(), suffix);
+        let width = bias.len();
+        let features = weights.len() / width;
+        self.initializers
+            .push((name("weights"), vec![width, features], weights));
+        self.initializers.push((name("bias"), vec![width], bias));
+        let mut gemm = Node::new(
+            name("gemm"),
+            "Gemm",
+            vec![node.inputs[0].clone(), name("weights"), name("bias")],
+            vec![output.clone()],
+        );
+        gemm.new_attributes.push(int_attribute("transB", 1));
+        self.nodes.push(gemm);
+        self.widths.insert(output, width);
+    }
+
+    /// `LinearClassifier` scores each class by an affine map of the features, then applies its
+    /// `post_transform`. A binary classifier with a single set of coefficients scores the first
+    /// class by their negation, as onnxruntime does.
+    fn linear_classifier(&mut self, node: &Node) -> Result<(), GraphError> {
+        let (coefficients, intercepts) = (node.floats("coefficients"), node.floats("intercepts"));
+        let rows = intercepts.len();
+        if rows == 0 || coefficients.len() % rows != 0 {
+            return Err(node.misformed("coefficients don't match the intercepts"));
+        }
+        let binary = rows == 1 && node.num_classes() == 2;
+        // skl2onnx mirrors the coefficients of binary classifiers itself
+        let mirrored = rows == 2 && {
+            let (first, second) = coefficients.split_at(coefficients.len() / 2);
+            first.iter().zip(second).all(|(a, b)| *a == -b) && intercepts[0] == -intercepts[1]
+        };
+        let (weights, bias) = match binary {
+            true => (
+                coefficients
+                    .iter()
+                    .map(|w| -w)
+                    .chain(coefficients.iter().copied())
+                    .collect(),
+                vec![-intercepts[0], intercepts[0]],
+            ),
+            false => (coefficients.to_vec(), intercepts.to_vec()),
+        };
+        let (label, scores) = match node.outputs.as_slice() {
+            [label, scores] => (label.clone(), scores.clone()),
+            _ => return Err(node.misformed("expected a label and a scores output")),
+        };
+        self.labels.insert(label);
+
+        let transform = match node.str("post_transform", "NONE").as_str() {
+            "NONE" => None,
+            "LOGISTIC" => Some("Sigmoid"),
+            "SOFTMAX" => Some("Softmax"),
+            other => return Err(node.misformed(&format!("unsupported post_transform {}", other))),
+        };
+        let Some(op) = transform else {
+            self.gemm(node, weights, bias, scores);
+            return Ok(());
+        };
+        let raw_scores = format!("{}/raw_scores", node.label());
+        let width = bias.len();
+        self.gemm(node, weights, bias, raw_scores.clone());
+        let mut transform = Node::new(
+            format!("{}/{}", node.label(), op.to_lowercase()),
+            op,
+            vec![raw_scores],
+            vec![scores.clone()],
+        );
+        if op == "Softmax" {
+            transform.new_attributes.push(int_attribute("axis", 1));
+        }
+        self.nodes.push(transform);
+        self.widths.insert(scores.clone(), width);
+        // sigmoid(-z) + sigmoid(z) = 1
+        if op == "Softmax" || binary || mirrored {
+            self.normalized.insert(scores);
+        }
+        Ok(())
+    }
+
+    /// `LinearRegressor` predicts each target by an affine map of the features.
+    fn linear_regressor(&mut self, node: &Node) -> Result<(), GraphError> {
+        if node.str("post_transform", "NONE") != "NONE" {
+            return Err(node.misformed("only a post_transform of NONE is supported"));
+        }
+        let targets = node.int("targets", 1).max(1) as usize;
+        let coefficients = node.floats("coefficients").to_vec();
+        if coefficients.is_empty() || coefficients.len() % targets != 0 {
+            return Err(node.misformed("coefficients don't match the targets"));
+        }
+        let intercepts = match node.floats("intercepts") {
+            [] => vec![0.0; targets],
+            intercepts if intercepts.len() == targets => intercepts.to_vec(),
+            _ => return Err(node.misformed("intercepts don't match the targets")),
+        };
+        self.gemm(node, coefficients, intercepts, node.outputs[0].clone());
+        Ok(())
+    }
+
+    /// `Normalizer` divides each row by its `L1` or `L2` norm, summing the rows with a matmul by a
+    /// column of ones. Rows already known to sum to 1 are passed through.
+    fn normalizer(&mut self, node: &Node) -> Result<(), GraphError> {
+        let (x, output) = (node.inputs[0].clone(), node.outputs[0].clone());
+        let name = |suffix: &str| format!("{}/{}", node.label(), suffix);
+        let norm = node.str("norm", "MAX");
+        if norm == "L1" && self.normalized.contains(&x) {
+            self.nodes.push(Node::new(
+                name("identity"),
+                "Identity",
+                vec![x],
+                vec![output.clone()],
+            ));
+            self.normalized.insert(output);
+            return Ok(());
+        }
+        let width = *self
+            .widths
+            .get(&x)
+            .ok_or_else(|| node.misformed("can only normalize the scores of a linear model"))?;
+        let magnitude = match norm.as_str() {
+            "L1" => Node::new(name("abs"), "Abs", vec![x.clone()], vec![name("magnitude")]),
+            "L2" => Node::new(
+                name("square"),
+                "Mul",
+                vec![x.clone(), x.clone()],
+                vec![name("magnitude")],
+            ),
+            other => return Err(node.misformed(&format!("unsupported norm {}", other))),
+        };
+        self.nodes.push(magnitude);
+        self.initializers
+            .push((name("ones"), vec![width, 1], vec![1.0; width]));
+        self.nodes.push(Node::new(
+            name("sum"),
+            "MatMul",
+            vec![name("magnitude"), name("ones")],
+            vec![name("sum")],
+        ));
+        let mut row_norms = name("sum");
+        if norm == "L2" {
+            self.nodes.push(Node::new(
+                name("sqrt"),
+                "Sqrt",
+                vec![name("sum")],
+                vec![name("norm")],
+            ));
+            row_norms = name("norm");
+        }
+        self.nodes.push(Node::new(
+            name("divide"),
+            "Div",
+            vec![x, row_norms],
+            vec![output.clone()],
+        ));
+        self.widths.insert(output.clone(), width);
+        if norm == "L1" {
+            self.normalized.insert(output);
+        }
+        Ok(())
+    }
+}
+
+fn lower_graph(graph: &[u8], num_lowered: &mut usize) -> Result<Option<Vec<u8>>, GraphError> {
+    let fields = parse(graph)?;
+    let mut nodes = vec![];
+    let mut graph_outputs = vec![];
+    let mut label_tensors = HashSet::new();
+    for field in &fields {
+        match field.number {
+            GRAPH_NODE => nodes.push(Node::decode(field.value)?),
+            GRAPH_OUTPUT => graph_outputs.push(name_of(field.value, VALUE_INFO_NAME)?),
+            GRAPH_INITIALIZER => {
+                let fields = parse(field.value)?;
+                let data_type = fields
+                    .iter()
+                    .find(|f| f.number == TENSOR_DATA_TYPE)
+                    .map(|f| read_varint(f.value, &mut 0))
+                    .transpose()?;
+                // the classes a label is looked up in are ints or strings
+                if data_type != Some(FLOAT) {
+                    label_tensors.insert(name_of(field.value, TENSOR_NAME)?);
+                }
+            }
+            _ => {}
+        }
+    }
+    if !nodes.iter().any(|n| SKLEARN_OPS.contains(&n.op.as_str())) {
+        return Ok(None);
+    }
+
+    let mut lowering = Lowering::default();
+    for node in nodes {
+        match node.op.as_str() {
+            "LinearClassifier" => lowering.linear_classifier(&node)?,
+            "LinearRegressor" => lowering.linear_regressor(&node)?,
+            "Normalizer" => lowering.normalizer(&node)?,
+            // a ZipMap only pairs each probability with its class label
+            "ZipMap" => {
+                let output = node.outputs[0].clone();
+                lowering.nodes.push(Node::new(
+                    node.label().to_string(),
+                    "Identity",
+                    vec![node.inputs[0].clone()],
+                    vec![output.clone()],
+                ));
+                lowering.absorbed.insert(output);
+            }
+            // looking up the class labels of predictions
+            "LabelEncoder" => {
+                lowering.labels.extend(node.outputs.iter().cloned());
+                lowering.nodes.push(node);
+                continue;
+            }
+            "ArrayFeatureExtractor"
+                if node
+                    .inputs
+                    .first()
+                    .is_some_and(|data| label_tensors.contains(data)) =>
+            {
+                lowering.labels.extend(node.outputs.iter().cloned());
+                lowering.nodes.push(node);
+                continue;
+            }
+            _ => {
+                lowering.nodes.push(node);
+                continue;
+            }
+        }
+        info!(
+            "lowered {} {} onto standard onnx ops",
+            node.op,
+            node.label()
+        );
+        *num_lowered += 1;
+    }
+
+    // everything computed from a label is a label too
+    for node in &lowering.nodes {
+        if node.inputs.iter().any(|i| lowering.labels.contains(i)) {
+            lowering.labels.extend(node.outputs.iter().cloned());
+        }
+    }
+    let kept_outputs = graph_outputs
+        .iter()
+        .filter(|o| !lowering.labels.contains(*o))
+        .cloned()
+        .collect::<HashSet<_>>();
+    if kept_outputs.is_empty() {
+        return Err(GraphError::MisformedParams(
+            "the model only outputs class labels, which can't be proven; export its probabilities or scores"
+                .to_string(),
+        ));
+    }
+    for dropped in graph_outputs.iter().filter(|o| !kept_outputs.contains(*o)) {
+        info!("dropping the label output {}", dropped);
+    }
+
+    // only keep the nodes the remaining outputs need
+    let mut needed = kept_outputs.clone();
+    let mut kept_nodes = vec![];
+    for node in lowering.nodes.into_iter().rev() {
+        if node.outputs.iter().any(|o| needed.contains(o)) {
+            needed.extend(node.inputs.iter().cloned());
+            kept_nodes.push(node);
+        }
+    }
+    kept_nodes.reverse();
+    let produced = kept_nodes
+        .iter()
+        .flat_map(|n| n.outputs.iter().cloned())
+        .collect::<HashSet<_>>();
+
+    let mut out = Vec::with_capacity(graph.len());
+    let mut nodes_written = false;
+    for field in &fields {
+        match field.number {
+            GRAPH_NODE if nodes_written => {}
+            GRAPH_NODE => {
+                for node in &kept_nodes {
+                    write_field(&mut out, GRAPH_NODE, WIRE_LEN, &node.encode());
+                }
+                nodes_written = true;
+            }
+            GRAPH_OUTPUT => {
+                let name = name_of(field.value, VALUE_INFO_NAME)?;
+                if !kept_outputs.contains(&name) {
+                    continue;
+                }
+                match lowering.absorbed.contains(&name) {
+                    true => write_field(&mut out, GRAPH_OUTPUT, WIRE_LEN, &float_value_info(&name)),
+                    false => write_field(&mut out, field.number, field.wire_type, field.value),
+                }
+            }
+            GRAPH_VALUE_INFO => {
+                let name = name_of(field.value, VALUE_INFO_NAME)?;
+                if produced.contains(&name) && !lowering.absorbed.contains(&name) {
+                    write_field(&mut out, field.number, field.wire_type, field.value);
+                }
+            }
+            _ => write_field(&mut out, field.number, field.wire_type, field.value),
+        }
+    }
+    for (name, dims, values) in &lowering.initializers {
+        write_field(
+            &mut out,
+            GRAPH_INITIALIZER,
+            WIRE_LEN,
+            &encode_tensor(name, dims, values),
+        );
+    }
+    Ok(Some(out))
+}
+
+/// Lowers the linear models skl2onnx exports onto standard onnx ops: a `LinearClassifier` or
+/// `LinearRegressor` becomes a `Gemm` followed by the `Sigmoid` or `Softmax` of its post transform,
+/// a `Normalizer` divides by the row norms, and a `ZipMap` passes its probabilities through
+/// untouched. Class labels, and every output computed from them, are dropped so that only the
+/// probabilities or scores are left for the circuit. Returns the rewritten graph, or `None` if it
+/// has no sklearn ops.
+pub fn lower_sklearn(graph: &[u8]) -> Result<Option<Vec<u8>>, GraphError> {
+    let mut num_lowered = 0;
+    let lowered = lower_graph(graph, &mut num_lowered)?;
+    if num_lowered > 0 {
+        info!("lowered {} sklearn ops", num_lowered);
+    }
+    Ok(lowered)
+}
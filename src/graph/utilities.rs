@@ -3,9 +3,9 @@ use super::errors::GraphError;
 use super::VarScales;
 use super::{Rescaled, SupportedOp, Visibility};
 #[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
-use crate::circuit::hybrid::HybridOp;
+use crate::circuit::hybrid::{DivConfig, HybridOp};
 #[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
-use crate::circuit::lookup::LookupOp;
+use crate::circuit::lookup::{Dequantization, LookupOp, Quantization};
 #[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
 use crate::circuit::poly::PolyOp;
 use crate::circuit::Op;
@@ -31,13 +31,14 @@ use tract_onnx::tract_core::ops::{
     einsum::EinSum,
     element_wise::ElementWiseOp,
     nn::{LeakyRelu, Reduce, Softmax},
+    quant::{DequantizeLinearF32, QuantizeLinearI8, QuantizeLinearU8},
     Downsample,
 };
 #[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
 use tract_onnx::tract_hir::{
     internal::DimLike,
     ops::array::{Pad, PadMode, TypedConcat},
-    ops::cnn::PoolSpec,
+    ops::cnn::{PaddingSpec, PoolSpec},
     ops::konst::Const,
     ops::nn::DataFormat,
     tract_core::ops::cast::Cast,
@@ -58,8 +59,9 @@ pub fn quantize_float(
 ) -> Result<IntegerRep, TensorError> {
     let mult = scale_to_multiplier(scale);
     let max_value = ((IntegerRep::MAX as f64 - shift) / mult).round(); // the maximum value that can be represented w/o sig bit truncation
+    let min_value = ((IntegerRep::MIN as f64 - shift) / mult).round();
 
-    if *elem > max_value {
+    if *elem > max_value || *elem < min_value {
         return Err(TensorError::SigBitTruncationError);
     }
 
@@ -98,8 +100,9 @@ pub fn extract_padding(
 ) -> Result<Vec<(usize, usize)>, GraphError> {
     let num_relevant_dims = pool_spec.kernel_shape.len();
 
-    // get the last num_relevant_dims of the image size
-    let image_size = &image_size[image_size.len() - num_relevant_dims..];
+    // get the num_relevant_dims spatial dims of the image size, which precede the channel dim for channels last data
+    let end = image_size.len() - is_channels_last(&pool_spec.data_format) as usize;
+    let image_size = &image_size[end - num_relevant_dims..end];
 
     let dims = pool_spec.computed_padding(image_size);
     let mut padding = Vec::new();
@@ -109,6 +112,12 @@ pub fn extract_padding(
     Ok(padding)
 }
 
+#[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
+/// Whether the data format places channels after the spatial dims (eg. tensorflow / keras exports).
+pub fn is_channels_last(data_format: &DataFormat) -> bool {
+    matches!(data_format, DataFormat::NHWC | DataFormat::HWC)
+}
+
 #[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
 /// Extracts the strides from a onnx node.
 pub fn extract_strides(pool_spec: &PoolSpec) -> Result<Vec<usize>, GraphError> {
@@ -119,6 +128,48 @@ pub fn extract_strides(pool_spec: &PoolSpec) -> Result<Vec<usize>, GraphError> {
         .to_vec())
 }
 
+/// Strips leading unit dims off `dims` until it is of rank `rank`.
+/// Returns `None` if a non-unit dim would have to be dropped to get there.
+pub fn squeeze_leading_dims(dims: &[usize], rank: usize) -> Option<Vec<usize>> {
+    if dims.len() < rank {
+        return None;
+    }
+    let num_extra = dims.len() - rank;
+    if dims[..num_extra].iter().any(|d| *d != 1) {
+        return None;
+    }
+    Some(dims[num_extra..].to_vec())
+}
+
+/// Derives the padding of a transposed conv from an explicit `output_shape`, as in the onnx ConvTranspose spec.
+/// Outputs smaller than the full transposed conv are cropped by the padding, larger ones are grown by the output padding.
+/// Returns the padding and output padding per spatial dim.
+pub fn deconv_padding_from_output_shape(
+    image: &[usize],
+    kernel: &[usize],
+    stride: &[usize],
+    output_padding: &[usize],
+    output_shape: &[usize],
+    same_upper: bool,
+) -> (Vec<(usize, usize)>, Vec<usize>) {
+    (0..stride.len())
+        .map(|i| {
+            let full = (image[i] - 1) * stride[i] + kernel[i] + output_padding[i];
+            if full >= output_shape[i] {
+                let total = full - output_shape[i];
+                let padding = if same_upper {
+                    (total / 2, total - total / 2)
+                } else {
+                    (total - total / 2, total / 2)
+                };
+                (padding, output_padding[i])
+            } else {
+                ((0, 0), output_padding[i] + output_shape[i] - full)
+            }
+        })
+        .unzip()
+}
+
 /// Gets the shape of a onnx node's outlets.
 #[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
 pub fn node_output_shapes(
@@ -137,6 +188,47 @@ pub fn node_output_shapes(
 }
 #[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
 use tract_onnx::prelude::SymbolValues;
+
+/// Orders the nodes of a tract graph topologically, breaking ties by node name and then op name,
+/// so that the order only depends on the graph itself and not on the ids tract happened to assign.
+/// Returns the canonical index of every node, keyed by its tract id.
+#[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
+pub fn canonical_node_order(
+    graph: &tract_onnx::prelude::Graph<TypedFact, Box<dyn TypedOp>>,
+) -> std::collections::BTreeMap<usize, usize> {
+    use std::collections::{BTreeMap, BTreeSet};
+
+    let key = |id: usize| {
+        let node = &graph.nodes[id];
+        (node.name.clone(), node.op().name().to_string(), id)
+    };
+
+    let mut num_pending_inputs = graph.nodes.iter().map(|n| n.inputs.len()).collect_vec();
+    let mut successors = vec![vec![]; graph.nodes.len()];
+    for node in &graph.nodes {
+        for input in &node.inputs {
+            successors[input.node].push(node.id);
+        }
+    }
+
+    let mut ready: BTreeSet<_> = graph
+        .nodes
+        .iter()
+        .filter(|n| n.inputs.is_empty())
+        .map(|n| key(n.id))
+        .collect();
+    let mut order = BTreeMap::new();
+    while let Some((_, _, id)) = ready.pop_first() {
+        order.insert(id, order.len());
+        for successor in &successors[id] {
+            num_pending_inputs[*successor] -= 1;
+            if num_pending_inputs[*successor] == 0 {
+                ready.insert(key(*successor));
+            }
+        }
+    }
+    order
+}
 #[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
 /// Extracts the raw values from a tensor.
 pub fn extract_tensor_value(
@@ -270,7 +362,10 @@ fn load_op<C: tract_onnx::prelude::Op + Clone>(
 /// * `param_visibility` - [Visibility] of the node.
 /// * `node` - the [OnnxNode] to be matched.
 /// * `inputs` - the node's inputs.
+/// * `masks` - the constants of the graph that are additive masks ahead of a softmax.
+/// * `custom_tables` - the user supplied lookup tables, keyed by op name.
 #[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
+#[allow(clippy::too_many_arguments)]
 pub fn new_op_from_onnx(
     idx: usize,
     scales: &VarScales,
@@ -278,6 +373,8 @@ pub fn new_op_from_onnx(
     inputs: &mut [super::NodeType],
     symbol_values: &SymbolValues,
     run_args: &crate::RunArgs,
+    masks: &std::collections::HashSet<usize>,
+    custom_tables: &std::collections::BTreeMap<String, crate::circuit::lookup::CustomTable>,
 ) -> Result<(SupportedOp, Vec<usize>), GraphError> {
     use tract_onnx::tract_core::ops::array::Trilu;
 
@@ -668,13 +765,36 @@ pub fn new_op_from_onnx(
                 constant_scale = 0;
             }
 
-            // Quantize the raw value
-            let quantized_value = quantize_tensor(
-                raw_value.clone(),
-                constant_scale,
-                &run_args.param_visibility,
-            )?;
-            let c = crate::circuit::ops::Constant::new(quantized_value, raw_value);
+            // the decomposition range at any scale the constant may be rescaled to
+            let bound = (run_args.decomp_base as f64).powi(run_args.decomp_legs as i32)
+                / 2.0
+                / scale_to_multiplier(std::cmp::max(scales.get_max(), constant_scale));
+            let bound = bound as f32;
+
+            // the raw value is only quantized once it's needed
+            let c = if masks.contains(&idx) {
+                // additive attention masks knock entries out with -inf (or -1e9 and the like),
+                // which saturate to the most negative value in range that the softmax maps to 0
+                crate::circuit::ops::Constant::saturating(
+                    raw_value,
+                    bound,
+                    constant_scale,
+                    &run_args.param_visibility,
+                )?
+            } else {
+                let out_of_range = raw_value
+                    .iter()
+                    .filter(|x| !x.is_finite() || x.abs() > bound)
+                    .count();
+                if out_of_range > 0 {
+                    return Err(GraphError::ConstantOutOfRange(idx, out_of_range, bound));
+                }
+                crate::circuit::ops::Constant::lazy(
+                    raw_value,
+                    constant_scale,
+                    &run_args.param_visibility,
+                )?
+            };
             // Create a constant op
             SupportedOp::Constant(c)
         }
@@ -781,10 +901,15 @@ pub fn new_op_from_onnx(
                             node.decrement_use();
                             deleted_indices.push(const_idx);
                         }
-                        SupportedOp::Linear(PolyOp::LeakyReLU {
-                            slope: 0.0.into(),
-                            scale: 1,
-                        })
+                        match run_args.relu_impl_of(&node.name) {
+                            crate::circuit::ReluImpl::Lookup => {
+                                SupportedOp::Linear(PolyOp::LeakyReLU {
+                                    slope: 0.0.into(),
+                                    scale: 1,
+                                })
+                            }
+                            crate::circuit::ReluImpl::Poly => SupportedOp::Linear(PolyOp::ReLU),
+                        }
                     } else {
                         SupportedOp::Hybrid(HybridOp::Max)
                     }
@@ -805,11 +930,22 @@ pub fn new_op_from_onnx(
         "Recip" => {
             let in_scale = input_scales[0];
             let max_scale = std::cmp::max(scales.get_max(), in_scale);
-            // If the input scale is larger than the params scale
-            SupportedOp::Hybrid(HybridOp::Recip {
-                input_scale: (scale_to_multiplier(in_scale) as f32).into(),
-                output_scale: (scale_to_multiplier(max_scale) as f32).into(),
-            })
+            let input_scale = (scale_to_multiplier(in_scale) as f32).into();
+            let output_scale = (scale_to_multiplier(max_scale) as f32).into();
+            // divisions by activations come through as a recip, which is the bulk of their cost
+            if run_args.recip_refinements > 0 {
+                SupportedOp::Hybrid(HybridOp::RefinedRecip(DivConfig::new(
+                    input_scale,
+                    output_scale,
+                    run_args.recip_refinements,
+                )))
+            } else {
+                // If the input scale is larger than the params scale
+                SupportedOp::Hybrid(HybridOp::Recip {
+                    input_scale,
+                    output_scale,
+                })
+            }
         }
 
         "LeakyRelu" => {
@@ -831,14 +967,41 @@ pub fn new_op_from_onnx(
         "Scan" => {
             unreachable!();
         }
-        "QuantizeLinearU8" | "DequantizeLinearF32" => {
-            SupportedOp::Linear(PolyOp::Identity { out_scale: None })
+        "QuantizeLinearU8" | "QuantizeLinearI8" => {
+            let op = load_op::<ElementWiseOp>(node.op(), idx, node.op().name().to_string())?;
+            // tract keeps the reciprocal of the onnx scale, which the codes are multiplied by
+            let (multiplier, zero_point, signed) =
+                if let Some(q) = op.0.downcast_ref::<QuantizeLinearU8>() {
+                    (q.scale, q.zero_point as i32, false)
+                } else if let Some(q) = op.0.downcast_ref::<QuantizeLinearI8>() {
+                    (q.scale, q.zero_point as i32, true)
+                } else {
+                    return Err(GraphError::OpMismatch(idx, "quantize linear".to_string()));
+                };
+            SupportedOp::Nonlinear(LookupOp::QuantizeLinear(Quantization {
+                scale: scale_to_multiplier(input_scales[0]).into(),
+                multiplier: multiplier.into(),
+                zero_point,
+                signed,
+            }))
+        }
+        "DequantizeLinearF32" => {
+            let op = load_op::<DequantizeLinearF32>(node.op(), idx, node.op().name().to_string())?;
+            SupportedOp::Nonlinear(LookupOp::DequantizeLinear(Dequantization {
+                scale: scale_to_multiplier(input_scales[0]).into(),
+                multiplier: op.scale.into(),
+                zero_point: op.zero_point,
+                output_scale: scale_to_multiplier(scales.input).into(),
+            }))
         }
         "Abs" => SupportedOp::Linear(PolyOp::Abs),
         "Neg" => SupportedOp::Linear(PolyOp::Neg),
         "HardSwish" => SupportedOp::Nonlinear(LookupOp::HardSwish {
             scale: scale_to_multiplier(input_scales[0]).into(),
         }),
+        "Mish" => SupportedOp::Nonlinear(LookupOp::Mish {
+            scale: scale_to_multiplier(input_scales[0]).into(),
+        }),
         "Sigmoid" => SupportedOp::Nonlinear(LookupOp::Sigmoid {
             scale: scale_to_multiplier(input_scales[0]).into(),
         }),
@@ -983,6 +1146,19 @@ pub fn new_op_from_onnx(
                                     input_scales[non_const_idx] + raw_values.log2() as i32,
                                 ),
                             });
+                        } else if run_args.whole_number_divisors
+                            && raw_values > 1.0
+                            && (raw_values - raw_values.round()).abs() < 1e-3
+                        {
+                            // tract turns divisions by a constant, eg. `x / 255`, into
+                            // multiplications by its reciprocal, which the param scale can be far
+                            // too coarse for. divide by the whole number instead
+                            inputs[const_idx].decrement_use();
+                            deleted_indices.push(const_idx);
+                            op = SupportedOp::Hybrid(HybridOp::Div {
+                                denom: raw_values.round().into(),
+                                use_range_check_for_int: !run_args.div_rebasing,
+                            });
                         }
                     }
                 }
@@ -1046,10 +1222,15 @@ pub fn new_op_from_onnx(
             let in_scale = input_scales[0];
             let max_scale = std::cmp::max(scales.get_max(), in_scale);
 
+            // whether the softmax reads an Add of one of the masks
+            let masked = inputs[0].inputs().iter().any(|(i, _)| masks.contains(i));
+
             SupportedOp::Hybrid(HybridOp::Softmax {
                 input_scale: scale_to_multiplier(in_scale).into(),
                 output_scale: scale_to_multiplier(max_scale).into(),
                 axes: softmax_op.axes.to_vec(),
+                refinements: run_args.recip_refinements,
+                masked,
             })
         }
         "MaxPool" => {
@@ -1064,11 +1245,12 @@ pub fn new_op_from_onnx(
 
             let pool_spec: &PoolSpec = &sumpool_node.pool_spec;
 
-            // only support pytorch type formatting for now
-            if pool_spec.data_format != DataFormat::NCHW {
-                return Err(GraphError::MissingParams(
-                    "data in wrong format".to_string(),
-                ));
+            // the pooling layout needs a batch dim
+            if !matches!(pool_spec.data_format, DataFormat::NCHW | DataFormat::NHWC) {
+                return Err(GraphError::MisformedParams(format!(
+                    "maxpool data format {:?} (expected NCHW or NHWC)",
+                    pool_spec.data_format
+                )));
             }
 
             let stride = extract_strides(pool_spec)?;
@@ -1079,6 +1261,7 @@ pub fn new_op_from_onnx(
                 padding,
                 stride: stride.to_vec(),
                 pool_dims: kernel_shape.to_vec(),
+                channels_last: is_channels_last(&pool_spec.data_format),
             })
         }
         "Ceil" => SupportedOp::Hybrid(HybridOp::Ceil {
@@ -1141,13 +1324,13 @@ pub fn new_op_from_onnx(
                 }
             }
 
-            if ((conv_node.pool_spec.data_format != DataFormat::NCHW)
-                && (conv_node.pool_spec.data_format != DataFormat::CHW))
-                || (conv_node.kernel_fmt != KernelFormat::OIHW)
-            {
-                return Err(GraphError::MisformedParams(
-                    "data or kernel in wrong format".to_string(),
-                ));
+            // onnx conv kernels are OIHW: a kernel stored HWIO, as tensorflow lays it out, reaches
+            // the conv through a Transpose, which tract folds into an OIHW constant
+            if conv_node.kernel_fmt != KernelFormat::OIHW {
+                return Err(GraphError::MisformedParams(format!(
+                    "conv kernel format {:?} (expected OIHW)",
+                    conv_node.kernel_fmt
+                )));
             }
 
             let pool_spec = &conv_node.pool_spec;
@@ -1166,17 +1349,53 @@ pub fn new_op_from_onnx(
                     replace_const(
                         output_scale,
                         2,
-                        SupportedOp::Unknown(crate::circuit::Unknown),
+                        SupportedOp::Unknown(crate::circuit::Unknown::default()),
                     )?;
                 }
             }
 
+            // some exporters add spurious leading unit dims to the kernel (e.g [1, O, I, H, W])
+            let kernel_rank = pool_spec.kernel_shape.len() + 2;
+            if input_dims[1].len() != kernel_rank {
+                let kernel_dims = squeeze_leading_dims(&input_dims[1], kernel_rank).ok_or(
+                    GraphError::InvalidDims(
+                        idx,
+                        format!(
+                            "conv kernel of shape {:?} is not of rank {}",
+                            input_dims[1], kernel_rank
+                        ),
+                    ),
+                )?;
+                if let Some(c) = inputs[1].opkind().get_mutable_constant() {
+                    if let Some(quantized) = c.quantized_values_mut() {
+                        quantized.reshape(&kernel_dims)?;
+                    }
+                    if !c.raw_values.is_empty() {
+                        c.raw_values.reshape(&kernel_dims)?;
+                    }
+                    inputs[1].replace_opkind(SupportedOp::Constant(c.clone()));
+                    if let super::NodeType::Node(n) = &mut inputs[1] {
+                        n.out_dims = kernel_dims;
+                    }
+                } else {
+                    return Err(GraphError::InvalidDims(
+                        idx,
+                        "conv kernel with extra leading dims must be a constant".to_string(),
+                    ));
+                }
+            }
+
             let group = conv_node.group;
 
             SupportedOp::Linear(PolyOp::Conv {
-                padding,
+                padding: padding
+                    .iter()
+                    .map(|(before, after)| (*before as isize, *after as isize))
+                    .collect(),
                 stride,
                 group,
+                channels_last: is_channels_last(&pool_spec.data_format),
+                strategy: run_args.conv_strategy,
             })
         }
         "Not" => SupportedOp::Linear(PolyOp::Not),
@@ -1211,7 +1430,37 @@ pub fn new_op_from_onnx(
             let pool_spec = &deconv_node.pool_spec;
 
             let stride = extract_strides(pool_spec)?;
-            let padding = extract_padding(pool_spec, &input_dims[0])?;
+            let mut padding = extract_padding(pool_spec, &input_dims[0])?;
+            let mut output_padding = deconv_node.adjustments.to_vec();
+
+            // an explicit output_shape overrides the shape given by the padding formula (the inverse is ambiguous for strides > 1),
+            // in which case the padding is implied by the output_shape instead
+            let num_spatial = stride.len();
+            let spatial = |dims: &[usize]| dims[dims.len() - num_spatial..].to_vec();
+            let image = spatial(&input_dims[0]);
+            let kernel = spatial(&input_dims[1]);
+            let output_shape = spatial(&node_output_shapes(&node, symbol_values)?[0]);
+            let formula_shape = (0..num_spatial)
+                .map(|i| {
+                    ((image[i] - 1) * stride[i] + kernel[i] + output_padding[i])
+                        .saturating_sub(padding[i].0 + padding[i].1)
+                })
+                .collect::<Vec<_>>();
+            if formula_shape != output_shape {
+                debug!(
+                    "deconv output shape {:?} differs from {:?}, deriving padding from it",
+                    output_shape, formula_shape
+                );
+                (padding, output_padding) = deconv_padding_from_output_shape(
+                    &image,
+                    &kernel,
+                    &stride,
+                    &output_padding,
+                    &output_shape,
+                    matches!(pool_spec.padding, PaddingSpec::SameUpper),
+                );
+            }
+
             // if bias exists then rescale it to the input + kernel scale
             if input_scales.len() == 3 {
                 let bias_scale = input_scales[2];
@@ -1223,14 +1472,14 @@ pub fn new_op_from_onnx(
                     replace_const(
                         output_scale,
                         2,
-                        SupportedOp::Unknown(crate::circuit::Unknown),
+                        SupportedOp::Unknown(crate::circuit::Unknown::default()),
                     )?;
                 }
             }
 
             SupportedOp::Linear(PolyOp::DeConv {
                 padding,
-                output_padding: deconv_node.adjustments.to_vec(),
+                output_padding,
                 stride,
                 group: deconv_node.group,
             })
@@ -1316,11 +1565,12 @@ pub fn new_op_from_onnx(
 
             let pool_spec: &PoolSpec = &sumpool_node.pool_spec;
 
-            // only support pytorch type formatting for now
-            if pool_spec.data_format != DataFormat::NCHW {
-                return Err(GraphError::MissingParams(
-                    "data in wrong format".to_string(),
-                ));
+            // the pooling layout needs a batch dim
+            if !matches!(pool_spec.data_format, DataFormat::NCHW | DataFormat::NHWC) {
+                return Err(GraphError::MisformedParams(format!(
+                    "sumpool data format {:?} (expected NCHW or NHWC)",
+                    pool_spec.data_format
+                )));
             }
 
             let stride = extract_strides(pool_spec)?;
@@ -1331,6 +1581,8 @@ pub fn new_op_from_onnx(
                 stride: stride.to_vec(),
                 kernel_shape: pool_spec.kernel_shape.to_vec(),
                 normalized: sumpool_node.normalize,
+                channels_last: is_channels_last(&pool_spec.data_format),
+                avgpool_mode: run_args.avgpool_mode,
             })
         }
         "Pad" => {
@@ -1346,8 +1598,9 @@ pub fn new_op_from_onnx(
                     tract_onnx::prelude::Tensor::zero::<f32>(&[])?,
                 ))
             {
-                return Err(GraphError::MisformedParams(
-                    "pad mode or pad type".to_string(),
+                return Err(GraphError::UnsupportedPadMode(
+                    idx,
+                    format!("{:?}", pad_node.mode),
                 ));
             }
 
@@ -1364,18 +1617,57 @@ pub fn new_op_from_onnx(
             SupportedOp::Linear(PolyOp::Reshape(output_shape))
         }
         "Flatten" => {
-            let new_dims: Vec<usize> = vec![inputs[0].out_dims()[0].iter().product::<usize>()];
-            SupportedOp::Linear(PolyOp::Flatten(new_dims))
+            // onnx flattens to 2d around `axis`, which keeps a leading batch dim of 1
+            let shapes = node_output_shapes(&node, symbol_values)?;
+            SupportedOp::Linear(PolyOp::Flatten(shapes[0].clone()))
         }
         c => {
-            warn!("Unknown op: {}", c);
-            SupportedOp::Unknown(crate::circuit::ops::Unknown)
+            match custom_tables.get(c).cloned() {
+                Some(table) => {
+                    if inputs.len() != 1 {
+                        return Err(GraphError::InvalidDims(
+                            idx,
+                            format!("custom table {} (expects a single input)", c),
+                        ));
+                    }
+                    if input_scales[0] != table.scale {
+                        return Err(GraphError::MisformedParams(format!(
+                            "custom table {} is at scale {} but its input is at scale {}",
+                            c, table.scale, input_scales[0]
+                        )));
+                    }
+                    if !table.covers(run_args.lookup_range) {
+                        return Err(GraphError::MisformedParams(format!(
+                            "custom table {} does not cover the lookup range {:?}",
+                            c, run_args.lookup_range
+                        )));
+                    }
+                    SupportedOp::Nonlinear(LookupOp::Custom {
+                        name: c.to_string(),
+                        table,
+                    })
+                }
+                None => {
+                    warn!("Unknown op: {}", c);
+                    SupportedOp::Unknown(crate::circuit::ops::Unknown { op: c.to_string() })
+                }
+            }
         }
     };
 
     Ok((node, deleted_indices))
 }
 
+/// Loads user supplied lookup tables for elementwise ops, keyed by op name, from a JSON file.
+pub fn load_custom_tables(
+    path: &std::path::Path,
+) -> Result<std::collections::BTreeMap<String, crate::circuit::lookup::CustomTable>, GraphError> {
+    let file = std::fs::File::open(path)
+        .map_err(|e| GraphError::ReadWriteFileError(path.display().to_string(), e.to_string()))?;
+    serde_json::from_reader(std::io::BufReader::new(file))
+        .map_err(|e| GraphError::ReadWriteFileError(path.display().to_string(), e.to_string()))
+}
+
 /// Extracts the raw values from a [crate::circuit::ops::Constant] op.
 pub fn extract_const_raw_values(op: SupportedOp) -> Option<Tensor<f32>> {
     match op {
@@ -1385,12 +1677,10 @@ pub fn extract_const_raw_values(op: SupportedOp) -> Option<Tensor<f32>> {
 }
 
 /// Extracts the quantized values from a [crate::circuit::ops::Constant] op.
-pub fn extract_const_quantized_values(op: SupportedOp) -> Option<Tensor<Fp>> {
+pub fn extract_const_quantized_values(op: SupportedOp) -> Result<Option<Tensor<Fp>>, GraphError> {
     match op {
-        SupportedOp::Constant(crate::circuit::ops::Constant {
-            quantized_values, ..
-        }) => Some(quantized_values),
-        _ => None,
+        SupportedOp::Constant(c) => Ok(Some(c.quantized_values()?.clone())),
+        _ => Ok(None),
     }
 }
 
@@ -1487,6 +1777,44 @@ pub mod tests {
 
     use super::*;
 
+    #[test]
+    fn test_squeeze_leading_dims() {
+        // [1, O, I, H, W] kernel from a malformed export
+        assert_eq!(
+            squeeze_leading_dims(&[1, 4, 3, 2, 2], 4),
+            Some(vec![4, 3, 2, 2])
+        );
+        assert_eq!(
+            squeeze_leading_dims(&[1, 1, 4, 3, 2, 2], 4),
+            Some(vec![4, 3, 2, 2])
+        );
+        assert_eq!(
+            squeeze_leading_dims(&[4, 3, 2, 2], 4),
+            Some(vec![4, 3, 2, 2])
+        );
+        // can't drop a non-unit dim
+        assert_eq!(squeeze_leading_dims(&[2, 4, 3, 2, 2], 4), None);
+        assert_eq!(squeeze_leading_dims(&[3, 2, 2], 4), None);
+    }
+
+    #[test]
+    fn test_deconv_padding_from_output_shape() {
+        // 3 -> 6 with stride 2 and a 3 wide kernel, whose full output is 7
+        let (padding, output_padding) =
+            deconv_padding_from_output_shape(&[3], &[3], &[2], &[0], &[6], false);
+        assert_eq!((padding, output_padding), (vec![(1, 0)], vec![0]));
+        let (padding, output_padding) =
+            deconv_padding_from_output_shape(&[3], &[3], &[2], &[0], &[6], true);
+        assert_eq!((padding, output_padding), (vec![(0, 1)], vec![0]));
+        // 3 -> 8 is past the full output, so it gets output padding
+        let (padding, output_padding) =
+            deconv_padding_from_output_shape(&[3, 3], &[3, 3], &[2, 2], &[0, 0], &[8, 5], false);
+        assert_eq!(
+            (padding, output_padding),
+            (vec![(0, 0), (1, 1)], vec![1, 0])
+        );
+    }
+
     #[test]
     fn test_flatten_valtensors() {
         let tensor1: Tensor<Fp> = (0..10).map(|x| x.into()).into();
@@ -0,0 +1,264 @@
+use super::errors::GraphError;
+use super::proto::{
+    name_of, parse, string, ATTRIBUTE_GRAPH, ATTRIBUTE_GRAPHS, GRAPH_INITIALIZER, GRAPH_INPUT,
+    GRAPH_NODE, GRAPH_OUTPUT, NODE_ATTRIBUTE, NODE_INPUT, NODE_NAME, NODE_OUTPUT, TENSOR_NAME,
+    VALUE_INFO_NAME,
+};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// The parts of a `NodeProto` the checks look at.
+struct Node<'a> {
+    name: String,
+    inputs: Vec<String>,
+    outputs: Vec<String>,
+    subgraphs: Vec<&'a [u8]>,
+}
+
+impl<'a> Node<'a> {
+    fn decode(msg: &'a [u8]) -> Result<Self, GraphError> {
+        let mut node = Node {
+            name: String::new(),
+            inputs: vec![],
+            outputs: vec![],
+            subgraphs: vec![],
+        };
+        for field in parse(msg)? {
+            match field.number {
+                NODE_INPUT => node.inputs.push(string(field.value)),
+                NODE_OUTPUT => node.outputs.push(string(field.value)),
+                NODE_NAME => node.name = string(field.value),
+                NODE_ATTRIBUTE => {
+                    for field in parse(field.value)? {
+                        if matches!(field.number, ATTRIBUTE_GRAPH | ATTRIBUTE_GRAPHS) {
+                            node.subgraphs.push(field.value);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(node)
+    }
+
+    /// How the node is referred to in errors.
+    fn label(&self) -> &str {
+        match self.name.is_empty() {
+            true => self.outputs.first().map_or("", |o| o.as_str()),
+            false => &self.name,
+        }
+    }
+}
+
+/// Checks the structure of an onnx model before tract loads it: node names are unique, every
+/// tensor a node reads is a graph input, an initializer or some node's output, and no nodes depend
+/// on each other in a cycle. Hand edited models breaking these otherwise surface as panics or hangs
+/// further down, so every problem found is reported at once, by node name.
+pub fn check_graph(graph: &[u8]) -> Result<(), GraphError> {
+    let mut problems = vec![];
+    check_subgraph(graph, &HashSet::new(), "", &mut problems)?;
+    match problems.is_empty() {
+        true => Ok(()),
+        false => Err(GraphError::MalformedGraph(problems.join("; "))),
+    }
+}
+
+/// Checks a graph whose nodes can also read the tensors `outer` of the graphs enclosing it.
+fn check_subgraph(
+    graph: &[u8],
+    outer: &HashSet<String>,
+    scope: &str,
+    problems: &mut Vec<String>,
+) -> Result<(), GraphError> {
+    let mut nodes = vec![];
+    let mut graph_outputs = vec![];
+    let mut available = outer.clone();
+    for field in parse(graph)? {
+        match field.number {
+            GRAPH_NODE => nodes.push(Node::decode(field.value)?),
+            GRAPH_INPUT => {
+                available.insert(name_of(field.value, VALUE_INFO_NAME)?);
+            }
+            GRAPH_INITIALIZER => {
+                available.insert(name_of(field.value, TENSOR_NAME)?);
+            }
+            GRAPH_OUTPUT => graph_outputs.push(name_of(field.value, VALUE_INFO_NAME)?),
+            _ => {}
+        }
+    }
+
+    // stable node identities rely on names being unique, unnamed nodes are left to tract to name
+    let mut uses = BTreeMap::new();
+    for node in nodes.iter().filter(|n| !n.name.is_empty()) {
+        *uses.entry(node.name.as_str()).or_insert(0) += 1;
+    }
+    for (name, count) in uses.into_iter().filter(|(_, count)| *count > 1) {
+        problems.push(format!(
+            "{}node name {} is used by {} nodes",
+            scope, name, count
+        ));
+    }
+
+    let mut producers: HashMap<&str, usize> = HashMap::new();
+    for (i, node) in nodes.iter().enumerate() {
+        for output in node.outputs.iter().filter(|o| !o.is_empty()) {
+            if let Some(j) = producers.insert(output, i) {
+                problems.push(format!(
+                    "{}tensor {} is produced by both node {} and node {}",
+                    scope,
+                    output,
+                    nodes[j].label(),
+                    node.label()
+                ));
+            }
+        }
+    }
+
+    // empty names stand for omitted optional inputs
+    let resolves = |t: &str| t.is_empty() || available.contains(t) || producers.contains_key(t);
+    for node in &nodes {
+        for input in node.inputs.iter().filter(|t| !resolves(t.as_str())) {
+            problems.push(format!(
+                "{}node {} reads {}, which isn't a graph input, an initializer or a node output",
+                scope,
+                node.label(),
+                input
+            ));
+        }
+    }
+    for output in graph_outputs.iter().filter(|t| !resolves(t.as_str())) {
+        problems.push(format!(
+            "{}graph output {} isn't produced by any node",
+            scope, output
+        ));
+    }
+
+    let cycle = cyclic_nodes(&nodes, &producers);
+    if !cycle.is_empty() {
+        problems.push(format!(
+            "{}nodes {} depend on each other in a cycle",
+            scope,
+            cycle
+                .iter()
+                .map(|i| nodes[*i].label())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+    }
+
+    let mut visible = available.clone();
+    visible.extend(producers.keys().map(|t| t.to_string()));
+    for node in &nodes {
+        let scope = format!("{}in a subgraph of node {}: ", scope, node.label());
+        for subgraph in &node.subgraphs {
+            check_subgraph(subgraph, &visible, &scope, problems)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// The nodes lying on a cycle (or between two cycles), in graph order. Nodes are peeled off from
+/// the inputs side and then from the outputs side, as in a topological sort, until only those
+/// remain.
+fn cyclic_nodes(nodes: &[Node], producers: &HashMap<&str, usize>) -> Vec<usize> {
+    let mut feeders = vec![HashSet::new(); nodes.len()];
+    let mut readers = vec![HashSet::new(); nodes.len()];
+    for (i, node) in nodes.iter().enumerate() {
+        for j in node.inputs.iter().filter_map(|t| producers.get(t.as_str())) {
+            feeders[i].insert(*j);
+            readers[*j].insert(i);
+        }
+    }
+
+    let mut remaining = vec![true; nodes.len()];
+    peel(&feeders, &readers, &mut remaining);
+    peel(&readers, &feeders, &mut remaining);
+    (0..nodes.len()).filter(|i| remaining[*i]).collect()
+}
+
+/// Removes the remaining nodes that can be reached by following `outgoing` edges from nodes with
+/// no `incoming` edges left.
+fn peel(incoming: &[HashSet<usize>], outgoing: &[HashSet<usize>], remaining: &mut [bool]) {
+    let mut degree = incoming
+        .iter()
+        .map(|edges| edges.iter().filter(|j| remaining[**j]).count())
+        .collect::<Vec<_>>();
+    let mut ready = (0..degree.len())
+        .filter(|i| remaining[*i] && degree[*i] == 0)
+        .collect::<Vec<_>>();
+    while let Some(i) = ready.pop() {
+        remaining[i] = false;
+        for k in outgoing[i].iter().filter(|k| remaining[**k]) {
+            degree[*k] -= 1;
+            if degree[*k] == 0 {
+                ready.push(*k);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::proto::MODEL_GRAPH;
+
+    /// The graph of the model at `path`.
+    fn load(path: &str) -> Vec<u8> {
+        let model = std::fs::read(format!("{}/{}", env!("CARGO_MANIFEST_DIR"), path)).unwrap();
+        let fields = parse(&model).unwrap();
+        let graph = fields.iter().find(|f| f.number == MODEL_GRAPH).unwrap();
+        graph.value.to_vec()
+    }
+
+    fn problems(fixture: &str) -> String {
+        check_graph(&load(&format!("tests/assets/malformed/{}.onnx", fixture)))
+            .unwrap_err()
+            .to_string()
+    }
+
+    #[test]
+    fn well_formed_models_pass() {
+        for model in ["1l_mlp", "1l_if_const", "lstm"] {
+            check_graph(&load(&format!("examples/onnx/{}/network.onnx", model))).unwrap();
+        }
+    }
+
+    #[test]
+    fn duplicate_names_are_reported() {
+        let err = problems("duplicate_names");
+        assert!(err.contains("node name act is used by 2 nodes"), "{}", err);
+    }
+
+    #[test]
+    fn missing_inputs_are_reported() {
+        let err = problems("missing_input");
+        assert!(
+            err.contains("node add reads shift, which isn't a graph input"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn cycles_are_reported() {
+        let err = problems("cycle");
+        assert!(
+            err.contains("nodes add, relu depend on each other in a cycle"),
+            "{}",
+            err
+        );
+        // the node reading off the cycle isn't part of it
+        assert!(!err.contains("relu, out"), "{}", err);
+    }
+
+    #[test]
+    fn malformed_models_fail_to_load() {
+        for fixture in ["duplicate_names", "missing_input", "cycle"] {
+            let bytes = load(&format!("tests/assets/malformed/{}.onnx", fixture));
+            let err = super::super::Model::new(&mut &bytes[..], &crate::RunArgs::default())
+                .err()
+                .unwrap();
+            assert!(matches!(err, GraphError::MalformedGraph(_)), "{}", err);
+        }
+    }
+}
@@ -42,6 +42,8 @@ pub enum Visibility {
     KZGCommit,
     /// assigned as a constant in the circuit
     Fixed,
+    /// Params only: kept private, with a public Merkle root over the Poseidon hash of each tensor
+    Merkle,
 }
 
 impl Display for Visibility {
@@ -51,6 +53,7 @@ impl Display for Visibility {
             Visibility::Private => write!(f, "private"),
             Visibility::Public => write!(f, "public"),
             Visibility::Fixed => write!(f, "fixed"),
+            Visibility::Merkle => write!(f, "merkle"),
             Visibility::Hashed {
                 hash_is_public,
                 outlets,
@@ -93,6 +96,7 @@ impl<'a> From<&'a str> for Visibility {
             "public" => Visibility::Public,
             "polycommit" => Visibility::KZGCommit,
             "fixed" => Visibility::Fixed,
+            "merkle" => Visibility::Merkle,
             "hashed" | "hashed/public" => Visibility::Hashed {
                 hash_is_public: true,
                 outlets: vec![],
@@ -114,6 +118,7 @@ impl IntoPy<PyObject> for Visibility {
             Visibility::Private => "private".to_object(py),
             Visibility::Public => "public".to_object(py),
             Visibility::Fixed => "fixed".to_object(py),
+            Visibility::Merkle => "merkle".to_object(py),
             Visibility::KZGCommit => "polycommit".to_object(py),
             Visibility::Hashed {
                 hash_is_public,
@@ -171,6 +176,7 @@ impl<'source> FromPyObject<'source> for Visibility {
                 outlets: vec![],
             }),
             "fixed" => Ok(Visibility::Fixed),
+            "merkle" => Ok(Visibility::Merkle),
             _ => Err(PyValueError::new_err("Invalid value for Visibility")),
         }
     }
@@ -198,6 +204,10 @@ impl Visibility {
     pub fn is_polycommit(&self) -> bool {
         matches!(&self, Visibility::KZGCommit)
     }
+    #[allow(missing_docs)]
+    pub fn is_merkle(&self) -> bool {
+        matches!(&self, Visibility::Merkle)
+    }
 
     #[allow(missing_docs)]
     pub fn is_hashed_public(&self) -> bool {
@@ -224,7 +234,10 @@ impl Visibility {
 
     #[allow(missing_docs)]
     pub fn requires_processing(&self) -> bool {
-        matches!(&self, Visibility::Hashed { .. }) | matches!(&self, Visibility::KZGCommit)
+        matches!(
+            &self,
+            Visibility::Hashed { .. } | Visibility::KZGCommit | Visibility::Merkle
+        )
     }
     #[allow(missing_docs)]
     pub fn overwrites_inputs(&self) -> Vec<usize> {
@@ -235,6 +248,79 @@ impl Visibility {
     }
 }
 
+/// Where a layer's parameters live, overriding the global param visibility for the constants
+/// that layer consumes: baked into the circuit's fixed columns (and so its keys), witnessed as
+/// private advice, or committed to by the param visibility's module (hashes, polycommits or a
+/// Merkle root)
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum ParamPlacement {
+    /// Assigned as constants in fixed columns, so changing them changes the verifying key
+    Fixed,
+    /// Witnessed as advice the verifier learns nothing about
+    Advice,
+    /// Witnessed as advice and committed to as per the global param visibility
+    Committed,
+}
+
+impl ParamPlacement {
+    /// The visibility of the params placed so, given the global `param_visibility`
+    pub fn visibility(&self, param_visibility: &Visibility) -> Visibility {
+        match self {
+            ParamPlacement::Fixed => Visibility::Fixed,
+            ParamPlacement::Advice => Visibility::Private,
+            ParamPlacement::Committed => param_visibility.clone(),
+        }
+    }
+}
+
+impl Display for ParamPlacement {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ParamPlacement::Fixed => write!(f, "fixed"),
+            ParamPlacement::Advice => write!(f, "advice"),
+            ParamPlacement::Committed => write!(f, "committed"),
+        }
+    }
+}
+
+#[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
+impl ToFlags for ParamPlacement {
+    fn to_flags(&self) -> Vec<String> {
+        vec![format!("{}", self)]
+    }
+}
+
+impl std::str::FromStr for ParamPlacement {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "fixed" => Ok(ParamPlacement::Fixed),
+            "advice" => Ok(ParamPlacement::Advice),
+            "committed" => Ok(ParamPlacement::Committed),
+            _ => Err("Invalid value for ParamPlacement".to_string()),
+        }
+    }
+}
+
+#[cfg(feature = "python-bindings")]
+/// Converts ParamPlacement into a PyObject (Required for ParamPlacement to be compatible with Python)
+impl IntoPy<PyObject> for ParamPlacement {
+    fn into_py(self, py: Python) -> PyObject {
+        self.to_string().to_object(py)
+    }
+}
+
+#[cfg(feature = "python-bindings")]
+/// Obtains ParamPlacement from PyObject (Required for ParamPlacement to be compatible with Python)
+impl<'source> FromPyObject<'source> for ParamPlacement {
+    fn extract(ob: &'source PyAny) -> PyResult<Self> {
+        let trystr = <PyString as PyTryFrom>::try_from(ob)?;
+        trystr.to_string().parse().map_err(PyValueError::new_err)
+    }
+}
+
 /// Represents the scale of the model input, model parameters.
 #[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, PartialOrd)]
 pub struct VarScales {
@@ -315,6 +401,10 @@ impl VarVisibility {
             return Err(GraphError::ParamsPublicVisibility);
         }
 
+        if input_vis.is_merkle() || output_vis.is_merkle() {
+            return Err(GraphError::MerkleVisibility);
+        }
+
         if !output_vis.is_public()
             & !params_vis.is_public()
             & !input_vis.is_public()
@@ -327,6 +417,7 @@ impl VarVisibility {
             & !output_vis.is_polycommit()
             & !params_vis.is_polycommit()
             & !input_vis.is_polycommit()
+            & !params_vis.is_merkle()
         {
             return Err(GraphError::Visibility);
         }
@@ -94,12 +94,12 @@ impl From<String> for EZKLError {
 
 use std::str::FromStr;
 
-use circuit::{table::Range, CheckMode, Tolerance};
+use circuit::{table::Range, AvgPoolMode, CheckMode, ConvStrategy, ReluImpl, Tolerance};
 #[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
 use clap::Args;
 #[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
 use fieldutils::IntegerRep;
-use graph::Visibility;
+use graph::{ParamPlacement, Visibility};
 use halo2_proofs::poly::{
     ipa::commitment::IPACommitmentScheme, kzg::commitment::KZGCommitmentScheme,
 };
@@ -120,6 +120,13 @@ pub mod circuit;
 /// CLI commands.
 #[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
 pub mod commands;
+/// Small onnx models built in code, for tests to generate the fixtures they need.
+#[cfg(all(
+    any(test, feature = "dev-fixtures"),
+    feature = "ezkl",
+    not(target_arch = "wasm32")
+))]
+pub mod dev_fixtures;
 #[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
 // abigen doesn't generate docs for this module
 #[allow(missing_docs)]
@@ -266,6 +273,10 @@ pub struct RunArgs {
     /// The denominator in the fixed point representation used when quantizing inputs
     #[cfg_attr(all(feature = "ezkl", not(target_arch = "wasm32")), arg(short = 'S', long, default_value = "7", value_hint = clap::ValueHint::Other))]
     pub input_scale: Scale,
+    /// The `min->max` range of the model's float inputs, eg. 0->255 for raw pixels. The input scale is lowered so that inputs this large fit the lookup range, rather than assuming they are at most 2 in magnitude. Calibration caps the input scales it tries the same way
+    #[cfg_attr(all(feature = "ezkl", not(target_arch = "wasm32")), arg(long, value_parser = parse_key_val::<f64, f64>, allow_hyphen_values = true, value_hint = clap::ValueHint::Other))]
+    #[serde(default)]
+    pub input_range: Option<(f64, f64)>,
     /// The denominator in the fixed point representation used when quantizing parameters
     #[cfg_attr(all(feature = "ezkl", not(target_arch = "wasm32")), arg(long, default_value = "7", value_hint = clap::ValueHint::Other))]
     pub param_scale: Scale,
@@ -290,9 +301,13 @@ pub struct RunArgs {
     /// Flags whether outputs are public, private, fixed, hashed, polycommit
     #[cfg_attr(all(feature = "ezkl", not(target_arch = "wasm32")), arg(long, default_value = "public", value_hint = clap::ValueHint::Other))]
     pub output_visibility: Visibility,
-    /// Flags whether params are fixed, private, hashed, polycommit
+    /// Flags whether params are fixed, private, hashed, polycommit, merkle
     #[cfg_attr(all(feature = "ezkl", not(target_arch = "wasm32")), arg(long, default_value = "private", value_hint = clap::ValueHint::Other))]
     pub param_visibility: Visibility,
+    /// Per layer overrides of where the params a layer consumes live: `fixed` columns, private `advice`, or `committed` to as per `param_visibility`, eg. head->advice
+    #[cfg_attr(all(feature = "ezkl", not(target_arch = "wasm32")), arg(long, value_parser = parse_param_placement, value_delimiter = ',', value_hint = clap::ValueHint::Other))]
+    #[serde(default)]
+    pub param_placement: Vec<(String, ParamPlacement)>,
     #[cfg_attr(
         all(feature = "ezkl", not(target_arch = "wasm32")),
         arg(long, default_value = "false")
@@ -305,6 +320,102 @@ pub struct RunArgs {
         arg(long, default_value = "false")
     )]
     pub rebase_frac_zero_constants: bool,
+    /// Lower a multiplication by the reciprocal of a whole number, as tract gets `x / 255`, to a division by that number rather than a multiplication by the reciprocal quantized at the param scale
+    #[cfg_attr(
+        all(feature = "ezkl", not(target_arch = "wasm32")),
+        arg(long, default_value = "false")
+    )]
+    #[serde(default)]
+    pub whole_number_divisors: bool,
+    /// Rescale every conv and matmul output back to the working scale so that all activations share a single scale
+    #[cfg_attr(
+        all(feature = "ezkl", not(target_arch = "wasm32")),
+        arg(long, default_value = "false")
+    )]
+    #[serde(default)]
+    pub uniform_scale: bool,
+    /// Path to a JSON file mapping unsupported elementwise op names to explicit lookup tables
+    #[cfg_attr(all(feature = "ezkl", not(target_arch = "wasm32")), arg(long, value_hint = clap::ValueHint::FilePath))]
+    #[serde(default)]
+    pub custom_tables: Option<std::path::PathBuf>,
+    /// How convolutions are laid out: `direct` or `im2col`
+    #[cfg_attr(all(feature = "ezkl", not(target_arch = "wasm32")), arg(long, default_value = "direct", value_hint = clap::ValueHint::Other))]
+    #[serde(default)]
+    pub conv_strategy: ConvStrategy,
+    /// How average pooling divides by the window size: `exact` or `approx` (by the nearest power of two)
+    #[cfg_attr(all(feature = "ezkl", not(target_arch = "wasm32")), arg(long, default_value = "exact", value_hint = clap::ValueHint::Other))]
+    #[serde(default)]
+    pub avgpool_mode: AvgPoolMode,
+    /// How relus are laid out: `lookup` (the sign of a range checked decomposition) or `poly` (a boolean sign bit)
+    #[cfg_attr(all(feature = "ezkl", not(target_arch = "wasm32")), arg(long, default_value = "lookup", value_hint = clap::ValueHint::Other))]
+    #[serde(default)]
+    pub relu_impl: ReluImpl,
+    /// Per node overrides of `relu_impl`, eg. relu_1->poly
+    #[cfg_attr(all(feature = "ezkl", not(target_arch = "wasm32")), arg(long, value_parser = parse_node_relu_impl, value_delimiter = ',', value_hint = clap::ValueHint::Other))]
+    #[serde(default)]
+    pub node_relu_impl: Vec<(String, ReluImpl)>,
+    /// Newton–Raphson steps refining reciprocals, and so divisions by activations and softmaxes, from a lookup table of the denominators rounded to half their scale's bits, rather than range checking an exact reciprocal. Denominators of fewer than `2^(input_scale / 2 - 1)` units of their scale then divide to 0
+    #[cfg_attr(all(feature = "ezkl", not(target_arch = "wasm32")), arg(long, default_value = "0", value_hint = clap::ValueHint::Other))]
+    #[serde(default)]
+    pub recip_refinements: usize,
+    /// Lay out matmuls by fixed constant weights with at least this fraction of zero entries sparsely, skipping the zero products.
+    /// Weights of any other param visibility stay dense, as the skipped entries would be left unconstrained
+    #[cfg_attr(all(feature = "ezkl", not(target_arch = "wasm32")), arg(long, value_hint = clap::ValueHint::Other))]
+    #[serde(default)]
+    pub sparse_weights_threshold: Option<f64>,
+    /// Budget of advice columns for the model's layout. Calibration picks the fewest rows whose layout fits it, trading logrows for width.
+    /// Layers aren't tiled to fit it: a model that needs more columns even at the largest logrows fails, with the width it needs there and its widest nodes
+    #[cfg_attr(all(feature = "ezkl", not(target_arch = "wasm32")), arg(long, value_hint = clap::ValueHint::Other))]
+    #[serde(default)]
+    pub max_advice_cols: Option<usize>,
+    /// Budget of public instances, which on-chain verification costs scale with. Settings that make more values public than this fail to generate
+    #[cfg_attr(all(feature = "ezkl", not(target_arch = "wasm32")), arg(long, value_hint = clap::ValueHint::Other))]
+    #[serde(default)]
+    pub max_instances: Option<usize>,
+    /// Fold back-to-back matmuls, gemms and 1x1 convs by constant weights into one layer before quantizing
+    #[cfg_attr(
+        all(feature = "ezkl", not(target_arch = "wasm32")),
+        arg(long, default_value = "false")
+    )]
+    #[serde(default)]
+    pub fuse_linear: bool,
+    /// Fold inference BatchNormalizations into the convs by constant weights before them, so the normalization costs no constraints
+    #[cfg_attr(
+        all(feature = "ezkl", not(target_arch = "wasm32")),
+        arg(long, default_value = "false")
+    )]
+    #[serde(default)]
+    pub fold_batch_norms: bool,
+    /// Reserve a public instance for the Poseidon hash of a caller-supplied context, so that each proof is bound to the context it was made for. The proof's metadata is bound along with the context
+    #[cfg_attr(
+        all(feature = "ezkl", not(target_arch = "wasm32")),
+        arg(long, default_value = "false")
+    )]
+    #[serde(default)]
+    pub bind_context: bool,
+    /// Prove the model's input is a row of a dataset committed to by a Merkle tree of this depth (see `commit-dataset`). The tree's root and the row's index become public instances
+    #[cfg_attr(all(feature = "ezkl", not(target_arch = "wasm32")), arg(long, value_hint = clap::ValueHint::Other))]
+    #[serde(default)]
+    pub dataset_depth: Option<usize>,
+    /// `start->end` ranges of the model's flattened and concatenated inputs, each holding a one-hot encoded categorical feature the circuit constrains to be valid
+    #[cfg_attr(all(feature = "ezkl", not(target_arch = "wasm32")), arg(long, value_parser = parse_key_val::<usize, usize>, value_delimiter = ',', value_hint = clap::ValueHint::Other))]
+    #[serde(default)]
+    pub categorical_groups: Vec<(usize, usize)>,
+    /// `class->threshold`: replace the model's output by a bit proving its element `class` exceeds `threshold`. The threshold is fixed into the circuit, so it is public through the settings and verifying key, and a new threshold needs new keys
+    #[cfg_attr(all(feature = "ezkl", not(target_arch = "wasm32")), arg(long, value_parser = parse_key_val::<usize, f64>, value_hint = clap::ValueHint::Other))]
+    #[serde(default)]
+    pub threshold_check: Option<(usize, f64)>,
+    /// Warn of constants that clip more than this fraction of their values to the decomposition range when quantized
+    #[cfg_attr(all(feature = "ezkl", not(target_arch = "wasm32")), arg(long, default_value = "0.001", value_hint = clap::ValueHint::Other))]
+    #[serde(default = "default_clip_warn_threshold")]
+    pub clip_warn_threshold: f64,
+    /// Fail witness generation, rather than warn, when a node's output exceeds the output_max it was calibrated for
+    #[cfg_attr(
+        all(feature = "ezkl", not(target_arch = "wasm32")),
+        arg(long, default_value = "false")
+    )]
+    #[serde(default)]
+    pub strict_output_max: bool,
     /// check mode (safe, unsafe, etc)
     #[cfg_attr(all(feature = "ezkl", not(target_arch = "wasm32")), arg(long, default_value = "unsafe", value_hint = clap::ValueHint::Other))]
     pub check_mode: CheckMode,
@@ -319,11 +430,16 @@ pub struct RunArgs {
     pub decomp_legs: usize,
 }
 
+fn default_clip_warn_threshold() -> f64 {
+    0.001
+}
+
 impl Default for RunArgs {
     fn default() -> Self {
         Self {
             tolerance: Tolerance::default(),
             input_scale: 7,
+            input_range: None,
             param_scale: 7,
             scale_rebase_multiplier: 1,
             lookup_range: (-32768, 32768),
@@ -333,8 +449,28 @@ impl Default for RunArgs {
             input_visibility: Visibility::Private,
             output_visibility: Visibility::Public,
             param_visibility: Visibility::Private,
+            param_placement: vec![],
             div_rebasing: false,
             rebase_frac_zero_constants: false,
+            whole_number_divisors: false,
+            uniform_scale: false,
+            custom_tables: None,
+            conv_strategy: ConvStrategy::Direct,
+            avgpool_mode: AvgPoolMode::Exact,
+            relu_impl: ReluImpl::Lookup,
+            node_relu_impl: vec![],
+            recip_refinements: 0,
+            sparse_weights_threshold: None,
+            max_advice_cols: None,
+            max_instances: None,
+            fuse_linear: false,
+            fold_batch_norms: false,
+            bind_context: false,
+            dataset_depth: None,
+            categorical_groups: vec![],
+            threshold_check: None,
+            clip_warn_threshold: default_clip_warn_threshold(),
+            strict_output_max: false,
             check_mode: CheckMode::UNSAFE,
             commitment: None,
             decomp_base: 16384,
@@ -352,24 +488,96 @@ impl RunArgs {
                     .into(),
             );
         }
+        if self.input_visibility.is_merkle() || self.output_visibility.is_merkle() {
+            return Err("merkle visibility is only supported for params".into());
+        }
         if self.scale_rebase_multiplier < 1 {
             return Err("scale_rebase_multiplier must be >= 1".into());
         }
         if self.lookup_range.0 > self.lookup_range.1 {
             return Err("lookup_range min is greater than max".into());
         }
+        if let Some((min, max)) = self.input_range {
+            if !(min.is_finite() && max.is_finite()) || min > max {
+                return Err("input_range must be a finite min->max with min <= max".into());
+            }
+        }
         if self.logrows < 1 {
             return Err("logrows must be >= 1".into());
         }
         if self.num_inner_cols < 1 {
             return Err("num_inner_cols must be >= 1".into());
         }
+        if let Some(threshold) = self.sparse_weights_threshold {
+            if !(0.0..=1.0).contains(&threshold) {
+                return Err("sparse_weights_threshold must be between 0 and 1".into());
+            }
+        }
+        if let Some((_, threshold)) = self.threshold_check {
+            if !threshold.is_finite() {
+                return Err("threshold_check's threshold must be finite".into());
+            }
+        }
+        if !(0.0..=1.0).contains(&self.clip_warn_threshold) {
+            return Err("clip_warn_threshold must be between 0 and 1".into());
+        }
+        if let Some(max_advice_cols) = self.max_advice_cols {
+            // the model's layout takes at least three blocks of num_inner_cols
+            if max_advice_cols < 3 * self.num_inner_cols {
+                return Err("max_advice_cols must be >= 3 * num_inner_cols".into());
+            }
+        }
+        if let Some(depth) = self.dataset_depth {
+            if !(1..=graph::dataset::MAX_DATASET_DEPTH).contains(&depth) {
+                return Err(format!(
+                    "dataset_depth must be between 1 and {}",
+                    graph::dataset::MAX_DATASET_DEPTH
+                ));
+            }
+            // the opened row is hashed as is, so it can't already be public or processed
+            if self.input_visibility != Visibility::Private {
+                return Err("dataset_depth requires input_visibility to be private".into());
+            }
+        }
+        if self
+            .param_placement
+            .iter()
+            .any(|(_, placement)| *placement == ParamPlacement::Committed)
+            && !self.param_visibility.requires_processing()
+        {
+            return Err(
+                "committed param placements require a hashed, polycommit or merkle param_visibility"
+                    .into(),
+            );
+        }
+        if let Some((start, end)) = self.categorical_groups.iter().find(|(s, e)| s >= e) {
+            return Err(format!("categorical group {start}->{end} is empty"));
+        }
         if self.tolerance.val > 0.0 && self.output_visibility != Visibility::Public {
             return Err("tolerance > 0.0 requires output_visibility to be public".into());
         }
         Ok(())
     }
 
+    /// How the relu named `node` is laid out. Ops tract splits a relu into are named after it with
+    /// a suffix, so they share its override.
+    pub fn relu_impl_of(&self, node: &str) -> ReluImpl {
+        self.node_relu_impl
+            .iter()
+            .find(|(name, _)| is_or_splits(node, name))
+            .map_or(self.relu_impl, |(_, relu_impl)| *relu_impl)
+    }
+
+    /// Where the params the layer named `node` consumes are placed, if `param_placement`
+    /// overrides `param_visibility` for it. As with relus, ops tract splits a layer into share
+    /// its placement.
+    pub fn param_placement_of(&self, node: &str) -> Option<ParamPlacement> {
+        self.param_placement
+            .iter()
+            .find(|(name, _)| is_or_splits(node, name))
+            .map(|(_, placement)| *placement)
+    }
+
     /// Export the ezkl configuration as json
     pub fn as_json(&self) -> Result<String, Box<dyn std::error::Error>> {
         let serialized = match serde_json::to_string(&self) {
@@ -386,6 +594,12 @@ impl RunArgs {
     }
 }
 
+/// Whether `node` is the node `name`, or one of the ops tract splits it into, named after it
+/// with a `.` suffix
+fn is_or_splits(node: &str, name: &str) -> bool {
+    node == name || node.strip_prefix(name).is_some_and(|s| s.starts_with('.'))
+}
+
 /// Parse a single key-value pair
 #[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
 fn parse_key_val<T, U>(
@@ -404,3 +618,21 @@ where
     let b = s[pos + 2..].parse()?;
     Ok((a, b))
 }
+
+/// Parse a per node relu implementation, eg. relu_1->poly
+#[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
+fn parse_node_relu_impl(s: &str) -> Result<(String, ReluImpl), String> {
+    let (node, relu_impl) = s
+        .split_once("->")
+        .ok_or_else(|| format!("invalid x->y: no `->` found in `{s}`"))?;
+    Ok((node.to_string(), relu_impl.parse()?))
+}
+
+/// Parse a per layer param placement, eg. head->advice
+#[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
+fn parse_param_placement(s: &str) -> Result<(String, ParamPlacement), String> {
+    let (node, placement) = s
+        .split_once("->")
+        .ok_or_else(|| format!("invalid x->y: no `->` found in `{s}`"))?;
+    Ok((node.to_string(), placement.parse()?))
+}
@@ -21,7 +21,26 @@ pub enum PfsysError {
     /// Failed to load vk from file
     #[error("failed to load vk from file: {0}")]
     LoadVk(String),
+    /// Failed to read the SRS
+    #[error("failed to load params: {0}")]
+    LoadParams(String),
+    /// The proof doesn't verify against its public inputs
+    #[error("the proof does not verify against its public inputs")]
+    ProofRejected,
     /// Failed to load pk from file
     #[error("failed to load pk from file: {0}")]
     LoadPk(String),
+    /// Artifact metadata is missing or doesn't match its integrity hash
+    #[error("invalid artifact metadata: {0}")]
+    Metadata(String),
+    /// The artifact was written for a different circuit format, see [crate::pfsys::CIRCUIT_FORMAT_VERSION]
+    #[error("{artifact} has circuit format version {found} but this ezkl expects version {expected}, regenerate it with this version")]
+    IncompatibleVersion {
+        /// the kind of artifact
+        artifact: String,
+        /// the version the artifact was written for
+        found: u32,
+        /// the version this build reads
+        expected: u32,
+    },
 }
@@ -7,6 +7,9 @@ pub mod srs;
 /// errors related to pfsys
 pub mod errors;
 
+/// A reference verifier for proofs of a single model
+pub mod verify;
+
 pub use errors::PfsysError;
 
 use crate::circuit::CheckMode;
@@ -28,7 +31,7 @@ use halo2curves::ff::{FromUniformBytes, PrimeField, WithSmallOrderMulGroup};
 use halo2curves::serde::SerdeObject;
 use halo2curves::CurveAffine;
 use instant::Instant;
-use log::{debug, info, trace};
+use log::{debug, info, trace, warn};
 #[cfg(not(feature = "det-prove"))]
 use rand::rngs::OsRng;
 #[cfg(feature = "det-prove")]
@@ -39,9 +42,10 @@ use snark_verifier::loader::native::NativeLoader;
 use snark_verifier::system::halo2::transcript::evm::EvmTranscript;
 use snark_verifier::verifier::plonk::PlonkProtocol;
 use std::fs::File;
-use std::io::{self, BufReader, BufWriter, Cursor, Write};
+use std::io::{self, BufRead, BufReader, BufWriter, Cursor, Write};
 use std::ops::Deref;
 use std::path::PathBuf;
+use std::time::Duration;
 use thiserror::Error as thisError;
 #[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
 use tosubcommand::ToFlags;
@@ -299,6 +303,39 @@ pub struct PrettyElements {
     pub outputs: Vec<Vec<String>>,
 }
 
+/// Wall-clock durations of each phase of the proving pipeline, each recorded by the command that
+/// runs it and carried along to the proof
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Timings {
+    /// loading the compiled circuit, witness, keys and srs
+    pub load: Duration,
+    /// the forward pass over the model, as recorded in the witness by `gen-witness`
+    pub forward: Duration,
+    /// configuring the circuit and generating the keys, as recorded in the proving key by `setup`
+    pub keygen: Duration,
+    /// loading the witness into the circuit and preparing the public inputs
+    pub witness: Duration,
+    /// creating the proof
+    pub prove: Duration,
+    /// checking the proof straight after creating it, zero if it isn't checked
+    /// ([CheckMode::UNSAFE])
+    pub verify: Duration,
+}
+
+impl Timings {
+    /// Runs `f`, returning its output and the time it took
+    pub fn time<T>(f: impl FnOnce() -> T) -> (T, Duration) {
+        let now = Instant::now();
+        let res = f();
+        (res, now.elapsed())
+    }
+
+    /// Total time spent across all phases
+    pub fn total(&self) -> Duration {
+        self.load + self.forward + self.keygen + self.witness + self.prove + self.verify
+    }
+}
+
 /// An application snark with proof and instance variables ready for aggregation (raw field element)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Snark<F: PrimeField + SerdeObject, C: CurveAffine>
@@ -324,6 +361,19 @@ where
     pub timestamp: Option<u128>,
     /// commitment
     pub commitment: Option<Commitments>,
+    /// time spent in each phase of producing the proof
+    #[serde(default)]
+    pub timings: Timings,
+    /// json summary of the circuit the proof is for
+    #[serde(default)]
+    pub metadata: Option<String>,
+    /// sha256 of the proof bytes and metadata, which catches accidental corruption. The metadata
+    /// is only bound by the proof under [crate::RunArgs::bind_context], see [bound_context]
+    #[serde(default)]
+    pub metadata_hash: Option<String>,
+    /// the [CIRCUIT_FORMAT_VERSION] the proof was produced with, `None` for proofs that predate it
+    #[serde(default)]
+    pub circuit_version: Option<u32>,
 }
 
 #[cfg(feature = "python-bindings")]
@@ -385,6 +435,10 @@ where
                     .as_millis(),
             ),
             commitment,
+            timings: Timings::default(),
+            metadata: None,
+            metadata_hash: None,
+            circuit_version: Some(CIRCUIT_FORMAT_VERSION),
         }
     }
 
@@ -417,10 +471,61 @@ where
         let reader = BufReader::with_capacity(*EZKL_BUF_CAPACITY, file);
         let proof: Self =
             serde_json::from_reader(reader).map_err(|e| PfsysError::LoadProof(format!("{}", e)))?;
+        proof.check_version()?;
         Ok(proof)
     }
 }
 
+impl<F: PrimeField + SerdeObject, C: CurveAffine> Snark<F, C>
+where
+    C::Scalar: Serialize + DeserializeOwned,
+    C::ScalarExt: Serialize + DeserializeOwned,
+{
+    /// Embeds `metadata` in the proof, hashed together with the proof bytes so a corrupted or
+    /// mismatched copy is caught. The hash isn't keyed, the proof binds the metadata only if its
+    /// context was built with [bound_context]
+    #[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
+    pub fn set_metadata(&mut self, metadata: String) {
+        self.metadata_hash = Some(metadata_hash(&self.proof, metadata.as_bytes()));
+        self.metadata = Some(metadata);
+    }
+
+    /// Returns the embedded metadata, if any, after checking it against its hash
+    #[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
+    pub fn metadata(&self) -> Result<Option<&str>, PfsysError> {
+        match (&self.metadata, &self.metadata_hash) {
+            (None, None) => Ok(None),
+            (Some(metadata), Some(hash))
+                if *hash == metadata_hash(&self.proof, metadata.as_bytes()) =>
+            {
+                Ok(Some(metadata.as_str()))
+            }
+            _ => Err(PfsysError::Metadata(
+                "proof metadata does not match its hash".to_string(),
+            )),
+        }
+    }
+
+    /// Checks that the proof was produced for this build's [CIRCUIT_FORMAT_VERSION].
+    /// Proofs that predate the version are let through with a warning.
+    pub fn check_version(&self) -> Result<(), PfsysError> {
+        match self.circuit_version {
+            Some(found) if found != CIRCUIT_FORMAT_VERSION => {
+                Err(PfsysError::IncompatibleVersion {
+                    artifact: "proof".to_string(),
+                    found,
+                    expected: CIRCUIT_FORMAT_VERSION,
+                })
+            }
+            Some(_) => Ok(()),
+            None => {
+                warn!("proof has no circuit format version, it may have been produced by an incompatible ezkl");
+                Ok(())
+            }
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 /// A proof split commit
 pub struct ProofSplitCommit {
@@ -618,10 +723,11 @@ where
         &mut rng,
         &mut transcript,
     )?;
+    let prove_time = now.elapsed();
     let proof = transcript.finalize();
     let hex_proof = format!("0x{}", hex::encode(&proof));
 
-    let checkable_pf = Snark::new(
+    let mut checkable_pf = Snark::new(
         protocol,
         instances,
         proof,
@@ -631,18 +737,23 @@ where
         None,
         Some(commitment),
     );
+    checkable_pf.timings.prove = prove_time;
 
     // sanity check that the generated proof is valid
     if check_mode == CheckMode::SAFE {
         debug!("verifying generated proof");
         let verifier_params = params.verifier_params();
-        verify_proof_circuit::<V, Scheme, Strategy, E, TR>(
-            &checkable_pf,
-            verifier_params,
-            pk.get_vk(),
-            strategy,
-            verifier_params.n(),
-        )?;
+        let (res, verify_time) = Timings::time(|| {
+            verify_proof_circuit::<V, Scheme, Strategy, E, TR>(
+                &checkable_pf,
+                verifier_params,
+                pk.get_vk(),
+                strategy,
+                verifier_params.n(),
+            )
+        });
+        res?;
+        checkable_pf.timings.verify = verify_time;
     }
     let elapsed = now.elapsed();
     info!(
@@ -680,6 +791,12 @@ where
     snark_new.proof[..proof_first_bytes.len()].copy_from_slice(&proof_first_bytes);
     snark_new.create_hex_proof();
 
+    // the metadata hash covers the proof bytes
+    #[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
+    if let Some(metadata) = snark.metadata()? {
+        snark_new.set_metadata(metadata.to_string());
+    }
+
     Ok(snark_new)
 }
 
@@ -793,6 +910,51 @@ where
     verify_proof::<Scheme, V, _, TR, _>(params, vk, strategy, instances, &mut transcript, orig_n)
 }
 
+/// The version of the circuit format: the constraints `configure` and `layout` produce for a
+/// given model and settings. Bump it whenever a change to either means keys and proofs written
+/// by an earlier build no longer match the circuit this build lays out.
+pub const CIRCUIT_FORMAT_VERSION: u32 = 1;
+
+/// Marks the start of a key that begins with a [CIRCUIT_FORMAT_VERSION] header
+const KEY_HEADER_MAGIC: &[u8; 8] = b"EZKLKEY\0";
+
+/// Writes the header every key starts with: [KEY_HEADER_MAGIC] then [CIRCUIT_FORMAT_VERSION] (u32 le).
+pub fn write_key_header<W: Write>(writer: &mut W) -> Result<(), io::Error> {
+    writer.write_all(KEY_HEADER_MAGIC)?;
+    writer.write_all(&CIRCUIT_FORMAT_VERSION.to_le_bytes())
+}
+
+/// Reads the header written by [write_key_header], leaving `reader` at the start of the key and
+/// erroring if the key was written for another [CIRCUIT_FORMAT_VERSION].
+/// Keys that predate the header are read as is, with a warning.
+pub fn read_key_header<R: BufRead>(reader: &mut R, artifact: &str) -> Result<(), PfsysError> {
+    let header_len = KEY_HEADER_MAGIC.len() + 4;
+    let buf = reader
+        .fill_buf()
+        .map_err(|e| PfsysError::Metadata(format!("{}: {}", artifact, e)))?;
+    if !buf.starts_with(KEY_HEADER_MAGIC) {
+        warn!(
+            "{} has no circuit format version, it may have been written by an incompatible ezkl",
+            artifact
+        );
+        return Ok(());
+    }
+    let found = buf
+        .get(KEY_HEADER_MAGIC.len()..header_len)
+        .and_then(|v| v.try_into().ok())
+        .map(u32::from_le_bytes)
+        .ok_or_else(|| PfsysError::Metadata(format!("truncated header in {}", artifact)))?;
+    if found != CIRCUIT_FORMAT_VERSION {
+        return Err(PfsysError::IncompatibleVersion {
+            artifact: artifact.to_string(),
+            found,
+            expected: CIRCUIT_FORMAT_VERSION,
+        });
+    }
+    reader.consume(header_len);
+    Ok(())
+}
+
 /// Loads a [VerifyingKey] at `path`.
 pub fn load_vk<Scheme: CommitmentScheme, C: Circuit<Scheme::Scalar>>(
     path: PathBuf,
@@ -806,6 +968,7 @@ where
     debug!("loading verification key from {:?}", path);
     let f = File::open(path.clone()).map_err(|e| PfsysError::LoadVk(format!("{}", e)))?;
     let mut reader = BufReader::with_capacity(*EZKL_BUF_CAPACITY, f);
+    read_key_header(&mut reader, "verification key")?;
     let vk = VerifyingKey::<Scheme::Curve>::read::<_, C>(
         &mut reader,
         serde_format_from_str(&EZKL_KEY_FORMAT),
@@ -829,6 +992,7 @@ where
     debug!("loading proving key from {:?}", path);
     let f = File::open(path.clone()).map_err(|e| PfsysError::LoadPk(format!("{}", e)))?;
     let mut reader = BufReader::with_capacity(*EZKL_BUF_CAPACITY, f);
+    read_key_header(&mut reader, "proving key")?;
     let pk = ProvingKey::<Scheme::Curve>::read::<_, C>(
         &mut reader,
         serde_format_from_str(&EZKL_KEY_FORMAT),
@@ -850,6 +1014,7 @@ where
     debug!("saving proving key 💾");
     let f = File::create(path)?;
     let mut writer = BufWriter::with_capacity(*EZKL_BUF_CAPACITY, f);
+    write_key_header(&mut writer)?;
     pk.write(&mut writer, serde_format_from_str(&EZKL_KEY_FORMAT))?;
     writer.flush()?;
     info!("done saving proving key ✅");
@@ -867,12 +1032,101 @@ where
     debug!("saving verification key 💾");
     let f = File::create(path)?;
     let mut writer = BufWriter::with_capacity(*EZKL_BUF_CAPACITY, f);
+    write_key_header(&mut writer)?;
     vk.write(&mut writer, serde_format_from_str(&EZKL_KEY_FORMAT))?;
     writer.flush()?;
     info!("done saving verification key ✅");
     Ok(())
 }
 
+/// Marks the end of a key file that has metadata appended to it
+#[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
+const METADATA_MAGIC: &[u8; 8] = b"EZKLMETA";
+
+/// Hashes an artifact together with its metadata.
+///
+/// The hash is unkeyed, so on its own it only catches accidental corruption, such as a truncated
+/// file or metadata copied from another artifact. A proof's metadata is bound by the proof itself
+/// when the circuit is compiled with [crate::RunArgs::bind_context], see [bound_context].
+#[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
+fn metadata_hash(artifact: &[u8], metadata: &[u8]) -> String {
+    sha256::digest([artifact, metadata].concat())
+}
+
+/// The context a proof binds in its context instance (see [crate::RunArgs::bind_context]): the
+/// caller's context followed by the sha256 of the proof's metadata, if it carries any.
+/// The instance is constrained by the proof, so swapping the metadata, or stripping it, makes the
+/// context check at verification fail.
+#[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
+pub fn bound_context(context: &[u8], metadata: Option<&str>) -> Vec<u8> {
+    match metadata {
+        Some(metadata) => [context, sha256::digest(metadata).as_bytes()].concat(),
+        None => context.to_vec(),
+    }
+}
+
+/// Appends json `metadata` to the key at `path`.
+/// The trailer is the metadata, its length (u64 le), the hex sha256 of the key and metadata bytes, and [METADATA_MAGIC].
+/// Key readers stop once they've read the key, so they ignore the trailer.
+#[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
+pub fn append_metadata(path: &PathBuf, metadata: &str) -> Result<(), PfsysError> {
+    let key = std::fs::read(path).map_err(|e| PfsysError::Metadata(format!("{}", e)))?;
+    if key.ends_with(METADATA_MAGIC) {
+        return Err(PfsysError::Metadata(format!(
+            "{} already has metadata",
+            path.display()
+        )));
+    }
+    let hash = metadata_hash(&key, metadata.as_bytes());
+    let mut f = std::fs::OpenOptions::new()
+        .append(true)
+        .open(path)
+        .map_err(|e| PfsysError::Metadata(format!("{}", e)))?;
+    f.write_all(metadata.as_bytes())
+        .and_then(|_| f.write_all(&(metadata.len() as u64).to_le_bytes()))
+        .and_then(|_| f.write_all(hash.as_bytes()))
+        .and_then(|_| f.write_all(METADATA_MAGIC))
+        .map_err(|e| PfsysError::Metadata(format!("{}", e)))?;
+    Ok(())
+}
+
+/// Reads the metadata appended to the key at `path` by [append_metadata], checking it against its hash.
+/// Returns `None` if the key has no metadata.
+#[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
+pub fn read_metadata(path: &PathBuf) -> Result<Option<String>, PfsysError> {
+    let bytes = std::fs::read(path).map_err(|e| PfsysError::Metadata(format!("{}", e)))?;
+    if !bytes.ends_with(METADATA_MAGIC) {
+        return Ok(None);
+    }
+    let malformed = || PfsysError::Metadata(format!("malformed trailer in {}", path.display()));
+
+    // hex encoded sha256
+    let hash_len = 64;
+    let hash_end = bytes.len() - METADATA_MAGIC.len();
+    let len_end = hash_end.checked_sub(hash_len).ok_or_else(malformed)?;
+    let metadata_end = len_end.checked_sub(8).ok_or_else(malformed)?;
+    let metadata_len = u64::from_le_bytes(
+        bytes[metadata_end..len_end]
+            .try_into()
+            .map_err(|_| malformed())?,
+    ) as usize;
+    let key_end = metadata_end
+        .checked_sub(metadata_len)
+        .ok_or_else(malformed)?;
+
+    let (key, metadata) = (&bytes[..key_end], &bytes[key_end..metadata_end]);
+    if bytes[len_end..hash_end] != *metadata_hash(key, metadata).as_bytes() {
+        return Err(PfsysError::Metadata(format!(
+            "metadata in {} does not match its hash",
+            path.display()
+        )));
+    }
+
+    String::from_utf8(metadata.to_vec())
+        .map(Some)
+        .map_err(|_| malformed())
+}
+
 /// Saves [CommitmentScheme] parameters to `path`.
 pub fn save_params<Scheme: CommitmentScheme>(
     path: &PathBuf,
@@ -920,6 +1174,10 @@ mod tests {
             pretty_public_inputs: None,
             timestamp: None,
             commitment: None,
+            timings: Timings::default(),
+            metadata: None,
+            metadata_hash: None,
+            circuit_version: Some(CIRCUIT_FORMAT_VERSION),
         };
 
         snark
@@ -932,5 +1190,58 @@ mod tests {
         assert_eq!(snark.instances, snark2.instances);
         assert_eq!(snark.proof, snark2.proof);
         assert_eq!(snark.transcript_type, snark2.transcript_type);
+        assert_eq!(snark2.circuit_version, Some(CIRCUIT_FORMAT_VERSION));
+    }
+
+    #[test]
+    fn test_key_header_versions() {
+        let mut key = vec![];
+        write_key_header(&mut key).unwrap();
+        key.extend_from_slice(b"key bytes");
+        let mut reader = BufReader::new(&key[..]);
+        read_key_header(&mut reader, "verification key").unwrap();
+        assert_eq!(reader.fill_buf().unwrap(), b"key bytes");
+
+        // keys from before the header are read from the start
+        let mut reader = BufReader::new(&b"key bytes"[..]);
+        read_key_header(&mut reader, "verification key").unwrap();
+        assert_eq!(reader.fill_buf().unwrap(), b"key bytes");
+
+        let mut key = KEY_HEADER_MAGIC.to_vec();
+        key.extend_from_slice(&(CIRCUIT_FORMAT_VERSION + 1).to_le_bytes());
+        key.extend_from_slice(b"key bytes");
+        let err = read_key_header(&mut BufReader::new(&key[..]), "verification key").unwrap_err();
+        assert!(matches!(
+            err,
+            PfsysError::IncompatibleVersion { found, expected, .. }
+                if found == CIRCUIT_FORMAT_VERSION + 1 && expected == CIRCUIT_FORMAT_VERSION
+        ));
+        let msg = err.to_string();
+        assert!(msg.contains(&(CIRCUIT_FORMAT_VERSION + 1).to_string()));
+        assert!(msg.contains(&CIRCUIT_FORMAT_VERSION.to_string()));
+    }
+
+    #[test]
+    fn test_proof_with_other_circuit_version_is_rejected() {
+        let path: PathBuf = "test_proof_with_other_circuit_version_is_rejected.json".into();
+        let mut snark = Snark::<Fr, G1Affine>::new(
+            None,
+            vec![vec![Fr::from(1)]],
+            vec![1, 2, 3, 4],
+            None,
+            TranscriptType::EVM,
+            None,
+            None,
+            None,
+        );
+        snark.circuit_version = Some(CIRCUIT_FORMAT_VERSION + 1);
+        snark.save(&path).unwrap();
+        let res = Snark::<Fr, G1Affine>::load::<KZGCommitmentScheme<Bn256>>(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(
+            res,
+            Err(PfsysError::IncompatibleVersion { found, expected, .. })
+                if found == CIRCUIT_FORMAT_VERSION + 1 && expected == CIRCUIT_FORMAT_VERSION
+        ));
     }
 }
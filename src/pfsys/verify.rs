@@ -0,0 +1,215 @@
+use super::{
+    read_key_header, serde_format_from_str, verify_proof_circuit, PfsysError, Snark, TranscriptType,
+};
+use crate::graph::{GraphCircuit, GraphSettings};
+use crate::pfsys::evm::aggregation_kzg::PoseidonTranscript;
+use crate::{Commitments, EZKL_KEY_FORMAT};
+use halo2_proofs::plonk::VerifyingKey;
+use halo2_proofs::poly::commitment::Params;
+use halo2_proofs::poly::ipa::commitment::{IPACommitmentScheme, ParamsIPA};
+use halo2_proofs::poly::ipa::multiopen::VerifierIPA;
+use halo2_proofs::poly::ipa::strategy::SingleStrategy as IPASingleStrategy;
+use halo2_proofs::poly::kzg::commitment::{KZGCommitmentScheme, ParamsKZG};
+use halo2_proofs::poly::kzg::multiopen::VerifierSHPLONK;
+use halo2_proofs::poly::kzg::strategy::SingleStrategy as KZGSingleStrategy;
+use halo2_proofs::poly::VerificationStrategy;
+use halo2curves::bn256::{Bn256, Fr, G1Affine};
+use log::debug;
+use snark_verifier::loader::native::NativeLoader;
+use snark_verifier::system::halo2::transcript::evm::EvmTranscript;
+use std::io::{BufReader, Read};
+
+/// Verifies a proof of a single model against `public_inputs`, from the serialized artifacts alone.
+///
+/// This is the reference verifier: the CLI, python and wasm verify commands are wrappers around
+/// it, and it depends on nothing but the bytes below, so it can be embedded wherever a proof has to
+/// be checked without the rest of the proving pipeline.
+///
+/// * `settings` - the settings the circuit was compiled with, they fix the commitment scheme, the
+///   number of rows and the layout of the instance columns.
+/// * `vk_bytes` - the verification key as written by `setup`: the header of [super::write_key_header]
+///   followed by the key in the `EZKL_KEY_FORMAT` serde format (raw bytes unless overridden).
+/// * `params_bytes` - the SRS as written by `gen-srs` or `get-srs`, i.e. halo2's `Params::write`,
+///   for at least `settings.run_args.logrows`. Larger SRS are downsized.
+/// * `proof_bytes` - the proof file written by `prove`, a json [Snark]. Only its `proof` and
+///   `transcript_type` are read, the instances it carries are ignored.
+/// * `public_inputs` - the instances to verify against, one `Vec` per instance column in the
+///   order of [Snark::instances].
+///
+/// Returns `Ok(false)` if the proof doesn't verify against `public_inputs`, and an error if any of
+/// the artifacts can't be decoded.
+///
+/// ```
+/// use ezkl::circuit::region::RegionSettings;
+/// use ezkl::circuit::CheckMode;
+/// use ezkl::graph::{GraphCircuit, GraphData};
+/// use ezkl::pfsys::evm::aggregation_kzg::PoseidonTranscript;
+/// use ezkl::pfsys::srs::gen_srs;
+/// use ezkl::pfsys::verify::verify_inference_proof;
+/// use ezkl::pfsys::{create_keys, create_proof_circuit, write_key_header, TranscriptType};
+/// use ezkl::{Commitments, RunArgs};
+/// use halo2_proofs::poly::commitment::Params;
+/// use halo2_proofs::poly::kzg::commitment::KZGCommitmentScheme;
+/// use halo2_proofs::poly::kzg::multiopen::{ProverSHPLONK, VerifierSHPLONK};
+/// use halo2_proofs::poly::kzg::strategy::SingleStrategy;
+/// use halo2curves::bn256::{Bn256, Fr};
+/// use snark_verifier::loader::native::NativeLoader;
+/// use std::path::PathBuf;
+///
+/// let run_args = RunArgs::default();
+/// let mut circuit =
+///     GraphCircuit::from_run_args(&run_args, &PathBuf::from("examples/onnx/1l_mlp/network.onnx"))
+///         .unwrap();
+/// let data = GraphData::from_path("examples/onnx/1l_mlp/input.json".into()).unwrap();
+/// let mut inputs = circuit.load_graph_from_file_exclusively(&data).unwrap();
+/// let region = RegionSettings::all_true(run_args.decomp_base, run_args.decomp_legs);
+/// let witness = circuit
+///     .forward::<KZGCommitmentScheme<Bn256>>(&mut inputs, None, None, region)
+///     .unwrap();
+/// circuit
+///     .calc_min_logrows(
+///         (witness.min_lookup_inputs, witness.max_lookup_inputs),
+///         witness.max_range_size,
+///         None,
+///         2.0,
+///     )
+///     .unwrap();
+/// let params = gen_srs::<KZGCommitmentScheme<Bn256>>(circuit.settings().run_args.logrows);
+/// let pk = create_keys::<KZGCommitmentScheme<Bn256>, GraphCircuit>(&circuit, &params, false)
+///     .unwrap();
+/// circuit.load_graph_witness(&witness).unwrap();
+/// let public_inputs = vec![circuit.prepare_public_inputs(&witness).unwrap()];
+/// let snark = create_proof_circuit::<
+///     KZGCommitmentScheme<_>,
+///     _,
+///     ProverSHPLONK<_>,
+///     VerifierSHPLONK<_>,
+///     SingleStrategy<_>,
+///     _,
+///     PoseidonTranscript<NativeLoader, _>,
+///     PoseidonTranscript<NativeLoader, _>,
+/// >(
+///     circuit.clone(),
+///     public_inputs.clone(),
+///     &params,
+///     &pk,
+///     CheckMode::UNSAFE,
+///     Commitments::KZG,
+///     TranscriptType::Poseidon,
+///     None,
+///     None,
+/// )
+/// .unwrap();
+///
+/// // the three artifacts exactly as `setup`, `gen-srs` and `prove` write them to disk
+/// let mut vk_bytes = vec![];
+/// write_key_header(&mut vk_bytes).unwrap();
+/// pk.get_vk().write(&mut vk_bytes, halo2_proofs::SerdeFormat::RawBytes).unwrap();
+/// let mut params_bytes = vec![];
+/// params.write(&mut params_bytes).unwrap();
+/// let proof_bytes = serde_json::to_vec(&snark).unwrap();
+///
+/// let settings = circuit.settings();
+/// assert!(verify_inference_proof(settings, &vk_bytes, &params_bytes, &proof_bytes, &public_inputs)
+///     .unwrap());
+///
+/// // the same proof doesn't verify against other public inputs
+/// let mut tampered = public_inputs.clone();
+/// tampered[0][0] += Fr::from(1);
+/// assert!(!verify_inference_proof(settings, &vk_bytes, &params_bytes, &proof_bytes, &tampered)
+///     .unwrap());
+/// ```
+pub fn verify_inference_proof(
+    settings: &GraphSettings,
+    vk_bytes: &[u8],
+    params_bytes: &[u8],
+    proof_bytes: &[u8],
+    public_inputs: &[Vec<Fr>],
+) -> Result<bool, PfsysError> {
+    let mut snark: Snark<Fr, G1Affine> =
+        serde_json::from_slice(proof_bytes).map_err(|e| PfsysError::LoadProof(format!("{}", e)))?;
+    snark.check_version()?;
+    snark.instances = public_inputs.to_vec();
+
+    let mut reader = BufReader::new(vk_bytes);
+    read_key_header(&mut reader, "verification key")?;
+    let vk = VerifyingKey::<G1Affine>::read::<_, GraphCircuit>(
+        &mut reader,
+        serde_format_from_str(&EZKL_KEY_FORMAT),
+        settings.clone(),
+    )
+    .map_err(|e| PfsysError::LoadVk(format!("{}", e)))?;
+
+    let logrows = settings.run_args.logrows;
+    let orig_n = 1 << logrows;
+    let commitment: Commitments = settings.run_args.commitment.into();
+
+    let mut reader = BufReader::new(params_bytes);
+    let result = match commitment {
+        Commitments::KZG => {
+            let params: ParamsKZG<Bn256> = read_params(&mut reader, logrows)?;
+            let strategy = KZGSingleStrategy::new(&params);
+            match snark.transcript_type {
+                TranscriptType::EVM => verify_proof_circuit::<
+                    VerifierSHPLONK<'_, Bn256>,
+                    KZGCommitmentScheme<Bn256>,
+                    KZGSingleStrategy<_>,
+                    _,
+                    EvmTranscript<G1Affine, _, _, _>,
+                >(&snark, &params, &vk, strategy, orig_n),
+                TranscriptType::Poseidon => {
+                    verify_proof_circuit::<
+                        VerifierSHPLONK<'_, Bn256>,
+                        KZGCommitmentScheme<Bn256>,
+                        KZGSingleStrategy<_>,
+                        _,
+                        PoseidonTranscript<NativeLoader, _>,
+                    >(&snark, &params, &vk, strategy, orig_n)
+                }
+            }
+        }
+        Commitments::IPA => {
+            let params: ParamsIPA<G1Affine> = read_params(&mut reader, logrows)?;
+            let strategy = IPASingleStrategy::new(&params);
+            match snark.transcript_type {
+                TranscriptType::EVM => verify_proof_circuit::<
+                    VerifierIPA<G1Affine>,
+                    IPACommitmentScheme<G1Affine>,
+                    IPASingleStrategy<_>,
+                    _,
+                    EvmTranscript<G1Affine, _, _, _>,
+                >(&snark, &params, &vk, strategy, orig_n),
+                TranscriptType::Poseidon => {
+                    verify_proof_circuit::<
+                        VerifierIPA<G1Affine>,
+                        IPACommitmentScheme<G1Affine>,
+                        IPASingleStrategy<_>,
+                        _,
+                        PoseidonTranscript<NativeLoader, _>,
+                    >(&snark, &params, &vk, strategy, orig_n)
+                }
+            }
+        }
+    };
+
+    match result {
+        Ok(_) => Ok(true),
+        Err(e) => {
+            debug!("proof rejected: {}", e);
+            Ok(false)
+        }
+    }
+}
+
+/// Reads an SRS written with halo2's `Params::write`, downsized to `logrows`.
+fn read_params<P: for<'a> Params<'a, G1Affine>>(
+    reader: &mut impl Read,
+    logrows: u32,
+) -> Result<P, PfsysError> {
+    let mut params = P::read(reader).map_err(|e| PfsysError::LoadParams(format!("{}", e)))?;
+    if logrows < params.k() {
+        debug!("downsizing params to {} logrows", logrows);
+        params.downsize(logrows);
+    }
+    Ok(params)
+}
@@ -508,7 +508,21 @@ impl<T: Clone + TensorType> Tensor<T> {
     }
 
     /// Returns the number of elements in the tensor.
+    ///
+    /// Rank-0 tensors hold a single value, unless they were created without any values.
+    /// ```
+    /// use ezkl::tensor::Tensor;
+    /// use ezkl::fieldutils::IntegerRep;
+    /// let a = Tensor::<IntegerRep>::new(Some(&[3]), &[]).unwrap();
+    /// assert_eq!(a.len(), 1);
+    /// assert!(a.is_singleton());
+    /// let b = Tensor::<IntegerRep>::new(None, &[]).unwrap();
+    /// assert_eq!(b.len(), 0);
+    /// ```
     pub fn len(&self) -> usize {
+        if self.dims().is_empty() {
+            return self.inner.len();
+        }
         self.dims().iter().product::<usize>()
     }
     /// Checks if the number of elements in tensor is 0.
@@ -981,58 +995,10 @@ impl<T: Clone + TensorType> Tensor<T> {
         assert!(source < self.dims.len());
         assert!(destination < self.dims.len());
 
-        let mut new_dims = self.dims.clone();
-        new_dims.remove(source);
-        new_dims.insert(destination, self.dims[source]);
-
-        // now reconfigure the elements appropriately in the new array
-        //  eg. if we have a 3x3x3 array and we want to move the 0th axis to the 2nd position
-        //  we need to move the elements at 0, 1, 2, 3, 4, 5, 6, 7, 8 to 0, 3, 6, 1, 4, 7, 2, 5, 8
-        //  so we need to move the elements at 0, 1, 2 to 0, 3, 6
-        //  and the elements at 3, 4, 5 to 1, 4, 7
-        //  and the elements at 6, 7, 8 to 2, 5, 8
-        let cartesian_coords = new_dims
-            .iter()
-            .map(|d| 0..*d)
-            .multi_cartesian_product()
-            .collect::<Vec<Vec<usize>>>();
-
-        let mut output = Tensor::new(None, &new_dims)?;
-
-        for coord in cartesian_coords {
-            let mut old_coord = vec![0; self.dims.len()];
-
-            // now fetch the old index
-            for (i, c) in coord.iter().enumerate() {
-                if i == destination {
-                    old_coord[source] = *c;
-                } else if i == source && source < destination {
-                    old_coord[source + 1] = *c;
-                } else if i == source && source > destination {
-                    old_coord[source - 1] = *c;
-                } else if (i < source && source < destination)
-                    || (i < destination && source > destination)
-                    || (i > source && source > destination)
-                    || (i > destination && source < destination)
-                {
-                    old_coord[i] = *c;
-                } else if i > source && source < destination {
-                    old_coord[i + 1] = *c;
-                } else if i > destination && source > destination {
-                    old_coord[i - 1] = *c;
-                } else {
-                    return Err(TensorError::DimError(
-                        "Unknown condition for moving the axis".to_string(),
-                    ));
-                }
-            }
-
-            let value = self.get(&old_coord);
-
-            output.set(&coord, value);
-        }
-
-        Ok(output)
+        let mut perm = (0..self.dims.len()).collect::<Vec<_>>();
+        perm.remove(source);
+        perm.insert(destination, source);
+        self.permute(&perm)
     }
 
     /// Swap axes of the tensor
@@ -1061,40 +1027,51 @@ impl<T: Clone + TensorType> Tensor<T> {
     pub fn swap_axes(&mut self, source: usize, destination: usize) -> Result<Self, TensorError> {
         assert!(source < self.dims.len());
         assert!(destination < self.dims.len());
-        let mut new_dims = self.dims.clone();
-        new_dims[source] = self.dims[destination];
-        new_dims[destination] = self.dims[source];
-
-        // now reconfigure the elements appropriately in the new array
-        //  eg. if we have a 3x3x3 array and we want to move the 0th axis to the 2nd position
-        //  we need to move the elements at 0, 1, 2, 3, 4, 5, 6, 7, 8 to 0, 3, 6, 1, 4, 7, 2, 5, 8
-        //  so we need to move the elements at 0, 1, 2 to 0, 3, 6
-        //  and the elements at 3, 4, 5 to 1, 4, 7
-        //  and the elements at 6, 7, 8 to 2, 5, 8
-        let cartesian_coords = new_dims
-            .iter()
-            .map(|d| 0..*d)
-            .multi_cartesian_product()
-            .collect::<Vec<Vec<usize>>>();
 
-        let mut output = Tensor::new(None, &new_dims)?;
+        let mut perm = (0..self.dims.len()).collect::<Vec<_>>();
+        perm.swap(source, destination);
+        self.permute(&perm)
+    }
 
-        for coord in cartesian_coords {
-            let mut old_coord = vec![0; self.dims.len()];
+    /// Permutes the axes of the tensor, axis `i` of the output being axis `perm[i]` of the input as
+    /// in onnx's Transpose. tract hands Transposes over as chains of MoveAxis, which
+    /// [Tensor::move_axis] lays out through this.
+    /// ```
+    /// use ezkl::tensor::Tensor;
+    /// use ezkl::fieldutils::IntegerRep;
+    /// let a = Tensor::<IntegerRep>::new(Some(&[1, 2, 3, 4, 5, 6]), &[2, 3]).unwrap();
+    /// let expected = Tensor::<IntegerRep>::new(Some(&[1, 4, 2, 5, 3, 6]), &[3, 2]).unwrap();
+    /// assert_eq!(a.permute(&[1, 0]).unwrap(), expected);
+    ///
+    /// // channels first to channels last
+    /// let a = Tensor::<IntegerRep>::new(Some(&[1, 2, 3, 4, 5, 6, 7, 8]), &[1, 2, 2, 2]).unwrap();
+    /// let expected = Tensor::<IntegerRep>::new(Some(&[1, 5, 2, 6, 3, 7, 4, 8]), &[1, 2, 2, 2]).unwrap();
+    /// assert_eq!(a.permute(&[0, 2, 3, 1]).unwrap(), expected);
+    ///
+    /// assert!(a.permute(&[0, 1, 1, 2]).is_err());
+    /// ```
+    pub fn permute(&self, perm: &[usize]) -> Result<Self, TensorError> {
+        let mut axes = perm.to_vec();
+        axes.sort();
+        if axes != (0..self.dims.len()).collect::<Vec<_>>() {
+            return Err(TensorError::DimError(format!(
+                "{:?} is not a permutation of the axes of a tensor of dims {:?}",
+                perm, self.dims
+            )));
+        }
+        if perm.iter().enumerate().all(|(i, p)| i == *p) {
+            return Ok(self.clone());
+        }
 
-            // now fetch the old index
-            for (i, c) in coord.iter().enumerate() {
-                if i == destination {
-                    old_coord[source] = *c;
-                } else if i == source {
-                    old_coord[destination] = *c;
-                } else {
-                    old_coord[i] = *c;
-                }
+        let new_dims = perm.iter().map(|p| self.dims[*p]).collect::<Vec<_>>();
+        let mut output = Tensor::new(None, &new_dims)?;
+        let mut old_coord = vec![0; self.dims.len()];
+        for coord in new_dims.iter().map(|d| 0..*d).multi_cartesian_product() {
+            for (c, p) in coord.iter().zip(perm) {
+                old_coord[*p] = *c;
             }
             output.set(&coord, self.get(&old_coord));
         }
-
         Ok(output)
     }
 
@@ -1801,6 +1778,57 @@ mod tests {
         assert_eq!(a.get_slice(&[0..2, 0..1]).unwrap(), b);
     }
 
+    #[test]
+    fn tensor_rank_zero() {
+        let a = Tensor::<IntegerRep>::new(Some(&[7]), &[]).unwrap();
+        let b = Tensor::<IntegerRep>::new(Some(&[1, 2, 3, 4]), &[2, 2]).unwrap();
+
+        // scalars broadcast against any shape
+        assert_eq!(
+            get_broadcasted_shape(a.dims(), b.dims()).unwrap(),
+            vec![2, 2]
+        );
+        let expanded = a.expand(b.dims()).unwrap();
+        assert_eq!(expanded, Tensor::new(Some(&[7, 7, 7, 7]), &[2, 2]).unwrap());
+
+        let mut c = a.clone();
+        c.reshape(&[1]).unwrap();
+        assert_eq!(c.len(), 1);
+        c.reshape(&[]).unwrap();
+        assert_eq!(c, a);
+
+        let val: ValTensor<halo2curves::bn256::Fr> = Tensor::new(
+            Some(&[ValType::Constant(halo2curves::bn256::Fr::from(7))]),
+            &[],
+        )
+        .unwrap()
+        .into();
+        assert_eq!(val.len(), 1);
+        assert!(!val.is_empty());
+    }
+
+    #[test]
+    #[cfg(feature = "onnx")]
+    fn tensor_permute_matches_ndarray() {
+        use tract_onnx::prelude::tract_ndarray::{ArrayD, IxDyn};
+
+        for dims in [vec![5], vec![2, 3], vec![2, 3, 4], vec![2, 3, 4, 5]] {
+            let len = dims.iter().product::<usize>();
+            let data = (0..len as IntegerRep).collect::<Vec<_>>();
+            let tensor = Tensor::new(Some(&data), &dims).unwrap();
+            let array = ArrayD::from_shape_vec(IxDyn(&dims), data).unwrap();
+            for perm in (0..dims.len()).permutations(dims.len()) {
+                let permuted = array.clone().permuted_axes(IxDyn(&perm));
+                let expected = Tensor::new(
+                    Some(&permuted.iter().cloned().collect::<Vec<_>>()),
+                    permuted.shape(),
+                )
+                .unwrap();
+                assert_eq!(tensor.permute(&perm).unwrap(), expected, "{:?}", perm);
+            }
+        }
+    }
+
     #[test]
     #[cfg(feature = "metal")]
     fn tensor_metal_int() {
@@ -1410,6 +1410,239 @@ pub fn slice<T: TensorType + Send + Sync>(
     t.get_slice(&slice)
 }
 
+/// Every coordinate of a tensor with the given dims, in row-major order.
+fn coordinates(dims: &[usize]) -> Vec<Vec<usize>> {
+    if dims.is_empty() {
+        return vec![vec![]];
+    }
+    dims.iter()
+        .map(|d| 0..*d)
+        .multi_cartesian_product()
+        .collect()
+}
+
+/// Evaluates an einsum equation directly, summing over every index missing from the output.
+/// # Examples
+/// ```
+/// use ezkl::tensor::Tensor;
+/// use ezkl::fieldutils::IntegerRep;
+/// use ezkl::tensor::ops::einsum;
+/// let x = Tensor::<IntegerRep>::new(Some(&[1, 2, 3, 4, 5, 6]), &[2, 3]).unwrap();
+/// let k = Tensor::<IntegerRep>::new(Some(&[1, 0, 2, 1, 0, -1]), &[3, 2]).unwrap();
+/// let result = einsum("ij,jk->ik", &[&x, &k]).unwrap();
+/// let expected = Tensor::<IntegerRep>::new(Some(&[5, -1, 14, -1]), &[2, 2]).unwrap();
+/// assert_eq!(result, expected);
+///
+/// let result = einsum("ij,ij->", &[&x, &x]).unwrap();
+/// let expected = Tensor::<IntegerRep>::new(Some(&[91]), &[1]).unwrap();
+/// assert_eq!(result, expected);
+/// ```
+pub fn einsum<T: TensorType + Mul<Output = T> + Add<Output = T> + Send + Sync>(
+    equation: &str,
+    inputs: &[&Tensor<T>],
+) -> Result<Tensor<T>, TensorError> {
+    let (input_exprs, output_expr) = equation
+        .split_once("->")
+        .ok_or_else(|| TensorError::DimError(format!("invalid einsum equation {}", equation)))?;
+    let input_exprs = input_exprs.split(',').collect_vec();
+    if input_exprs.len() != inputs.len() {
+        return Err(TensorError::DimMismatch("einsum".to_string()));
+    }
+
+    let mut sizes = std::collections::BTreeMap::new();
+    for (expr, input) in input_exprs.iter().zip(inputs) {
+        if expr.len() != input.dims().len() {
+            return Err(TensorError::DimMismatch("einsum".to_string()));
+        }
+        for (c, d) in expr.chars().zip(input.dims()) {
+            if *sizes.entry(c).or_insert(*d) != *d {
+                return Err(TensorError::DimMismatch("einsum".to_string()));
+            }
+        }
+    }
+
+    let output_dims = output_expr
+        .chars()
+        .map(|c| {
+            sizes.get(&c).copied().ok_or_else(|| {
+                TensorError::DimError(format!("invalid einsum equation {}", equation))
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let summed = sizes
+        .keys()
+        .filter(|c| !output_expr.contains(**c))
+        .copied()
+        .collect_vec();
+    let summed_coords = coordinates(&summed.iter().map(|c| sizes[c]).collect_vec());
+    let output_coords = coordinates(&output_dims);
+
+    let mut output = Tensor::new(None, &[output_coords.len()])?;
+    output = output.par_enum_map(|i, _: T| {
+        let mut index: std::collections::BTreeMap<char, usize> = output_expr
+            .chars()
+            .zip(output_coords[i].iter().copied())
+            .collect();
+        let mut acc = T::zero().unwrap();
+        for coord in summed_coords.iter() {
+            index.extend(summed.iter().copied().zip(coord.iter().copied()));
+            let prod =
+                input_exprs
+                    .iter()
+                    .zip(inputs)
+                    .fold(T::one().unwrap(), |prod, (expr, input)| {
+                        prod * input.get(&expr.chars().map(|c| index[&c]).collect_vec())
+                    });
+            acc = acc + prod;
+        }
+        Ok::<_, TensorError>(acc)
+    })?;
+
+    if !output_dims.is_empty() {
+        output.reshape(&output_dims)?;
+    }
+
+    Ok(output)
+}
+
+/// Splits conv padding, negative where it crops the image, into the ranges of the image that are
+/// kept and the (non-negative) padding added around them. `image_dims` are batch, channels, then
+/// the spatial dims `padding` applies to, one pair per spatial dim.
+/// ```
+/// use ezkl::tensor::ops::crop_negative_padding;
+///
+/// let (kept, padding) = crop_negative_padding(&[1, 2, 5, 5], &[(-1, 2), (0, -2)]).unwrap();
+/// assert_eq!(kept, vec![0..1, 0..2, 1..5, 0..3]);
+/// assert_eq!(padding, vec![(0, 2), (0, 0)]);
+///
+/// // padding only the height of a 2d image is an error rather than leaving the width unpadded
+/// assert!(crop_negative_padding(&[1, 2, 5, 5], &[(1, 1)]).is_err());
+/// ```
+pub fn crop_negative_padding(
+    image_dims: &[usize],
+    padding: &[(isize, isize)],
+) -> Result<(Vec<std::ops::Range<usize>>, Vec<(usize, usize)>), TensorError> {
+    if image_dims.len() != padding.len() + 2 {
+        return Err(TensorError::DimMismatch(format!(
+            "conv padding {:?} for an image of shape {:?}",
+            padding, image_dims
+        )));
+    }
+    let mut kept = image_dims.iter().map(|d| 0..*d).collect::<Vec<_>>();
+    let mut non_negative = Vec::with_capacity(padding.len());
+    for (i, (before, after)) in padding.iter().enumerate() {
+        let dim = image_dims[i + 2];
+        let start = (*before).min(0).unsigned_abs();
+        let end = dim
+            .checked_sub((*after).min(0).unsigned_abs())
+            .filter(|end| *end > start)
+            .ok_or_else(|| {
+                TensorError::DimMismatch(format!(
+                    "padding ({}, {}) crops away all of a dim of size {}",
+                    before, after, dim
+                ))
+            })?;
+        kept[i + 2] = start..end;
+        non_negative.push(((*before).max(0) as usize, (*after).max(0) as usize));
+    }
+    Ok((kept, non_negative))
+}
+
+/// Convolves an image (with an optional leading batch dim) with a kernel and an optional bias.
+/// # Examples
+/// ```
+/// use ezkl::tensor::Tensor;
+/// use ezkl::fieldutils::IntegerRep;
+/// use ezkl::tensor::ops::conv;
+/// let x = Tensor::<IntegerRep>::new(Some(&[1, 2, 3, 4, 5, 6, 7, 8, 9]), &[1, 1, 3, 3]).unwrap();
+/// let k = Tensor::<IntegerRep>::new(Some(&[1, 0, 0, 1]), &[1, 1, 2, 2]).unwrap();
+/// let b = Tensor::<IntegerRep>::new(Some(&[1]), &[1]).unwrap();
+/// let result = conv(&[&x, &k, &b], &[(0, 0), (0, 0)], &[1, 1], 1).unwrap();
+/// let expected = Tensor::<IntegerRep>::new(Some(&[7, 9, 13, 15]), &[1, 1, 2, 2]).unwrap();
+/// assert_eq!(result, expected);
+///
+/// let result = conv(&[&x, &k], &[(1, 1), (1, 1)], &[2, 2], 1).unwrap();
+/// let expected = Tensor::<IntegerRep>::new(Some(&[1, 3, 7, 14]), &[1, 1, 2, 2]).unwrap();
+/// assert_eq!(result, expected);
+/// ```
+pub fn conv<T: TensorType + Mul<Output = T> + Add<Output = T> + Send + Sync>(
+    inputs: &[&Tensor<T>],
+    padding: &[(isize, isize)],
+    stride: &[usize],
+    num_groups: usize,
+) -> Result<Tensor<T>, TensorError> {
+    let (mut image, kernel) = (inputs[0].clone(), inputs[1]);
+    if image.dims().len() + 1 == kernel.dims().len() {
+        let mut dims = vec![1];
+        dims.extend(image.dims());
+        image.reshape(&dims)?;
+    }
+    let (kept, padding) = crop_negative_padding(image.dims(), padding)?;
+    let image = pad(&image.get_slice(&kept)?, padding, 2)?;
+    let (image_dims, kernel_dims) = (image.dims(), kernel.dims());
+
+    let (batch_size, input_channels, output_channels) =
+        (image_dims[0], image_dims[1], kernel_dims[0]);
+    if num_groups == 0 || input_channels % num_groups != 0 || output_channels % num_groups != 0 {
+        return Err(TensorError::DimMismatch("conv".to_string()));
+    }
+    let input_channels_per_group = input_channels / num_groups;
+    let output_channels_per_group = output_channels / num_groups;
+    // zipping over the spatial dims below would silently drop any that a short stride or kernel misses
+    if kernel_dims.len() != image_dims.len() || stride.len() + 2 != image_dims.len() {
+        return Err(TensorError::DimMismatch(format!(
+            "conv of an image of shape {:?} by a kernel of shape {:?} with stride {:?}",
+            image_dims, kernel_dims, stride
+        )));
+    }
+
+    let slides = image_dims[2..]
+        .iter()
+        .zip(&kernel_dims[2..])
+        .zip(stride)
+        .map(|((d, k), s)| {
+            d.checked_sub(*k)
+                .filter(|_| *s > 0)
+                .map(|x| x / s + 1)
+                .ok_or_else(|| TensorError::Overflow("conv".to_string()))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut output_dims = vec![batch_size, output_channels];
+    output_dims.extend(slides);
+    let output_coords = coordinates(&output_dims);
+    let kernel_coords = coordinates(&kernel_dims[1..]);
+
+    let mut output = Tensor::new(None, &[output_coords.len()])?;
+    output = output.par_enum_map(|i, _: T| {
+        let coord = &output_coords[i];
+        let (batch, channel) = (coord[0], coord[1]);
+        let group = channel / output_channels_per_group;
+        let mut acc = match inputs.get(2) {
+            Some(bias) if bias.len() > 1 => bias[channel].clone(),
+            Some(bias) => bias[0].clone(),
+            None => T::zero().unwrap(),
+        };
+        for k in kernel_coords.iter() {
+            let mut image_coord = vec![batch, group * input_channels_per_group + k[0]];
+            image_coord.extend(
+                coord[2..]
+                    .iter()
+                    .zip(stride)
+                    .zip(&k[1..])
+                    .map(|((x, s), k)| x * s + k),
+            );
+            let mut kernel_coord = vec![channel];
+            kernel_coord.extend(k);
+            acc = acc + image.get(&image_coord) * kernel.get(&kernel_coord);
+        }
+        Ok::<_, TensorError>(acc)
+    })?;
+    output.reshape(&output_dims)?;
+
+    Ok(output)
+}
+
 // ---------------------------------------------------------------------------------------------------------
 // -- nonlinear Functions ---------------------------------------------------------------------------------
 // ---------------------------------------------------------------------------------------------------------
@@ -1588,6 +1821,42 @@ pub mod nonlinearities {
         .unwrap()
     }
 
+    /// Elementwise applies mish to a tensor of integers.
+    /// Mish is defined as `x * tanh(softplus(x))`, with `softplus(x) = ln(1 + e^x)`. It dips to
+    /// about -0.31 just below zero, tends to 0 for large negative inputs and to `x` for large
+    /// positive ones. Outputs are rounded to the nearest multiple of `1 / scale_input`, so each
+    /// is within `0.5 / scale_input` of mish.
+    /// # Arguments
+    ///
+    /// * `a` - Tensor
+    /// * `scale_input` - Single value
+    /// # Examples
+    /// ```
+    /// use ezkl::tensor::Tensor;
+    /// use ezkl::fieldutils::IntegerRep;
+    /// use ezkl::tensor::ops::nonlinearities::mish;
+    /// let x = Tensor::<IntegerRep>::new(
+    ///     Some(&[-40, -10, -1, 0, 1, 40]),
+    ///     &[2, 3],
+    /// ).unwrap();
+    /// let result = mish(&x, 10.0);
+    /// let expected = Tensor::<IntegerRep>::new(Some(&[-1, -3, -1, 0, 1, 40]), &[2, 3]).unwrap();
+    ///
+    /// assert_eq!(result, expected);
+    ///
+    /// ```
+    pub fn mish(a: &Tensor<IntegerRep>, scale_input: f64) -> Tensor<IntegerRep> {
+        a.par_enum_map(|_, a_i| {
+            let kix = (a_i as f64) / scale_input;
+            // softplus without overflowing e^x for large inputs
+            let softplus = kix.max(0.0) + (-kix.abs()).exp().ln_1p();
+            let fout = kix * softplus.tanh();
+            let rounded = (fout * scale_input).round();
+            Ok::<_, TensorError>(rounded as IntegerRep)
+        })
+        .unwrap()
+    }
+
     /// Elementwise applies exponential to a tensor of integers.
     /// # Arguments
     ///
@@ -2178,6 +2447,85 @@ pub mod nonlinearities {
         .unwrap()
     }
 
+    /// Elementwise quantizes a tensor onto the codes of an 8 bit tensor, as onnx's `QuantizeLinear`:
+    /// `clamp(round(x * multiplier) + zero_point)`, rounding half to even. Codes are integers, so
+    /// they are returned at scale 0.
+    /// # Arguments
+    ///
+    /// * `a` - Tensor
+    /// * `scale_input` - Single value
+    /// * `multiplier` - Single value, the reciprocal of the onnx scale
+    /// * `zero_point` - Single value
+    /// * `signed` - Whether codes are int8 rather than uint8
+    /// # Examples
+    /// ```
+    /// use ezkl::tensor::Tensor;
+    /// use ezkl::fieldutils::IntegerRep;
+    /// use ezkl::tensor::ops::nonlinearities::quantize_linear;
+    /// let x = Tensor::<IntegerRep>::new(
+    ///     Some(&[-64, 5, 6, 7, 200, 30]),
+    ///     &[2, 3],
+    /// ).unwrap();
+    /// let result = quantize_linear(&x, 2.0, 0.5, 10, false);
+    /// let expected = Tensor::<IntegerRep>::new(Some(&[0, 11, 12, 12, 60, 18]), &[2, 3]).unwrap();
+    /// assert_eq!(result, expected);
+    /// ```
+    pub fn quantize_linear(
+        a: &Tensor<IntegerRep>,
+        scale_input: f64,
+        multiplier: f64,
+        zero_point: i32,
+        signed: bool,
+    ) -> Tensor<IntegerRep> {
+        let (min, max) = if signed {
+            (i8::MIN as IntegerRep, i8::MAX as IntegerRep)
+        } else {
+            (u8::MIN as IntegerRep, u8::MAX as IntegerRep)
+        };
+        a.par_enum_map(|_, a_i| {
+            let code = ((a_i as f64) / scale_input * multiplier).round_ties_even() as IntegerRep;
+            Ok::<_, TensorError>((code + zero_point as IntegerRep).clamp(min, max))
+        })
+        .unwrap()
+    }
+
+    /// Elementwise maps the codes of an 8 bit tensor back to the values they stand for, as onnx's
+    /// `DequantizeLinear`: `(x - zero_point) * multiplier`.
+    /// # Arguments
+    ///
+    /// * `a` - Tensor
+    /// * `scale_input` - Single value
+    /// * `multiplier` - Single value, the onnx scale
+    /// * `zero_point` - Single value
+    /// * `scale_output` - Single value
+    /// # Examples
+    /// ```
+    /// use ezkl::tensor::Tensor;
+    /// use ezkl::fieldutils::IntegerRep;
+    /// use ezkl::tensor::ops::nonlinearities::dequantize_linear;
+    /// let x = Tensor::<IntegerRep>::new(
+    ///     Some(&[0, 10, 11, 12, 60, 18]),
+    ///     &[2, 3],
+    /// ).unwrap();
+    /// let result = dequantize_linear(&x, 1.0, 0.5, 10, 4.0);
+    /// let expected = Tensor::<IntegerRep>::new(Some(&[-20, 0, 2, 4, 100, 16]), &[2, 3]).unwrap();
+    /// assert_eq!(result, expected);
+    /// ```
+    pub fn dequantize_linear(
+        a: &Tensor<IntegerRep>,
+        scale_input: f64,
+        multiplier: f64,
+        zero_point: i32,
+        scale_output: f64,
+    ) -> Tensor<IntegerRep> {
+        a.par_enum_map(|_, a_i| {
+            let code = (a_i as f64) / scale_input;
+            let value = (code - zero_point as f64) * multiplier;
+            Ok::<_, TensorError>((value * scale_output).round() as IntegerRep)
+        })
+        .unwrap()
+    }
+
     /// Elementwise inverse.
     /// # Arguments
     ///
@@ -2231,6 +2579,40 @@ pub mod nonlinearities {
         })
         .unwrap()
     }
+
+    /// Elementwise inverse, as [recip] but with the inverse of 0 taken to be 0.
+    /// # Arguments
+    ///
+    /// * `a` - Tensor
+    /// * `input_scale` - Single value
+    /// * `out_scale` - Single value
+    /// # Examples
+    /// ```
+    /// use ezkl::tensor::Tensor;
+    /// use ezkl::fieldutils::IntegerRep;
+    /// use ezkl::tensor::ops::nonlinearities::recip_or_zero;
+    /// let x = Tensor::<IntegerRep>::new(
+    ///     Some(&[2, 1, 0, -7, 4, -1]),
+    ///     &[2, 3],
+    /// ).unwrap();
+    /// let result = recip_or_zero(&x, 2.0, 8.0);
+    /// let expected = Tensor::<IntegerRep>::new(Some(&[8, 16, 0, -2, 4, -16]), &[2, 3]).unwrap();
+    /// assert_eq!(result, expected);
+    /// ```
+    pub fn recip_or_zero(
+        a: &Tensor<IntegerRep>,
+        input_scale: f64,
+        out_scale: f64,
+    ) -> Tensor<IntegerRep> {
+        a.par_enum_map(|_, a_i| {
+            if a_i == 0 {
+                return Ok::<_, TensorError>(0);
+            }
+            let d_inv_x = out_scale * input_scale / (a_i as f64);
+            Ok::<_, TensorError>(d_inv_x.round() as IntegerRep)
+        })
+        .unwrap()
+    }
 }
 
 /// Ops that return the transcript i.e intermediate calcs of an op
@@ -724,6 +724,16 @@ impl<F: PrimeField + TensorType + PartialOrd + std::hash::Hash> ValTensor<F> {
         })
     }
 
+    /// Fetches the cells the values were assigned to. Errors if any value is yet to be assigned.
+    pub fn assigned_cells(&self) -> Result<Tensor<AssignedCell<F, F>>, TensorError> {
+        let inner = self.get_inner_tensor()?;
+        let cells = inner
+            .iter()
+            .map(|v| v.assigned_cell().ok_or(TensorError::WrongMethod))
+            .collect::<Result<Vec<_>, _>>()?;
+        Tensor::new(Some(&cells), inner.dims())
+    }
+
     /// Fetches the inner tensor as a `Tensor<Value<F>>`
     pub fn get_inner(&self) -> Result<Tensor<Value<F>>, TensorError> {
         Ok(match self {
@@ -1045,8 +1055,10 @@ impl<F: PrimeField + TensorType + PartialOrd + std::hash::Hash> ValTensor<F> {
     /// Calls `len` on the inner [Tensor].
     pub fn len(&self) -> usize {
         match self {
-            ValTensor::Value { dims, .. } => {
-                if !dims.is_empty() && (dims != &[0]) {
+            ValTensor::Value { inner, dims, .. } => {
+                if inner.is_singleton() {
+                    1
+                } else if !dims.is_empty() && (dims != &[0]) {
                     dims.iter().product::<usize>()
                 } else {
                     0
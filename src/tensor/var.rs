@@ -2,7 +2,7 @@ use std::collections::HashSet;
 
 use log::{debug, error, warn};
 
-use crate::circuit::{region::ConstantsMap, CheckMode};
+use crate::circuit::{region::ConstantsMap, witness_log, CheckMode};
 
 use super::*;
 /// A wrapper around Halo2's `Column<Fixed>` or `Column<Advice>`.
@@ -51,6 +51,13 @@ impl VarTensor {
         base.pow(logrows as u32) as usize - cs.blinding_factors() - 1
     }
 
+    /// The number of blocks of `block_size` cells needed to hold `capacity` assignments.
+    /// Assignments that straddle two blocks duplicate a cell, so each extra block also needs room for one more.
+    pub fn num_blocks_for(capacity: usize, block_size: usize) -> usize {
+        let modulo = (capacity / block_size) + 1;
+        ((capacity + modulo) / block_size) + 1
+    }
+
     /// Create a new VarTensor::Advice that is unblinded
     /// Arguments
     /// * `cs` - The constraint system
@@ -64,9 +71,7 @@ impl VarTensor {
     ) -> Self {
         let max_rows = Self::max_rows(cs, logrows) * num_inner_cols;
 
-        let mut modulo = (capacity / max_rows) + 1;
-        // we add a buffer for duplicated rows (we get at most 1 duplicated row per column)
-        modulo = ((capacity + modulo) / max_rows) + 1;
+        let modulo = Self::num_blocks_for(capacity, max_rows);
         let mut advices = vec![];
 
         if modulo > 1 {
@@ -107,9 +112,7 @@ impl VarTensor {
         let max_rows = Self::max_rows(cs, logrows);
         let max_assignments = Self::max_rows(cs, logrows) * num_inner_cols;
 
-        let mut modulo = (capacity / max_assignments) + 1;
-        // we add a buffer for duplicated rows (we get at most 1 duplicated row per column)
-        modulo = ((capacity + modulo) / max_assignments) + 1;
+        let modulo = Self::num_blocks_for(capacity, max_assignments);
         let mut advices = vec![];
 
         if modulo > 1 {
@@ -154,9 +157,7 @@ impl VarTensor {
 
         let max_rows = Self::max_rows(cs, logrows);
 
-        let mut modulo = num_constants / max_rows + 1;
-        // we add a buffer for duplicated rows (we get at most 1 duplicated row per column)
-        modulo = (num_constants + modulo) / max_rows + 1;
+        let modulo = Self::num_blocks_for(num_constants, max_rows);
 
         if modulo > 1 {
             debug!("using column duplication for {} fixed columns", modulo - 1);
@@ -608,6 +609,7 @@ impl VarTensor {
         let res = match k {
             ValType::Value(v) => match &self {
                 VarTensor::Advice { inner: advices, .. } => {
+                    let v = witness_log::log_advice(advices[x][y], z, v);
                     ValType::PrevAssigned(region.assign_advice(|| "k", advices[x][y], z, || v)?)
                 }
                 _ => unimplemented!(),
@@ -625,6 +627,11 @@ impl VarTensor {
                 _ => unimplemented!(),
             },
             ValType::AssignedValue(v) => match &self {
+                // logged values are evaluated, which skips batching their inversions
+                VarTensor::Advice { inner: advices, .. } if witness_log::is_active() => {
+                    let v = witness_log::log_advice(advices[x][y], z, v.evaluate());
+                    ValType::PrevAssigned(region.assign_advice(|| "k", advices[x][y], z, || v)?)
+                }
                 VarTensor::Advice { inner: advices, .. } => ValType::PrevAssigned(
                     region
                         .assign_advice(|| "k", advices[x][y], z, || v)?
@@ -0,0 +1,35 @@
+import numpy as np
+import onnx
+from onnx import TensorProto, helper, numpy_helper
+
+# models with the structural defects ezkl rejects before loading, which onnx.checker would also
+# reject, so they're saved without being checked
+
+x = helper.make_tensor_value_info('input', TensorProto.FLOAT, [1, 4])
+y = helper.make_tensor_value_info('output', TensorProto.FLOAT, [1, 4])
+bias = numpy_helper.from_array(np.array([0.5, -0.5, 0.25, -0.25], dtype=np.float32), 'bias')
+
+
+def save(nodes, name, initializers=()):
+    graph = helper.make_graph(nodes, name, [x], [y], list(initializers))
+    model = helper.make_model(graph, opset_imports=[helper.make_opsetid('', 13)])
+    onnx.save(model, name + '.onnx')
+
+
+# two nodes share the name act
+save([
+    helper.make_node('Relu', ['input'], ['hidden'], name='act'),
+    helper.make_node('Relu', ['hidden'], ['output'], name='act'),
+], 'duplicate_names')
+
+# add reads shift, which nothing produces
+save([
+    helper.make_node('Add', ['input', 'shift'], ['output'], name='add'),
+], 'missing_input', [bias])
+
+# add and relu feed each other, and out reads off the cycle
+save([
+    helper.make_node('Add', ['input', 'looped'], ['summed'], name='add'),
+    helper.make_node('Relu', ['summed'], ['looped'], name='relu'),
+    helper.make_node('Relu', ['summed'], ['output'], name='out'),
+], 'cycle')
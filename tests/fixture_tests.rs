@@ -0,0 +1,566 @@
+#[cfg(all(feature = "dev-fixtures", not(target_arch = "wasm32")))]
+#[cfg(test)]
+mod fixture_tests {
+
+    use ezkl::circuit::region::RegionSettings;
+    use ezkl::dev_fixtures::{
+        avg_pool, bias_free_matmuls, channel_gate, channels_last_head, cifar_classifier,
+        conv_batch_norm, conv_mish, conv_reshape_head, conv_skip, conv_stack, dense_block,
+        dilated_conv, global_avg_pool_head, max_pool, mean_centered_gemm, mixed_placement,
+        mixed_support, mlp, mnist_classifier, normalized_gemm, outlier_matmul, pad_conv,
+        padded_conv, pruned_head, ratio, regression_cases, relu_chain, relu_mean, relu_sum,
+        residual_block, sliced_conv, squeezed_gemm, Fixture,
+    };
+    use ezkl::graph::coverage::{CoverageReport, Support};
+    use ezkl::graph::input::GraphData;
+    use ezkl::graph::model::Model;
+    use ezkl::graph::{GraphCircuit, OnnxModelBuilder};
+    use ezkl::RunArgs;
+    use halo2_proofs::poly::kzg::commitment::KZGCommitmentScheme;
+    use halo2curves::bn256::Bn256;
+    use std::path::Path;
+
+    /// The error allowed on each output against tract's float evaluation, on top of a relative
+    /// error of the same size: a quantization step at the default scale per layer, with room.
+    const TOLERANCE: f32 = 0.05;
+
+    /// The fixtures checked against tract, each with the run args to check it under and, for
+    /// those there to exercise a lowering, an op the circuit must contain.
+    fn fixtures() -> Vec<(Fixture, RunArgs, Option<&'static str>)> {
+        let refined_recips = RunArgs {
+            recip_refinements: 1,
+            ..RunArgs::default()
+        };
+        let mut fixtures = vec![
+            (mlp(&[4, 8, 3]), None),
+            (mlp(&[6, 6, 6, 2]), None),
+            (conv_stack(&[1, 2, 2], 6, 3), None),
+            (residual_block(2, 5), None),
+            (conv_mish(2, 5), None),
+            (pruned_head(4, 3), None),
+            (max_pool(16, 14, 2, 2), None),
+            (avg_pool(4, 6, 3, 3), None),
+            (global_avg_pool_head(8, 4, 3), None),
+            (mnist_classifier(2), None),
+            (relu_chain(4, 20), None),
+            (mixed_placement(3, 4, 2), None),
+            (conv_skip(2, 4), None),
+            (conv_reshape_head(4, 14, 10), Some("RESHAPE")),
+            (ratio(6), None),
+            (bias_free_matmuls(4, 6, 3), Some("EINSUM")),
+            (dense_block(2, 6), Some("CONCAT")),
+            (pad_conv(2, 5, "constant"), None),
+            (channels_last_head(3, 4, 5), Some("MOVEAXIS")),
+            (squeezed_gemm(4, 3), None),
+            (channel_gate(3, 4), Some("MULT")),
+            (mean_centered_gemm(4, 3), Some("SUB")),
+        ]
+        .into_iter()
+        .map(|(fixture, op)| (fixture, RunArgs::default(), op))
+        .collect::<Vec<_>>();
+        fixtures.push((ratio(6), refined_recips, Some("REFINED_RECIP")));
+        fixtures.extend(
+            regression_cases()
+                .into_iter()
+                .map(|fixture| (fixture, RunArgs::default(), None)),
+        );
+        fixtures
+    }
+
+    /// Writes a fixture and builds its circuit.
+    fn circuit_of(fixture: &Fixture, run_args: &RunArgs) -> GraphCircuit {
+        let dir = fixture.write().unwrap();
+        GraphCircuit::from_run_args(run_args, &dir.join("network.onnx"))
+            .unwrap_or_else(|e| panic!("{}: {}", fixture.name, e))
+    }
+
+    /// The ops a circuit's nodes lower to.
+    fn ops(circuit: &GraphCircuit) -> Vec<String> {
+        circuit
+            .model()
+            .graph
+            .nodes
+            .values()
+            .map(|n| n.as_str())
+            .collect()
+    }
+
+    /// Builds a fixture's circuit, asserting some node lowers to `op` and none is left unknown.
+    fn assert_lowers_to(fixture: &Fixture, run_args: &RunArgs, op: &str) -> GraphCircuit {
+        let circuit = circuit_of(fixture, run_args);
+        let lowered = ops(&circuit);
+        assert!(
+            lowered.iter().any(|o| o.contains(op)),
+            "{}: no {} in {:?}",
+            fixture.name,
+            op,
+            lowered
+        );
+        assert!(
+            lowered.iter().all(|o| !o.starts_with("Unknown")),
+            "{}: {:?}",
+            fixture.name,
+            lowered
+        );
+        circuit
+    }
+
+    /// Writes a fixture, checks the circuit's outputs agree with tract's float evaluation of it
+    /// and that a mock prove of them is satisfied.
+    fn check(fixture: &Fixture, run_args: &RunArgs) {
+        let name = fixture.name.as_str();
+        let dir = fixture.write().unwrap();
+        let model_path = dir.join("network.onnx");
+        let data = GraphData::from_path(dir.join("input.json")).unwrap();
+        let circuit = GraphCircuit::from_run_args(run_args, &model_path)
+            .unwrap_or_else(|e| panic!("{}: {}", name, e));
+        let input_shapes = circuit.model().graph.input_shapes().unwrap();
+        assert_eq!(input_shapes, fixture.input_shapes, "{}", name);
+        check_circuit(name, circuit, &model_path, data);
+    }
+
+    /// Checks a fixture's circuit on `data` as [check] does.
+    fn check_circuit(name: &str, mut circuit: GraphCircuit, model_path: &Path, data: GraphData) {
+        let run_args = &circuit.settings().run_args.clone();
+        let input_shapes = circuit.model().graph.input_shapes().unwrap();
+        let mut inputs = circuit.load_graph_from_file_exclusively(&data).unwrap();
+        let witness = circuit
+            .forward::<KZGCommitmentScheme<Bn256>>(
+                &mut inputs,
+                None,
+                None,
+                RegionSettings::all_true(run_args.decomp_base, run_args.decomp_legs),
+            )
+            .unwrap_or_else(|e| panic!("{}: {}", name, e));
+        let scales = circuit.model().graph.get_output_scales().unwrap();
+        let outputs = witness.get_float_outputs(&scales);
+        let expected =
+            Model::run_onnx_predictions(run_args, model_path, &[data], input_shapes).unwrap();
+        assert_eq!(outputs.len(), expected[0].len(), "{}", name);
+        for (output, expected) in outputs.iter().zip(expected[0].iter()) {
+            assert_eq!(output.len(), expected.len(), "{}", name);
+            for (o, e) in output.iter().zip(expected.iter()) {
+                assert!(
+                    (o - e).abs() <= TOLERANCE * e.abs().max(1.0),
+                    "{}: {} vs {}",
+                    name,
+                    o,
+                    e
+                );
+            }
+        }
+
+        circuit
+            .calc_min_logrows(
+                (witness.min_lookup_inputs, witness.max_lookup_inputs),
+                witness.max_range_size,
+                None,
+                2.0,
+            )
+            .unwrap();
+        circuit.load_graph_witness(&witness).unwrap();
+        let public_inputs = circuit.prepare_public_inputs(&witness).unwrap();
+        halo2_proofs::dev::MockProver::run(
+            circuit.settings().run_args.logrows,
+            &circuit,
+            vec![public_inputs],
+        )
+        .unwrap()
+        .assert_satisfied();
+    }
+
+    #[test]
+    fn generated_fixtures_match_tract_and_mock() {
+        for (fixture, run_args, op) in fixtures() {
+            if let Some(op) = op {
+                assert_lowers_to(&fixture, &run_args, op);
+            }
+            check(&fixture, &run_args);
+        }
+    }
+
+    #[test]
+    fn concats_rescale_inputs_of_mixed_scales() {
+        let fixture = dense_block(2, 6);
+        // the conv features are brought to the param scale, the input stays at its own
+        let run_args = RunArgs {
+            input_scale: 7,
+            param_scale: 9,
+            ..RunArgs::default()
+        };
+        let circuit = assert_lowers_to(&fixture, &run_args, "CONCAT");
+        let concats = circuit
+            .model()
+            .graph
+            .nodes
+            .values()
+            .filter(|n| n.as_str().contains("CONCAT"))
+            .collect::<Vec<_>>();
+        assert_eq!(concats.len(), 2);
+        for concat in &concats {
+            assert!(
+                concat.as_str().contains("RESCALED INPUT"),
+                "{}",
+                concat.as_str()
+            );
+            assert_eq!(concat.out_scales(), vec![9]);
+        }
+        assert_eq!(
+            circuit.model().graph.output_shapes().unwrap(),
+            vec![vec![1, 6, 6, 6]]
+        );
+        check(&fixture, &run_args);
+    }
+
+    #[test]
+    fn raw_pixels_lower_the_input_scale_to_fit() {
+        let fixture = cifar_classifier(2, 8, 4);
+        let dir = fixture.write().unwrap();
+        let model_path = dir.join("network.onnx");
+        let data = fixture.pixel_data();
+        // at the default scale of 7 a pixel of 255 quantizes to 32640, far past 14 bits
+        let circuit = OnnxModelBuilder::new(&model_path)
+            .bits(14)
+            .input_range(0.0, 255.0)
+            .build()
+            .unwrap();
+        let run_args = &circuit.settings().run_args;
+        assert_eq!((run_args.input_scale, run_args.param_scale), (5, 7));
+        assert_eq!(run_args.lookup_range, (-8192, 8192));
+
+        // without a range, the same scale is found from the pixels calibrated on
+        let calibrated = OnnxModelBuilder::new(&model_path)
+            .bits(14)
+            .calibrate_with(vec![data.clone()])
+            .build()
+            .unwrap();
+        assert_eq!(calibrated.settings().run_args.input_scale, 5);
+
+        check_circuit(&fixture.name, circuit, &model_path, data);
+    }
+
+    #[test]
+    fn pad_then_conv_matches_a_padded_conv() {
+        let outputs = |fixture: Fixture| {
+            let run_args = RunArgs::default();
+            let dir = fixture.write().unwrap();
+            let mut circuit =
+                GraphCircuit::from_run_args(&run_args, &dir.join("network.onnx")).unwrap();
+            let data = GraphData::from_path(dir.join("input.json")).unwrap();
+            let mut inputs = circuit.load_graph_from_file_exclusively(&data).unwrap();
+            circuit
+                .forward::<KZGCommitmentScheme<Bn256>>(
+                    &mut inputs,
+                    None,
+                    None,
+                    RegionSettings::all_true(run_args.decomp_base, run_args.decomp_legs),
+                )
+                .unwrap()
+                .outputs
+        };
+        assert_eq!(
+            outputs(pad_conv(2, 5, "constant")),
+            outputs(padded_conv(2, 5))
+        );
+    }
+
+    #[test]
+    fn only_constant_padding_is_supported() {
+        let dir = pad_conv(2, 5, "reflect").write().unwrap();
+        match GraphCircuit::from_run_args(&RunArgs::default(), &dir.join("network.onnx")) {
+            Ok(_) => panic!("expected reflect padding to be unsupported"),
+            Err(e) => assert!(e.to_string().contains("unsupported pad mode"), "{}", e),
+        }
+    }
+
+    #[test]
+    fn squeezing_the_batch_axis_keeps_the_dims() {
+        let circuit = circuit_of(&squeezed_gemm(4, 3), &RunArgs::default());
+        // the batch axis is a real dim of the model's shapes, added and removed again
+        assert_eq!(circuit.model().graph.input_shapes().unwrap(), vec![vec![4]]);
+        assert_eq!(
+            circuit.model().graph.output_shapes().unwrap(),
+            vec![vec![3]]
+        );
+    }
+
+    #[test]
+    fn muls_broadcast_constants_and_gate_activations() {
+        let circuit = circuit_of(&channel_gate(3, 4), &RunArgs::default());
+        // the [3, 1, 1] constant is broadcast over the image rather than rejected
+        let muls = ops(&circuit).iter().filter(|o| o.contains("MULT")).count();
+        assert_eq!(muls, 2);
+        assert_eq!(
+            circuit.model().graph.output_shapes().unwrap(),
+            vec![vec![1, 3, 4, 4]]
+        );
+    }
+
+    #[test]
+    fn subs_take_a_constant_on_either_side() {
+        let circuit = circuit_of(&mean_centered_gemm(4, 3), &RunArgs::default());
+        let subs = ops(&circuit).iter().filter(|o| o.contains("SUB")).count();
+        assert_eq!(subs, 2);
+    }
+
+    #[test]
+    fn divisions_by_a_constant_divide_by_the_whole_number() {
+        let fixture = normalized_gemm(6, 3);
+        let run_args = RunArgs {
+            whole_number_divisors: true,
+            ..RunArgs::default()
+        };
+        // rather than multiplying by 1 / 255, which quantizes to 1 / 128 at the default scale
+        let circuit = assert_lowers_to(&fixture, &run_args, "DIV (denom=255,");
+        let model_path = fixture.write().unwrap().join("network.onnx");
+        check_circuit(&fixture.name, circuit, &model_path, fixture.pixel_data());
+    }
+
+    #[test]
+    fn slices_along_channels_rows_and_columns() {
+        for axis in 1..4 {
+            let fixture = sliced_conv(axis, 4, 5);
+            let circuit = circuit_of(&fixture, &RunArgs::default());
+            let mut expected = vec![1, 4, 5, 5];
+            expected[axis] = 2;
+            assert_eq!(
+                circuit.model().graph.output_shapes().unwrap(),
+                vec![expected],
+                "axis {}",
+                axis
+            );
+            check(&fixture, &RunArgs::default());
+        }
+    }
+
+    #[test]
+    fn reduce_sums_over_an_axis_or_all_of_them() {
+        let cases: [(&[usize], bool, Vec<usize>); 4] = [
+            (&[1], true, vec![1, 1, 4, 4]),
+            (&[2], false, vec![1, 3, 4]),
+            (&[3], true, vec![1, 3, 4, 1]),
+            (&[], true, vec![1, 1, 1, 1]),
+        ];
+        for (axes, keepdims, expected) in cases {
+            let fixture = relu_sum(axes, keepdims, 3, 4);
+            let circuit = circuit_of(&fixture, &RunArgs::default());
+            assert_eq!(
+                circuit.model().graph.output_shapes().unwrap(),
+                vec![expected],
+                "{}",
+                fixture.name
+            );
+            check(&fixture, &RunArgs::default());
+        }
+    }
+
+    #[test]
+    fn reduce_means_divide_the_sum_by_the_count() {
+        // means over 3 and 48 elements divide by the count, the one over 16 shifts the scale
+        let cases: [(&[usize], bool, Vec<usize>, &str); 3] = [
+            (&[1], true, vec![1, 1, 4, 4], "DIV (denom=3,"),
+            (&[2, 3], false, vec![1, 3], "IDENTITY"),
+            (&[], true, vec![1, 1, 1, 1], "DIV (denom=48,"),
+        ];
+        for (axes, keepdims, expected, division) in cases {
+            let fixture = relu_mean(axes, keepdims, 3, 4);
+            let circuit = assert_lowers_to(&fixture, &RunArgs::default(), division);
+            assert_eq!(
+                circuit.model().graph.output_shapes().unwrap(),
+                vec![expected],
+                "{}",
+                fixture.name
+            );
+            check(&fixture, &RunArgs::default());
+        }
+    }
+
+    #[test]
+    fn max_pool_halves_the_image() {
+        let circuit = circuit_of(&max_pool(16, 14, 2, 2), &RunArgs::default());
+        assert_eq!(
+            circuit.model().graph.output_shapes().unwrap(),
+            vec![vec![1, 16, 7, 7]]
+        );
+    }
+
+    #[test]
+    fn pooling_shapes_and_scales() {
+        let run_args = RunArgs::default();
+        // the default exact division by the window size keeps the input's scale
+        let circuit = circuit_of(&avg_pool(4, 6, 3, 3), &run_args);
+        assert_eq!(
+            circuit.model().graph.output_shapes().unwrap(),
+            vec![vec![1, 4, 2, 2]]
+        );
+        assert_eq!(
+            circuit.model().graph.get_output_scales().unwrap(),
+            vec![run_args.input_scale]
+        );
+
+        let circuit = circuit_of(&global_avg_pool_head(8, 4, 3), &run_args);
+        assert_eq!(
+            circuit.model().graph.output_shapes().unwrap(),
+            vec![vec![1, 3]]
+        );
+    }
+
+    #[test]
+    fn mnist_classifier_exposes_its_probabilities() {
+        let fixture = mnist_classifier(2);
+        let dir = fixture.write().unwrap();
+        let run_args = RunArgs::default();
+        let mut circuit =
+            GraphCircuit::from_run_args(&run_args, &dir.join("network.onnx")).unwrap();
+        let scales = circuit.model().graph.get_output_scales().unwrap();
+        // probabilities are in [0, 1], so they need at least the input's precision
+        assert!(scales[0] >= run_args.input_scale);
+
+        let data = GraphData::from_path(dir.join("input.json")).unwrap();
+        let mut inputs = circuit.load_graph_from_file_exclusively(&data).unwrap();
+        let witness = circuit
+            .forward::<KZGCommitmentScheme<Bn256>>(
+                &mut inputs,
+                None,
+                None,
+                RegionSettings::all_true(run_args.decomp_base, run_args.decomp_legs),
+            )
+            .unwrap();
+        let probabilities = &witness.get_float_outputs(&scales)[0];
+        assert!(probabilities
+            .iter()
+            .all(|p| (-TOLERANCE..=1.0 + TOLERANCE).contains(p)));
+        assert!((probabilities.iter().sum::<f32>() - 1.0).abs() <= TOLERANCE);
+
+        // the public instances are the probability vector
+        circuit.load_graph_witness(&witness).unwrap();
+        assert_eq!(
+            circuit.prepare_public_inputs(&witness).unwrap(),
+            witness.outputs[0]
+        );
+    }
+
+    #[test]
+    fn peak_live_values_are_independent_of_depth() {
+        let run_args = RunArgs::default();
+        let peak = |depth| {
+            let circuit = circuit_of(&relu_chain(4, depth), &run_args);
+            let estimate = circuit.model().estimate_resources(&run_args).unwrap();
+            estimate.peak_live_values
+        };
+        // about the input, the Relu being laid out and the one it consumes, not every Relu's
+        assert!(peak(20) < 5 * 4);
+        assert_eq!(peak(20), peak(5));
+    }
+
+    #[test]
+    fn batch_norms_are_folded_into_convs() {
+        let run_args = RunArgs {
+            fold_batch_norms: true,
+            ..RunArgs::default()
+        };
+        check(&conv_batch_norm((2, 3), 5), &run_args);
+        let nodes = |fixture: Fixture| circuit_of(&fixture, &run_args).model().graph.nodes.len();
+        // the same nodes as the conv and relu alone, so the batch norm adds no constraints
+        assert_eq!(
+            nodes(conv_batch_norm((2, 3), 5)),
+            nodes(conv_stack(&[2, 3], 5, 3))
+        );
+    }
+
+    #[test]
+    fn outlier_weights_are_out_of_range() {
+        let run_args = RunArgs::default();
+        let load = |fixture: Fixture| {
+            let dir = fixture.write().unwrap();
+            GraphCircuit::from_run_args(&run_args, &dir.join("network.onnx"))
+        };
+
+        // only additive masks saturate, other constants past the decomposition range are errors
+        let err = load(outlier_matmul(8, 4)).err().unwrap().to_string();
+        assert!(
+            err.contains("8 values outside the decomposition range"),
+            "{}",
+            err
+        );
+
+        let circuit = circuit_of(&mlp(&[4, 8, 3]), &run_args);
+        let estimate = circuit.model().estimate_resources(&run_args).unwrap();
+        assert!(estimate.clip_rates.is_empty());
+    }
+
+    #[test]
+    fn dilated_convs_are_rejected() {
+        let dir = dilated_conv(2, 6).write().unwrap();
+        let err = GraphCircuit::from_run_args(&RunArgs::default(), &dir.join("network.onnx"))
+            .err()
+            .unwrap()
+            .to_string();
+        assert!(err.contains("non unit dilations not supported"), "{}", err);
+    }
+
+    #[test]
+    fn coverage_flags_unsupported_ops_and_restrictions() {
+        let report = CoverageReport::new(&mixed_support(2, 6).model.to_bytes()).unwrap();
+        let ops = report
+            .ops
+            .iter()
+            .map(|o| (o.op.as_str(), o.count, o.support))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            ops,
+            vec![
+                ("Conv", 2, Support::Partial),
+                ("LRN", 1, Support::Unsupported),
+                ("Relu", 1, Support::Supported),
+            ]
+        );
+        assert_eq!(report.ops[0].restrictions, vec!["dilation≠1 unsupported"]);
+        assert!(!report.provable);
+        assert!(report.table().contains("Conv: dilation≠1 unsupported"));
+        let json: CoverageReport =
+            serde_json::from_str(&serde_json::to_string(&report).unwrap()).unwrap();
+        assert_eq!(json, report);
+
+        let report = CoverageReport::new(&conv_stack(&[1, 2, 2], 6, 3).model.to_bytes()).unwrap();
+        assert!(report.provable, "{}", report.table());
+    }
+
+    #[test]
+    fn reshapes_flatten_conv_features_into_a_gemm() {
+        let circuit = circuit_of(&conv_reshape_head(4, 14, 10), &RunArgs::default());
+        // the -1 is inferred as 4 * 14 * 14 and the batch dim of 1 is kept
+        let reshape = circuit
+            .model()
+            .graph
+            .nodes
+            .values()
+            .find(|n| n.as_str().contains("RESHAPE"))
+            .unwrap();
+        assert_eq!(reshape.out_dims(), vec![vec![1, 784]]);
+    }
+
+    #[test]
+    fn bias_free_matmuls_take_the_weight_on_either_side() {
+        let circuit = circuit_of(&bias_free_matmuls(4, 6, 3), &RunArgs::default());
+        let einsums = ops(&circuit)
+            .iter()
+            .filter(|o| o.contains("EINSUM"))
+            .count();
+        assert_eq!(einsums, 2);
+        // the weight first matmul keeps the column shape of the features it's applied to
+        assert_eq!(
+            circuit.model().graph.output_shapes().unwrap(),
+            vec![vec![3, 1]]
+        );
+    }
+
+    #[test]
+    fn regenerated_fixtures_are_identical() {
+        let fixture = residual_block(2, 5);
+        let dir = fixture.write().unwrap();
+        let first = std::fs::read(dir.join("network.onnx")).unwrap();
+        fixture.write().unwrap();
+        assert_eq!(std::fs::read(dir.join("network.onnx")).unwrap(), first);
+    }
+}
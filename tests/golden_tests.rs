@@ -0,0 +1,163 @@
+#[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
+#[cfg(test)]
+mod golden_tests {
+
+    use ezkl::circuit::region::RegionSettings;
+    use ezkl::fieldutils::{integer_rep_to_felt, IntegerRep};
+    use ezkl::graph::input::GraphData;
+    use ezkl::graph::GraphCircuit;
+    use ezkl::RunArgs;
+    use halo2_proofs::poly::kzg::commitment::KZGCommitmentScheme;
+    use halo2curves::bn256::{Bn256, Fr as Fp};
+    use serde::Deserialize;
+    use std::path::{Path, PathBuf};
+
+    /// The fixtures, and the script regenerating them.
+    const GOLDEN_DIR: &str = "examples/golden";
+
+    /// What ezkl is expected to compute for a fixture's input, as written by `gen.py`.
+    #[derive(Deserialize)]
+    struct Golden {
+        output_scale: ezkl::Scale,
+        outputs: Vec<Vec<IntegerRep>>,
+    }
+
+    fn fixtures() -> Vec<PathBuf> {
+        let mut fixtures = std::fs::read_dir(GOLDEN_DIR)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.join("golden.json").exists())
+            .collect::<Vec<_>>();
+        fixtures.sort();
+        assert!(!fixtures.is_empty(), "no fixtures in {}", GOLDEN_DIR);
+        fixtures
+    }
+
+    /// Loads a fixture, runs its input through the circuit and checks the outputs and public
+    /// instances against its golden values.
+    fn golden_witness(dir: &Path) -> (GraphCircuit, Vec<Fp>) {
+        let golden: Golden =
+            serde_json::from_str(&std::fs::read_to_string(dir.join("golden.json")).unwrap())
+                .unwrap();
+        let data = GraphData::from_path(dir.join("input.json")).unwrap();
+        let run_args = RunArgs::default();
+        let mut circuit =
+            GraphCircuit::from_run_args(&run_args, &dir.join("network.onnx")).unwrap();
+        assert_eq!(
+            circuit.model().graph.get_output_scales().unwrap(),
+            vec![golden.output_scale; golden.outputs.len()],
+            "{}",
+            dir.display()
+        );
+
+        let mut inputs = circuit.load_graph_from_file_exclusively(&data).unwrap();
+        let witness = circuit
+            .forward::<KZGCommitmentScheme<Bn256>>(
+                &mut inputs,
+                None,
+                None,
+                RegionSettings::all_true(run_args.decomp_base, run_args.decomp_legs),
+            )
+            .unwrap();
+        let expected = golden
+            .outputs
+            .iter()
+            .map(|o| {
+                o.iter()
+                    .map(|x| integer_rep_to_felt(*x))
+                    .collect::<Vec<Fp>>()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(witness.outputs, expected, "{}", dir.display());
+
+        circuit
+            .calc_min_logrows(
+                (witness.min_lookup_inputs, witness.max_lookup_inputs),
+                witness.max_range_size,
+                None,
+                2.0,
+            )
+            .unwrap();
+        circuit.load_graph_witness(&witness).unwrap();
+        let public_inputs = circuit.prepare_public_inputs(&witness).unwrap();
+        // outputs are the only public values under the default visibility
+        assert_eq!(public_inputs, expected.concat(), "{}", dir.display());
+        (circuit, public_inputs)
+    }
+
+    #[test]
+    fn golden_mock_prove() {
+        for dir in fixtures() {
+            let (circuit, public_inputs) = golden_witness(&dir);
+            halo2_proofs::dev::MockProver::run(
+                circuit.settings().run_args.logrows,
+                &circuit,
+                vec![public_inputs],
+            )
+            .unwrap()
+            .assert_satisfied();
+        }
+    }
+
+    #[cfg(feature = "golden-prove")]
+    #[test]
+    fn golden_prove_and_verify() {
+        use ezkl::circuit::CheckMode;
+        use ezkl::pfsys::{
+            create_keys, create_proof_circuit, srs::gen_srs, verify_proof_circuit, TranscriptType,
+        };
+        use ezkl::Commitments;
+        use halo2_proofs::poly::commitment::ParamsProver;
+        use halo2_proofs::poly::kzg::{
+            multiopen::{ProverSHPLONK, VerifierSHPLONK},
+            strategy::SingleStrategy,
+        };
+        use snark_verifier::system::halo2::transcript::evm::EvmTranscript;
+
+        for dir in fixtures() {
+            let (circuit, public_inputs) = golden_witness(&dir);
+            let params = gen_srs::<KZGCommitmentScheme<Bn256>>(circuit.settings().run_args.logrows);
+            let pk =
+                create_keys::<KZGCommitmentScheme<Bn256>, GraphCircuit>(&circuit, &params, false)
+                    .unwrap();
+            let snark = create_proof_circuit::<
+                KZGCommitmentScheme<_>,
+                _,
+                ProverSHPLONK<_>,
+                VerifierSHPLONK<_>,
+                SingleStrategy<_>,
+                _,
+                EvmTranscript<_, _, _, _>,
+                EvmTranscript<_, _, _, _>,
+            >(
+                circuit.clone(),
+                vec![public_inputs.clone()],
+                &params,
+                &pk,
+                CheckMode::UNSAFE,
+                Commitments::KZG,
+                TranscriptType::EVM,
+                None,
+                None,
+            )
+            .unwrap();
+            assert_eq!(snark.instances, vec![public_inputs], "{}", dir.display());
+
+            let verifier_params = params.verifier_params();
+            let res = verify_proof_circuit::<
+                VerifierSHPLONK<_>,
+                KZGCommitmentScheme<_>,
+                SingleStrategy<_>,
+                _,
+                EvmTranscript<_, _, _, _>,
+            >(
+                &snark,
+                verifier_params,
+                pk.get_vk(),
+                SingleStrategy::new(verifier_params),
+                verifier_params.n(),
+            );
+            assert!(res.is_ok(), "{}", dir.display());
+        }
+    }
+}
@@ -5,12 +5,14 @@ mod native_tests {
     use ezkl::circuit::Tolerance;
     use ezkl::fieldutils::{felt_to_integer_rep, integer_rep_to_felt, IntegerRep};
     // use ezkl::circuit::table::RESERVED_BLINDING_ROWS_PAD;
+    use ezkl::execute::get_srs_path;
     use ezkl::graph::input::{FileSource, FileSourceInner, GraphData};
     use ezkl::graph::{DataSource, GraphSettings, GraphWitness};
+    use ezkl::pfsys::verify::verify_inference_proof;
     use ezkl::pfsys::Snark;
     use ezkl::Commitments;
     use halo2_proofs::poly::kzg::commitment::KZGCommitmentScheme;
-    use halo2curves::bn256::Bn256;
+    use halo2curves::bn256::{Bn256, Fr, G1Affine};
     use lazy_static::lazy_static;
     use rand::Rng;
     use std::env::var;
@@ -205,7 +207,7 @@ mod native_tests {
         "1l_tiny_div",
     ];
 
-    const TESTS: [&str; 95] = [
+    const TESTS: [&str; 99] = [
         "1l_mlp", //0
         "1l_slice",
         "1l_concat",
@@ -305,6 +307,10 @@ mod native_tests {
         "lstm_medium", // 92
         "lenet_5",     // 93
         "rsqrt",       // 94
+        "scalar_affine",
+        "1l_conv_transpose_output_shape",
+        "1l_conv_3x3",
+        "1l_conv_nonsquare",
     ];
 
     const WASM_TESTS: [&str; 46] = [
@@ -517,6 +523,9 @@ mod native_tests {
             use crate::native_tests::run_js_tests;
             use crate::native_tests::render_circuit;
             use crate::native_tests::model_serialization_different_binaries;
+            use crate::native_tests::prove_batch_and_verify;
+            use crate::native_tests::export_verifier_and_verify;
+            use crate::native_tests::verify_claimed_instances;
             use rand::Rng;
             use tempdir::TempDir;
             use ezkl::Commitments;
@@ -532,6 +541,39 @@ mod native_tests {
                 test_dir.close().unwrap();
             }
 
+            #[test]
+            fn kzg_prove_batch_in_chunks_() {
+                crate::native_tests::init_binary();
+                let test = "1l_mlp";
+                let test_dir = TempDir::new(test).unwrap();
+                let path = test_dir.path().to_str().unwrap();
+                crate::native_tests::mv_test_(path, test);
+                prove_batch_and_verify(path, test.to_string());
+                test_dir.close().unwrap();
+            }
+
+            #[test]
+            fn kzg_export_verifier_() {
+                crate::native_tests::init_binary();
+                let test = "1l_mlp";
+                let test_dir = TempDir::new(test).unwrap();
+                let path = test_dir.path().to_str().unwrap();
+                crate::native_tests::mv_test_(path, test);
+                export_verifier_and_verify(path, test.to_string());
+                test_dir.close().unwrap();
+            }
+
+            #[test]
+            fn kzg_verify_claimed_instances_() {
+                crate::native_tests::init_binary();
+                let test = "1l_mlp";
+                let test_dir = TempDir::new(test).unwrap();
+                let path = test_dir.path().to_str().unwrap();
+                crate::native_tests::mv_test_(path, test);
+                verify_claimed_instances(path, test.to_string());
+                test_dir.close().unwrap();
+            }
+
             seq!(N in 0..=5 {
             #(#[test_case(ACCURACY_CAL_TESTS[N])])*
             fn mock_accuracy_cal_tests(test: &str) {
@@ -543,7 +585,7 @@ mod native_tests {
             }
         });
 
-            seq!(N in 0..=94 {
+            seq!(N in 0..=98 {
 
             #(#[test_case(TESTS[N])])*
             #[ignore]
@@ -1712,6 +1754,8 @@ mod native_tests {
                 &format!("{}/{}/network.compiled", test_dir, example_name),
                 "-O",
                 &format!("{}/{}/witness.json", test_dir, example_name),
+                // check every laid out node against the forward pass when mocking and proving
+                "--check-witness",
             ])
             .stdout(std::process::Stdio::null())
             .status()
@@ -2101,6 +2145,37 @@ mod native_tests {
         let graph_settings = serde_json::from_str::<GraphSettings>(&settings)
             .expect("failed to parse settings file");
 
+        // the reference verifier agrees with the cli on the same artifacts
+        let proof_bytes = std::fs::read(format!("{}/{}/proof.pf", test_dir, example_name)).unwrap();
+        let vk_bytes = std::fs::read(format!("{}/{}/key.vk", test_dir, example_name)).unwrap();
+        let params_bytes = std::fs::read(get_srs_path(
+            graph_settings.run_args.logrows,
+            None,
+            graph_settings.run_args.commitment.into(),
+        ))
+        .unwrap();
+        let proof = serde_json::from_slice::<Snark<Fr, G1Affine>>(&proof_bytes).unwrap();
+        assert!(verify_inference_proof(
+            &graph_settings,
+            &vk_bytes,
+            &params_bytes,
+            &proof_bytes,
+            &proof.instances,
+        )
+        .unwrap());
+        let mut tampered = proof.instances.clone();
+        if let Some(first) = tampered.iter_mut().find_map(|column| column.first_mut()) {
+            *first += Fr::from(1);
+            assert!(!verify_inference_proof(
+                &graph_settings,
+                &vk_bytes,
+                &params_bytes,
+                &proof_bytes,
+                &tampered,
+            )
+            .unwrap());
+        }
+
         // get_srs for the graph_settings_num_instances
         download_srs(1, graph_settings.run_args.commitment.into());
 
@@ -2119,6 +2194,263 @@ mod native_tests {
         assert!(status.success());
     }
 
+    // prove a batch of 5 samples in chunks of the circuit's batch size 2, with the same key
+    fn prove_batch_and_verify(test_dir: &str, example_name: String) {
+        let chunked = format!("batch_chunks_{}", example_name);
+        let batch = format!("batch_{}", example_name);
+        mk_data_batches_(test_dir, &example_name, &chunked, 2);
+        mk_data_batches_(test_dir, &example_name, &batch, 5);
+
+        gen_circuit_settings_and_witness(
+            test_dir,
+            chunked.clone(),
+            "private",
+            "private",
+            "public",
+            2,
+            "resources",
+            None,
+            2,
+            false,
+            &mut 0.0,
+            Commitments::KZG,
+            2,
+        );
+
+        let settings_path = format!("{}/{}/settings.json", test_dir, chunked);
+        init_params(settings_path.clone().into());
+
+        let status = Command::new(format!("{}/release/ezkl", *CARGO_TARGET_DIR))
+            .args([
+                "setup",
+                "-M",
+                &format!("{}/{}/network.compiled", test_dir, chunked),
+                "--pk-path",
+                &format!("{}/{}/key.pk", test_dir, chunked),
+                "--vk-path",
+                &format!("{}/{}/key.vk", test_dir, chunked),
+            ])
+            .status()
+            .expect("failed to execute process");
+        assert!(status.success());
+
+        let proof_dir = format!("{}/{}/proofs", test_dir, chunked);
+        let status = Command::new(format!("{}/release/ezkl", *CARGO_TARGET_DIR))
+            .args([
+                "prove-batch",
+                "-D",
+                &format!("{}/{}/input.json", test_dir, batch),
+                "-M",
+                &format!("{}/{}/network.compiled", test_dir, chunked),
+                "--pk-path",
+                &format!("{}/{}/key.pk", test_dir, chunked),
+                "--proof-dir",
+                &proof_dir,
+            ])
+            .status()
+            .expect("failed to execute process");
+        assert!(status.success());
+
+        let manifest =
+            std::fs::read_to_string(format!("{}/{}", proof_dir, ezkl::execute::BATCH_MANIFEST))
+                .expect("failed to read manifest");
+        let manifest: ezkl::execute::BatchManifest = serde_json::from_str(&manifest).unwrap();
+        assert_eq!(manifest.num_samples, 5);
+        assert_eq!(manifest.chunk_size, 2);
+        assert_eq!(
+            manifest
+                .chunks
+                .iter()
+                .map(|c| (c.start, c.num_samples))
+                .collect::<Vec<_>>(),
+            vec![(0, 2), (2, 2), (4, 1)]
+        );
+
+        for chunk in manifest.chunks {
+            let status = Command::new(format!("{}/release/ezkl", *CARGO_TARGET_DIR))
+                .args([
+                    "verify",
+                    format!("--settings-path={}", settings_path).as_str(),
+                    "--proof-path",
+                    chunk.proof.to_str().unwrap(),
+                    "--vk-path",
+                    &format!("{}/{}/key.vk", test_dir, chunked),
+                ])
+                .status()
+                .expect("failed to execute process");
+            assert!(status.success());
+        }
+    }
+
+    // export a standalone verifier crate, build it and check a real proof with it
+    fn export_verifier_and_verify(test_dir: &str, example_name: String) {
+        prove_and_verify(
+            test_dir,
+            example_name.clone(),
+            "safe",
+            "private",
+            "private",
+            "public",
+            1,
+            None,
+            false,
+            "single",
+            Commitments::KZG,
+            2,
+        );
+
+        let verifier_dir = format!("{}/{}/verifier", test_dir, example_name);
+        let status = Command::new(format!("{}/release/ezkl", *CARGO_TARGET_DIR))
+            .args([
+                "export-verifier",
+                format!(
+                    "--settings-path={}/{}/settings.json",
+                    test_dir, example_name
+                )
+                .as_str(),
+                "--vk-path",
+                &format!("{}/{}/key.vk", test_dir, example_name),
+                "--out",
+                &verifier_dir,
+            ])
+            .status()
+            .expect("failed to execute process");
+        assert!(status.success());
+
+        // the exported crate pins halo2curves, snark-verifier and halo2 to ezkl's own sources
+        let manifest = |path: &str| {
+            std::fs::read_to_string(path)
+                .unwrap()
+                .parse::<toml::Value>()
+                .unwrap()
+        };
+        let (ezkl, exported) = (
+            manifest("Cargo.toml"),
+            manifest(&format!("{}/Cargo.toml", verifier_dir)),
+        );
+        for dependency in ["halo2curves", "snark-verifier"] {
+            for key in ["git", "rev", "branch"] {
+                assert_eq!(
+                    ezkl["dependencies"][dependency].get(key),
+                    exported["dependencies"][dependency].get(key),
+                    "{}.{}",
+                    dependency,
+                    key
+                );
+            }
+        }
+        let halo2 = "https://github.com/zkonduit/halo2";
+        assert_eq!(
+            ezkl["patch"][halo2]["halo2_proofs"],
+            exported["patch"][halo2]["halo2_proofs"]
+        );
+
+        let snark = Snark::load::<KZGCommitmentScheme<Bn256>>(&PathBuf::from(format!(
+            "{}/{}/proof.pf",
+            test_dir, example_name
+        )))
+        .unwrap();
+        let proof_path = format!("{}/{}/proof.bin", test_dir, example_name);
+        std::fs::write(&proof_path, &snark.proof).unwrap();
+        let outputs = snark
+            .pretty_public_inputs
+            .unwrap()
+            .rescaled_outputs
+            .concat();
+
+        let verify = |outputs: &[String]| {
+            Command::new("cargo")
+                .args([
+                    "run",
+                    "--release",
+                    "--manifest-path",
+                    &format!("{}/Cargo.toml", verifier_dir),
+                    "--example",
+                    "verify",
+                    "--",
+                    &proof_path,
+                ])
+                .args(outputs)
+                .status()
+                .expect("failed to execute process")
+        };
+        assert!(verify(&outputs).success());
+
+        // the proof doesn't verify for outputs it didn't prove
+        let mut other = outputs.clone();
+        other[0] = (other[0].parse::<f32>().unwrap() + 1.0).to_string();
+        assert!(!verify(&other).success());
+    }
+
+    fn verify_claimed_instances(test_dir: &str, example_name: String) {
+        prove_and_verify(
+            test_dir,
+            example_name.clone(),
+            "safe",
+            "private",
+            "private",
+            "public",
+            1,
+            None,
+            false,
+            "single",
+            Commitments::KZG,
+            2,
+        );
+
+        let snark = Snark::load::<KZGCommitmentScheme<Bn256>>(&PathBuf::from(format!(
+            "{}/{}/proof.pf",
+            test_dir, example_name
+        )))
+        .unwrap();
+        let outputs: Vec<Vec<f64>> = snark
+            .pretty_public_inputs
+            .unwrap()
+            .rescaled_outputs
+            .iter()
+            .map(|o| o.iter().map(|v| v.parse().unwrap()).collect())
+            .collect();
+
+        let instances_path = format!("{}/{}/instances.json", test_dir, example_name);
+        let verify = |output_data: &[Vec<f64>]| {
+            std::fs::write(
+                &instances_path,
+                serde_json::json!({ "output_data": output_data }).to_string(),
+            )
+            .unwrap();
+            Command::new(format!("{}/release/ezkl", *CARGO_TARGET_DIR))
+                .args([
+                    "verify",
+                    format!(
+                        "--settings-path={}/{}/settings.json",
+                        test_dir, example_name
+                    )
+                    .as_str(),
+                    "--proof-path",
+                    &format!("{}/{}/proof.pf", test_dir, example_name),
+                    "--vk-path",
+                    &format!("{}/{}/key.vk", test_dir, example_name),
+                    "--instances",
+                    &instances_path,
+                ])
+                .output()
+                .expect("failed to execute process")
+        };
+        assert!(verify(&outputs).status.success());
+
+        // a claim of the wrong shape is rejected before verifying
+        let mut short = outputs.clone();
+        short[0].pop();
+        let output = verify(&short);
+        assert!(!output.status.success());
+        assert!(String::from_utf8_lossy(&output.stdout).contains("invalid instance data"));
+
+        // the proof doesn't verify for outputs it didn't prove
+        let mut other = outputs.clone();
+        other[0][0] += 1.0;
+        assert!(!verify(&other).status.success());
+    }
+
     // prove-serialize-verify, the usual full path
     fn kzg_evm_prove_and_verify(
         num_inner_columns: usize,
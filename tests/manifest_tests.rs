@@ -0,0 +1,269 @@
+#[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
+#[cfg(test)]
+mod manifest_tests {
+
+    use clap::Parser;
+    use ezkl::commands::Cli;
+    #[cfg(feature = "manifest-prove")]
+    use ezkl::graph::{DecodedOutput, NamedOutput};
+    use ezkl::graph::{GraphSettings, GraphWitness};
+    use serde::Deserialize;
+    use std::path::{Path, PathBuf};
+    use tempdir::TempDir;
+
+    /// The fixtures to run, see the comment at its top for the format.
+    const MANIFEST: &str = "tests/manifest.toml";
+
+    #[derive(Deserialize)]
+    struct Manifest {
+        fixture: Vec<Fixture>,
+    }
+
+    /// A model run end to end through the CLI.
+    #[derive(Deserialize)]
+    struct Fixture {
+        name: String,
+        /// Holds the fixture's `network.onnx` and `input.json`
+        dir: PathBuf,
+        /// Passed to gen-settings
+        #[serde(default)]
+        args: Vec<String>,
+        /// The float outputs the witness should hold
+        outputs: Vec<Vec<f64>>,
+        /// The absolute error allowed on each output
+        #[serde(default)]
+        tolerance: f64,
+        /// Whether to prove and verify under the `manifest-prove` feature
+        #[serde(default = "default_prove")]
+        #[cfg_attr(not(feature = "manifest-prove"), allow(dead_code))]
+        prove: bool,
+    }
+
+    fn default_prove() -> bool {
+        true
+    }
+
+    fn fixtures() -> Vec<Fixture> {
+        let manifest: Manifest =
+            toml::from_str(&std::fs::read_to_string(MANIFEST).unwrap()).unwrap();
+        assert!(!manifest.fixture.is_empty(), "no fixtures in {}", MANIFEST);
+        manifest.fixture
+    }
+
+    /// Runs an ezkl subcommand as the CLI would, failing the fixture on error, and returns its output.
+    fn ezkl(fixture: &str, args: &[&str]) -> String {
+        let cli = Cli::try_parse_from(std::iter::once("ezkl").chain(args.iter().copied()))
+            .unwrap_or_else(|e| panic!("{}: bad args for {}: {}", fixture, args[0], e));
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime
+            .block_on(ezkl::execute::run(cli.command.unwrap()))
+            .unwrap_or_else(|e| panic!("{}: {} failed: {}", fixture, args[0], e))
+    }
+
+    fn path(dir: &Path, file: &str) -> String {
+        dir.join(file).display().to_string()
+    }
+
+    /// Settings, compile, witness and mock for a fixture, checking the witness' outputs.
+    fn mock(fixture: &Fixture, dir: &Path) {
+        let name = fixture.name.as_str();
+        let model = fixture.dir.join("network.onnx").display().to_string();
+        let data = fixture.dir.join("input.json").display().to_string();
+        let (settings, compiled, witness) = (
+            path(dir, "settings.json"),
+            path(dir, "network.compiled"),
+            path(dir, "witness.json"),
+        );
+
+        let mut args = vec!["gen-settings", "-M", &model, "-O", &settings];
+        args.extend(fixture.args.iter().map(|a| a.as_str()));
+        ezkl(name, &args);
+        ezkl(
+            name,
+            &[
+                "compile-circuit",
+                "-M",
+                &model,
+                "--compiled-circuit",
+                &compiled,
+                "-S",
+                &settings,
+            ],
+        );
+        ezkl(
+            name,
+            &["gen-witness", "-D", &data, "-M", &compiled, "-O", &witness],
+        );
+        // the fixture's outputs are checked by mock itself, and again below on the floats
+        let expected = path(dir, "expected.json");
+        std::fs::write(
+            &expected,
+            serde_json::json!({ "output_data": fixture.outputs }).to_string(),
+        )
+        .unwrap();
+        let atol = fixture.tolerance.to_string();
+        ezkl(
+            name,
+            &[
+                "mock",
+                "-W",
+                &witness,
+                "-M",
+                &compiled,
+                "--expected-output",
+                &expected,
+                "--output-atol",
+                &atol,
+                "--output-rtol",
+                "0",
+            ],
+        );
+
+        let scales = GraphSettings::load(&PathBuf::from(&settings))
+            .unwrap()
+            .model_output_scales;
+        let outputs = GraphWitness::from_path(PathBuf::from(&witness))
+            .unwrap()
+            .get_float_outputs(&scales);
+        assert_eq!(outputs.len(), fixture.outputs.len(), "{}", name);
+        for (output, expected) in outputs.iter().zip(&fixture.outputs) {
+            assert_eq!(output.len(), expected.len(), "{}", name);
+            for (x, y) in output.iter().zip(expected) {
+                assert!(
+                    (*x as f64 - y).abs() <= fixture.tolerance,
+                    "{}: output {} is not within {} of {}",
+                    name,
+                    x,
+                    fixture.tolerance,
+                    y
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn manifest_mock() {
+        for fixture in fixtures() {
+            let dir = TempDir::new(&fixture.name).unwrap();
+            mock(&fixture, dir.path());
+        }
+    }
+
+    #[cfg(feature = "manifest-prove")]
+    #[test]
+    fn manifest_prove_and_verify() {
+        for fixture in fixtures().into_iter().filter(|f| f.prove) {
+            let name = fixture.name.as_str();
+            let dir = TempDir::new(name).unwrap();
+            let dir = dir.path();
+            mock(&fixture, dir);
+
+            let run_args = GraphSettings::load(&dir.join("settings.json"))
+                .unwrap()
+                .run_args;
+            let logrows = run_args.logrows.to_string();
+            let [settings, compiled, witness, srs, vk, pk, proof, expected] = [
+                "settings.json",
+                "network.compiled",
+                "witness.json",
+                "kzg.srs",
+                "vk.key",
+                "pk.key",
+                "proof.json",
+                "expected.json",
+            ]
+            .map(|file| path(dir, file));
+
+            ezkl(
+                name,
+                &["gen-srs", "--srs-path", &srs, "--logrows", &logrows],
+            );
+            ezkl(
+                name,
+                &[
+                    "setup",
+                    "-M",
+                    &compiled,
+                    "--srs-path",
+                    &srs,
+                    "--vk-path",
+                    &vk,
+                    "--pk-path",
+                    &pk,
+                ],
+            );
+            ezkl(
+                name,
+                &[
+                    "prove",
+                    "-W",
+                    &witness,
+                    "-M",
+                    &compiled,
+                    "--pk-path",
+                    &pk,
+                    "--proof-path",
+                    &proof,
+                    "--srs-path",
+                    &srs,
+                ],
+            );
+            let mut args = vec![
+                "verify",
+                "-S",
+                &settings,
+                "--proof-path",
+                &proof,
+                "--vk-path",
+                &vk,
+                "--srs-path",
+                &srs,
+            ];
+            // only public outputs can be checked against the expected ones
+            let atol = fixture.tolerance.to_string();
+            if run_args.output_visibility.is_public() {
+                args.extend([
+                    "--expected-output",
+                    &expected,
+                    "--output-atol",
+                    &atol,
+                    "--output-rtol",
+                    "0",
+                ]);
+            }
+            ezkl(name, &args);
+
+            // each output is decoded from the proof at its own scale
+            if run_args.output_visibility.is_public() {
+                let decoded = ezkl(
+                    name,
+                    &[
+                        "decode-outputs",
+                        "--proof-path",
+                        &proof,
+                        "--output-format",
+                        "float",
+                    ],
+                );
+                let decoded: Vec<NamedOutput> = serde_json::from_str(&decoded).unwrap();
+                assert_eq!(decoded.len(), fixture.outputs.len(), "{}", name);
+                for (output, expected) in decoded.iter().zip(&fixture.outputs) {
+                    let DecodedOutput::Float(output) = &output.decoded else {
+                        panic!("{}: {} wasn't decoded as floats", name, output.name);
+                    };
+                    assert_eq!(output.len(), expected.len(), "{}", name);
+                    for (x, y) in output.iter().zip(expected) {
+                        assert!(
+                            (x - y).abs() <= fixture.tolerance,
+                            "{}: decoded output {} is not within {} of {}",
+                            name,
+                            x,
+                            fixture.tolerance,
+                            y
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,315 @@
+#[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
+#[cfg(test)]
+mod soundness_tests {
+
+    use ezkl::circuit::region::RegionSettings;
+    use ezkl::graph::input::{DataSource, FileSourceInner, GraphData};
+    use ezkl::graph::{GraphCircuit, GraphWitness};
+    use ezkl::RunArgs;
+    use halo2_proofs::poly::kzg::commitment::KZGCommitmentScheme;
+    use halo2curves::bn256::{Bn256, Fr as Fp};
+    use halo2curves::ff::Field;
+    use std::path::Path;
+
+    /// The example models every property is checked on.
+    const MODELS: [&str; 3] = ["1l_mlp", "1l_relu", "1l_sigmoid"];
+
+    /// A model's circuit, sized for both its example input and a shifted copy of it.
+    struct Fixture {
+        circuit: GraphCircuit,
+        #[cfg_attr(not(feature = "soundness-prove"), allow(dead_code))]
+        data: [GraphData; 2],
+        witnesses: [GraphWitness; 2],
+    }
+
+    fn witness(circuit: &mut GraphCircuit, data: &GraphData) -> GraphWitness {
+        let run_args = circuit.settings().run_args.clone();
+        let mut inputs = circuit.load_graph_from_file_exclusively(data).unwrap();
+        circuit
+            .forward::<KZGCommitmentScheme<Bn256>>(
+                &mut inputs,
+                None,
+                None,
+                RegionSettings::all_true(run_args.decomp_base, run_args.decomp_legs),
+            )
+            .unwrap()
+    }
+
+    fn fixture(model: &str, run_args: &RunArgs) -> Fixture {
+        let dir = Path::new("examples/onnx").join(model);
+        let mut circuit = GraphCircuit::from_run_args(run_args, &dir.join("network.onnx")).unwrap();
+        let data = GraphData::from_path(dir.join("input.json")).unwrap();
+        let DataSource::File(inputs) = &data.input_data else {
+            panic!("{}: expected file data", model);
+        };
+        let shifted = GraphData::new(DataSource::File(
+            inputs
+                .iter()
+                .map(|input| {
+                    input
+                        .iter()
+                        .map(|x| FileSourceInner::new_float(x.to_float() + 0.5))
+                        .collect()
+                })
+                .collect(),
+        ));
+        let data = [data, shifted];
+        let witnesses = [
+            witness(&mut circuit, &data[0]),
+            witness(&mut circuit, &data[1]),
+        ];
+        // proofs can only be told apart if the inputs lead to different outputs
+        assert_ne!(witnesses[0].outputs, witnesses[1].outputs, "{}", model);
+
+        let min = witnesses.iter().map(|w| w.min_lookup_inputs).min().unwrap();
+        let max = witnesses.iter().map(|w| w.max_lookup_inputs).max().unwrap();
+        let max_range_size = witnesses.iter().map(|w| w.max_range_size).max().unwrap();
+        circuit
+            .calc_min_logrows((min, max), max_range_size, None, 2.0)
+            .unwrap();
+        Fixture {
+            circuit,
+            data,
+            witnesses,
+        }
+    }
+
+    #[test]
+    fn perturbed_output_instances_fail_mock() {
+        for model in MODELS {
+            let Fixture {
+                mut circuit,
+                witnesses,
+                ..
+            } = fixture(model, &RunArgs::default());
+            circuit.load_graph_witness(&witnesses[0]).unwrap();
+            let public_inputs = circuit.prepare_public_inputs(&witnesses[0]).unwrap();
+            let logrows = circuit.settings().run_args.logrows;
+            halo2_proofs::dev::MockProver::run(logrows, &circuit, vec![public_inputs.clone()])
+                .unwrap()
+                .assert_satisfied();
+
+            // outputs are public under the default visibility, each of them is constrained
+            for i in 0..public_inputs.len() {
+                let mut perturbed = public_inputs.clone();
+                perturbed[i] += Fp::ONE;
+                let prover =
+                    halo2_proofs::dev::MockProver::run(logrows, &circuit, vec![perturbed]).unwrap();
+                assert!(prover.verify().is_err(), "{}: instance {}", model, i);
+            }
+        }
+    }
+
+    #[cfg(feature = "soundness-prove")]
+    mod prove {
+        use super::*;
+        use ezkl::circuit::CheckMode;
+        use ezkl::graph::model::NodeType;
+        use ezkl::graph::node::{Node, SupportedOp};
+        use ezkl::graph::Visibility;
+        use ezkl::pfsys::{
+            create_keys, create_proof_circuit, srs::gen_srs, verify_proof_circuit, Snark,
+            TranscriptType,
+        };
+        use ezkl::Commitments;
+        use halo2_proofs::plonk::{ProvingKey, VerifyingKey};
+        use halo2_proofs::poly::commitment::ParamsProver;
+        use halo2_proofs::poly::kzg::{
+            commitment::ParamsKZG,
+            multiopen::{ProverSHPLONK, VerifierSHPLONK},
+            strategy::SingleStrategy,
+        };
+        use halo2curves::bn256::G1Affine;
+        use snark_verifier::system::halo2::transcript::evm::EvmTranscript;
+        use tempdir::TempDir;
+
+        fn keys(circuit: &GraphCircuit, params: &ParamsKZG<Bn256>) -> ProvingKey<G1Affine> {
+            create_keys::<KZGCommitmentScheme<Bn256>, GraphCircuit>(circuit, params, false).unwrap()
+        }
+
+        /// Proves a witness honestly, with the public instances it leads to.
+        fn prove(
+            circuit: &mut GraphCircuit,
+            witness: &GraphWitness,
+            params: &ParamsKZG<Bn256>,
+            pk: &ProvingKey<G1Affine>,
+        ) -> Snark<Fp, G1Affine> {
+            circuit.load_graph_witness(witness).unwrap();
+            let public_inputs = circuit.prepare_public_inputs(witness).unwrap();
+            create_proof_circuit::<
+                KZGCommitmentScheme<_>,
+                _,
+                ProverSHPLONK<_>,
+                VerifierSHPLONK<_>,
+                SingleStrategy<_>,
+                _,
+                EvmTranscript<_, _, _, _>,
+                EvmTranscript<_, _, _, _>,
+            >(
+                circuit.clone(),
+                vec![public_inputs],
+                params,
+                pk,
+                CheckMode::UNSAFE,
+                Commitments::KZG,
+                TranscriptType::EVM,
+                None,
+                None,
+            )
+            .unwrap()
+        }
+
+        fn verifies(
+            snark: &Snark<Fp, G1Affine>,
+            params: &ParamsKZG<Bn256>,
+            vk: &VerifyingKey<G1Affine>,
+        ) -> bool {
+            let verifier_params = params.verifier_params();
+            verify_proof_circuit::<
+                VerifierSHPLONK<_>,
+                KZGCommitmentScheme<_>,
+                SingleStrategy<_>,
+                _,
+                EvmTranscript<_, _, _, _>,
+            >(
+                snark,
+                verifier_params,
+                vk,
+                SingleStrategy::new(verifier_params),
+                verifier_params.n(),
+            )
+            .is_ok()
+        }
+
+        /// Bumps one quantized weight of the model, returning false if it has none.
+        fn flip_weight(circuit: &mut GraphCircuit) -> bool {
+            for node in circuit.core.model.graph.nodes.values_mut() {
+                if let NodeType::Node(Node {
+                    opkind: SupportedOp::Constant(constant),
+                    ..
+                }) = node
+                {
+                    if constant.raw_values.len() > 1 {
+                        constant.quantized_values().unwrap();
+                        constant.quantized_values_mut().unwrap()[0] += Fp::ONE;
+                        return true;
+                    }
+                }
+            }
+            false
+        }
+
+        #[test]
+        fn perturbed_and_swapped_instances_fail() {
+            for model in MODELS {
+                let Fixture {
+                    mut circuit,
+                    witnesses,
+                    ..
+                } = fixture(model, &RunArgs::default());
+                let params =
+                    gen_srs::<KZGCommitmentScheme<Bn256>>(circuit.settings().run_args.logrows);
+                let pk = keys(&circuit, &params);
+                let snarks = witnesses
+                    .each_ref()
+                    .map(|w| prove(&mut circuit, w, &params, &pk));
+                for snark in &snarks {
+                    assert!(verifies(snark, &params, pk.get_vk()), "{}", model);
+                }
+
+                for i in 0..snarks[0].instances[0].len() {
+                    let mut perturbed = snarks[0].clone();
+                    perturbed.instances[0][i] += Fp::ONE;
+                    assert!(
+                        !verifies(&perturbed, &params, pk.get_vk()),
+                        "{}: instance {}",
+                        model,
+                        i
+                    );
+                }
+
+                // a proof for one input can't be passed off as one for another
+                let mut swapped = snarks[0].clone();
+                swapped.instances = snarks[1].instances.clone();
+                assert!(!verifies(&swapped, &params, pk.get_vk()), "{}", model);
+            }
+        }
+
+        #[test]
+        fn flipped_fixed_weights_fail() {
+            let run_args = RunArgs {
+                param_visibility: Visibility::Fixed,
+                ..RunArgs::default()
+            };
+            let mut flipped_any = false;
+            for model in MODELS {
+                let Fixture {
+                    mut circuit, data, ..
+                } = fixture(model, &run_args);
+                let mut flipped = circuit.clone();
+                if !flip_weight(&mut flipped) {
+                    continue;
+                }
+                flipped_any = true;
+
+                let params =
+                    gen_srs::<KZGCommitmentScheme<Bn256>>(circuit.settings().run_args.logrows);
+                let pk = keys(&circuit, &params);
+                let flipped_pk = keys(&flipped, &params);
+                let honest = witness(&mut circuit, &data[0]);
+                assert!(verifies(
+                    &prove(&mut circuit, &honest, &params, &pk),
+                    &params,
+                    pk.get_vk()
+                ));
+
+                // fixed weights are committed to by the verifying key, so an otherwise honest
+                // proof for other weights only verifies against its own key
+                let witness = witness(&mut flipped, &data[0]);
+                let snark = prove(&mut flipped, &witness, &params, &flipped_pk);
+                assert!(verifies(&snark, &params, flipped_pk.get_vk()), "{}", model);
+                assert!(!verifies(&snark, &params, pk.get_vk()), "{}", model);
+            }
+            assert!(flipped_any, "none of {:?} have weights", MODELS);
+        }
+
+        #[test]
+        fn truncated_proofs_fail_cleanly() {
+            for model in MODELS {
+                let Fixture {
+                    mut circuit,
+                    witnesses,
+                    ..
+                } = fixture(model, &RunArgs::default());
+                let params =
+                    gen_srs::<KZGCommitmentScheme<Bn256>>(circuit.settings().run_args.logrows);
+                let pk = keys(&circuit, &params);
+                let snark = prove(&mut circuit, &witnesses[0], &params, &pk);
+
+                let len = snark.proof.len();
+                for cut in [0, 1, len / 2, len - 1] {
+                    let mut truncated = snark.clone();
+                    truncated.proof.truncate(cut);
+                    assert!(
+                        !verifies(&truncated, &params, pk.get_vk()),
+                        "{}: proof cut to {} bytes",
+                        model,
+                        cut
+                    );
+                }
+
+                // a proof file cut short is a load error
+                let dir = TempDir::new(model).unwrap();
+                let path = dir.path().join("proof.json");
+                snark.save(&path).unwrap();
+                let bytes = std::fs::read(&path).unwrap();
+                std::fs::write(&path, &bytes[..bytes.len() / 2]).unwrap();
+                assert!(
+                    Snark::<Fp, G1Affine>::load::<KZGCommitmentScheme<Bn256>>(&path).is_err(),
+                    "{}",
+                    model
+                );
+            }
+        }
+    }
+}